@@ -0,0 +1,257 @@
+//! Reading and writing [CARv1][car] files, the IPLD format for archiving and sharing blocks.
+//!
+//! A CAR file is a header listing the root CIDs of the archived DAGs, followed by the blocks. The
+//! header and each block are prefixed with their length as an unsigned varint. The header is
+//! DAG-CBOR encoded, and each block is its CID in binary followed by its data.
+//!
+//! [car]: https://ipld.io/specs/transport/car/carv1/
+
+use bytes::Bytes;
+use ipld_core::cid::Cid;
+use multihash_codetable::{Code, MultihashDigest};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, BufWriter};
+
+use crate::{StoreError, StoreResult};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The version of the CAR format that is read and written.
+const CAR_VERSION: u64 = 1;
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// The header of a CAR file.
+///
+/// The fields are declared in the canonical DAG-CBOR key order, so the header is encoded the same
+/// way by every implementation.
+#[derive(Debug, Serialize, Deserialize)]
+struct CarHeader {
+    /// The CIDs of the roots of the archived DAGs.
+    roots: Vec<Cid>,
+
+    /// The version of the CAR format.
+    version: u64,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Writes `blocks` as a CARv1 file with the given `roots`.
+///
+/// The blocks are written in the given order.
+pub async fn write_car<'a>(
+    roots: &[Cid],
+    blocks: impl IntoIterator<Item = (&'a Cid, &'a [u8])>,
+    writer: impl AsyncWrite + Unpin,
+) -> StoreResult<()> {
+    let mut writer = BufWriter::new(writer);
+
+    let header = CarHeader {
+        roots: roots.to_vec(),
+        version: CAR_VERSION,
+    };
+    let header = serde_ipld_dagcbor::to_vec(&header).map_err(StoreError::custom)?;
+    write_section(&mut writer, &[&header]).await?;
+
+    for (cid, data) in blocks {
+        write_section(&mut writer, &[&cid.to_bytes(), data]).await?;
+    }
+
+    writer.flush().await.map_err(StoreError::custom)?;
+
+    Ok(())
+}
+
+/// Reads a CARv1 file, returning its roots and its blocks in the order they were written.
+///
+/// ## Errors
+///
+/// Returns `StoreError::InvalidCar` if the file is malformed or uses a hash function that is not
+/// supported. Returns `StoreError::BlockCidMismatch` if the data of a block doesn't hash to its
+/// CID.
+pub async fn read_car(
+    reader: impl AsyncRead + Unpin,
+) -> StoreResult<(Vec<Cid>, Vec<(Cid, Bytes)>)> {
+    let mut reader = BufReader::new(reader);
+
+    let header = read_section(&mut reader)
+        .await?
+        .ok_or_else(|| StoreError::InvalidCar("missing header".to_string()))?;
+    let header: CarHeader = serde_ipld_dagcbor::from_slice(&header)
+        .map_err(|e| StoreError::InvalidCar(format!("invalid header: {e}")))?;
+    if header.version != CAR_VERSION {
+        return Err(StoreError::InvalidCar(format!(
+            "unsupported version: {}",
+            header.version
+        )));
+    }
+
+    let mut blocks = Vec::new();
+    while let Some(section) = read_section(&mut reader).await? {
+        let mut data = section.as_slice();
+        let cid = Cid::read_bytes(&mut data)
+            .map_err(|e| StoreError::InvalidCar(format!("invalid block CID: {e}")))?;
+        verify_block(&cid, data)?;
+        blocks.push((cid, Bytes::copy_from_slice(data)));
+    }
+
+    Ok((header.roots, blocks))
+}
+
+/// Checks that `data` hashes to the digest in `cid`.
+fn verify_block(cid: &Cid, data: &[u8]) -> StoreResult<()> {
+    let code = Code::try_from(cid.hash().code()).map_err(|_| {
+        StoreError::InvalidCar(format!(
+            "unsupported hash function {:#x} in block {cid}",
+            cid.hash().code()
+        ))
+    })?;
+
+    if code.digest(data) != *cid.hash() {
+        return Err(StoreError::BlockCidMismatch(*cid));
+    }
+
+    Ok(())
+}
+
+/// Writes the concatenation of `parts` prefixed with its length.
+async fn write_section(writer: &mut (impl AsyncWrite + Unpin), parts: &[&[u8]]) -> StoreResult<()> {
+    let len = parts.iter().map(|part| part.len() as u64).sum();
+    let mut prefix = Vec::new();
+    encode_varint(len, &mut prefix);
+
+    writer
+        .write_all(&prefix)
+        .await
+        .map_err(StoreError::custom)?;
+    for part in parts {
+        writer.write_all(part).await.map_err(StoreError::custom)?;
+    }
+
+    Ok(())
+}
+
+/// Reads a section prefixed with its length, or returns `None` at the end of the file.
+async fn read_section(reader: &mut (impl AsyncRead + Unpin)) -> StoreResult<Option<Vec<u8>>> {
+    let Some(len) = read_varint(reader).await? else {
+        return Ok(None);
+    };
+
+    // Read through `take`, so a bogus length doesn't allocate more than the file has
+    let mut section = Vec::new();
+    reader
+        .take(len)
+        .read_to_end(&mut section)
+        .await
+        .map_err(StoreError::custom)?;
+    if section.len() as u64 != len {
+        return Err(StoreError::InvalidCar(format!(
+            "section truncated at {} of {len} bytes",
+            section.len()
+        )));
+    }
+
+    Ok(Some(section))
+}
+
+/// Appends `value` to `buf` as an unsigned LEB128 varint.
+fn encode_varint(mut value: u64, buf: &mut Vec<u8>) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf.push(byte);
+            return;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+/// Reads an unsigned LEB128 varint, or returns `None` if the reader is at its end.
+async fn read_varint(reader: &mut (impl AsyncRead + Unpin)) -> StoreResult<Option<u64>> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        let mut byte = [0u8];
+        let n = reader.read(&mut byte).await.map_err(StoreError::custom)?;
+        if n == 0 {
+            if shift == 0 {
+                return Ok(None);
+            }
+            return Err(StoreError::InvalidCar("truncated length".to_string()));
+        }
+
+        if shift >= 64 {
+            return Err(StoreError::InvalidCar("length too large".to_string()));
+        }
+
+        value |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(Some(value));
+        }
+        shift += 7;
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::{utils, Codec};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_car_varint_round_trip() -> anyhow::Result<()> {
+        for value in [0, 1, 127, 128, 300, 16_384, u32::MAX as u64, u64::MAX] {
+            let mut buf = Vec::new();
+            encode_varint(value, &mut buf);
+            assert_eq!(read_varint(&mut buf.as_slice()).await?, Some(value));
+        }
+
+        assert_eq!(read_varint(&mut [].as_slice()).await?, None);
+        assert!(matches!(
+            read_varint(&mut [0x80].as_slice()).await,
+            Err(StoreError::InvalidCar(_))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_car_rejects_tampered_block() -> anyhow::Result<()> {
+        let data = b"hello".as_slice();
+        let cid = utils::generate_cid(Codec::Raw, data);
+
+        let mut car = Vec::new();
+        write_car(&[cid], [(&cid, data)], &mut car).await?;
+
+        let (roots, blocks) = read_car(car.as_slice()).await?;
+        assert_eq!(roots, vec![cid]);
+        assert_eq!(blocks, vec![(cid, Bytes::from_static(b"hello"))]);
+
+        // Flip a byte of the block data, which is at the end of the file
+        let last = car.len() - 1;
+        car[last] ^= 0xff;
+        assert_eq!(
+            read_car(car.as_slice()).await,
+            Err(StoreError::BlockCidMismatch(cid))
+        );
+
+        // A truncated file is rejected too
+        assert!(matches!(
+            read_car(&car[..last]).await,
+            Err(StoreError::InvalidCar(_))
+        ));
+
+        Ok(())
+    }
+}
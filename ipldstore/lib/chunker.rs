@@ -1,9 +1,49 @@
+use std::collections::HashSet;
+
 use async_trait::async_trait;
 use bytes::Bytes;
-use futures::stream::BoxStream;
+use futures::{stream::BoxStream, StreamExt};
+use getset::CopyGetters;
 use tokio::io::AsyncRead;
 
-use super::StoreResult;
+use super::{Codec, IpldStore, StoreResult};
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// Statistics about the chunks a [`Chunker`] split some data into.
+///
+/// This is returned by [`Chunker::chunk_with_stats`] and is useful for comparing chunkers and
+/// tuning their chunk size parameters on real data.
+#[derive(Debug, Clone, Default, PartialEq, CopyGetters)]
+#[getset(get_copy = "pub with_prefix")]
+pub struct ChunkerStats {
+    /// The number of chunks.
+    chunk_count: u64,
+
+    /// The total size of all the chunks in bytes.
+    total_size: u64,
+
+    /// The size of the smallest chunk in bytes.
+    min_size: u64,
+
+    /// The size of the largest chunk in bytes.
+    max_size: u64,
+
+    /// The average chunk size in bytes.
+    avg_size: f64,
+
+    /// The standard deviation of the chunk sizes in bytes.
+    stddev_size: f64,
+
+    /// The size in bytes of the chunks that would not need to be stored, either because they are
+    /// already in the store or because they appeared earlier in the data.
+    deduplicated_size: u64,
+
+    /// The fraction of the data that is deduplicated, from `0.0` (nothing) to `1.0` (everything).
+    dedup_ratio: f64,
+}
 
 //--------------------------------------------------------------------------------------------------
 // Traits
@@ -22,4 +62,243 @@ pub trait Chunker {
 
     /// Returns the allowed maximum chunk size. If there is no limit, `None` is returned.
     async fn chunk_max_size(&self) -> StoreResult<Option<u64>>;
+
+    /// Chunks the given reader and returns statistics about the chunks.
+    ///
+    /// Chunks are deduplicated against each other and against the raw blocks already in `store`.
+    /// Nothing is written to the store.
+    async fn chunk_with_stats(
+        &self,
+        reader: impl AsyncRead + Send + Sync + 'life0,
+        store: &(impl IpldStore + Sync),
+    ) -> StoreResult<ChunkerStats>
+    where
+        Self: Sync,
+    {
+        let cid_config = store.get_cid_config().await;
+        let mut chunk_stream = self.chunk(reader).await?;
+        let mut seen = HashSet::new();
+        let mut unseen = Vec::new();
+        let mut sizes = Vec::new();
+        let mut deduplicated_size = 0;
+        while let Some(chunk) = chunk_stream.next().await {
+            let chunk = chunk?;
+            let size = chunk.len() as u64;
+            let cid = cid_config.generate_cid(Codec::Raw, &chunk)?;
+            if seen.insert(cid) {
+                unseen.push((cid, size));
+            } else {
+                deduplicated_size += size;
+            }
+
+            sizes.push(size);
+        }
+
+        // Check the chunks against the store all at once.
+        let cids = unseen.iter().map(|(cid, _)| *cid).collect::<Vec<_>>();
+        let present = store.has_many(&cids).await?;
+        for ((_, size), present) in unseen.iter().zip(present) {
+            if present {
+                deduplicated_size += size;
+            }
+        }
+
+        Ok(ChunkerStats::from_sizes(&sizes, deduplicated_size))
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl ChunkerStats {
+    fn from_sizes(sizes: &[u64], deduplicated_size: u64) -> Self {
+        if sizes.is_empty() {
+            return Self::default();
+        }
+
+        let chunk_count = sizes.len() as u64;
+        let total_size: u64 = sizes.iter().sum();
+        let avg_size = total_size as f64 / chunk_count as f64;
+        let variance = sizes
+            .iter()
+            .map(|&size| (size as f64 - avg_size).powi(2))
+            .sum::<f64>()
+            / chunk_count as f64;
+
+        Self {
+            chunk_count,
+            total_size,
+            min_size: sizes.iter().copied().min().unwrap_or_default(),
+            max_size: sizes.iter().copied().max().unwrap_or_default(),
+            avg_size,
+            stddev_size: variance.sqrt(),
+            deduplicated_size,
+            dedup_ratio: if total_size == 0 {
+                0.0
+            } else {
+                deduplicated_size as f64 / total_size as f64
+            },
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::{
+        FastCDCChunker, FixedSizeChunker, GearCDCChunker, MemoryStore, RawStore, DEFAULT_GEAR_TABLE,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_chunk_with_stats_fixed() -> anyhow::Result<()> {
+        let data = helper::repetitive_data();
+        let store = MemoryStore::default();
+        let chunker = FixedSizeChunker::new(4096);
+
+        // Fixed boundaries line up with every repeat of the block, so only the first one is new
+        let stats = chunker.chunk_with_stats(&data[..], &store).await?;
+        assert_eq!(stats.get_chunk_count(), 512);
+        assert_eq!(stats.get_total_size(), data.len() as u64);
+        assert_eq!(
+            stats.get_deduplicated_size(),
+            (data.len() - helper::BLOCK_SIZE) as u64
+        );
+        assert_eq!(stats.get_dedup_ratio(), 31.0 / 32.0);
+        assert_eq!(stats.get_min_size(), 4096);
+        assert_eq!(stats.get_max_size(), 4096);
+        assert_eq!(stats.get_stddev_size(), 0.0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_chunk_with_stats_fastcdc() -> anyhow::Result<()> {
+        let data = helper::repetitive_data();
+        let store = MemoryStore::default();
+        let chunker = FastCDCChunker::new(4096, 1024, 16384, DEFAULT_GEAR_TABLE);
+
+        let stats = chunker.chunk_with_stats(&data[..], &store).await?;
+        assert_eq!(stats.get_total_size(), data.len() as u64);
+        assert!(stats.get_chunk_count() > 1);
+
+        // Boundaries resynchronize within every repeat of the block, so nearly all of it is reused
+        assert!(stats.get_dedup_ratio() > 0.9);
+        assert_eq!(
+            stats.get_dedup_ratio(),
+            stats.get_deduplicated_size() as f64 / data.len() as f64
+        );
+        assert!(stats.get_avg_size() >= 1024.0 && stats.get_avg_size() <= 16384.0);
+        assert!(stats.get_max_size() <= 16384);
+        assert!(stats.get_min_size() <= stats.get_max_size());
+        assert!(stats.get_stddev_size() >= 0.0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_chunk_with_stats_gearcdc() -> anyhow::Result<()> {
+        let data = helper::repetitive_data();
+        let store = MemoryStore::default();
+        let chunker = GearCDCChunker::new(4096, DEFAULT_GEAR_TABLE);
+
+        let stats = chunker.chunk_with_stats(&data[..], &store).await?;
+        assert_eq!(stats.get_total_size(), data.len() as u64);
+        assert!(stats.get_dedup_ratio() > 0.9);
+        assert!(stats.get_avg_size() >= 1024.0 && stats.get_avg_size() <= 16384.0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_chunk_with_stats_shifted_input() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(98765);
+        let data = (0..256 * 1024).map(|_| rng.random()).collect::<Vec<u8>>();
+        let prefix = (0..100).map(|_| rng.random()).collect::<Vec<u8>>();
+        let shifted = [&prefix[..], &data[..]].concat();
+
+        // Content-defined boundaries move with the content, so only the chunks around the
+        // insertion are new
+        let chunker = FastCDCChunker::new(4096, 1024, 16384, DEFAULT_GEAR_TABLE);
+        let store = helper::store_chunks(&chunker, &data).await?;
+        let stats = chunker.chunk_with_stats(&shifted[..], &store).await?;
+        assert!(stats.get_dedup_ratio() > 0.75);
+
+        let chunker = GearCDCChunker::new(4096, DEFAULT_GEAR_TABLE);
+        let store = helper::store_chunks(&chunker, &data).await?;
+        let stats = chunker.chunk_with_stats(&shifted[..], &store).await?;
+        assert!(stats.get_dedup_ratio() > 0.75);
+
+        // Fixed boundaries all move, so nothing is reused
+        let chunker = FixedSizeChunker::new(4096);
+        let store = helper::store_chunks(&chunker, &data).await?;
+        let stats = chunker.chunk_with_stats(&shifted[..], &store).await?;
+        assert_eq!(stats.get_dedup_ratio(), 0.0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_chunk_with_stats_against_store() -> anyhow::Result<()> {
+        let mut rng = StdRng::seed_from_u64(54321);
+        let data = (0..256 * 1024).map(|_| rng.random()).collect::<Vec<u8>>();
+        let store = MemoryStore::default();
+        let chunker = FastCDCChunker::new(4096, 1024, 16384, DEFAULT_GEAR_TABLE);
+
+        // Random data doesn't deduplicate against itself
+        let stats = chunker.chunk_with_stats(&data[..], &store).await?;
+        assert_eq!(stats.get_deduplicated_size(), 0);
+        assert_eq!(stats.get_dedup_ratio(), 0.0);
+
+        // Once the chunks are in the store, everything is deduplicated
+        let mut chunk_stream = chunker.chunk(&data[..]).await?;
+        while let Some(chunk) = chunk_stream.next().await {
+            store.put_raw_block(chunk?).await?;
+        }
+
+        let stats = chunker.chunk_with_stats(&data[..], &store).await?;
+        assert_eq!(stats.get_deduplicated_size(), data.len() as u64);
+        assert_eq!(stats.get_dedup_ratio(), 1.0);
+
+        // Empty input
+        let stats = chunker.chunk_with_stats(&b""[..], &store).await?;
+        assert_eq!(stats, ChunkerStats::default());
+
+        Ok(())
+    }
+
+    mod helper {
+        use super::*;
+
+        /// The size of the block repeated by [`repetitive_data`].
+        pub(super) const BLOCK_SIZE: usize = 64 * 1024;
+
+        /// Returns a random block repeated 32 times.
+        pub(super) fn repetitive_data() -> Vec<u8> {
+            let mut rng = StdRng::seed_from_u64(12345);
+            let block = (0..BLOCK_SIZE).map(|_| rng.random()).collect::<Vec<u8>>();
+            block.repeat(32)
+        }
+
+        /// Returns a store holding the chunks of `data`.
+        pub(super) async fn store_chunks(
+            chunker: &impl Chunker,
+            data: &[u8],
+        ) -> StoreResult<MemoryStore> {
+            let store = MemoryStore::default();
+            let mut chunk_stream = chunker.chunk(data).await?;
+            while let Some(chunk) = chunk_stream.next().await {
+                store.put_raw_block(chunk?).await?;
+            }
+
+            Ok(store)
+        }
+    }
 }
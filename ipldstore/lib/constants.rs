@@ -14,6 +14,15 @@ pub const DEFAULT_MAX_CHUNK_SIZE: u64 = 512 * 1024;
 /// The default maximum node block size is 1 MiB.
 pub const DEFAULT_MAX_NODE_BLOCK_SIZE: u64 = 1 * 1024 * 1024;
 
+/// The default number of chunk bytes a layout buffers before writing them to the store is 4 MiB.
+pub const DEFAULT_LAYOUT_BATCH_SIZE: u64 = 4 * 1024 * 1024;
+
+/// The default zstd compression level.
+pub const DEFAULT_COMPRESSION_LEVEL: i32 = 3;
+
+/// The default block cache size is 64 MiB.
+pub const DEFAULT_BLOCK_CACHE_SIZE: u64 = 64 * 1024 * 1024;
+
 /// The gear table is used to generate the rolling hash mask.
 #[rustfmt::skip]
 pub static DEFAULT_GEAR_TABLE: [u64; 256] = [
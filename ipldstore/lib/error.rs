@@ -38,6 +38,39 @@ pub enum StoreError {
     #[error("Unexpected block codec: expected: {0:?} got: {1:?}")]
     UnexpectedBlockCodec(Codec, Codec),
 
+    /// The block header is not recognized.
+    #[error("Invalid block header: {0:#04x}")]
+    InvalidBlockHeader(u8),
+
+    /// The block could not be encrypted.
+    #[error("Failed to encrypt block: {0}")]
+    EncryptionFailed(Cid),
+
+    /// The block could not be decrypted, e.g. because the key is wrong or the block was tampered
+    /// with.
+    #[error("Failed to decrypt block: {0}")]
+    DecryptionFailed(Cid),
+
+    /// The block wrapping a transformed node in the underlying store is malformed.
+    #[error("Invalid transformed node block: {0}")]
+    InvalidTransformedNode(Cid),
+
+    /// The CAR file is malformed or not supported.
+    #[error("Invalid CAR file: {0}")]
+    InvalidCar(String),
+
+    /// The data of the block does not hash to its CID.
+    #[error("Block does not match its CID: {0}")]
+    BlockCidMismatch(Cid),
+
+    /// The multihash code is not supported.
+    #[error("Unsupported multihash code: {0:#x}")]
+    UnsupportedHash(u64),
+
+    /// The CID configuration is not valid.
+    #[error("Invalid CID config: {0}")]
+    InvalidCidConfig(String),
+
     /// Custom error.
     #[error("Custom error: {0}")]
     Custom(#[from] AnyError),
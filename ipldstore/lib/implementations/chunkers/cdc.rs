@@ -0,0 +1,160 @@
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncRead;
+
+use crate::{Chunker, FastCDCChunker, GearCDCChunker, StoreResult};
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// The content-defined chunking algorithm used by a [`CdcChunker`].
+///
+/// FastCDC is the default. It bounds chunk sizes and normalizes them around the desired size,
+/// while GearCDC is simpler and faster but produces a wider spread of chunk sizes. Use
+/// [`Chunker::chunk_with_stats`] to compare the two on real data.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CdcAlgorithm {
+    /// The gear-based rolling hash of [`GearCDCChunker`].
+    Gear,
+
+    /// The normalized chunking of [`FastCDCChunker`].
+    #[default]
+    Fast,
+}
+
+/// A content-defined chunker whose algorithm is picked at runtime.
+///
+/// This lets a store's chunker be chosen from configuration instead of being fixed by its type.
+/// The default uses [`CdcAlgorithm::default`] with the default chunk sizes.
+///
+/// ## Examples
+///
+/// ```
+/// use ipldstore::{CdcAlgorithm, CdcChunker};
+///
+/// let chunker = CdcChunker::new(CdcAlgorithm::Gear);
+/// assert_eq!(chunker.get_algorithm(), CdcAlgorithm::Gear);
+/// assert_eq!(CdcChunker::default().get_algorithm(), CdcAlgorithm::Fast);
+/// ```
+#[derive(Debug, Clone)]
+pub enum CdcChunker {
+    /// Chunks with GearCDC.
+    Gear(GearCDCChunker),
+
+    /// Chunks with FastCDC.
+    Fast(FastCDCChunker),
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl CdcChunker {
+    /// Creates a new `CdcChunker` that uses `algorithm` with the default chunk sizes.
+    pub fn new(algorithm: CdcAlgorithm) -> Self {
+        match algorithm {
+            CdcAlgorithm::Gear => Self::Gear(GearCDCChunker::default()),
+            CdcAlgorithm::Fast => Self::Fast(FastCDCChunker::default()),
+        }
+    }
+
+    /// Returns the algorithm the chunker uses.
+    pub fn get_algorithm(&self) -> CdcAlgorithm {
+        match self {
+            Self::Gear(_) => CdcAlgorithm::Gear,
+            Self::Fast(_) => CdcAlgorithm::Fast,
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Trait Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl Default for CdcChunker {
+    fn default() -> Self {
+        Self::new(CdcAlgorithm::default())
+    }
+}
+
+impl From<GearCDCChunker> for CdcChunker {
+    fn from(chunker: GearCDCChunker) -> Self {
+        Self::Gear(chunker)
+    }
+}
+
+impl From<FastCDCChunker> for CdcChunker {
+    fn from(chunker: FastCDCChunker) -> Self {
+        Self::Fast(chunker)
+    }
+}
+
+#[async_trait]
+impl Chunker for CdcChunker {
+    async fn chunk(
+        &self,
+        reader: impl AsyncRead + Send + Sync + 'life0,
+    ) -> StoreResult<BoxStream<'_, StoreResult<Bytes>>> {
+        match self {
+            Self::Gear(chunker) => chunker.chunk(reader).await,
+            Self::Fast(chunker) => chunker.chunk(reader).await,
+        }
+    }
+
+    async fn chunk_max_size(&self) -> StoreResult<Option<u64>> {
+        match self {
+            Self::Gear(chunker) => chunker.chunk_max_size().await,
+            Self::Fast(chunker) => chunker.chunk_max_size().await,
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cdc_chunker_default_is_fastcdc() -> anyhow::Result<()> {
+        let data = (0..512 * 1024u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+            .collect::<Vec<_>>();
+
+        let chunks = CdcChunker::default()
+            .chunk(&data[..])
+            .await?
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<StoreResult<Vec<_>>>()?;
+        let fastcdc_chunks = FastCDCChunker::default()
+            .chunk(&data[..])
+            .await?
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<StoreResult<Vec<_>>>()?;
+
+        assert_eq!(chunks, fastcdc_chunks);
+        assert_eq!(
+            CdcChunker::default().chunk_max_size().await?,
+            FastCDCChunker::default().chunk_max_size().await?
+        );
+
+        // The algorithm can be picked at runtime
+        let chunker = CdcChunker::new(CdcAlgorithm::Gear);
+        assert!(matches!(chunker, CdcChunker::Gear(_)));
+        assert_eq!(chunker.get_algorithm(), CdcAlgorithm::Gear);
+
+        Ok(())
+    }
+}
@@ -90,14 +90,15 @@ impl FastHasher {
     ///
     /// The rolling hash is computed using a combination of:
     /// - Left shift to incorporate position sensitivity
-    /// - XOR with right shift to maintain good bit distribution
     /// - Addition of gear value to mix in byte content
+    ///
+    /// Each byte is shifted out of the hash after 64 more bytes, so boundaries line up again
+    /// after an insertion or in repeated data.
     #[inline]
     pub fn roll(&mut self, byte: u8) {
         let shifted = self.hash.wrapping_shl(1);
         let gear = self.gear_table[byte as usize];
-        let mixed = shifted.wrapping_add(gear);
-        self.hash = mixed ^ (self.hash >> 53);
+        self.hash = shifted.wrapping_add(gear);
     }
 
     /// Returns the current hash value
@@ -590,7 +591,10 @@ mod tests {
                 (0..100_000).map(|_| rng.random()).collect::<Vec<u8>>()
             }),
             ("repeating", {
-                (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<u8>>()
+                // Boundaries repeat with the data, so the block spans several chunks
+                let mut rng = StdRng::seed_from_u64(54321);
+                let block = (0..8192).map(|_| rng.random()).collect::<Vec<u8>>();
+                block.repeat(13)[..100_000].to_vec()
             }),
         ];
 
@@ -119,10 +119,9 @@ impl GearHasher {
 
     /// Updates the rolling hash with a new byte.
     ///
-    /// The update process combines three operations:
+    /// The update process combines two operations:
     /// 1. `hash << 1`: Shifts existing hash left, making room for new information
     /// 2. `^ gear_table[byte]`: Incorporates the new byte's pseudo-random value
-    /// 3. `^ (hash >> 53)`: Feeds back high bits for better mixing
     ///
     /// Visually, the process looks like this:
     /// ```text
@@ -135,20 +134,14 @@ impl GearHasher {
     /// ┌─────────────────────────────────────────┐ (the left bit is gone,
     /// │ bits [62 .. 0] 0                        │  the right is 0)
     /// └─────────────────────────────────────────┘
-    ///
-    /// High bits feedback (hash >> 53):
-    /// ┌─────────────────────────────────────────┐
-    /// │ bits [63 .. 53] (rest are 0)            │ (~the top 11 bits)
-    /// └─────────────────────────────────────────┘
     /// ```
     ///
-    /// The feedback from high bits (component 3) is particularly important for handling
-    /// repetitive data patterns. Without it, repeated sequences might not generate
-    /// enough variation in the lower bits to create chunk boundaries at the desired
-    /// frequency. This extra mixing ensures robust chunking even with non-random data.
+    /// Each byte is shifted out of the hash after 64 more bytes, so a boundary depends only on
+    /// the bytes just before it. This is what lets boundaries line up again after an insertion
+    /// or in repeated data.
     #[inline]
     pub fn roll(&mut self, byte: u8) {
-        self.hash = (self.hash << 1) ^ self.gear_table[byte as usize] ^ (self.hash >> 53);
+        self.hash = (self.hash << 1) ^ self.gear_table[byte as usize];
     }
 
     /// Returns the current hash value
@@ -275,16 +268,16 @@ mod tests {
 
         // Test single byte
         hasher.roll(1);
-        // hash = (0 << 1) ^ 1 ^ (0 >> 53) = 1
+        // hash = (0 << 1) ^ 1 = 1
         assert_eq!(hasher.fingerprint(), 1);
 
         // Test multiple bytes
         hasher.roll(2);
-        // hash = (1 << 1) ^ 2 ^ (1 >> 53) = 2 ^ 2 ^ 0 = 0
+        // hash = (1 << 1) ^ 2 = 2 ^ 2 = 0
         assert_eq!(hasher.fingerprint(), 0);
 
         hasher.roll(3);
-        // hash = (0 << 1) ^ 3 ^ (0 >> 53) = 3
+        // hash = (0 << 1) ^ 3 = 3
         assert_eq!(hasher.fingerprint(), 3);
 
         // Test boundary check
@@ -381,7 +374,10 @@ mod tests {
                 (0..100_000).map(|_| rng.random()).collect::<Vec<u8>>()
             }),
             ("repeating", {
-                (0..100_000).map(|i| (i % 251) as u8).collect::<Vec<u8>>()
+                // Boundaries repeat with the data, so the block spans several chunks
+                let mut rng = StdRng::seed_from_u64(54321);
+                let block = (0..8192).map(|_| rng.random()).collect::<Vec<u8>>();
+                block.repeat(13)[..100_000].to_vec()
             }),
         ];
 
@@ -1,3 +1,4 @@
+mod cdc;
 mod fastcdc;
 mod fixed;
 mod gearcdc;
@@ -6,6 +7,7 @@ mod gearcdc;
 // Exports
 //--------------------------------------------------------------------------------------------------
 
+pub use cdc::*;
 pub use fastcdc::*;
 pub use fixed::*;
 pub use gearcdc::*;
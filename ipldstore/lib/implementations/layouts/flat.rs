@@ -14,7 +14,10 @@ use ipld_core::cid::Cid;
 use monoutils::SeekableReader;
 use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
 
-use crate::{IpldStore, Layout, LayoutError, LayoutSeekable, MerkleNode, StoreError, StoreResult};
+use crate::{
+    IpldStore, Layout, LayoutError, LayoutSeekable, MerkleNode, StoreError, StoreResult,
+    DEFAULT_LAYOUT_BATCH_SIZE,
+};
 
 //--------------------------------------------------------------------------------------------------
 // Types
@@ -257,12 +260,33 @@ impl Layout for FlatLayout {
         store: impl IpldStore + Send + Sync + 'static,
     ) -> StoreResult<BoxStream<'a, StoreResult<Cid>>> {
         let s = try_stream! {
+            // Chunks are written in batches as they arrive, so only a batch is held in memory at
+            // a time rather than the whole input.
             let mut children = Vec::new();
-            while let Some(Ok(chunk)) = stream.next().await {
-                let len = chunk.len();
-                let cid = store.put_raw_block(chunk).await?;
-                children.push((cid, len));
-                yield cid;
+            let mut batch = Vec::new();
+            let mut batch_size = 0;
+            loop {
+                let chunk = stream.next().await.transpose()?;
+                let done = chunk.is_none();
+                if let Some(chunk) = chunk {
+                    batch_size += chunk.len() as u64;
+                    batch.push(chunk);
+                }
+
+                if !batch.is_empty() && (done || batch_size >= DEFAULT_LAYOUT_BATCH_SIZE) {
+                    let sizes = batch.iter().map(|chunk| chunk.len()).collect::<Vec<_>>();
+                    let cids = store.put_many(std::mem::take(&mut batch)).await?;
+                    for cid in cids.iter() {
+                        yield *cid;
+                    }
+
+                    children.extend(cids.into_iter().zip(sizes));
+                    batch_size = 0;
+                }
+
+                if done {
+                    break;
+                }
             }
 
             if children.is_empty() {
@@ -576,11 +600,9 @@ mod helper {
 
     use super::*;
 
-    pub(super) fn data_and_chunk_stream() -> (
-        [u8; 56],
-        Vec<Bytes>,
-        Pin<Box<dyn Stream<Item = StoreResult<Bytes>> + Send + 'static>>,
-    ) {
+    type ChunkStream = Pin<Box<dyn Stream<Item = StoreResult<Bytes>> + Send + 'static>>;
+
+    pub(super) fn data_and_chunk_stream() -> ([u8; 56], Vec<Bytes>, ChunkStream) {
         let data = b"Lorem ipsum dolor sit amet, consectetur adipiscing elit.".to_owned();
 
         let chunks = vec![
@@ -596,11 +618,7 @@ mod helper {
             Bytes::from("t."),              // 2 bytes
         ];
 
-        let chunks_result = chunks
-            .iter()
-            .cloned()
-            .map(|b| crate::Ok(b))
-            .collect::<Vec<_>>();
+        let chunks_result = chunks.iter().cloned().map(crate::Ok).collect::<Vec<_>>();
 
         let chunk_stream = Box::pin(stream::iter(chunks_result));
 
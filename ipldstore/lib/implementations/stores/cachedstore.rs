@@ -0,0 +1,408 @@
+use std::{collections::HashSet, pin::Pin, sync::Arc};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use getset::{CopyGetters, Getters};
+use ipld_core::{cid::Cid, ipld::Ipld};
+use lru::LruCache;
+use monoutils::SeekableReader;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::{io::AsyncRead, sync::Mutex};
+
+use crate::{
+    CidConfig, Codec, FlatLayout, IpldReferences, IpldStore, IpldStoreSeekable, Layout,
+    LayoutSeekable, RawStore, StoreError, StoreResult, DEFAULT_BLOCK_CACHE_SIZE,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// A store that keeps recently read blocks of an underlying [`IpldStore`] in memory.
+///
+/// Blocks are content-addressed and therefore immutable, so cached blocks never need to be
+/// invalidated. The cache is bounded by the total size of the cached blocks in bytes and evicts the
+/// least recently used blocks first once that limit is exceeded. Blocks larger than the limit are
+/// never cached.
+///
+/// Writes go straight to the underlying store. Reads check the cache first and populate it on a
+/// miss. Chunked data read with [`get_bytes`][IpldStore::get_bytes] is resolved block by block
+/// through the cache using the configured layout, which must match the layout of the underlying
+/// store.
+///
+/// ## Examples
+///
+/// ```
+/// use ipldstore::{CachedStore, MemoryStore};
+///
+/// // Cache up to 16 MiB of blocks
+/// let store = CachedStore::new(MemoryStore::default(), 16 * 1024 * 1024);
+/// assert_eq!(store.get_capacity(), 16 * 1024 * 1024);
+/// ```
+#[derive(Debug, Clone, Getters, CopyGetters)]
+pub struct CachedStoreImpl<S, L = FlatLayout>
+where
+    S: IpldStore,
+    L: Layout + Default,
+{
+    /// The underlying store.
+    #[getset(get = "pub with_prefix")]
+    store: S,
+
+    /// The maximum total size of the cached blocks in bytes.
+    #[getset(get_copy = "pub with_prefix")]
+    capacity: u64,
+
+    /// The cached blocks.
+    cache: Arc<Mutex<BlockCache>>,
+
+    /// The layout strategy used to retrieve chunked data.
+    layout: Arc<L>,
+}
+
+/// A store that keeps recently read blocks of an underlying [`IpldStore`] in memory.
+///
+/// This version of the store uses [`FlatLayout`] to retrieve chunked data.
+pub type CachedStore<S> = CachedStoreImpl<S, FlatLayout>;
+
+/// An LRU cache of blocks bounded by the total size of the blocks.
+#[derive(Debug)]
+struct BlockCache {
+    /// The cached blocks, from most to least recently used.
+    blocks: LruCache<Cid, Bytes>,
+
+    /// The total size of the cached blocks in bytes.
+    size: u64,
+
+    /// The number of block lookups served from the cache.
+    hits: u64,
+
+    /// The number of block lookups that had to go to the underlying store.
+    misses: u64,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl<S, L> CachedStoreImpl<S, L>
+where
+    S: IpldStore,
+    L: Layout + Default,
+{
+    /// Creates a new `CachedStore` over the given store that caches at most `capacity` bytes.
+    pub fn new(store: S, capacity: u64) -> Self {
+        Self {
+            store,
+            capacity,
+            cache: Arc::new(Mutex::new(BlockCache {
+                blocks: LruCache::unbounded(),
+                size: 0,
+                hits: 0,
+                misses: 0,
+            })),
+            layout: Arc::new(L::default()),
+        }
+    }
+
+    /// Creates a new `CachedStore` over the given store using the default cache capacity.
+    pub fn with_default_capacity(store: S) -> Self {
+        Self::new(store, DEFAULT_BLOCK_CACHE_SIZE)
+    }
+
+    /// Returns the total size of the currently cached blocks in bytes.
+    pub async fn get_cached_size(&self) -> u64 {
+        self.cache.lock().await.size
+    }
+
+    /// Returns the number of block lookups served from the cache.
+    pub async fn get_hits(&self) -> u64 {
+        self.cache.lock().await.hits
+    }
+
+    /// Returns the number of block lookups that had to go to the underlying store.
+    pub async fn get_misses(&self) -> u64 {
+        self.cache.lock().await.misses
+    }
+
+    /// Removes all blocks from the cache.
+    pub async fn clear_cache(&self) {
+        let mut cache = self.cache.lock().await;
+        cache.blocks.clear();
+        cache.size = 0;
+    }
+
+    /// Returns the cached block with the given `Cid`, marking it as most recently used.
+    async fn get_cached(&self, cid: &Cid) -> Option<Bytes> {
+        let mut cache = self.cache.lock().await;
+        let bytes = cache.blocks.get(cid).cloned();
+        match bytes {
+            Some(_) => cache.hits += 1,
+            None => cache.misses += 1,
+        }
+
+        bytes
+    }
+
+    /// Adds a block to the cache, evicting the least recently used blocks to stay within capacity.
+    async fn insert_cached(&self, cid: Cid, bytes: Bytes) {
+        let size = bytes.len() as u64;
+        if size > self.capacity {
+            return;
+        }
+
+        let mut cache = self.cache.lock().await;
+        if let Some(old) = cache.blocks.put(cid, bytes) {
+            cache.size -= old.len() as u64;
+        }
+
+        cache.size += size;
+        while cache.size > self.capacity {
+            match cache.blocks.pop_lru() {
+                Some((_, evicted)) => cache.size -= evicted.len() as u64,
+                None => break,
+            }
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Trait Implementations
+//--------------------------------------------------------------------------------------------------
+
+#[async_trait]
+impl<S, L> IpldStore for CachedStoreImpl<S, L>
+where
+    S: IpldStore + Send + Sync + 'static,
+    L: Layout + Default + Clone + Send + Sync + 'static,
+{
+    async fn put_node<T>(&self, node: &T) -> StoreResult<Cid>
+    where
+        T: Serialize + IpldReferences + Sync,
+    {
+        self.store.put_node(node).await
+    }
+
+    async fn put_bytes(&self, reader: impl AsyncRead + Send + Sync) -> StoreResult<Cid> {
+        self.store.put_bytes(reader).await
+    }
+
+    async fn get_node<D>(&self, cid: &Cid) -> StoreResult<D>
+    where
+        D: DeserializeOwned + Send,
+    {
+        match cid.codec().try_into()? {
+            Codec::DagCbor => {
+                let bytes = match self.get_cached(cid).await {
+                    Some(bytes) => bytes,
+                    None => {
+                        // Fetch the node generically so its canonical encoding can be cached.
+                        let ipld: Ipld = self.store.get_node(cid).await?;
+                        let bytes = Bytes::from(
+                            serde_ipld_dagcbor::to_vec(&ipld).map_err(StoreError::custom)?,
+                        );
+
+                        self.insert_cached(*cid, bytes.clone()).await;
+                        bytes
+                    }
+                };
+
+                serde_ipld_dagcbor::from_slice::<D>(&bytes).map_err(StoreError::custom)
+            }
+            codec => Err(StoreError::UnexpectedBlockCodec(Codec::DagCbor, codec)),
+        }
+    }
+
+    async fn get_bytes(&self, cid: &Cid) -> StoreResult<Pin<Box<dyn AsyncRead + Send>>> {
+        self.layout.retrieve(cid, self.clone()).await
+    }
+
+    async fn get_bytes_size(&self, cid: &Cid) -> StoreResult<u64> {
+        self.layout.get_size(cid, self.clone()).await
+    }
+
+    async fn has(&self, cid: &Cid) -> bool {
+        if self.cache.lock().await.blocks.contains(cid) {
+            return true;
+        }
+
+        self.store.has(cid).await
+    }
+
+    async fn get_supported_codecs(&self) -> HashSet<Codec> {
+        self.store.get_supported_codecs().await
+    }
+
+    async fn get_max_node_block_size(&self) -> StoreResult<Option<u64>> {
+        self.store.get_max_node_block_size().await
+    }
+
+    async fn get_cid_config(&self) -> CidConfig {
+        self.store.get_cid_config().await
+    }
+
+    async fn get_block_count(&self) -> StoreResult<u64> {
+        self.store.get_block_count().await
+    }
+
+    async fn supports_garbage_collection(&self) -> bool {
+        self.store.supports_garbage_collection().await
+    }
+
+    async fn garbage_collect(&self, cid: &Cid) -> StoreResult<HashSet<Cid>> {
+        let removed = self.store.garbage_collect(cid).await?;
+
+        // Drop removed blocks so the cache doesn't serve blocks the store no longer has.
+        let mut cache = self.cache.lock().await;
+        for cid in removed.iter() {
+            if let Some(bytes) = cache.blocks.pop(cid) {
+                cache.size -= bytes.len() as u64;
+            }
+        }
+
+        Ok(removed)
+    }
+}
+
+#[async_trait]
+impl<S, L> RawStore for CachedStoreImpl<S, L>
+where
+    S: IpldStore + Send + Sync + 'static,
+    L: Layout + Default + Clone + Send + Sync + 'static,
+{
+    async fn put_raw_block(&self, bytes: impl Into<Bytes> + Send) -> StoreResult<Cid> {
+        self.store.put_raw_block(bytes).await
+    }
+
+    async fn get_raw_block(&self, cid: &Cid) -> StoreResult<Bytes> {
+        if let Some(bytes) = self.get_cached(cid).await {
+            return Ok(bytes);
+        }
+
+        let bytes = self.store.get_raw_block(cid).await?;
+        self.insert_cached(*cid, bytes.clone()).await;
+
+        Ok(bytes)
+    }
+
+    async fn get_max_raw_block_size(&self) -> StoreResult<Option<u64>> {
+        self.store.get_max_raw_block_size().await
+    }
+}
+
+#[async_trait]
+impl<S, L> IpldStoreSeekable for CachedStoreImpl<S, L>
+where
+    S: IpldStore + Send + Sync + 'static,
+    L: LayoutSeekable + Default + Clone + Send + Sync + 'static,
+{
+    async fn get_seekable_bytes(
+        &self,
+        cid: &Cid,
+    ) -> StoreResult<Pin<Box<dyn SeekableReader + Send + 'static>>> {
+        self.layout.retrieve_seekable(cid, self.clone()).await
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+
+    use crate::{CountingStore, MemoryStore};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cached_store_hits_backing_store_once() -> anyhow::Result<()> {
+        let backing = CountingStore::new(MemoryStore::default());
+        let store = CachedStore::new(backing.clone(), 1024 * 1024);
+
+        // Raw blocks
+        let raw_cid = store.put_raw_block(b"hot block".to_vec()).await?;
+        assert_eq!(store.get_raw_block(&raw_cid).await?.as_ref(), b"hot block");
+        assert_eq!(store.get_raw_block(&raw_cid).await?.as_ref(), b"hot block");
+        assert_eq!(backing.get_metrics().get_block_reads(), 1);
+        assert_eq!(store.get_hits().await, 1);
+        assert_eq!(store.get_misses().await, 1);
+
+        // Nodes
+        let node = ("hot".to_string(), "node".to_string());
+        let node_cid = store.put_node(&node).await?;
+        let first: (String, String) = store.get_node(&node_cid).await?;
+        let second: (String, String) = store.get_node(&node_cid).await?;
+        assert_eq!(first, node);
+        assert_eq!(second, node);
+        assert_eq!(backing.get_metrics().get_block_reads(), 2);
+
+        // Chunked bytes
+        let data = b"hello cached world".to_vec();
+        let bytes_cid = store.put_bytes(data.as_slice()).await?;
+        for _ in 0..2 {
+            let mut reader = store.get_bytes(&bytes_cid).await?;
+            let mut retrieved = Vec::new();
+            reader.read_to_end(&mut retrieved).await?;
+            assert_eq!(retrieved, data);
+        }
+        // The merkle node and its single chunk
+        assert_eq!(backing.get_metrics().get_block_reads(), 4);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cached_store_evicts_least_recently_used() -> anyhow::Result<()> {
+        let backing = CountingStore::new(MemoryStore::default());
+        let store = CachedStore::new(backing.clone(), 10);
+
+        let a = store.put_raw_block(vec![b'a'; 4]).await?;
+        let b = store.put_raw_block(vec![b'b'; 4]).await?;
+        let c = store.put_raw_block(vec![b'c'; 4]).await?;
+        let large = store.put_raw_block(vec![b'l'; 11]).await?;
+
+        store.get_raw_block(&a).await?;
+        store.get_raw_block(&b).await?;
+        store.get_raw_block(&a).await?; // `a` is now more recently used than `b`
+        assert_eq!(backing.get_metrics().get_block_reads(), 2);
+
+        // Caching `c` exceeds the capacity and evicts `b`
+        store.get_raw_block(&c).await?;
+        assert_eq!(store.get_cached_size().await, 8);
+        store.get_raw_block(&a).await?;
+        assert_eq!(backing.get_metrics().get_block_reads(), 3);
+        store.get_raw_block(&b).await?;
+        assert_eq!(backing.get_metrics().get_block_reads(), 4);
+
+        // Blocks larger than the capacity are never cached
+        store.get_raw_block(&large).await?;
+        store.get_raw_block(&large).await?;
+        assert_eq!(backing.get_metrics().get_block_reads(), 6);
+        assert!(store.get_cached_size().await <= 10);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cached_store_concurrent_reads() -> anyhow::Result<()> {
+        let store = CachedStore::new(MemoryStore::default(), 1024);
+        let cid = store.put_raw_block(b"shared".to_vec()).await?;
+
+        let handles = (0..8)
+            .map(|_| {
+                let store = store.clone();
+                tokio::spawn(async move { store.get_raw_block(&cid).await })
+            })
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            assert_eq!(handle.await??.as_ref(), b"shared");
+        }
+
+        assert_eq!(store.get_cached_size().await, 6);
+
+        Ok(())
+    }
+}
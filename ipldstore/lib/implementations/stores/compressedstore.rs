@@ -0,0 +1,290 @@
+use bytes::Bytes;
+use ipld_core::cid::Cid;
+
+use crate::{
+    BlockTransform, Chunker, FastCDCChunker, FlatLayout, IpldStore, Layout, StoreError,
+    StoreResult, TransformStoreImpl, DEFAULT_COMPRESSION_LEVEL,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The header byte for blocks stored as is.
+const RAW_BLOCK_HEADER: u8 = 0x00;
+
+/// The header byte for blocks compressed with zstd.
+const ZSTD_BLOCK_HEADER: u8 = 0x01;
+
+/// The size of the header prepended to every block written to the underlying store.
+const BLOCK_HEADER_SIZE: u64 = 1;
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// A store that transparently compresses blocks with [zstd][zstd] before writing them to an
+/// underlying [`IpldStore`].
+///
+/// Every block written to the underlying store is prefixed with a header byte that says whether
+/// the payload is compressed or stored as is. Blocks that do not shrink when compressed are stored
+/// as is, so incompressible data is never inflated beyond the header byte.
+///
+/// ## CID Semantics
+///
+/// CIDs address the uncompressed data, so the same data gets the same CID when written through a
+/// `CompressedStore` as when written to a plain store. The store indexes where the compressed
+/// blocks are in the underlying store, and a new `CompressedStore` reads everything written before
+/// once it has loaded the saved index. The header byte is all that is needed to decompress a
+/// block, so stores at different levels read each other's blocks. Compression is deterministic
+/// for a given level, so deduplication is unaffected. See [`TransformStoreImpl`].
+///
+/// ## Chunking and Layout
+///
+/// Compression is applied per block, after chunking. The chunker is configurable via the `chunker`
+/// field and the layout strategy via the `layout` field.
+///
+/// ## Seeking
+///
+/// Each chunk is compressed on its own, so the layout's index of chunk sizes doubles as an index
+/// of the compressed blocks. A seekable reader jumps straight to the chunk holding the new
+/// position and only decompresses the chunks it reads.
+///
+/// ## Examples
+///
+/// ```
+/// use ipldstore::{CompressedStore, MemoryStore};
+///
+/// let store = CompressedStore::with_level(MemoryStore::default(), 19);
+/// assert_eq!(store.get_level(), 19);
+/// ```
+///
+/// [zstd]: https://facebook.github.io/zstd/
+pub type CompressedStoreImpl<S, C = FastCDCChunker, L = FlatLayout> =
+    TransformStoreImpl<S, Compression, C, L>;
+
+/// A store that transparently compresses blocks with zstd before writing them to an underlying
+/// [`IpldStore`].
+///
+/// This version of the store uses a [`FastCDCChunker`] for chunking and [`FlatLayout`] for layout.
+pub type CompressedStore<S> = CompressedStoreImpl<S, FastCDCChunker, FlatLayout>;
+
+/// The [`BlockTransform`] of a [`CompressedStoreImpl`], compressing blocks with zstd.
+#[derive(Debug, Clone, Copy)]
+pub struct Compression {
+    /// The zstd compression level.
+    level: i32,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl<S, C, L> CompressedStoreImpl<S, C, L>
+where
+    S: IpldStore + Sync,
+    C: Chunker + Default,
+    L: Layout + Default,
+{
+    /// Creates a new `CompressedStore` over the given store using the default compression level.
+    pub fn new(store: S) -> Self {
+        Self::with_level(store, DEFAULT_COMPRESSION_LEVEL)
+    }
+
+    /// Creates a new `CompressedStore` over the given store using the given compression level.
+    ///
+    /// The level is clamped to the range supported by zstd.
+    pub fn with_level(store: S, level: i32) -> Self {
+        Self::with_transform(store, Compression::new(level))
+    }
+
+    /// Returns the zstd compression level.
+    pub fn get_level(&self) -> i32 {
+        self.get_transform().level
+    }
+}
+
+impl Compression {
+    /// Creates a new `Compression` with the given level, clamped to the range supported by zstd.
+    pub fn new(level: i32) -> Self {
+        let levels = zstd::compression_level_range();
+        Self {
+            level: level.clamp(*levels.start(), *levels.end()),
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Trait Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl BlockTransform for Compression {
+    /// Compresses `bytes`, falling back to the original bytes if compression doesn't shrink them,
+    /// and prepends the header byte.
+    fn encode(&self, _cid: &Cid, bytes: &[u8]) -> StoreResult<Vec<u8>> {
+        let compressed = zstd::encode_all(bytes, self.level).map_err(StoreError::custom)?;
+
+        let (header, payload) = if compressed.len() < bytes.len() {
+            (ZSTD_BLOCK_HEADER, compressed.as_slice())
+        } else {
+            (RAW_BLOCK_HEADER, bytes)
+        };
+
+        let mut encoded = Vec::with_capacity(payload.len() + BLOCK_HEADER_SIZE as usize);
+        encoded.push(header);
+        encoded.extend_from_slice(payload);
+
+        Ok(encoded)
+    }
+
+    /// Strips the header byte from `bytes` and decompresses the payload if necessary.
+    fn decode(&self, _cid: &Cid, bytes: &[u8]) -> StoreResult<Bytes> {
+        match bytes.split_first() {
+            Some((&RAW_BLOCK_HEADER, payload)) => Ok(Bytes::copy_from_slice(payload)),
+            Some((&ZSTD_BLOCK_HEADER, payload)) => zstd::decode_all(payload)
+                .map(Bytes::from)
+                .map_err(StoreError::custom),
+            Some((&header, _)) => Err(StoreError::InvalidBlockHeader(header)),
+            None => Err(StoreError::custom(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "missing block header",
+            ))),
+        }
+    }
+
+    /// Returns the size of the header byte, added in the worst case where the block is stored as
+    /// is.
+    fn get_overhead(&self) -> u64 {
+        BLOCK_HEADER_SIZE
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use rand::RngCore;
+    use tokio::io::AsyncReadExt;
+
+    use crate::{Codec, MemoryStore, RawStore, DEFAULT_MAX_CHUNK_SIZE};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_compressed_store_compressible_bytes() -> anyhow::Result<()> {
+        let inner = MemoryStore::default();
+        let store = CompressedStore::new(inner.clone());
+
+        // Highly repetitive data spanning several chunks
+        let data = b"hello compressed world! ".repeat(DEFAULT_MAX_CHUNK_SIZE as usize / 8);
+        let cid = store.put_bytes(data.as_slice()).await?;
+
+        // Read it back
+        let mut reader = store.get_bytes(&cid).await?;
+        let mut retrieved = Vec::new();
+        reader.read_to_end(&mut retrieved).await?;
+        assert_eq!(retrieved, data);
+        assert_eq!(store.get_bytes_size(&cid).await?, data.len() as u64);
+
+        // The CID addresses the uncompressed data, the same as in a plain store
+        let plain_cid = MemoryStore::default().put_bytes(data.as_slice()).await?;
+        assert_eq!(cid, plain_cid);
+        let stored_cid = store.get_stored_cid(&cid).await.unwrap();
+        assert_ne!(stored_cid, cid);
+        assert!(inner.has(&stored_cid).await);
+
+        // The underlying store holds fewer bytes than the original data
+        let stored = inner.get_blocks().read().await;
+        let stored_size: usize = stored.values().map(|(_, bytes)| bytes.len()).sum();
+        assert!(stored_size < data.len());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_compressed_store_random_bytes() -> anyhow::Result<()> {
+        let inner = MemoryStore::default();
+        let store = CompressedStore::new(inner.clone());
+
+        let mut data = vec![0u8; 64 * 1024];
+        rand::rng().fill_bytes(&mut data);
+        let cid = store.put_raw_block(data.clone()).await?;
+
+        // Incompressible data is stored as is, plus the header byte
+        let stored = inner.get_blocks().read().await;
+        let (_, stored_bytes) = &stored[&store.get_stored_cid(&cid).await.unwrap()];
+        assert_eq!(stored_bytes[0], RAW_BLOCK_HEADER);
+        assert_eq!(
+            stored_bytes.len() as u64,
+            data.len() as u64 + BLOCK_HEADER_SIZE
+        );
+        drop(stored);
+
+        let retrieved = store.get_raw_block(&cid).await?;
+        assert_eq!(retrieved.as_ref(), data.as_slice());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_compressed_store_node() -> anyhow::Result<()> {
+        let store = CompressedStore::with_level(MemoryStore::default(), 3);
+
+        let node = "compressed ".repeat(100);
+        let cid = store.put_node(&node).await?;
+        assert!(store.has(&cid).await);
+
+        let retrieved: String = store.get_node(&cid).await?;
+        assert_eq!(retrieved, node);
+
+        // Storing the same node again is deduplicated
+        assert_eq!(store.put_node(&node).await?, cid);
+        assert_eq!(store.get_block_count().await?, 1);
+
+        // Nodes can't be read as raw blocks
+        assert!(matches!(
+            store.get_raw_block(&cid).await,
+            Err(StoreError::UnexpectedBlockCodec(Codec::Raw, Codec::DagCbor))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_compressed_store_reopen() -> anyhow::Result<()> {
+        let inner = MemoryStore::default();
+        let store = CompressedStore::new(inner.clone());
+
+        let data = b"reopened ".repeat(DEFAULT_MAX_CHUNK_SIZE as usize / 4);
+        let bytes_cid = store.put_bytes(data.as_slice()).await?;
+        let node_cid = store.put_node(&"reopened node").await?;
+        let block_count = store.get_block_count().await?;
+        let index_cid = store.get_index_cid().await.unwrap();
+        drop(store);
+
+        // A store without the index doesn't know the blocks
+        assert!(!CompressedStore::new(inner.clone()).has(&bytes_cid).await);
+
+        // A new store over the same underlying store, even at another level, reads everything back
+        let store =
+            CompressedStore::open_with_transform(inner, Compression::new(19), &index_cid).await?;
+        assert!(store.has(&bytes_cid).await);
+        assert!(store.has(&node_cid).await);
+        assert_eq!(store.get_block_count().await?, block_count);
+
+        let mut retrieved = Vec::new();
+        store
+            .get_bytes(&bytes_cid)
+            .await?
+            .read_to_end(&mut retrieved)
+            .await?;
+        assert_eq!(retrieved, data);
+
+        let node: String = store.get_node(&node_cid).await?;
+        assert_eq!(node, "reopened node");
+
+        Ok(())
+    }
+}
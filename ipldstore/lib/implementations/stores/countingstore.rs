@@ -0,0 +1,370 @@
+use std::{
+    collections::HashSet,
+    io::SeekFrom,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use getset::{CopyGetters, Getters};
+use ipld_core::cid::Cid;
+use monoutils::SeekableReader;
+use serde::{de::DeserializeOwned, Serialize};
+use tokio::io::{AsyncRead, AsyncSeek, ReadBuf};
+
+use crate::{
+    CidConfig, Codec, IpldReferences, IpldStore, IpldStoreSeekable, RawStore, StoreResult,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// A store that counts the operations issued to an underlying [`IpldStore`].
+///
+/// Calls to [`get_bytes`][IpldStore::get_bytes], [`put_bytes`][IpldStore::put_bytes] and
+/// [`has`][IpldStore::has] are counted along with the number of bytes read from and written through
+/// them. This is useful for diagnosing write amplification or checking that deduplication works.
+/// Single blocks fetched with [`get_node`][IpldStore::get_node],
+/// [`get_raw_block`][RawStore::get_raw_block] and [`get_many`][IpldStore::get_many] are counted
+/// too, which is useful for checking that a cache in front of the store works. All other methods
+/// are delegated to the underlying store without being counted.
+///
+/// The counters are shared between clones of the store.
+///
+/// ## Examples
+///
+/// ```
+/// use ipldstore::{CountingStore, IpldStore, MemoryStore};
+///
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// let store = CountingStore::new(MemoryStore::default());
+/// store.put_bytes(b"Hello, World!".as_slice()).await?;
+///
+/// let metrics = store.get_metrics();
+/// assert_eq!(metrics.get_writes(), 1);
+/// assert_eq!(metrics.get_bytes_written(), 13);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Getters)]
+pub struct CountingStore<S>
+where
+    S: IpldStore,
+{
+    /// The underlying store.
+    #[getset(get = "pub with_prefix")]
+    store: S,
+
+    /// The operation counters.
+    counters: Arc<Counters>,
+}
+
+/// A snapshot of the operations counted by a [`CountingStore`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, CopyGetters)]
+#[getset(get_copy = "pub with_prefix")]
+pub struct CountingStoreMetrics {
+    /// The number of calls to [`get_bytes`][IpldStore::get_bytes].
+    reads: u64,
+
+    /// The number of calls to [`put_bytes`][IpldStore::put_bytes].
+    writes: u64,
+
+    /// The number of calls to [`has`][IpldStore::has].
+    has_checks: u64,
+
+    /// The number of blocks fetched with [`get_node`][IpldStore::get_node],
+    /// [`get_raw_block`][RawStore::get_raw_block] and [`get_many`][IpldStore::get_many].
+    block_reads: u64,
+
+    /// The number of bytes read from readers returned by [`get_bytes`][IpldStore::get_bytes].
+    bytes_read: u64,
+
+    /// The number of bytes passed to [`put_bytes`][IpldStore::put_bytes].
+    bytes_written: u64,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    reads: AtomicU64,
+    writes: AtomicU64,
+    has_checks: AtomicU64,
+    block_reads: AtomicU64,
+    bytes_read: Arc<AtomicU64>,
+    bytes_written: Arc<AtomicU64>,
+}
+
+/// A reader that adds the number of bytes read through it to a counter.
+struct CountingReader<R> {
+    reader: R,
+    count: Arc<AtomicU64>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl<S> CountingStore<S>
+where
+    S: IpldStore,
+{
+    /// Creates a new `CountingStore` over the given store with all counters at zero.
+    pub fn new(store: S) -> Self {
+        Self {
+            store,
+            counters: Arc::new(Counters::default()),
+        }
+    }
+
+    /// Returns a snapshot of the counters.
+    pub fn get_metrics(&self) -> CountingStoreMetrics {
+        CountingStoreMetrics {
+            reads: self.counters.reads.load(Ordering::Relaxed),
+            writes: self.counters.writes.load(Ordering::Relaxed),
+            has_checks: self.counters.has_checks.load(Ordering::Relaxed),
+            block_reads: self.counters.block_reads.load(Ordering::Relaxed),
+            bytes_read: self.counters.bytes_read.load(Ordering::Relaxed),
+            bytes_written: self.counters.bytes_written.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Resets all counters to zero.
+    pub fn reset_metrics(&self) {
+        self.counters.reads.store(0, Ordering::Relaxed);
+        self.counters.writes.store(0, Ordering::Relaxed);
+        self.counters.has_checks.store(0, Ordering::Relaxed);
+        self.counters.block_reads.store(0, Ordering::Relaxed);
+        self.counters.bytes_read.store(0, Ordering::Relaxed);
+        self.counters.bytes_written.store(0, Ordering::Relaxed);
+    }
+}
+
+impl<R> CountingReader<R> {
+    fn new(reader: R, count: Arc<AtomicU64>) -> Self {
+        Self { reader, count }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Trait Implementations
+//--------------------------------------------------------------------------------------------------
+
+#[async_trait]
+impl<S> IpldStore for CountingStore<S>
+where
+    S: IpldStore + Send + Sync,
+{
+    async fn put_node<T>(&self, node: &T) -> StoreResult<Cid>
+    where
+        T: Serialize + IpldReferences + Sync,
+    {
+        self.store.put_node(node).await
+    }
+
+    async fn put_bytes(&self, reader: impl AsyncRead + Send + Sync) -> StoreResult<Cid> {
+        self.counters.writes.fetch_add(1, Ordering::Relaxed);
+        let reader = CountingReader::new(Box::pin(reader), self.counters.bytes_written.clone());
+        self.store.put_bytes(reader).await
+    }
+
+    async fn get_node<D>(&self, cid: &Cid) -> StoreResult<D>
+    where
+        D: DeserializeOwned + Send,
+    {
+        self.counters.block_reads.fetch_add(1, Ordering::Relaxed);
+        self.store.get_node(cid).await
+    }
+
+    async fn get_bytes(&self, cid: &Cid) -> StoreResult<Pin<Box<dyn AsyncRead + Send>>> {
+        self.counters.reads.fetch_add(1, Ordering::Relaxed);
+        let reader = self.store.get_bytes(cid).await?;
+        Ok(Box::pin(CountingReader::new(
+            reader,
+            self.counters.bytes_read.clone(),
+        )))
+    }
+
+    async fn get_bytes_size(&self, cid: &Cid) -> StoreResult<u64> {
+        self.store.get_bytes_size(cid).await
+    }
+
+    async fn has(&self, cid: &Cid) -> bool {
+        self.counters.has_checks.fetch_add(1, Ordering::Relaxed);
+        self.store.has(cid).await
+    }
+
+    async fn put_many(&self, blocks: Vec<Bytes>) -> StoreResult<Vec<Cid>> {
+        self.store.put_many(blocks).await
+    }
+
+    async fn put_nodes<T>(&self, nodes: &[T]) -> StoreResult<Vec<Cid>>
+    where
+        T: Serialize + IpldReferences + Sync,
+    {
+        self.store.put_nodes(nodes).await
+    }
+
+    async fn get_many(&self, cids: &[Cid]) -> StoreResult<Vec<Option<Bytes>>> {
+        self.counters
+            .block_reads
+            .fetch_add(cids.len() as u64, Ordering::Relaxed);
+        self.store.get_many(cids).await
+    }
+
+    async fn get_supported_codecs(&self) -> HashSet<Codec> {
+        self.store.get_supported_codecs().await
+    }
+
+    async fn get_max_node_block_size(&self) -> StoreResult<Option<u64>> {
+        self.store.get_max_node_block_size().await
+    }
+
+    async fn get_cid_config(&self) -> CidConfig {
+        self.store.get_cid_config().await
+    }
+
+    async fn is_empty(&self) -> StoreResult<bool> {
+        self.store.is_empty().await
+    }
+
+    async fn get_block_count(&self) -> StoreResult<u64> {
+        self.store.get_block_count().await
+    }
+
+    async fn supports_garbage_collection(&self) -> bool {
+        self.store.supports_garbage_collection().await
+    }
+
+    async fn garbage_collect(&self, cid: &Cid) -> StoreResult<HashSet<Cid>> {
+        self.store.garbage_collect(cid).await
+    }
+}
+
+#[async_trait]
+impl<S> RawStore for CountingStore<S>
+where
+    S: IpldStore + Send + Sync,
+{
+    async fn put_raw_block(&self, bytes: impl Into<Bytes> + Send) -> StoreResult<Cid> {
+        self.store.put_raw_block(bytes).await
+    }
+
+    async fn get_raw_block(&self, cid: &Cid) -> StoreResult<Bytes> {
+        self.counters.block_reads.fetch_add(1, Ordering::Relaxed);
+        self.store.get_raw_block(cid).await
+    }
+
+    async fn get_max_raw_block_size(&self) -> StoreResult<Option<u64>> {
+        self.store.get_max_raw_block_size().await
+    }
+}
+
+#[async_trait]
+impl<S> IpldStoreSeekable for CountingStore<S>
+where
+    S: IpldStoreSeekable + Send + Sync,
+{
+    async fn get_seekable_bytes(
+        &self,
+        cid: &Cid,
+    ) -> StoreResult<Pin<Box<dyn SeekableReader + Send + 'static>>> {
+        self.counters.reads.fetch_add(1, Ordering::Relaxed);
+        let reader = self.store.get_seekable_bytes(cid).await?;
+        Ok(Box::pin(CountingReader::new(
+            reader,
+            self.counters.bytes_read.clone(),
+        )))
+    }
+}
+
+impl<R> AsyncRead for CountingReader<R>
+where
+    R: AsyncRead + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let filled = buf.filled().len();
+        let result = Pin::new(&mut self.reader).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = result {
+            let read = (buf.filled().len() - filled) as u64;
+            self.count.fetch_add(read, Ordering::Relaxed);
+        }
+
+        result
+    }
+}
+
+impl<R> AsyncSeek for CountingReader<R>
+where
+    R: AsyncSeek + Unpin,
+{
+    fn start_seek(mut self: Pin<&mut Self>, position: SeekFrom) -> std::io::Result<()> {
+        Pin::new(&mut self.reader).start_seek(position)
+    }
+
+    fn poll_complete(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<u64>> {
+        Pin::new(&mut self.reader).poll_complete(cx)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+
+    use crate::MemoryStore;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_counting_store_counts_deduplicated_puts() -> anyhow::Result<()> {
+        let store = CountingStore::new(MemoryStore::default());
+        let data = b"Hello, World!".to_vec();
+
+        // Storing the same bytes twice issues two puts but yields one CID
+        let cid1 = store.put_bytes(data.as_slice()).await?;
+        let cid2 = store.put_bytes(data.as_slice()).await?;
+        assert_eq!(cid1, cid2);
+        assert_eq!(store.get_block_count().await?, 2); // The chunk and its merkle node
+
+        let metrics = store.get_metrics();
+        assert_eq!(metrics.get_writes(), 2);
+        assert_eq!(metrics.get_bytes_written(), 2 * data.len() as u64);
+
+        // Reads and existence checks
+        assert!(store.has(&cid1).await);
+        let mut retrieved = Vec::new();
+        store
+            .get_bytes(&cid1)
+            .await?
+            .read_to_end(&mut retrieved)
+            .await?;
+        assert_eq!(retrieved, data);
+
+        let metrics = store.get_metrics();
+        assert_eq!(metrics.get_has_checks(), 1);
+        assert_eq!(metrics.get_reads(), 1);
+        assert_eq!(metrics.get_bytes_read(), data.len() as u64);
+
+        // Clones share the counters
+        store.clone().has(&cid1).await;
+        assert_eq!(store.get_metrics().get_has_checks(), 2);
+
+        store.reset_metrics();
+        assert_eq!(store.get_metrics(), CountingStoreMetrics::default());
+
+        Ok(())
+    }
+}
@@ -7,7 +7,7 @@ use ipld_core::cid::Cid;
 use serde::{de::DeserializeOwned, Serialize};
 use tokio::io::AsyncRead;
 
-use crate::{Codec, IpldReferences, IpldStore, RawStore, StoreError, StoreResult};
+use crate::{CidConfig, Codec, IpldReferences, IpldStore, RawStore, StoreError, StoreResult};
 
 //--------------------------------------------------------------------------------------------------
 // Types
@@ -314,6 +314,13 @@ where
         Ok(max_size_a.max(max_size_b))
     }
 
+    async fn get_cid_config(&self) -> CidConfig {
+        match self.config.write_to {
+            Choice::A => self.store_a.get_cid_config().await,
+            Choice::B => self.store_b.get_cid_config().await,
+        }
+    }
+
     async fn is_empty(&self) -> StoreResult<bool> {
         Ok(self.store_a.is_empty().await? && self.store_b.is_empty().await?)
     }
@@ -0,0 +1,311 @@
+use std::fmt;
+
+use bytes::Bytes;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    XChaCha20Poly1305, XNonce,
+};
+use ipld_core::cid::Cid;
+
+use crate::{
+    BlockTransform, Chunker, FastCDCChunker, FlatLayout, IpldStore, Layout, StoreError,
+    StoreResult, TransformStoreImpl,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The size of the nonce prepended to every encrypted block.
+const NONCE_SIZE: u64 = 24;
+
+/// The size of the Poly1305 authentication tag appended to every encrypted block.
+const TAG_SIZE: u64 = 16;
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// A store that transparently encrypts blocks with [XChaCha20-Poly1305][xchacha] before writing
+/// them to an underlying [`IpldStore`].
+///
+/// ## CID Semantics
+///
+/// CIDs address the plaintext, so the same data gets the same CID when written through an
+/// `EncryptedStore` as when written to a plain store. The underlying store only sees the CIDs of
+/// the encrypted blocks, which reveal nothing about the plaintext. The store indexes where the
+/// encrypted blocks are, and records every entry of the index encrypted in the underlying store,
+/// so a store opened with the same key from the latest entry reads everything written before. See
+/// [`TransformStoreImpl`].
+///
+/// ## Nonces
+///
+/// Each block is encrypted with a nonce derived from its plaintext CID using a keyed [BLAKE3][blake3]
+/// hash. Nonces are therefore unique per distinct plaintext, and storing the same plaintext twice
+/// yields identical ciphertext, which the underlying store deduplicates. The nonce is stored in
+/// front of the ciphertext, so a block can be decrypted without knowing its plaintext CID.
+///
+/// Blocks are authenticated, so reading with the wrong key fails instead of returning garbage.
+///
+/// ## Seeking
+///
+/// Each chunk is encrypted on its own, so the layout's index of chunk sizes doubles as an index
+/// of the encrypted blocks. A seekable reader jumps straight to the chunk holding the new
+/// position and only decrypts the chunks it reads.
+///
+/// ## Examples
+///
+/// ```
+/// use ipldstore::{EncryptedStore, MemoryStore};
+///
+/// let store = EncryptedStore::new(MemoryStore::default(), [7; 32]);
+/// ```
+///
+/// [xchacha]: https://datatracker.ietf.org/doc/html/draft-irtf-cfrg-xchacha
+/// [blake3]: https://github.com/BLAKE3-team/BLAKE3
+pub type EncryptedStoreImpl<S, C = FastCDCChunker, L = FlatLayout> =
+    TransformStoreImpl<S, Encryption, C, L>;
+
+/// A store that transparently encrypts blocks with XChaCha20-Poly1305 before writing them to an
+/// underlying [`IpldStore`].
+///
+/// This version of the store uses a [`FastCDCChunker`] for chunking and [`FlatLayout`] for layout.
+pub type EncryptedStore<S> = EncryptedStoreImpl<S, FastCDCChunker, FlatLayout>;
+
+/// The [`BlockTransform`] of an [`EncryptedStoreImpl`], encrypting blocks with
+/// XChaCha20-Poly1305.
+pub struct Encryption {
+    /// The cipher used to encrypt and decrypt blocks.
+    cipher: XChaCha20Poly1305,
+
+    /// The key used to derive per-block nonces.
+    nonce_key: [u8; 32],
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl<S, C, L> EncryptedStoreImpl<S, C, L>
+where
+    S: IpldStore + Sync,
+    C: Chunker + Default,
+    L: Layout + Default,
+{
+    /// Creates a new `EncryptedStore` over the given store that encrypts blocks with `key`.
+    pub fn new(store: S, key: [u8; 32]) -> Self {
+        Self::with_transform(store, Encryption::new(key))
+    }
+
+    /// Opens an `EncryptedStore` over the given store with the index a previous store recorded
+    /// there up to `index_cid`, so the blocks it wrote can be read back.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`StoreError::DecryptionFailed`] if the index was encrypted with another key.
+    pub async fn open(store: S, key: [u8; 32], index_cid: &Cid) -> StoreResult<Self> {
+        Self::open_with_transform(store, Encryption::new(key), index_cid).await
+    }
+}
+
+impl Encryption {
+    /// Creates a new `Encryption` that encrypts blocks with `key`.
+    pub fn new(key: [u8; 32]) -> Self {
+        // Derive a separate key for nonces so the encryption key is never used for two purposes.
+        let nonce_key = blake3::derive_key("monoutils-store encrypted store nonce key", &key);
+
+        Self {
+            cipher: XChaCha20Poly1305::new(&key.into()),
+            nonce_key,
+        }
+    }
+
+    /// Derives the nonce for the block with the given plaintext `Cid`.
+    fn derive_nonce(&self, cid: &Cid) -> XNonce {
+        let hash = blake3::keyed_hash(&self.nonce_key, &cid.to_bytes());
+        *XNonce::from_slice(&hash.as_bytes()[..NONCE_SIZE as usize])
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Trait Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl BlockTransform for Encryption {
+    /// Encrypts `bytes` and prepends the nonce.
+    fn encode(&self, cid: &Cid, bytes: &[u8]) -> StoreResult<Vec<u8>> {
+        let nonce = self.derive_nonce(cid);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, bytes)
+            .map_err(|_| StoreError::EncryptionFailed(*cid))?;
+
+        let mut encoded = Vec::with_capacity(nonce.len() + ciphertext.len());
+        encoded.extend_from_slice(&nonce);
+        encoded.extend_from_slice(&ciphertext);
+
+        Ok(encoded)
+    }
+
+    /// Splits the nonce off `bytes` and decrypts the ciphertext.
+    fn decode(&self, cid: &Cid, bytes: &[u8]) -> StoreResult<Bytes> {
+        if (bytes.len() as u64) < NONCE_SIZE {
+            return Err(StoreError::DecryptionFailed(*cid));
+        }
+
+        let (nonce, ciphertext) = bytes.split_at(NONCE_SIZE as usize);
+        let plaintext = self
+            .cipher
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| StoreError::DecryptionFailed(*cid))?;
+
+        Ok(Bytes::from(plaintext))
+    }
+
+    /// Returns the size of the nonce and the authentication tag.
+    fn get_overhead(&self) -> u64 {
+        NONCE_SIZE + TAG_SIZE
+    }
+}
+
+impl fmt::Debug for Encryption {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The keys are deliberately left out.
+        f.debug_struct("Encryption").finish_non_exhaustive()
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+
+    use crate::{MemoryStore, RawStore};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_encrypted_store_round_trip() -> anyhow::Result<()> {
+        let inner = MemoryStore::default();
+        let store = EncryptedStore::new(inner.clone(), [1; 32]);
+
+        let data = b"the quick brown fox jumps over the lazy dog. ".repeat(1000);
+        let cid = store.put_bytes(data.as_slice()).await?;
+
+        // The CID addresses the plaintext, the same as in a plain store
+        let plain_cid = MemoryStore::default().put_bytes(data.as_slice()).await?;
+        assert_eq!(cid, plain_cid);
+        assert!(!inner.has(&cid).await);
+        assert!(inner.has(&store.get_stored_cid(&cid).await.unwrap()).await);
+
+        let mut reader = store.get_bytes(&cid).await?;
+        let mut retrieved = Vec::new();
+        reader.read_to_end(&mut retrieved).await?;
+        assert_eq!(retrieved, data);
+
+        let node = ("secret".to_string(), "node".to_string());
+        let node_cid = store.put_node(&node).await?;
+        let retrieved: (String, String) = store.get_node(&node_cid).await?;
+        assert_eq!(retrieved, node);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_store_hides_plaintext() -> anyhow::Result<()> {
+        let inner = MemoryStore::default();
+        let store = EncryptedStore::new(inner.clone(), [2; 32]);
+
+        let secret = b"my very secret plaintext";
+        store.put_raw_block(secret.to_vec()).await?;
+        store.put_node(&"my very secret node".to_string()).await?;
+
+        // Each block is stored along with its entry of the index
+        let blocks = inner.get_blocks().read().await;
+        assert_eq!(blocks.len(), 4);
+        for (_, bytes) in blocks.values() {
+            assert!(!bytes.windows(secret.len()).any(|w| w == secret));
+            assert!(!bytes.windows(14).any(|w| w == b"my very secret"));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_store_same_plaintext_twice() -> anyhow::Result<()> {
+        let inner = MemoryStore::default();
+        let store = EncryptedStore::new(inner.clone(), [3; 32]);
+
+        let cid1 = store.put_raw_block(b"duplicate".to_vec()).await?;
+        let cid2 = store.put_raw_block(b"duplicate".to_vec()).await?;
+        assert_eq!(cid1, cid2);
+        assert_eq!(store.get_block_count().await?, 1);
+
+        // The block and its entry of the index
+        assert_eq!(inner.get_block_count().await?, 2);
+
+        // A second store with the same key produces the same ciphertext
+        let other = EncryptedStore::new(inner.clone(), [3; 32]);
+        other.put_raw_block(b"duplicate".to_vec()).await?;
+        assert_eq!(inner.get_block_count().await?, 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_store_reopen() -> anyhow::Result<()> {
+        let inner = MemoryStore::default();
+        let store = EncryptedStore::new(inner.clone(), [8; 32]);
+
+        let data = b"reopened with the same key. ".repeat(1000);
+        let bytes_cid = store.put_bytes(data.as_slice()).await?;
+        let node_cid = store.put_node(&"reopened node").await?;
+        let block_count = store.get_block_count().await?;
+        let index_cid = store.get_index_cid().await.unwrap();
+        drop(store);
+
+        // A new store with the same key over the same underlying store reads everything back
+        let store = EncryptedStore::open(inner, [8; 32], &index_cid).await?;
+        assert!(store.has(&bytes_cid).await);
+        assert!(store.has(&node_cid).await);
+        assert_eq!(store.get_block_count().await?, block_count);
+
+        let mut retrieved = Vec::new();
+        store
+            .get_bytes(&bytes_cid)
+            .await?
+            .read_to_end(&mut retrieved)
+            .await?;
+        assert_eq!(retrieved, data);
+
+        let node: String = store.get_node(&node_cid).await?;
+        assert_eq!(node, "reopened node");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_store_wrong_key() -> anyhow::Result<()> {
+        let inner = MemoryStore::default();
+        let store = EncryptedStore::new(inner.clone(), [4; 32]);
+        let cid = store
+            .put_raw_block(b"for the right key only".to_vec())
+            .await?;
+        let index_cid = store.get_index_cid().await.unwrap();
+
+        // Same underlying store, different key
+        let wrong = EncryptedStore::open(inner.clone(), [5; 32], &index_cid).await;
+        assert!(matches!(wrong, Err(StoreError::DecryptionFailed(failed)) if failed == index_cid));
+        assert!(!EncryptedStore::new(inner.clone(), [5; 32]).has(&cid).await);
+
+        assert_eq!(
+            store.get_raw_block(&cid).await?.as_ref(),
+            b"for the right key only"
+        );
+
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{hash_map::Entry, HashMap, HashSet},
     pin::Pin,
     sync::Arc,
 };
@@ -12,13 +12,16 @@ use ipld_core::{cid::Cid, codec::Links};
 use monoutils::SeekableReader;
 use serde::{de::DeserializeOwned, Serialize};
 use serde_ipld_dagcbor::codec::DagCborCodec;
-use tokio::{io::AsyncRead, sync::RwLock};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::RwLock,
+};
 use typed_builder::TypedBuilder;
 
 use crate::{
-    utils, Chunker, Codec, FastCDCChunker, FixedSizeChunker, FlatLayout, IpldReferences, IpldStore,
-    IpldStoreSeekable, Layout, LayoutSeekable, RawStore, StoreError, StoreResult,
-    DEFAULT_MAX_NODE_BLOCK_SIZE,
+    car, walk_dag, Chunker, CidConfig, Codec, FastCDCChunker, FixedSizeChunker, FlatLayout,
+    IpldReferences, IpldStore, IpldStoreSeekable, Layout, LayoutSeekable, RawStore, StoreError,
+    StoreResult, DEFAULT_MAX_NODE_BLOCK_SIZE,
 };
 
 //--------------------------------------------------------------------------------------------------
@@ -79,6 +82,11 @@ where
     /// The layout strategy used to store chunked data.
     #[builder(default)]
     layout: Arc<L>,
+
+    /// How the CIDs of the stored blocks are generated.
+    #[builder(default)]
+    #[getset(skip)]
+    cid_config: CidConfig,
 }
 
 /// An in-memory storage for IPLD nodes and bytes.
@@ -133,6 +141,7 @@ where
             blocks: Arc::new(RwLock::new(HashMap::new())),
             chunker: Arc::new(C::default()),
             layout: Arc::new(L::default()),
+            cid_config: CidConfig::default(),
         }
     }
 
@@ -190,14 +199,14 @@ where
 
     /// Stores raw bytes in the store without any size checks.
     /// Returns a tuple of (Cid, bool) where the bool indicates if the data already existed in the store.
-    async fn store_raw(&self, bytes: Bytes, codec: Codec) -> (Cid, bool) {
-        let cid = utils::generate_cid(codec, &bytes);
+    async fn store_raw(&self, bytes: Bytes, codec: Codec) -> StoreResult<(Cid, bool)> {
+        let cid = self.cid_config.generate_cid(codec, &bytes)?;
         let mut blocks = self.blocks.write().await;
         let existed = blocks.contains_key(&cid);
         if !existed {
             blocks.insert(cid, (0, bytes));
         }
-        (cid, existed)
+        Ok((cid, existed))
     }
 
     /// Clears all blocks from the store.
@@ -205,6 +214,107 @@ where
         self.blocks.write().await.clear();
         Ok(())
     }
+
+    /// Returns the CIDs of all the blocks in the store, in CID order.
+    ///
+    /// Each block is listed once, however many times it was stored, so this is handy for checking
+    /// deduplication and garbage collection in tests. The number of blocks is also available with
+    /// [`get_block_count`][IpldStore::get_block_count].
+    pub async fn list_cids(&self) -> Vec<Cid> {
+        let mut cids: Vec<_> = self.blocks.read().await.keys().copied().collect();
+        cids.sort();
+        cids
+    }
+
+    /// Returns the total size in bytes of all the blocks in the store.
+    pub async fn get_total_bytes(&self) -> u64 {
+        self.blocks
+            .read()
+            .await
+            .values()
+            .map(|(_, bytes)| bytes.len() as u64)
+            .sum()
+    }
+
+    /// Exports the blocks reachable from `roots` as a [CARv1][car] file with the given `roots`.
+    ///
+    /// The blocks are found with [`walk_dag`], so blocks that no root links to are left out. They
+    /// are written in CID order, so the same DAGs always export to the same bytes.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `StoreError::BlockNotFound` if one of the roots is not in the store.
+    ///
+    /// [car]: https://ipld.io/specs/transport/car/carv1/
+    pub async fn export_car(
+        &self,
+        roots: &[Cid],
+        writer: impl AsyncWrite + Unpin + Send,
+    ) -> StoreResult<()>
+    where
+        C: Clone + Send + Sync + 'static,
+        L: Clone + Send + Sync + 'static,
+    {
+        let mut reachable = HashSet::new();
+        for root in roots {
+            walk_dag(self, root, |cid| {
+                reachable.insert(*cid);
+            })
+            .await?;
+        }
+
+        let blocks = self.blocks.read().await;
+        let mut sorted: Vec<_> = blocks
+            .iter()
+            .filter(|(cid, _)| reachable.contains(*cid))
+            .map(|(cid, (_, bytes))| (cid, bytes.as_ref()))
+            .collect();
+        sorted.sort_by_key(|(cid, _)| *cid);
+
+        car::write_car(roots, sorted, writer).await
+    }
+
+    /// Imports the blocks of a [CARv1][car] file into a new store, returning it along with the
+    /// roots of the file.
+    ///
+    /// The reference counts of the blocks are rebuilt from the links in the imported DAG-CBOR
+    /// nodes. Blocks of any other codec are imported as they are, without following their links.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `StoreError::BlockCidMismatch` if the data of a block doesn't hash to its CID, and
+    /// `StoreError::InvalidCar` if the file is malformed.
+    ///
+    /// [car]: https://ipld.io/specs/transport/car/carv1/
+    pub async fn import_car(
+        reader: impl AsyncRead + Unpin + Send,
+    ) -> StoreResult<(Self, Vec<Cid>)> {
+        let (roots, blocks) = car::read_car(reader).await?;
+
+        let store = Self::new();
+        let mut stored = store.blocks.write().await;
+        for (cid, bytes) in blocks {
+            stored.entry(cid).or_insert((0, bytes));
+        }
+
+        // Only DAG-CBOR blocks are decoded for their links. Blocks of other codecs, like DAG-PB,
+        // are kept as opaque bytes.
+        let mut links = Vec::new();
+        for (cid, (_, bytes)) in stored.iter() {
+            if cid.codec() == u64::from(Codec::DagCbor) {
+                links.extend(DagCborCodec::links(bytes).map_err(StoreError::custom)?);
+            }
+        }
+
+        for link in links {
+            if let Some((count, _)) = stored.get_mut(&link) {
+                *count += 1;
+            }
+        }
+
+        drop(stored);
+        Ok((store, roots))
+    }
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -231,7 +341,7 @@ where
             }
         }
 
-        let (cid, existed) = self.store_raw(bytes, Codec::DagCbor).await;
+        let (cid, existed) = self.store_raw(bytes, Codec::DagCbor).await?;
 
         // Only increment reference counts if this is a new entry
         if !existed {
@@ -285,6 +395,82 @@ where
         blocks.contains_key(cid)
     }
 
+    async fn has_many(&self, cids: &[Cid]) -> StoreResult<Vec<bool>> {
+        let blocks = self.blocks.read().await;
+        Ok(cids.iter().map(|cid| blocks.contains_key(cid)).collect())
+    }
+
+    async fn put_many(&self, blocks: Vec<Bytes>) -> StoreResult<Vec<Cid>> {
+        // Check all the sizes first so that nothing is stored if any block is too large.
+        if let Some(max_size) = self.get_max_raw_block_size().await? {
+            if let Some(bytes) = blocks.iter().find(|b| b.len() as u64 > max_size) {
+                return Err(StoreError::RawBlockTooLarge(bytes.len() as u64, max_size));
+            }
+        }
+
+        let mut stored = self.blocks.write().await;
+        blocks
+            .into_iter()
+            .map(|bytes| {
+                let cid = self.cid_config.generate_cid(Codec::Raw, &bytes)?;
+                stored.entry(cid).or_insert((0, bytes));
+                Ok(cid)
+            })
+            .collect()
+    }
+
+    async fn put_nodes<T>(&self, nodes: &[T]) -> StoreResult<Vec<Cid>>
+    where
+        T: Serialize + IpldReferences + Sync,
+    {
+        // Serialize and check all the nodes first so that nothing is stored if any is too large.
+        let max_size = self.get_max_node_block_size().await?;
+        let mut encoded = Vec::with_capacity(nodes.len());
+        for node in nodes {
+            let bytes = Bytes::from(serde_ipld_dagcbor::to_vec(node).map_err(StoreError::custom)?);
+            if let Some(max_size) = max_size {
+                if bytes.len() as u64 > max_size {
+                    return Err(StoreError::NodeBlockTooLarge(bytes.len() as u64, max_size));
+                }
+            }
+
+            encoded.push(bytes);
+        }
+
+        let mut stored = self.blocks.write().await;
+        let mut cids = Vec::with_capacity(nodes.len());
+        for (node, bytes) in nodes.iter().zip(encoded) {
+            let cid = self.cid_config.generate_cid(Codec::DagCbor, &bytes)?;
+
+            // Only increment reference counts if this is a new entry
+            if let Entry::Vacant(entry) = stored.entry(cid) {
+                entry.insert((0, bytes));
+                for reference in node.get_references() {
+                    if let Some((count, _)) = stored.get_mut(reference) {
+                        *count += 1;
+                    }
+                }
+            }
+
+            cids.push(cid);
+        }
+
+        Ok(cids)
+    }
+
+    async fn get_many(&self, cids: &[Cid]) -> StoreResult<Vec<Option<Bytes>>> {
+        let blocks = self.blocks.read().await;
+        cids.iter()
+            .map(|cid| match blocks.get(cid) {
+                Some((_, bytes)) => match cid.codec().try_into()? {
+                    Codec::Raw => Ok(Some(bytes.clone())),
+                    codec => Err(StoreError::UnexpectedBlockCodec(Codec::Raw, codec)),
+                },
+                None => Ok(None),
+            })
+            .collect()
+    }
+
     async fn get_supported_codecs(&self) -> HashSet<Codec> {
         let mut codecs = HashSet::new();
         codecs.insert(Codec::DagCbor);
@@ -296,6 +482,10 @@ where
         Ok(Some(DEFAULT_MAX_NODE_BLOCK_SIZE))
     }
 
+    async fn get_cid_config(&self) -> CidConfig {
+        self.cid_config
+    }
+
     async fn get_block_count(&self) -> StoreResult<u64> {
         Ok(self.blocks.read().await.len() as u64)
     }
@@ -380,7 +570,7 @@ where
             }
         }
 
-        Ok(self.store_raw(bytes, Codec::Raw).await.0)
+        Ok(self.store_raw(bytes, Codec::Raw).await?.0)
     }
 
     async fn get_raw_block(&self, cid: &Cid) -> StoreResult<Bytes> {
@@ -427,6 +617,7 @@ where
             blocks: Arc::new(RwLock::new(HashMap::new())),
             chunker: Arc::new(C::default()),
             layout: Arc::new(L::default()),
+            cid_config: CidConfig::default(),
         }
     }
 }
@@ -437,11 +628,14 @@ where
 
 #[cfg(test)]
 mod tests {
-    use crate::DEFAULT_MAX_CHUNK_SIZE;
+    use std::io::SeekFrom;
+
+    use crate::{utils, IpldStoreExt, DEFAULT_LAYOUT_BATCH_SIZE, DEFAULT_MAX_CHUNK_SIZE};
 
     use super::{helper::TestNode, *};
+    use ipld_core::cid::Version;
     use multihash_codetable::{Code, MultihashDigest};
-    use tokio::io::AsyncReadExt;
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
 
     #[tokio::test]
     async fn test_memory_store_raw_block() -> anyhow::Result<()> {
@@ -464,6 +658,203 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_memory_store_list_cids() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+
+        // Store three distinct blocks and a duplicate of one of them
+        let mut cids = Vec::new();
+        for data in [&b"first"[..], b"second", b"third", b"second"] {
+            cids.push(store.put_raw_block(data.to_vec()).await?);
+        }
+        assert_eq!(cids[1], cids[3]);
+
+        // Each block is listed once, in CID order
+        let listed = store.list_cids().await;
+        let mut expected = cids[..3].to_vec();
+        expected.sort();
+        assert_eq!(listed, expected);
+        assert_eq!(store.list_cids().await, listed);
+
+        assert_eq!(store.get_block_count().await?, 3);
+        assert_eq!(store.get_total_bytes().await, 16);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_put_many_get_many() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+
+        let blocks = vec![
+            Bytes::from("one"),
+            Bytes::from("two"),
+            Bytes::from("three"),
+            Bytes::from("two"),
+        ];
+        let cids = store.put_many(blocks.clone()).await?;
+
+        // CIDs come back in the same order as the blocks
+        assert_eq!(cids.len(), 4);
+        for (cid, bytes) in cids.iter().zip(blocks.iter()) {
+            assert_eq!(*cid, store.put_raw_block(bytes.clone()).await?);
+        }
+        assert_eq!(cids[1], cids[3]);
+        assert_eq!(store.get_block_count().await?, 3);
+
+        // Missing blocks come back as `None`
+        let missing = utils::generate_cid(Codec::Raw, b"missing");
+        let retrieved = store.get_many(&[cids[2], missing, cids[0]]).await?;
+        assert_eq!(
+            retrieved,
+            vec![Some(blocks[2].clone()), None, Some(blocks[0].clone())]
+        );
+
+        let present = store.has_many(&[cids[2], missing, cids[0]]).await?;
+        assert_eq!(present, vec![true, false, true]);
+
+        // Nothing is stored if any block is too large
+        let max_size = store.get_max_raw_block_size().await?.unwrap() as usize;
+        let result = store
+            .put_many(vec![
+                Bytes::from("four"),
+                Bytes::from(vec![0; max_size + 1]),
+            ])
+            .await;
+        assert!(matches!(result, Err(StoreError::RawBlockTooLarge(_, _))));
+        assert_eq!(store.get_block_count().await?, 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_put_nodes() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+        let leaf = store.put_raw_block(b"leaf".to_vec()).await?;
+
+        let nodes = (0..3)
+            .map(|value| TestNode {
+                name: "node".to_string(),
+                value,
+                refs: vec![leaf],
+            })
+            .collect::<Vec<_>>();
+        let cids = store.put_nodes(&nodes).await?;
+
+        // CIDs come back in the same order as the nodes
+        assert_eq!(cids.len(), 3);
+        for (cid, node) in cids.iter().zip(nodes.iter()) {
+            assert_eq!(*cid, store.put_node(node).await?);
+            assert_eq!(store.get_node::<TestNode>(cid).await?, *node);
+        }
+        assert_eq!(store.get_block_count().await?, 4);
+
+        // Every new node holds a reference to the leaf
+        assert!(store.garbage_collect(&leaf).await?.is_empty());
+        for cid in &cids[..2] {
+            store.garbage_collect(cid).await?;
+        }
+        assert!(store.has(&leaf).await);
+        store.garbage_collect(&cids[2]).await?;
+        assert!(!store.has(&leaf).await);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_car_round_trip() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+
+        let data: Vec<u8> = (0..(DEFAULT_MAX_CHUNK_SIZE * 3) as usize)
+            .map(|i| (i % 255) as u8)
+            .collect();
+        let bytes_cid = store.put_bytes(data.as_slice()).await?;
+        let raw_cid = store.put_raw_block(b"raw block".to_vec()).await?;
+        let node_cid = store
+            .put_node(&TestNode {
+                name: "root".to_string(),
+                value: 42,
+                refs: vec![bytes_cid, raw_cid],
+            })
+            .await?;
+
+        let mut car = Vec::new();
+        store.export_car(&[node_cid], &mut car).await?;
+
+        let (imported, roots) = MemoryStore::import_car(car.as_slice()).await?;
+        assert_eq!(roots, vec![node_cid]);
+
+        // The blocks, and their reference counts, are identical
+        assert_eq!(*imported.blocks.read().await, *store.blocks.read().await);
+        assert_eq!(imported.read_all(&bytes_cid).await?, data);
+
+        // And so is the export of the imported store
+        let mut reexported = Vec::new();
+        imported.export_car(&[node_cid], &mut reexported).await?;
+        assert_eq!(reexported, car);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_car_imports_unknown_codecs() -> anyhow::Result<()> {
+        let data = b"dag-pb block";
+        let dag_pb_cid = Cid::new_v1(Codec::DagPb.into(), Code::Sha2_256.digest(data));
+        let unknown_cid = Cid::new_v1(0x300001, Code::Sha2_256.digest(data));
+
+        let mut car = Vec::new();
+        car::write_car(
+            &[dag_pb_cid],
+            [(&dag_pb_cid, &data[..]), (&unknown_cid, &data[..])],
+            &mut car,
+        )
+        .await?;
+
+        // The blocks are kept as opaque bytes
+        let (imported, roots) = MemoryStore::import_car(car.as_slice()).await?;
+        assert_eq!(roots, vec![dag_pb_cid]);
+        let blocks = imported.blocks.read().await.clone();
+        assert_eq!(blocks[&dag_pb_cid], (0, Bytes::from_static(data)));
+        assert_eq!(blocks[&unknown_cid], (0, Bytes::from_static(data)));
+
+        // And exported again from the root
+        let mut reexported = Vec::new();
+        imported.export_car(&[dag_pb_cid], &mut reexported).await?;
+        let (_, blocks) = car::read_car(reexported.as_slice()).await?;
+        assert_eq!(blocks, vec![(dag_pb_cid, Bytes::from_static(data))]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_car_exports_reachable_blocks() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+
+        let raw_cid = store.put_raw_block(b"raw block".to_vec()).await?;
+        let orphan_cid = store.put_raw_block(b"orphan".to_vec()).await?;
+        let node_cid = store
+            .put_node(&TestNode {
+                name: "root".to_string(),
+                value: 42,
+                refs: vec![raw_cid],
+            })
+            .await?;
+
+        let mut car = Vec::new();
+        store.export_car(&[node_cid], &mut car).await?;
+
+        // Blocks no root links to are left out
+        let (imported, _) = MemoryStore::import_car(car.as_slice()).await?;
+        assert_eq!(imported.list_cids().await, {
+            let mut cids = vec![node_cid, raw_cid];
+            cids.sort();
+            cids
+        });
+        assert!(!imported.has(&orphan_cid).await);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_memory_store_bytes() -> anyhow::Result<()> {
         let store = MemoryStore::default();
@@ -489,6 +880,112 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_memory_store_streams_bytes_in_bounded_memory() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+        let len = 32 * 1024 * 1024;
+
+        // The reader fails if it gets too far ahead of what has been written to the store.
+        let bound = DEFAULT_LAYOUT_BATCH_SIZE + 2 * DEFAULT_MAX_CHUNK_SIZE;
+        let mut reader = helper::BoundedReader::new(store.clone(), len, bound);
+        let cid = store.put_bytes(&mut reader).await?;
+
+        assert_eq!(reader.produced, len);
+        assert!(reader.max_pending <= bound);
+        assert_eq!(store.get_bytes_size(&cid).await?, len);
+
+        // Read it back in chunks, without holding the whole payload.
+        let mut reader = store.get_seekable_bytes(&cid).await?;
+        let mut buffer = vec![0; 64 * 1024];
+        let mut expected = vec![0; buffer.len()];
+        let mut generator = helper::byte_generator();
+        let mut offset = 0;
+        loop {
+            let n = reader.read(&mut buffer).await?;
+            if n == 0 {
+                break;
+            }
+
+            helper::fill_bytes(&mut generator, &mut expected[..n]);
+            assert_eq!(buffer[..n], expected[..n]);
+            offset += n as u64;
+        }
+
+        assert_eq!(offset, len);
+
+        // Seek into the middle of the payload.
+        let position = len / 2 + 7;
+        reader.seek(SeekFrom::Start(position)).await?;
+        let n = reader.read(&mut buffer).await?;
+        assert!(n > 0);
+        let mut expected = vec![0; position as usize + n];
+        helper::fill_bytes(&mut helper::byte_generator(), &mut expected);
+        assert_eq!(buffer[..n], expected[position as usize..]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_cid_config() -> anyhow::Result<()> {
+        let blake3_store = MemoryStore::default();
+        let sha256_store = MemoryStore::builder()
+            .cid_config(CidConfig::new(Code::Sha2_256, Version::V1)?)
+            .build();
+
+        let data = b"Hello, World!".as_slice();
+        let blake3_cid = blake3_store.put_bytes(data).await?;
+        let sha256_cid = sha256_store.put_bytes(data).await?;
+        assert_ne!(blake3_cid, sha256_cid);
+
+        // The chunks are raw blocks under version 1 CIDs, with the configured hash.
+        let blake3_chunk = blake3_store.put_raw_block(Bytes::from(data)).await?;
+        let sha256_chunk = sha256_store.put_raw_block(Bytes::from(data)).await?;
+        assert_eq!(blake3_chunk.hash().code(), u64::from(Code::Blake3_256));
+        assert_eq!(sha256_chunk.hash().code(), u64::from(Code::Sha2_256));
+        assert!(blake3_chunk.to_string().starts_with("bafkr4i"));
+        assert!(sha256_chunk.to_string().starts_with("bafkrei"));
+
+        // Both stores read back the same bytes.
+        assert_eq!(blake3_store.read_all(&blake3_cid).await?, data);
+        assert_eq!(sha256_store.read_all(&sha256_cid).await?, data);
+
+        // Nodes and raw blocks can't have version 0 CIDs, so they are rejected.
+        let v0_store = MemoryStore::builder()
+            .cid_config(CidConfig::new(Code::Sha2_256, Version::V0)?)
+            .build();
+
+        let node = TestNode {
+            name: "v0".to_string(),
+            value: 0,
+            refs: vec![],
+        };
+        assert!(matches!(
+            v0_store.put_node(&node).await,
+            Err(StoreError::InvalidCidConfig(_))
+        ));
+        assert!(matches!(
+            v0_store.put_bytes(data).await,
+            Err(StoreError::InvalidCidConfig(_))
+        ));
+        assert!(v0_store.is_empty().await?);
+
+        // Unsupported hashes and versions are rejected.
+        assert!(matches!(
+            CidConfig::from_codes(0xdead, 1),
+            Err(StoreError::UnsupportedHash(0xdead))
+        ));
+        assert!(matches!(
+            CidConfig::from_codes(u64::from(Code::Blake3_256), 0),
+            Err(StoreError::InvalidCidConfig(_))
+        ));
+        assert!(matches!(
+            CidConfig::from_codes(u64::from(Code::Sha2_256), 2),
+            Err(StoreError::InvalidCidConfig(_))
+        ));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_memory_store_node() -> anyhow::Result<()> {
         let store = MemoryStore::default();
@@ -780,10 +1277,87 @@ mod tests {
 
 #[cfg(test)]
 mod helper {
+    use std::task::{Context, Poll};
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
     use serde::Deserialize;
+    use tokio::io::ReadBuf;
 
     use super::*;
 
+    /// A reader that generates `len` pseudo-random bytes and tracks how far it runs ahead of the
+    /// bytes written to `store`.
+    ///
+    /// The bytes come from [`byte_generator`], and don't repeat, so no chunk gets deduplicated.
+    pub(super) struct BoundedReader {
+        store: MemoryStore,
+        rng: StdRng,
+        len: u64,
+        bound: u64,
+        pub(super) produced: u64,
+        pub(super) max_pending: u64,
+    }
+
+    impl BoundedReader {
+        pub(super) fn new(store: MemoryStore, len: u64, bound: u64) -> Self {
+            Self {
+                store,
+                rng: byte_generator(),
+                len,
+                bound,
+                produced: 0,
+                max_pending: 0,
+            }
+        }
+    }
+
+    impl AsyncRead for BoundedReader {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            // Skip the check if a write holds the lock, the next read will catch up.
+            let stored = self
+                .store
+                .blocks
+                .try_read()
+                .map(|blocks| blocks.values().map(|(_, bytes)| bytes.len() as u64).sum())
+                .ok();
+
+            if let Some(stored) = stored {
+                let pending = self.produced.saturating_sub(stored);
+                self.max_pending = self.max_pending.max(pending);
+                if pending > self.bound {
+                    return Poll::Ready(Err(std::io::Error::other(format!(
+                        "{pending} bytes read but not yet stored"
+                    ))));
+                }
+            }
+
+            let n = (buf.remaining() as u64).min(self.len - self.produced);
+            let mut bytes = vec![0; n as usize];
+            fill_bytes(&mut self.rng, &mut bytes);
+            buf.put_slice(&bytes);
+
+            self.produced += n;
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Returns the generator of the bytes produced by `BoundedReader`.
+    pub(super) fn byte_generator() -> StdRng {
+        StdRng::seed_from_u64(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Fills `bytes` from `rng` one byte at a time.
+    ///
+    /// Unlike `RngCore::fill_bytes`, which drops the unused bytes of its last word, the stream
+    /// doesn't depend on how it is split across calls.
+    pub(super) fn fill_bytes(rng: &mut StdRng, bytes: &mut [u8]) {
+        bytes.iter_mut().for_each(|byte| *byte = rng.random());
+    }
+
     #[derive(Debug, Serialize, Deserialize, PartialEq)]
     pub(super) struct TestNode {
         pub(super) name: String,
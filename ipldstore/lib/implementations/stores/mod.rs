@@ -1,9 +1,19 @@
+mod cachedstore;
+mod compressedstore;
+mod countingstore;
 mod dualstore;
+mod encryptedstore;
 mod memstore;
+mod transformstore;
 
 //--------------------------------------------------------------------------------------------------
 // Exports
 //--------------------------------------------------------------------------------------------------
 
+pub use cachedstore::*;
+pub use compressedstore::*;
+pub use countingstore::*;
 pub use dualstore::*;
+pub use encryptedstore::*;
 pub use memstore::*;
+pub use transformstore::*;
@@ -0,0 +1,632 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    pin::Pin,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::StreamExt;
+use getset::Getters;
+use ipld_core::{cid::Cid, ipld::Ipld};
+use monoutils::SeekableReader;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::{io::AsyncRead, sync::RwLock};
+
+use crate::{
+    Chunker, CidConfig, Codec, IpldReferences, IpldStore, IpldStoreSeekable, Layout,
+    LayoutSeekable, RawStore, StoreError, StoreResult,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The key of the transformed node in the underlying store's wrapper block.
+const NODE_DATA_KEY: &str = "data";
+
+/// The key of the references of a transformed node in the underlying store's wrapper block.
+const NODE_REFERENCES_KEY: &str = "references";
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// A reversible transformation applied to every block a [`TransformStoreImpl`] writes to its
+/// underlying store.
+///
+/// The transformed block must carry everything needed to undo the transformation, so a block can
+/// be read back from its CID alone.
+pub trait BlockTransform {
+    /// Transforms `bytes`, the content of the block whose CID is `cid` before transformation.
+    fn encode(&self, cid: &Cid, bytes: &[u8]) -> StoreResult<Vec<u8>>;
+
+    /// Undoes the transformation of `bytes`, read from the block with the given `cid`.
+    fn decode(&self, cid: &Cid, bytes: &[u8]) -> StoreResult<Bytes>;
+
+    /// Returns the maximum number of bytes the transformation adds to a block.
+    fn get_overhead(&self) -> u64;
+}
+
+/// A store that applies a [`BlockTransform`] to every block before writing it to an underlying
+/// [`IpldStore`], and undoes it when reading the block back.
+///
+/// ## CID Semantics
+///
+/// CIDs address the *original* blocks, so the same data gets the same CID as when written to a
+/// plain store. The store keeps an index from these CIDs to the transformed blocks in the
+/// underlying store. Transformations are deterministic, so the same data always gets the same
+/// transformed block and deduplication is unaffected.
+///
+/// ## Index
+///
+/// Every block added to the index is also recorded in the underlying store, as a transformed
+/// entry linking to the previous one. [`get_index_cid`][Self::get_index_cid] returns the latest
+/// entry, and [`open_with_transform`][Self::open_with_transform] reads the index back from it, so
+/// a store dropped and reopened over the same underlying store and with the same transformation
+/// can read all the blocks it wrote.
+///
+/// ## Nodes
+///
+/// Nodes are written to the underlying store as DAG-CBOR blocks holding the transformed node and
+/// links to the transformed blocks the node references. The underlying store therefore sees the
+/// same references as the original DAG, which keeps its reference counting and garbage collection
+/// working.
+///
+/// ## Chunking and Layout
+///
+/// Transformations are applied per block, after chunking. The chunker is configurable via the
+/// `chunker` field and the layout strategy via the `layout` field.
+#[derive(Debug, Getters)]
+pub struct TransformStoreImpl<S, T, C, L>
+where
+    S: IpldStore,
+    C: Chunker + Default,
+    L: Layout + Default,
+{
+    /// The underlying store the transformed blocks are written to.
+    #[getset(get = "pub with_prefix")]
+    store: S,
+
+    /// The transformation applied to every block.
+    transform: Arc<T>,
+
+    /// The chunking algorithm used to split data into chunks.
+    chunker: Arc<C>,
+
+    /// The layout strategy used to store chunked data.
+    layout: Arc<L>,
+
+    /// The index of the blocks the store holds.
+    index: Arc<RwLock<Index>>,
+}
+
+/// The index of the blocks a [`TransformStoreImpl`] holds.
+#[derive(Debug, Default)]
+struct Index {
+    /// Maps the CIDs of the original blocks to the CIDs of the transformed blocks in the
+    /// underlying store.
+    stored_cids: HashMap<Cid, Cid>,
+
+    /// The CID of the latest entry of the index written to the underlying store, if any.
+    head: Option<Cid>,
+}
+
+/// An entry of the index a [`TransformStoreImpl`] records in its underlying store.
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexEntry {
+    /// The CID of the original block.
+    cid: Cid,
+
+    /// The CID of the transformed block in the underlying store.
+    stored_cid: Cid,
+
+    /// The CID of the entry written before this one, if any.
+    previous: Option<Cid>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl<S, T, C, L> TransformStoreImpl<S, T, C, L>
+where
+    S: IpldStore + Sync,
+    T: BlockTransform,
+    C: Chunker + Default,
+    L: Layout + Default,
+{
+    /// Creates a new `TransformStoreImpl` over the given store that applies `transform` to every
+    /// block.
+    pub fn with_transform(store: S, transform: T) -> Self {
+        Self {
+            store,
+            transform: Arc::new(transform),
+            chunker: Arc::new(C::default()),
+            layout: Arc::new(L::default()),
+            index: Arc::new(RwLock::new(Index::default())),
+        }
+    }
+
+    /// Opens a store over the given store that applies `transform` to every block, with the index
+    /// a previous store over the same underlying store recorded up to `index_cid`.
+    ///
+    /// ## Arguments
+    ///
+    /// * `store` - The underlying store the previous store wrote to
+    /// * `transform` - The transformation the previous store applied
+    /// * `index_cid` - The CID returned by the previous store's [`get_index_cid`][Self::get_index_cid]
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if an entry of the index can't be read or its transformation can't be
+    /// undone, e.g. because it was encrypted with another key.
+    pub async fn open_with_transform(store: S, transform: T, index_cid: &Cid) -> StoreResult<Self> {
+        let opened = Self::with_transform(store, transform);
+
+        let mut stored_cids = HashMap::new();
+        let mut next = Some(*index_cid);
+        while let Some(entry_cid) = next {
+            let encoded = opened.load_wrapped(&entry_cid).await?;
+            let bytes = opened.transform.decode(&entry_cid, &encoded)?;
+            let entry: IndexEntry =
+                serde_ipld_dagcbor::from_slice(&bytes).map_err(StoreError::custom)?;
+
+            stored_cids.entry(entry.cid).or_insert(entry.stored_cid);
+            next = entry.previous;
+        }
+
+        *opened.index.write().await = Index {
+            stored_cids,
+            head: Some(*index_cid),
+        };
+
+        Ok(opened)
+    }
+
+    /// Returns the transformation applied to every block.
+    pub fn get_transform(&self) -> &T {
+        &self.transform
+    }
+
+    /// Returns the CID of the transformed block in the underlying store that holds the block with
+    /// the given CID, or `None` if this store has not written or loaded the block.
+    pub async fn get_stored_cid(&self, cid: &Cid) -> Option<Cid> {
+        self.index.read().await.stored_cids.get(cid).copied()
+    }
+
+    /// Returns the CID of the latest entry of the index recorded in the underlying store, to pass
+    /// to [`open_with_transform`][Self::open_with_transform], or `None` if the store holds no
+    /// blocks.
+    pub async fn get_index_cid(&self) -> Option<Cid> {
+        self.index.read().await.head
+    }
+
+    /// Transforms and stores `bytes` as a block with the given codec, returning the CID of the
+    /// original block.
+    ///
+    /// Nodes are wrapped in a DAG-CBOR block that links to the transformed blocks of their
+    /// `references`, raw blocks are stored as is.
+    async fn store_block<'a>(
+        &self,
+        bytes: Bytes,
+        codec: Codec,
+        references: impl Iterator<Item = &'a Cid>,
+    ) -> StoreResult<Cid> {
+        let cid = self
+            .store
+            .get_cid_config()
+            .await
+            .generate_cid(codec.clone(), &bytes)?;
+        if let Some(stored_cid) = self.get_stored_cid(&cid).await {
+            if self.store.has(&stored_cid).await {
+                return Ok(cid);
+            }
+        }
+
+        let encoded = self.transform.encode(&cid, &bytes)?;
+        let stored_cid = match codec {
+            Codec::Raw => self.store.put_raw_block(encoded).await?,
+            _ => {
+                let index = self.index.read().await;
+                let links = references
+                    .filter_map(|reference| index.stored_cids.get(reference))
+                    .copied()
+                    .collect();
+                drop(index);
+
+                self.store_wrapped(encoded, links).await?
+            }
+        };
+
+        self.add_to_index(cid, stored_cid).await?;
+
+        Ok(cid)
+    }
+
+    /// Adds the block with the given CID to the index, recording it in the underlying store.
+    async fn add_to_index(&self, cid: Cid, stored_cid: Cid) -> StoreResult<()> {
+        let mut index = self.index.write().await;
+        if index.stored_cids.get(&cid) == Some(&stored_cid) {
+            return Ok(());
+        }
+
+        let entry = IndexEntry {
+            cid,
+            stored_cid,
+            previous: index.head,
+        };
+        let bytes = serde_ipld_dagcbor::to_vec(&entry).map_err(StoreError::custom)?;
+        let entry_cid = self
+            .store
+            .get_cid_config()
+            .await
+            .generate_cid(Codec::DagCbor, &bytes)?;
+        let encoded = self.transform.encode(&entry_cid, &bytes)?;
+
+        // Linking the block and the previous entry keeps them alive in the underlying store
+        let links = std::iter::once(stored_cid).chain(entry.previous).collect();
+        index.head = Some(self.store_wrapped(encoded, links).await?);
+        index.stored_cids.insert(cid, stored_cid);
+
+        Ok(())
+    }
+
+    /// Stores transformed `encoded` bytes in a DAG-CBOR block that links to `links` in the
+    /// underlying store, returning the CID of the block.
+    async fn store_wrapped(&self, encoded: Vec<u8>, links: Vec<Cid>) -> StoreResult<Cid> {
+        let links = links.into_iter().map(Ipld::Link).collect();
+        let node = Ipld::Map(BTreeMap::from([
+            (NODE_DATA_KEY.to_string(), Ipld::Bytes(encoded)),
+            (NODE_REFERENCES_KEY.to_string(), Ipld::List(links)),
+        ]));
+
+        self.store.put_node(&node).await
+    }
+
+    /// Fetches the transformed bytes of a block stored by [`store_wrapped`][Self::store_wrapped].
+    async fn load_wrapped(&self, stored_cid: &Cid) -> StoreResult<Vec<u8>> {
+        match self.store.get_node(stored_cid).await? {
+            Ipld::Map(mut node) => match node.remove(NODE_DATA_KEY) {
+                Some(Ipld::Bytes(encoded)) => Ok(encoded),
+                _ => Err(StoreError::InvalidTransformedNode(*stored_cid)),
+            },
+            _ => Err(StoreError::InvalidTransformedNode(*stored_cid)),
+        }
+    }
+
+    /// Fetches the block with the given CID and undoes its transformation.
+    async fn load_block(&self, cid: &Cid) -> StoreResult<Bytes> {
+        let stored_cid = self
+            .get_stored_cid(cid)
+            .await
+            .ok_or(StoreError::BlockNotFound(*cid))?;
+
+        let encoded = match stored_cid.codec().try_into()? {
+            Codec::Raw => self.store.get_raw_block(&stored_cid).await?.to_vec(),
+            _ => self.load_wrapped(&stored_cid).await?,
+        };
+
+        self.transform.decode(cid, &encoded)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Trait Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<S, T, C, L> Clone for TransformStoreImpl<S, T, C, L>
+where
+    S: IpldStore,
+    C: Chunker + Default,
+    L: Layout + Default,
+{
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            transform: self.transform.clone(),
+            chunker: self.chunker.clone(),
+            layout: self.layout.clone(),
+            index: self.index.clone(),
+        }
+    }
+}
+
+#[async_trait]
+impl<S, T, C, L> IpldStore for TransformStoreImpl<S, T, C, L>
+where
+    S: IpldStore + Send + Sync + 'static,
+    T: BlockTransform + Send + Sync + 'static,
+    C: Chunker + Default + Clone + Send + Sync + 'static,
+    L: Layout + Default + Clone + Send + Sync + 'static,
+{
+    async fn put_node<D>(&self, node: &D) -> StoreResult<Cid>
+    where
+        D: Serialize + IpldReferences + Sync,
+    {
+        // Serialize the data to bytes.
+        let bytes = Bytes::from(serde_ipld_dagcbor::to_vec(&node).map_err(StoreError::custom)?);
+
+        // Check if the data exceeds the node maximum block size.
+        if let Some(max_size) = self.get_max_node_block_size().await? {
+            if bytes.len() as u64 > max_size {
+                return Err(StoreError::NodeBlockTooLarge(bytes.len() as u64, max_size));
+            }
+        }
+
+        self.store_block(bytes, Codec::DagCbor, node.get_references())
+            .await
+    }
+
+    async fn put_bytes(&self, reader: impl AsyncRead + Send + Sync) -> StoreResult<Cid> {
+        let chunk_stream = self.chunker.chunk(reader).await?;
+        let mut cid_stream = self.layout.organize(chunk_stream, self.clone()).await?;
+
+        // Take the last `Cid` from the stream.
+        let mut cid = cid_stream.next().await.unwrap()?;
+        while let Some(result) = cid_stream.next().await {
+            cid = result?;
+        }
+
+        Ok(cid)
+    }
+
+    async fn get_node<D>(&self, cid: &Cid) -> StoreResult<D>
+    where
+        D: DeserializeOwned + Send,
+    {
+        match cid.codec().try_into()? {
+            Codec::DagCbor => {
+                let bytes = self.load_block(cid).await?;
+                serde_ipld_dagcbor::from_slice::<D>(&bytes).map_err(StoreError::custom)
+            }
+            codec => Err(StoreError::UnexpectedBlockCodec(Codec::DagCbor, codec)),
+        }
+    }
+
+    async fn get_bytes(&self, cid: &Cid) -> StoreResult<Pin<Box<dyn AsyncRead + Send>>> {
+        self.layout.retrieve(cid, self.clone()).await
+    }
+
+    async fn get_bytes_size(&self, cid: &Cid) -> StoreResult<u64> {
+        self.layout.get_size(cid, self.clone()).await
+    }
+
+    async fn has(&self, cid: &Cid) -> bool {
+        match self.get_stored_cid(cid).await {
+            Some(stored_cid) => self.store.has(&stored_cid).await,
+            None => false,
+        }
+    }
+
+    async fn get_supported_codecs(&self) -> HashSet<Codec> {
+        let mut codecs = HashSet::new();
+        codecs.insert(Codec::DagCbor);
+        codecs.insert(Codec::Raw);
+        codecs
+    }
+
+    async fn get_max_node_block_size(&self) -> StoreResult<Option<u64>> {
+        // Leave room for what the transformation adds in the worst case. The links to the
+        // node's references are checked against the underlying store's limit when written.
+        Ok(self
+            .store
+            .get_max_node_block_size()
+            .await?
+            .map(|max_size| max_size.saturating_sub(self.transform.get_overhead())))
+    }
+
+    async fn get_cid_config(&self) -> CidConfig {
+        self.store.get_cid_config().await
+    }
+
+    async fn get_block_count(&self) -> StoreResult<u64> {
+        Ok(self.index.read().await.stored_cids.len() as u64)
+    }
+}
+
+#[async_trait]
+impl<S, T, C, L> RawStore for TransformStoreImpl<S, T, C, L>
+where
+    S: IpldStore + Send + Sync + 'static,
+    T: BlockTransform + Send + Sync + 'static,
+    C: Chunker + Default + Clone + Send + Sync + 'static,
+    L: Layout + Default + Clone + Send + Sync + 'static,
+{
+    async fn put_raw_block(&self, bytes: impl Into<Bytes> + Send) -> StoreResult<Cid> {
+        let bytes = bytes.into();
+        if let Some(max_size) = self.get_max_raw_block_size().await? {
+            if bytes.len() as u64 > max_size {
+                return Err(StoreError::RawBlockTooLarge(bytes.len() as u64, max_size));
+            }
+        }
+
+        self.store_block(bytes, Codec::Raw, std::iter::empty())
+            .await
+    }
+
+    async fn get_raw_block(&self, cid: &Cid) -> StoreResult<Bytes> {
+        match cid.codec().try_into()? {
+            Codec::Raw => self.load_block(cid).await,
+            codec => Err(StoreError::UnexpectedBlockCodec(Codec::Raw, codec)),
+        }
+    }
+
+    async fn get_max_raw_block_size(&self) -> StoreResult<Option<u64>> {
+        // Leave room for what the transformation adds in the worst case.
+        Ok(self
+            .store
+            .get_max_raw_block_size()
+            .await?
+            .map(|max_size| max_size.saturating_sub(self.transform.get_overhead())))
+    }
+}
+
+#[async_trait]
+impl<S, T, C, L> IpldStoreSeekable for TransformStoreImpl<S, T, C, L>
+where
+    S: IpldStore + Send + Sync + 'static,
+    T: BlockTransform + Send + Sync + 'static,
+    C: Chunker + Default + Clone + Send + Sync + 'static,
+    L: LayoutSeekable + Default + Clone + Send + Sync + 'static,
+{
+    async fn get_seekable_bytes(
+        &self,
+        cid: &Cid,
+    ) -> StoreResult<Pin<Box<dyn SeekableReader + Send + 'static>>> {
+        self.layout.retrieve_seekable(cid, self.clone()).await
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::{Compression, Encryption, DEFAULT_COMPRESSION_LEVEL};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_transform_store_seeks_without_reading_earlier_blocks() -> anyhow::Result<()> {
+        helper::assert_seeks_without_reading_earlier_blocks(Compression::new(
+            DEFAULT_COMPRESSION_LEVEL,
+        ))
+        .await?;
+        helper::assert_seeks_without_reading_earlier_blocks(Encryption::new([6; 32])).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_transform_store_reopens_with_recorded_index() -> anyhow::Result<()> {
+        helper::assert_reopens_with_recorded_index(|| Compression::new(DEFAULT_COMPRESSION_LEVEL))
+            .await?;
+        helper::assert_reopens_with_recorded_index(|| Encryption::new([6; 32])).await?;
+
+        Ok(())
+    }
+
+    mod helper {
+        use std::io::SeekFrom;
+
+        use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+        use crate::{FastCDCChunker, FlatLayout, MemoryStore, MerkleNode};
+
+        use super::*;
+
+        /// Asserts that seeking into bytes stored with `transform` and reading a mid-range only
+        /// reads and untransforms the blocks the range covers.
+        pub(super) async fn assert_seeks_without_reading_earlier_blocks<T>(
+            transform: T,
+        ) -> anyhow::Result<()>
+        where
+            T: BlockTransform + Send + Sync + 'static,
+        {
+            let inner = MemoryStore::default();
+            let store = TransformStoreImpl::<_, _, FastCDCChunker, FlatLayout>::with_transform(
+                inner.clone(),
+                transform,
+            );
+
+            // Distinct lines, so no two chunks are the same
+            let data = (0..200_000)
+                .flat_map(|i| format!("line {i}\n").into_bytes())
+                .collect::<Vec<_>>();
+            let cid = store.put_bytes(data.as_slice()).await?;
+            let node: MerkleNode = store.get_node(&cid).await?;
+            assert!(node.children.len() >= 3, "{} chunks", node.children.len());
+
+            // Drop the first two blocks from the underlying store
+            for (chunk_cid, _) in &node.children[..2] {
+                inner
+                    .get_blocks()
+                    .write()
+                    .await
+                    .remove(&store.get_stored_cid(chunk_cid).await.unwrap());
+            }
+
+            // Seeking past them and reading a mid-range only needs the blocks it covers
+            let offset = (node.children[0].1 + node.children[1].1 + 100) as u64;
+            let mut reader = store.get_seekable_bytes(&cid).await?;
+            reader.seek(SeekFrom::Start(offset)).await?;
+            let mut retrieved = vec![0; 1000];
+            reader.read_exact(&mut retrieved).await?;
+            assert_eq!(retrieved, data[offset as usize..offset as usize + 1000]);
+
+            // Reading from the start still needs the dropped blocks
+            let mut retrieved = Vec::new();
+            let result = store
+                .get_bytes(&cid)
+                .await?
+                .read_to_end(&mut retrieved)
+                .await;
+            assert!(result.is_err());
+
+            Ok(())
+        }
+
+        /// Asserts that a store with the transform made by `transform`, dropped after writing, can
+        /// be reopened over the same underlying store from the index it recorded there.
+        pub(super) async fn assert_reopens_with_recorded_index<T>(
+            transform: impl Fn() -> T,
+        ) -> anyhow::Result<()>
+        where
+            T: BlockTransform + Send + Sync + 'static,
+        {
+            let inner = MemoryStore::default();
+            let store = TransformStoreImpl::<_, _, FastCDCChunker, FlatLayout>::with_transform(
+                inner.clone(),
+                transform(),
+            );
+            assert_eq!(store.get_index_cid().await, None);
+
+            let data = (0..50_000)
+                .flat_map(|i| format!("line {i}\n").into_bytes())
+                .collect::<Vec<_>>();
+            let bytes_cid = store.put_bytes(data.as_slice()).await?;
+            let node_cid = store.put_node(&"reopened node").await?;
+            let raw_cid = store.put_raw_block(b"reopened block".to_vec()).await?;
+            let block_count = store.get_block_count().await?;
+            let index_cid = store.get_index_cid().await.unwrap();
+            drop(store);
+
+            let store =
+                TransformStoreImpl::<_, _, FastCDCChunker, FlatLayout>::open_with_transform(
+                    inner,
+                    transform(),
+                    &index_cid,
+                )
+                .await?;
+            assert_eq!(store.get_block_count().await?, block_count);
+            assert_eq!(store.get_index_cid().await, Some(index_cid));
+
+            let mut retrieved = Vec::new();
+            store
+                .get_bytes(&bytes_cid)
+                .await?
+                .read_to_end(&mut retrieved)
+                .await?;
+            assert_eq!(retrieved, data);
+
+            let node: String = store.get_node(&node_cid).await?;
+            assert_eq!(node, "reopened node");
+            assert_eq!(
+                store.get_raw_block(&raw_cid).await?.as_ref(),
+                b"reopened block"
+            );
+
+            // Blocks written after reopening extend the recorded index
+            let new_cid = store
+                .put_raw_block(b"written after reopening".to_vec())
+                .await?;
+            assert_ne!(store.get_index_cid().await, Some(index_cid));
+            assert!(store.has(&new_cid).await);
+            assert_eq!(store.get_block_count().await?, block_count + 1);
+
+            Ok(())
+        }
+    }
+}
@@ -3,12 +3,13 @@
 #![warn(missing_docs)]
 #![allow(clippy::module_inception)]
 
+pub mod car;
 mod chunker;
 mod constants;
 mod error;
 mod implementations;
 mod layout;
-mod merkle;
+pub mod merkle;
 mod references;
 mod storable;
 mod store;
@@ -1,7 +1,12 @@
-use ipld_core::cid::Cid;
+//! Merkle DAG types and verification.
+
+use std::collections::HashSet;
+
+use ipld_core::{cid::Cid, ipld::Ipld};
+use multihash_codetable::{Code, MultihashDigest};
 use serde::{Deserialize, Serialize};
 
-use super::IpldReferences;
+use super::{Codec, IpldReferences, IpldStore, StoreError, StoreResult};
 
 //--------------------------------------------------------------------------------------------------
 // Types
@@ -52,6 +57,16 @@ pub struct MerkleNode {
     pub children: Vec<(Cid, usize)>,
 }
 
+/// A problem with a block found by [`verify`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MerkleProblem {
+    /// The block is not in the store.
+    MissingBlock(Cid),
+
+    /// The content of the block does not hash to its CID.
+    HashMismatch(Cid),
+}
+
 //--------------------------------------------------------------------------------------------------
 // Methods
 //--------------------------------------------------------------------------------------------------
@@ -82,3 +97,132 @@ impl IpldReferences for MerkleNode {
         Box::new(self.children.iter().map(|(cid, _)| cid))
     }
 }
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Verifies the DAG rooted at `root`, returning every missing or corrupt block.
+///
+/// Every block reachable from `root` is checked to be in the store and to hash to its CID. The
+/// links of DAG-CBOR blocks are followed, so this works for [`MerkleNode`] trees as well as any
+/// other DAG-CBOR data. The children of a corrupt block are not checked since its links cannot be
+/// trusted.
+///
+/// ## Examples
+///
+/// ```
+/// use ipldstore::{merkle, IpldStore, MemoryStore};
+///
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// let store = MemoryStore::default();
+/// let cid = store.put_bytes(&b"Hello, World!"[..]).await?;
+///
+/// assert!(merkle::verify(&store, &cid).await?.is_empty());
+/// # Ok(())
+/// # }
+/// ```
+pub async fn verify(store: &impl IpldStore, root: &Cid) -> StoreResult<Vec<MerkleProblem>> {
+    let mut problems = Vec::new();
+    let mut visited = HashSet::new();
+    let mut stack = vec![*root];
+    while let Some(cid) = stack.pop() {
+        if !visited.insert(cid) {
+            continue;
+        }
+
+        match verify_block(store, &cid).await? {
+            Ok(Some(node)) => stack.extend(node.get_references().copied()),
+            Ok(None) => {}
+            Err(problem) => problems.push(problem),
+        }
+    }
+
+    Ok(problems)
+}
+
+/// Verifies that a single block is in the store and hashes to its CID.
+///
+/// Returns the decoded block if it is a DAG-CBOR block, so that callers can follow its links.
+pub async fn verify_block(
+    store: &impl IpldStore,
+    cid: &Cid,
+) -> StoreResult<Result<Option<Ipld>, MerkleProblem>> {
+    let code = Code::try_from(cid.hash().code()).map_err(StoreError::custom)?;
+    let (bytes, node) = match cid.codec().try_into()? {
+        Codec::Raw => match store.get_raw_block(cid).await {
+            Ok(bytes) => (bytes.to_vec(), None),
+            Err(StoreError::BlockNotFound(_)) => return Ok(Err(MerkleProblem::MissingBlock(*cid))),
+            Err(e) => return Err(e),
+        },
+        Codec::DagCbor => match store.get_node::<Ipld>(cid).await {
+            // DAG-CBOR is canonical, so re-encoding gives back the stored bytes.
+            Ok(node) => (
+                serde_ipld_dagcbor::to_vec(&node).map_err(StoreError::custom)?,
+                Some(node),
+            ),
+            Err(StoreError::BlockNotFound(_)) => return Ok(Err(MerkleProblem::MissingBlock(*cid))),
+            // A block that doesn't decode can't be the block the CID was created from.
+            Err(_) => return Ok(Err(MerkleProblem::HashMismatch(*cid))),
+        },
+        _ => {
+            if store.has(cid).await {
+                return Ok(Ok(None));
+            }
+            return Ok(Err(MerkleProblem::MissingBlock(*cid)));
+        }
+    };
+
+    if code.digest(&bytes) != *cid.hash() {
+        return Ok(Err(MerkleProblem::HashMismatch(*cid)));
+    }
+
+    Ok(Ok(node))
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use crate::{utils, MemoryStore, RawStore};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_merkle_verify() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+        let data: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+        let cid = store.put_bytes(&data[..]).await?;
+        assert!(verify(&store, &cid).await?.is_empty());
+
+        let node: MerkleNode = store.get_node(&cid).await?;
+        let (missing, _) = node.children[0];
+        let (corrupt, _) = node.children[1];
+        {
+            let mut blocks = store.get_blocks().write().await;
+            blocks.remove(&missing);
+            blocks.get_mut(&corrupt).unwrap().1 = Bytes::from_static(b"corrupt");
+        }
+
+        let problems = verify(&store, &cid).await?;
+        assert_eq!(problems.len(), 2);
+        assert!(problems.contains(&MerkleProblem::MissingBlock(missing)));
+        assert!(problems.contains(&MerkleProblem::HashMismatch(corrupt)));
+
+        let unknown = utils::generate_cid(Codec::Raw, b"unknown");
+        assert_eq!(
+            verify(&store, &unknown).await?,
+            vec![MerkleProblem::MissingBlock(unknown)]
+        );
+
+        let raw = store.put_raw_block(b"raw".to_vec()).await?;
+        assert!(verify_block(&store, &raw).await?.is_ok());
+
+        Ok(())
+    }
+}
@@ -1,8 +1,10 @@
-use std::iter;
+use std::{collections::HashSet, iter};
 
 use bytes::Bytes;
 use ipld_core::{cid::Cid, ipld::Ipld};
 
+use crate::{Codec, IpldStore, StoreError, StoreResult};
+
 //--------------------------------------------------------------------------------------------------
 // Traits
 //--------------------------------------------------------------------------------------------------
@@ -118,3 +120,151 @@ impl IpldReferences for Ipld {
         }
     }
 }
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Walks the DAG rooted at `root`, calling `visit` once for every block reachable from it.
+///
+/// The links of DAG-CBOR blocks are followed, raw blocks are leaves. Every visited CID is
+/// remembered, so blocks shared by several parents are visited once and cyclic links, e.g. from a
+/// store that doesn't check that blocks hash to their CIDs, don't send the walk into a loop.
+///
+/// Blocks linked to but missing from the store are skipped, as a store may hold a partial DAG.
+///
+/// ## Arguments
+///
+/// * `store` - The store to read the blocks from
+/// * `root` - The CID of the block to start the walk from
+/// * `visit` - Called with the CID of each block in the store reachable from `root`
+///
+/// ## Errors
+///
+/// Returns `StoreError::BlockNotFound` if `root` is not in the store, and a store error if a
+/// DAG-CBOR block can't be read or decoded.
+///
+/// ## Examples
+///
+/// ```
+/// use ipldstore::{ipld::ipld::Ipld, walk_dag, IpldStore, MemoryStore, RawStore};
+///
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// let store = MemoryStore::default();
+/// let leaf = store.put_raw_block(b"leaf".to_vec()).await?;
+/// let root = store
+///     .put_node(&Ipld::List(vec![Ipld::Link(leaf), Ipld::Link(leaf)]))
+///     .await?;
+///
+/// let mut visited = Vec::new();
+/// walk_dag(&store, &root, |cid| visited.push(*cid)).await?;
+/// assert_eq!(visited, vec![root, leaf]);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn walk_dag<S>(store: &S, root: &Cid, mut visit: impl FnMut(&Cid)) -> StoreResult<()>
+where
+    S: IpldStore,
+{
+    let mut visited = HashSet::new();
+    let mut stack = vec![*root];
+    while let Some(cid) = stack.pop() {
+        if !visited.insert(cid) {
+            continue;
+        }
+
+        match cid.codec().try_into()? {
+            Codec::DagCbor => match store.get_node::<Ipld>(&cid).await {
+                Ok(node) => {
+                    visit(&cid);
+                    stack.extend(node.get_references().copied());
+                }
+                Err(StoreError::BlockNotFound(_)) if cid != *root => {}
+                Err(e) => return Err(e),
+            },
+            _ if store.has(&cid).await => visit(&cid),
+            _ if cid == *root => return Err(StoreError::BlockNotFound(cid)),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::{utils, MemoryStore, RawStore};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_walk_dag_visits_cyclic_blocks_once() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+        let leaf = store.put_raw_block(b"leaf".to_vec()).await?;
+
+        // Content addressing rules out cycles, so link the blocks under made-up CIDs instead:
+        // a -> b -> c -> a, with a self link on b and a shared leaf.
+        let [a, b, c] = [b"a", b"b", b"c"].map(|seed| utils::generate_cid(Codec::DagCbor, seed));
+        helper::insert_node(&store, a, Ipld::List(vec![Ipld::Link(b), Ipld::Link(leaf)])).await?;
+        helper::insert_node(&store, b, Ipld::List(vec![Ipld::Link(c), Ipld::Link(b)])).await?;
+        helper::insert_node(&store, c, Ipld::List(vec![Ipld::Link(a), Ipld::Link(leaf)])).await?;
+
+        for root in [a, b, c] {
+            let mut visits = HashMap::<Cid, usize>::new();
+            walk_dag(&store, &root, |cid| *visits.entry(*cid).or_default() += 1).await?;
+
+            assert_eq!(visits.len(), 4);
+            assert!(visits.values().all(|count| *count == 1), "{visits:?}");
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_walk_dag_missing_blocks() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+        let missing = utils::generate_cid(Codec::Raw, b"missing");
+        let leaf = store.put_raw_block(b"leaf".to_vec()).await?;
+        let root = store
+            .put_node(&Ipld::List(vec![Ipld::Link(leaf), Ipld::Link(missing)]))
+            .await?;
+
+        // Missing descendants are skipped
+        let mut visited = Vec::new();
+        walk_dag(&store, &root, |cid| visited.push(*cid)).await?;
+        assert_eq!(visited.len(), 2);
+        assert!(visited.contains(&root) && visited.contains(&leaf));
+
+        // But a missing root is an error
+        let result = walk_dag(&store, &missing, |_| {}).await;
+        assert!(matches!(result, Err(StoreError::BlockNotFound(cid)) if cid == missing));
+
+        Ok(())
+    }
+
+    mod helper {
+        use super::*;
+
+        /// Stores `node` under `cid` without checking that it hashes to it.
+        pub(super) async fn insert_node(
+            store: &MemoryStore,
+            cid: Cid,
+            node: Ipld,
+        ) -> anyhow::Result<()> {
+            let bytes = serde_ipld_dagcbor::to_vec(&node)?;
+            store
+                .get_blocks()
+                .write()
+                .await
+                .insert(cid, (1, Bytes::from(bytes)));
+            Ok(())
+        }
+    }
+}
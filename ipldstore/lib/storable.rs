@@ -1,7 +1,11 @@
+use std::pin::Pin;
+
 use futures::Future;
 use ipld_core::cid::Cid;
+use monoutils::SeekableReader;
+use tokio::io::AsyncRead;
 
-use super::{IpldStore, StoreResult};
+use super::{IpldStore, IpldStoreSeekable, StoreResult};
 
 //--------------------------------------------------------------------------------------------------
 // Traits
@@ -18,3 +22,23 @@ where
     /// Loads the type from the IPLD store.
     fn load(cid: &Cid, store: S) -> impl Future<Output = StoreResult<Self>> + Send;
 }
+
+/// A trait for storable types that carry a byte payload too large to hold in memory at once.
+///
+/// The payload is written from and read back as a stream, so only a bounded part of it is ever
+/// in memory.
+pub trait StorableStream<S>: Storable<S>
+where
+    S: IpldStoreSeekable,
+{
+    /// Replaces the payload with the bytes read from `reader` and returns the Cid of the payload.
+    fn store_stream(
+        &mut self,
+        reader: impl AsyncRead + Send + Sync,
+    ) -> impl Future<Output = StoreResult<Cid>> + Send;
+
+    /// Returns a seekable reader over the payload.
+    fn load_stream(
+        &self,
+    ) -> impl Future<Output = StoreResult<Pin<Box<dyn SeekableReader + Send>>>> + Send;
+}
@@ -2,8 +2,10 @@ use std::{collections::HashSet, future::Future, pin::Pin};
 
 use async_trait::async_trait;
 use bytes::Bytes;
-use ipld_core::cid::Cid;
+use getset::Getters;
+use ipld_core::cid::{Cid, Version};
 use monoutils::SeekableReader;
+use multihash_codetable::{Code, MultihashDigest};
 use serde::{de::DeserializeOwned, Serialize};
 use tokio::io::{AsyncRead, AsyncReadExt};
 
@@ -32,6 +34,38 @@ pub enum Codec {
     Unknown(u64),
 }
 
+/// Selects how a store generates the [`CID`s][cid] of the blocks it writes.
+///
+/// The default hashes blocks with Blake3-256 into version 1 CIDs. Version 0 CIDs can only
+/// address DAG-PB blocks hashed with SHA2-256, so a version 0 config can't generate CIDs for
+/// blocks with any other codec.
+///
+/// ## Examples
+///
+/// ```
+/// use ipldstore::{CidConfig, Codec};
+/// use ipldstore::codetable::Code;
+/// use ipldstore::ipld::cid::Version;
+///
+/// let config = CidConfig::new(Code::Sha2_256, Version::V0).unwrap();
+/// let cid = config.generate_cid(Codec::DagPb, b"Hello, World!").unwrap();
+/// assert!(cid.to_string().starts_with("Qm"));
+/// assert!(config.generate_cid(Codec::DagCbor, b"Hello, World!").is_err());
+///
+/// assert!(CidConfig::new(Code::Blake3_256, Version::V0).is_err());
+/// ```
+///
+/// [cid]: https://docs.ipfs.tech/concepts/content-addressing/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Getters)]
+#[getset(get = "pub with_prefix")]
+pub struct CidConfig {
+    /// The hash function used to hash the blocks.
+    hash: Code,
+
+    /// The version of the generated CIDs.
+    cid_version: Version,
+}
+
 //--------------------------------------------------------------------------------------------------
 // Traits: IpldStore, IpldStoreSeekable, IpldStoreExt, *
 //--------------------------------------------------------------------------------------------------
@@ -163,6 +197,112 @@ pub trait IpldStore: RawStore + Clone {
     /// Returns true if the block exists, false otherwise.
     async fn has(&self, cid: &Cid) -> bool;
 
+    /// Checks which of several blocks exist in the store.
+    ///
+    /// The default implementation calls [`IpldStore::has`] for each CID, stores may override it to
+    /// batch the checks.
+    ///
+    /// ## Arguments
+    ///
+    /// * `cids` - The CIDs to check for
+    ///
+    /// ## Returns
+    ///
+    /// Returns whether each block exists, in the same order as `cids`.
+    async fn has_many(&self, cids: &[Cid]) -> StoreResult<Vec<bool>> {
+        let mut present = Vec::with_capacity(cids.len());
+        for cid in cids {
+            present.push(self.has(cid).await);
+        }
+
+        Ok(present)
+    }
+
+    /// Stores several raw blocks in the store at once.
+    ///
+    /// Unlike [`put_bytes`][IpldStore::put_bytes], the blocks are not chunked. The default
+    /// implementation calls [`RawStore::put_raw_block`] for each block, stores may override it to
+    /// batch the writes.
+    ///
+    /// ## Arguments
+    ///
+    /// * `blocks` - The blocks to store
+    ///
+    /// ## Returns
+    ///
+    /// Returns the CIDs of the stored blocks, in the same order as `blocks`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `StoreError::RawBlockTooLarge` if any block exceeds the store's maximum block size.
+    async fn put_many(&self, blocks: Vec<Bytes>) -> StoreResult<Vec<Cid>> {
+        let mut cids = Vec::with_capacity(blocks.len());
+        for bytes in blocks {
+            cids.push(self.put_raw_block(bytes).await?);
+        }
+
+        Ok(cids)
+    }
+
+    /// Stores several serializable objects in the store at once.
+    ///
+    /// The default implementation calls [`IpldStore::put_node`] for each node, stores may override
+    /// it to batch the writes.
+    ///
+    /// ## Arguments
+    ///
+    /// * `nodes` - The objects to store
+    ///
+    /// ## Returns
+    ///
+    /// Returns the CIDs of the stored objects, in the same order as `nodes`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `StoreError::NodeBlockTooLarge` if any serialized node exceeds the store's maximum
+    /// node block size.
+    async fn put_nodes<T>(&self, nodes: &[T]) -> StoreResult<Vec<Cid>>
+    where
+        T: Serialize + IpldReferences + Sync,
+    {
+        let mut cids = Vec::with_capacity(nodes.len());
+        for node in nodes {
+            cids.push(self.put_node(node).await?);
+        }
+
+        Ok(cids)
+    }
+
+    /// Retrieves several raw blocks from the store at once.
+    ///
+    /// The default implementation calls [`RawStore::get_raw_block`] for each CID, stores may
+    /// override it to batch the reads.
+    ///
+    /// ## Arguments
+    ///
+    /// * `cids` - The CIDs of the blocks to retrieve
+    ///
+    /// ## Returns
+    ///
+    /// Returns the blocks in the same order as `cids`, with `None` for blocks that are not in the
+    /// store.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `StoreError::UnexpectedBlockCodec` if any of the blocks is not a raw block.
+    async fn get_many(&self, cids: &[Cid]) -> StoreResult<Vec<Option<Bytes>>> {
+        let mut blocks = Vec::with_capacity(cids.len());
+        for cid in cids {
+            match self.get_raw_block(cid).await {
+                Ok(bytes) => blocks.push(Some(bytes)),
+                Err(StoreError::BlockNotFound(_)) => blocks.push(None),
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(blocks)
+    }
+
     /// Returns the set of IPLD codecs that this store supports.
     ///
     /// The supported codecs determine what types of IPLD data can be stored and retrieved.
@@ -180,6 +320,11 @@ pub trait IpldStore: RawStore + Clone {
     /// Returns the size limit in bytes, or None if there is no limit.
     async fn get_max_node_block_size(&self) -> StoreResult<Option<u64>>;
 
+    /// Returns how the store generates the CIDs of the blocks it writes.
+    async fn get_cid_config(&self) -> CidConfig {
+        CidConfig::default()
+    }
+
     /// Checks if the store contains any blocks.
     ///
     /// ## Returns
@@ -327,10 +472,74 @@ pub trait StoreConfig {
     fn get_config(&self) -> Self::Config;
 }
 
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl CidConfig {
+    /// Creates a new `CidConfig`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `StoreError::InvalidCidConfig` if `cid_version` is version 0 and `hash` is not
+    /// SHA2-256, since version 0 CIDs can't encode any other hash.
+    pub fn new(hash: Code, cid_version: Version) -> StoreResult<Self> {
+        if cid_version == Version::V0 && hash != Code::Sha2_256 {
+            return Err(StoreError::InvalidCidConfig(format!(
+                "CIDv0 requires SHA2-256, got multihash code {:#x}",
+                u64::from(hash)
+            )));
+        }
+
+        Ok(Self { hash, cid_version })
+    }
+
+    /// Creates a new `CidConfig` from a multihash code and a CID version number.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `StoreError::UnsupportedHash` if `hash` is not a hash function enabled in the
+    /// [`multihash_codetable`] re-export, and `StoreError::InvalidCidConfig` if `cid_version` is
+    /// not a known version or can't be combined with `hash`.
+    pub fn from_codes(hash: u64, cid_version: u64) -> StoreResult<Self> {
+        let hash = Code::try_from(hash).map_err(|_| StoreError::UnsupportedHash(hash))?;
+        let cid_version = Version::try_from(cid_version).map_err(|_| {
+            StoreError::InvalidCidConfig(format!("unknown CID version {cid_version}"))
+        })?;
+
+        Self::new(hash, cid_version)
+    }
+
+    /// Hashes `data` and returns a new [`Cid`] to it with the given codec.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `StoreError::InvalidCidConfig` if the CID version can't address blocks with
+    /// `codec`, i.e. version 0 with any codec but DAG-PB.
+    pub fn generate_cid(&self, codec: Codec, data: &[u8]) -> StoreResult<Cid> {
+        let codec = u64::from(codec);
+        Cid::new(self.cid_version, codec, self.hash.digest(data)).map_err(|_| {
+            StoreError::InvalidCidConfig(format!(
+                "CIDv{} can't address blocks with codec {codec:#x}",
+                u64::from(self.cid_version)
+            ))
+        })
+    }
+}
+
 //--------------------------------------------------------------------------------------------------
 // Trait Implementations
 //--------------------------------------------------------------------------------------------------
 
+impl Default for CidConfig {
+    fn default() -> Self {
+        Self {
+            hash: Code::Blake3_256,
+            cid_version: Version::V1,
+        }
+    }
+}
+
 impl TryFrom<u64> for Codec {
     type Error = StoreError;
 
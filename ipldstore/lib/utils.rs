@@ -1,9 +1,8 @@
 //! Utilities for working with the `monoutils-store` crate.
 
 use ipld_core::cid::Cid;
-use multihash_codetable::{Code, MultihashDigest};
 
-use crate::Codec;
+use crate::{CidConfig, Codec};
 
 //--------------------------------------------------------------------------------------------------
 // Functions
@@ -11,8 +10,11 @@ use crate::Codec;
 
 /// Hashes data with [Blake3-256][blake] and returns a new [`Cid`] to it.
 ///
+/// This is what stores use unless given another [`CidConfig`].
+///
 /// [blake]: https://en.wikipedia.org/wiki/BLAKE_(hash_function)
 pub fn generate_cid(codec: Codec, data: &[u8]) -> Cid {
-    let digest = Code::Blake3_256.digest(data);
-    Cid::new_v1(codec.into(), digest)
+    CidConfig::default()
+        .generate_cid(codec, data)
+        .expect("version 1 CIDs address blocks with any codec")
 }
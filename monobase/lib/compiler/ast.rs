@@ -1,6 +1,136 @@
+use crate::compiler::Span;
+
 //--------------------------------------------------------------------------------------------------
 // Types
 //--------------------------------------------------------------------------------------------------
 
 /// Represents the Abstract Syntax Tree (AST) of a monoql program.
 pub struct Ast {}
+
+/// An expression, along with its location in the source code.
+///
+/// The span of a parenthesized expression covers its parentheses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expr<'a> {
+    /// The location of this expression in the source code
+    pub span: Span,
+
+    /// The type of this expression and its operands
+    pub kind: ExprKind<'a>,
+}
+
+/// Represents the different types of expressions.
+///
+/// The lifetime parameter 'a represents borrowed string data from the source code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExprKind<'a> {
+    /// Identifiers, plain or escaped: `name`, `` `keyword` ``
+    Identifier(&'a str),
+    /// Variables: `$count`
+    Variable(&'a str),
+    /// Integers in any base: `42`, `0xFF`
+    Integer(&'a str),
+    /// Floating point numbers: `3.14`
+    Float(&'a str),
+    /// String literals, with their quotes: `"hello"`
+    String(&'a str),
+    /// Boolean literals: `true`, `false`
+    Boolean(bool),
+    /// The none literal: `none`
+    None,
+    /// A prefix operation: `not a`, `-b`
+    Unary {
+        /// The operator
+        op: UnaryOp,
+        /// The operand
+        operand: Box<Expr<'a>>,
+    },
+    /// An infix operation: `a + b`, `a and b`
+    Binary {
+        /// The operator
+        op: BinaryOp,
+        /// The left operand
+        lhs: Box<Expr<'a>>,
+        /// The right operand
+        rhs: Box<Expr<'a>>,
+    },
+}
+
+/// Prefix operators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnaryOp {
+    /// Logical NOT: `!` or `not`
+    Not,
+    /// Negation: `-`
+    Neg,
+    /// Identity: `+`
+    Pos,
+}
+
+/// Infix operators, from the loosest to the tightest binding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinaryOp {
+    /// Logical OR: `||` or `or`
+    Or,
+    /// Logical AND: `&&` or `and`
+    And,
+    /// Equality: `==`
+    Eq,
+    /// Identity comparison: `=` or `is`
+    Is,
+    /// Negative identity comparison: `!=` or `is not`
+    IsNot,
+    /// Less than: `<`
+    Lt,
+    /// Greater than: `>`
+    Gt,
+    /// Less than or equal: `<=`
+    Lte,
+    /// Greater than or equal: `>=`
+    Gte,
+    /// Addition: `+`
+    Add,
+    /// Subtraction: `-`
+    Sub,
+    /// Multiplication: `*` or `×`
+    Mul,
+    /// Division: `/` or `÷`
+    Div,
+    /// Modulo: `%`
+    Mod,
+    /// Power: `**`
+    Pow,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl BinaryOp {
+    /// Returns the left and right binding powers of the operator.
+    ///
+    /// An operator binds tighter than another if its powers are higher. Left associative
+    /// operators bind tighter on the right, and right associative ones on the left. The
+    /// precedence follows the `monoql` grammar.
+    pub fn binding_power(&self) -> (u8, u8) {
+        match self {
+            BinaryOp::Or => (1, 2),
+            BinaryOp::And => (3, 4),
+            BinaryOp::Eq | BinaryOp::Is | BinaryOp::IsNot => (5, 6),
+            BinaryOp::Lt | BinaryOp::Gt | BinaryOp::Lte | BinaryOp::Gte => (7, 8),
+            BinaryOp::Add | BinaryOp::Sub => (9, 10),
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => (11, 12),
+            BinaryOp::Pow => (14, 13),
+        }
+    }
+}
+
+impl UnaryOp {
+    /// Returns the binding power of the operator on its operand.
+    ///
+    /// It is higher than that of any infix operator, so `-a ** 2` is `(-a) ** 2` and `not a = b`
+    /// is `(not a) = b`, as in the `monoql` grammar.
+    pub fn binding_power(&self) -> u8 {
+        15
+    }
+}
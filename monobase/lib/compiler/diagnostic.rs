@@ -0,0 +1,129 @@
+use crate::compiler::{ParseError, Span};
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Renders `message` with the line of `source` that `span` starts on, the start of the span as
+/// `line:column` and the spanned range underlined with carets.
+///
+/// Lines and columns are 1-based, and columns count characters, not bytes. A span that covers
+/// several lines is underlined up to the end of its first line, and an empty span, e.g. at the
+/// end of the input, is pointed at with a single caret.
+///
+/// ## Examples
+///
+/// ```
+/// use monobase::compiler::render_diagnostic;
+///
+/// let rendered = render_diagnostic("a = or b", &(4..6), "Unexpected `or`");
+/// assert_eq!(
+///     rendered,
+///     "error: Unexpected `or`\n --> 1:5\n  |\n1 | a = or b\n  |     ^^\n"
+/// );
+/// ```
+pub fn render_diagnostic(source: &str, span: &Span, message: &str) -> String {
+    let start = floor_char_boundary(source, span.start);
+    let end = floor_char_boundary(source, span.end.max(start));
+
+    let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[start..]
+        .find('\n')
+        .map_or(source.len(), |i| start + i);
+    let line = source[line_start..line_end].trim_end_matches('\r');
+    let line_number = source[..start].matches('\n').count() + 1;
+    let column = source[line_start..start].chars().count() + 1;
+
+    // Keep the tabs before the span, so the carets line up with the line above them
+    let padding: String = source[line_start..start]
+        .chars()
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let carets = "^".repeat(source[start..end.min(line_end)].chars().count().max(1));
+
+    let gutter = " ".repeat(line_number.to_string().len());
+    format!(
+        "error: {message}\n{gutter}--> {line_number}:{column}\n{gutter} |\n{line_number} | {line}\n{gutter} | {padding}{carets}\n"
+    )
+}
+
+/// Returns the largest index not greater than `index` that is on a character boundary of
+/// `source`.
+fn floor_char_boundary(source: &str, index: usize) -> usize {
+    let mut index = index.min(source.len());
+    while !source.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl ParseError {
+    /// Renders the error with the snippet of `source` it points at.
+    ///
+    /// See [`render_diagnostic`].
+    pub fn render(&self, source: &str) -> String {
+        render_diagnostic(source, &self.span, &self.message)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use crate::compiler::Parser;
+
+    use super::*;
+
+    #[test]
+    fn test_render_diagnostic_points_at_column_mid_line() {
+        let source = "a = 1 and\n  b > or c";
+        let error = Parser::new(source).parse_expr().unwrap_err();
+
+        assert_eq!(
+            error.render(source),
+            [
+                "error: Unexpected `or`, expected an expression",
+                " --> 2:7",
+                "  |",
+                "2 |   b > or c",
+                "  |       ^^",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn test_render_diagnostic_counts_columns_in_characters() {
+        let source = "×× @@ b";
+        let rendered = render_diagnostic(source, &(5..7), "Unexpected characters: '@@'");
+
+        assert!(rendered.contains(" --> 1:4\n"));
+        assert!(rendered.ends_with("1 | ×× @@ b\n  |    ^^\n"));
+    }
+
+    #[test]
+    fn test_render_diagnostic_edge_spans() {
+        // An empty span at the end of the input gets a single caret
+        let source = "(a or b";
+        let error = Parser::new(source).parse_expr().unwrap_err();
+        assert!(error
+            .render(source)
+            .ends_with(" --> 1:8\n  |\n1 | (a or b\n  |        ^\n"));
+
+        // A span over several lines is underlined to the end of its first line
+        let rendered = render_diagnostic("x = \"ab\ncd\"", &(4..11), "Unterminated");
+        assert!(rendered.ends_with("1 | x = \"ab\n  |     ^^^\n"));
+
+        // The gutter fits the line number, and tabs are kept to line the carets up
+        let source = format!("{}\tfoo bar", "\n".repeat(11));
+        let rendered = render_diagnostic(&source, &(16..19), "Unexpected `bar`");
+        assert!(rendered.ends_with("  --> 12:6\n   |\n12 | \tfoo bar\n   | \t    ^^^\n"));
+    }
+}
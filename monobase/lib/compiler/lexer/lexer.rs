@@ -22,6 +22,15 @@ use crate::compiler::Span;
 /// * `start` - Starting position of the current token being lexed (in bytes)
 /// * `line` - Current line number (1-based)
 /// * `column` - Current column number (1-based)
+/// * `recover` - Whether lexing continues past invalid input
+/// * `errors` - The lexical errors found so far
+///
+/// ## Error Recovery
+///
+/// By default, the lexer returns an `Error` token for each character that can't start a token.
+/// With [`Lexer::with_error_recovery`], it instead returns a single `Error` token spanning the
+/// whole run of invalid input and continues with the next valid token, so each error in the
+/// source is reported once. Either way, the errors found are available from [`Lexer::errors`].
 ///
 /// ## Examples
 ///
@@ -46,6 +55,22 @@ pub struct Lexer<'a> {
 
     /// Current column number (1-based)
     column: usize,
+
+    /// Whether lexing continues past invalid input
+    recover: bool,
+
+    /// Lexical errors found so far
+    errors: Vec<LexError>,
+}
+
+/// A lexical error, such as a character that can't start any token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexError {
+    /// The location of the invalid input in the source code
+    pub span: Span,
+
+    /// A description of the error
+    pub message: String,
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -69,9 +94,36 @@ impl<'a> Lexer<'a> {
             start: 0,
             line: 1,
             column: 1,
+            recover: false,
+            errors: Vec::new(),
         }
     }
 
+    /// Makes the lexer report each run of invalid input as a single error instead of one error
+    /// per character.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use monobase::compiler::{Lexer, TokenKind};
+    ///
+    /// let mut lexer = Lexer::new("a @@ b").with_error_recovery();
+    ///
+    /// assert!(matches!(lexer.next_token().kind, TokenKind::PlainIdentifier("a")));
+    /// assert!(matches!(lexer.next_token().kind, TokenKind::Error(_)));
+    /// assert!(matches!(lexer.next_token().kind, TokenKind::PlainIdentifier("b")));
+    /// assert_eq!(lexer.errors()[0].span, 2..4);
+    /// ```
+    pub fn with_error_recovery(mut self) -> Self {
+        self.recover = true;
+        self
+    }
+
+    /// Returns the lexical errors found so far, in source order.
+    pub fn errors(&self) -> &[LexError] {
+        &self.errors
+    }
+
     /// Returns the next token from the source code.
     ///
     /// This method advances through the source code, skipping whitespace and comments,
@@ -102,110 +154,136 @@ impl<'a> Lexer<'a> {
 
         self.start = self.pos;
 
-        let c = match self.peek() {
-            Some(c) => c,
-            None => return self.make_token(TokenKind::Eof),
-        };
+        if self.peek().is_none() {
+            return self.make_token(TokenKind::Eof);
+        }
 
-        match c {
+        match self.token_lexer() {
+            Some(lex) => lex(self),
+            None => self.invalid_input(),
+        }
+    }
+
+    /// Returns the function that lexes the token starting at the current position, or `None` if
+    /// no token can start there.
+    ///
+    /// This is the table both `next_token` and error recovery go by, so they agree on where a
+    /// token starts.
+    fn token_lexer(&self) -> Option<fn(&mut Self) -> Token<'a>> {
+        let lexer: fn(&mut Self) -> Token<'a> = match self.peek()? {
             // Single character tokens
-            '(' => {
-                self.advance();
-                self.make_token(TokenKind::ParenOpen)
-            }
-            ')' => {
-                self.advance();
-                self.make_token(TokenKind::ParenClose)
-            }
-            '[' => {
-                self.advance();
-                self.make_token(TokenKind::BracketOpen)
-            }
-            ']' => {
-                self.advance();
-                self.make_token(TokenKind::BracketClose)
-            }
-            '{' => {
-                self.advance();
-                self.make_token(TokenKind::BraceOpen)
-            }
-            '}' => {
-                self.advance();
-                self.make_token(TokenKind::BraceClose)
-            }
-            ',' => {
-                self.advance();
-                self.make_token(TokenKind::Comma)
-            }
-            ';' => {
-                self.advance();
-                self.make_token(TokenKind::Terminator)
-            }
+            '(' => |lexer| lexer.single_char(TokenKind::ParenOpen),
+            ')' => |lexer| lexer.single_char(TokenKind::ParenClose),
+            '[' => |lexer| lexer.single_char(TokenKind::BracketOpen),
+            ']' => |lexer| lexer.single_char(TokenKind::BracketClose),
+            '{' => |lexer| lexer.single_char(TokenKind::BraceOpen),
+            '}' => |lexer| lexer.single_char(TokenKind::BraceClose),
+            ',' => |lexer| lexer.single_char(TokenKind::Comma),
+            ';' => |lexer| lexer.single_char(TokenKind::Terminator),
 
             // Byte strings (must come before identifier check)
             'b' if self.peek_ahead(1) == Some('"') || self.peek_ahead(1) == Some('\'') => {
-                self.byte_string()
+                Self::byte_string
             }
 
             // Identifiers and keywords (moved after byte string check)
-            'a'..='z' | 'A'..='Z' | '_' => self.identifier(),
+            'a'..='z' | 'A'..='Z' | '_' => Self::identifier,
 
             // Numbers
-            '0'..='9' => self.number(),
+            '0'..='9' => Self::number,
 
             // Strings
-            '"' | '\'' => self.string(),
+            '"' | '\'' => Self::string,
 
             // Escaped identifiers
-            '`' => self.escaped_identifier(),
+            '`' => Self::escaped_identifier,
 
             // Regex literals
-            '/' if self.peek_ahead(1) == Some('/') => self.regex(),
+            '/' if self.peek_ahead(1) == Some('/') => Self::regex,
 
             // Operators
-            '+' => self.operator_plus(),
-            '-' => self.operator_minus(),
-            '*' => self.operator_star(),
-            '/' => self.operator_slash(),
-            '=' => self.operator_equals(),
-            '<' => self.operator_less(),
-            '>' => self.operator_greater(),
-            '&' => self.operator_amp(),
-            '|' => self.operator_pipe(),
-            '^' => self.operator_caret(),
-            '~' => self.operator_tilde(),
-            '!' => self.operator_bang(),
-            '?' => self.operator_question(),
-            ':' => self.operator_colon(),
-            '.' => self.operator_dot(),
-            '$' => self.variable(),
+            '+' => Self::operator_plus,
+            '-' => Self::operator_minus,
+            '*' => Self::operator_star,
+            '/' => Self::operator_slash,
+            '=' => Self::operator_equals,
+            '<' => Self::operator_less,
+            '>' => Self::operator_greater,
+            '&' => Self::operator_amp,
+            '|' => Self::operator_pipe,
+            '^' => Self::operator_caret,
+            '~' => Self::operator_tilde,
+            '!' => Self::operator_bang,
+            '?' => Self::operator_question,
+            ':' => Self::operator_colon,
+            '.' => Self::operator_dot,
+            '$' => Self::variable,
 
             // Add modulo operator
-            '%' => {
-                self.advance();
-                if self.peek() == Some('=') {
-                    self.advance();
-                    self.make_token(TokenKind::AssignMod)
-                } else {
-                    self.make_token(TokenKind::Mod)
-                }
-            }
+            '%' => Self::operator_percent,
 
             // Add Unicode operators
-            '×' => self.operator_mul_lexer(),
-            '÷' => self.operator_div_lexer(),
-            '∋' => self.operator_contains_lexer(),
-            '∌' => self.operator_not_contains_lexer(),
-            '⊅' => self.operator_contains_none_lexer(),
-            '⊇' => self.operator_contains_all_lexer(),
-            '⊃' => self.operator_contains_any_lexer(),
+            '×' => Self::operator_mul_lexer,
+            '÷' => Self::operator_div_lexer,
+            '∋' => Self::operator_contains_lexer,
+            '∌' => Self::operator_not_contains_lexer,
+            '⊅' => Self::operator_contains_none_lexer,
+            '⊇' => Self::operator_contains_all_lexer,
+            '⊃' => Self::operator_contains_any_lexer,
 
             // Invalid character
-            c => {
+            _ => return None,
+        };
+
+        Some(lexer)
+    }
+
+    /// Handles a token made of a single character.
+    fn single_char(&mut self, kind: TokenKind<'a>) -> Token<'a> {
+        self.advance();
+        self.make_token(kind)
+    }
+
+    /// Handles the modulo operators `%` and `%=`.
+    fn operator_percent(&mut self) -> Token<'a> {
+        self.advance();
+        if self.peek() == Some('=') {
+            self.advance();
+            self.make_token(TokenKind::AssignMod)
+        } else {
+            self.make_token(TokenKind::Mod)
+        }
+    }
+
+    /// Handles input that can't start any token, recording it as an error.
+    ///
+    /// With error recovery, the error spans the whole run of invalid input, up to the next
+    /// whitespace or character that can start a token.
+    fn invalid_input(&mut self) -> Token<'a> {
+        self.advance();
+        if self.recover {
+            while let Some(c) = self.peek() {
+                if c.is_whitespace() || self.token_lexer().is_some() {
+                    break;
+                }
                 self.advance();
-                self.make_token(TokenKind::Error(format!("Unexpected character: '{}'", c)))
             }
         }
+
+        let text = &self.source[self.start..self.pos];
+        let message = if text.chars().count() == 1 {
+            format!("Unexpected character: '{}'", text)
+        } else {
+            format!("Unexpected characters: '{}'", text)
+        };
+
+        let token = self.make_token(TokenKind::Error(message.clone()));
+        self.errors.push(LexError {
+            span: token.span.clone(),
+            message,
+        });
+
+        token
     }
 
     /// Handles numeric literals including integers and floating point numbers.
@@ -905,6 +983,21 @@ mod tests {
         assert_eq!(tokens, expected);
     }
 
+    fn assert_tokens_with_recovery(input: &str, expected: Vec<TokenKind>) {
+        let mut lexer = Lexer::new(input).with_error_recovery();
+        let mut tokens: Vec<TokenKind> = Vec::new();
+
+        loop {
+            let kind = lexer.next_token().kind;
+            tokens.push(kind.clone());
+            if matches!(kind, TokenKind::Eof) {
+                break;
+            }
+        }
+
+        assert_eq!(tokens, expected);
+    }
+
     #[test]
     fn test_operators() {
         // Test basic operators
@@ -1065,6 +1158,102 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_error_recovery_reports_all_errors() {
+        let source = "let x = 1 @@ y ## 2";
+        let mut lexer = Lexer::new(source).with_error_recovery();
+        let mut tokens = Vec::new();
+        loop {
+            let token = lexer.next_token();
+            let eof = token.kind == TokenKind::Eof;
+            tokens.push(token);
+            if eof {
+                break;
+            }
+        }
+
+        let kinds: Vec<_> = tokens.iter().map(|t| t.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TokenKind::PlainIdentifier("let"),
+                TokenKind::PlainIdentifier("x"),
+                TokenKind::Is,
+                TokenKind::DecInteger("1"),
+                TokenKind::Error("Unexpected characters: '@@'".to_string()),
+                TokenKind::PlainIdentifier("y"),
+                TokenKind::Error("Unexpected characters: '##'".to_string()),
+                TokenKind::DecInteger("2"),
+                TokenKind::Eof,
+            ]
+        );
+
+        // The error tokens and the valid tokens around them have the right spans
+        assert_eq!(tokens[3].span, 8..9);
+        assert_eq!(tokens[4].span, 10..12);
+        assert_eq!(tokens[5].span, 13..14);
+        assert_eq!(tokens[6].span, 15..17);
+        assert_eq!(tokens[7].span, 18..19);
+
+        assert_eq!(
+            lexer.errors(),
+            [
+                LexError {
+                    span: 10..12,
+                    message: "Unexpected characters: '@@'".to_string(),
+                },
+                LexError {
+                    span: 15..17,
+                    message: "Unexpected characters: '##'".to_string(),
+                },
+            ]
+        );
+        assert_eq!(&source[lexer.errors()[1].span.clone()], "##");
+    }
+
+    #[test]
+    fn test_error_recovery_stops_at_token_start() {
+        // The run of invalid input ends where a valid token starts, even without whitespace
+        assert_tokens_with_recovery(
+            "@#(x)#",
+            vec![
+                TokenKind::Error("Unexpected characters: '@#'".to_string()),
+                TokenKind::ParenOpen,
+                TokenKind::PlainIdentifier("x"),
+                TokenKind::ParenClose,
+                TokenKind::Error("Unexpected character: '#'".to_string()),
+                TokenKind::Eof,
+            ],
+        );
+    }
+
+    #[test]
+    fn test_errors_without_recovery() {
+        // Each invalid character is an error of its own, and lexing carries on after them
+        let mut lexer = Lexer::new("x @@ y");
+        let mut tokens: Vec<TokenKind> = Vec::new();
+        loop {
+            let kind = lexer.next_token().kind;
+            tokens.push(kind.clone());
+            if matches!(kind, TokenKind::Eof) {
+                break;
+            }
+        }
+
+        assert_eq!(
+            tokens,
+            vec![
+                TokenKind::PlainIdentifier("x"),
+                TokenKind::Error("Unexpected character: '@'".to_string()),
+                TokenKind::Error("Unexpected character: '@'".to_string()),
+                TokenKind::PlainIdentifier("y"),
+                TokenKind::Eof,
+            ]
+        );
+        assert_eq!(lexer.errors().len(), 2);
+        assert_eq!(lexer.errors()[1].span, 3..4);
+    }
+
     #[test]
     fn test_byte_strings() {
         assert_tokens(
@@ -6,8 +6,10 @@
 //! - A lexer that tokenizes source code
 //! - A parser that builds an AST from tokens
 //! - Token definitions and span tracking
+//! - Diagnostics that point at the source of errors
 
 mod ast;
+mod diagnostic;
 mod lexer;
 mod parser;
 mod span;
@@ -17,6 +19,7 @@ mod span;
 //--------------------------------------------------------------------------------------------------
 
 pub use ast::*;
+pub use diagnostic::*;
 pub use lexer::*;
 pub use parser::*;
 pub use span::*;
@@ -1,7 +1,9 @@
-use crate::compiler::Lexer;
+use std::{error::Error, fmt};
+
+use crate::compiler::{BinaryOp, Expr, ExprKind, Lexer, Span, Token, TokenKind, UnaryOp};
 
 //--------------------------------------------------------------------------------------------------
-// Exports
+// Types
 //--------------------------------------------------------------------------------------------------
 
 /// Parser is responsible for converting a stream of tokens into an Abstract Syntax Tree (AST).
@@ -9,7 +11,387 @@ use crate::compiler::Lexer;
 /// The parser takes tokens from a lexer and constructs a structured representation of the program
 /// following the language's grammar rules. It performs syntactic analysis to ensure the code follows
 /// the correct structure and produces meaningful error messages for syntax errors.
+///
+/// Expressions are parsed by precedence climbing, with the operator precedence and associativity
+/// of the `monoql` grammar. Keyword operators like `and` are case-insensitive.
+///
+/// ## Examples
+///
+/// ```
+/// use monobase::compiler::{BinaryOp, ExprKind, Parser};
+///
+/// let mut parser = Parser::new("a = 1 and (b > 2 or c < 3)");
+/// let expr = parser.parse_expr().unwrap();
+///
+/// assert!(matches!(expr.kind, ExprKind::Binary { op: BinaryOp::And, .. }));
+/// ```
 pub struct Parser<'a> {
+    /// The source code being parsed.
+    source: &'a str,
+
     /// The lexer that provides the tokens to be parsed.
-    _lexer: Lexer<'a>,
+    lexer: Lexer<'a>,
+
+    /// The next token to be parsed.
+    current: Token<'a>,
+}
+
+/// A syntax error, or a lexical error found while parsing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    /// The location of the error in the source code
+    pub span: Span,
+
+    /// A description of the error
+    pub message: String,
+}
+
+/// The result of parsing.
+pub type ParseResult<T> = Result<T, ParseError>;
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl<'a> Parser<'a> {
+    /// Creates a new parser for the given source code.
+    pub fn new(source: &'a str) -> Self {
+        let mut lexer = Lexer::new(source);
+        let current = lexer.next_token();
+        Self {
+            source,
+            lexer,
+            current,
+        }
+    }
+
+    /// Parses an expression, leaving the tokens after it to be parsed.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use monobase::compiler::{BinaryOp, ExprKind, Parser};
+    ///
+    /// let expr = Parser::new("1 + 2 * 3").parse_expr().unwrap();
+    /// let ExprKind::Binary { op, rhs, .. } = expr.kind else {
+    ///     panic!("expected a binary expression");
+    /// };
+    ///
+    /// assert_eq!(op, BinaryOp::Add);
+    /// assert!(matches!(rhs.kind, ExprKind::Binary { op: BinaryOp::Mul, .. }));
+    /// ```
+    pub fn parse_expr(&mut self) -> ParseResult<Expr<'a>> {
+        self.expr(0)
+    }
+
+    /// Parses an expression whose infix operators bind at least as tight as `min_power`.
+    fn expr(&mut self, min_power: u8) -> ParseResult<Expr<'a>> {
+        let mut lhs = self.prefix_expr()?;
+
+        while let Some(op) = self.peek_binary_op() {
+            let (left_power, right_power) = op.binding_power();
+            if left_power < min_power {
+                break;
+            }
+
+            let op = self.binary_op(op);
+            let rhs = self.expr(right_power)?;
+            lhs = Expr {
+                span: lhs.span.start..rhs.span.end,
+                kind: ExprKind::Binary {
+                    op,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                },
+            };
+        }
+
+        Ok(lhs)
+    }
+
+    /// Parses an operand: a literal, an identifier, a variable, a prefix operation or a
+    /// parenthesized expression.
+    fn prefix_expr(&mut self) -> ParseResult<Expr<'a>> {
+        let token = self.advance();
+        let kind = match token.kind {
+            TokenKind::Not => return self.unary_expr(UnaryOp::Not, token.span),
+            TokenKind::PlainIdentifier(s) if s.eq_ignore_ascii_case("not") => {
+                return self.unary_expr(UnaryOp::Not, token.span)
+            }
+            TokenKind::Minus => return self.unary_expr(UnaryOp::Neg, token.span),
+            TokenKind::Plus => return self.unary_expr(UnaryOp::Pos, token.span),
+            TokenKind::ParenOpen => {
+                let mut expr = self.expr(0)?;
+                let close = self.expect(TokenKind::ParenClose, "`)`")?;
+                expr.span = token.span.start..close.span.end;
+                return Ok(expr);
+            }
+            TokenKind::DecInteger(s)
+            | TokenKind::BinInteger(s)
+            | TokenKind::OctInteger(s)
+            | TokenKind::HexInteger(s) => ExprKind::Integer(s),
+            TokenKind::Float(s) => ExprKind::Float(s),
+            TokenKind::String(s) => ExprKind::String(s),
+            TokenKind::PlainIdentifier(s) if s.eq_ignore_ascii_case("true") => {
+                ExprKind::Boolean(true)
+            }
+            TokenKind::PlainIdentifier(s) if s.eq_ignore_ascii_case("false") => {
+                ExprKind::Boolean(false)
+            }
+            TokenKind::PlainIdentifier(s) if s.eq_ignore_ascii_case("none") => ExprKind::None,
+            TokenKind::PlainIdentifier(s) if Self::is_operator_keyword(s) => {
+                return Err(self.unexpected(token.span, "an expression"))
+            }
+            TokenKind::PlainIdentifier(s) | TokenKind::EscapedIdentifier(s) => {
+                ExprKind::Identifier(s)
+            }
+            TokenKind::Variable(s) => ExprKind::Variable(s),
+            _ => return Err(self.unexpected_token(token, "an expression")),
+        };
+
+        Ok(Expr {
+            span: token.span,
+            kind,
+        })
+    }
+
+    /// Parses the operand of the prefix operator `op`, which spans `op_span`.
+    fn unary_expr(&mut self, op: UnaryOp, op_span: Span) -> ParseResult<Expr<'a>> {
+        let operand = self.expr(op.binding_power())?;
+        Ok(Expr {
+            span: op_span.start..operand.span.end,
+            kind: ExprKind::Unary {
+                op,
+                operand: Box::new(operand),
+            },
+        })
+    }
+
+    /// Returns the infix operator the current token starts, if any, without consuming it.
+    fn peek_binary_op(&self) -> Option<BinaryOp> {
+        let op = match self.current.kind {
+            TokenKind::Or => BinaryOp::Or,
+            TokenKind::And => BinaryOp::And,
+            TokenKind::Eq => BinaryOp::Eq,
+            TokenKind::Is => BinaryOp::Is,
+            TokenKind::IsNot => BinaryOp::IsNot,
+            TokenKind::Lt => BinaryOp::Lt,
+            TokenKind::Gt => BinaryOp::Gt,
+            TokenKind::Lte => BinaryOp::Lte,
+            TokenKind::Gte => BinaryOp::Gte,
+            TokenKind::Plus => BinaryOp::Add,
+            TokenKind::Minus => BinaryOp::Sub,
+            TokenKind::Star | TokenKind::Mul => BinaryOp::Mul,
+            TokenKind::Div => BinaryOp::Div,
+            TokenKind::Mod => BinaryOp::Mod,
+            TokenKind::Pow => BinaryOp::Pow,
+            TokenKind::PlainIdentifier(s) if s.eq_ignore_ascii_case("or") => BinaryOp::Or,
+            TokenKind::PlainIdentifier(s) if s.eq_ignore_ascii_case("and") => BinaryOp::And,
+            TokenKind::PlainIdentifier(s) if s.eq_ignore_ascii_case("is") => BinaryOp::Is,
+            _ => return None,
+        };
+
+        Some(op)
+    }
+
+    /// Consumes the infix operator `op` returned by `peek_binary_op`, which is `is not` if the
+    /// `is` keyword is followed by `not`.
+    fn binary_op(&mut self, op: BinaryOp) -> BinaryOp {
+        let token = self.advance();
+        let is_keyword = matches!(token.kind, TokenKind::PlainIdentifier(_));
+        if op == BinaryOp::Is && is_keyword && self.at_keyword("not") {
+            self.advance();
+            return BinaryOp::IsNot;
+        }
+
+        op
+    }
+
+    /// Consumes the current token if it is `kind`, or fails with an error mentioning `expected`.
+    fn expect(&mut self, kind: TokenKind<'a>, expected: &str) -> ParseResult<Token<'a>> {
+        if self.current.kind != kind {
+            let token = self.advance();
+            return Err(self.unexpected_token(token, expected));
+        }
+
+        Ok(self.advance())
+    }
+
+    /// Returns the current token and moves on to the next one.
+    fn advance(&mut self) -> Token<'a> {
+        let next = self.lexer.next_token();
+        std::mem::replace(&mut self.current, next)
+    }
+
+    /// Whether the current token is the keyword `keyword`.
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(self.current.kind, TokenKind::PlainIdentifier(s) if s.eq_ignore_ascii_case(keyword))
+    }
+
+    /// Whether `s` is a keyword that can only be used as an operator.
+    fn is_operator_keyword(s: &str) -> bool {
+        ["and", "or", "is"]
+            .iter()
+            .any(|keyword| s.eq_ignore_ascii_case(keyword))
+    }
+
+    /// Returns the error for finding `token` where `expected` was expected.
+    fn unexpected_token(&self, token: Token<'a>, expected: &str) -> ParseError {
+        match token.kind {
+            TokenKind::Error(message) => ParseError {
+                span: token.span,
+                message,
+            },
+            TokenKind::Eof => ParseError {
+                span: token.span,
+                message: format!("Unexpected end of input, expected {expected}"),
+            },
+            _ => self.unexpected(token.span, expected),
+        }
+    }
+
+    /// Returns the error for finding the source at `span` where `expected` was expected.
+    fn unexpected(&self, span: Span, expected: &str) -> ParseError {
+        ParseError {
+            message: format!(
+                "Unexpected `{}`, expected {expected}",
+                &self.source[span.clone()]
+            ),
+            span,
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Trait Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}..{}",
+            self.message, self.span.start, self.span.end
+        )
+    }
+}
+
+impl Error for ParseError {}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_expr_precedence() {
+        // Arithmetic binds tighter than comparison, which binds tighter than logic
+        assert_eq!(
+            helper::parse("a + 1 * 2 > b - c / 3 and d"),
+            "(And (Gt (Add a (Mul 1 2)) (Sub b (Div c 3))) d)"
+        );
+        assert_eq!(helper::parse("a or b and c = d"), "(Or a (And b (Is c d)))");
+        assert_eq!(
+            helper::parse("a != 1 or b <= 2 and c >= 3"),
+            "(Or (IsNot a 1) (And (Lte b 2) (Gte c 3)))"
+        );
+
+        // Prefix operators bind tightest
+        assert_eq!(helper::parse("-a ** 2"), "(Pow (Neg a) 2)");
+        assert_eq!(helper::parse("not a = b"), "(Is (Not a) b)");
+        assert_eq!(helper::parse("!a && +b"), "(And (Not a) (Pos b))");
+    }
+
+    #[test]
+    fn test_parse_expr_associativity() {
+        assert_eq!(helper::parse("a - b - c"), "(Sub (Sub a b) c)");
+        assert_eq!(helper::parse("a / b * c % d"), "(Mod (Mul (Div a b) c) d)");
+        assert_eq!(helper::parse("a or b || c"), "(Or (Or a b) c)");
+        assert_eq!(helper::parse("a ** b ** c"), "(Pow a (Pow b c))");
+    }
+
+    #[test]
+    fn test_parse_expr_parenthesized_grouping() {
+        let source = "a = 1 and (b > 2 or c < 3)";
+        let expr = Parser::new(source).parse_expr().unwrap();
+        assert_eq!(helper::sexp(&expr), "(And (Is a 1) (Or (Gt b 2) (Lt c 3)))");
+
+        let ExprKind::Binary { lhs, rhs, .. } = &expr.kind else {
+            panic!("expected a binary expression");
+        };
+        assert_eq!(&source[expr.span.clone()], source);
+        assert_eq!(&source[lhs.span.clone()], "a = 1");
+        assert_eq!(&source[rhs.span.clone()], "(b > 2 or c < 3)");
+
+        assert_eq!(helper::parse("(a + b) * c"), "(Mul (Add a b) c)");
+        assert_eq!(helper::parse("-(a + b)"), "(Neg (Add a b))");
+        assert_eq!(helper::parse("((a))"), "a");
+    }
+
+    #[test]
+    fn test_parse_expr_keywords_and_literals() {
+        assert_eq!(
+            helper::parse("A AND NOT b OR c IS NOT none"),
+            "(Or (And A (Not b)) (IsNot c none))"
+        );
+        assert_eq!(
+            helper::parse("$x is true and `and` = 0x1F"),
+            "(And (Is $x true) (Is `and` 0x1F))"
+        );
+        assert_eq!(
+            helper::parse("'s' == \"s\" or 1.5 >= false"),
+            "(Or (Eq 's' \"s\") (Gte 1.5 false))"
+        );
+    }
+
+    #[test]
+    fn test_parse_expr_errors() {
+        let error = Parser::new("a and").parse_expr().unwrap_err();
+        assert_eq!(error.span, 5..5);
+        assert_eq!(
+            error.message,
+            "Unexpected end of input, expected an expression"
+        );
+
+        let error = Parser::new("(a or b").parse_expr().unwrap_err();
+        assert_eq!(error.message, "Unexpected end of input, expected `)`");
+
+        let error = Parser::new("a = or b").parse_expr().unwrap_err();
+        assert_eq!(error.span, 4..6);
+        assert_eq!(error.message, "Unexpected `or`, expected an expression");
+
+        let error = Parser::new("a + @").parse_expr().unwrap_err();
+        assert_eq!(error.span, 4..5);
+        assert_eq!(error.message, "Unexpected character: '@'");
+    }
+}
+
+#[cfg(test)]
+mod helper {
+    use super::*;
+
+    /// Parses `source` as an expression and renders it with [`sexp`].
+    pub(super) fn parse(source: &str) -> String {
+        sexp(&Parser::new(source).parse_expr().unwrap())
+    }
+
+    /// Renders `expr` as an s-expression, e.g. `(Add a (Mul b c))`, so its shape is easy to assert.
+    pub(super) fn sexp(expr: &Expr) -> String {
+        match &expr.kind {
+            ExprKind::Identifier(s)
+            | ExprKind::Variable(s)
+            | ExprKind::Integer(s)
+            | ExprKind::Float(s)
+            | ExprKind::String(s) => s.to_string(),
+            ExprKind::Boolean(b) => b.to_string(),
+            ExprKind::None => "none".to_string(),
+            ExprKind::Unary { op, operand } => format!("({op:?} {})", sexp(operand)),
+            ExprKind::Binary { op, lhs, rhs } => {
+                format!("({op:?} {} {})", sexp(lhs), sexp(rhs))
+            }
+        }
+    }
 }
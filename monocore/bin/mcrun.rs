@@ -26,6 +26,9 @@
 //!     --scope=group \
 //!     --ip=192.168.1.1 \
 //!     --subnet=192.168.1.0/24 \
+//!     --seccomp-profile=default \
+//!     --cap-drop=ALL \
+//!     --agent-socket=/path/to/agent.sock \
 //!     --envs=KEY=VALUE \
 //!     -- -m http.server 8080
 //! ```
@@ -52,6 +55,10 @@
 //!     --scope=group \
 //!     --ip=192.168.1.1 \
 //!     --subnet=192.168.1.0/24 \
+//!     --agent-socket=/path/to/agent.sock \
+//!     --restart-policy=on-failure \
+//!     --max-restarts=5 \
+//!     --boot-timeout=30 \
 //!     -- -m http.server 8080
 //! ```
 //!
@@ -63,24 +70,29 @@
 //!     --port 8080 \
 //!     --path /path/to/namespaces \
 //!     --disable-default \
-//!     --key my_secret_key
+//!     --key my_secret_key \
+//!     --shutdown-timeout 30
 //! ```
 
 use std::{
     env,
     net::{IpAddr, Ipv4Addr, SocketAddr},
+    time::Duration,
 };
 
 use anyhow::Result;
 use clap::Parser;
 use monocore::{
     cli::{McrunArgs, McrunSubcommand},
-    config::{EnvPair, PathPair, PortPair, DEFAULT_SERVER_PORT},
-    runtime::MicroVmMonitor,
+    config::{Capability, EnvPair, PathPair, PortPair, DEFAULT_SERVER_PORT},
+    runtime::{AgentHealthProbe, MicroVmMonitor},
     server::SandboxServer,
-    vm::{MicroVm, Rootfs},
+    vm::{KrunLauncher, MicroVmConfig, MicroVmLauncher, Rootfs},
+};
+use monoutils::{
+    runtime::{HealthCheck, RestartBackoff, Supervisor},
+    DEFAULT_RESTART_MAX_RETRIES,
 };
-use monoutils::runtime::Supervisor;
 
 //--------------------------------------------------------------------------------------------------
 // Functions: main
@@ -106,6 +118,10 @@ async fn main() -> Result<()> {
             scope,
             ip,
             subnet,
+            seccomp_profile,
+            cap_add,
+            cap_drop,
+            agent_socket,
             args,
         } => {
             tracing_subscriber::fmt::init();
@@ -140,7 +156,7 @@ async fn main() -> Result<()> {
             let env: Vec<EnvPair> = env.iter().map(|s| s.parse()).collect::<Result<_, _>>()?;
 
             // Create and configure MicroVM
-            let mut builder = MicroVm::builder().rootfs(rootfs).exec_path(exec_path);
+            let mut builder = MicroVmConfig::builder().rootfs(rootfs).exec_path(exec_path);
 
             // Set num vcpus if provided
             if let Some(num_vcpus) = num_vcpus {
@@ -187,6 +203,34 @@ async fn main() -> Result<()> {
                 builder = builder.subnet(subnet.parse()?);
             }
 
+            // Set seccomp profile if provided
+            if let Some(seccomp_profile) = seccomp_profile {
+                builder = builder.seccomp_profile(seccomp_profile.parse()?);
+            }
+
+            // Set capabilities to add if provided
+            if !cap_add.is_empty() {
+                let cap_add: Vec<Capability> = cap_add
+                    .iter()
+                    .map(|s| s.parse())
+                    .collect::<Result<_, _>>()?;
+                builder = builder.cap_add(cap_add);
+            }
+
+            // Set capabilities to drop if provided
+            if !cap_drop.is_empty() {
+                let cap_drop: Vec<Capability> = cap_drop
+                    .iter()
+                    .map(|s| s.parse())
+                    .collect::<Result<_, _>>()?;
+                builder = builder.cap_drop(cap_drop);
+            }
+
+            // Set agent socket if provided
+            if let Some(agent_socket) = agent_socket {
+                builder = builder.agent_socket(agent_socket.display().to_string());
+            }
+
             // Set env if provided
             if !env.is_empty() {
                 builder = builder.env(env);
@@ -198,10 +242,10 @@ async fn main() -> Result<()> {
             }
 
             // Build and start the MicroVM
-            let vm = builder.build()?;
+            let config = builder.build();
 
             tracing::info!("starting µvm");
-            vm.start()?;
+            KrunLauncher.launch(config)?;
         }
         McrunSubcommand::Supervisor {
             log_dir,
@@ -209,8 +253,13 @@ async fn main() -> Result<()> {
             sandbox_name,
             config_file,
             config_last_modified,
+            config_hash,
             log_level,
             forward_output,
+            log_format,
+            restart_policy,
+            max_restarts,
+            boot_timeout,
             native_rootfs,
             overlayfs_layer,
             num_vcpus,
@@ -223,6 +272,10 @@ async fn main() -> Result<()> {
             scope,
             ip,
             subnet,
+            seccomp_profile,
+            cap_add,
+            cap_drop,
+            agent_socket,
             args,
         } => {
             tracing_subscriber::fmt::init();
@@ -246,6 +299,25 @@ async fn main() -> Result<()> {
                 }
             };
 
+            // Create the guest agent health probe if the guest agent is to be probed
+            let health_probe = match (boot_timeout, &agent_socket) {
+                (Some(boot_timeout), Some(agent_socket)) => {
+                    let probe = AgentHealthProbe::new(
+                        agent_socket,
+                        &sandbox_db_path,
+                        sandbox_name.clone(),
+                        config_file.clone(),
+                    )
+                    .await?;
+                    let health_check = HealthCheck {
+                        boot_timeout: Duration::from_secs(boot_timeout),
+                        ..Default::default()
+                    };
+                    Some((probe, health_check))
+                }
+                _ => None,
+            };
+
             // Create microvm monitor
             let process_monitor = MicroVmMonitor::new(
                 supervisor_pid,
@@ -257,7 +329,9 @@ async fn main() -> Result<()> {
                 rootfs.clone(),
                 forward_output,
             )
-            .await?;
+            .await?
+            .with_log_format(log_format)
+            .with_config_hash(config_hash);
 
             // Compose child arguments
             let mut child_args = vec!["microvm".to_string(), format!("--exec-path={}", exec_path)];
@@ -325,6 +399,26 @@ async fn main() -> Result<()> {
                 child_args.push(format!("--subnet={}", subnet));
             }
 
+            // Set seccomp profile if provided
+            if let Some(seccomp_profile) = seccomp_profile {
+                child_args.push(format!("--seccomp-profile={}", seccomp_profile));
+            }
+
+            // Set capabilities to add if provided
+            for cap in cap_add {
+                child_args.push(format!("--cap-add={}", cap));
+            }
+
+            // Set capabilities to drop if provided
+            for cap in cap_drop {
+                child_args.push(format!("--cap-drop={}", cap));
+            }
+
+            // Set agent socket if provided
+            if let Some(agent_socket) = agent_socket {
+                child_args.push(format!("--agent-socket={}", agent_socket.display()));
+            }
+
             // Set log level if provided
             if let Some(log_level) = log_level {
                 child_args.push(format!("--log-level={}", log_level));
@@ -348,8 +442,16 @@ async fn main() -> Result<()> {
             }
 
             // Create and start supervisor
+            let restart_backoff = RestartBackoff {
+                max_retries: max_restarts.unwrap_or(DEFAULT_RESTART_MAX_RETRIES),
+                ..Default::default()
+            };
             let mut supervisor =
-                Supervisor::new(child_exe, child_args, child_envs, log_dir, process_monitor);
+                Supervisor::new(child_exe, child_args, child_envs, log_dir, process_monitor)
+                    .with_restart_policy(restart_policy, restart_backoff);
+            if let Some((probe, health_check)) = health_probe {
+                supervisor = supervisor.with_health_probe(probe, health_check);
+            }
 
             supervisor.start().await?;
         }
@@ -358,10 +460,12 @@ async fn main() -> Result<()> {
             path,
             disable_default,
             key,
+            shutdown_timeout,
+            stop_sandboxes_on_shutdown,
         } => {
             tracing_subscriber::fmt::init();
 
-            let server = SandboxServer::new(
+            let mut server = SandboxServer::new(
                 path,
                 !disable_default,
                 SocketAddr::new(
@@ -369,7 +473,12 @@ async fn main() -> Result<()> {
                     port.unwrap_or(DEFAULT_SERVER_PORT),
                 ),
                 key,
-            )?;
+            )?
+            .with_stop_sandboxes_on_shutdown(stop_sandboxes_on_shutdown);
+            if let Some(shutdown_timeout) = shutdown_timeout {
+                server = server.with_shutdown_timeout(Duration::from_secs(shutdown_timeout));
+            }
+
             server.serve().await?;
         }
     }
@@ -1,9 +1,12 @@
 use clap::{error::ErrorKind, CommandFactory};
 use monocore::{
-    cli::{AnsiStyles, MonocoreArgs},
+    cli::{AnsiStyles, MonocoreArgs, OutputFormat},
     management::{
         config::{self, Component, ComponentType},
-        menv, orchestra, sandbox, server,
+        menv,
+        orchestra::{self, ApplyPlan, SandboxStatus},
+        sandbox,
+        server::{self, ShutdownOptions},
     },
     oci::Reference,
     MonocoreError, MonocoreResult,
@@ -93,12 +96,57 @@ pub async fn list_subcommand(
     group: bool,
     path: Option<PathBuf>,
     config: Option<String>,
+    format: OutputFormat,
 ) -> MonocoreResult<()> {
     trio_conflict_error(build, sandbox, group, "list", "[NAMES]");
     unsupported_build_group_error(build, group, "list", "[NAMES]");
-    let names = config::list(ComponentType::Sandbox, path.as_deref(), config.as_deref()).await?;
-    for name in names {
-        println!("{}", name);
+    match format {
+        OutputFormat::Table => {
+            let names =
+                config::list(ComponentType::Sandbox, path.as_deref(), config.as_deref()).await?;
+            for name in names {
+                println!("{}", name);
+            }
+        }
+        OutputFormat::Json => {
+            let statuses = orchestra::status(vec![], path.as_deref(), config.as_deref()).await?;
+            println!("{}", serde_json::to_string_pretty(&statuses)?);
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn status_subcommand(
+    sandbox: bool,
+    build: bool,
+    group: bool,
+    name: String,
+    path: Option<PathBuf>,
+    config: Option<String>,
+    format: OutputFormat,
+) -> MonocoreResult<()> {
+    trio_conflict_error(build, sandbox, group, "status", "[NAME]");
+    unsupported_build_group_error(build, group, "status", "[NAME]");
+    let statuses = orchestra::status(vec![name], path.as_deref(), config.as_deref()).await?;
+    match format {
+        OutputFormat::Table => print_status_table(&statuses),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&statuses)?),
+    }
+
+    Ok(())
+}
+
+pub async fn apply_subcommand(
+    path: Option<PathBuf>,
+    config: Option<String>,
+    plan: bool,
+) -> MonocoreResult<()> {
+    if plan {
+        let plan = orchestra::plan(path.as_deref(), config.as_deref()).await?;
+        print_apply_plan(&plan);
+    } else {
+        orchestra::apply(path.as_deref(), config.as_deref()).await?;
     }
 
     Ok(())
@@ -234,6 +282,7 @@ pub async fn tmp_subcommand(
     volumes: Vec<String>,
     ports: Vec<String>,
     envs: Vec<String>,
+    env_file: Option<PathBuf>,
     workdir: Option<Utf8UnixPathBuf>,
     exec: Option<String>,
     args: Vec<String>,
@@ -262,6 +311,7 @@ pub async fn tmp_subcommand(
         volumes,
         ports,
         envs,
+        env_file.as_deref(),
         workdir,
         exec.as_deref(),
         args,
@@ -393,6 +443,7 @@ pub async fn server_start_subcommand(
     disable_default: bool,
     secure: bool,
     key: Option<String>,
+    shutdown: ShutdownOptions,
     detach: bool,
 ) -> MonocoreResult<()> {
     if !secure && key.is_some() {
@@ -409,7 +460,7 @@ pub async fn server_start_subcommand(
             .exit();
     }
 
-    server::start(port, path, disable_default, secure, key, detach).await
+    server::start(port, path, disable_default, secure, key, shutdown, detach).await
 }
 
 pub async fn server_keygen_subcommand(expire: Option<String>) -> MonocoreResult<()> {
@@ -572,3 +623,67 @@ fn parse_duration_string(duration_str: &str) -> MonocoreResult<chrono::Duration>
         ))),
     }
 }
+
+/// Prints sandbox statuses as a table, one row per sandbox
+fn print_status_table(statuses: &[SandboxStatus]) {
+    let or_dash = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    println!(
+        "{:<20} {:<12} {:<8} {:<8} {:<10} {:<5} {:<8} HEALTH",
+        "NAME", "GROUP", "STATE", "PID", "UPTIME", "CPUS", "RAM"
+    );
+
+    for status in statuses {
+        println!(
+            "{:<20} {:<12} {:<8} {:<8} {:<10} {:<5} {:<8} {}",
+            status.get_name(),
+            or_dash(status.get_group().clone()),
+            format!("{:?}", status.get_state()).to_lowercase(),
+            or_dash(status.get_pid().map(|pid| pid.to_string())),
+            or_dash(status.get_uptime_secs().map(format_uptime)),
+            or_dash(status.get_cpus().map(|cpus| cpus.to_string())),
+            or_dash(status.get_ram_mib().map(|ram| format!("{ram}MiB"))),
+            or_dash(
+                status
+                    .get_health()
+                    .map(|health| format!("{health:?}").to_lowercase())
+            ),
+        );
+    }
+}
+
+/// Prints the changes an apply would make as a diff of sandboxes
+fn print_apply_plan(plan: &ApplyPlan) {
+    for name in plan.get_create() {
+        println!("+ {name} (create)");
+    }
+    for name in plan.get_update() {
+        println!("~ {name} (update)");
+    }
+    for name in plan.get_remove() {
+        println!("- {name} (remove)");
+    }
+    for name in plan.get_unchanged() {
+        println!("  {name} (unchanged)");
+    }
+
+    if plan.is_empty() {
+        println!("No changes. Running sandboxes match the configuration.");
+    } else {
+        println!(
+            "Plan: {} to create, {} to update, {} to remove.",
+            plan.get_create().len(),
+            plan.get_update().len(),
+            plan.get_remove().len()
+        );
+    }
+}
+
+/// Formats an uptime in seconds like "2h3m", "3m4s" or "5s"
+fn format_uptime(secs: u64) -> String {
+    let (hours, minutes, seconds) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    match (hours, minutes) {
+        (0, 0) => format!("{seconds}s"),
+        (0, _) => format!("{minutes}m{seconds}s"),
+        _ => format!("{hours}h{minutes}m"),
+    }
+}
@@ -5,7 +5,10 @@ use clap::{CommandFactory, Parser};
 use internal::handlers;
 use monocore::{
     cli::{MonocoreArgs, MonocoreSubcommand, ServerSubcommand},
-    management::{image, orchestra, server},
+    management::{
+        image, orchestra,
+        server::{self, ShutdownOptions},
+    },
     MonocoreResult,
 };
 
@@ -77,8 +80,20 @@ async fn main() -> MonocoreResult<()> {
             group,
             path,
             config,
+            format,
         }) => {
-            handlers::list_subcommand(sandbox, build, group, path, config).await?;
+            handlers::list_subcommand(sandbox, build, group, path, config, format).await?;
+        }
+        Some(MonocoreSubcommand::Status {
+            sandbox,
+            build,
+            group,
+            name,
+            path,
+            config,
+            format,
+        }) => {
+            handlers::status_subcommand(sandbox, build, group, name, path, config, format).await?;
         }
         Some(MonocoreSubcommand::Pull {
             image,
@@ -153,15 +168,18 @@ async fn main() -> MonocoreResult<()> {
             volumes,
             ports,
             envs,
+            env_file,
             workdir,
             exec,
             args,
         }) => {
-            handlers::tmp_subcommand(name, cpus, ram, volumes, ports, envs, workdir, exec, args)
-                .await?;
+            handlers::tmp_subcommand(
+                name, cpus, ram, volumes, ports, envs, env_file, workdir, exec, args,
+            )
+            .await?;
         }
-        Some(MonocoreSubcommand::Apply { path, config }) => {
-            orchestra::apply(path.as_deref(), config.as_deref()).await?;
+        Some(MonocoreSubcommand::Apply { path, config, plan }) => {
+            handlers::apply_subcommand(path, config, plan).await?;
         }
         Some(MonocoreSubcommand::Up {
             sandbox,
@@ -203,10 +221,24 @@ async fn main() -> MonocoreResult<()> {
                 disable_default,
                 secure,
                 key,
+                shutdown_timeout,
+                stop_sandboxes_on_shutdown,
                 detach,
             } => {
-                handlers::server_start_subcommand(port, path, disable_default, secure, key, detach)
-                    .await?;
+                let shutdown = ShutdownOptions {
+                    timeout: shutdown_timeout,
+                    stop_sandboxes: stop_sandboxes_on_shutdown,
+                };
+                handlers::server_start_subcommand(
+                    port,
+                    path,
+                    disable_default,
+                    secure,
+                    key,
+                    shutdown,
+                    detach,
+                )
+                .await?;
             }
             ServerSubcommand::Stop => {
                 server::stop().await?;
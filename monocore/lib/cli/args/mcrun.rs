@@ -2,6 +2,7 @@ use std::path::PathBuf;
 
 use chrono::{DateTime, Utc};
 use clap::{Parser, Subcommand};
+use monoutils::{LogFormat, RestartPolicy};
 
 use crate::cli::styles;
 
@@ -75,6 +76,22 @@ pub enum McrunSubcommand {
         #[arg(long)]
         subnet: Option<String>,
 
+        /// Seccomp profile of the VMM process (`default`, `unconfined` or a path to a profile file)
+        #[arg(long)]
+        seccomp_profile: Option<String>,
+
+        /// Capabilities to add to the VMM process
+        #[arg(long)]
+        cap_add: Vec<String>,
+
+        /// Capabilities to drop from the VMM process
+        #[arg(long)]
+        cap_drop: Vec<String>,
+
+        /// Path of the UNIX socket the guest agent is reachable through
+        #[arg(long)]
+        agent_socket: Option<PathBuf>,
+
         /// Additional arguments after `--`
         #[arg(last = true)]
         args: Vec<String>,
@@ -101,6 +118,10 @@ pub enum McrunSubcommand {
         #[arg(long)]
         config_last_modified: DateTime<Utc>,
 
+        /// Hash of the sandbox spec the sandbox is started with
+        #[arg(long)]
+        config_hash: Option<String>,
+
         /// Log level
         #[arg(long)]
         log_level: Option<u8>,
@@ -109,6 +130,23 @@ pub enum McrunSubcommand {
         #[arg(long, default_value = "true")]
         forward_output: bool,
 
+        /// Format of the child's log (`plain` or `json`)
+        #[arg(long, default_value_t = LogFormat::Plain)]
+        log_format: LogFormat,
+
+        /// When to restart the child after it exits (`never`, `always` or `on-failure`)
+        #[arg(long, default_value_t = RestartPolicy::Never)]
+        restart_policy: RestartPolicy,
+
+        /// Number of times to restart the child in a row before leaving it stopped
+        #[arg(long)]
+        max_restarts: Option<u32>,
+
+        /// Seconds the guest agent has to answer after the child starts before the child is
+        /// stopped. The guest agent is only probed if this and the agent socket are set
+        #[arg(long)]
+        boot_timeout: Option<u64>,
+
         // Sandbox specific arguments
         /// Native root filesystem path
         #[arg(long)]
@@ -158,6 +196,22 @@ pub enum McrunSubcommand {
         #[arg(long)]
         subnet: Option<String>,
 
+        /// Seccomp profile of the VMM process (`default`, `unconfined` or a path to a profile file)
+        #[arg(long)]
+        seccomp_profile: Option<String>,
+
+        /// Capabilities to add to the VMM process
+        #[arg(long)]
+        cap_add: Vec<String>,
+
+        /// Capabilities to drop from the VMM process
+        #[arg(long)]
+        cap_drop: Vec<String>,
+
+        /// Path of the UNIX socket the guest agent is reachable through
+        #[arg(long)]
+        agent_socket: Option<PathBuf>,
+
         /// Additional arguments after `--`
         #[arg(last = true)]
         args: Vec<String>,
@@ -179,5 +233,13 @@ pub enum McrunSubcommand {
         /// Set server secret key to authenticate API requests
         #[arg(long)]
         key: Option<String>,
+
+        /// Seconds to wait for in-flight requests to complete when shutting down
+        #[arg(long)]
+        shutdown_timeout: Option<u64>,
+
+        /// Stop running sandboxes when the server shuts down
+        #[arg(long, default_value_t = false)]
+        stop_sandboxes_on_shutdown: bool,
     },
 }
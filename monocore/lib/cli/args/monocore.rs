@@ -83,7 +83,7 @@ pub enum MonocoreSubcommand {
         #[arg(long = "env", name = "ENV")]
         envs: Vec<String>,
 
-        /// Environment file
+        /// Environment file of <key>=<value> lines. Variables given with `--env` take precedence
         #[arg(long)]
         env_file: Option<Utf8UnixPathBuf>,
 
@@ -174,6 +174,10 @@ pub enum MonocoreSubcommand {
         /// Config path
         #[arg(short, long)]
         config: Option<String>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
     },
 
     /// Show logs of a running build, sandbox, or group
@@ -367,6 +371,10 @@ pub enum MonocoreSubcommand {
         #[arg(long = "env", name = "ENV")]
         envs: Vec<String>,
 
+        /// Environment file of <key>=<value> lines. Variables given with `--env` take precedence
+        #[arg(long)]
+        env_file: Option<PathBuf>,
+
         /// Working directory
         #[arg(long)]
         workdir: Option<Utf8UnixPathBuf>,
@@ -431,6 +439,10 @@ pub enum MonocoreSubcommand {
         /// Config path
         #[arg(short, long)]
         config: Option<String>,
+
+        /// Show the changes that would be made without making them
+        #[arg(long, alias = "dry-run")]
+        plan: bool,
     },
 
     /// Start project sandboxes
@@ -515,6 +527,10 @@ pub enum MonocoreSubcommand {
         /// Config path
         #[arg(short, long)]
         config: Option<String>,
+
+        /// Output format
+        #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+        format: OutputFormat,
     },
 
     /// Clean project data
@@ -623,10 +639,19 @@ pub enum ServerSubcommand {
         #[arg(long, default_value_t = false)]
         secure: bool,
 
-        /// Set secret key for server. Automatically generated if not provided.
+        /// Set secret key for server. Falls back to MONOCORE_SERVER_KEY, then automatically
+        /// generated if neither is provided.
         #[arg(long)]
         key: Option<String>,
 
+        /// Seconds to wait for in-flight requests to complete when shutting down
+        #[arg(long)]
+        shutdown_timeout: Option<u64>,
+
+        /// Stop running sandboxes when the server shuts down
+        #[arg(long, default_value_t = false)]
+        stop_sandboxes_on_shutdown: bool,
+
         /// Run server in the background``
         #[arg(short, long)]
         detach: bool,
@@ -644,6 +669,17 @@ pub enum ServerSubcommand {
     },
 }
 
+/// Output formats for subcommands that report on sandboxes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable table
+    #[default]
+    Table,
+
+    /// Machine-readable JSON
+    Json,
+}
+
 /// Actions for the self subcommand
 #[derive(Debug, Clone, clap::ValueEnum)]
 pub enum SelfAction {
@@ -1,4 +1,4 @@
-use std::{fs, path::PathBuf, sync::LazyLock};
+use std::{fs, path::PathBuf, sync::LazyLock, time::Duration};
 
 use crate::utils::MONOCORE_HOME_DIR;
 
@@ -40,6 +40,45 @@ pub static DEFAULT_MCRUN_EXE_PATH: LazyLock<PathBuf> = LazyLock::new(|| {
     actual_exe.parent().unwrap().join("mcrun")
 });
 
+/// The default number of image layers downloaded at the same time when pulling an image.
+pub const DEFAULT_MAX_CONCURRENT_DOWNLOADS: usize = 4;
+
+/// The default number of times an interrupted image layer download is resumed before giving up.
+pub const DEFAULT_DOWNLOAD_MAX_RETRIES: u32 = 5;
+
+/// The default delay before resuming an interrupted image layer download. It doubles with each
+/// retry of the same download.
+pub const DEFAULT_DOWNLOAD_RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+/// The default time a sandbox has to become ready before the sandboxes depending on it are
+/// started.
+pub const DEFAULT_SANDBOX_READY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The default interval at which a starting sandbox is checked for readiness.
+pub const DEFAULT_SANDBOX_READY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The default interval between attempts of a sandbox's readiness probe.
+pub const DEFAULT_READINESS_PROBE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The default time a single attempt of a sandbox's readiness probe has to succeed.
+pub const DEFAULT_READINESS_PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The default number of failed attempts of a sandbox's readiness probe before giving up.
+pub const DEFAULT_READINESS_PROBE_RETRIES: u32 = 30;
+
+/// The default time a sandbox has to shut down after being asked to before it is killed.
+///
+/// This is longer than the supervisor's own [`monoutils::DEFAULT_SHUTDOWN_GRACE_PERIOD`], so the
+/// supervisor has time to stop its MicroVM before the supervisor itself is killed.
+pub const DEFAULT_SANDBOX_STOP_GRACE_PERIOD: Duration =
+    Duration::from_secs(monoutils::DEFAULT_SHUTDOWN_GRACE_PERIOD.as_secs() + 5);
+
+/// The default interval at which a sandbox whose logs are being streamed is checked for exit.
+pub const DEFAULT_SANDBOX_EXIT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The default interval at which the resources used by a running sandbox's MicroVM are recorded.
+pub const DEFAULT_SANDBOX_USAGE_INTERVAL: Duration = Duration::from_secs(5);
+
 /// The default working directory for the sandbox.
 pub const DEFAULT_WORKDIR: &str = "/";
 
@@ -48,3 +87,6 @@ pub const DEFAULT_SERVER_NAMESPACE: &str = "default";
 
 /// The default port for the sandbox server.
 pub const DEFAULT_SERVER_PORT: u16 = 5050;
+
+/// The default time the sandbox server waits for in-flight requests to complete when shutting down.
+pub const DEFAULT_SERVER_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
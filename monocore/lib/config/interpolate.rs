@@ -0,0 +1,358 @@
+//! Variable interpolation for configuration values.
+
+use crate::{config::EnvPair, MonocoreError, MonocoreResult};
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Expands variable references in a configuration value.
+///
+/// The following forms are supported:
+/// - `${NAME}` - Replaced with the value of `NAME`
+/// - `${NAME:-default}` - Replaced with the value of `NAME`, or `default` if `NAME` is undefined
+///   or empty
+/// - `$$` - A literal `$`
+///
+/// A `$` that isn't followed by `{` or `$` is kept as is.
+///
+/// ## Arguments
+///
+/// * `value` - The value to expand
+/// * `lookup` - Returns the value of a variable, or `None` if it is undefined
+///
+/// ## Examples
+///
+/// ```
+/// use monocore::config::interpolate;
+///
+/// let lookup = |name: &str| (name == "USER").then(|| "alice".to_string());
+///
+/// assert_eq!(interpolate("/home/${USER}", lookup).unwrap(), "/home/alice");
+/// assert_eq!(interpolate("${SHELL:-/bin/sh}", lookup).unwrap(), "/bin/sh");
+/// assert_eq!(interpolate("cost: $$5", lookup).unwrap(), "cost: $5");
+/// assert!(interpolate("${HOME}", lookup).is_err());
+/// ```
+///
+/// ## Errors
+///
+/// Returns [`MonocoreError::UndefinedConfigVariable`] if a variable without a default is
+/// undefined, and [`MonocoreError::InvalidConfigInterpolation`] if a reference is malformed.
+pub fn interpolate(value: &str, lookup: impl Fn(&str) -> Option<String>) -> MonocoreResult<String> {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(idx) = rest.find('$') {
+        result.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+
+        if let Some(after) = rest.strip_prefix("$$") {
+            result.push('$');
+            rest = after;
+            continue;
+        }
+
+        let Some(after) = rest.strip_prefix("${") else {
+            result.push('$');
+            rest = &rest[1..];
+            continue;
+        };
+
+        let end = after.find('}').ok_or_else(|| {
+            MonocoreError::InvalidConfigInterpolation(format!("unclosed `${{` in {value}"))
+        })?;
+        let (name, default) = match after[..end].split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (&after[..end], None),
+        };
+
+        if !is_valid_name(name) {
+            return Err(MonocoreError::InvalidConfigInterpolation(format!(
+                "invalid variable name `{name}` in {value}"
+            )));
+        }
+
+        match (lookup(name), default) {
+            (Some(found), Some(default)) if found.is_empty() => result.push_str(default),
+            (Some(found), _) => result.push_str(&found),
+            (None, Some(default)) => result.push_str(default),
+            (None, None) => {
+                return Err(MonocoreError::UndefinedConfigVariable(name.to_string()));
+            }
+        }
+
+        rest = &after[end + 1..];
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Parses the contents of an environment file into variables, in the order they are defined.
+///
+/// Each line is a `NAME=value` pair, where `NAME` is a valid shell identifier. Blank lines and
+/// lines starting with `#` are skipped, and a value wrapped in matching single or double quotes
+/// has them removed. If a name is defined more than once, the last value wins, but the variable
+/// keeps the position of its first definition.
+///
+/// ## Examples
+///
+/// ```
+/// use monocore::config::parse_env_file;
+///
+/// let vars = parse_env_file("# comment\nHOST=localhost\nGREETING=\"hello world\"\n").unwrap();
+/// assert_eq!(vars[0].to_string(), "HOST=localhost");
+/// assert_eq!(vars[1].to_string(), "GREETING=hello world");
+///
+/// assert!(parse_env_file("HOST=localhost\n1NVALID=value").is_err());
+/// ```
+///
+/// ## Errors
+///
+/// Returns [`MonocoreError::InvalidEnvFileEntry`] with the 1-based line number if a line isn't a
+/// `NAME=value` pair or its name isn't a valid shell identifier.
+pub fn parse_env_file(contents: &str) -> MonocoreResult<Vec<EnvPair>> {
+    let mut vars: Vec<EnvPair> = Vec::new();
+    for (idx, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let Some((name, value)) = line.split_once('=') else {
+            return Err(MonocoreError::InvalidEnvFileEntry(
+                idx + 1,
+                format!("expected `NAME=value`, found `{line}`"),
+            ));
+        };
+
+        let name = name.trim();
+        if !is_valid_name(name) {
+            return Err(MonocoreError::InvalidEnvFileEntry(
+                idx + 1,
+                format!("invalid variable name `{name}`"),
+            ));
+        }
+
+        let value = [('"', '"'), ('\'', '\'')]
+            .iter()
+            .find_map(|(open, close)| {
+                value
+                    .strip_prefix(*open)
+                    .and_then(|v| v.strip_suffix(*close))
+            })
+            .unwrap_or(value);
+
+        let pair = EnvPair::new(name, value);
+        match vars.iter_mut().find(|var| var.get_name() == name) {
+            Some(var) => *var = pair,
+            None => vars.push(pair),
+        }
+    }
+
+    Ok(vars)
+}
+
+/// Parses a `NAME=value` environment variable given explicitly, for example with `--env`.
+///
+/// Unlike parsing an [`EnvPair`], this requires `NAME` to be a valid shell identifier.
+///
+/// ## Errors
+///
+/// Returns [`MonocoreError::InvalidEnvPair`] if `s` isn't a `NAME=value` pair or its name isn't a
+/// valid shell identifier.
+pub fn parse_env_arg(s: &str) -> MonocoreResult<EnvPair> {
+    let pair = s.parse::<EnvPair>()?;
+    if !is_valid_name(pair.get_name()) {
+        return Err(MonocoreError::InvalidEnvPair(s.to_string()));
+    }
+
+    Ok(pair)
+}
+
+/// Returns `true` if `name` is a valid variable name: a letter or underscore, followed by letters,
+/// digits, or underscores.
+fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn test_interpolate_defined_variables() -> anyhow::Result<()> {
+        let lookup = helper::lookup(&[("HOME", "/home/alice"), ("PORT", "8080")]);
+
+        assert_eq!(interpolate("${HOME}/data", &lookup)?, "/home/alice/data");
+        assert_eq!(interpolate("${HOME}:${PORT}", &lookup)?, "/home/alice:8080");
+        assert_eq!(interpolate("${PORT:-3000}", &lookup)?, "8080");
+        assert_eq!(interpolate("no variables", &lookup)?, "no variables");
+        assert_eq!(interpolate("", &lookup)?, "");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_interpolate_defaulted_variables() -> anyhow::Result<()> {
+        let lookup = helper::lookup(&[("EMPTY", "")]);
+
+        assert_eq!(interpolate("${PORT:-3000}", &lookup)?, "3000");
+        assert_eq!(interpolate("${EMPTY:-fallback}", &lookup)?, "fallback");
+        assert_eq!(
+            interpolate("${DIR:-/var/lib/app}", &lookup)?,
+            "/var/lib/app"
+        );
+        assert_eq!(interpolate("${NAME:-}", &lookup)?, "");
+
+        // A defined but empty variable without a default expands to nothing
+        assert_eq!(interpolate("a${EMPTY}b", &lookup)?, "ab");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_interpolate_undefined_variable_without_default() {
+        let lookup = helper::lookup(&[("HOME", "/home/alice")]);
+
+        assert!(matches!(
+            interpolate("${HOME}/${MISSING}", &lookup),
+            Err(MonocoreError::UndefinedConfigVariable(name)) if name == "MISSING"
+        ));
+    }
+
+    #[test]
+    fn test_interpolate_escaped_and_bare_dollars() -> anyhow::Result<()> {
+        let lookup = helper::lookup(&[("NAME", "value")]);
+
+        assert_eq!(interpolate("$${NAME}", &lookup)?, "${NAME}");
+        assert_eq!(interpolate("$$$${NAME}", &lookup)?, "$${NAME}");
+        assert_eq!(interpolate("$$${NAME}", &lookup)?, "$value");
+        assert_eq!(interpolate("price: $5", &lookup)?, "price: $5");
+        assert_eq!(interpolate("$NAME", &lookup)?, "$NAME");
+        assert_eq!(interpolate("trailing $", &lookup)?, "trailing $");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_interpolate_malformed_references() {
+        let lookup = helper::lookup(&[]);
+
+        for value in ["${NAME", "${}", "${1NAME}", "${NA-ME}", "${:-default}"] {
+            assert!(
+                matches!(
+                    interpolate(value, &lookup),
+                    Err(MonocoreError::InvalidConfigInterpolation(_))
+                ),
+                "{value}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_env_file() -> anyhow::Result<()> {
+        let vars = parse_env_file(
+            "# database settings\n\
+             DB_HOST=localhost\n\
+             \n\
+             DB_URL=postgres://${DB_HOST}:5432\n\
+             GREETING=\"hello world\"\n\
+             QUOTE='single'\n\
+             EMPTY=\n",
+        )?;
+
+        let vars: HashMap<_, _> = vars
+            .iter()
+            .map(|var| (var.get_name().as_str(), var.get_value().as_str()))
+            .collect();
+        assert_eq!(vars.len(), 5);
+        assert_eq!(vars["DB_HOST"], "localhost");
+        assert_eq!(vars["DB_URL"], "postgres://${DB_HOST}:5432");
+        assert_eq!(vars["GREETING"], "hello world");
+        assert_eq!(vars["QUOTE"], "single");
+        assert_eq!(vars["EMPTY"], "");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_env_file_comments_and_order() -> anyhow::Result<()> {
+        let vars = parse_env_file(
+            "  # indented comment\n\
+             \n\
+             B=1\n\
+             A=2 # not a comment\n\
+             \t\n\
+             B=3\n",
+        )?;
+
+        assert_eq!(
+            vars,
+            [
+                EnvPair::new("B", "3"),
+                EnvPair::new("A", "2 # not a comment")
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_env_file_malformed_lines() {
+        for (contents, line) in [
+            ("NOT_A_PAIR", 1),
+            ("A=1\n# comment\n\nNOT_A_PAIR\n", 4),
+            ("A=1\n=value", 2),
+            ("A=1\nB=2\n1NAME=value", 3),
+            ("MY-NAME=value", 1),
+            ("MY NAME=value", 1),
+        ] {
+            match parse_env_file(contents) {
+                Err(MonocoreError::InvalidEnvFileEntry(found, _)) => {
+                    assert_eq!(found, line, "{contents:?}")
+                }
+                other => panic!("expected an invalid entry for {contents:?}, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_env_arg() -> anyhow::Result<()> {
+        assert_eq!(parse_env_arg("NAME=a=b")?, EnvPair::new("NAME", "a=b"));
+        assert_eq!(parse_env_arg("_NAME2=")?, EnvPair::new("_NAME2", ""));
+
+        for arg in ["NAME", "=value", "1NAME=value", "VAR.WITH.DOTS=value"] {
+            assert!(
+                matches!(parse_env_arg(arg), Err(MonocoreError::InvalidEnvPair(_))),
+                "{arg}"
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod helper {
+    use std::collections::HashMap;
+
+    /// Returns a lookup over the given variables.
+    pub(super) fn lookup(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+}
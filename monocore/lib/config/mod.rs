@@ -2,11 +2,13 @@
 
 mod defaults;
 mod env_pair;
+mod interpolate;
 mod monocore;
 mod path_pair;
 mod path_segment;
 mod port_pair;
 mod reference_path;
+mod security;
 
 //--------------------------------------------------------------------------------------------------
 // Exports
@@ -14,8 +16,10 @@ mod reference_path;
 
 pub use defaults::*;
 pub use env_pair::*;
+pub use interpolate::*;
 pub use monocore::*;
 pub use path_pair::*;
 pub use path_segment::*;
 pub use port_pair::*;
 pub use reference_path::*;
+pub use security::*;
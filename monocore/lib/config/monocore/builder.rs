@@ -1,14 +1,19 @@
 use std::collections::HashMap;
 
+use monoutils::RestartPolicy;
 use semver::Version;
 use typed_path::Utf8UnixPathBuf;
 
 use crate::{
-    config::{EnvPair, PathPair, PortPair, ReferenceOrPath, DEFAULT_SHELL},
+    config::{
+        Capability, EnvPair, PathPair, PortPair, ReferenceOrPath, SeccompProfile, DEFAULT_SHELL,
+    },
     MonocoreResult,
 };
 
-use super::{Build, Group, Meta, Module, Monocore, NetworkScope, Proxy, Sandbox, SandboxGroup};
+use super::{
+    Build, Group, Meta, Module, Monocore, NetworkScope, Proxy, Readiness, Sandbox, SandboxGroup,
+};
 
 //--------------------------------------------------------------------------------------------------
 // Types
@@ -55,6 +60,13 @@ pub struct MonocoreBuilder {
 /// - `exports`: The files to export
 /// - `scope`: The network scope for the sandbox
 /// - `proxy`: The proxy to use
+/// - `seccomp`: The seccomp profile to harden the virtual machine monitor with
+/// - `cap_add`: The capabilities to add to the virtual machine monitor
+/// - `cap_drop`: The capabilities to drop from the virtual machine monitor
+/// - `restart`: When the sandbox is restarted after it exits
+/// - `max_restarts`: The number of times the sandbox is restarted in a row
+/// - `readiness`: The probe that decides when the sandbox is ready
+/// - `boot_timeout`: How long in seconds the guest agent has to answer after the MicroVM starts
 pub struct SandboxBuilder<I, S> {
     version: Option<Version>,
     meta: Option<Meta>,
@@ -74,6 +86,13 @@ pub struct SandboxBuilder<I, S> {
     exports: HashMap<String, Utf8UnixPathBuf>,
     scope: NetworkScope,
     proxy: Option<Proxy>,
+    seccomp: Option<SeccompProfile>,
+    cap_add: Vec<Capability>,
+    cap_drop: Vec<Capability>,
+    restart: Option<RestartPolicy>,
+    max_restarts: Option<u32>,
+    readiness: Option<Readiness>,
+    boot_timeout: Option<u64>,
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -164,6 +183,13 @@ impl<I, S> SandboxBuilder<I, S> {
             exports: self.exports,
             scope: self.scope,
             proxy: self.proxy,
+            seccomp: self.seccomp,
+            cap_add: self.cap_add,
+            cap_drop: self.cap_drop,
+            restart: self.restart,
+            max_restarts: self.max_restarts,
+            readiness: self.readiness,
+            boot_timeout: self.boot_timeout,
         }
     }
 
@@ -248,6 +274,13 @@ impl<I, S> SandboxBuilder<I, S> {
             exports: self.exports,
             scope: self.scope,
             proxy: self.proxy,
+            seccomp: self.seccomp,
+            cap_add: self.cap_add,
+            cap_drop: self.cap_drop,
+            restart: self.restart,
+            max_restarts: self.max_restarts,
+            readiness: self.readiness,
+            boot_timeout: self.boot_timeout,
         }
     }
 
@@ -289,6 +322,54 @@ impl<I, S> SandboxBuilder<I, S> {
         self.proxy = Some(proxy);
         self
     }
+
+    /// Sets the seccomp profile to harden the sandbox's virtual machine monitor with
+    pub fn seccomp(mut self, seccomp: SeccompProfile) -> SandboxBuilder<I, S> {
+        self.seccomp = Some(seccomp);
+        self
+    }
+
+    /// Sets the capabilities to add to the sandbox's virtual machine monitor
+    pub fn cap_add(
+        mut self,
+        cap_add: impl IntoIterator<Item = Capability>,
+    ) -> SandboxBuilder<I, S> {
+        self.cap_add = cap_add.into_iter().collect();
+        self
+    }
+
+    /// Sets the capabilities to drop from the sandbox's virtual machine monitor
+    pub fn cap_drop(
+        mut self,
+        cap_drop: impl IntoIterator<Item = Capability>,
+    ) -> SandboxBuilder<I, S> {
+        self.cap_drop = cap_drop.into_iter().collect();
+        self
+    }
+
+    /// Sets when the sandbox is restarted after it exits
+    pub fn restart(mut self, restart: RestartPolicy) -> SandboxBuilder<I, S> {
+        self.restart = Some(restart);
+        self
+    }
+
+    /// Sets the number of times the sandbox is restarted in a row before it is left stopped
+    pub fn max_restarts(mut self, max_restarts: u32) -> SandboxBuilder<I, S> {
+        self.max_restarts = Some(max_restarts);
+        self
+    }
+
+    /// Sets the readiness probe for the sandbox
+    pub fn readiness(mut self, readiness: Readiness) -> SandboxBuilder<I, S> {
+        self.readiness = Some(readiness);
+        self
+    }
+
+    /// Sets how long in seconds the sandbox's guest agent has to answer after the MicroVM starts
+    pub fn boot_timeout(mut self, boot_timeout: u64) -> SandboxBuilder<I, S> {
+        self.boot_timeout = Some(boot_timeout);
+        self
+    }
 }
 
 impl SandboxBuilder<ReferenceOrPath, String> {
@@ -313,6 +394,13 @@ impl SandboxBuilder<ReferenceOrPath, String> {
             exports: self.exports,
             scope: self.scope,
             proxy: self.proxy,
+            seccomp: self.seccomp,
+            cap_add: self.cap_add,
+            cap_drop: self.cap_drop,
+            restart: self.restart,
+            max_restarts: self.max_restarts,
+            readiness: self.readiness,
+            boot_timeout: self.boot_timeout,
         }
     }
 }
@@ -342,6 +430,13 @@ impl Default for SandboxBuilder<(), String> {
             exports: HashMap::new(),
             scope: NetworkScope::Group,
             proxy: None,
+            seccomp: None,
+            cap_add: Vec::new(),
+            cap_drop: Vec::new(),
+            restart: None,
+            max_restarts: None,
+            readiness: None,
+            boot_timeout: None,
         }
     }
 }
@@ -6,17 +6,24 @@ use std::{
     fmt::{self, Display},
     net::Ipv4Addr,
     str::FromStr,
+    time::Duration,
 };
 
 use getset::Getters;
 use ipnetwork::Ipv4Network as Ipv4Net;
+use monoutils::RestartPolicy;
 use semver::Version;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use typed_builder::TypedBuilder;
 use typed_path::Utf8UnixPathBuf;
 
 use crate::{
-    config::{EnvPair, PathPair, PortPair, ReferenceOrPath, DEFAULT_SHELL},
+    config::{
+        self, Capability, EnvPair, PathPair, PortPair, Protocol, ReferenceOrPath, SeccompProfile,
+        DEFAULT_READINESS_PROBE_INTERVAL, DEFAULT_READINESS_PROBE_RETRIES,
+        DEFAULT_READINESS_PROBE_TIMEOUT, DEFAULT_SHELL,
+    },
     MonocoreError, MonocoreResult,
 };
 
@@ -145,7 +152,11 @@ pub struct Build {
     pub(crate) volumes: Vec<PathPair>,
 
     /// The ports to expose.
-    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    #[serde(
+        skip_serializing_if = "Vec::is_empty",
+        default,
+        deserialize_with = "config::deserialize_port_pairs"
+    )]
     #[builder(default)]
     pub(crate) ports: Vec<PortPair>,
 
@@ -294,6 +305,72 @@ pub enum Proxy {
     },
 }
 
+/// The readiness probe of a sandbox, which decides when a started sandbox is up.
+///
+/// Exactly one of `tcp`, `exec` and `http` must be set. The probe is attempted every `interval`
+/// seconds until it succeeds or has failed `retries` times.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Getters)]
+#[getset(get = "pub with_prefix")]
+pub struct Readiness {
+    /// Checks that a TCP connection can be made to a port.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub(crate) tcp: Option<TcpProbe>,
+
+    /// Checks that a command run in the sandbox exits successfully.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub(crate) exec: Option<ExecProbe>,
+
+    /// Checks that an HTTP GET request to a port returns a success status.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub(crate) http: Option<HttpProbe>,
+
+    /// The number of seconds between attempts.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    #[getset(skip)]
+    pub(crate) interval: Option<u64>,
+
+    /// The number of seconds a single attempt has to succeed.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    #[getset(skip)]
+    pub(crate) timeout: Option<u64>,
+
+    /// The number of failed attempts before giving up.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    #[getset(skip)]
+    pub(crate) retries: Option<u32>,
+}
+
+/// A readiness probe that connects to a TCP port.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Getters)]
+#[getset(get = "pub with_prefix")]
+pub struct TcpProbe {
+    /// The guest port to connect to. It must be exposed in the sandbox's `ports`.
+    pub(crate) port: u16,
+}
+
+/// A readiness probe that runs a command in the sandbox.
+///
+/// The command is run through the sandbox's guest agent, so the image must provide one. See
+/// [`exec`](crate::management::exec) for what the agent has to implement.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Getters)]
+#[getset(get = "pub with_prefix")]
+pub struct ExecProbe {
+    /// The command and its arguments.
+    pub(crate) command: Vec<String>,
+}
+
+/// A readiness probe that sends an HTTP GET request.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Getters)]
+#[getset(get = "pub with_prefix")]
+pub struct HttpProbe {
+    /// The guest port to send the request to. It must be exposed in the sandbox's `ports`.
+    pub(crate) port: u16,
+
+    /// The path to request.
+    #[serde(default = "HttpProbe::default_path")]
+    pub(crate) path: String,
+}
+
 /// The sandbox to run.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Getters)]
 #[getset(get = "pub with_prefix")]
@@ -322,7 +399,11 @@ pub struct Sandbox {
     pub(crate) volumes: Vec<PathPair>,
 
     /// The ports to expose.
-    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    #[serde(
+        skip_serializing_if = "Vec::is_empty",
+        default,
+        deserialize_with = "config::deserialize_port_pairs"
+    )]
     pub(crate) ports: Vec<PortPair>,
 
     /// The environment variables to use.
@@ -387,6 +468,37 @@ pub struct Sandbox {
     /// The proxy configuration.
     #[serde(skip_serializing_if = "Option::is_none", default)]
     pub(crate) proxy: Option<Proxy>,
+
+    /// The seccomp profile to harden the sandbox's virtual machine monitor process with.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub(crate) seccomp: Option<SeccompProfile>,
+
+    /// The capabilities to add to the sandbox's virtual machine monitor process.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub(crate) cap_add: Vec<Capability>,
+
+    /// The capabilities to drop from the sandbox's virtual machine monitor process.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub(crate) cap_drop: Vec<Capability>,
+
+    /// When the sandbox is restarted after it exits. It is never restarted by default.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub(crate) restart: Option<RestartPolicy>,
+
+    /// The number of times the sandbox is restarted in a row before it is left stopped.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub(crate) max_restarts: Option<u32>,
+
+    /// The probe that decides when the sandbox is ready.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub(crate) readiness: Option<Readiness>,
+
+    /// How long in seconds the sandbox's guest agent has to answer after the MicroVM starts before
+    /// the MicroVM is stopped. Once it has answered, the guest agent is probed periodically and
+    /// the sandbox is reported unhealthy if it stops answering. It isn't probed by default, since
+    /// the image must provide the guest agent.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub(crate) boot_timeout: Option<u64>,
 }
 
 /// Configuration for a sandbox's group membership.
@@ -458,8 +570,31 @@ impl Monocore {
     }
 
     /// Validates the configuration.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`MonocoreError::ConfigValidationErrors`] listing every problem found, such as an
+    /// incomplete readiness probe.
     pub fn validate(&self) -> MonocoreResult<()> {
-        // TODO: Add validation logic here
+        let mut errors = Vec::new();
+        for (name, sandbox) in &self.sandboxes {
+            if let Some(readiness) = &sandbox.readiness {
+                errors.extend(readiness.validation_errors(name, sandbox));
+            }
+
+            if sandbox.boot_timeout == Some(0) {
+                errors.push(format!(
+                    "sandbox '{}' must have a boot timeout of at least 1 second",
+                    name
+                ));
+            }
+        }
+
+        if !errors.is_empty() {
+            errors.sort();
+            return Err(MonocoreError::ConfigValidationErrors(errors));
+        }
+
         Ok(())
     }
 
@@ -497,6 +632,174 @@ impl Sandbox {
             Cow::Owned(scripts)
         }
     }
+
+    /// Returns a hash of the sandbox's spec, which changes whenever a setting of the sandbox
+    /// changes.
+    ///
+    /// The metadata and readiness probe don't affect how the sandbox runs, so they aren't part of
+    /// the spec. The spec is
+    /// hashed in a canonical form, so the order of map entries in the configuration doesn't affect
+    /// the hash either.
+    pub fn spec_hash(&self) -> MonocoreResult<String> {
+        let spec = Self {
+            meta: None,
+            readiness: None,
+            ..self.clone()
+        };
+
+        // JSON values keep object keys sorted, unlike the `HashMap`s in the sandbox
+        let spec = serde_json::to_value(&spec)?;
+        Ok(hex::encode(Sha256::digest(spec.to_string())))
+    }
+
+    /// Expands `${NAME}` and `${NAME:-default}` references in the environment variable values and
+    /// volume paths of the sandbox.
+    ///
+    /// See [`interpolate`](config::interpolate) for the supported syntax.
+    ///
+    /// ## Arguments
+    ///
+    /// * `lookup` - Returns the value of a variable, or `None` if it is undefined
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if a reference is malformed or names an undefined variable without a
+    /// default, or if an expanded volume is not a valid path pair.
+    pub fn interpolate(&mut self, lookup: impl Fn(&str) -> Option<String>) -> MonocoreResult<()> {
+        for env in &mut self.envs {
+            let value = config::interpolate(env.get_value(), &lookup)?;
+            *env = EnvPair::new(env.get_name().clone(), value);
+        }
+
+        for volume in &mut self.volumes {
+            *volume = config::interpolate(&volume.to_string(), &lookup)?.parse()?;
+        }
+
+        Ok(())
+    }
+
+    /// Merges the variables of the sandbox's env file into its environment variables.
+    ///
+    /// Variables defined in `envs` take precedence over env file variables of the same name. The
+    /// env file variables come first, in the order they are defined in the file, followed by the
+    /// variables in `envs`.
+    ///
+    /// ## Arguments
+    ///
+    /// * `env_file_vars` - The variables of the env file, as parsed by
+    ///   [`parse_env_file`](config::parse_env_file)
+    pub fn merge_env_file(&mut self, env_file_vars: Vec<EnvPair>) {
+        let mut envs: Vec<EnvPair> = env_file_vars
+            .into_iter()
+            .filter(|var| !self.envs.iter().any(|env| env.get_name() == var.get_name()))
+            .collect();
+
+        envs.append(&mut self.envs);
+        self.envs = envs;
+    }
+
+    /// Returns the host port that the given guest port is exposed on over TCP, if any.
+    pub fn get_exposed_tcp_port(&self, guest_port: u16) -> Option<u16> {
+        self.ports
+            .iter()
+            .find(|port| port.get_guest() == guest_port && port.get_protocol() == Protocol::Tcp)
+            .map(PortPair::get_host)
+    }
+}
+
+impl Readiness {
+    /// Returns the time between attempts of the probe.
+    pub fn get_interval(&self) -> Duration {
+        self.interval
+            .map_or(DEFAULT_READINESS_PROBE_INTERVAL, Duration::from_secs)
+    }
+
+    /// Returns the time a single attempt of the probe has to succeed.
+    pub fn get_timeout(&self) -> Duration {
+        self.timeout
+            .map_or(DEFAULT_READINESS_PROBE_TIMEOUT, Duration::from_secs)
+    }
+
+    /// Returns the number of failed attempts of the probe before giving up.
+    pub fn get_retries(&self) -> u32 {
+        self.retries.unwrap_or(DEFAULT_READINESS_PROBE_RETRIES)
+    }
+
+    /// Returns the problems with the probe of the sandbox named `name`, if any.
+    fn validation_errors(&self, name: &str, sandbox: &Sandbox) -> Vec<String> {
+        let mut errors = Vec::new();
+        let probes = [self.tcp.is_some(), self.exec.is_some(), self.http.is_some()];
+        match probes.iter().filter(|set| **set).count() {
+            0 => errors.push(format!(
+                "readiness probe of sandbox '{}' must set one of `tcp`, `exec` or `http`",
+                name
+            )),
+            1 => {}
+            _ => errors.push(format!(
+                "readiness probe of sandbox '{}' must set only one of `tcp`, `exec` or `http`",
+                name
+            )),
+        }
+
+        if let Some(exec) = &self.exec {
+            if exec.command.is_empty() || exec.command[0].is_empty() {
+                errors.push(format!(
+                    "exec readiness probe of sandbox '{}' has no command",
+                    name
+                ));
+            }
+        }
+
+        if let Some(http) = &self.http {
+            if !http.path.starts_with('/') {
+                errors.push(format!(
+                    "http readiness probe of sandbox '{}' has a relative path: {}",
+                    name, http.path
+                ));
+            }
+        }
+
+        let ports = self.tcp.iter().map(|tcp| ("tcp", tcp.port));
+        let ports = ports.chain(self.http.iter().map(|http| ("http", http.port)));
+        for (kind, port) in ports {
+            if sandbox.get_exposed_tcp_port(port).is_none() {
+                errors.push(format!(
+                    "{} readiness probe of sandbox '{}' checks unexposed guest port {}",
+                    kind, name, port
+                ));
+            }
+        }
+
+        if self.interval == Some(0) {
+            errors.push(format!(
+                "readiness probe of sandbox '{}' must have an interval of at least 1 second",
+                name
+            ));
+        }
+
+        if self.timeout == Some(0) {
+            errors.push(format!(
+                "readiness probe of sandbox '{}' must have a timeout of at least 1 second",
+                name
+            ));
+        }
+
+        if self.retries == Some(0) {
+            errors.push(format!(
+                "readiness probe of sandbox '{}' must have at least 1 retry",
+                name
+            ));
+        }
+
+        errors
+    }
+}
+
+impl HttpProbe {
+    /// Returns the default path of an HTTP probe.
+    pub fn default_path() -> String {
+        "/".to_string()
+    }
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -1072,6 +1375,64 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_monocore_config_security_configuration() {
+        let yaml = r#"
+            sandboxes:
+              test_sandbox:
+                image: "alpine:latest"
+                shell: "/bin/sh"
+                seccomp: "default"
+                cap_add:
+                  - "NET_ADMIN"
+                cap_drop:
+                  - "ALL"
+              profiled_sandbox:
+                image: "alpine:latest"
+                shell: "/bin/sh"
+                seccomp: "./seccomp.json"
+        "#;
+
+        let config: Monocore = serde_yaml::from_str(yaml).unwrap();
+        let sandboxes = &config.sandboxes;
+
+        let sandbox = sandboxes.get("test_sandbox").unwrap();
+        assert_eq!(sandbox.seccomp, Some(SeccompProfile::Default));
+        assert_eq!(sandbox.cap_add, vec![Capability::Single(12)]);
+        assert_eq!(sandbox.cap_drop, vec![Capability::All]);
+
+        let sandbox = sandboxes.get("profiled_sandbox").unwrap();
+        assert_eq!(
+            sandbox.seccomp,
+            Some(SeccompProfile::Path(Utf8UnixPathBuf::from(
+                "./seccomp.json"
+            )))
+        );
+        assert!(sandbox.cap_add.is_empty());
+        assert!(sandbox.cap_drop.is_empty());
+
+        // Unknown named profile
+        let yaml = r#"
+            sandboxes:
+              test_sandbox:
+                image: "alpine:latest"
+                shell: "/bin/sh"
+                seccomp: "strict"
+        "#;
+        assert!(serde_yaml::from_str::<Monocore>(yaml).is_err());
+
+        // Unknown capability
+        let yaml = r#"
+            sandboxes:
+              test_sandbox:
+                image: "alpine:latest"
+                shell: "/bin/sh"
+                cap_add:
+                  - "NET_MAGIC"
+        "#;
+        assert!(serde_yaml::from_str::<Monocore>(yaml).is_err());
+    }
+
     #[test]
     fn test_monocore_config_build_dependencies() {
         let yaml = r#"
@@ -1498,4 +1859,382 @@ mod tests {
             "10.30.0.0/24"
         );
     }
+
+    #[test]
+    fn test_monocore_config_sandbox_interpolate() -> anyhow::Result<()> {
+        let yaml = r#"
+            sandboxes:
+              app:
+                image: "alpine:latest"
+                shell: "/bin/sh"
+                envs:
+                  - "DATA_DIR=${DATA_DIR}"
+                  - "PORT=${PORT:-8080}"
+                  - "PRICE=$$5"
+                volumes:
+                  - "${DATA_DIR}:/data"
+                  - "${CACHE_DIR:-/tmp/cache}"
+        "#;
+
+        let config: Monocore = serde_yaml::from_str(yaml)?;
+        let mut sandbox = config.sandboxes["app"].clone();
+        sandbox.interpolate(|name| (name == "DATA_DIR").then(|| "/srv/app".to_string()))?;
+
+        assert_eq!(
+            sandbox.envs,
+            [
+                "DATA_DIR=/srv/app".parse::<EnvPair>()?,
+                "PORT=8080".parse()?,
+                "PRICE=$5".parse()?,
+            ]
+        );
+        assert_eq!(
+            sandbox.volumes,
+            ["/srv/app:/data".parse::<PathPair>()?, "/tmp/cache".parse()?]
+        );
+
+        // Undefined variables without a default are an error
+        let mut sandbox = config.sandboxes["app"].clone();
+        assert!(matches!(
+            sandbox.interpolate(|_| None),
+            Err(MonocoreError::UndefinedConfigVariable(name)) if name == "DATA_DIR"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_monocore_config_sandbox_merge_env_file() -> anyhow::Result<()> {
+        let yaml = r#"
+            sandboxes:
+              app:
+                image: "alpine:latest"
+                shell: "/bin/sh"
+                env_file: ".env"
+                envs:
+                  - "PORT=9090"
+                  - "DEBUG=true"
+        "#;
+
+        let config: Monocore = serde_yaml::from_str(yaml)?;
+        let mut sandbox = config.sandboxes["app"].clone();
+        sandbox.merge_env_file(config::parse_env_file(
+            "HOST=localhost\nPORT=8080\nDEBUG=false\n",
+        )?);
+
+        // Explicit envs override env file entries of the same name
+        assert_eq!(
+            sandbox.envs,
+            [
+                "HOST=localhost".parse::<EnvPair>()?,
+                "PORT=9090".parse()?,
+                "DEBUG=true".parse()?,
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_monocore_config_sandbox_spec_hash() -> anyhow::Result<()> {
+        let yaml = r#"
+            sandboxes:
+              app:
+                image: "alpine:latest"
+                shell: "/bin/sh"
+                scripts:
+                  start: "./start.sh"
+                  test: "./test.sh"
+              reordered:
+                meta:
+                  description: "Only the metadata and map order differ"
+                shell: "/bin/sh"
+                scripts:
+                  test: "./test.sh"
+                  start: "./start.sh"
+                image: "alpine:latest"
+              bigger:
+                image: "alpine:latest"
+                shell: "/bin/sh"
+                ram: 1024
+                scripts:
+                  start: "./start.sh"
+                  test: "./test.sh"
+        "#;
+
+        let config: Monocore = serde_yaml::from_str(yaml)?;
+        let hash = config.sandboxes["app"].spec_hash()?;
+        assert_eq!(hash, config.sandboxes["app"].clone().spec_hash()?);
+        assert_eq!(hash, config.sandboxes["reordered"].spec_hash()?);
+        assert_ne!(hash, config.sandboxes["bigger"].spec_hash()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_monocore_config_boot_timeout() -> anyhow::Result<()> {
+        let yaml = r#"
+            sandboxes:
+              probed:
+                image: "alpine:latest"
+                shell: "/bin/sh"
+                boot_timeout: 20
+              unprobed:
+                image: "alpine:latest"
+                shell: "/bin/sh"
+        "#;
+
+        let config: Monocore = serde_yaml::from_str(yaml)?;
+        assert_eq!(config.sandboxes["probed"].boot_timeout, Some(20));
+        assert_eq!(config.sandboxes["unprobed"].boot_timeout, None);
+        config.validate()?;
+
+        // A MicroVM can't boot in no time
+        let yaml = r#"
+            sandboxes:
+              instant:
+                image: "alpine:latest"
+                shell: "/bin/sh"
+                boot_timeout: 0
+        "#;
+
+        let config: Monocore = serde_yaml::from_str(yaml)?;
+        let Err(MonocoreError::ConfigValidationErrors(errors)) = config.validate() else {
+            panic!("expected validation errors");
+        };
+        assert_eq!(
+            errors,
+            vec!["sandbox 'instant' must have a boot timeout of at least 1 second"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_monocore_config_restart_policy() -> anyhow::Result<()> {
+        let yaml = r#"
+            sandboxes:
+              worker:
+                image: "alpine:latest"
+                shell: "/bin/sh"
+                restart: on-failure
+                max_restarts: 3
+              oneshot:
+                image: "alpine:latest"
+                shell: "/bin/sh"
+        "#;
+
+        let config: Monocore = serde_yaml::from_str(yaml)?;
+
+        let worker = &config.sandboxes["worker"];
+        assert_eq!(worker.restart, Some(RestartPolicy::OnFailure));
+        assert_eq!(worker.max_restarts, Some(3));
+
+        let oneshot = &config.sandboxes["oneshot"];
+        assert_eq!(oneshot.restart, None);
+        assert_eq!(oneshot.max_restarts, None);
+
+        // An unknown policy is rejected
+        let yaml = r#"
+            sandboxes:
+              worker:
+                image: "alpine:latest"
+                shell: "/bin/sh"
+                restart: sometimes
+        "#;
+        assert!(serde_yaml::from_str::<Monocore>(yaml).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_monocore_config_readiness_tcp_probe() -> anyhow::Result<()> {
+        let yaml = r#"
+            sandboxes:
+              db:
+                image: "postgres:16"
+                shell: "/bin/sh"
+                ports:
+                  - "15432:5432"
+                readiness:
+                  tcp:
+                    port: 5432
+                  interval: 2
+                  timeout: 3
+                  retries: 10
+        "#;
+
+        let config: Monocore = serde_yaml::from_str(yaml)?;
+        config.validate()?;
+
+        let sandbox = &config.sandboxes["db"];
+        let readiness = sandbox.readiness.as_ref().unwrap();
+        assert_eq!(readiness.tcp, Some(TcpProbe { port: 5432 }));
+        assert!(readiness.exec.is_none());
+        assert!(readiness.http.is_none());
+        assert_eq!(readiness.get_interval(), Duration::from_secs(2));
+        assert_eq!(readiness.get_timeout(), Duration::from_secs(3));
+        assert_eq!(readiness.get_retries(), 10);
+        assert_eq!(sandbox.get_exposed_tcp_port(5432), Some(15432));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_monocore_config_readiness_exec_probe() -> anyhow::Result<()> {
+        let yaml = r#"
+            sandboxes:
+              db:
+                image: "postgres:16"
+                shell: "/bin/sh"
+                readiness:
+                  exec:
+                    command: ["pg_isready", "-U", "postgres"]
+        "#;
+
+        let config: Monocore = serde_yaml::from_str(yaml)?;
+        config.validate()?;
+
+        let readiness = config.sandboxes["db"].readiness.as_ref().unwrap();
+        assert_eq!(
+            readiness.exec,
+            Some(ExecProbe {
+                command: vec![
+                    "pg_isready".to_string(),
+                    "-U".to_string(),
+                    "postgres".to_string()
+                ],
+            })
+        );
+
+        // Unset timings fall back to the defaults
+        assert_eq!(readiness.get_interval(), DEFAULT_READINESS_PROBE_INTERVAL);
+        assert_eq!(readiness.get_timeout(), DEFAULT_READINESS_PROBE_TIMEOUT);
+        assert_eq!(readiness.get_retries(), DEFAULT_READINESS_PROBE_RETRIES);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_monocore_config_readiness_http_probe() -> anyhow::Result<()> {
+        let yaml = r#"
+            sandboxes:
+              api:
+                image: "node:20"
+                shell: "/bin/sh"
+                ports:
+                  - "8080"
+                readiness:
+                  http:
+                    port: 8080
+                    path: "/healthz"
+                  retries: 5
+              web:
+                image: "nginx:latest"
+                shell: "/bin/sh"
+                ports:
+                  - "8081:80"
+                readiness:
+                  http:
+                    port: 80
+        "#;
+
+        let config: Monocore = serde_yaml::from_str(yaml)?;
+        config.validate()?;
+
+        let readiness = config.sandboxes["api"].readiness.as_ref().unwrap();
+        assert_eq!(
+            readiness.http,
+            Some(HttpProbe {
+                port: 8080,
+                path: "/healthz".to_string(),
+            })
+        );
+        assert_eq!(readiness.get_retries(), 5);
+
+        // The path defaults to the root
+        let readiness = config.sandboxes["web"].readiness.as_ref().unwrap();
+        assert_eq!(readiness.http.as_ref().unwrap().path, "/");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_monocore_config_readiness_invalid_probes() -> anyhow::Result<()> {
+        let yaml = r#"
+            sandboxes:
+              empty:
+                image: "alpine:latest"
+                shell: "/bin/sh"
+                readiness:
+                  interval: 1
+              both:
+                image: "alpine:latest"
+                shell: "/bin/sh"
+                ports:
+                  - "80"
+                readiness:
+                  tcp:
+                    port: 80
+                  exec:
+                    command: ["true"]
+              no_command:
+                image: "alpine:latest"
+                shell: "/bin/sh"
+                readiness:
+                  exec:
+                    command: []
+              unexposed:
+                image: "alpine:latest"
+                shell: "/bin/sh"
+                ports:
+                  - "5353:5353"
+                readiness:
+                  tcp:
+                    port: 53
+                  retries: 0
+              relative_path:
+                image: "alpine:latest"
+                shell: "/bin/sh"
+                ports:
+                  - "80"
+                readiness:
+                  http:
+                    port: 80
+                    path: "healthz"
+                  timeout: 0
+        "#;
+
+        let config: Monocore = serde_yaml::from_str(yaml)?;
+        let Err(MonocoreError::ConfigValidationErrors(errors)) = config.validate() else {
+            panic!("expected validation errors");
+        };
+
+        assert_eq!(
+            errors,
+            vec![
+                "exec readiness probe of sandbox 'no_command' has no command",
+                "http readiness probe of sandbox 'relative_path' has a relative path: healthz",
+                "readiness probe of sandbox 'both' must set only one of `tcp`, `exec` or `http`",
+                "readiness probe of sandbox 'empty' must set one of `tcp`, `exec` or `http`",
+                "readiness probe of sandbox 'relative_path' must have a timeout of at least 1 second",
+                "readiness probe of sandbox 'unexposed' must have at least 1 retry",
+                "tcp readiness probe of sandbox 'unexposed' checks unexposed guest port 53",
+            ]
+        );
+
+        // A probe missing a required field doesn't parse
+        let yaml = r#"
+            sandboxes:
+              api:
+                image: "alpine:latest"
+                shell: "/bin/sh"
+                readiness:
+                  http:
+                    path: "/healthz"
+        "#;
+        assert!(serde_yaml::from_str::<Monocore>(yaml).is_err());
+
+        Ok(())
+    }
 }
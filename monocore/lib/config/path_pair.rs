@@ -16,6 +16,10 @@ use crate::MonocoreError;
 /// - `host:guest` - Maps a host path to a different guest path (e.g., "/host/path:/container/path")
 /// - `path` or `path:path` - Maps the same path on both host and guest (e.g., "/data" or "/data:/data")
 ///
+/// The `host:guest` format can end with a mount option, either `ro` to mount the path read-only or
+/// `rw` to mount it read-write (e.g., "/host/data:/container/data:ro"). Paths are mounted
+/// read-write by default.
+///
 /// ## Examples
 ///
 /// Creating path pairs:
@@ -35,6 +39,10 @@ use crate::MonocoreError;
 /// // Parse from string
 /// let from_str = "/host/data:/container/data".parse::<PathPair>().unwrap();
 /// assert_eq!(from_str, distinct_paths);
+///
+/// // Read-only mount
+/// let read_only = "/host/data:/container/data:ro".parse::<PathPair>().unwrap();
+/// assert!(read_only.is_read_only());
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PathPair {
@@ -45,9 +53,30 @@ pub enum PathPair {
 
         /// The guest path.
         guest: Utf8UnixPathBuf,
+
+        /// Whether the path is mounted read-only or read-write.
+        mode: MountMode,
     },
     /// The guest path and host path are the same.
-    Same(Utf8UnixPathBuf),
+    Same(Utf8UnixPathBuf, MountMode),
+}
+
+/// Whether a path is mounted read-only or read-write.
+///
+/// Read-only mounts are enforced on both sides of the virtio-fs share. The guest mounts the path
+/// with the `ro` option, and on Linux the MicroVm process bind mounts the host directory
+/// read-only over itself in a private mount namespace before it starts, so the virtio-fs device
+/// cannot write to it either. This needs `CAP_SYS_ADMIN`, and a MicroVm with read-only mounts
+/// fails to start without it or on other platforms. Mounts nested below the host directory are
+/// not made read-only.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MountMode {
+    /// The guest can read and write the path. This is the default.
+    #[default]
+    ReadWrite,
+
+    /// The guest can only read the path.
+    ReadOnly,
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -55,29 +84,53 @@ pub enum PathPair {
 //--------------------------------------------------------------------------------------------------
 
 impl PathPair {
-    /// Creates a new `PathPair` with the same host and guest path.
+    /// Creates a new read-write `PathPair` with the same host and guest path.
     pub fn with_same(path: Utf8UnixPathBuf) -> Self {
-        Self::Same(path)
+        Self::Same(path, MountMode::ReadWrite)
     }
 
-    /// Creates a new `PathPair` with distinct host and guest paths.
+    /// Creates a new read-write `PathPair` with distinct host and guest paths.
     pub fn with_distinct(host: Utf8UnixPathBuf, guest: Utf8UnixPathBuf) -> Self {
-        Self::Distinct { host, guest }
+        Self::Distinct {
+            host,
+            guest,
+            mode: MountMode::ReadWrite,
+        }
+    }
+
+    /// Returns the path pair with its mount mode set to `mode`.
+    pub fn with_mode(self, mode: MountMode) -> Self {
+        match self {
+            Self::Distinct { host, guest, .. } => Self::Distinct { host, guest, mode },
+            Self::Same(path, _) => Self::Same(path, mode),
+        }
     }
 
     /// Returns the host path.
     pub fn get_host(&self) -> &Utf8UnixPathBuf {
         match self {
-            Self::Distinct { host, .. } | Self::Same(host) => host,
+            Self::Distinct { host, .. } | Self::Same(host, _) => host,
         }
     }
 
     /// Returns the guest path.
     pub fn get_guest(&self) -> &Utf8UnixPathBuf {
         match self {
-            Self::Distinct { guest, .. } | Self::Same(guest) => guest,
+            Self::Distinct { guest, .. } | Self::Same(guest, _) => guest,
+        }
+    }
+
+    /// Returns whether the path is mounted read-only or read-write.
+    pub fn get_mode(&self) -> MountMode {
+        match self {
+            Self::Distinct { mode, .. } | Self::Same(_, mode) => *mode,
         }
     }
+
+    /// Returns whether the path is mounted read-only.
+    pub fn is_read_only(&self) -> bool {
+        self.get_mode() == MountMode::ReadOnly
+    }
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -93,33 +146,69 @@ impl FromStr for PathPair {
         }
 
         if s.contains(':') {
-            let (host, guest) = s.split_once(':').unwrap();
+            let mut fields = s.splitn(3, ':');
+            let host = fields.next().unwrap();
+            let guest = fields.next().unwrap();
+            let mode = match fields.next() {
+                Some(option) => option.parse().map_err(|_| {
+                    MonocoreError::InvalidPathPair(format!(
+                        "{s}: unknown mount option '{option}', expected 'ro' or 'rw'"
+                    ))
+                })?,
+                None => MountMode::ReadWrite,
+            };
+
             if guest.is_empty() || host.is_empty() {
                 return Err(MonocoreError::InvalidPathPair(s.to_string()));
             }
 
             if guest == host {
-                return Ok(Self::Same(host.into()));
+                return Ok(Self::Same(host.into(), mode));
             } else {
                 return Ok(Self::Distinct {
                     host: host.into(),
                     guest: guest.into(),
+                    mode,
                 });
             }
         }
 
-        Ok(Self::Same(s.into()))
+        Ok(Self::Same(s.into(), MountMode::ReadWrite))
     }
 }
 
 impl fmt::Display for PathPair {
-    /// Formats the path pair following the format "host:guest".
+    /// Formats the path pair following the format "host:guest", with a ":ro" suffix for read-only
+    /// mounts.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.get_host(), self.get_guest())?;
+        if self.is_read_only() {
+            write!(f, ":{}", self.get_mode())?;
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for MountMode {
+    type Err = MonocoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rw" => Ok(Self::ReadWrite),
+            "ro" => Ok(Self::ReadOnly),
+            _ => Err(MonocoreError::InvalidPathPair(format!(
+                "unknown mount option: {s}"
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for MountMode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Distinct { host, guest } => {
-                write!(f, "{}:{}", host, guest)
-            }
-            Self::Same(path) => write!(f, "{}:{}", path, path),
+            Self::ReadWrite => write!(f, "rw"),
+            Self::ReadOnly => write!(f, "ro"),
         }
     }
 }
@@ -156,11 +245,11 @@ mod tests {
         // Test same paths
         assert_eq!(
             "/data".parse::<PathPair>().unwrap(),
-            PathPair::Same("/data".into())
+            PathPair::Same("/data".into(), MountMode::ReadWrite)
         );
         assert_eq!(
             "/data:/data".parse::<PathPair>().unwrap(),
-            PathPair::Same("/data".into())
+            PathPair::Same("/data".into(), MountMode::ReadWrite)
         );
 
         // Test distinct paths (host:guest format)
@@ -168,7 +257,8 @@ mod tests {
             "/host/data:/container/data".parse::<PathPair>().unwrap(),
             PathPair::Distinct {
                 host: "/host/data".into(),
-                guest: "/container/data".into()
+                guest: "/container/data".into(),
+                mode: MountMode::ReadWrite
             }
         );
 
@@ -182,13 +272,17 @@ mod tests {
     #[test]
     fn test_path_pair_display() {
         // Test same paths
-        assert_eq!(PathPair::Same("/data".into()).to_string(), "/data:/data");
+        assert_eq!(
+            PathPair::Same("/data".into(), MountMode::ReadWrite).to_string(),
+            "/data:/data"
+        );
 
         // Test distinct paths (host:guest format)
         assert_eq!(
             PathPair::Distinct {
                 host: "/host/data".into(),
-                guest: "/container/data".into()
+                guest: "/container/data".into(),
+                mode: MountMode::ReadWrite
             }
             .to_string(),
             "/host/data:/container/data"
@@ -198,7 +292,7 @@ mod tests {
     #[test]
     fn test_path_pair_getters() {
         // Test same paths
-        let same = PathPair::Same("/data".into());
+        let same = PathPair::Same("/data".into(), MountMode::ReadWrite);
         assert_eq!(same.get_host().as_str(), "/data");
         assert_eq!(same.get_guest().as_str(), "/data");
 
@@ -206,6 +300,7 @@ mod tests {
         let distinct = PathPair::Distinct {
             host: "/host/data".into(),
             guest: "/container/data".into(),
+            mode: MountMode::ReadWrite,
         };
         assert_eq!(distinct.get_host().as_str(), "/host/data");
         assert_eq!(distinct.get_guest().as_str(), "/container/data");
@@ -215,14 +310,71 @@ mod tests {
     fn test_path_pair_constructors() {
         assert_eq!(
             PathPair::with_same("/data".into()),
-            PathPair::Same("/data".into())
+            PathPair::Same("/data".into(), MountMode::ReadWrite)
         );
         assert_eq!(
             PathPair::with_distinct("/host/data".into(), "/container/data".into()),
             PathPair::Distinct {
                 host: "/host/data".into(),
-                guest: "/container/data".into()
+                guest: "/container/data".into(),
+                mode: MountMode::ReadWrite
+            }
+        );
+    }
+
+    #[test]
+    fn test_path_pair_mount_options() -> anyhow::Result<()> {
+        // Read-only
+        let read_only = "/host/data:/container/data:ro".parse::<PathPair>()?;
+        assert_eq!(
+            read_only,
+            PathPair::Distinct {
+                host: "/host/data".into(),
+                guest: "/container/data".into(),
+                mode: MountMode::ReadOnly
             }
         );
+        assert!(read_only.is_read_only());
+        assert_eq!(read_only.to_string(), "/host/data:/container/data:ro");
+        assert_eq!(read_only.to_string().parse::<PathPair>()?, read_only);
+        assert_eq!(
+            "/data:/data:ro".parse::<PathPair>()?,
+            PathPair::Same("/data".into(), MountMode::ReadOnly)
+        );
+
+        // Explicit read-write is the same as the default
+        let read_write = "/host/data:/container/data:rw".parse::<PathPair>()?;
+        assert_eq!(
+            read_write,
+            PathPair::with_distinct("/host/data".into(), "/container/data".into())
+        );
+        assert!(!read_write.is_read_only());
+        assert_eq!(read_write.to_string(), "/host/data:/container/data");
+
+        // Legacy two-field form is read-write
+        let legacy = "/host/data:/container/data".parse::<PathPair>()?;
+        assert_eq!(legacy.get_mode(), MountMode::ReadWrite);
+
+        assert_eq!(
+            PathPair::with_same("/data".into()).with_mode(MountMode::ReadOnly),
+            PathPair::Same("/data".into(), MountMode::ReadOnly)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_path_pair_unknown_mount_option() {
+        let result = "/host/data:/container/data:rox".parse::<PathPair>();
+        assert!(matches!(
+            result,
+            Err(MonocoreError::InvalidPathPair(ref message))
+                if message.contains("unknown mount option 'rox'")
+        ));
+        assert!("/host/data:/container/data:".parse::<PathPair>().is_err());
+        assert!("/host/data:/container/data:ro:rw"
+            .parse::<PathPair>()
+            .is_err());
+        assert!("/host/data::ro".parse::<PathPair>().is_err());
     }
 }
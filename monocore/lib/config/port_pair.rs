@@ -1,8 +1,8 @@
-use std::{fmt, str::FromStr};
+use std::{fmt, ops::RangeInclusive, str::FromStr};
 
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
-use crate::MonocoreError;
+use crate::{MonocoreError, MonocoreResult};
 
 //--------------------------------------------------------------------------------------------------
 // Types
@@ -15,11 +15,17 @@ use crate::MonocoreError;
 /// - `host:guest` - Maps the host port to a different guest port (e.g., "8080:80")
 /// - `port` or `port:port` - Maps the same port number on both host and guest (e.g., "8080" or "8080:8080")
 ///
+/// Either format can end with a `/tcp` or `/udp` protocol suffix (e.g., "5353:53/udp"). The
+/// protocol defaults to TCP.
+///
+/// A range of ports like "8000-8010:9000-9010" is expanded into one port pair per port with
+/// [`PortPair::parse_range`].
+///
 /// ## Examples
 ///
 /// Creating port pairs:
 /// ```
-/// use monocore::config::PortPair;
+/// use monocore::config::{PortPair, Protocol};
 ///
 /// // Same port on host and guest (8080:8080)
 /// let same_port = PortPair::with_same(8080);
@@ -30,6 +36,14 @@ use crate::MonocoreError;
 /// // Parse from string
 /// let from_str = "8080:80".parse::<PortPair>().unwrap();
 /// assert_eq!(from_str, distinct_ports);
+///
+/// // Explicit TCP port pair
+/// let tcp = "8080:80/tcp".parse::<PortPair>().unwrap();
+/// assert_eq!(tcp.get_protocol(), Protocol::Tcp);
+///
+/// // UDP port pair
+/// let udp = "5353:53/udp".parse::<PortPair>().unwrap();
+/// assert_eq!(udp.get_protocol(), Protocol::Udp);
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum PortPair {
@@ -40,10 +54,24 @@ pub enum PortPair {
 
         /// The guest port.
         guest: u16,
+
+        /// The transport protocol of the mapping.
+        protocol: Protocol,
     },
 
     /// The guest port and the host port are the same.
-    Same(u16),
+    Same(u16, Protocol),
+}
+
+/// The transport protocol of a port mapping.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Protocol {
+    /// TCP, the default.
+    #[default]
+    Tcp,
+
+    /// UDP.
+    Udp,
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -51,28 +79,114 @@ pub enum PortPair {
 //--------------------------------------------------------------------------------------------------
 
 impl PortPair {
-    /// Creates a new `PortPair` with the same guest and host port.
+    /// Creates a new TCP `PortPair` with the same guest and host port.
     pub fn with_same(port: u16) -> Self {
-        Self::Same(port)
+        Self::Same(port, Protocol::Tcp)
     }
 
-    /// Creates a new `PortPair` with distinct guest and host ports.
+    /// Creates a new TCP `PortPair` with distinct guest and host ports.
     pub fn with_distinct(host: u16, guest: u16) -> Self {
-        Self::Distinct { host, guest }
+        Self::Distinct {
+            host,
+            guest,
+            protocol: Protocol::Tcp,
+        }
+    }
+
+    /// Returns the port pair with its protocol set to `protocol`.
+    pub fn with_protocol(self, protocol: Protocol) -> Self {
+        match self {
+            Self::Distinct { host, guest, .. } => Self::Distinct {
+                host,
+                guest,
+                protocol,
+            },
+            Self::Same(port, _) => Self::Same(port, protocol),
+        }
     }
 
     /// Returns the host port.
     pub fn get_host(&self) -> u16 {
         match self {
-            Self::Distinct { host, .. } | Self::Same(host) => *host,
+            Self::Distinct { host, .. } | Self::Same(host, _) => *host,
         }
     }
 
     /// Returns the guest port.
     pub fn get_guest(&self) -> u16 {
         match self {
-            Self::Distinct { guest, .. } | Self::Same(guest) => *guest,
+            Self::Distinct { guest, .. } | Self::Same(guest, _) => *guest,
+        }
+    }
+
+    /// Returns the transport protocol.
+    pub fn get_protocol(&self) -> Protocol {
+        match self {
+            Self::Distinct { protocol, .. } | Self::Same(_, protocol) => *protocol,
+        }
+    }
+
+    /// Parses a port pair that may map a range of ports, returning one port pair per port.
+    ///
+    /// Ranges are written `start-end` on either side, e.g. "8000-8010:9000-9010" maps host port
+    /// 8000 to guest port 9000, 8001 to 9001, and so on. A single port on one side is a range of
+    /// one port.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use monocore::config::PortPair;
+    ///
+    /// let pairs = PortPair::parse_range("8000-8002:9000-9002").unwrap();
+    /// assert_eq!(
+    ///     pairs,
+    ///     [
+    ///         PortPair::with_distinct(8000, 9000),
+    ///         PortPair::with_distinct(8001, 9001),
+    ///         PortPair::with_distinct(8002, 9002),
+    ///     ]
+    /// );
+    /// ```
+    ///
+    /// ## Errors
+    ///
+    /// Returns `MonocoreError::InvalidPortPair` if `s` is malformed, a range ends before it
+    /// starts, or the host and guest ranges span a different number of ports.
+    pub fn parse_range(s: &str) -> MonocoreResult<Vec<Self>> {
+        let (ports, protocol) = match s.rsplit_once('/') {
+            Some((ports, protocol)) => (ports, protocol.parse()?),
+            None => (s, Protocol::Tcp),
+        };
+
+        let (host, guest) = match ports.split_once(':') {
+            Some((host, guest)) => (parse_port_range(host, s)?, parse_port_range(guest, s)?),
+            None => (parse_port_range(ports, s)?, parse_port_range(ports, s)?),
+        };
+
+        if host.len() != guest.len() {
+            return Err(MonocoreError::InvalidPortPair(format!(
+                "{s}: host range spans {} ports but guest range spans {}",
+                host.len(),
+                guest.len()
+            )));
         }
+
+        let pairs = host
+            .zip(guest)
+            .map(|(host, guest)| {
+                if host == guest {
+                    Self::Same(host, protocol)
+                } else {
+                    Self::Distinct {
+                        host,
+                        guest,
+                        protocol,
+                    }
+                }
+            })
+            .collect();
+
+        Ok(pairs)
     }
 }
 
@@ -84,48 +198,27 @@ impl FromStr for PortPair {
     type Err = MonocoreError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.is_empty() {
-            return Err(MonocoreError::InvalidPortPair(s.to_string()));
+        let mut pairs = Self::parse_range(s)?;
+        if pairs.len() != 1 {
+            return Err(MonocoreError::InvalidPortPair(format!(
+                "{s}: expected a single port pair, but got a range of {}",
+                pairs.len()
+            )));
         }
 
-        if s.contains(':') {
-            let (host, guest) = s.split_once(':').unwrap();
-            if guest.is_empty() || host.is_empty() {
-                return Err(MonocoreError::InvalidPortPair(s.to_string()));
-            }
-
-            if guest == host {
-                return Ok(Self::Same(
-                    host.parse()
-                        .map_err(|_| MonocoreError::InvalidPortPair(s.to_string()))?,
-                ));
-            } else {
-                return Ok(Self::Distinct {
-                    host: host
-                        .parse()
-                        .map_err(|_| MonocoreError::InvalidPortPair(s.to_string()))?,
-                    guest: guest
-                        .parse()
-                        .map_err(|_| MonocoreError::InvalidPortPair(s.to_string()))?,
-                });
-            }
-        }
-
-        Ok(Self::Same(s.parse().map_err(|_| {
-            MonocoreError::InvalidPortPair(s.to_string())
-        })?))
+        Ok(pairs.remove(0))
     }
 }
 
 impl fmt::Display for PortPair {
-    /// Formats the port pair following the format "host:guest".
+    /// Formats the port pair following the format "host:guest", with a "/udp" suffix for UDP.
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Distinct { host, guest } => {
-                write!(f, "{}:{}", host, guest)
-            }
-            Self::Same(port) => write!(f, "{}:{}", port, port),
+        write!(f, "{}:{}", self.get_host(), self.get_guest())?;
+        if self.get_protocol() != Protocol::Tcp {
+            write!(f, "/{}", self.get_protocol())?;
         }
+
+        Ok(())
     }
 }
 
@@ -148,6 +241,59 @@ impl<'de> Deserialize<'de> for PortPair {
     }
 }
 
+impl FromStr for Protocol {
+    type Err = MonocoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "tcp" => Ok(Self::Tcp),
+            "udp" => Ok(Self::Udp),
+            _ => Err(MonocoreError::InvalidPortPair(format!(
+                "unsupported protocol: {s}"
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp => write!(f, "tcp"),
+            Self::Udp => write!(f, "udp"),
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Deserializes a list of port pairs, expanding port ranges into one port pair per port.
+pub(crate) fn deserialize_port_pairs<'de, D>(deserializer: D) -> Result<Vec<PortPair>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut pairs = Vec::new();
+    for s in Vec::<String>::deserialize(deserializer)? {
+        pairs.extend(PortPair::parse_range(&s).map_err(serde::de::Error::custom)?);
+    }
+
+    Ok(pairs)
+}
+
+/// Parses a port or a `start-end` range of ports. `pair` is the whole port pair, for errors.
+fn parse_port_range(s: &str, pair: &str) -> MonocoreResult<RangeInclusive<u16>> {
+    let invalid = || MonocoreError::InvalidPortPair(pair.to_string());
+    let (start, end) = s.split_once('-').unwrap_or((s, s));
+    let start: u16 = start.parse().map_err(|_| invalid())?;
+    let end: u16 = end.parse().map_err(|_| invalid())?;
+    if start > end {
+        return Err(invalid());
+    }
+
+    Ok(start..=end)
+}
+
 //--------------------------------------------------------------------------------------------------
 // Tests
 //--------------------------------------------------------------------------------------------------
@@ -159,10 +305,13 @@ mod tests {
     #[test]
     fn test_port_pair_from_str() {
         // Test same ports
-        assert_eq!("8080".parse::<PortPair>().unwrap(), PortPair::Same(8080));
+        assert_eq!(
+            "8080".parse::<PortPair>().unwrap(),
+            PortPair::Same(8080, Protocol::Tcp)
+        );
         assert_eq!(
             "8080:8080".parse::<PortPair>().unwrap(),
-            PortPair::Same(8080)
+            PortPair::Same(8080, Protocol::Tcp)
         );
 
         // Test distinct ports (host:guest format)
@@ -170,7 +319,8 @@ mod tests {
             "8080:80".parse::<PortPair>().unwrap(),
             PortPair::Distinct {
                 host: 8080,
-                guest: 80
+                guest: 80,
+                protocol: Protocol::Tcp
             }
         );
 
@@ -183,16 +333,47 @@ mod tests {
         assert!("80:invalid".parse::<PortPair>().is_err());
     }
 
+    #[test]
+    fn test_port_pair_protocol() {
+        assert_eq!(
+            "8080:80/tcp".parse::<PortPair>().unwrap(),
+            PortPair::with_distinct(8080, 80)
+        );
+        assert_eq!(
+            PortPair::parse_range("8000-8001/TCP").unwrap(),
+            [PortPair::with_same(8000), PortPair::with_same(8001)]
+        );
+
+        assert_eq!(
+            "5353:53/udp".parse::<PortPair>().unwrap(),
+            PortPair::with_distinct(5353, 53).with_protocol(Protocol::Udp)
+        );
+        assert_eq!(
+            PortPair::parse_range("8000-8001/UDP").unwrap(),
+            [
+                PortPair::with_same(8000).with_protocol(Protocol::Udp),
+                PortPair::with_same(8001).with_protocol(Protocol::Udp)
+            ]
+        );
+        assert!("8080:80/sctp".parse::<PortPair>().is_err());
+
+        // The protocol survives a round trip through the string form
+        let udp = PortPair::with_distinct(5353, 53).with_protocol(Protocol::Udp);
+        assert_eq!(udp.to_string(), "5353:53/udp");
+        assert_eq!(udp.to_string().parse::<PortPair>().unwrap(), udp);
+    }
+
     #[test]
     fn test_port_pair_display() {
         // Test same ports
-        assert_eq!(PortPair::Same(8080).to_string(), "8080:8080");
+        assert_eq!(PortPair::Same(8080, Protocol::Tcp).to_string(), "8080:8080");
 
         // Test distinct ports (host:guest format)
         assert_eq!(
             PortPair::Distinct {
                 host: 8080,
-                guest: 80
+                guest: 80,
+                protocol: Protocol::Tcp
             }
             .to_string(),
             "8080:80"
@@ -202,7 +383,7 @@ mod tests {
     #[test]
     fn test_port_pair_getters() {
         // Test same ports
-        let same = PortPair::Same(8080);
+        let same = PortPair::Same(8080, Protocol::Tcp);
         assert_eq!(same.get_host(), 8080);
         assert_eq!(same.get_guest(), 8080);
 
@@ -210,6 +391,7 @@ mod tests {
         let distinct = PortPair::Distinct {
             host: 8080,
             guest: 80,
+            protocol: Protocol::Tcp,
         };
         assert_eq!(distinct.get_host(), 8080);
         assert_eq!(distinct.get_guest(), 80);
@@ -217,12 +399,16 @@ mod tests {
 
     #[test]
     fn test_port_pair_constructors() {
-        assert_eq!(PortPair::with_same(8080), PortPair::Same(8080));
+        assert_eq!(
+            PortPair::with_same(8080),
+            PortPair::Same(8080, Protocol::Tcp)
+        );
         assert_eq!(
             PortPair::with_distinct(8080, 80),
             PortPair::Distinct {
                 host: 8080,
-                guest: 80
+                guest: 80,
+                protocol: Protocol::Tcp
             }
         );
     }
@@ -0,0 +1,321 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use typed_path::Utf8UnixPathBuf;
+
+use crate::MonocoreError;
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The Linux capability names, without the `CAP_` prefix, indexed by capability number.
+pub const CAPABILITY_NAMES: &[&str] = &[
+    "CHOWN",
+    "DAC_OVERRIDE",
+    "DAC_READ_SEARCH",
+    "FOWNER",
+    "FSETID",
+    "KILL",
+    "SETGID",
+    "SETUID",
+    "SETPCAP",
+    "LINUX_IMMUTABLE",
+    "NET_BIND_SERVICE",
+    "NET_BROADCAST",
+    "NET_ADMIN",
+    "NET_RAW",
+    "IPC_LOCK",
+    "IPC_OWNER",
+    "SYS_MODULE",
+    "SYS_RAWIO",
+    "SYS_CHROOT",
+    "SYS_PTRACE",
+    "SYS_PACCT",
+    "SYS_ADMIN",
+    "SYS_BOOT",
+    "SYS_NICE",
+    "SYS_RESOURCE",
+    "SYS_TIME",
+    "SYS_TTY_CONFIG",
+    "MKNOD",
+    "LEASE",
+    "AUDIT_WRITE",
+    "AUDIT_CONTROL",
+    "SETFCAP",
+    "MAC_OVERRIDE",
+    "MAC_ADMIN",
+    "SYSLOG",
+    "WAKE_ALARM",
+    "BLOCK_SUSPEND",
+    "AUDIT_READ",
+    "PERFMON",
+    "BPF",
+    "CHECKPOINT_RESTORE",
+];
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// The seccomp profile to harden a sandbox's virtual machine monitor process with.
+///
+/// The profile filters the system calls the monitor makes on the host. The guest's own system
+/// calls are handled by the guest kernel and are not filtered.
+///
+/// ## Format
+/// A profile is either one of the named profiles or a path to a profile file:
+/// - `default` - Blocks syscalls the monitor never needs, like loading kernel modules or
+///   rebooting the host.
+/// - `unconfined` - No seccomp filtering.
+/// - `./path/to/profile.json` - A [seccompiler][seccompiler] JSON filter file. The file must define a
+///   filter named `microvm`.
+///
+/// Anything containing a `/` or a `.` is treated as a path, everything else must be a named
+/// profile.
+///
+/// ## Examples
+///
+/// ```
+/// use monocore::config::SeccompProfile;
+///
+/// assert_eq!("default".parse::<SeccompProfile>().unwrap(), SeccompProfile::Default);
+/// assert!(matches!("./seccomp.json".parse::<SeccompProfile>().unwrap(), SeccompProfile::Path(_)));
+/// assert!("strict".parse::<SeccompProfile>().is_err());
+/// ```
+///
+/// [seccompiler]: https://github.com/rust-vmm/seccompiler
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SeccompProfile {
+    /// The built-in default profile.
+    Default,
+
+    /// No seccomp filtering.
+    Unconfined,
+
+    /// A profile loaded from a file.
+    Path(Utf8UnixPathBuf),
+}
+
+/// A Linux capability, or `ALL` to refer to every capability.
+///
+/// Capabilities can be written with or without the `CAP_` prefix and in any case, so `NET_ADMIN`,
+/// `CAP_NET_ADMIN` and `net_admin` are all the same capability.
+///
+/// ## Examples
+///
+/// ```
+/// use monocore::config::Capability;
+///
+/// let cap = "cap_net_admin".parse::<Capability>().unwrap();
+/// assert_eq!(cap.to_string(), "NET_ADMIN");
+/// assert_eq!(cap.get_number(), Some(12));
+/// assert!("NET_MAGIC".parse::<Capability>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// Every capability.
+    All,
+
+    /// A single capability, identified by its number.
+    Single(u8),
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl Capability {
+    /// Returns the capability number, or `None` for `ALL`.
+    pub fn get_number(&self) -> Option<u8> {
+        match self {
+            Self::All => None,
+            Self::Single(number) => Some(*number),
+        }
+    }
+
+    /// Returns the numbers of the capabilities this refers to.
+    pub fn get_numbers(&self) -> Vec<u8> {
+        match self {
+            Self::All => (0..CAPABILITY_NAMES.len() as u8).collect(),
+            Self::Single(number) => vec![*number],
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Trait Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl FromStr for SeccompProfile {
+    type Err = MonocoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains('/') || s.contains('.') {
+            return Ok(Self::Path(Utf8UnixPathBuf::from(s)));
+        }
+
+        match s.to_lowercase().as_str() {
+            "default" => Ok(Self::Default),
+            "unconfined" => Ok(Self::Unconfined),
+            _ => Err(MonocoreError::InvalidSeccompProfile(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for SeccompProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "default"),
+            Self::Unconfined => write!(f, "unconfined"),
+            Self::Path(path) => write!(f, "{}", path),
+        }
+    }
+}
+
+impl Serialize for SeccompProfile {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for SeccompProfile {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Self::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+impl FromStr for Capability {
+    type Err = MonocoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let upper = s.to_uppercase();
+        let name = upper.strip_prefix("CAP_").unwrap_or(&upper);
+        if name == "ALL" {
+            return Ok(Self::All);
+        }
+
+        CAPABILITY_NAMES
+            .iter()
+            .position(|n| *n == name)
+            .map(|number| Self::Single(number as u8))
+            .ok_or_else(|| MonocoreError::InvalidCapability(s.to_string()))
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::All => write!(f, "ALL"),
+            Self::Single(number) => write!(f, "{}", CAPABILITY_NAMES[*number as usize]),
+        }
+    }
+}
+
+impl Serialize for Capability {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Capability {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        Self::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seccomp_profile_parsing() -> anyhow::Result<()> {
+        assert_eq!(
+            "default".parse::<SeccompProfile>()?,
+            SeccompProfile::Default
+        );
+        assert_eq!(
+            "Default".parse::<SeccompProfile>()?,
+            SeccompProfile::Default
+        );
+        assert_eq!(
+            "unconfined".parse::<SeccompProfile>()?,
+            SeccompProfile::Unconfined
+        );
+        assert_eq!(
+            "./profiles/seccomp.json".parse::<SeccompProfile>()?,
+            SeccompProfile::Path("./profiles/seccomp.json".into())
+        );
+        assert_eq!(
+            "/etc/monocore/seccomp.json".parse::<SeccompProfile>()?,
+            SeccompProfile::Path("/etc/monocore/seccomp.json".into())
+        );
+
+        // Round trip
+        for profile in ["default", "unconfined", "/etc/monocore/seccomp.json"] {
+            assert_eq!(profile.parse::<SeccompProfile>()?.to_string(), profile);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_seccomp_profile_unknown_named_profile() {
+        for profile in ["strict", "runtime-default", ""] {
+            assert!(matches!(
+                profile.parse::<SeccompProfile>(),
+                Err(MonocoreError::InvalidSeccompProfile(p)) if p == profile
+            ));
+        }
+
+        let result = serde_yaml::from_str::<SeccompProfile>("strict");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_capability_parsing() -> anyhow::Result<()> {
+        assert_eq!("CHOWN".parse::<Capability>()?, Capability::Single(0));
+        assert_eq!(
+            "CAP_NET_ADMIN".parse::<Capability>()?,
+            Capability::Single(12)
+        );
+        assert_eq!("net_admin".parse::<Capability>()?, Capability::Single(12));
+        assert_eq!("all".parse::<Capability>()?, Capability::All);
+        assert_eq!(
+            "CHECKPOINT_RESTORE".parse::<Capability>()?.get_number(),
+            Some(40)
+        );
+
+        assert_eq!(
+            "cap_sys_admin".parse::<Capability>()?.to_string(),
+            "SYS_ADMIN"
+        );
+        assert_eq!(Capability::All.get_numbers().len(), CAPABILITY_NAMES.len());
+
+        assert!(matches!(
+            "NET_MAGIC".parse::<Capability>(),
+            Err(MonocoreError::InvalidCapability(_))
+        ));
+        assert!("".parse::<Capability>().is_err());
+
+        Ok(())
+    }
+}
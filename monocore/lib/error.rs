@@ -63,6 +63,18 @@ pub enum MonocoreError {
     #[error("image layer download failed: {0}")]
     ImageLayerDownloadFailed(String),
 
+    /// An error that occurred when a cached image blob doesn't match its digest.
+    #[error("corrupted cached blob: {0}")]
+    CorruptedBlob(String),
+
+    /// An error that occurred when a blob fetched from a registry doesn't match its digest.
+    #[error("blob does not match its digest: {0}")]
+    BlobDigestMismatch(String),
+
+    /// An error that occurred when a blob needed in offline mode is not in the cache.
+    #[error("not in the image cache, and registry access is disabled in offline mode: {0}")]
+    BlobNotCached(String),
+
     /// An error that occurred when an invalid path pair was used.
     #[error("invalid path pair: {0}")]
     InvalidPathPair(String),
@@ -71,10 +83,22 @@ pub enum MonocoreError {
     #[error("invalid port pair: {0}")]
     InvalidPortPair(String),
 
+    /// An error that occurred when more than one sandbox maps the same host port.
+    #[error("host port {0} is mapped by more than one sandbox: {1}")]
+    PortConflict(u16, String),
+
+    /// An error that occurred when a sandbox maps a host port that is already in use.
+    #[error("host port {0} mapped by sandbox '{1}' is already in use")]
+    HostPortInUse(u16, String),
+
     /// An error that occurred when an invalid environment variable pair was used.
     #[error("invalid environment variable pair: {0}")]
     InvalidEnvPair(String),
 
+    /// An error that occurred when a line of an environment file is malformed.
+    #[error("invalid environment file entry on line {0}: {1}")]
+    InvalidEnvFileEntry(usize, String),
+
     /// An error that occurred when an invalid MicroVm configuration was used.
     #[error("invalid MicroVm configuration: {0}")]
     InvalidMicroVMConfig(InvalidMicroVMConfigError),
@@ -231,6 +255,14 @@ pub enum MonocoreError {
     #[error("failed to parse configuration file: {0}")]
     ConfigParseError(String),
 
+    /// An error that occurred when a configuration value referenced an undefined variable
+    #[error("undefined variable in configuration: {0}")]
+    UndefinedConfigVariable(String),
+
+    /// An error that occurred when a configuration value contained a malformed variable reference
+    #[error("invalid variable interpolation in configuration: {0}")]
+    InvalidConfigInterpolation(String),
+
     /// An error that occurred when a log file was not found
     #[error("log not found: {0}")]
     LogNotFound(String),
@@ -278,6 +310,26 @@ pub enum MonocoreError {
     #[error("cannot find sandbox: '{0}' at '{1}'")]
     SandboxNotFoundInConfig(String, PathBuf),
 
+    /// An error that occurred when sandboxes depend on each other in a cycle
+    #[error("sandbox dependency cycle: {0}")]
+    SandboxDependencyCycle(String),
+
+    /// An error that occurred when a sandbox that must be running is not
+    #[error("sandbox '{0}' is not running")]
+    SandboxNotRunning(String),
+
+    /// An error that occurred when talking to the guest agent of a sandbox
+    #[error("guest agent error: {0}")]
+    GuestAgentError(String),
+
+    /// An error that occurred when a sandbox did not become ready in time
+    #[error("sandbox '{0}' did not become ready within {1:?}")]
+    SandboxReadyTimeout(String, std::time::Duration),
+
+    /// An error that occurred when a sandbox's readiness probe failed
+    #[error("readiness probe of sandbox '{0}' failed: {1}")]
+    ReadinessProbeFailed(String, String),
+
     /// An error that occurs when an invalid log level is used.
     #[error("invalid log level: {0}")]
     InvalidLogLevel(u8),
@@ -301,6 +353,18 @@ pub enum MonocoreError {
     /// An error that occurred when an invalid network scope was used.
     #[error("invalid network scope: {0}")]
     InvalidNetworkScope(String),
+
+    /// An error that occurred when an invalid or unknown seccomp profile was used.
+    #[error("invalid seccomp profile: {0} (expected `default`, `unconfined` or a path)")]
+    InvalidSeccompProfile(String),
+
+    /// An error that occurred when an unknown capability was used.
+    #[error("invalid capability: {0}")]
+    InvalidCapability(String),
+
+    /// An error that occurred when applying the seccomp profile or capabilities.
+    #[error("failed to apply security settings: {0}")]
+    SecuritySettingsError(String),
 }
 
 /// An error that occurred when an invalid MicroVm configuration was used.
@@ -329,6 +393,14 @@ pub enum InvalidMicroVMConfigError {
     /// An error that occurs when conflicting guest paths are detected.
     #[error("Conflicting guest paths: '{0}' and '{1}' overlap")]
     ConflictingGuestPaths(String, String),
+
+    /// The seccomp profile file does not exist.
+    #[error("seccomp profile does not exist: {0}")]
+    SeccompProfileDoesNotExist(String),
+
+    /// A resource limit's soft limit is greater than its hard limit.
+    #[error("resource limit soft value exceeds hard value: {0}")]
+    RLimitSoftExceedsHard(String),
 }
 
 /// An error that can represent any error.
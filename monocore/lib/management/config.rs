@@ -13,7 +13,7 @@ use tokio::fs;
 use typed_path::Utf8UnixPathBuf;
 
 use crate::{
-    config::{EnvPair, Monocore, PathSegment, PortPair, Sandbox, START_SCRIPT_NAME},
+    config::{self, EnvPair, Monocore, PathSegment, PortPair, Sandbox, START_SCRIPT_NAME},
     oci::Reference,
     utils::MONOCORE_CONFIG_FILENAME,
     MonocoreError, MonocoreResult,
@@ -115,7 +115,23 @@ pub async fn add(
     project_dir: Option<&Path>,
     config_file: Option<&str>,
 ) -> MonocoreResult<()> {
-    let (_, _, full_config_path) = resolve_config_paths(project_dir, config_file).await?;
+    let (canonical_project_dir, _, full_config_path) =
+        resolve_config_paths(project_dir, config_file).await?;
+
+    // Validate the environment variables and env file before changing the configuration. An env
+    // file that doesn't exist yet is only checked when the sandbox is loaded.
+    if let Component::Sandbox { envs, env_file, .. } = component {
+        for env in envs {
+            config::parse_env_arg(env)?;
+        }
+
+        if let Some(env_file) = env_file {
+            let env_file_path = canonical_project_dir.join(env_file.as_str());
+            if fs::try_exists(&env_file_path).await? {
+                config::parse_env_file(&fs::read_to_string(&env_file_path).await?)?;
+            }
+        }
+    }
 
     // Read the configuration file content
     let config_contents = fs::read_to_string(&full_config_path).await?;
@@ -408,6 +424,9 @@ pub async fn list(
 /// - Validating the config file path
 /// - Checking if the config file exists
 /// - Reading and parsing the config file
+/// - Expanding `${NAME}` references in sandbox environment variables and volumes, from the host
+///   environment or the sandbox's env file
+/// - Validating the configuration, such as the sandboxes' readiness probes
 ///
 /// ## Arguments
 ///
@@ -426,6 +445,8 @@ pub async fn list(
 /// - The config file does not exist
 /// - The config file cannot be read
 /// - The config file contains invalid YAML
+/// - A sandbox references an undefined variable or its env file cannot be read
+/// - The configuration is invalid, such as a sandbox having an incomplete readiness probe
 pub async fn load_config(
     project_dir: Option<&Path>,
     config_file: Option<&str>,
@@ -448,11 +469,43 @@ pub async fn load_config(
 
     // Read and parse the config file
     let config_contents = fs::read_to_string(&full_config_path).await?;
-    let config: Monocore = serde_yaml::from_str(&config_contents)?;
+    let mut config: Monocore = serde_yaml::from_str(&config_contents)?;
+    for sandbox in config.sandboxes.values_mut() {
+        interpolate_sandbox(sandbox, &canonical_project_dir).await?;
+    }
+    config.validate()?;
 
     Ok((config, canonical_project_dir, config_file.to_string()))
 }
 
+/// Expands variable references in a sandbox's environment variables and volumes, and merges its
+/// env file into its environment variables.
+///
+/// Variables are looked up in the host environment first, then in the sandbox's env file, if it
+/// has one. A relative env file path is resolved against the project directory. Env file values
+/// are used as is, and are overridden by the sandbox's own environment variables.
+async fn interpolate_sandbox(sandbox: &mut Sandbox, project_dir: &Path) -> MonocoreResult<()> {
+    let env_file_vars = match sandbox.get_env_file() {
+        Some(env_file) => {
+            let contents = fs::read_to_string(project_dir.join(env_file.as_str())).await?;
+            config::parse_env_file(&contents)?
+        }
+        None => Vec::new(),
+    };
+
+    sandbox.interpolate(|name| {
+        std::env::var(name).ok().or_else(|| {
+            env_file_vars
+                .iter()
+                .find(|var| var.get_name() == name)
+                .map(|var| var.get_value().clone())
+        })
+    })?;
+
+    sandbox.merge_env_file(env_file_vars);
+    Ok(())
+}
+
 /// Resolves the paths for a Monocore configuration.
 ///
 /// This function is similar to `load_config` but without actually loading the file.
@@ -655,23 +708,16 @@ pub async fn apply_image_defaults(
                     let mut additional_ports = Vec::new();
 
                     for port_key in exposed_ports_obj.keys() {
-                        // Port keys in OCI format are like "80/tcp"
-                        if let Some(container_port) = port_key.split('/').next() {
-                            if let Ok(port_num) = container_port.parse::<u16>() {
-                                // Create a port mapping from host port to container port
-                                // We'll use the same port on both sides
-                                let port_pair =
-                                    format!("{}:{}", port_num, port_num).parse::<PortPair>();
-                                if let Ok(port_pair) = port_pair {
-                                    // Only add if not already defined in sandbox config
-                                    let existing_ports = sandbox_config.get_ports();
-                                    if !existing_ports
-                                        .iter()
-                                        .any(|p| p.get_guest() == port_pair.get_guest())
-                                    {
-                                        additional_ports.push(port_pair);
-                                    }
-                                }
+                        // Port keys in OCI format are like "80/tcp", which maps the same port
+                        // on both sides
+                        if let Ok(port_pair) = port_key.parse::<PortPair>() {
+                            // Only add if not already defined in sandbox config
+                            let existing_ports = sandbox_config.get_ports();
+                            if !existing_ports.iter().any(|p| {
+                                p.get_guest() == port_pair.get_guest()
+                                    && p.get_protocol() == port_pair.get_protocol()
+                            }) {
+                                additional_ports.push(port_pair);
                             }
                         }
                     }
@@ -9,12 +9,16 @@ use std::path::Path;
 
 use chrono::{DateTime, NaiveDateTime, Utc};
 use oci_spec::image::{ImageConfiguration, ImageIndex, ImageManifest, MediaType, Platform};
-use sqlx::{migrate::Migrator, sqlite::SqlitePoolOptions, Pool, Row, Sqlite};
+use sqlx::{
+    migrate::Migrator,
+    sqlite::{SqlitePoolOptions, SqliteRow},
+    Pool, Row, Sqlite,
+};
 use tokio::fs;
 
 use crate::{
     models::{Config, Image, Index, Layer, Manifest, Sandbox},
-    runtime::SANDBOX_STATUS_RUNNING,
+    runtime::{SandboxUsage, SANDBOX_STATUS_RUNNING},
     MonocoreResult,
 };
 
@@ -111,6 +115,7 @@ pub(crate) async fn save_or_update_sandbox(
     name: &str,
     config_file: &str,
     config_last_modified: &DateTime<Utc>,
+    config_hash: Option<&str>,
     status: &str,
     supervisor_pid: u32,
     microvm_pid: u32,
@@ -123,12 +128,15 @@ pub(crate) async fn save_or_update_sandbox(
         name: name.to_string(),
         config_file: config_file.to_string(),
         config_last_modified: config_last_modified.clone(),
+        config_hash: config_hash.map(String::from),
         status: status.to_string(),
         supervisor_pid,
         microvm_pid,
         rootfs_paths: rootfs_paths.to_string(),
         group_id,
         group_ip,
+        started_at: Some(Utc::now()),
+        health: None,
         created_at: Utc::now(),
         modified_at: Utc::now(),
     };
@@ -138,18 +146,22 @@ pub(crate) async fn save_or_update_sandbox(
         r#"
         UPDATE sandboxes
         SET config_last_modified = ?,
+            config_hash = ?,
             status = ?,
             supervisor_pid = ?,
             microvm_pid = ?,
             rootfs_paths = ?,
             group_id = ?,
             group_ip = ?,
+            started_at = CURRENT_TIMESTAMP,
+            health = NULL,
             modified_at = CURRENT_TIMESTAMP
         WHERE name = ? AND config_file = ?
         RETURNING id
         "#,
     )
     .bind(&sandbox.config_last_modified.to_rfc3339())
+    .bind(&sandbox.config_hash)
     .bind(&sandbox.status)
     .bind(&sandbox.supervisor_pid)
     .bind(&sandbox.microvm_pid)
@@ -170,17 +182,18 @@ pub(crate) async fn save_or_update_sandbox(
         let record = sqlx::query(
             r#"
             INSERT INTO sandboxes (
-                name, config_file, config_last_modified,
+                name, config_file, config_last_modified, config_hash,
                 status, supervisor_pid, microvm_pid, rootfs_paths,
-                group_id, group_ip
+                group_id, group_ip, started_at
             )
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, CURRENT_TIMESTAMP)
             RETURNING id
             "#,
         )
         .bind(sandbox.name)
         .bind(sandbox.config_file)
         .bind(sandbox.config_last_modified.to_rfc3339())
+        .bind(sandbox.config_hash)
         .bind(sandbox.status)
         .bind(sandbox.supervisor_pid)
         .bind(sandbox.microvm_pid)
@@ -201,9 +214,9 @@ pub(crate) async fn get_sandbox(
 ) -> MonocoreResult<Option<Sandbox>> {
     let record = sqlx::query(
         r#"
-        SELECT id, name, config_file, config_last_modified, status,
+        SELECT id, name, config_file, config_last_modified, config_hash, status,
                supervisor_pid, microvm_pid, rootfs_paths,
-               group_id, group_ip, created_at, modified_at
+               group_id, group_ip, started_at, health, created_at, modified_at
         FROM sandboxes
         WHERE name = ? AND config_file = ?
         "#,
@@ -213,23 +226,7 @@ pub(crate) async fn get_sandbox(
     .fetch_optional(pool)
     .await?;
 
-    Ok(record.map(|row| Sandbox {
-        id: row.get("id"),
-        name: row.get("name"),
-        config_file: row.get("config_file"),
-        config_last_modified: row
-            .get::<String, _>("config_last_modified")
-            .parse::<DateTime<Utc>>()
-            .unwrap(),
-        status: row.get("status"),
-        supervisor_pid: row.get("supervisor_pid"),
-        microvm_pid: row.get("microvm_pid"),
-        rootfs_paths: row.get("rootfs_paths"),
-        group_id: row.get("group_id"),
-        group_ip: row.get("group_ip"),
-        created_at: parse_sqlite_datetime(&row.get::<String, _>("created_at")),
-        modified_at: parse_sqlite_datetime(&row.get::<String, _>("modified_at")),
-    }))
+    Ok(record.as_ref().map(sandbox_from_row))
 }
 
 /// Updates the status of a sandbox identified by name and config file
@@ -256,6 +253,33 @@ pub(crate) async fn update_sandbox_status(
     Ok(())
 }
 
+/// Updates the health of a sandbox identified by name and config file, as reported by its
+/// supervisor.
+///
+/// The modification time is left alone, since it is used to tell whether the sandbox's MicroVM
+/// process is still the one that was recorded.
+pub(crate) async fn update_sandbox_health(
+    pool: &Pool<Sqlite>,
+    name: &str,
+    config_file: &str,
+    health: &str,
+) -> MonocoreResult<()> {
+    sqlx::query(
+        r#"
+        UPDATE sandboxes
+        SET health = ?
+        WHERE name = ? AND config_file = ?
+        "#,
+    )
+    .bind(health)
+    .bind(name)
+    .bind(config_file)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
 /// Gets all sandboxes associated with a specific config file
 pub(crate) async fn get_running_config_sandboxes(
     pool: &Pool<Sqlite>,
@@ -263,9 +287,9 @@ pub(crate) async fn get_running_config_sandboxes(
 ) -> MonocoreResult<Vec<Sandbox>> {
     let records = sqlx::query(
         r#"
-        SELECT id, name, config_file, config_last_modified, status,
+        SELECT id, name, config_file, config_last_modified, config_hash, status,
                supervisor_pid, microvm_pid, rootfs_paths,
-               group_id, group_ip, created_at, modified_at
+               group_id, group_ip, started_at, health, created_at, modified_at
         FROM sandboxes
         WHERE config_file = ? AND status = ?
         ORDER BY created_at DESC
@@ -276,26 +300,83 @@ pub(crate) async fn get_running_config_sandboxes(
     .fetch_all(pool)
     .await?;
 
-    Ok(records
-        .into_iter()
-        .map(|row| Sandbox {
-            id: row.get("id"),
-            name: row.get("name"),
-            config_file: row.get("config_file"),
-            config_last_modified: row
-                .get::<String, _>("config_last_modified")
-                .parse::<DateTime<Utc>>()
-                .unwrap(),
-            status: row.get("status"),
-            supervisor_pid: row.get("supervisor_pid"),
-            microvm_pid: row.get("microvm_pid"),
-            rootfs_paths: row.get("rootfs_paths"),
-            group_id: row.get("group_id"),
-            group_ip: row.get("group_ip"),
-            created_at: parse_sqlite_datetime(&row.get::<String, _>("created_at")),
-            modified_at: parse_sqlite_datetime(&row.get::<String, _>("modified_at")),
-        })
-        .collect())
+    Ok(records.iter().map(sandbox_from_row).collect())
+}
+
+/// Gets all running sandboxes, whichever config file they are defined in
+pub(crate) async fn get_running_sandboxes(pool: &Pool<Sqlite>) -> MonocoreResult<Vec<Sandbox>> {
+    let records = sqlx::query(
+        r#"
+        SELECT id, name, config_file, config_last_modified, config_hash, status,
+               supervisor_pid, microvm_pid, rootfs_paths,
+               group_id, group_ip, started_at, health, created_at, modified_at
+        FROM sandboxes
+        WHERE status = ?
+        ORDER BY created_at DESC
+        "#,
+    )
+    .bind(SANDBOX_STATUS_RUNNING)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(records.iter().map(sandbox_from_row).collect())
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions: Sandbox Metrics
+//--------------------------------------------------------------------------------------------------
+
+/// Records a snapshot of the resources used by a sandbox's MicroVM.
+///
+/// The CPU time is stored in seconds as `cpu_usage`, and the resident set size in bytes as
+/// `memory_usage`.
+pub(crate) async fn save_sandbox_usage(
+    pool: &Pool<Sqlite>,
+    sandbox_id: i64,
+    usage: &SandboxUsage,
+) -> MonocoreResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO sandbox_metrics (sandbox_id, cpu_usage, memory_usage)
+        VALUES (?, ?, ?)
+        "#,
+    )
+    .bind(sandbox_id)
+    .bind(usage.cpu_time_ms.map(|ms| ms as f64 / 1000.0))
+    .bind(usage.memory_rss_bytes.map(|bytes| bytes as i64))
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Returns the most recently recorded snapshot of the resources used by a sandbox's MicroVM, if
+/// any.
+pub(crate) async fn get_latest_sandbox_usage(
+    pool: &Pool<Sqlite>,
+    sandbox_id: i64,
+) -> MonocoreResult<Option<SandboxUsage>> {
+    let record = sqlx::query(
+        r#"
+        SELECT cpu_usage, memory_usage
+        FROM sandbox_metrics
+        WHERE sandbox_id = ?
+        ORDER BY timestamp DESC, id DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(sandbox_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(record.map(|row| SandboxUsage {
+        cpu_time_ms: row
+            .get::<Option<f64>, _>("cpu_usage")
+            .map(|secs| (secs * 1000.0).round() as u64),
+        memory_rss_bytes: row
+            .get::<Option<i64>, _>("memory_usage")
+            .map(|bytes| bytes as u64),
+    }))
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -802,6 +883,101 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_save_or_update_sandbox_records_start_time() -> MonocoreResult<()> {
+        let temp_dir = tempdir()?;
+        let pool = initialize(
+            temp_dir.path().join("test_sandbox.db"),
+            &SANDBOX_DB_MIGRATOR,
+        )
+        .await?;
+
+        let before = Utc::now() - chrono::Duration::seconds(1);
+        save_or_update_sandbox(
+            &pool,
+            "app",
+            "monocore.yaml",
+            &Utc::now(),
+            None,
+            SANDBOX_STATUS_RUNNING,
+            1,
+            2,
+            "",
+            None,
+            None,
+        )
+        .await?;
+
+        let sandbox = get_sandbox(&pool, "app", "monocore.yaml").await?.unwrap();
+        let started_at = sandbox.started_at.unwrap();
+        assert!(started_at >= before && started_at <= Utc::now());
+
+        // Status updates don't change the start time
+        update_sandbox_status(
+            &pool,
+            "app",
+            "monocore.yaml",
+            crate::runtime::SANDBOX_STATUS_STOPPED,
+        )
+        .await?;
+        let sandbox = get_sandbox(&pool, "app", "monocore.yaml").await?.unwrap();
+        assert_eq!(sandbox.started_at, Some(started_at));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sandbox_health_is_reset_on_restart() -> MonocoreResult<()> {
+        let temp_dir = tempdir()?;
+        let pool = initialize(
+            temp_dir.path().join("test_sandbox.db"),
+            &SANDBOX_DB_MIGRATOR,
+        )
+        .await?;
+
+        let config_last_modified = Utc::now();
+        let save = || {
+            save_or_update_sandbox(
+                &pool,
+                "app",
+                "monocore.yaml",
+                &config_last_modified,
+                None,
+                SANDBOX_STATUS_RUNNING,
+                1,
+                2,
+                "",
+                None,
+                None,
+            )
+        };
+
+        save().await?;
+        let sandbox = get_sandbox(&pool, "app", "monocore.yaml").await?.unwrap();
+        assert_eq!(sandbox.health, None);
+
+        update_sandbox_health(
+            &pool,
+            "app",
+            "monocore.yaml",
+            crate::runtime::SANDBOX_HEALTH_UNHEALTHY,
+        )
+        .await?;
+        let updated = get_sandbox(&pool, "app", "monocore.yaml").await?.unwrap();
+        assert_eq!(
+            updated.health.as_deref(),
+            Some(crate::runtime::SANDBOX_HEALTH_UNHEALTHY)
+        );
+        assert_eq!(updated.modified_at, sandbox.modified_at);
+
+        // A restarted MicroVM has to report its health again
+        save().await?;
+        let sandbox = get_sandbox(&pool, "app", "monocore.yaml").await?.unwrap();
+        assert_eq!(sandbox.health, None);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_init_oci_db() -> MonocoreResult<()> {
         // Create temporary directory
@@ -860,6 +1036,32 @@ fn parse_sqlite_datetime(s: &str) -> DateTime<Utc> {
     DateTime::from_naive_utc_and_offset(naive_dt, Utc)
 }
 
+/// Maps a row selected from the sandboxes table to a [`Sandbox`].
+fn sandbox_from_row(row: &SqliteRow) -> Sandbox {
+    Sandbox {
+        id: row.get("id"),
+        name: row.get("name"),
+        config_file: row.get("config_file"),
+        config_last_modified: row
+            .get::<String, _>("config_last_modified")
+            .parse::<DateTime<Utc>>()
+            .unwrap(),
+        config_hash: row.get("config_hash"),
+        status: row.get("status"),
+        supervisor_pid: row.get("supervisor_pid"),
+        microvm_pid: row.get("microvm_pid"),
+        rootfs_paths: row.get("rootfs_paths"),
+        group_id: row.get("group_id"),
+        group_ip: row.get("group_ip"),
+        started_at: row
+            .get::<Option<String>, _>("started_at")
+            .map(|s| parse_sqlite_datetime(&s)),
+        health: row.get("health"),
+        created_at: parse_sqlite_datetime(&row.get::<String, _>("created_at")),
+        modified_at: parse_sqlite_datetime(&row.get::<String, _>("modified_at")),
+    }
+}
+
 /// Sometimes the json columns in the database can have literal "null" values.
 /// This function converts those to None.
 fn null_to_none(value: Option<String>) -> Option<String> {
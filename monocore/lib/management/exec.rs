@@ -0,0 +1,619 @@
+//! Command execution in running sandboxes.
+//!
+//! The MicroVM of every sandbox listens on a UNIX socket in the project's `.menv` directory and
+//! forwards each connection to the guest agent, which listens on the vsock port
+//! [`AGENT_VSOCK_PORT`]. This module connects to that socket to run additional commands in a
+//! live sandbox and stream their stdio, which is useful for debugging running workloads.
+//!
+//! The protocol is newline-delimited JSON. The host sends an [`AgentRequest::Exec`], followed by
+//! any number of [`AgentRequest::Stdin`] chunks and an [`AgentRequest::CloseStdin`] once stdin is
+//! closed. The agent answers with [`AgentResponse::Stdout`] and [`AgentResponse::Stderr`] chunks
+//! and ends the exchange with an [`AgentResponse::Exit`] or an [`AgentResponse::Error`].
+//!
+//! An [`AgentRequest::Signal`] is sent on a connection of its own and gets no answer.
+//!
+//! ## Guest agent
+//!
+//! Monocore does not ship the guest agent, so the sandbox's image must provide one and start it
+//! alongside the workload. An agent:
+//! - Listens on vsock port [`AGENT_VSOCK_PORT`] for any CID and serves each connection on its own
+//! - Reads one JSON object per line, tagged by a `type` field in snake case, such as
+//!   `{"type":"exec","argv":["ps","aux"],"tty":false}`, with the byte chunks of `stdin`,
+//!   `stdout` and `stderr` messages encoded as arrays of numbers
+//! - Runs the `argv` of an `exec` request with `argv[0]` looked up in `PATH`, in a
+//!   pseudo-terminal if `tty` is set, and answers with an `error` message if it can't be started
+//! - Sends the command's output as it is produced and an `exit` message once it exits, with the
+//!   exit code, or 128 plus the signal number if it was killed by a signal
+//! - Delivers the Linux signal number of a `signal` request to the sandbox's init process, PID 1,
+//!   and closes the connection
+//! - Kills the command if its connection is closed before it exits
+//!
+//! [`AGENT_VSOCK_PORT`]: crate::vm::AGENT_VSOCK_PORT
+
+use std::path::{Path, PathBuf};
+
+use nix::sys::signal::Signal;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{self, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, DuplexStream},
+    net::{
+        unix::{OwnedReadHalf, OwnedWriteHalf},
+        UnixStream,
+    },
+    task::JoinHandle,
+};
+
+use crate::{
+    management::{config, db, menv},
+    runtime::SANDBOX_STATUS_RUNNING,
+    utils::{AGENT_SOCKET_SUBDIR, MONOCORE_ENV_DIR, SANDBOX_DB_FILENAME},
+    MonocoreError, MonocoreResult,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The size of the in-memory pipes between an exec handle and the guest agent connection
+const EXEC_PIPE_CAPACITY: usize = 64 * 1024;
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// A message sent to the guest agent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentRequest {
+    /// Runs a command in the sandbox.
+    Exec {
+        /// The program to run, followed by its arguments.
+        argv: Vec<String>,
+
+        /// Whether to run the command in a pseudo-terminal, which merges stderr into stdout.
+        tty: bool,
+    },
+
+    /// A chunk of the command's stdin.
+    Stdin {
+        /// The bytes of the chunk.
+        data: Vec<u8>,
+    },
+
+    /// Closes the command's stdin.
+    CloseStdin,
+
+    /// Sends a signal to the sandbox's init process.
+    Signal {
+        /// The Linux number of the signal, which can differ from the host's.
+        signal: i32,
+    },
+}
+
+/// A message received from the guest agent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentResponse {
+    /// A chunk of the command's stdout.
+    Stdout {
+        /// The bytes of the chunk.
+        data: Vec<u8>,
+    },
+
+    /// A chunk of the command's stderr.
+    Stderr {
+        /// The bytes of the chunk.
+        data: Vec<u8>,
+    },
+
+    /// The command exited.
+    Exit {
+        /// The exit code of the command.
+        code: i32,
+    },
+
+    /// The command could not be run.
+    Error {
+        /// What went wrong.
+        message: String,
+    },
+}
+
+/// A command running in a sandbox.
+///
+/// The stdio of the command is streamed through in-memory pipes. Output that is not read is
+/// discarded once the handle is waited on, so read `stdout` and `stderr` before calling
+/// [`wait`][Self::wait] to capture them.
+#[derive(Debug)]
+pub struct ExecHandle {
+    /// The command's stdin. Shutting it down closes the command's stdin.
+    pub stdin: DuplexStream,
+
+    /// The command's stdout.
+    pub stdout: DuplexStream,
+
+    /// The command's stderr. Always empty for commands run in a pseudo-terminal.
+    pub stderr: DuplexStream,
+
+    /// The task relaying the command's stdio, which resolves to its exit code.
+    exit: JoinHandle<MonocoreResult<i32>>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl ExecHandle {
+    /// Waits for the command to exit.
+    ///
+    /// Closes the command's stdin and discards any output that has not been read yet.
+    ///
+    /// ## Returns
+    ///
+    /// The exit code of the command, or a `MonocoreError` if:
+    /// - The guest agent could not run the command
+    /// - The connection to the guest agent was lost before the command exited
+    pub async fn wait(self) -> MonocoreResult<i32> {
+        drop(self.stdin);
+        drop(self.stdout);
+        drop(self.stderr);
+        self.exit.await?
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Executes a command in a running sandbox.
+///
+/// ## Arguments
+///
+/// * `sandbox_name` - The name of the sandbox as defined in the Monocore config file
+/// * `argv` - The program to run, followed by its arguments
+/// * `tty` - Whether to run the command in a pseudo-terminal
+/// * `project_dir` - Optional path to the project directory. If None, defaults to current directory
+/// * `config_file` - Optional path to the Monocore config file. If None, uses default filename
+///
+/// ## Returns
+///
+/// A handle to the running command, or a `MonocoreError` if:
+/// - The config file is not found
+/// - The specified sandbox is not found in the config
+/// - The sandbox is not running
+/// - The guest agent of the sandbox can't be reached
+///
+/// ## Example
+///
+/// ```no_run
+/// use monocore::management::exec;
+/// use tokio::io::AsyncReadExt;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let argv = vec!["ps".to_string(), "aux".to_string()];
+///     let mut handle = exec::exec("dev", &argv, false, None, None).await?;
+///
+///     let mut output = String::new();
+///     handle.stdout.read_to_string(&mut output).await?;
+///     println!("{}", output);
+///
+///     let code = handle.wait().await?;
+///     println!("exited with {}", code);
+///     Ok(())
+/// }
+/// ```
+pub async fn exec(
+    sandbox_name: &str,
+    argv: &[String],
+    tty: bool,
+    project_dir: Option<&Path>,
+    config_file: Option<&str>,
+) -> MonocoreResult<ExecHandle> {
+    let (config, canonical_project_dir, config_file) =
+        config::load_config(project_dir, config_file).await?;
+
+    if config.get_sandbox(sandbox_name).is_none() {
+        return Err(MonocoreError::SandboxNotFoundInConfig(
+            sandbox_name.to_string(),
+            canonical_project_dir.join(&config_file),
+        ));
+    }
+
+    // Ensure menv files exist
+    let menv_path = canonical_project_dir.join(MONOCORE_ENV_DIR);
+    menv::ensure_menv_files(&menv_path).await?;
+
+    // Only a running sandbox has a guest agent to talk to
+    let db_path = menv_path.join(SANDBOX_DB_FILENAME);
+    let pool = db::get_or_create_pool(&db_path, &db::SANDBOX_DB_MIGRATOR).await?;
+    let running = db::get_sandbox(&pool, sandbox_name, &config_file)
+        .await?
+        .is_some_and(|sandbox| sandbox.status == SANDBOX_STATUS_RUNNING);
+
+    if !running {
+        return Err(MonocoreError::SandboxNotRunning(sandbox_name.to_string()));
+    }
+
+    let socket_path = agent_socket_path(&menv_path, &config_file, sandbox_name);
+    exec_with_agent(&socket_path, argv, tty).await
+}
+
+/// Executes a command through the guest agent listening on a UNIX socket.
+///
+/// ## Arguments
+///
+/// * `socket_path` - The path of the UNIX socket the guest agent is reachable through
+/// * `argv` - The program to run, followed by its arguments
+/// * `tty` - Whether to run the command in a pseudo-terminal
+///
+/// ## Returns
+///
+/// A handle to the running command, or a `MonocoreError` if the guest agent can't be reached.
+pub async fn exec_with_agent(
+    socket_path: impl AsRef<Path>,
+    argv: &[String],
+    tty: bool,
+) -> MonocoreResult<ExecHandle> {
+    let socket_path = socket_path.as_ref();
+    let stream = UnixStream::connect(socket_path).await.map_err(|e| {
+        MonocoreError::GuestAgentError(format!(
+            "failed to connect to {}: {}",
+            socket_path.display(),
+            e
+        ))
+    })?;
+
+    let (reader, mut writer) = stream.into_split();
+    send_request(
+        &mut writer,
+        &AgentRequest::Exec {
+            argv: argv.to_vec(),
+            tty,
+        },
+    )
+    .await?;
+
+    let (stdin, stdin_reader) = io::duplex(EXEC_PIPE_CAPACITY);
+    let (stdout_writer, stdout) = io::duplex(EXEC_PIPE_CAPACITY);
+    let (stderr_writer, stderr) = io::duplex(EXEC_PIPE_CAPACITY);
+
+    let exit = tokio::spawn(async move {
+        let stdin_task = tokio::spawn(forward_stdin(stdin_reader, writer));
+        let result = relay_output(reader, stdout_writer, stderr_writer).await;
+        stdin_task.abort();
+        result
+    });
+
+    Ok(ExecHandle {
+        stdin,
+        stdout,
+        stderr,
+        exit,
+    })
+}
+
+/// Sends a signal to the init process of a sandbox through the guest agent listening on a UNIX
+/// socket.
+///
+/// ## Arguments
+///
+/// * `socket_path` - The path of the UNIX socket the guest agent is reachable through
+/// * `signal` - The signal to send
+///
+/// ## Returns
+///
+/// Nothing once the signal is handed to the guest agent, or a `MonocoreError` if the signal has no
+/// Linux equivalent or the guest agent can't be reached.
+pub async fn signal_with_agent(
+    socket_path: impl AsRef<Path>,
+    signal: Signal,
+) -> MonocoreResult<()> {
+    let signal = to_linux_signal(signal)?;
+    let socket_path = socket_path.as_ref();
+    let stream = UnixStream::connect(socket_path).await.map_err(|e| {
+        MonocoreError::GuestAgentError(format!(
+            "failed to connect to {}: {}",
+            socket_path.display(),
+            e
+        ))
+    })?;
+
+    let (_, mut writer) = stream.into_split();
+    send_request(&mut writer, &AgentRequest::Signal { signal }).await?;
+    writer.shutdown().await?;
+
+    Ok(())
+}
+
+/// Returns the path of the UNIX socket the guest agent of a sandbox is reachable through.
+///
+/// ## Arguments
+///
+/// * `menv_path` - The path of the project's `.menv` directory
+/// * `config_file` - The name of the config file that defines the sandbox
+/// * `sandbox_name` - The name of the sandbox
+pub fn agent_socket_path(menv_path: &Path, config_file: &str, sandbox_name: &str) -> PathBuf {
+    menv_path
+        .join(AGENT_SOCKET_SUBDIR)
+        .join(format!("{}-{}.sock", config_file, sandbox_name))
+}
+
+/// Returns the number of a host signal in the Linux guest.
+///
+/// Signal numbers are not portable, e.g. `SIGUSR1` is 30 on macOS but 10 on Linux, so the host's
+/// numbering can't be sent to the guest as is.
+fn to_linux_signal(signal: Signal) -> MonocoreResult<i32> {
+    let number = match signal {
+        Signal::SIGHUP => 1,
+        Signal::SIGINT => 2,
+        Signal::SIGQUIT => 3,
+        Signal::SIGILL => 4,
+        Signal::SIGTRAP => 5,
+        Signal::SIGABRT => 6,
+        Signal::SIGBUS => 7,
+        Signal::SIGFPE => 8,
+        Signal::SIGKILL => 9,
+        Signal::SIGUSR1 => 10,
+        Signal::SIGSEGV => 11,
+        Signal::SIGUSR2 => 12,
+        Signal::SIGPIPE => 13,
+        Signal::SIGALRM => 14,
+        Signal::SIGTERM => 15,
+        Signal::SIGCHLD => 17,
+        Signal::SIGCONT => 18,
+        Signal::SIGSTOP => 19,
+        Signal::SIGTSTP => 20,
+        Signal::SIGTTIN => 21,
+        Signal::SIGTTOU => 22,
+        Signal::SIGURG => 23,
+        Signal::SIGXCPU => 24,
+        Signal::SIGXFSZ => 25,
+        Signal::SIGVTALRM => 26,
+        Signal::SIGPROF => 27,
+        Signal::SIGWINCH => 28,
+        Signal::SIGIO => 29,
+        Signal::SIGSYS => 31,
+        #[cfg(target_os = "linux")]
+        Signal::SIGPWR => 30,
+        #[allow(unreachable_patterns)]
+        _ => {
+            return Err(MonocoreError::GuestAgentError(format!(
+                "{} has no Linux equivalent",
+                signal
+            )))
+        }
+    };
+
+    Ok(number)
+}
+
+/// Writes a request to the guest agent as a line of JSON.
+async fn send_request(writer: &mut OwnedWriteHalf, request: &AgentRequest) -> MonocoreResult<()> {
+    let mut line = serde_json::to_vec(request)?;
+    line.push(b'\n');
+    writer.write_all(&line).await?;
+    Ok(())
+}
+
+/// Forwards the command's stdin to the guest agent until it is closed.
+async fn forward_stdin(mut stdin: DuplexStream, mut writer: OwnedWriteHalf) -> MonocoreResult<()> {
+    let mut buf = vec![0; EXEC_PIPE_CAPACITY];
+    loop {
+        let n = stdin.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+
+        let data = buf[..n].to_vec();
+        send_request(&mut writer, &AgentRequest::Stdin { data }).await?;
+    }
+
+    send_request(&mut writer, &AgentRequest::CloseStdin).await
+}
+
+/// Relays the command's output from the guest agent until the command exits.
+///
+/// Output is dropped once the handle's end of a pipe is closed, so that an unread pipe never
+/// blocks the exit code.
+async fn relay_output(
+    reader: OwnedReadHalf,
+    mut stdout: DuplexStream,
+    mut stderr: DuplexStream,
+) -> MonocoreResult<i32> {
+    let mut lines = BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        match serde_json::from_str(&line)? {
+            AgentResponse::Stdout { data } => {
+                let _ = stdout.write_all(&data).await;
+            }
+            AgentResponse::Stderr { data } => {
+                let _ = stderr.write_all(&data).await;
+            }
+            AgentResponse::Exit { code } => return Ok(code),
+            AgentResponse::Error { message } => {
+                return Err(MonocoreError::GuestAgentError(message))
+            }
+        }
+    }
+
+    Err(MonocoreError::GuestAgentError(
+        "connection closed before the command exited".to_string(),
+    ))
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+    use tokio::net::UnixListener;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_exec_with_agent() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let socket_path = temp_dir.path().join("agent.sock");
+        let listener = UnixListener::bind(&socket_path)?;
+        let agent = tokio::spawn(helper::mock_agent(listener, 3));
+
+        let argv = vec!["cat".to_string(), "-n".to_string()];
+        let mut handle = exec_with_agent(&socket_path, &argv, true).await?;
+
+        handle.stdin.write_all(b"hello").await?;
+        handle.stdin.shutdown().await?;
+
+        let mut stdout = Vec::new();
+        handle.stdout.read_to_end(&mut stdout).await?;
+        let mut stderr = Vec::new();
+        handle.stderr.read_to_end(&mut stderr).await?;
+
+        // The agent received the command as sent, and the exit code is propagated
+        assert_eq!(handle.wait().await?, 3);
+        assert_eq!(
+            agent.await??,
+            AgentRequest::Exec {
+                argv: argv.clone(),
+                tty: true,
+            }
+        );
+
+        // The agent echoes stdin to stdout and the argv to stderr
+        assert_eq!(stdout, b"hello");
+        assert_eq!(stderr, b"cat -n");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_exec_with_agent_errors() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let socket_path = temp_dir.path().join("agent.sock");
+
+        // No agent listening
+        let argv = vec!["true".to_string()];
+        assert!(matches!(
+            exec_with_agent(&socket_path, &argv, false).await,
+            Err(MonocoreError::GuestAgentError(_))
+        ));
+
+        // The agent reports an error
+        let listener = UnixListener::bind(&socket_path)?;
+        let agent = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await?;
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            lines.next_line().await?;
+            writer
+                .write_all(b"{\"type\":\"error\",\"message\":\"not found\"}\n")
+                .await?;
+            anyhow::Ok(())
+        });
+
+        let handle = exec_with_agent(&socket_path, &argv, false).await?;
+        assert!(matches!(
+            handle.wait().await,
+            Err(MonocoreError::GuestAgentError(message)) if message == "not found"
+        ));
+        agent.await??;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_agent_message_encoding() -> anyhow::Result<()> {
+        // The encoding the guest agent is specified to speak
+        let exec = AgentRequest::Exec {
+            argv: vec!["ps".to_string(), "aux".to_string()],
+            tty: false,
+        };
+        assert_eq!(
+            serde_json::to_string(&exec)?,
+            r#"{"type":"exec","argv":["ps","aux"],"tty":false}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&AgentRequest::Stdin {
+                data: vec![104, 105]
+            })?,
+            r#"{"type":"stdin","data":[104,105]}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&AgentRequest::CloseStdin)?,
+            r#"{"type":"close_stdin"}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<AgentResponse>(r#"{"type":"exit","code":0}"#)?,
+            AgentResponse::Exit { code: 0 }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_linux_signal() -> anyhow::Result<()> {
+        assert_eq!(to_linux_signal(Signal::SIGINT)?, 2);
+        assert_eq!(to_linux_signal(Signal::SIGTERM)?, 15);
+
+        // Numbered differently on macOS
+        assert_eq!(to_linux_signal(Signal::SIGUSR1)?, 10);
+        assert_eq!(to_linux_signal(Signal::SIGCHLD)?, 17);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_agent_socket_path() {
+        assert_eq!(
+            agent_socket_path(Path::new("/project/.menv"), "Sandboxfile", "app"),
+            PathBuf::from("/project/.menv/agent/Sandboxfile-app.sock")
+        );
+    }
+}
+
+#[cfg(test)]
+mod helper {
+    use tokio::net::UnixListener;
+
+    use super::*;
+
+    /// Runs a guest agent that serves one command by echoing its stdin to stdout and its argv to
+    /// stderr, then exits with `code`. Returns the exec request it received.
+    pub(super) async fn mock_agent(
+        listener: UnixListener,
+        code: i32,
+    ) -> anyhow::Result<AgentRequest> {
+        let (stream, _) = listener.accept().await?;
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+
+        let request: AgentRequest =
+            serde_json::from_str(&lines.next_line().await?.unwrap_or_default())?;
+        let AgentRequest::Exec { argv, .. } = &request else {
+            anyhow::bail!("expected an exec request, got {:?}", request);
+        };
+
+        let mut responses = vec![AgentResponse::Stderr {
+            data: argv.join(" ").into_bytes(),
+        }];
+
+        while let Some(line) = lines.next_line().await? {
+            match serde_json::from_str(&line)? {
+                AgentRequest::Stdin { data } => responses.push(AgentResponse::Stdout { data }),
+                AgentRequest::CloseStdin => break,
+                other => anyhow::bail!("unexpected request: {:?}", other),
+            }
+        }
+
+        responses.push(AgentResponse::Exit { code });
+        for response in responses {
+            let mut line = serde_json::to_vec(&response)?;
+            line.push(b'\n');
+            writer.write_all(&line).await?;
+        }
+
+        Ok(request)
+    }
+}
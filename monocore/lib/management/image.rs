@@ -5,11 +5,12 @@
 //! handling image layers, and managing the local image cache.
 
 use crate::{
+    config::DEFAULT_MAX_CONCURRENT_DOWNLOADS,
     management::db::{self, OCI_DB_MIGRATOR},
     oci::{DockerRegistry, OciRegistryPull, Reference},
     utils::{
-        env::get_monocore_home_path,
-        path::{LAYERS_SUBDIR, OCI_DB_FILENAME},
+        env::{get_monocore_home_path, is_offline},
+        path::{DOWNLOADS_SUBDIR, LAYERS_SUBDIR, OCI_DB_FILENAME},
         EXTRACTED_LAYER_SUFFIX,
     },
     MonocoreError, MonocoreResult,
@@ -17,7 +18,6 @@ use crate::{
 use futures::future;
 use sqlx::{Pool, Sqlite};
 use std::path::{Path, PathBuf};
-use tempfile::tempdir;
 use tokio::{fs, process::Command};
 
 //--------------------------------------------------------------------------------------------------
@@ -95,9 +95,15 @@ pub async fn pull(
 
     // Single image pull mode (default if both flags are false, or if image is true)
     let registry = name.to_string().split('/').next().unwrap_or("").to_string();
-    let temp_download_dir = tempdir()?.into_path();
+    let download_dir = get_monocore_home_path().join(DOWNLOADS_SUBDIR);
     if registry == DOCKER_REGISTRY {
-        pull_from_docker_registry(&name, &temp_download_dir, layer_path).await
+        pull_from_docker_registry(
+            &name,
+            &download_dir,
+            layer_path,
+            DEFAULT_MAX_CONCURRENT_DOWNLOADS,
+        )
+        .await
     } else {
         Err(MonocoreError::InvalidArgument(format!(
             "Unsupported registry: {}",
@@ -111,8 +117,11 @@ pub async fn pull(
 /// ## Arguments
 ///
 /// * `image` - The reference to the Docker image to pull
-/// * `download_dir` - The directory to download the image layers to
+/// * `download_dir` - The directory where downloaded image layers and metadata are cached, so
+///   blobs shared with previously pulled images are not downloaded again. When the
+///   `MONOCORE_OFFLINE` environment variable is set, the image is pulled from this cache only
 /// * `layer_path` - Optional custom path to store layers
+/// * `max_concurrent_downloads` - The maximum number of layers downloaded at the same time
 ///
 /// ## Errors
 ///
@@ -124,6 +133,7 @@ pub async fn pull_from_docker_registry(
     image: &Reference,
     download_dir: impl AsRef<Path>,
     layer_path: Option<PathBuf>,
+    max_concurrent_downloads: usize,
 ) -> MonocoreResult<()> {
     let download_dir = download_dir.as_ref();
     let monocore_home_path = get_monocore_home_path();
@@ -138,7 +148,9 @@ pub async fn pull_from_docker_registry(
     // Create layers directory if it doesn't exist
     fs::create_dir_all(&layers_dir).await?;
 
-    let docker_registry = DockerRegistry::new(download_dir, &db_path).await?;
+    let mut docker_registry = DockerRegistry::new(download_dir, &db_path).await?;
+    docker_registry.set_max_concurrent_downloads(max_concurrent_downloads);
+    docker_registry.set_offline(is_offline());
 
     // Get or create a connection pool to the database
     let pool = db::get_or_create_pool(&db_path, &OCI_DB_MIGRATOR).await?;
@@ -153,8 +165,14 @@ pub async fn pull_from_docker_registry(
         .pull_image(image.get_repository(), image.get_selector().clone())
         .await?;
 
-    // Find and extract layers in parallel
-    let layer_paths = collect_layer_files(download_dir).await?;
+    // Find and extract the image's layers in parallel. The download directory is a cache shared
+    // with other images, so only the layers recorded for this image are extracted.
+    let layer_paths = db::get_image_layers(&pool, &image.to_string())
+        .await?
+        .into_iter()
+        .map(|layer| download_dir.join(layer.digest))
+        .collect::<Vec<_>>();
+    tracing::info!("found {} layers to extract", layer_paths.len());
 
     let extraction_futures: Vec<_> = layer_paths
         .into_iter()
@@ -349,26 +367,6 @@ async fn extract_layer(
     Ok(())
 }
 
-/// Collects all layer files in the given directory that start with "sha256:".
-async fn collect_layer_files(dir: impl AsRef<Path>) -> MonocoreResult<Vec<PathBuf>> {
-    let mut layer_paths = Vec::new();
-    let mut read_dir = fs::read_dir(dir).await?;
-
-    while let Ok(Some(entry)) = read_dir.next_entry().await {
-        let path = entry.path();
-        if path.is_file() {
-            if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-                if file_name.starts_with("sha256:") {
-                    layer_paths.push(path.clone());
-                }
-            }
-        }
-    }
-
-    tracing::info!("found {} layers to extract", layer_paths.len());
-    Ok(layer_paths)
-}
-
 //--------------------------------------------------------------------------------------------------
 // Tests
 //--------------------------------------------------------------------------------------------------
@@ -395,7 +393,13 @@ mod tests {
         let image_ref: Reference = "docker.io/library/nginx:stable-alpine".parse().unwrap();
 
         // Call the function under test
-        pull_from_docker_registry(&image_ref, &download_dir, None).await?;
+        pull_from_docker_registry(
+            &image_ref,
+            &download_dir,
+            None,
+            DEFAULT_MAX_CONCURRENT_DOWNLOADS,
+        )
+        .await?;
 
         // Initialize database connection for verification
         let db_path = monocore_home.join(OCI_DB_FILENAME);
@@ -7,6 +7,7 @@
 //!
 //! Key components:
 //! - `db`: Database management for storing container and sandbox metadata
+//! - `exec`: Command execution in running sandboxes through their guest agents
 //! - `image`: Container image handling and registry operations
 //! - `menv`: Monocore environment management
 //! - `rootfs`: Root filesystem operations for containers
@@ -19,6 +20,7 @@
 
 pub mod config;
 pub mod db;
+pub mod exec;
 pub mod image;
 pub mod menv;
 pub mod orchestra;
@@ -9,22 +9,231 @@
 //! - `up`: Start up all sandboxes defined in configuration
 //! - `down`: Gracefully shut down all running sandboxes
 //! - `apply`: Reconcile running sandboxes with configuration
+//! - `plan`: Report the changes `apply` would make, without making them
+//! - `status`: Report the state of sandboxes defined in configuration
+//!
+//! Sandboxes are started in dependency order: a sandbox is only started once every sandbox in its
+//! `depends_on` list is running, and sandboxes that depend on each other in a cycle are rejected.
+//! They are stopped in the reverse order, so a sandbox is stopped before the sandboxes it depends
+//! on.
 
+use chrono::{DateTime, Utc};
+use futures::future;
+use getset::Getters;
 use nix::{
     sys::signal::{self, Signal},
     unistd::Pid,
 };
-use std::path::Path;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Sqlite};
+use std::{
+    collections::{BTreeMap, HashMap},
+    future::Future,
+    io::ErrorKind,
+    net::{Ipv4Addr, TcpListener, UdpSocket},
+    path::Path,
+    time::{Duration, Instant},
+};
+use tokio::net::TcpStream;
 
 use crate::{
-    config::{Monocore, START_SCRIPT_NAME},
-    management::{config, sandbox},
+    config::{
+        Monocore, Protocol, Readiness, Sandbox, TcpProbe, DEFAULT_SANDBOX_READY_POLL_INTERVAL,
+        DEFAULT_SANDBOX_READY_TIMEOUT, DEFAULT_SANDBOX_STOP_GRACE_PERIOD, START_SCRIPT_NAME,
+    },
+    management::{config, exec, sandbox},
+    models,
+    runtime::{SandboxUsage, SANDBOX_HEALTH_UNHEALTHY},
     utils::{MONOCORE_ENV_DIR, SANDBOX_DB_FILENAME},
     MonocoreError, MonocoreResult,
 };
 
 use super::{db, menv};
 
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// How much later than its pid was recorded a process can appear to have started and still be
+/// taken for the recorded process. Procfs only reports the boot time to the second.
+const PROCESS_START_TIME_TOLERANCE: chrono::Duration = chrono::Duration::seconds(2);
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// The status of a sandbox defined in the configuration.
+///
+/// This is serialized as the JSON output of `monocore status` and `monocore list`, so its field
+/// names are part of a stable schema.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Getters)]
+#[getset(get = "pub with_prefix")]
+pub struct SandboxStatus {
+    /// The name of the sandbox.
+    name: String,
+
+    /// The group the sandbox runs in, if any.
+    group: Option<String>,
+
+    /// Whether the sandbox is running.
+    state: SandboxState,
+
+    /// The PID of the sandbox's MicroVM, if it is running.
+    pid: Option<u32>,
+
+    /// How long the sandbox's MicroVM has been running for in seconds, if it is running and its
+    /// start was recorded.
+    uptime_secs: Option<u64>,
+
+    /// The number of vCPUs the sandbox is limited to.
+    cpus: Option<u8>,
+
+    /// The amount of RAM in MiB the sandbox is limited to.
+    ram_mib: Option<u32>,
+
+    /// Whether the sandbox's MicroVM is alive, if the sandbox is running.
+    health: Option<SandboxHealth>,
+
+    /// The resources used by the sandbox's MicroVM, if the sandbox is running.
+    usage: Option<SandboxUsage>,
+}
+
+/// The changes [`apply`] makes to bring the running sandboxes in line with the configuration.
+///
+/// Each list of sandbox names is sorted.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Getters)]
+#[getset(get = "pub with_prefix")]
+pub struct ApplyPlan {
+    /// Sandboxes in the configuration that aren't running, which are started.
+    create: Vec<String>,
+
+    /// Running sandboxes whose spec has changed since they were started, which are restarted.
+    update: Vec<String>,
+
+    /// Running sandboxes that are no longer in the configuration, which are stopped.
+    remove: Vec<String>,
+
+    /// Running sandboxes whose spec hasn't changed, which are left alone.
+    unchanged: Vec<String>,
+}
+
+/// Whether a sandbox is running.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SandboxState {
+    /// The sandbox is running.
+    Running,
+
+    /// The sandbox is not running.
+    Stopped,
+}
+
+/// Whether a running sandbox's MicroVM is alive and, if its guest agent is probed, healthy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SandboxHealth {
+    /// The MicroVM process is alive, and its guest agent hasn't been reported unhealthy.
+    Healthy,
+
+    /// The sandbox is recorded as running, but its MicroVM process is gone, or its guest agent has
+    /// failed too many health probes in a row.
+    Unhealthy,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl SandboxStatus {
+    /// Creates the status of sandbox `name` from its configuration and, if it is running, its
+    /// database record. The resource usage is left unset, see [`SandboxStatus::with_usage`].
+    ///
+    /// ## Arguments
+    ///
+    /// * `name` - The name of the sandbox
+    /// * `sandbox` - The configuration of the sandbox
+    /// * `running` - The database record of the sandbox and its health, if it is running
+    /// * `now` - The current time, used to compute the uptime
+    pub fn new(
+        name: impl Into<String>,
+        sandbox: &Sandbox,
+        running: Option<(&models::Sandbox, SandboxHealth)>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let mut groups: Vec<&String> = sandbox.get_groups().keys().collect();
+        groups.sort();
+
+        Self {
+            name: name.into(),
+            group: groups.first().map(|group| group.to_string()),
+            state: match running {
+                Some(_) => SandboxState::Running,
+                None => SandboxState::Stopped,
+            },
+            pid: running.map(|(record, _)| record.microvm_pid),
+            uptime_secs: running
+                .and_then(|(record, _)| record.started_at)
+                .map(|started_at| (now - started_at).num_seconds().max(0) as u64),
+            cpus: *sandbox.get_cpus(),
+            ram_mib: *sandbox.get_ram(),
+            health: running.map(|(_, health)| health),
+            usage: None,
+        }
+    }
+
+    /// Returns the status with the resources used by the sandbox's MicroVM set to `usage`.
+    pub fn with_usage(mut self, usage: SandboxUsage) -> Self {
+        self.usage = Some(usage);
+        self
+    }
+}
+
+impl ApplyPlan {
+    /// Works out the changes to make to the `running` sandboxes to bring them in line with
+    /// `config`.
+    ///
+    /// A running sandbox has changed if the hash of its spec in the configuration differs from the
+    /// one it was started with. Sandboxes started without a recorded hash are treated as changed.
+    pub fn new(config: &Monocore, running: &[models::Sandbox]) -> MonocoreResult<Self> {
+        let mut plan = Self::default();
+        for (name, sandbox) in config.get_sandboxes() {
+            match running.iter().find(|record| &record.name == name) {
+                None => plan.create.push(name.clone()),
+                Some(record) => {
+                    if record.config_hash.as_deref() == Some(sandbox.spec_hash()?.as_str()) {
+                        plan.unchanged.push(name.clone());
+                    } else {
+                        plan.update.push(name.clone());
+                    }
+                }
+            }
+        }
+
+        plan.remove = running
+            .iter()
+            .filter(|record| !config.get_sandboxes().contains_key(&record.name))
+            .map(|record| record.name.clone())
+            .collect();
+
+        for names in [
+            &mut plan.create,
+            &mut plan.update,
+            &mut plan.remove,
+            &mut plan.unchanged,
+        ] {
+            names.sort();
+            names.dedup();
+        }
+
+        Ok(plan)
+    }
+
+    /// Returns whether applying the plan changes nothing.
+    pub fn is_empty(&self) -> bool {
+        self.create.is_empty() && self.update.is_empty() && self.remove.is_empty()
+    }
+}
+
 //--------------------------------------------------------------------------------------------------
 // Functions
 //--------------------------------------------------------------------------------------------------
@@ -32,13 +241,13 @@ use super::{db, menv};
 /// Reconciles the running sandboxes with the configuration.
 ///
 /// This function ensures that the set of running sandboxes matches what is defined in the
-/// configuration by:
-/// - Starting any sandboxes that are in the config but not running
+/// configuration by carrying out the [`plan`]:
 /// - Stopping any sandboxes that are running but not in the config
+/// - Restarting any running sandboxes whose config has changed since they were started
+/// - Starting any sandboxes that are in the config but not running, in dependency order
 ///
-/// The function uses a file-based lock to prevent concurrent apply operations.
-/// If another apply operation is in progress, this function will fail immediately.
-/// The lock is automatically released when the function completes or if it fails.
+/// Running sandboxes whose config hasn't changed are left alone, so applying an unchanged
+/// configuration again does nothing.
 ///
 /// ## Arguments
 ///
@@ -47,10 +256,12 @@ use super::{db, menv};
 ///
 /// ## Returns
 ///
-/// Returns `MonocoreResult<()>` indicating success or failure. Possible failures include:
+/// Returns the plan that was carried out. Possible failures include:
 /// - Config file not found or invalid
+/// - Sandboxes depending on each other in a cycle
+/// - Sandboxes mapping the same host port, or a host port already being in use
 /// - Database errors
-/// - Sandbox start/stop failures
+/// - Sandbox start/stop failures, or a sandbox not becoming ready in time
 ///
 /// ## Example
 ///
@@ -71,8 +282,11 @@ use super::{db, menv};
 ///     Ok(())
 /// }
 /// ```
-pub async fn apply(project_dir: Option<&Path>, config_file: Option<&str>) -> MonocoreResult<()> {
-    // Load the configuration first to validate it exists before acquiring lock
+pub async fn apply(
+    project_dir: Option<&Path>,
+    config_file: Option<&str>,
+) -> MonocoreResult<ApplyPlan> {
+    // Load the configuration first to validate it exists
     let (config, canonical_project_dir, config_file) =
         config::load_config(project_dir, config_file).await?;
 
@@ -84,51 +298,114 @@ pub async fn apply(project_dir: Option<&Path>, config_file: Option<&str>) -> Mon
     let db_path = menv_path.join(SANDBOX_DB_FILENAME);
     let pool = db::get_or_create_pool(&db_path, &db::SANDBOX_DB_MIGRATOR).await?;
 
-    // Get all sandboxes defined in config
-    let config_sandboxes = config.get_sandboxes();
-
-    // Get all running sandboxes from database
+    // Work out what to change from the running sandboxes in the database
     let running_sandboxes = db::get_running_config_sandboxes(&pool, &config_file).await?;
-    let running_sandbox_names: Vec<String> =
-        running_sandboxes.iter().map(|s| s.name.clone()).collect();
+    let plan = ApplyPlan::new(&config, &running_sandboxes)?;
+    let supervisor_pids: HashMap<String, (u32, DateTime<Utc>)> = running_sandboxes
+        .into_iter()
+        .map(|s| (s.name, (s.supervisor_pid, s.modified_at)))
+        .collect();
 
-    // Start sandboxes that are in config but not active
-    for (name, _) in config_sandboxes {
-        // Should start in parallel
-        if !running_sandbox_names.contains(name) {
-            tracing::info!("Starting sandbox: {}", name);
-            sandbox::run(
-                name,
-                Some(START_SCRIPT_NAME),
-                Some(&canonical_project_dir),
-                Some(&config_file),
-                vec![],
-                true,
-                None,
-                true,
-            )
-            .await?;
-        }
+    // Check ports before changing anything. Sandboxes to update still hold their ports until they
+    // are restarted.
+    let all_sandbox_names: Vec<String> = config.get_sandboxes().keys().cloned().collect();
+    let stages = startup_stages(&config, &all_sandbox_names)?;
+    let holding_ports: Vec<String> = plan.unchanged.iter().chain(&plan.update).cloned().collect();
+    check_port_conflicts(&config, &stages, &holding_ports)?;
+
+    // Stop sandboxes that are no longer in the config
+    for name in &plan.remove {
+        let (supervisor_pid, recorded_at) = supervisor_pids[name];
+        stop_sandbox(
+            name,
+            supervisor_pid,
+            recorded_at,
+            DEFAULT_SANDBOX_STOP_GRACE_PERIOD,
+        )
+        .await?;
     }
 
-    // Stop sandboxes that are active but not in config
-    for sandbox in running_sandboxes {
-        if !config_sandboxes.contains_key(&sandbox.name) {
-            tracing::info!("Stopping sandbox: {}", sandbox.name);
-            signal::kill(
-                Pid::from_raw(sandbox.supervisor_pid as i32),
-                Signal::SIGTERM,
-            )?;
+    // Stop sandboxes whose config has changed, dependents first, so they restart with the new
+    // config below
+    let update_stages = shutdown_stages(&config, &plan.update)?;
+    stop_in_reverse_dependency_order(update_stages, |name| {
+        let (supervisor_pid, recorded_at) = supervisor_pids[&name];
+        async move {
+            stop_sandbox(
+                &name,
+                supervisor_pid,
+                recorded_at,
+                DEFAULT_SANDBOX_STOP_GRACE_PERIOD,
+            )
+            .await
         }
-    }
+    })
+    .await?;
 
-    Ok(())
+    // Start sandboxes that are in config but not running, dependencies first
+    start_in_dependency_order(
+        stages,
+        |name| start_sandbox(name, &plan.unchanged, &canonical_project_dir, &config_file),
+        |name| wait_for_sandbox_ready(name, &config, &menv_path, &pool, &config_file),
+    )
+    .await?;
+
+    Ok(plan)
+}
+
+/// Works out the changes [`apply`] would make, without making them.
+///
+/// ## Arguments
+///
+/// * `project_dir` - Optional path to the project directory. If None, defaults to current directory
+/// * `config_file` - Optional path to the Monocore config file. If None, uses default filename
+///
+/// ## Returns
+///
+/// Returns the sandboxes `apply` would create, update and remove, and those it would leave
+/// unchanged. Possible failures include:
+/// - Config file not found or invalid
+/// - Database errors
+///
+/// ## Example
+///
+/// ```no_run
+/// use monocore::management::orchestra;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let plan = orchestra::plan(None, None).await?;
+///     println!("Would create: {:?}", plan.get_create());
+///     Ok(())
+/// }
+/// ```
+pub async fn plan(
+    project_dir: Option<&Path>,
+    config_file: Option<&str>,
+) -> MonocoreResult<ApplyPlan> {
+    let (config, canonical_project_dir, config_file) =
+        config::load_config(project_dir, config_file).await?;
+
+    // Without a database no sandbox has ever run, so there is nothing to create it for
+    let db_path = canonical_project_dir
+        .join(MONOCORE_ENV_DIR)
+        .join(SANDBOX_DB_FILENAME);
+    let running_sandboxes = if db_path.exists() {
+        let pool = db::get_or_create_pool(&db_path, &db::SANDBOX_DB_MIGRATOR).await?;
+        db::get_running_config_sandboxes(&pool, &config_file).await?
+    } else {
+        vec![]
+    };
+
+    ApplyPlan::new(&config, &running_sandboxes)
 }
 
 /// Starts specified sandboxes from the configuration if they are not already running.
 ///
 /// This function ensures that the specified sandboxes are running by:
-/// - Starting any specified sandboxes that are in the config but not running
+/// - Starting any specified sandboxes that are in the config but not running, along with the
+///   sandboxes they depend on
+/// - Starting each sandbox only once the sandboxes it depends on are ready
 /// - Ignoring sandboxes that are not specified or already running
 ///
 /// ## Arguments
@@ -141,8 +418,10 @@ pub async fn apply(project_dir: Option<&Path>, config_file: Option<&str>) -> Mon
 ///
 /// Returns `MonocoreResult<()>` indicating success or failure. Possible failures include:
 /// - Config file not found or invalid
+/// - Sandboxes depending on each other in a cycle
+/// - Sandboxes mapping the same host port, or a host port already being in use
 /// - Database errors
-/// - Sandbox start failures
+/// - Sandbox start failures, or a sandbox not becoming ready in time
 ///
 /// ## Example
 ///
@@ -189,32 +468,27 @@ pub async fn up(
     let db_path = menv_path.join(SANDBOX_DB_FILENAME);
     let pool = db::get_or_create_pool(&db_path, &db::SANDBOX_DB_MIGRATOR).await?;
 
-    // Get all sandboxes defined in config
-    let config_sandboxes = config.get_sandboxes();
-
     // Get all running sandboxes from database
     let running_sandboxes = db::get_running_config_sandboxes(&pool, &config_file).await?;
     let running_sandbox_names: Vec<String> =
         running_sandboxes.iter().map(|s| s.name.clone()).collect();
 
-    // Start specified sandboxes that are in config but not active
-    for (sandbox_name, _) in config_sandboxes {
-        // Only start if sandbox is in the specified list and not already running
-        if sandbox_names.contains(sandbox_name) && !running_sandbox_names.contains(sandbox_name) {
-            tracing::info!("Starting sandbox: {}", sandbox_name);
-            sandbox::run(
-                sandbox_name,
-                Some(START_SCRIPT_NAME),
-                Some(&canonical_project_dir),
-                Some(&config_file),
-                vec![],
-                true,
-                None,
-                true,
+    // Start specified sandboxes that are not active, dependencies first
+    let stages = startup_stages(&config, &sandbox_names)?;
+    check_port_conflicts(&config, &stages, &running_sandbox_names)?;
+    start_in_dependency_order(
+        stages,
+        |name| {
+            start_sandbox(
+                name,
+                &running_sandbox_names,
+                &canonical_project_dir,
+                &config_file,
             )
-            .await?;
-        }
-    }
+        },
+        |name| wait_for_sandbox_ready(name, &config, &menv_path, &pool, &config_file),
+    )
+    .await?;
 
     Ok(())
 }
@@ -222,7 +496,9 @@ pub async fn up(
 /// Stops specified sandboxes that are both in the configuration and currently running.
 ///
 /// This function ensures that the specified sandboxes are stopped by:
-/// - Stopping any specified sandboxes that are both in the config and currently running
+/// - Stopping any specified sandboxes that are both in the config and currently running, in
+///   reverse dependency order so that a sandbox stops before the sandboxes it depends on
+/// - Killing a sandbox that hasn't stopped within [`DEFAULT_SANDBOX_STOP_GRACE_PERIOD`]
 /// - Ignoring sandboxes that are not specified, not in config, or not running
 ///
 /// ## Arguments
@@ -235,6 +511,7 @@ pub async fn up(
 ///
 /// Returns `MonocoreResult<()>` indicating success or failure. Possible failures include:
 /// - Config file not found or invalid
+/// - Sandboxes depending on each other in a cycle
 /// - Database errors
 /// - Sandbox stop failures
 ///
@@ -283,26 +560,129 @@ pub async fn down(
     let db_path = menv_path.join(SANDBOX_DB_FILENAME);
     let pool = db::get_or_create_pool(&db_path, &db::SANDBOX_DB_MIGRATOR).await?;
 
-    // Get all sandboxes defined in config
-    let config_sandboxes = config.get_sandboxes();
-
     // Get all running sandboxes from database
     let running_sandboxes = db::get_running_config_sandboxes(&pool, &config_file).await?;
+    let supervisor_pids: HashMap<String, (u32, DateTime<Utc>)> = running_sandboxes
+        .into_iter()
+        .map(|s| (s.name, (s.supervisor_pid, s.modified_at)))
+        .collect();
 
-    // Stop specified sandboxes that are both in config and running
-    for sandbox in running_sandboxes {
-        if sandbox_names.contains(&sandbox.name) && config_sandboxes.contains_key(&sandbox.name) {
-            tracing::info!("Stopping sandbox: {}", sandbox.name);
-            signal::kill(
-                Pid::from_raw(sandbox.supervisor_pid as i32),
-                Signal::SIGTERM,
-            )?;
+    // Stop specified sandboxes that are running, dependents first
+    let stages = shutdown_stages(&config, &sandbox_names)?;
+    stop_in_reverse_dependency_order(stages, |name| {
+        let supervisor_pid = supervisor_pids.get(&name).copied();
+        async move {
+            match supervisor_pid {
+                Some((pid, recorded_at)) => {
+                    stop_sandbox(&name, pid, recorded_at, DEFAULT_SANDBOX_STOP_GRACE_PERIOD).await
+                }
+                None => Ok(()),
+            }
         }
-    }
+    })
+    .await?;
 
     Ok(())
 }
 
+/// Returns the status of specified sandboxes from the configuration.
+///
+/// ## Arguments
+///
+/// * `sandbox_names` - List of sandbox names to report on. If empty, reports on all sandboxes in
+///   the configuration
+/// * `project_dir` - Optional path to the project directory. If None, defaults to current directory
+/// * `config_file` - Optional path to the Monocore config file. If None, uses default filename
+///
+/// ## Returns
+///
+/// Returns the status of each sandbox, sorted by name. Possible failures include:
+/// - Config file not found or invalid
+/// - Specified sandboxes not found in the config
+/// - Database errors
+///
+/// ## Example
+///
+/// ```no_run
+/// use monocore::management::orchestra;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     for status in orchestra::status(vec![], None, None).await? {
+///         println!("{}: {:?}", status.get_name(), status.get_state());
+///     }
+///     Ok(())
+/// }
+/// ```
+pub async fn status(
+    sandbox_names: Vec<String>,
+    project_dir: Option<&Path>,
+    config_file: Option<&str>,
+) -> MonocoreResult<Vec<SandboxStatus>> {
+    // Load the configuration first to validate it exists
+    let (config, canonical_project_dir, config_file) =
+        config::load_config(project_dir, config_file).await?;
+
+    // Validate all sandbox names exist in config before proceeding
+    validate_sandbox_names(
+        &sandbox_names,
+        &config,
+        &canonical_project_dir,
+        &config_file,
+    )?;
+
+    // Ensure menv files exist
+    let menv_path = canonical_project_dir.join(MONOCORE_ENV_DIR);
+    menv::ensure_menv_files(&menv_path).await?;
+
+    // Get database connection pool
+    let db_path = menv_path.join(SANDBOX_DB_FILENAME);
+    let pool = db::get_or_create_pool(&db_path, &db::SANDBOX_DB_MIGRATOR).await?;
+
+    // Get all running sandboxes from database
+    let running_sandboxes: HashMap<String, models::Sandbox> =
+        db::get_running_config_sandboxes(&pool, &config_file)
+            .await?
+            .into_iter()
+            .map(|s| (s.name.clone(), s))
+            .collect();
+
+    let mut sandbox_names = if sandbox_names.is_empty() {
+        config.get_sandboxes().keys().cloned().collect()
+    } else {
+        sandbox_names
+    };
+    sandbox_names.sort();
+    sandbox_names.dedup();
+
+    let now = Utc::now();
+    let mut statuses = Vec::with_capacity(sandbox_names.len());
+    for name in sandbox_names {
+        let running = running_sandboxes.get(&name).map(|record| {
+            // The supervisor records the health reported by the guest agent, if it probes it
+            let unhealthy = record.health.as_deref() == Some(SANDBOX_HEALTH_UNHEALTHY);
+            let health = if !unhealthy && is_process_running(record.microvm_pid, record.modified_at)
+            {
+                SandboxHealth::Healthy
+            } else {
+                SandboxHealth::Unhealthy
+            };
+            (record, health)
+        });
+
+        let mut status = SandboxStatus::new(&name, &config.get_sandboxes()[&name], running, now);
+        if let Some((record, _)) = running {
+            // The sandbox's supervisor records the MicroVM's resource usage periodically
+            let usage = db::get_latest_sandbox_usage(&pool, record.id).await?;
+            status = status.with_usage(usage.unwrap_or_default());
+        }
+
+        statuses.push(status);
+    }
+
+    Ok(statuses)
+}
+
 //--------------------------------------------------------------------------------------------------
 // Functions: Helpers
 //--------------------------------------------------------------------------------------------------
@@ -332,41 +712,997 @@ fn validate_sandbox_names(
     Ok(())
 }
 
-/// Checks if specified sandboxes from the configuration are running.
-async fn _check_running(
-    sandbox_names: Vec<String>,
+/// Groups the given sandboxes, and every sandbox they depend on, into stages to start in order.
+///
+/// Every sandbox is in a later stage than all of its dependencies, and in the earliest stage it
+/// can be in, so the sandboxes in a stage don't depend on each other. The names in each stage are
+/// sorted.
+///
+/// ## Errors
+///
+/// Returns [`MonocoreError::SandboxDependencyCycle`] naming the cycle if sandboxes depend on each
+/// other in a cycle, and [`MonocoreError::ConfigValidation`] if a sandbox depends on one that isn't
+/// in the configuration or the dependency chain is longer than
+/// [`Monocore::MAX_DEPENDENCY_DEPTH`].
+fn startup_stages(config: &Monocore, sandbox_names: &[String]) -> MonocoreResult<Vec<Vec<String>>> {
+    let mut depths = HashMap::new();
+    let mut path = Vec::new();
+    for name in sandbox_names {
+        dependency_depth(config.get_sandboxes(), name, &mut depths, &mut path)?;
+    }
+
+    let num_stages = depths.values().max().map_or(0, |depth| depth + 1);
+    let mut stages = vec![Vec::new(); num_stages];
+    for (name, depth) in depths {
+        stages[depth].push(name);
+    }
+
+    for stage in &mut stages {
+        stage.sort();
+    }
+
+    Ok(stages)
+}
+
+/// Returns the length of the longest dependency chain below sandbox `name`, which is 0 if it has
+/// no dependencies.
+///
+/// Depths are memoized in `depths`. `path` holds the chain of dependents being visited, to detect
+/// cycles.
+fn dependency_depth(
+    sandboxes: &HashMap<String, Sandbox>,
+    name: &str,
+    depths: &mut HashMap<String, usize>,
+    path: &mut Vec<String>,
+) -> MonocoreResult<usize> {
+    if let Some(depth) = depths.get(name) {
+        return Ok(*depth);
+    }
+
+    if let Some(start) = path.iter().position(|visited| visited == name) {
+        let mut cycle = path[start..].to_vec();
+        cycle.push(name.to_string());
+        return Err(MonocoreError::SandboxDependencyCycle(cycle.join(" -> ")));
+    }
+
+    if path.len() >= Monocore::MAX_DEPENDENCY_DEPTH {
+        return Err(MonocoreError::ConfigValidation(format!(
+            "sandbox dependency chain is longer than {}: {}",
+            Monocore::MAX_DEPENDENCY_DEPTH,
+            path.join(" -> ")
+        )));
+    }
+
+    let Some(sandbox) = sandboxes.get(name) else {
+        return Err(MonocoreError::ConfigValidation(format!(
+            "sandbox '{}' depends on unknown sandbox '{}'",
+            path.last().map_or("", String::as_str),
+            name
+        )));
+    };
+
+    path.push(name.to_string());
+    let mut depth = 0;
+    for dependency in sandbox.get_depends_on() {
+        depth = depth.max(dependency_depth(sandboxes, dependency, depths, path)? + 1);
+    }
+    path.pop();
+
+    depths.insert(name.to_string(), depth);
+    Ok(depth)
+}
+
+/// Checks that the sandboxes about to be started can bind their host ports, before any of them is
+/// started.
+///
+/// Sandboxes in `stages` that are already running keep their ports, so they are only checked for
+/// conflicts with the other sandboxes, while the host ports of the sandboxes to start must also be
+/// free.
+///
+/// ## Errors
+///
+/// Returns [`MonocoreError::PortConflict`] naming the sandboxes if more than one sandbox maps the
+/// same host port, and [`MonocoreError::HostPortInUse`] if a host port of a sandbox to start is
+/// already bound by another process.
+fn check_port_conflicts(
+    config: &Monocore,
+    stages: &[Vec<String>],
+    running_sandbox_names: &[String],
+) -> MonocoreResult<()> {
+    let mut sandbox_names: Vec<String> = stages.iter().flatten().cloned().collect();
+    sandbox_names.extend(
+        running_sandbox_names
+            .iter()
+            .filter(|name| config.get_sandboxes().contains_key(*name))
+            .cloned(),
+    );
+    sandbox_names.sort();
+    sandbox_names.dedup();
+
+    let host_ports = collect_host_ports(config, &sandbox_names)?;
+
+    for ((port, protocol), name) in host_ports {
+        if !running_sandbox_names.contains(&name) && !is_host_port_free(port, protocol) {
+            return Err(MonocoreError::HostPortInUse(port, name));
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the host ports and their protocols mapped by the given sandboxes, and the sandbox
+/// mapping each of them.
+///
+/// ## Errors
+///
+/// Returns [`MonocoreError::PortConflict`] naming the sandboxes if more than one sandbox maps the
+/// same host port with the same protocol.
+fn collect_host_ports(
     config: &Monocore,
+    sandbox_names: &[String],
+) -> MonocoreResult<BTreeMap<(u16, Protocol), String>> {
+    let mut bindings: BTreeMap<(u16, Protocol), Vec<&str>> = BTreeMap::new();
+    for name in sandbox_names {
+        let Some(sandbox) = config.get_sandboxes().get(name) else {
+            continue;
+        };
+
+        for port in sandbox.get_ports() {
+            let names = bindings
+                .entry((port.get_host(), port.get_protocol()))
+                .or_default();
+            if !names.contains(&name.as_str()) {
+                names.push(name);
+            }
+        }
+    }
+
+    let mut host_ports = BTreeMap::new();
+    for ((port, protocol), names) in bindings {
+        if names.len() > 1 {
+            let names: Vec<String> = names.iter().map(|name| format!("'{name}'")).collect();
+            return Err(MonocoreError::PortConflict(port, names.join(", ")));
+        }
+
+        host_ports.insert((port, protocol), names[0].to_string());
+    }
+
+    Ok(host_ports)
+}
+
+/// Returns whether no other process is bound to host port `port` with `protocol`.
+fn is_host_port_free(port: u16, protocol: Protocol) -> bool {
+    let address = (Ipv4Addr::UNSPECIFIED, port);
+    let result = match protocol {
+        Protocol::Tcp => TcpListener::bind(address).map(drop),
+        Protocol::Udp => UdpSocket::bind(address).map(drop),
+    };
+
+    match result {
+        Ok(()) => true,
+        Err(e) => e.kind() != ErrorKind::AddrInUse,
+    }
+}
+
+/// Starts the sandboxes in each stage, and waits for all of them to be ready before moving on to
+/// the next stage.
+///
+/// The sandboxes in a stage are started one at a time and then waited on together.
+async fn start_in_dependency_order<S, SFut, R, RFut>(
+    stages: Vec<Vec<String>>,
+    start: S,
+    wait_ready: R,
+) -> MonocoreResult<()>
+where
+    S: Fn(String) -> SFut,
+    SFut: Future<Output = MonocoreResult<()>>,
+    R: Fn(String) -> RFut,
+    RFut: Future<Output = MonocoreResult<()>>,
+{
+    for stage in stages {
+        for name in &stage {
+            start(name.clone()).await?;
+        }
+
+        future::try_join_all(stage.into_iter().map(&wait_ready)).await?;
+    }
+
+    Ok(())
+}
+
+/// Starts a sandbox in the background, unless it is already running.
+async fn start_sandbox(
+    name: String,
+    running_sandbox_names: &[String],
     project_dir: &Path,
     config_file: &str,
-) -> MonocoreResult<Vec<(String, bool)>> {
-    // Ensure menv files exist
-    let canonical_project_dir = project_dir.canonicalize().map_err(|e| {
-        MonocoreError::InvalidArgument(format!("Failed to canonicalize project directory: {}", e))
-    })?;
-    let menv_path = canonical_project_dir.join(MONOCORE_ENV_DIR);
-    menv::ensure_menv_files(&menv_path).await?;
+) -> MonocoreResult<()> {
+    if running_sandbox_names.contains(&name) {
+        return Ok(());
+    }
 
-    // Get database connection pool
-    let db_path = menv_path.join(SANDBOX_DB_FILENAME);
-    let pool = db::get_or_create_pool(&db_path, &db::SANDBOX_DB_MIGRATOR).await?;
+    tracing::info!("Starting sandbox: {}", name);
+    sandbox::run(
+        &name,
+        Some(START_SCRIPT_NAME),
+        Some(project_dir),
+        Some(config_file),
+        vec![],
+        true,
+        None,
+        true,
+    )
+    .await
+}
 
-    // Get all sandboxes defined in config
-    let config_sandboxes = config.get_sandboxes();
+/// Waits for a sandbox to be recorded as running, which its supervisor does once the MicroVM has
+/// started, and then for its readiness probe to succeed.
+///
+/// See [`readiness_probe`] for the probe used when the sandbox doesn't configure one.
+async fn wait_for_sandbox_ready(
+    name: String,
+    config: &Monocore,
+    menv_path: &Path,
+    pool: &Pool<Sqlite>,
+    config_file: &str,
+) -> MonocoreResult<()> {
+    let started = Instant::now();
+    loop {
+        let running_sandboxes = db::get_running_config_sandboxes(pool, config_file).await?;
+        if running_sandboxes.iter().any(|s| s.name == name) {
+            break;
+        }
 
-    // Get all running sandboxes from database
-    let running_sandboxes = db::get_running_config_sandboxes(&pool, config_file).await?;
-    let running_sandbox_names: Vec<String> =
-        running_sandboxes.iter().map(|s| s.name.clone()).collect();
+        if started.elapsed() >= DEFAULT_SANDBOX_READY_TIMEOUT {
+            return Err(MonocoreError::SandboxReadyTimeout(
+                name,
+                DEFAULT_SANDBOX_READY_TIMEOUT,
+            ));
+        }
 
-    // Check status of specified sandboxes
-    let mut statuses = Vec::new();
-    for sandbox_name in sandbox_names {
-        // Only check if sandbox exists in config
-        if config_sandboxes.contains_key(&sandbox_name) {
-            let is_running = running_sandbox_names.contains(&sandbox_name);
-            statuses.push((sandbox_name, is_running));
+        tokio::time::sleep(DEFAULT_SANDBOX_READY_POLL_INTERVAL).await;
+    }
+
+    if let Some(sandbox) = config.get_sandbox(&name) {
+        if let Some(readiness) = readiness_probe(sandbox) {
+            let agent_socket = exec::agent_socket_path(menv_path, config_file, &name);
+            wait_for_readiness_probe(&name, sandbox, &readiness, &agent_socket).await?;
         }
     }
 
-    Ok(statuses)
+    tracing::info!("Sandbox ready: {}", name);
+    Ok(())
+}
+
+/// Returns the readiness probe of a sandbox.
+///
+/// A sandbox without a configured probe that exposes TCP ports is ready once the first of them
+/// accepts connections. A sandbox that exposes no TCP ports has nothing to probe from the host, so
+/// it is ready as soon as it is recorded as running.
+fn readiness_probe(sandbox: &Sandbox) -> Option<Readiness> {
+    if let Some(readiness) = sandbox.get_readiness() {
+        return Some(readiness.clone());
+    }
+
+    let port = sandbox
+        .get_ports()
+        .iter()
+        .find(|port| port.get_protocol() == Protocol::Tcp)?;
+
+    Some(Readiness {
+        tcp: Some(TcpProbe {
+            port: port.get_guest(),
+        }),
+        exec: None,
+        http: None,
+        interval: None,
+        timeout: None,
+        retries: None,
+    })
+}
+
+/// Attempts a sandbox's readiness probe every interval until it succeeds, giving up once it has
+/// failed as many times as the probe allows.
+async fn wait_for_readiness_probe(
+    name: &str,
+    sandbox: &Sandbox,
+    readiness: &Readiness,
+    agent_socket: &Path,
+) -> MonocoreResult<()> {
+    let mut failures = 0;
+    loop {
+        let attempt = tokio::time::timeout(
+            readiness.get_timeout(),
+            run_readiness_probe(name, sandbox, readiness, agent_socket),
+        )
+        .await;
+
+        let error = match attempt {
+            Ok(Ok(())) => return Ok(()),
+            Ok(Err(e)) => e.to_string(),
+            Err(_) => format!("timed out after {:?}", readiness.get_timeout()),
+        };
+
+        failures += 1;
+        tracing::debug!(
+            "Readiness probe of sandbox {} failed ({}/{}): {}",
+            name,
+            failures,
+            readiness.get_retries(),
+            error
+        );
+
+        if failures >= readiness.get_retries() {
+            return Err(MonocoreError::ReadinessProbeFailed(
+                name.to_string(),
+                format!("gave up after {} attempts: {}", failures, error),
+            ));
+        }
+
+        tokio::time::sleep(readiness.get_interval()).await;
+    }
+}
+
+/// Attempts a sandbox's readiness probe once.
+///
+/// TCP and HTTP probes are made from the host, to the host port the probed guest port is exposed
+/// on. Exec probes run their command in the sandbox through its guest agent, which the sandbox's
+/// image must provide, so they keep failing until the agent is up.
+async fn run_readiness_probe(
+    name: &str,
+    sandbox: &Sandbox,
+    readiness: &Readiness,
+    agent_socket: &Path,
+) -> MonocoreResult<()> {
+    let host_port = |guest_port: u16| {
+        sandbox.get_exposed_tcp_port(guest_port).ok_or_else(|| {
+            MonocoreError::ConfigValidation(format!(
+                "guest port {} of sandbox '{}' isn't exposed over tcp",
+                guest_port, name
+            ))
+        })
+    };
+
+    if let Some(tcp) = readiness.get_tcp() {
+        let port = host_port(*tcp.get_port())?;
+        TcpStream::connect((Ipv4Addr::LOCALHOST, port)).await?;
+    } else if let Some(http) = readiness.get_http() {
+        let port = host_port(*http.get_port())?;
+        let url = format!("http://{}:{}{}", Ipv4Addr::LOCALHOST, port, http.get_path());
+        reqwest::get(url).await?.error_for_status()?;
+    } else if let Some(probe) = readiness.get_exec() {
+        let code = exec::exec_with_agent(agent_socket, probe.get_command(), false)
+            .await?
+            .wait()
+            .await?;
+        if code != 0 {
+            return Err(MonocoreError::ReadinessProbeFailed(
+                name.to_string(),
+                format!("command exited with code {}", code),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Groups the given sandboxes into stages to stop in order.
+///
+/// This is the reverse of [`startup_stages`], limited to the given sandboxes: every sandbox is in
+/// an earlier stage than the sandboxes it depends on, so dependents stop first.
+fn shutdown_stages(
+    config: &Monocore,
+    sandbox_names: &[String],
+) -> MonocoreResult<Vec<Vec<String>>> {
+    let stages = startup_stages(config, sandbox_names)?
+        .into_iter()
+        .rev()
+        .map(|stage| {
+            stage
+                .into_iter()
+                .filter(|name| sandbox_names.contains(name))
+                .collect::<Vec<_>>()
+        })
+        .filter(|stage| !stage.is_empty())
+        .collect();
+
+    Ok(stages)
+}
+
+/// Stops the sandboxes in each stage, and waits for all of them to stop before moving on to the
+/// next stage.
+async fn stop_in_reverse_dependency_order<S, SFut>(
+    stages: Vec<Vec<String>>,
+    stop: S,
+) -> MonocoreResult<()>
+where
+    S: Fn(String) -> SFut,
+    SFut: Future<Output = MonocoreResult<()>>,
+{
+    for stage in stages {
+        future::try_join_all(stage.into_iter().map(&stop)).await?;
+    }
+
+    Ok(())
+}
+
+/// Asks a sandbox's supervisor to shut down, and kills it if it is still running after
+/// `grace_period`.
+///
+/// `recorded_at` is when the supervisor's pid was recorded. The supervisor is only signalled while
+/// the pid still belongs to it, see [`is_process_running`].
+async fn stop_sandbox(
+    name: &str,
+    supervisor_pid: u32,
+    recorded_at: DateTime<Utc>,
+    grace_period: Duration,
+) -> MonocoreResult<()> {
+    if !is_process_running(supervisor_pid, recorded_at) {
+        tracing::info!("Sandbox already stopped: {}", name);
+        return Ok(());
+    }
+
+    tracing::info!("Stopping sandbox: {}", name);
+    let pid = Pid::from_raw(supervisor_pid as i32);
+    signal::kill(pid, Signal::SIGTERM)?;
+
+    let started = Instant::now();
+    while is_process_running(supervisor_pid, recorded_at) {
+        if started.elapsed() >= grace_period {
+            tracing::warn!(
+                "Sandbox {} did not stop within {:?}, killing it",
+                name,
+                grace_period
+            );
+            signal::kill(pid, Signal::SIGKILL)?;
+            break;
+        }
+
+        tokio::time::sleep(DEFAULT_SANDBOX_READY_POLL_INTERVAL).await;
+    }
+
+    tracing::info!("Sandbox stopped: {}", name);
+    Ok(())
+}
+
+/// Returns whether the process with `pid`, recorded at `recorded_at`, still exists.
+///
+/// A pid is reused once its process exits, so a process that started after `recorded_at` is a
+/// different one. On platforms without procfs, only the pid's existence is checked.
+fn is_process_running(pid: u32, recorded_at: DateTime<Utc>) -> bool {
+    if signal::kill(Pid::from_raw(pid as i32), None).is_err() {
+        return false;
+    }
+
+    match process_started_at(pid) {
+        Some(started_at) => started_at <= recorded_at + PROCESS_START_TIME_TOLERANCE,
+        None => true,
+    }
+}
+
+/// Returns when the process with `pid` started, as reported by procfs.
+fn process_started_at(pid: u32) -> Option<DateTime<Utc>> {
+    let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+
+    // The command name can contain spaces, so the fields are counted after its closing paren.
+    // starttime, in clock ticks since boot, is field 22 of the whole line.
+    let (_, fields) = stat.rsplit_once(')')?;
+    let start_ticks: u64 = fields.split_whitespace().nth(19)?.parse().ok()?;
+
+    let boot_time: i64 = std::fs::read_to_string("/proc/stat")
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))?
+        .trim()
+        .parse()
+        .ok()?;
+
+    // SAFETY: sysconf only reads a system configuration value and has no preconditions.
+    let ticks_per_sec = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks_per_sec <= 0 {
+        return None;
+    }
+
+    let since_boot =
+        chrono::Duration::milliseconds((start_ticks * 1000 / ticks_per_sec as u64) as i64);
+    Some(DateTime::from_timestamp(boot_time, 0)? + since_boot)
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[test]
+    fn test_startup_stages_linear_chain() -> anyhow::Result<()> {
+        let config = helper::config(&[
+            ("web", &["api"]),
+            ("api", &["db"]),
+            ("db", &[]),
+            ("unrelated", &[]),
+        ])?;
+
+        // Dependencies are included even when not requested
+        assert_eq!(
+            startup_stages(&config, &["web".to_string()])?,
+            [["db"], ["api"], ["web"]]
+        );
+        assert_eq!(startup_stages(&config, &["db".to_string()])?, [["db"]]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_startup_stages_diamond() -> anyhow::Result<()> {
+        let config = helper::config(&[
+            ("app", &["cache", "queue"]),
+            ("cache", &["store"]),
+            ("queue", &["store"]),
+            ("store", &[]),
+            ("metrics", &[]),
+        ])?;
+
+        let all_sandboxes = ["app", "cache", "queue", "store", "metrics"].map(String::from);
+        assert_eq!(
+            startup_stages(&config, &all_sandboxes)?,
+            [
+                vec!["metrics", "store"],
+                vec!["cache", "queue"],
+                vec!["app"]
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_startup_stages_rejects_cycles() -> anyhow::Result<()> {
+        let config = helper::config(&[("a", &["b"]), ("b", &["c"]), ("c", &["a"]), ("d", &["d"])])?;
+
+        let result = startup_stages(&config, &["a".to_string()]);
+        assert!(matches!(
+            result,
+            Err(MonocoreError::SandboxDependencyCycle(ref cycle)) if cycle == "a -> b -> c -> a"
+        ));
+
+        let result = startup_stages(&config, &["d".to_string()]);
+        assert!(matches!(
+            result,
+            Err(MonocoreError::SandboxDependencyCycle(ref cycle)) if cycle == "d -> d"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_startup_stages_rejects_unknown_dependency() -> anyhow::Result<()> {
+        let config = helper::config(&[("api", &["missing"])])?;
+
+        let result = startup_stages(&config, &["api".to_string()]);
+        assert!(matches!(
+            result,
+            Err(MonocoreError::ConfigValidation(ref message))
+                if message.contains("'api' depends on unknown sandbox 'missing'")
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_port_conflicts_rejects_shared_host_port() -> anyhow::Result<()> {
+        let config = helper::config_with_ports(&[
+            ("web", &["8080:80"]),
+            ("admin", &["9000:80", "8080:8000"]),
+            ("db", &["5432"]),
+        ])?;
+        let stages = startup_stages(&config, &["web".to_string(), "admin".to_string()])?;
+
+        let result = check_port_conflicts(&config, &stages, &[]);
+        assert!(matches!(
+            result,
+            Err(MonocoreError::PortConflict(8080, ref names)) if names == "'admin', 'web'"
+        ));
+
+        // A running sandbox still holds its ports
+        let stages = startup_stages(&config, &["web".to_string()])?;
+        let result = check_port_conflicts(&config, &stages, &["admin".to_string()]);
+        assert!(matches!(result, Err(MonocoreError::PortConflict(8080, _))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_port_conflicts_accepts_distinct_host_ports() -> anyhow::Result<()> {
+        let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        let used_port = listener.local_addr()?.port();
+        let free_port = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0))?
+            .local_addr()?
+            .port();
+
+        let web_port = format!("{free_port}:80");
+        let api_port = format!("{used_port}:80");
+        let config = helper::config_with_ports(&[
+            ("web", &[web_port.as_str()]),
+            ("api", &[api_port.as_str()]),
+        ])?;
+        let web = startup_stages(&config, &["web".to_string()])?;
+        check_port_conflicts(&config, &web, &[])?;
+
+        // The port of a sandbox to start must be free, unless the sandbox is already running
+        let all_sandboxes = startup_stages(&config, &["web".to_string(), "api".to_string()])?;
+        let result = check_port_conflicts(&config, &all_sandboxes, &[]);
+        assert!(matches!(
+            result,
+            Err(MonocoreError::HostPortInUse(port, ref name)) if port == used_port && name == "api"
+        ));
+        check_port_conflicts(&config, &all_sandboxes, &["api".to_string()])?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_host_port_free_probes_the_pair_protocol() -> anyhow::Result<()> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        let port = socket.local_addr()?.port();
+        assert!(!is_host_port_free(port, Protocol::Udp));
+
+        let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+        let port = listener.local_addr()?.port();
+        assert!(!is_host_port_free(port, Protocol::Tcp));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_readiness_probe_defaults_to_first_exposed_tcp_port() -> anyhow::Result<()> {
+        let config =
+            helper::config_with_ports(&[("web", &["8080:80", "8443:443"]), ("worker", &[])])?;
+
+        let readiness = readiness_probe(config.get_sandbox("web").unwrap()).unwrap();
+        assert_eq!(
+            readiness.get_tcp().as_ref().map(|tcp| *tcp.get_port()),
+            Some(80)
+        );
+        assert_eq!(
+            readiness.get_retries(),
+            crate::config::DEFAULT_READINESS_PROBE_RETRIES
+        );
+
+        // Nothing to probe from the host
+        assert!(readiness_probe(config.get_sandbox("worker").unwrap()).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_shutdown_stages_only_stops_requested_sandboxes() -> anyhow::Result<()> {
+        let config = helper::config(&[("web", &["api"]), ("api", &["db"]), ("db", &[])])?;
+
+        assert_eq!(shutdown_stages(&config, &["api".to_string()])?, [["api"]]);
+        assert_eq!(
+            shutdown_stages(&config, &["db".to_string(), "web".to_string()])?,
+            [["web"], ["db"]]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stop_in_reverse_dependency_order_three_tier_app() -> anyhow::Result<()> {
+        let config = helper::config(&[("web", &["api"]), ("api", &["db"]), ("db", &[])])?;
+        let all_sandboxes = ["db", "api", "web"].map(String::from);
+        let stages = shutdown_stages(&config, &all_sandboxes)?;
+        let stopped = Mutex::new(Vec::new());
+
+        stop_in_reverse_dependency_order(stages, |name| {
+            let stopped = &stopped;
+            async move {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                stopped.lock().unwrap().push(name);
+                Ok(())
+            }
+        })
+        .await?;
+
+        assert_eq!(stopped.into_inner().unwrap(), ["web", "api", "db"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stop_sandbox_kills_after_grace_period() -> anyhow::Result<()> {
+        // A process that ignores SIGTERM
+        let mut child = tokio::process::Command::new("sh")
+            .args(["-c", "trap '' TERM; exec sleep 30"])
+            .spawn()?;
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let pid = child.id().unwrap();
+        stop_sandbox("stubborn", pid, Utc::now(), Duration::from_millis(200)).await?;
+
+        let status = child.wait().await?;
+        assert!(!status.success());
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_is_process_running_rejects_reused_pid() {
+        let pid = std::process::id();
+        assert!(is_process_running(pid, Utc::now()));
+
+        // The process started after the pid was recorded, so the pid has been reused
+        assert!(!is_process_running(pid, DateTime::UNIX_EPOCH));
+    }
+
+    #[test]
+    fn test_sandbox_status_json_schema() -> anyhow::Result<()> {
+        let config: Monocore = serde_yaml::from_str(
+            r#"
+            sandboxes:
+              api:
+                image: alpine
+                shell: /bin/sh
+                cpus: 2
+                ram: 512
+                groups:
+                  backend: {}
+            "#,
+        )?;
+        let sandbox = &config.get_sandboxes()["api"];
+        let now = Utc::now();
+        let record = models::Sandbox {
+            // Updating the record doesn't restart the uptime
+            modified_at: now,
+            ..helper::running_record("api", 4242, now - chrono::Duration::seconds(90))
+        };
+
+        let usage: SandboxUsage = serde_json::from_value(serde_json::json!({
+            "cpu_time_ms": 1500,
+            "memory_rss_bytes": 1048576,
+        }))?;
+        let status =
+            SandboxStatus::new("api", sandbox, Some((&record, SandboxHealth::Healthy)), now)
+                .with_usage(usage);
+        let json = serde_json::to_value(&status)?;
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "name": "api",
+                "group": "backend",
+                "state": "running",
+                "pid": 4242,
+                "uptime_secs": 90,
+                "cpus": 2,
+                "ram_mib": 512,
+                "health": "healthy",
+                "usage": {
+                    "cpu_time_ms": 1500,
+                    "memory_rss_bytes": 1048576,
+                },
+            })
+        );
+        assert_eq!(serde_json::from_value::<SandboxStatus>(json)?, status);
+
+        // Fields of a stopped sandbox are still present, as nulls
+        let status = SandboxStatus::new("api", sandbox, None, now);
+        let json = serde_json::to_value(&status)?;
+        assert_eq!(json["state"], "stopped");
+        for field in ["pid", "uptime_secs", "health", "usage"] {
+            assert!(json[field].is_null(), "{field} should be null: {json}");
+        }
+        assert_eq!(
+            serde_json::from_str::<SandboxStatus>(&serde_json::to_string(&status)?)?,
+            status
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_start_in_dependency_order_waits_for_dependencies() -> anyhow::Result<()> {
+        let config = helper::config(&[
+            ("app", &["cache", "queue"]),
+            ("cache", &["store"]),
+            ("queue", &["store"]),
+            ("store", &[]),
+        ])?;
+        let stages = startup_stages(&config, &["app".to_string()])?;
+        let events = Mutex::new(Vec::new());
+
+        start_in_dependency_order(
+            stages,
+            |name| {
+                events.lock().unwrap().push(format!("start {name}"));
+                async { Ok(()) }
+            },
+            |name| {
+                let events = &events;
+                async move {
+                    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                    events.lock().unwrap().push(format!("ready {name}"));
+                    Ok(())
+                }
+            },
+        )
+        .await?;
+
+        let events = events.into_inner().unwrap();
+        let position = |event: &str| events.iter().position(|e| e == event).unwrap();
+        for (dependent, dependency) in [
+            ("app", "cache"),
+            ("app", "queue"),
+            ("cache", "store"),
+            ("queue", "store"),
+        ] {
+            assert!(
+                position(&format!("ready {dependency}")) < position(&format!("start {dependent}")),
+                "{dependent} started before {dependency} was ready: {events:?}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_start_in_dependency_order_stops_at_first_failure() -> anyhow::Result<()> {
+        let config = helper::config(&[("api", &["db"]), ("db", &[])])?;
+        let stages = startup_stages(&config, &["api".to_string()])?;
+        let started = Mutex::new(Vec::new());
+
+        let result = start_in_dependency_order(
+            stages,
+            |name| {
+                started.lock().unwrap().push(name);
+                async { Ok(()) }
+            },
+            |name| async move {
+                Err(MonocoreError::SandboxReadyTimeout(
+                    name,
+                    DEFAULT_SANDBOX_READY_TIMEOUT,
+                ))
+            },
+        )
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(MonocoreError::SandboxReadyTimeout(ref name, _)) if name == "db"
+        ));
+        assert_eq!(started.into_inner().unwrap(), ["db"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_plan_classifies_sandboxes() -> anyhow::Result<()> {
+        let config = helper::config(&[("api", &[]), ("db", &[]), ("legacy", &[]), ("web", &[])])?;
+        let started_at = Utc::now();
+        let running = [
+            helper::running_record_with_hash(
+                "db",
+                Some(&config.get_sandboxes()["db"].spec_hash()?),
+                started_at,
+            ),
+            helper::running_record_with_hash("api", Some("stale"), started_at),
+            helper::running_record_with_hash("legacy", None, started_at),
+            helper::running_record_with_hash("old", Some("stale"), started_at),
+        ];
+
+        let plan = ApplyPlan::new(&config, &running)?;
+        assert_eq!(plan.get_create(), &["web"]);
+        assert_eq!(plan.get_update(), &["api", "legacy"]);
+        assert_eq!(plan.get_remove(), &["old"]);
+        assert_eq!(plan.get_unchanged(), &["db"]);
+        assert!(!plan.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_plan_is_empty_when_up_to_date() -> anyhow::Result<()> {
+        let config = helper::config(&[("api", &["db"]), ("db", &[])])?;
+        let running: Vec<models::Sandbox> = config
+            .get_sandboxes()
+            .iter()
+            .map(|(name, sandbox)| {
+                Ok(helper::running_record_with_hash(
+                    name,
+                    Some(&sandbox.spec_hash()?),
+                    Utc::now(),
+                ))
+            })
+            .collect::<MonocoreResult<_>>()?;
+
+        let plan = ApplyPlan::new(&config, &running)?;
+        assert!(plan.is_empty());
+        assert_eq!(plan.get_unchanged(), &["api", "db"]);
+
+        // Nothing running means everything is created
+        let plan = ApplyPlan::new(&config, &[])?;
+        assert_eq!(plan.get_create(), &["api", "db"]);
+        assert!(plan.get_update().is_empty() && plan.get_remove().is_empty());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod helper {
+    use super::*;
+
+    /// Builds a configuration with the given sandboxes and their dependencies.
+    pub(super) fn config(sandboxes: &[(&str, &[&str])]) -> MonocoreResult<Monocore> {
+        let sandboxes = sandboxes
+            .iter()
+            .map(|(name, depends_on)| {
+                format!(
+                    "  {name}:\n    image: alpine\n    shell: /bin/sh\n    depends_on: [{}]\n",
+                    depends_on.join(", ")
+                )
+            })
+            .collect::<String>();
+
+        Ok(serde_yaml::from_str(&format!("sandboxes:\n{sandboxes}"))?)
+    }
+
+    /// Builds a configuration with the given sandboxes and their port mappings.
+    pub(super) fn config_with_ports(sandboxes: &[(&str, &[&str])]) -> MonocoreResult<Monocore> {
+        let sandboxes = sandboxes
+            .iter()
+            .map(|(name, ports)| {
+                let ports: Vec<String> = ports.iter().map(|port| format!("\"{port}\"")).collect();
+                format!(
+                    "  {name}:\n    image: alpine\n    shell: /bin/sh\n    ports: [{}]\n",
+                    ports.join(", ")
+                )
+            })
+            .collect::<String>();
+
+        Ok(serde_yaml::from_str(&format!("sandboxes:\n{sandboxes}"))?)
+    }
+
+    /// Builds the database record of a sandbox that has been running since `started_at`, started
+    /// with the spec hash `config_hash`.
+    pub(super) fn running_record_with_hash(
+        name: &str,
+        config_hash: Option<&str>,
+        started_at: DateTime<Utc>,
+    ) -> models::Sandbox {
+        models::Sandbox {
+            config_hash: config_hash.map(String::from),
+            ..running_record(name, 1001, started_at)
+        }
+    }
+
+    /// Builds the database record of a sandbox that has been running since `started_at`.
+    pub(super) fn running_record(
+        name: &str,
+        microvm_pid: u32,
+        started_at: DateTime<Utc>,
+    ) -> models::Sandbox {
+        models::Sandbox {
+            id: 1,
+            name: name.to_string(),
+            config_file: "monocore.yaml".to_string(),
+            config_last_modified: started_at,
+            config_hash: None,
+            status: crate::runtime::SANDBOX_STATUS_RUNNING.to_string(),
+            supervisor_pid: microvm_pid - 1,
+            microvm_pid,
+            rootfs_paths: String::new(),
+            group_id: None,
+            group_ip: None,
+            started_at: Some(started_at),
+            health: None,
+            created_at: started_at,
+            modified_at: started_at,
+        }
+    }
 }
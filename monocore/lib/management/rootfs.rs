@@ -1,8 +1,11 @@
 //! Root filesystem management for Monocore sandboxes.
 //!
 //! This module provides functionality for managing root filesystems used by Monocore sandboxes.
-//! It handles the creation, extraction, and merging of filesystem layers following OCI (Open
-//! Container Initiative) specifications.
+//!
+//! Image layers are never flattened into a single rootfs here. The extracted layers are passed to
+//! the MicroVM as [`Rootfs::Overlayfs`](crate::vm::Rootfs::Overlayfs) lower layers, and the OCI
+//! whiteouts (`.wh.<name>`) and opaque directory markers (`.wh..wh..opq`) in them are applied by
+//! the overlay filesystem the MicroVM mounts.
 
 use std::{
     borrow::Cow, collections::HashMap, fs::Permissions, os::unix::fs::PermissionsExt, path::Path,
@@ -12,16 +15,6 @@ use tokio::fs;
 
 use crate::{config::PathPair, vm::VIRTIOFS_TAG_PREFIX, MonocoreResult};
 
-//--------------------------------------------------------------------------------------------------
-// Constants
-//--------------------------------------------------------------------------------------------------
-
-/// The opaque directory marker file name used in OCI layers.
-pub const OPAQUE_WHITEOUT_MARKER: &str = ".wh..wh..opq";
-
-/// The prefix for whiteout files in OCI layers.
-pub const WHITEOUT_PREFIX: &str = ".wh.";
-
 //--------------------------------------------------------------------------------------------------
 // Functions
 //--------------------------------------------------------------------------------------------------
@@ -89,7 +82,8 @@ pub async fn patch_with_sandbox_scripts(
 /// ```text
 /// virtiofs_N  /guest/path  virtiofs  defaults  0  0
 /// ```
-/// where N is the index of the mapped directory.
+/// where N is the index of the mapped directory. Read-only directories are mounted with the
+/// `defaults,ro` options instead.
 ///
 /// ## Arguments
 /// * `root_path` - Path to the guest rootfs
@@ -130,11 +124,16 @@ async fn _patch_with_virtiofs_mounts(
     for (idx, dir) in mapped_dirs.iter().enumerate() {
         let tag = format!("{}_{}", VIRTIOFS_TAG_PREFIX, idx);
         let guest_path = dir.get_guest();
+        let options = if dir.is_read_only() {
+            "defaults,ro"
+        } else {
+            "defaults"
+        };
 
         // Add entry for this mapped directory
         fstab_content.push_str(&format!(
-            "{}\t{}\tvirtiofs\tdefaults\t0\t0\n",
-            tag, guest_path
+            "{}\t{}\tvirtiofs\t{}\t0\t0\n",
+            tag, guest_path, options
         ));
 
         // Create the mount point directory in the guest rootfs
@@ -263,7 +262,7 @@ mod tests {
         let mapped_dirs = vec![
             format!("{}:/container/data", host_data.display()).parse::<PathPair>()?,
             format!("{}:/etc/app/config", host_config.display()).parse::<PathPair>()?,
-            format!("{}:/app", host_app.display()).parse::<PathPair>()?,
+            format!("{}:/app:ro", host_app.display()).parse::<PathPair>()?,
         ];
 
         // Update fstab
@@ -283,7 +282,7 @@ mod tests {
         // Check entries
         assert!(fstab_content.contains("virtiofs_0\t/container/data\tvirtiofs\tdefaults\t0\t0"));
         assert!(fstab_content.contains("virtiofs_1\t/etc/app/config\tvirtiofs\tdefaults\t0\t0"));
-        assert!(fstab_content.contains("virtiofs_2\t/app\tvirtiofs\tdefaults\t0\t0"));
+        assert!(fstab_content.contains("virtiofs_2\t/app\tvirtiofs\tdefaults,ro\t0\t0"));
 
         // Verify mount points were created
         assert!(root_path.join("container/data").exists());
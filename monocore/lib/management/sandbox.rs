@@ -6,26 +6,38 @@
 
 use std::{
     path::{Path, PathBuf},
-    process::Stdio,
+    process::{ExitStatus, Stdio},
+    time::Duration,
 };
 
 use chrono::{DateTime, Utc};
+use futures::{stream, Stream, StreamExt};
+use nix::{
+    sys::signal::{self, Signal},
+    unistd::Pid,
+};
 use sqlx::{Pool, Sqlite};
 use tempfile;
-use tokio::{fs, process::Command};
+use tokio::{
+    fs,
+    process::{Child, Command},
+    signal::unix::{self, SignalKind},
+    time,
+};
 use typed_path::Utf8UnixPathBuf;
 
 use crate::{
     config::{
-        EnvPair, Monocore, PathPair, PortPair, ReferenceOrPath, Sandbox, DEFAULT_MCRUN_EXE_PATH,
+        parse_env_arg, parse_env_file, EnvPair, Monocore, PathPair, PortPair, ReferenceOrPath,
+        Sandbox, SeccompProfile, DEFAULT_MCRUN_EXE_PATH, DEFAULT_SANDBOX_STOP_GRACE_PERIOD,
         START_SCRIPT_NAME,
     },
-    management::{config, db, image, menv, rootfs},
+    management::{config, db, exec, image, menv, rootfs},
     oci::Reference,
     utils::{
-        env, EXTRACTED_LAYER_SUFFIX, LAYERS_SUBDIR, LOG_SUBDIR, MCRUN_EXE_ENV_VAR,
-        MONOCORE_CONFIG_FILENAME, MONOCORE_ENV_DIR, OCI_DB_FILENAME, PATCH_SUBDIR, RW_SUBDIR,
-        SANDBOX_DB_FILENAME, SANDBOX_SCRIPT_DIR, SHELL_SCRIPT_NAME,
+        env, AGENT_SOCKET_SUBDIR, EXTRACTED_LAYER_SUFFIX, LAYERS_SUBDIR, LOG_SUBDIR,
+        MCRUN_EXE_ENV_VAR, MONOCORE_CONFIG_FILENAME, MONOCORE_ENV_DIR, OCI_DB_FILENAME,
+        PATCH_SUBDIR, RW_SUBDIR, SANDBOX_DB_FILENAME, SANDBOX_SCRIPT_DIR, SHELL_SCRIPT_NAME,
     },
     vm::Rootfs,
     MonocoreError, MonocoreResult,
@@ -37,6 +49,9 @@ use crate::{
 
 const TEMPORARY_SANDBOX_NAME: &str = "tmp";
 
+/// The name the env file of a temporary sandbox is copied to in its project directory
+const TEMPORARY_ENV_FILENAME: &str = ".env";
+
 //--------------------------------------------------------------------------------------------------
 // Functions
 //--------------------------------------------------------------------------------------------------
@@ -120,6 +135,10 @@ pub async fn run(
 
     tracing::debug!("Original sandbox config: {:#?}", sandbox_config);
 
+    // Hash the sandbox spec as configured, before image defaults are applied, so that `apply` can
+    // detect config changes without pulling images
+    let config_hash = sandbox_config.spec_hash()?;
+
     // Sandbox database path
     let sandbox_db_path = menv_path.join(SANDBOX_DB_FILENAME);
 
@@ -162,6 +181,13 @@ pub async fn run(
     let log_dir = menv_path.join(LOG_SUBDIR);
     fs::create_dir_all(&log_dir).await?;
 
+    // Guest agent socket. The MicroVm creates it, so a stale one from a previous run is removed.
+    let agent_socket = exec::agent_socket_path(&menv_path, &config_file, sandbox_name);
+    fs::create_dir_all(menv_path.join(AGENT_SOCKET_SUBDIR)).await?;
+    if fs::try_exists(&agent_socket).await? {
+        fs::remove_file(&agent_socket).await?;
+    }
+
     // Get the exec path. If exec is provided, use it as the exec path.
     // Otherwise, use the script name.
     let exec_path = match exec {
@@ -187,12 +213,16 @@ pub async fn run(
         .arg(&config_file)
         .arg("--config-last-modified")
         .arg(&config_last_modified.to_rfc3339())
+        .arg("--config-hash")
+        .arg(&config_hash)
         .arg("--sandbox-db-path")
         .arg(&sandbox_db_path)
         .arg("--scope")
         .arg(sandbox_config.get_scope().to_string())
         .arg("--exec-path")
-        .arg(&exec_path);
+        .arg(&exec_path)
+        .arg("--agent-socket")
+        .arg(&agent_socket);
 
     // CPU
     if let Some(cpus) = sandbox_config.get_cpus() {
@@ -224,6 +254,44 @@ pub async fn run(
         command.arg("--mapped-dir").arg(volume.to_string());
     }
 
+    // Seccomp profile, with profile paths resolved relative to the project directory
+    if let Some(seccomp) = sandbox_config.get_seccomp() {
+        let seccomp = match seccomp {
+            SeccompProfile::Path(path) => SeccompProfile::Path(
+                canonical_project_dir
+                    .join(path.as_str())
+                    .display()
+                    .to_string()
+                    .into(),
+            ),
+            profile => profile.clone(),
+        };
+        command.arg("--seccomp-profile").arg(seccomp.to_string());
+    }
+
+    // Capabilities
+    for cap in sandbox_config.get_cap_add() {
+        command.arg("--cap-add").arg(cap.to_string());
+    }
+
+    for cap in sandbox_config.get_cap_drop() {
+        command.arg("--cap-drop").arg(cap.to_string());
+    }
+
+    // Restart policy
+    if let Some(restart) = sandbox_config.get_restart() {
+        command.arg("--restart-policy").arg(restart.to_string());
+    }
+
+    if let Some(max_restarts) = sandbox_config.get_max_restarts() {
+        command.arg("--max-restarts").arg(max_restarts.to_string());
+    }
+
+    // Guest agent health probing
+    if let Some(boot_timeout) = sandbox_config.get_boot_timeout() {
+        command.arg("--boot-timeout").arg(boot_timeout.to_string());
+    }
+
     // Pass the rootfs
     match rootfs {
         Rootfs::Native(path) => {
@@ -298,8 +366,15 @@ pub async fn run(
         return Ok(());
     }
 
-    // Wait for the child process to complete
-    let status = child.wait().await?;
+    // Wait for the child process to complete, forwarding SIGINT and SIGTERM to the guest's init so
+    // the sandbox can shut down cleanly
+    let status = wait_forwarding_signals(
+        &mut child,
+        &agent_socket,
+        shutdown_signals()?,
+        DEFAULT_SANDBOX_STOP_GRACE_PERIOD,
+    )
+    .await?;
     if !status.success() {
         tracing::error!(
             "child process — supervisor — exited with status: {}",
@@ -329,6 +404,8 @@ pub async fn run(
 /// * `volumes` - List of volume mappings in the format "host_path:guest_path"
 /// * `ports` - List of port mappings in the format "host_port:guest_port"
 /// * `envs` - List of environment variables in the format "KEY=VALUE"
+/// * `env_file` - Optional path to an env file of "KEY=VALUE" lines. Variables in `envs` take
+///   precedence over it
 /// * `workdir` - Optional working directory path inside the sandbox
 /// * `exec` - Optional command to execute within the sandbox. Overrides `script` if provided.
 /// * `args` - Additional arguments to pass to the specified script or command
@@ -368,6 +445,7 @@ pub async fn run(
 ///         vec![              // Set environment variables
 ///             "DEBUG=1".to_string()
 ///         ],
+///         None,              // No env file
 ///         Some("/app".into()), // Set working directory
 ///         None,              // No exec command
 ///         vec![],            // No additional args
@@ -384,6 +462,7 @@ pub async fn run_temp(
     volumes: Vec<String>,
     ports: Vec<String>,
     envs: Vec<String>,
+    env_file: Option<&Path>,
     workdir: Option<Utf8UnixPathBuf>,
     exec: Option<&str>,
     args: Vec<String>,
@@ -397,9 +476,29 @@ pub async fn run_temp(
     menv::initialize(Some(temp_dir_path.clone())).await?;
 
     // Parse the volume, port, and env strings into their respective types
-    let volumes: Vec<PathPair> = volumes.into_iter().filter_map(|v| v.parse().ok()).collect();
-    let ports: Vec<PortPair> = ports.into_iter().filter_map(|p| p.parse().ok()).collect();
-    let envs: Vec<EnvPair> = envs.into_iter().filter_map(|e| e.parse().ok()).collect();
+    let volumes: Vec<PathPair> = volumes
+        .iter()
+        .map(|v| v.parse())
+        .collect::<MonocoreResult<_>>()?;
+    let ports: Vec<PortPair> = ports
+        .iter()
+        .map(|p| PortPair::parse_range(p))
+        .collect::<MonocoreResult<Vec<_>>>()?
+        .into_iter()
+        .flatten()
+        .collect();
+    let envs: Vec<EnvPair> = envs
+        .iter()
+        .map(|e| parse_env_arg(e))
+        .collect::<MonocoreResult<_>>()?;
+
+    // Validate the env file and copy it next to the temporary config, so it is merged into the
+    // environment variables when the sandbox is loaded, like the env file of any other sandbox
+    if let Some(env_file) = env_file {
+        let contents = fs::read_to_string(env_file).await?;
+        parse_env_file(&contents)?;
+        fs::write(temp_dir_path.join(TEMPORARY_ENV_FILENAME), contents).await?;
+    }
 
     // Build the temporary sandbox configuration.
     let sandbox = {
@@ -429,6 +528,10 @@ pub async fn run_temp(
             b = b.envs(envs);
         }
 
+        if env_file.is_some() {
+            b = b.env_file(TEMPORARY_ENV_FILENAME);
+        }
+
         b.build()
     };
 
@@ -465,6 +568,92 @@ pub async fn run_temp(
 // Functions: Helpers
 //--------------------------------------------------------------------------------------------------
 
+/// Waits for the supervisor process to exit, forwarding a shutdown signal to the sandbox's init
+/// through its guest agent.
+///
+/// If the sandbox doesn't exit within the grace period after the signal is forwarded, or another
+/// shutdown signal is received first, the supervisor is sent SIGTERM so it can stop the MicroVM
+/// and clean up. The supervisor is killed if that doesn't stop it either.
+///
+/// ## Arguments
+///
+/// * `child` - The supervisor process
+/// * `agent_socket` - The path of the UNIX socket the guest agent is reachable through
+/// * `shutdown` - Yields each shutdown signal as it is received
+/// * `grace_period` - How long to wait for the sandbox to exit before escalating
+async fn wait_forwarding_signals(
+    child: &mut Child,
+    agent_socket: &Path,
+    mut shutdown: impl Stream<Item = Signal> + Unpin,
+    grace_period: Duration,
+) -> MonocoreResult<ExitStatus> {
+    let signal = tokio::select! {
+        status = child.wait() => return Ok(status?),
+        Some(signal) = shutdown.next() => signal,
+    };
+
+    tracing::info!("forwarding {} to the sandbox", signal);
+    if let Err(e) = exec::signal_with_agent(agent_socket, signal).await {
+        tracing::warn!("failed to forward {} to the sandbox: {}", signal, e);
+    }
+
+    if let Some(status) = wait_or_escalate(child, &mut shutdown, grace_period).await? {
+        return Ok(status);
+    }
+
+    if let Some(pid) = child.id() {
+        tracing::info!("sending SIGTERM to the supervisor");
+        if let Err(e) = signal::kill(Pid::from_raw(pid as i32), Signal::SIGTERM) {
+            tracing::warn!("failed to send SIGTERM to the supervisor: {}", e);
+        }
+
+        if let Some(status) = wait_or_escalate(child, &mut shutdown, grace_period).await? {
+            return Ok(status);
+        }
+    }
+
+    tracing::warn!("killing the supervisor");
+    child.kill().await?;
+    Ok(child.wait().await?)
+}
+
+/// Waits for the supervisor process to exit, returning `None` if the grace period is over or
+/// another shutdown signal is received first.
+async fn wait_or_escalate(
+    child: &mut Child,
+    shutdown: &mut (impl Stream<Item = Signal> + Unpin),
+    grace_period: Duration,
+) -> MonocoreResult<Option<ExitStatus>> {
+    tokio::select! {
+        status = child.wait() => Ok(Some(status?)),
+        _ = time::sleep(grace_period) => {
+            tracing::warn!("sandbox did not exit within {:?}", grace_period);
+            Ok(None)
+        }
+        Some(signal) = shutdown.next() => {
+            tracing::warn!("received {} again while the sandbox is shutting down", signal);
+            Ok(None)
+        }
+    }
+}
+
+/// Starts listening for SIGINT and SIGTERM, returning a stream that yields each one as it is
+/// received.
+fn shutdown_signals() -> MonocoreResult<impl Stream<Item = Signal> + Unpin> {
+    let sigint = unix::signal(SignalKind::interrupt())?;
+    let sigterm = unix::signal(SignalKind::terminate())?;
+
+    let signals = stream::unfold((sigint, sigterm), |(mut sigint, mut sigterm)| async move {
+        let signal = tokio::select! {
+            _ = sigint.recv() => Signal::SIGINT,
+            _ = sigterm.recv() => Signal::SIGTERM,
+        };
+        Some((signal, (sigint, sigterm)))
+    });
+
+    Ok(signals.boxed())
+}
+
 async fn setup_image_rootfs(
     image: &Reference,
     sandbox_name: &str,
@@ -564,6 +753,8 @@ async fn setup_image_rootfs(
     layer_paths.push(patch_dir);
     layer_paths.push(top_rw_path);
 
+    // The layers are overlaid in the MicroVM rather than flattened, so their OCI whiteouts and
+    // opaque directory markers are applied by the overlay filesystem there
     Ok(Rootfs::Overlayfs(layer_paths))
 }
 
@@ -629,3 +820,141 @@ async fn has_sandbox_config_changed(
         None => true, // No existing sandbox, need to patch
     })
 }
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use tempfile::TempDir;
+    use tokio::{
+        io::{AsyncBufReadExt, BufReader},
+        net::UnixListener,
+    };
+
+    use crate::management::exec::AgentRequest;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wait_forwarding_signals() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let agent_socket = temp_dir.path().join("agent.sock");
+        let listener = UnixListener::bind(&agent_socket)?;
+
+        // A fake guest that shuts down when its init is signalled
+        let mut child = helper::spawn_fake_guest()?;
+        let pid = Pid::from_raw(child.id().unwrap() as i32);
+        let agent = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await?;
+            let mut lines = BufReader::new(stream).lines();
+            let request: AgentRequest = serde_json::from_str(&lines.next_line().await?.unwrap())?;
+            signal::kill(pid, Signal::SIGTERM)?;
+            anyhow::Ok(request)
+        });
+
+        let grace_period = Duration::from_secs(10);
+        let start = Instant::now();
+        let status = wait_forwarding_signals(
+            &mut child,
+            &agent_socket,
+            helper::signals([Signal::SIGINT]),
+            grace_period,
+        )
+        .await?;
+
+        assert!(start.elapsed() < grace_period);
+        assert!(!status.success());
+        assert_eq!(agent.await??, AgentRequest::Signal { signal: 2 });
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_wait_forwarding_signals_kills_after_grace_period() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let agent_socket = temp_dir.path().join("agent.sock");
+
+        // No agent to forward the signal to, so the fake guest never exits on its own
+        let mut child = helper::spawn_fake_guest()?;
+        let start = Instant::now();
+        let status = wait_forwarding_signals(
+            &mut child,
+            &agent_socket,
+            helper::signals([Signal::SIGTERM]),
+            Duration::from_millis(200),
+        )
+        .await?;
+
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(!status.success());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_wait_forwarding_signals_escalates_on_repeated_signal() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let agent_socket = temp_dir.path().join("agent.sock");
+
+        // A second signal stops the supervisor without waiting out the grace period
+        let mut child = helper::spawn_fake_guest()?;
+        let grace_period = Duration::from_secs(10);
+        let start = Instant::now();
+        let status = wait_forwarding_signals(
+            &mut child,
+            &agent_socket,
+            helper::signals([Signal::SIGINT, Signal::SIGINT]),
+            grace_period,
+        )
+        .await?;
+
+        assert!(start.elapsed() < grace_period);
+        assert_eq!(
+            std::os::unix::process::ExitStatusExt::signal(&status),
+            Some(Signal::SIGTERM as i32)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_wait_forwarding_signals_without_signal() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let agent_socket = temp_dir.path().join("agent.sock");
+
+        let mut child = Command::new("true").spawn()?;
+        let status = wait_forwarding_signals(
+            &mut child,
+            &agent_socket,
+            stream::pending(),
+            Duration::from_millis(200),
+        )
+        .await?;
+
+        assert!(status.success());
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod helper {
+    use super::*;
+
+    /// Spawns a process standing in for a sandbox that only exits when signalled.
+    pub(super) fn spawn_fake_guest() -> MonocoreResult<Child> {
+        let child = Command::new("sleep").arg("30").kill_on_drop(true).spawn()?;
+        Ok(child)
+    }
+
+    /// Returns a stream of shutdown signals that stays open once they are all yielded.
+    pub(super) fn signals<const N: usize>(
+        signals: [Signal; N],
+    ) -> impl Stream<Item = Signal> + Unpin {
+        stream::iter(signals).chain(stream::pending())
+    }
+}
@@ -6,7 +6,9 @@
 //!
 //! Key features include:
 //! - Starting the server with configurable options (port, namespace path, etc.)
-//! - Stopping the server and cleaning up resources
+//! - Stopping the server and cleaning up resources, after in-flight requests have been drained
+//! - Requiring a bearer token on API requests in secure mode, with the secret key taken from
+//!   `--key`, the `MONOCORE_SERVER_KEY` environment variable, or generated
 //!
 //! The server uses a PID file to track the running process and supports
 //! detached mode for running as a background service.
@@ -16,7 +18,10 @@ use std::{path::PathBuf, process::Stdio};
 use chrono::{Duration, Utc};
 use jsonwebtoken::{encode, EncodingKey, Header};
 use rand::{distributions::Alphanumeric, Rng};
-use tokio::{fs, process::Command};
+use tokio::{
+    fs,
+    process::{Child, Command},
+};
 
 use crate::{
     config::DEFAULT_MCRUN_EXE_PATH,
@@ -35,6 +40,20 @@ const SERVER_KEY_LENGTH: usize = 32;
 /// Prefix for the API key
 pub const API_KEY_PREFIX: &str = "msb_";
 
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// Options controlling how the sandbox server shuts down.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ShutdownOptions {
+    /// Seconds to wait for in-flight requests to complete, or the server's default if `None`.
+    pub timeout: Option<u64>,
+
+    /// Whether to stop running sandboxes when the server shuts down.
+    pub stop_sandboxes: bool,
+}
+
 //--------------------------------------------------------------------------------------------------
 // Functions
 //--------------------------------------------------------------------------------------------------
@@ -46,6 +65,7 @@ pub async fn start(
     disable_default: bool,
     secure: bool,
     key: Option<String>,
+    shutdown: ShutdownOptions,
     detach: bool,
 ) -> MonocoreResult<()> {
     // Ensure monocore home directory exists
@@ -71,12 +91,22 @@ pub async fn start(
         command.arg("--disable-default");
     }
 
+    if let Some(shutdown_timeout) = shutdown.timeout {
+        command
+            .arg("--shutdown-timeout")
+            .arg(shutdown_timeout.to_string());
+    }
+
+    if shutdown.stop_sandboxes {
+        command.arg("--stop-sandboxes-on-shutdown");
+    }
+
     // Handle secure mode and key
     if secure {
         // Create a key file with either the provided key or a generated one
         let key_file_path = monocore_home_path.join(SERVER_KEY_FILE);
 
-        let server_key = if let Some(key) = key {
+        let server_key = if let Some(key) = key.or_else(utils::get_server_key) {
             command.arg("--key").arg(&key);
             key
         } else {
@@ -175,10 +205,8 @@ pub async fn start(
         _ = sigterm.recv() => {
             tracing::info!("received SIGTERM signal");
 
-            // Send SIGTERM to child process
-            if let Err(e) = child.kill().await {
-                tracing::error!("failed to send SIGTERM to child process: {}", e);
-            }
+            // Forward SIGTERM so the server can drain in-flight requests
+            forward_sigterm(&child);
 
             // Wait for child to exit after sending signal
             if let Err(e) = child.wait().await {
@@ -194,10 +222,8 @@ pub async fn start(
         _ = sigint.recv() => {
             tracing::info!("received SIGINT signal");
 
-            // Send SIGTERM to child process
-            if let Err(e) = child.kill().await {
-                tracing::error!("failed to send SIGTERM to child process: {}", e);
-            }
+            // Forward SIGTERM so the server can drain in-flight requests
+            forward_sigterm(&child);
 
             // Wait for child to exit after sending signal
             if let Err(e) = child.wait().await {
@@ -216,6 +242,9 @@ pub async fn start(
 }
 
 /// Stop the sandbox server
+///
+/// The server is sent SIGTERM, so it stops accepting connections and drains in-flight requests
+/// before exiting.
 pub async fn stop() -> MonocoreResult<()> {
     let monocore_home_path = utils::get_monocore_home_path();
     let pid_file_path = monocore_home_path.join(SERVER_PID_FILE);
@@ -284,7 +313,16 @@ pub async fn keygen(expire: Option<Duration>) -> MonocoreResult<()> {
 
     // Determine token expiration (default: 24 hours)
     let expire = expire.unwrap_or(Duration::hours(24));
+    let custom_token = generate_api_key(&server_key, expire)?;
 
+    println!("Generated new API token:");
+    println!("{}", custom_token);
+
+    Ok(())
+}
+
+/// Generate an API key signed with `server_key` that expires after `expire`
+pub(crate) fn generate_api_key(server_key: &str, expire: Duration) -> MonocoreResult<String> {
     // Generate JWT token with the specified expiration
     let now = Utc::now();
     let expiry = now + expire;
@@ -303,12 +341,21 @@ pub async fn keygen(expire: Option<Duration>) -> MonocoreResult<()> {
     .map_err(|e| MonocoreError::SandboxServerError(format!("Failed to generate token: {}", e)))?;
 
     // Convert the JWT token to our custom API key format
-    let custom_token = convert_jwt_to_api_key(&jwt_token)?;
+    convert_jwt_to_api_key(&jwt_token)
+}
 
-    println!("Generated new API token:");
-    println!("{}", custom_token);
+/// Send SIGTERM to the server child process, if it is still running
+fn forward_sigterm(child: &Child) {
+    let Some(pid) = child.id() else {
+        return;
+    };
 
-    Ok(())
+    if unsafe { libc::kill(pid as i32, libc::SIGTERM) } != 0 {
+        tracing::error!(
+            "failed to send SIGTERM to child process: {}",
+            std::io::Error::last_os_error()
+        );
+    }
 }
 
 /// Generate a random key for JWT token signing
@@ -0,0 +1,4 @@
+-- Add down migration script here
+
+-- Drop the sandbox spec hash
+ALTER TABLE sandboxes DROP COLUMN config_hash;
@@ -0,0 +1,4 @@
+-- Add up migration script here
+
+-- Add the hash of the sandbox spec the sandbox was started with
+ALTER TABLE sandboxes ADD COLUMN config_hash TEXT;
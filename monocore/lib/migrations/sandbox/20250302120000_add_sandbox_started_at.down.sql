@@ -0,0 +1,4 @@
+-- Add down migration script here
+
+-- Drop when the sandbox's MicroVM was last started
+ALTER TABLE sandboxes DROP COLUMN started_at;
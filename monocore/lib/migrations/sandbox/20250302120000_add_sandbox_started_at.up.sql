@@ -0,0 +1,4 @@
+-- Add up migration script here
+
+-- Add when the sandbox's MicroVM was last started
+ALTER TABLE sandboxes ADD COLUMN started_at DATETIME;
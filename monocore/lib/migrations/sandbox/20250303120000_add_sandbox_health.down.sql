@@ -0,0 +1,4 @@
+-- Add down migration script here
+
+-- Drop the health of the sandbox's MicroVM
+ALTER TABLE sandboxes DROP COLUMN health;
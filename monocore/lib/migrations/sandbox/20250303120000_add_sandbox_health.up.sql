@@ -0,0 +1,4 @@
+-- Add up migration script here
+
+-- Add the health of the sandbox's MicroVM as last reported by its supervisor
+ALTER TABLE sandboxes ADD COLUMN health TEXT;
@@ -21,6 +21,9 @@ pub struct Sandbox {
     /// The last modified date and time of the Monocore configuration file.
     pub config_last_modified: DateTime<Utc>,
 
+    /// The hash of the sandbox spec the sandbox was started with, if it was recorded.
+    pub config_hash: Option<String>,
+
     /// The status of the sandbox.
     pub status: String,
 
@@ -39,6 +42,13 @@ pub struct Sandbox {
     /// The IP address of the group that the sandbox belongs to.
     pub group_ip: Option<String>,
 
+    /// When the MicroVM of the sandbox was last started, if it was recorded.
+    pub started_at: Option<DateTime<Utc>>,
+
+    /// The health of the MicroVM as last reported by the sandbox's supervisor, if it was reported
+    /// since the MicroVM was last started.
+    pub health: Option<String>,
+
     /// When the sandbox was created
     pub created_at: DateTime<Utc>,
 
@@ -0,0 +1,1013 @@
+use std::{
+    error::Error,
+    future::Future,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use futures::{stream::BoxStream, StreamExt};
+use getset::Getters;
+use oci_spec::image::{Digest, DigestAlgorithm};
+use tokio::{
+    fs::{self, OpenOptions},
+    io::AsyncWriteExt,
+};
+
+use crate::{
+    config::{DEFAULT_DOWNLOAD_MAX_RETRIES, DEFAULT_DOWNLOAD_RETRY_BACKOFF},
+    utils, MonocoreError, MonocoreResult,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The suffix of blobs that are still being downloaded.
+const PARTIAL_BLOB_SUFFIX: &str = "partial";
+
+/// The subdirectory of the cache where the index digests that image tags point to are stored.
+const REFS_SUBDIR: &str = "refs";
+
+/// A counter that makes the download paths of this process unique.
+static DOWNLOAD_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// A content-addressed cache of the blobs pulled from an image registry.
+///
+/// Each blob, be it a layer, an index, a manifest or a config, is stored in the cache directory
+/// under its digest, e.g. `sha256:<hex>`, so blobs shared between images are downloaded once. A
+/// blob is only moved under its digest after its content is verified, and it is verified again
+/// every time it is read from the cache.
+///
+/// Each download writes to a file of its own, so concurrent downloads of the same blob, from this
+/// process or another, never write to the same file. A download interrupted by a transient error
+/// is resumed from its last byte after an exponential backoff, and one that still fails is left as
+/// `<digest>.partial`, to be resumed by the next download of the same blob. Only one download can
+/// claim a given `.partial` file, and the others start over.
+///
+/// Tags can move, so they aren't content-addressed. The digest of the index a tag pointed to when
+/// it was last pulled is kept in `refs/<repository>/<tag>`, so the image can be pulled offline.
+#[derive(Debug, Clone, Getters)]
+#[getset(get = "pub with_prefix")]
+pub struct BlobCache {
+    /// The directory where blobs are stored.
+    dir: PathBuf,
+
+    /// The number of times an interrupted download is resumed before giving up.
+    max_retries: u32,
+
+    /// The delay before resuming an interrupted download, doubled with each retry.
+    retry_backoff: Duration,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl BlobCache {
+    /// Creates a cache that stores blobs in `dir`. The directory is created on the first download.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_retries: DEFAULT_DOWNLOAD_MAX_RETRIES,
+            retry_backoff: DEFAULT_DOWNLOAD_RETRY_BACKOFF,
+        }
+    }
+
+    /// Sets the number of times an interrupted download is resumed before giving up.
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Sets the delay before resuming an interrupted download, which is doubled with each retry.
+    pub fn with_retry_backoff(mut self, retry_backoff: Duration) -> Self {
+        self.retry_backoff = retry_backoff;
+        self
+    }
+
+    /// Returns the path where the blob with `digest` is stored when it is cached.
+    pub fn blob_path(&self, digest: &Digest) -> PathBuf {
+        self.dir.join(digest.to_string())
+    }
+
+    /// Returns the path of the cached blob with `digest`, or `None` if it is not cached.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `MonocoreError::CorruptedBlob` if the cached blob doesn't match its digest.
+    pub async fn get(&self, digest: &Digest) -> MonocoreResult<Option<PathBuf>> {
+        let path = self.blob_path(digest);
+        if !fs::try_exists(&path).await? {
+            return Ok(None);
+        }
+
+        let actual_hash = hash_file(&path, digest).await?;
+        if actual_hash != digest.digest() {
+            return Err(MonocoreError::CorruptedBlob(format!(
+                "{} hashes to {actual_hash}",
+                path.display()
+            )));
+        }
+
+        Ok(Some(path))
+    }
+
+    /// Returns the path of the cached blob with `digest`, downloading it with `fetch` first if it
+    /// is not cached or the cached copy is corrupted.
+    ///
+    /// `fetch` is called with the offset to download from, which is past the start of the blob
+    /// when resuming an interrupted download, and is not called at all on a cache hit. When
+    /// `fetch` or the stream it returns fails, or the stream ends before the end of the blob, the
+    /// download is resumed from its last byte, up to the configured number of retries.
+    ///
+    /// ## Arguments
+    ///
+    /// * `digest` - The digest of the blob
+    /// * `size` - The size of the blob in bytes
+    /// * `fetch` - Starts downloading the blob from the given offset
+    ///
+    /// ## Errors
+    ///
+    /// Returns the error of the last attempt if the download is still interrupted after all
+    /// retries. What was downloaded is kept, so the next attempt resumes it.
+    ///
+    /// Returns `MonocoreError::ImageLayerDownloadFailed` if the downloaded blob doesn't match its
+    /// digest. The download is discarded, so the next attempt starts over.
+    pub async fn get_or_fetch<F, Fut>(
+        &self,
+        digest: &Digest,
+        size: u64,
+        fetch: F,
+    ) -> MonocoreResult<PathBuf>
+    where
+        F: Fn(u64) -> Fut,
+        Fut: Future<Output = MonocoreResult<BoxStream<'static, MonocoreResult<Bytes>>>>,
+    {
+        self.get_or_fetch_with_progress(digest, size, fetch, |_| {})
+            .await
+    }
+
+    /// Returns the path of the cached blob with `digest` like [`BlobCache::get_or_fetch`],
+    /// calling `progress` with the number of bytes of the blob obtained each time more of it is.
+    ///
+    /// A cached blob is reported whole, and a resumed download reports what was downloaded before
+    /// it first, so the bytes reported add up to `size` once the blob is cached.
+    ///
+    /// ## Errors
+    ///
+    /// Returns the same errors as [`BlobCache::get_or_fetch`].
+    pub async fn get_or_fetch_with_progress<F, Fut>(
+        &self,
+        digest: &Digest,
+        size: u64,
+        fetch: F,
+        progress: impl Fn(u64),
+    ) -> MonocoreResult<PathBuf>
+    where
+        F: Fn(u64) -> Fut,
+        Fut: Future<Output = MonocoreResult<BoxStream<'static, MonocoreResult<Bytes>>>>,
+    {
+        match self.get(digest).await {
+            Ok(Some(path)) => {
+                tracing::info!("layer {digest} found in cache, skipping download");
+                progress(size);
+                return Ok(path);
+            }
+            Ok(None) => {}
+            Err(MonocoreError::CorruptedBlob(e)) => {
+                tracing::warn!("corrupted cached layer {digest} ({e}), downloading it again");
+                fs::remove_file(self.blob_path(digest)).await?;
+            }
+            Err(e) => return Err(e),
+        }
+
+        fs::create_dir_all(&self.dir).await?;
+
+        // Claim a previous download by moving it to a file of our own. A concurrent download that
+        // claimed it first makes the rename fail, and we start over.
+        let partial_path = self.partial_path(digest);
+        let download_path = self.download_path(digest);
+        let downloaded_size = match fs::rename(&partial_path, &download_path).await {
+            Ok(()) => match fs::metadata(&download_path).await?.len() {
+                len if len < size => len,
+                _ => 0,
+            },
+            Err(e) if e.kind() == ErrorKind::NotFound => 0,
+            Err(e) => return Err(e.into()),
+        };
+
+        progress(downloaded_size);
+
+        // Leave what was downloaded for the next download to resume
+        if let Err(e) = self
+            .download(
+                digest,
+                size,
+                &download_path,
+                downloaded_size,
+                fetch,
+                &progress,
+            )
+            .await
+        {
+            match fs::rename(&download_path, &partial_path).await {
+                Err(rename_error) if rename_error.kind() != ErrorKind::NotFound => {
+                    tracing::warn!("failed to keep the download of layer {digest}: {rename_error}");
+                }
+                _ => {}
+            }
+            return Err(e);
+        }
+
+        // Delete the download if the hash does not match, so the next attempt starts over
+        let expected_hash = digest.digest();
+        let actual_hash = hash_file(&download_path, digest).await?;
+        if actual_hash != expected_hash {
+            fs::remove_file(&download_path).await?;
+            return Err(MonocoreError::ImageLayerDownloadFailed(format!(
+                "({digest}) file hash {actual_hash} does not match expected hash {expected_hash}",
+            )));
+        }
+
+        // Concurrent downloads of the blob all verified their copy, so whichever is moved in place
+        // last is as good as the others
+        let path = self.blob_path(digest);
+        fs::rename(&download_path, &path).await?;
+
+        Ok(path)
+    }
+
+    /// Downloads the blob with `digest` to `download_path` with `fetch`, appending to the
+    /// `downloaded_size` bytes already there and resuming interrupted downloads.
+    async fn download<F, Fut>(
+        &self,
+        digest: &Digest,
+        size: u64,
+        download_path: &Path,
+        mut downloaded_size: u64,
+        fetch: F,
+        progress: impl Fn(u64),
+    ) -> MonocoreResult<()>
+    where
+        F: Fn(u64) -> Fut,
+        Fut: Future<Output = MonocoreResult<BoxStream<'static, MonocoreResult<Bytes>>>>,
+    {
+        let mut file = if downloaded_size == 0 {
+            OpenOptions::new()
+                .create(true)
+                .truncate(true)
+                .write(true)
+                .open(download_path)
+                .await?
+        } else {
+            OpenOptions::new().append(true).open(download_path).await?
+        };
+
+        // Only transient errors from `fetch` and its stream are retried, not local write errors
+        let mut retries = 0;
+        loop {
+            let error = match fetch(downloaded_size).await {
+                Ok(mut stream) => loop {
+                    match stream.next().await {
+                        Some(Ok(chunk)) => {
+                            file.write_all(&chunk).await?;
+                            downloaded_size += chunk.len() as u64;
+                            progress(chunk.len() as u64);
+                        }
+                        Some(Err(e)) => {
+                            let transient = is_transient(&e);
+                            break Some((e, transient));
+                        }
+                        None if downloaded_size < size => {
+                            let e = MonocoreError::ImageLayerDownloadFailed(format!(
+                                "({digest}) connection closed at byte {downloaded_size} of {size}"
+                            ));
+                            break Some((e, true));
+                        }
+                        None => break None,
+                    }
+                },
+                Err(e) => {
+                    let transient = is_transient(&e);
+                    Some((e, transient))
+                }
+            };
+
+            let Some((error, transient)) = error else {
+                break;
+            };
+
+            file.flush().await?;
+            if !transient || retries == self.max_retries {
+                return Err(error);
+            }
+
+            let delay = self.retry_backoff * 2u32.pow(retries);
+            retries += 1;
+            tracing::warn!(
+                "download of layer {digest} interrupted at byte {downloaded_size} ({error}), \
+                resuming in {delay:?} (retry {retries} of {})",
+                self.max_retries
+            );
+            tokio::time::sleep(delay).await;
+        }
+        file.flush().await?;
+
+        Ok(())
+    }
+
+    /// Returns the content of the cached blob with `digest`, or `None` if it is not cached.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `MonocoreError::CorruptedBlob` if the cached blob doesn't match its digest.
+    pub async fn get_bytes(&self, digest: &Digest) -> MonocoreResult<Option<Bytes>> {
+        let path = self.blob_path(digest);
+        let data = match fs::read(&path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let actual_hash = hash_bytes(&data, digest)?;
+        if actual_hash != digest.digest() {
+            return Err(MonocoreError::CorruptedBlob(format!(
+                "{} hashes to {actual_hash}",
+                path.display()
+            )));
+        }
+
+        Ok(Some(data.into()))
+    }
+
+    /// Stores `data` as the blob with `digest`.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `MonocoreError::BlobDigestMismatch` if `data` doesn't match `digest`, in which case
+    /// nothing is stored.
+    pub async fn put_bytes(&self, digest: &Digest, data: &[u8]) -> MonocoreResult<()> {
+        let actual_hash = hash_bytes(data, digest)?;
+        if actual_hash != digest.digest() {
+            return Err(MonocoreError::BlobDigestMismatch(format!(
+                "({digest}) hash {actual_hash} does not match expected hash {}",
+                digest.digest()
+            )));
+        }
+
+        // Write next to the blob and move it in place, so a reader never sees half a blob
+        fs::create_dir_all(&self.dir).await?;
+        let download_path = self.download_path(digest);
+        fs::write(&download_path, data).await?;
+        fs::rename(&download_path, self.blob_path(digest)).await?;
+
+        Ok(())
+    }
+
+    /// Returns the content of the cached blob with `digest`, fetching it with `fetch` first if it
+    /// is not cached or the cached copy is corrupted.
+    ///
+    /// This is meant for small blobs like manifests and configs, which are held in memory. Layers
+    /// are downloaded with [`BlobCache::get_or_fetch`] instead.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `MonocoreError::BlobDigestMismatch` if the fetched blob doesn't match its digest.
+    pub async fn get_or_fetch_bytes<F, Fut>(
+        &self,
+        digest: &Digest,
+        fetch: F,
+    ) -> MonocoreResult<Bytes>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = MonocoreResult<Bytes>>,
+    {
+        match self.get_bytes(digest).await {
+            Ok(Some(data)) => {
+                tracing::info!("blob {digest} found in cache, skipping download");
+                return Ok(data);
+            }
+            Ok(None) => {}
+            Err(MonocoreError::CorruptedBlob(e)) => {
+                tracing::warn!("corrupted cached blob {digest} ({e}), downloading it again");
+            }
+            Err(e) => return Err(e),
+        }
+
+        let data = fetch().await?;
+        self.put_bytes(digest, &data).await?;
+
+        Ok(data)
+    }
+
+    /// Returns the digest of the index that `tag` of `repository` pointed to when it was last
+    /// pulled, or `None` if it has never been pulled.
+    pub async fn get_tag(&self, repository: &str, tag: &str) -> MonocoreResult<Option<Digest>> {
+        let path = self.tag_path(repository, tag);
+        let digest = match fs::read_to_string(&path).await {
+            Ok(digest) => digest,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let digest = Digest::from_str(digest.trim()).map_err(|e| {
+            MonocoreError::CorruptedBlob(format!("{} is not a digest: {e}", path.display()))
+        })?;
+
+        Ok(Some(digest))
+    }
+
+    /// Records that `tag` of `repository` points to the index with `digest`.
+    pub async fn put_tag(
+        &self,
+        repository: &str,
+        tag: &str,
+        digest: &Digest,
+    ) -> MonocoreResult<()> {
+        let path = self.tag_path(repository, tag);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        fs::write(&path, digest.to_string()).await?;
+
+        Ok(())
+    }
+
+    /// Returns the path where the digest of the index `tag` of `repository` points to is stored.
+    fn tag_path(&self, repository: &str, tag: &str) -> PathBuf {
+        self.dir.join(REFS_SUBDIR).join(repository).join(tag)
+    }
+
+    /// Returns the path where an interrupted download of the blob with `digest` is left to be
+    /// resumed.
+    fn partial_path(&self, digest: &Digest) -> PathBuf {
+        self.dir.join(format!("{digest}.{PARTIAL_BLOB_SUFFIX}"))
+    }
+
+    /// Returns a path no other download uses to download the blob with `digest` to.
+    fn download_path(&self, digest: &Digest) -> PathBuf {
+        let counter = DOWNLOAD_COUNTER.fetch_add(1, Ordering::Relaxed);
+        self.dir.join(format!(
+            "{digest}.{}.{counter}.{PARTIAL_BLOB_SUFFIX}",
+            std::process::id()
+        ))
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Returns the sha256 digest of `data`.
+pub fn sha256_digest(data: &[u8]) -> MonocoreResult<Digest> {
+    let hash = utils::get_bytes_hash(data, &DigestAlgorithm::Sha256)?;
+    Digest::from_str(&format!("sha256:{}", hex::encode(hash))).map_err(MonocoreError::custom)
+}
+
+/// Returns whether `error`, which interrupted a download, is worth retrying: the connection
+/// failed, timed out or dropped, or the registry failed with a server error.
+fn is_transient(error: &MonocoreError) -> bool {
+    let error = match error {
+        MonocoreError::HttpRequest(e) => e,
+        MonocoreError::HttpMiddleware(reqwest_middleware::Error::Reqwest(e)) => e,
+        MonocoreError::Io(e) => {
+            return matches!(
+                e.kind(),
+                ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::BrokenPipe
+                    | ErrorKind::TimedOut
+                    | ErrorKind::UnexpectedEof
+            );
+        }
+        _ => return false,
+    };
+
+    // A connection dropped mid-stream surfaces as an error decoding the body, unlike a body that
+    // isn't the expected JSON
+    let dropped = error.is_body()
+        || (error.is_decode()
+            && error
+                .source()
+                .is_some_and(|source| !source.is::<serde_json::Error>()));
+
+    error.is_connect()
+        || error.is_timeout()
+        || dropped
+        || error
+            .status()
+            .is_some_and(|status| status.is_server_error())
+}
+
+/// Hashes the file at `path` with the algorithm of `digest`, returning the hex-encoded hash.
+async fn hash_file(path: &Path, digest: &Digest) -> MonocoreResult<String> {
+    let hash = utils::get_file_hash(path, digest.algorithm()).await?;
+    Ok(hex::encode(hash))
+}
+
+/// Hashes `data` with the algorithm of `digest`, returning the hex-encoded hash.
+fn hash_bytes(data: &[u8], digest: &Digest) -> MonocoreResult<String> {
+    let hash = utils::get_bytes_hash(data, digest.algorithm())?;
+    Ok(hex::encode(hash))
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    };
+
+    use monoutils::{ProgressEvent, ProgressTracker};
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_blob_cache_second_pull_fetches_nothing() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache = BlobCache::new(temp_dir.path().join("layers"));
+        let layers = [
+            b"base layer".to_vec(),
+            b"app layer".to_vec(),
+            vec![7; 100_000],
+        ];
+        let fetches = AtomicUsize::new(0);
+
+        // Pull the same image twice
+        for _ in 0..2 {
+            for layer in &layers {
+                let digest = helper::digest_of(layer);
+                let path = cache
+                    .get_or_fetch(&digest, layer.len() as u64, |offset| {
+                        helper::fetch(&fetches, layer, offset)
+                    })
+                    .await?;
+                assert_eq!(fs::read(&path).await?, *layer);
+                assert_eq!(path, cache.blob_path(&digest));
+            }
+        }
+
+        assert_eq!(fetches.load(Ordering::SeqCst), layers.len());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blob_cache_refetches_corrupted_blob() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache = BlobCache::new(temp_dir.path());
+        let layer = b"layer content".to_vec();
+        let digest = helper::digest_of(&layer);
+        let fetches = AtomicUsize::new(0);
+
+        cache
+            .get_or_fetch(&digest, layer.len() as u64, |offset| {
+                helper::fetch(&fetches, &layer, offset)
+            })
+            .await?;
+
+        // Corrupt the cached blob
+        fs::write(cache.blob_path(&digest), b"layer c0ntent").await?;
+        assert!(matches!(
+            cache.get(&digest).await,
+            Err(MonocoreError::CorruptedBlob(_))
+        ));
+
+        let path = cache
+            .get_or_fetch(&digest, layer.len() as u64, |offset| {
+                helper::fetch(&fetches, &layer, offset)
+            })
+            .await?;
+        assert_eq!(fs::read(&path).await?, layer);
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+        assert_eq!(cache.get(&digest).await?, Some(path));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blob_cache_resumes_and_verifies_downloads() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache = BlobCache::new(temp_dir.path());
+        let layer = b"0123456789".to_vec();
+        let digest = helper::digest_of(&layer);
+        let fetches = AtomicUsize::new(0);
+
+        // An interrupted download is resumed where it stopped
+        fs::write(cache.partial_path(&digest), &layer[..4]).await?;
+        let path = cache
+            .get_or_fetch(&digest, layer.len() as u64, |offset| {
+                assert_eq!(offset, 4);
+                helper::fetch(&fetches, &layer, offset)
+            })
+            .await?;
+        assert_eq!(fs::read(&path).await?, layer);
+        assert!(!fs::try_exists(cache.partial_path(&digest)).await?);
+
+        // A download that doesn't match the digest is discarded
+        let other = helper::digest_of(b"other");
+        let result = cache
+            .get_or_fetch(&other, 5, |offset| {
+                helper::fetch(&fetches, b"wrong", offset)
+            })
+            .await;
+        assert!(matches!(
+            result,
+            Err(MonocoreError::ImageLayerDownloadFailed(_))
+        ));
+        assert_eq!(cache.get(&other).await?, None);
+        assert!(!fs::try_exists(cache.partial_path(&other)).await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blob_cache_resumes_dropped_download() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache = BlobCache::new(temp_dir.path()).with_retry_backoff(Duration::from_millis(1));
+        let layer: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+        let digest = helper::digest_of(&layer);
+
+        // The connection drops after 40000 bytes, twice
+        let server = helper::serve_flaky_blob(layer.clone(), 40_000, 2).await?;
+        let client = reqwest::Client::new();
+
+        let path = cache
+            .get_or_fetch(&digest, layer.len() as u64, |offset| {
+                helper::fetch_http(&client, &server.url, offset)
+            })
+            .await?;
+        assert_eq!(fs::read(&path).await?, layer);
+        assert_eq!(server.offsets(), [0, 40_000, 80_000]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blob_cache_reports_progress() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache = BlobCache::new(temp_dir.path()).with_retry_backoff(Duration::from_millis(1));
+        let layer: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+        let digest = helper::digest_of(&layer);
+        let server = helper::serve_flaky_blob(layer.clone(), 40_000, 1).await?;
+        let client = reqwest::Client::new();
+
+        // A download resumed after the connection drops, then a cache hit
+        for _ in 0..2 {
+            let events = Mutex::new(Vec::new());
+            let progress = |event| events.lock().unwrap().push(event);
+            let tracker = ProgressTracker::new(layer.len() as u64, &progress);
+            cache
+                .get_or_fetch_with_progress(
+                    &digest,
+                    layer.len() as u64,
+                    |offset| helper::fetch_http(&client, &server.url, offset),
+                    |bytes| tracker.advance(bytes),
+                )
+                .await?;
+
+            // The bytes done never decrease and end up at the total
+            let events = events.into_inner().unwrap();
+            let done = events
+                .iter()
+                .map(|event| match event {
+                    ProgressEvent::Bytes { done, .. } => *done,
+                    ProgressEvent::ItemCompleted { .. } => unreachable!(),
+                })
+                .collect::<Vec<_>>();
+            assert!(done.windows(2).all(|pair| pair[0] <= pair[1]));
+            assert_eq!(
+                events.last(),
+                Some(&ProgressEvent::Bytes {
+                    done: layer.len() as u64,
+                    total: layer.len() as u64
+                })
+            );
+        }
+        assert_eq!(server.offsets(), [0, 40_000]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blob_cache_gives_up_after_max_retries() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache = BlobCache::new(temp_dir.path())
+            .with_max_retries(2)
+            .with_retry_backoff(Duration::from_millis(1));
+        let layer: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+        let digest = helper::digest_of(&layer);
+
+        let server = helper::serve_flaky_blob(layer.clone(), 10_000, 3).await?;
+        let client = reqwest::Client::new();
+
+        // The first try and both retries are dropped, and what they downloaded is kept
+        let result = cache
+            .get_or_fetch(&digest, layer.len() as u64, |offset| {
+                helper::fetch_http(&client, &server.url, offset)
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(server.offsets(), [0, 10_000, 20_000]);
+        assert_eq!(
+            fs::metadata(cache.partial_path(&digest)).await?.len(),
+            30_000
+        );
+
+        // The next download picks up where they stopped
+        let path = cache
+            .get_or_fetch(&digest, layer.len() as u64, |offset| {
+                helper::fetch_http(&client, &server.url, offset)
+            })
+            .await?;
+        assert_eq!(fs::read(&path).await?, layer);
+        assert_eq!(server.offsets(), [0, 10_000, 20_000, 30_000]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blob_cache_does_not_retry_permanent_errors() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache = BlobCache::new(temp_dir.path())
+            .with_max_retries(3)
+            .with_retry_backoff(Duration::from_millis(1));
+        let digest = helper::digest_of(b"layer");
+        let fetches = AtomicUsize::new(0);
+
+        // A missing blob fails the same way on every try
+        let result = cache
+            .get_or_fetch(&digest, 5, |_| {
+                helper::fail(&fetches, MonocoreError::ManifestNotFound)
+            })
+            .await;
+        assert!(matches!(result, Err(MonocoreError::ManifestNotFound)));
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // A reset connection is retried
+        let result = cache
+            .get_or_fetch(&digest, 5, |_| {
+                let reset = std::io::Error::new(ErrorKind::ConnectionReset, "reset");
+                helper::fail(&fetches, MonocoreError::Io(reset))
+            })
+            .await;
+        assert!(matches!(result, Err(MonocoreError::Io(_))));
+        assert_eq!(fetches.load(Ordering::SeqCst), 5);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blob_cache_second_metadata_fetch_hits_cache() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache = BlobCache::new(temp_dir.path());
+        let manifest = br#"{"schemaVersion":2}"#.to_vec();
+        let digest = helper::digest_of(&manifest);
+        let fetches = AtomicUsize::new(0);
+
+        for _ in 0..2 {
+            let data = cache
+                .get_or_fetch_bytes(&digest, || async {
+                    fetches.fetch_add(1, Ordering::SeqCst);
+                    Ok(Bytes::from(manifest.clone()))
+                })
+                .await?;
+            assert_eq!(data, manifest);
+        }
+
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        assert_eq!(cache.get_bytes(&digest).await?, Some(Bytes::from(manifest)));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blob_cache_rejects_digest_mismatch() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache = BlobCache::new(temp_dir.path());
+        let manifest = br#"{"schemaVersion":2}"#.to_vec();
+        let digest = helper::digest_of(&manifest);
+
+        // A fetched blob that doesn't match its digest is not stored
+        let result = cache
+            .get_or_fetch_bytes(&digest, || async {
+                Ok(Bytes::from_static(br#"{"schemaVersion":1}"#))
+            })
+            .await;
+        assert!(matches!(result, Err(MonocoreError::BlobDigestMismatch(_))));
+        assert_eq!(cache.get_bytes(&digest).await?, None);
+
+        // A cached blob that was tampered with is rejected on load
+        cache.put_bytes(&digest, &manifest).await?;
+        fs::write(cache.blob_path(&digest), br#"{"schemaVersion":3}"#).await?;
+        assert!(matches!(
+            cache.get_bytes(&digest).await,
+            Err(MonocoreError::CorruptedBlob(_))
+        ));
+
+        // And fetched again
+        let data = cache
+            .get_or_fetch_bytes(&digest, || async { Ok(Bytes::from(manifest.clone())) })
+            .await?;
+        assert_eq!(data, manifest);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blob_cache_tags() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let cache = BlobCache::new(temp_dir.path());
+        let first = helper::digest_of(b"first index");
+        let second = helper::digest_of(b"second index");
+
+        assert_eq!(cache.get_tag("library/alpine", "latest").await?, None);
+
+        cache.put_tag("library/alpine", "latest", &first).await?;
+        assert_eq!(
+            cache.get_tag("library/alpine", "latest").await?,
+            Some(first)
+        );
+
+        // A tag is moved by pulling it again
+        cache.put_tag("library/alpine", "latest", &second).await?;
+        assert_eq!(
+            cache.get_tag("library/alpine", "latest").await?,
+            Some(second)
+        );
+        assert_eq!(cache.get_tag("library/alpine", "3.20").await?, None);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod helper {
+    use std::{
+        io,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+    };
+
+    use axum::{
+        body::Body,
+        extract::State,
+        http::{
+            header::{CONTENT_LENGTH, RANGE},
+            HeaderMap, StatusCode,
+        },
+        response::{IntoResponse, Response},
+        routing::get,
+        Router,
+    };
+    use futures::stream;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// A blob served over HTTP by a server that drops the connection partway through its first
+    /// responses.
+    pub(super) struct FlakyBlob {
+        /// The URL the blob is served at.
+        pub(super) url: String,
+
+        /// The state shared with the server.
+        state: Arc<FlakyBlobState>,
+    }
+
+    struct FlakyBlobState {
+        data: Vec<u8>,
+        drop_after: usize,
+        drops_left: AtomicUsize,
+        offsets: Mutex<Vec<u64>>,
+    }
+
+    impl FlakyBlob {
+        /// Returns the offsets of the range requests made so far.
+        pub(super) fn offsets(&self) -> Vec<u64> {
+            self.state.offsets.lock().unwrap().clone()
+        }
+    }
+
+    /// Serves `data` with range requests, dropping the connection after `drop_after` bytes of
+    /// each of the first `drops` responses.
+    pub(super) async fn serve_flaky_blob(
+        data: Vec<u8>,
+        drop_after: usize,
+        drops: usize,
+    ) -> anyhow::Result<FlakyBlob> {
+        let state = Arc::new(FlakyBlobState {
+            data,
+            drop_after,
+            drops_left: AtomicUsize::new(drops),
+            offsets: Mutex::new(Vec::new()),
+        });
+
+        let app = Router::new()
+            .route("/blob", get(serve_blob))
+            .with_state(state.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}/blob", listener.local_addr()?);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        Ok(FlakyBlob { url, state })
+    }
+
+    async fn serve_blob(State(state): State<Arc<FlakyBlobState>>, headers: HeaderMap) -> Response {
+        let offset = headers
+            .get(RANGE)
+            .and_then(|range| range.to_str().ok())
+            .and_then(|range| range.strip_prefix("bytes="))
+            .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok())
+            .unwrap_or(0);
+        state.offsets.lock().unwrap().push(offset as u64);
+
+        let rest = Bytes::copy_from_slice(&state.data[offset..]);
+        let content_length = rest.len().to_string();
+        let drop = state
+            .drops_left
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            .is_ok();
+
+        let body = if drop {
+            // Give the sent bytes time to reach the client before the connection is dropped
+            let sent = rest.slice(..state.drop_after.min(rest.len()));
+            Body::from_stream(stream::once(async { Ok(sent) }).chain(stream::once(async {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                Err(io::Error::new(io::ErrorKind::ConnectionReset, "dropped"))
+            })))
+        } else {
+            Body::from(rest)
+        };
+
+        (
+            StatusCode::PARTIAL_CONTENT,
+            [(CONTENT_LENGTH, content_length)],
+            body,
+        )
+            .into_response()
+    }
+
+    /// Fetches the blob at `url` from `offset` with a range request.
+    pub(super) fn fetch_http(
+        client: &reqwest::Client,
+        url: &str,
+        offset: u64,
+    ) -> impl Future<Output = MonocoreResult<BoxStream<'static, MonocoreResult<Bytes>>>> {
+        let request = client.get(url).header(RANGE, format!("bytes={offset}-"));
+        async move {
+            let response = request.send().await?;
+            let stream = response
+                .bytes_stream()
+                .map(|item| item.map_err(|e| e.into()));
+            Ok(stream.boxed())
+        }
+    }
+
+    /// Fails with `error`, counting the fetch.
+    pub(super) fn fail(
+        fetches: &AtomicUsize,
+        error: MonocoreError,
+    ) -> impl Future<Output = MonocoreResult<BoxStream<'static, MonocoreResult<Bytes>>>> {
+        fetches.fetch_add(1, Ordering::SeqCst);
+        async move { Err(error) }
+    }
+
+    /// Returns the sha256 digest of `data`.
+    pub(super) fn digest_of(data: &[u8]) -> Digest {
+        sha256_digest(data).unwrap()
+    }
+
+    /// Serves `data` from `offset` in two chunks, counting the fetch.
+    pub(super) fn fetch(
+        fetches: &AtomicUsize,
+        data: &[u8],
+        offset: u64,
+    ) -> impl Future<Output = MonocoreResult<BoxStream<'static, MonocoreResult<Bytes>>>> {
+        fetches.fetch_add(1, Ordering::SeqCst);
+        let rest = data[offset as usize..].to_vec();
+        let (first, second) = rest.split_at(rest.len() / 2);
+        let chunks = vec![
+            Ok(Bytes::copy_from_slice(first)),
+            Ok(Bytes::copy_from_slice(second)),
+        ];
+
+        async move { Ok(stream::iter(chunks).boxed()) }
+    }
+}
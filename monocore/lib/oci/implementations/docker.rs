@@ -1,4 +1,5 @@
 use std::{
+    future::Future,
     ops::RangeBounds,
     path::{Path, PathBuf},
 };
@@ -8,21 +9,19 @@ use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use futures::{future, stream::BoxStream, StreamExt};
 use getset::{Getters, Setters};
+use monoutils::{ProgressEvent, ProgressTracker};
 use oci_spec::image::{Digest, ImageConfiguration, ImageIndex, ImageManifest, Os, Platform};
-use reqwest::Client;
+use reqwest::{Client, StatusCode};
 use reqwest_middleware::{ClientBuilder, ClientWithMiddleware};
 use reqwest_retry::{policies::ExponentialBackoff, RetryTransientMiddleware};
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use sqlx::{Pool, Sqlite};
 use thiserror::Error;
-use tokio::{
-    fs::{self, OpenOptions},
-    io::AsyncWriteExt,
-};
 
 use crate::{
+    config::DEFAULT_MAX_CONCURRENT_DOWNLOADS,
     management::db,
-    oci::{OciRegistryPull, ReferenceSelector},
+    oci::{self, BlobCache, OciRegistryPull, ReferenceSelector},
     utils, MonocoreError, MonocoreResult,
 };
 
@@ -77,8 +76,20 @@ pub struct DockerRegistry {
     /// The HTTP client used to make requests to the Docker registry.
     client: ClientWithMiddleware,
 
-    /// The directory where image layers are downloaded.
-    layer_download_dir: PathBuf,
+    /// The base URL of the registry's v2 API.
+    registry_url: String,
+
+    /// The endpoint that issues the tokens the registry accepts.
+    auth_realm: String,
+
+    /// The cache where image layers, indexes, manifests and configs are downloaded.
+    blob_cache: BlobCache,
+
+    /// Whether images are pulled from the cache only, without making any requests to the registry.
+    offline: bool,
+
+    /// The maximum number of image layers downloaded at the same time.
+    max_concurrent_downloads: usize,
 
     /// The database where image configurations, indexes, and manifests are stored.
     oci_db: Pool<Sqlite>,
@@ -133,7 +144,7 @@ impl DockerRegistry {
     ///
     /// ## Arguments
     ///
-    /// * `layer_download_dir` - The directory where downloaded image layers will be stored
+    /// * `layer_download_dir` - The directory where downloaded image layers and metadata are cached
     /// * `oci_db_path` - The path to the SQLite database that stores OCI-related metadata
     pub async fn new(
         layer_download_dir: impl Into<PathBuf>,
@@ -147,22 +158,15 @@ impl DockerRegistry {
 
         Ok(Self {
             client,
-            layer_download_dir: layer_download_dir.into(),
+            registry_url: DOCKER_REGISTRY_URL.to_string(),
+            auth_realm: DOCKER_AUTH_REALM.to_string(),
+            blob_cache: BlobCache::new(layer_download_dir),
+            offline: false,
+            max_concurrent_downloads: DEFAULT_MAX_CONCURRENT_DOWNLOADS,
             oci_db: db::get_or_create_pool(oci_db_path.as_ref(), &db::OCI_DB_MIGRATOR).await?,
         })
     }
 
-    /// Gets the size of a downloaded file if it exists.
-    fn get_downloaded_file_size(&self, digest: &Digest) -> u64 {
-        let download_path = self.layer_download_dir.join(digest.to_string());
-        // If the file does not exist, return 0 indicating no bytes have been downloaded
-        if !download_path.exists() {
-            return 0;
-        }
-
-        download_path.metadata().unwrap().len()
-    }
-
     /// Gets the necessary authentication credentials for the given repository and tag.
     ///
     /// Currently, Docker tokens expire after 300 seconds, so we need to re-authenticate
@@ -175,7 +179,7 @@ impl DockerRegistry {
     ) -> MonocoreResult<DockerAuthMaterial> {
         let request = self
             .client
-            .get(DOCKER_AUTH_REALM)
+            .get(&self.auth_realm)
             .query(&[
                 ("service", service),
                 (
@@ -191,65 +195,101 @@ impl DockerRegistry {
         Ok(auth_credentials)
     }
 
-    /// Downloads a blob from the registry, supports download resumption if the file already partially exists.
+    /// Downloads a blob from the registry into the blob cache, unless it is already cached.
+    ///
+    /// Interrupted downloads are resumed, and a cached blob that doesn't match its digest is
+    /// downloaded again. `progress` is called with the number of bytes of the blob obtained each
+    /// time more of it is, see [`BlobCache::get_or_fetch_with_progress`].
+    ///
+    /// ## Returns
+    ///
+    /// The path of the blob in the cache.
+    ///
+    /// ## Errors
+    ///
+    /// In offline mode, returns `MonocoreError::BlobNotCached` if the blob is not cached.
     pub async fn download_image_blob(
         &self,
         repository: &str,
         digest: &Digest,
         download_size: u64,
-    ) -> MonocoreResult<()> {
-        let download_path = self.layer_download_dir.join(digest.to_string());
+        progress: impl Fn(u64),
+    ) -> MonocoreResult<PathBuf> {
+        if self.offline {
+            let path = self
+                .blob_cache
+                .get(digest)
+                .await?
+                .ok_or_else(|| MonocoreError::BlobNotCached(digest.to_string()))?;
+            progress(download_size);
 
-        // Ensure the destination directory exists
-        if let Some(parent) = download_path.parent() {
-            fs::create_dir_all(parent).await?;
+            return Ok(path);
         }
 
-        // Get the size of the already downloaded file if it exists
-        let downloaded_size = self.get_downloaded_file_size(digest);
+        self.blob_cache
+            .get_or_fetch_with_progress(
+                digest,
+                download_size,
+                |offset| self.fetch_image_blob(repository, digest, offset..),
+                progress,
+            )
+            .await
+    }
 
-        // Open the file for writing, create if it doesn't exist
-        let mut file = if downloaded_size == 0 {
-            OpenOptions::new()
-                .create(true)
-                .truncate(true)
-                .write(true)
-                .open(&download_path)
+    /// Gets a metadata blob, e.g. a manifest, from the blob cache, fetching it with `fetch` first
+    /// if it is not cached, and parses it.
+    ///
+    /// ## Errors
+    ///
+    /// In offline mode, returns `MonocoreError::BlobNotCached` if the blob is not cached.
+    async fn get_or_fetch_metadata<T, F, Fut>(&self, digest: &Digest, fetch: F) -> MonocoreResult<T>
+    where
+        T: DeserializeOwned,
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = MonocoreResult<Bytes>>,
+    {
+        let data = if self.offline {
+            self.blob_cache
+                .get_bytes(digest)
                 .await?
-        } else if downloaded_size < download_size {
-            OpenOptions::new().append(true).open(&download_path).await?
+                .ok_or_else(|| MonocoreError::BlobNotCached(digest.to_string()))?
         } else {
-            tracing::info!(
-                "file already exists skipping download: {}",
-                download_path.display()
-            );
-            return Ok(());
+            self.blob_cache.get_or_fetch_bytes(digest, fetch).await?
         };
 
-        let mut stream = self
-            .fetch_image_blob(repository, digest, downloaded_size..)
-            .await?;
+        Ok(serde_json::from_slice(&data)?)
+    }
 
-        // Write the stream to the file
-        while let Some(chunk) = stream.next().await {
-            let bytes = chunk?;
-            file.write_all(&bytes).await?;
-        }
+    /// Fetches a document, e.g. `manifests/<digest>`, of the repository from the registry.
+    ///
+    /// ## Returns
+    ///
+    /// The raw body of the response, so it can be verified against its digest before it is parsed.
+    async fn fetch_registry_bytes(
+        &self,
+        repository: &str,
+        path: &str,
+        accept: &str,
+    ) -> MonocoreResult<Bytes> {
+        let token = self
+            .get_access_credentials(repository, DOCKER_AUTH_SERVICE, &["pull"])
+            .await?
+            .token;
+
+        let request = self
+            .client
+            .get(format!("{}/v2/{}/{}", self.registry_url, repository, path))
+            .bearer_auth(token)
+            .header("Accept", accept)
+            .build()?;
 
-        // Verify the hash of the downloaded file
-        let algorithm = digest.algorithm();
-        let expected_hash = digest.digest();
-        let actual_hash = hex::encode(utils::get_file_hash(&download_path, algorithm).await?);
-
-        // Delete the already downloaded file if the hash does not match
-        if actual_hash != expected_hash {
-            fs::remove_file(&download_path).await?;
-            return Err(MonocoreError::ImageLayerDownloadFailed(format!(
-                    "({repository}:{digest}) file hash {actual_hash} does not match expected hash {expected_hash}",
-                )));
+        let response = self.client.execute(request).await?;
+        if !response.status().is_success() {
+            let err = response.json::<DockerRegistryResponseError>().await?;
+            return Err(err.into());
         }
 
-        Ok(())
+        Ok(response.bytes().await?)
     }
 }
 
@@ -259,10 +299,11 @@ impl DockerRegistry {
 
 #[async_trait]
 impl OciRegistryPull for DockerRegistry {
-    async fn pull_image(
+    async fn pull_image_with_progress(
         &self,
         repository: &str,
         selector: ReferenceSelector,
+        progress: impl Fn(ProgressEvent) + Send + Sync,
     ) -> MonocoreResult<()> {
         // Calculate total size and save image record
         let index = self.fetch_index(repository, selector.clone()).await?;
@@ -326,15 +367,22 @@ impl OciRegistryPull for DockerRegistry {
             .await?;
         db::save_config(&self.oci_db, manifest_id, &config).await?;
 
-        // Download layers concurrently and save to database
-        let layer_futures: Vec<_> = manifest
-            .layers()
-            .iter()
-            .zip(config.rootfs().diff_ids())
-            .map(|(layer_desc, diff_id)| async {
+        // Download layers concurrently and save to database, stopping at the first failure
+        let layers_size = manifest.layers().iter().map(|layer| layer.size()).sum();
+        let tracker = &ProgressTracker::new(layers_size, &progress);
+        let layers = manifest.layers().iter().zip(config.rootfs().diff_ids());
+        oci::download_concurrently(
+            layers,
+            self.max_concurrent_downloads,
+            |(layer_desc, diff_id)| async move {
                 // Download the layer if it doesn't exist
-                self.download_image_blob(repository, layer_desc.digest(), layer_desc.size())
-                    .await?;
+                self.download_image_blob(
+                    repository,
+                    layer_desc.digest(),
+                    layer_desc.size(),
+                    |bytes| tracker.advance(bytes),
+                )
+                .await?;
 
                 // Save layer metadata to database
                 db::save_or_update_layer(
@@ -346,15 +394,12 @@ impl OciRegistryPull for DockerRegistry {
                     diff_id,
                 )
                 .await?;
+                tracker.complete_item(layer_desc.digest().to_string());
 
-                Ok::<_, MonocoreError>(())
-            })
-            .collect();
-
-        // Wait for all layers to download and save
-        for result in future::join_all(layer_futures).await {
-            result?;
-        }
+                Ok(())
+            },
+        )
+        .await?;
 
         Ok(())
     }
@@ -364,44 +409,49 @@ impl OciRegistryPull for DockerRegistry {
         repository: &str,
         selector: ReferenceSelector,
     ) -> MonocoreResult<ImageIndex> {
-        let token = self
-            .get_access_credentials(repository, DOCKER_AUTH_SERVICE, &["pull"])
-            .await?
-            .token;
-
         // Construct URL based on selector type
-        let reference = match &selector {
+        let (reference, digest) = match &selector {
             ReferenceSelector::Tag { tag, digest } => {
                 let digest_part = digest
                     .as_ref()
                     .map(|d| format!("@{}:{}", d.algorithm(), d.digest()))
                     .unwrap_or_default();
-                format!("{tag}{digest_part}")
-            }
-            ReferenceSelector::Digest(digest) => {
-                format!("@{}:{}", digest.algorithm(), digest.digest())
+                (format!("{tag}{digest_part}"), digest.clone())
             }
+            ReferenceSelector::Digest(digest) => (
+                format!("@{}:{}", digest.algorithm(), digest.digest()),
+                Some(digest.clone()),
+            ),
         };
 
-        let request = self
-            .client
-            .get(format!(
-                "{}/v2/{}/manifests/{}",
-                DOCKER_REGISTRY_URL, repository, reference
-            ))
-            .bearer_auth(token)
-            .header("Accept", DOCKER_MANIFEST_LIST_MIME_TYPE)
-            .build()?;
+        let path = format!("manifests/{reference}");
+        let digest = match digest {
+            Some(digest) => digest,
+            // A tag can move, so it is only resolved from the cache in offline mode. The reference
+            // is the bare tag here.
+            None if self.offline => self
+                .blob_cache
+                .get_tag(repository, &reference)
+                .await?
+                .ok_or_else(|| MonocoreError::BlobNotCached(format!("{repository}:{reference}")))?,
+            None => {
+                let data = self
+                    .fetch_registry_bytes(repository, &path, DOCKER_MANIFEST_LIST_MIME_TYPE)
+                    .await?;
+                let digest = oci::sha256_digest(&data)?;
+                self.blob_cache.put_bytes(&digest, &data).await?;
+                self.blob_cache
+                    .put_tag(repository, &reference, &digest)
+                    .await?;
 
-        let response = self.client.execute(request).await?;
-        let image_index = response
-            .json::<DockerRegistryResponse<ImageIndex>>()
-            .await?;
+                return Ok(serde_json::from_slice(&data)?);
+            }
+        };
 
-        match image_index {
-            DockerRegistryResponse::Ok(index) => Ok(index),
-            DockerRegistryResponse::Error(err) => Err(err.into()),
-        }
+        self.get_or_fetch_metadata(&digest, || {
+            self.fetch_registry_bytes(repository, &path, DOCKER_MANIFEST_LIST_MIME_TYPE)
+        })
+        .await
     }
 
     async fn fetch_manifest(
@@ -409,30 +459,11 @@ impl OciRegistryPull for DockerRegistry {
         repository: &str,
         digest: &Digest,
     ) -> MonocoreResult<ImageManifest> {
-        let token = self
-            .get_access_credentials(repository, DOCKER_AUTH_SERVICE, &["pull"])
-            .await?
-            .token;
-
-        let request = self
-            .client
-            .get(format!(
-                "{}/v2/{}/manifests/{}",
-                DOCKER_REGISTRY_URL, repository, digest
-            ))
-            .bearer_auth(token)
-            .header("Accept", DOCKER_MANIFEST_MIME_TYPE)
-            .build()?;
-
-        let response = self.client.execute(request).await?;
-        let manifest = response
-            .json::<DockerRegistryResponse<ImageManifest>>()
-            .await?;
-
-        match manifest {
-            DockerRegistryResponse::Ok(manifest) => Ok(manifest),
-            DockerRegistryResponse::Error(err) => Err(err.into()),
-        }
+        let path = format!("manifests/{digest}");
+        self.get_or_fetch_metadata(digest, || {
+            self.fetch_registry_bytes(repository, &path, DOCKER_MANIFEST_MIME_TYPE)
+        })
+        .await
     }
 
     async fn fetch_config(
@@ -440,30 +471,11 @@ impl OciRegistryPull for DockerRegistry {
         repository: &str,
         digest: &Digest,
     ) -> MonocoreResult<ImageConfiguration> {
-        let token = self
-            .get_access_credentials(repository, DOCKER_AUTH_SERVICE, &["pull"])
-            .await?
-            .token;
-
-        let request = self
-            .client
-            .get(format!(
-                "{}/v2/{}/blobs/{}",
-                DOCKER_REGISTRY_URL, repository, digest
-            ))
-            .bearer_auth(token)
-            .header("Accept", DOCKER_CONFIG_MIME_TYPE)
-            .build()?;
-
-        let response = self.client.execute(request).await?;
-        let config = response
-            .json::<DockerRegistryResponse<ImageConfiguration>>()
-            .await?;
-
-        match config {
-            DockerRegistryResponse::Ok(config) => Ok(config),
-            DockerRegistryResponse::Error(err) => Err(err.into()),
-        }
+        let path = format!("blobs/{digest}");
+        self.get_or_fetch_metadata(digest, || {
+            self.fetch_registry_bytes(repository, &path, DOCKER_CONFIG_MIME_TYPE)
+        })
+        .await
     }
 
     async fn fetch_image_blob(
@@ -490,17 +502,42 @@ impl OciRegistryPull for DockerRegistry {
             .client
             .get(format!(
                 "{}/v2/{}/blobs/{}",
-                DOCKER_REGISTRY_URL, repository, digest
+                self.registry_url, repository, digest
             ))
             .bearer_auth(token)
             .header("Accept", DOCKER_IMAGE_BLOB_MIME_TYPE)
             .header("Range", format!("bytes={start}-{end}"))
             .build()?;
 
+        // A server error keeps its status, so the download is retried
         let response = self.client.execute(request).await?;
+        if response.status().is_server_error() {
+            return Err(response.error_for_status().unwrap_err().into());
+        }
+        if !response.status().is_success() {
+            let err = response.json::<DockerRegistryResponseError>().await?;
+            return Err(err.into());
+        }
+
+        // A registry that ignores the range sends the whole blob, so the bytes before the range
+        // are skipped
+        let skip = if response.status() == StatusCode::PARTIAL_CONTENT {
+            0
+        } else {
+            start
+        };
+
         let stream = response
             .bytes_stream()
-            .map(|item| item.map_err(|e| e.into()));
+            .map(|item| item.map_err(|e| e.into()))
+            .scan(skip, |skip, item: MonocoreResult<Bytes>| {
+                let item = item.map(|mut chunk| {
+                    let skipped = (*skip).min(chunk.len() as u64);
+                    *skip -= skipped;
+                    chunk.split_off(skipped as usize)
+                });
+                future::ready(Some(item))
+            });
 
         Ok(stream.boxed())
     }
@@ -516,7 +553,8 @@ mod tests {
     use chrono::DateTime;
     use oci_spec::image::{DigestAlgorithm, Os};
     use sqlx::Row;
-    use tokio::test;
+    use tempfile::TempDir;
+    use tokio::{fs, io::AsyncWriteExt, test};
 
     #[test]
     #[ignore = "makes network requests to Docker registry to pull an image"]
@@ -760,6 +798,105 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    async fn test_docker_offline_pulls_from_cache() -> anyhow::Result<()> {
+        let (mut client, _temp_download_dir, _temp_db_dir) = helper::setup_test_client().await;
+        client.set_offline(true);
+        let repository = "library/alpine";
+
+        let manifest = helper::manifest_json();
+        let manifest_digest = oci::sha256_digest(manifest.as_bytes())?;
+        let index = helper::index_json(&manifest_digest);
+        let index_digest = oci::sha256_digest(index.as_bytes())?;
+        let cache = client.get_blob_cache();
+        cache.put_bytes(&index_digest, index.as_bytes()).await?;
+        cache
+            .put_bytes(&manifest_digest, manifest.as_bytes())
+            .await?;
+        cache.put_tag(repository, "latest", &index_digest).await?;
+
+        // The tag resolves to the index it pointed to when it was last pulled
+        let index = client
+            .fetch_index(repository, ReferenceSelector::tag("latest"))
+            .await?;
+        assert_eq!(index.manifests()[0].digest(), &manifest_digest);
+
+        let manifest = client.fetch_manifest(repository, &manifest_digest).await?;
+        assert_eq!(manifest.layers().len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    async fn test_docker_offline_errors_on_cache_miss() -> anyhow::Result<()> {
+        let (mut client, _temp_download_dir, _temp_db_dir) = helper::setup_test_client().await;
+        client.set_offline(true);
+        let repository = "library/alpine";
+
+        let manifest = helper::manifest_json();
+        let manifest_digest = oci::sha256_digest(manifest.as_bytes())?;
+        client
+            .get_blob_cache()
+            .put_bytes(&manifest_digest, manifest.as_bytes())
+            .await?;
+        let manifest = client.fetch_manifest(repository, &manifest_digest).await?;
+
+        let result = client
+            .fetch_index(repository, ReferenceSelector::tag("latest"))
+            .await;
+        assert!(matches!(result, Err(MonocoreError::BlobNotCached(_))));
+
+        let result = client
+            .fetch_config(repository, manifest.config().digest())
+            .await;
+        assert!(matches!(result, Err(MonocoreError::BlobNotCached(_))));
+
+        let layer = &manifest.layers()[0];
+        let result = client
+            .download_image_blob(repository, layer.digest(), layer.size(), |_| {})
+            .await;
+        assert!(matches!(result, Err(MonocoreError::BlobNotCached(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    async fn test_docker_concurrent_pulls_of_the_same_layer() -> anyhow::Result<()> {
+        let layer: Vec<u8> = (0..200_000).map(|i| (i % 251) as u8).collect();
+        let digest = oci::sha256_digest(&layer)?;
+        let registry_url = helper::serve_registry(layer.clone()).await?;
+
+        // Two pulls sharing a cache, e.g. from two processes
+        let download_dir = TempDir::new()?;
+        let db_dir = TempDir::new()?;
+        let mut clients = Vec::new();
+        for name in ["first.db", "second.db"] {
+            let mut client =
+                DockerRegistry::new(download_dir.path(), db_dir.path().join(name)).await?;
+            client.set_registry_url(registry_url.clone());
+            client.set_auth_realm(format!("{registry_url}/token"));
+            clients.push(client);
+        }
+
+        let (first, second) = tokio::join!(
+            clients[0].download_image_blob("library/alpine", &digest, layer.len() as u64, |_| {}),
+            clients[1].download_image_blob("library/alpine", &digest, layer.len() as u64, |_| {}),
+        );
+        let (first, second) = (first?, second?);
+        assert_eq!(first, second);
+        assert_eq!(fs::read(&first).await?, layer);
+
+        // Nothing but the blob is left behind
+        let mut entries = fs::read_dir(download_dir.path()).await?;
+        let mut names = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            names.push(entry.file_name());
+        }
+        assert_eq!(names, [first.file_name().unwrap()]);
+
+        Ok(())
+    }
+
     #[test]
     #[ignore = "makes network requests to Docker registry to get authentication credentials"]
     async fn test_docker_get_access_credentials() -> anyhow::Result<()> {
@@ -783,7 +920,18 @@ mod tests {
 
 #[cfg(test)]
 mod helper {
+    use std::time::Duration;
+
+    use axum::{
+        body::Body,
+        http::{header::RANGE, HeaderMap, StatusCode},
+        response::{IntoResponse, Response},
+        routing::get,
+        Json, Router,
+    };
+    use futures::stream;
     use tempfile::TempDir;
+    use tokio::net::TcpListener;
 
     use super::*;
 
@@ -799,4 +947,91 @@ mod helper {
 
         (client, temp_download_dir, temp_db_dir)
     }
+
+    /// Serves a registry with `blob` at every blob path, and a token endpoint at `/token`.
+    ///
+    /// The blob is sent in small chunks with pauses in between, so concurrent downloads overlap.
+    ///
+    /// ## Returns
+    ///
+    /// The base URL of the registry.
+    pub(super) async fn serve_registry(blob: Vec<u8>) -> anyhow::Result<String> {
+        let blob = Bytes::from(blob);
+        let app = Router::new()
+            .route(
+                "/token",
+                get(|| async {
+                    Json(serde_json::json!({
+                        "token": "token",
+                        "access_token": "token",
+                        "expires_in": 300,
+                        "issued_at": Utc::now(),
+                    }))
+                }),
+            )
+            .route(
+                "/v2/{*path}",
+                get(move |headers: HeaderMap| serve_blob(blob.clone(), headers)),
+            );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        Ok(url)
+    }
+
+    async fn serve_blob(blob: Bytes, headers: HeaderMap) -> Response {
+        let offset = headers
+            .get(RANGE)
+            .and_then(|range| range.to_str().ok())
+            .and_then(|range| range.strip_prefix("bytes="))
+            .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok())
+            .unwrap_or(0);
+
+        let rest = blob.slice(offset..);
+        let chunks = (0..rest.len())
+            .step_by(16_384)
+            .map(|start| rest.slice(start..(start + 16_384).min(rest.len())))
+            .collect::<Vec<_>>();
+        let body = Body::from_stream(stream::iter(chunks).then(|chunk| async move {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            Ok::<_, std::io::Error>(chunk)
+        }));
+
+        (StatusCode::PARTIAL_CONTENT, body).into_response()
+    }
+
+    /// Returns an image manifest with one layer.
+    pub(super) fn manifest_json() -> String {
+        serde_json::json!({
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.oci.image.manifest.v1+json",
+            "config": {
+                "mediaType": "application/vnd.oci.image.config.v1+json",
+                "digest": format!("sha256:{}", "a".repeat(64)),
+                "size": 1024
+            },
+            "layers": [{
+                "mediaType": "application/vnd.oci.image.layer.v1.tar+gzip",
+                "digest": format!("sha256:{}", "b".repeat(64)),
+                "size": 4096
+            }]
+        })
+        .to_string()
+    }
+
+    /// Returns an image index with the linux/amd64 manifest with `manifest_digest`.
+    pub(super) fn index_json(manifest_digest: &Digest) -> String {
+        serde_json::json!({
+            "schemaVersion": 2,
+            "manifests": [{
+                "mediaType": "application/vnd.oci.image.manifest.v1+json",
+                "digest": manifest_digest.to_string(),
+                "size": 512,
+                "platform": { "architecture": "amd64", "os": "linux" }
+            }]
+        })
+        .to_string()
+    }
 }
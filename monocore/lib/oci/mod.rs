@@ -4,7 +4,9 @@
 //! - Pulling container images from OCI-compliant registries
 //! - Parsing and validating image references (tags and digests)
 //! - Managing image manifests, configurations, and layers
+//! - Caching downloaded layers, manifests and configs by digest, for pulling offline
 
+mod cache;
 mod implementations;
 mod pull;
 mod reference;
@@ -13,6 +15,7 @@ mod reference;
 // Exports
 //--------------------------------------------------------------------------------------------------
 
+pub use cache::*;
 pub use implementations::*;
 pub use pull::*;
 pub use reference::*;
@@ -1,11 +1,13 @@
-use std::ops::RangeBounds;
+use std::{future::Future, ops::RangeBounds};
 
 use async_trait::async_trait;
 use bytes::Bytes;
-use futures::stream::BoxStream;
+use futures::{future, stream::BoxStream};
+use monoutils::ProgressEvent;
 use oci_spec::image::{Digest, ImageConfiguration, ImageIndex, ImageManifest};
+use tokio::sync::Semaphore;
 
-use crate::MonocoreResult;
+use crate::{MonocoreError, MonocoreResult};
 
 use super::ReferenceSelector;
 
@@ -21,8 +23,27 @@ pub trait OciRegistryPull {
     /// the image manifest, fetching the image configuration, and downloading the image layers.
     ///
     /// The image can be selected either by tag or digest using the [`ReferenceSelector`] enum.
-    async fn pull_image(&self, repository: &str, selector: ReferenceSelector)
-        -> MonocoreResult<()>;
+    async fn pull_image(
+        &self,
+        repository: &str,
+        selector: ReferenceSelector,
+    ) -> MonocoreResult<()> {
+        self.pull_image_with_progress(repository, selector, |_| {})
+            .await
+    }
+
+    /// Pulls an OCI image like [`OciRegistryPull::pull_image`], reporting the progress of the
+    /// layer downloads to `progress`.
+    ///
+    /// The total is the size of the image's layers. Layers that are already cached count as done
+    /// as soon as they are found, and each layer is reported as an item, named after its digest,
+    /// once it is downloaded and saved.
+    async fn pull_image_with_progress(
+        &self,
+        repository: &str,
+        selector: ReferenceSelector,
+        progress: impl Fn(ProgressEvent) + Send + Sync,
+    ) -> MonocoreResult<()>;
 
     /// Fetches the image index (manifest list) for multi-platform support.
     /// Retrieves the appropriate manifest for the target platform.
@@ -62,3 +83,178 @@ pub trait OciRegistryPull {
         range: impl RangeBounds<u64> + Send,
     ) -> MonocoreResult<BoxStream<'static, MonocoreResult<Bytes>>>;
 }
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Runs `download` for every item, with at most `max_concurrent_downloads` running at a time.
+///
+/// The results are returned in the order of `items`, whatever order the downloads finish in, so
+/// layers can be merged in the order the manifest lists them. A `max_concurrent_downloads` of 0
+/// is treated as 1.
+///
+/// ## Errors
+///
+/// Returns the first error of any download. The downloads still in flight are cancelled and the
+/// ones not yet started are never started.
+///
+/// ## Examples
+///
+/// ```no_run
+/// use monocore::oci::download_concurrently;
+///
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// let sizes = download_concurrently(["a", "b", "c"], 2, |name| async move {
+///     // Download the layer named `name` here
+///     Ok::<_, monocore::MonocoreError>(name.len())
+/// })
+/// .await?;
+/// assert_eq!(sizes, [1, 1, 1]);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn download_concurrently<I, F, Fut, T>(
+    items: I,
+    max_concurrent_downloads: usize,
+    download: F,
+) -> MonocoreResult<Vec<T>>
+where
+    I: IntoIterator,
+    F: Fn(I::Item) -> Fut,
+    Fut: Future<Output = MonocoreResult<T>>,
+{
+    let semaphore = Semaphore::new(max_concurrent_downloads.max(1));
+    let downloads = items.into_iter().map(|item| {
+        let semaphore = &semaphore;
+        let download = &download;
+        async move {
+            let _permit = semaphore.acquire().await.map_err(MonocoreError::custom)?;
+            download(item).await
+        }
+    });
+
+    // Dropping the other futures on the first error cancels them
+    future::try_join_all(downloads).await
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_download_concurrently_is_sublinear() -> anyhow::Result<()> {
+        let registry = helper::MockRegistry::new(Duration::from_millis(100));
+        let layers = (0..8).collect::<Vec<_>>();
+
+        let start = Instant::now();
+        let downloaded =
+            download_concurrently(layers.clone(), 4, |layer| registry.fetch(layer)).await?;
+        let elapsed = start.elapsed();
+
+        // 8 layers, 4 at a time, take two rounds rather than eight
+        assert_eq!(downloaded, layers);
+        assert!(elapsed < Duration::from_millis(500), "took {elapsed:?}");
+        assert_eq!(registry.max_in_flight.load(Ordering::SeqCst), 4);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_download_concurrently_preserves_order() -> anyhow::Result<()> {
+        let registry = &helper::MockRegistry::new(Duration::from_millis(10));
+
+        // Earlier layers take longer, so they finish last
+        let downloaded = download_concurrently((0..5).rev(), 5, |layer| async move {
+            tokio::time::sleep(Duration::from_millis(10 * layer as u64)).await;
+            registry.fetch(layer).await
+        })
+        .await?;
+
+        assert_eq!(downloaded, [4, 3, 2, 1, 0]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_download_concurrently_cancels_on_first_error() {
+        let finished = Arc::new(AtomicBool::new(false));
+        let started = AtomicUsize::new(0);
+
+        let result = download_concurrently(0..10, 2, |layer| {
+            let finished = finished.clone();
+            started.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if layer == 0 {
+                    return Err(MonocoreError::ImageLayerDownloadFailed(
+                        "layer 0".to_string(),
+                    ));
+                }
+
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                finished.store(true, Ordering::SeqCst);
+                Ok(layer)
+            }
+        })
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(MonocoreError::ImageLayerDownloadFailed(m)) if m == "layer 0"
+        ));
+
+        // The download in flight was cancelled, and later ones never started
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!finished.load(Ordering::SeqCst));
+        assert!(started.load(Ordering::SeqCst) <= 2);
+    }
+}
+
+#[cfg(test)]
+mod helper {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use super::*;
+
+    /// A registry that serves every layer after a fixed delay, tracking concurrent fetches.
+    pub(super) struct MockRegistry {
+        delay: Duration,
+        in_flight: AtomicUsize,
+        pub(super) max_in_flight: AtomicUsize,
+    }
+
+    impl MockRegistry {
+        pub(super) fn new(delay: Duration) -> Self {
+            Self {
+                delay,
+                in_flight: AtomicUsize::new(0),
+                max_in_flight: AtomicUsize::new(0),
+            }
+        }
+
+        /// Fetches a layer, returning its number.
+        pub(super) async fn fetch(&self, layer: usize) -> MonocoreResult<usize> {
+            let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            self.in_flight.fetch_sub(1, Ordering::SeqCst);
+            Ok(layer)
+        }
+    }
+}
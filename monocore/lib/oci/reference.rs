@@ -54,6 +54,24 @@ pub enum ReferenceSelector {
 // Methods
 //--------------------------------------------------------------------------------------------------
 
+impl Reference {
+    /// Returns the tag of the image, if the reference selects it by tag.
+    pub fn get_tag(&self) -> Option<&str> {
+        match &self.selector {
+            ReferenceSelector::Tag { tag, .. } => Some(tag),
+            ReferenceSelector::Digest(_) => None,
+        }
+    }
+
+    /// Returns the digest of the image, if the reference pins one.
+    pub fn get_digest(&self) -> Option<&Digest> {
+        match &self.selector {
+            ReferenceSelector::Tag { digest, .. } => digest.as_ref(),
+            ReferenceSelector::Digest(digest) => Some(digest),
+        }
+    }
+}
+
 impl ReferenceSelector {
     /// Creates a new ReferenceSelector with the specified tag and no digest.
     pub fn tag(tag: impl Into<String>) -> Self {
@@ -95,6 +113,8 @@ impl FromStr for Reference {
     ///
     /// If the registry is omitted, it defaults to the value from [`get_oci_registry`].
     /// If the tag is omitted, it defaults to [`DEFAULT_OCI_REFERENCE_TAG`].
+    /// A digest must be `algorithm:encoded`, and lowercase hex of the right length for `sha256`
+    /// and `sha512`.
     ///
     /// ## Returns
     ///
@@ -115,6 +135,7 @@ impl FromStr for Reference {
                 // Treat as digest branch
                 let (pre, digest_part) = s.split_at(at_idx);
                 let digest_str = &digest_part[1..]; // Skip '@'
+                validate_digest(digest_str)?;
                 let parsed_digest = digest_str.parse::<Digest>().map_err(|e| {
                     MonocoreError::ImageReferenceError(format!("invalid digest: {}", e))
                 })?;
@@ -133,10 +154,10 @@ impl FromStr for Reference {
                     selector: ReferenceSelector::tag_with_digest(tag, parsed_digest),
                 })
             } else {
-                return Err(MonocoreError::ImageReferenceError(format!(
+                Err(MonocoreError::ImageReferenceError(format!(
                     "invalid digest: {}",
                     potential_digest
-                )));
+                )))
             }
         } else {
             let (registry, remainder) = extract_registry_and_path(s, &default_registry);
@@ -189,6 +210,37 @@ impl TryFrom<String> for Reference {
 // Functions
 //--------------------------------------------------------------------------------------------------
 
+/// Validates the digest string.
+///
+/// The digest must be `algorithm:encoded` as defined by the OCI image spec. For the registered
+/// `sha256` and `sha512` algorithms, the encoded part must also be lowercase hex of the right length.
+fn validate_digest(digest: &str) -> Result<(), MonocoreError> {
+    let digest_re = Regex::new(r"^([a-z0-9]+(?:[+._-][a-z0-9]+)*):([a-zA-Z0-9=_-]+)$").unwrap();
+    let valid = digest_re.captures(digest).is_some_and(|captures| {
+        let encoded = &captures[2];
+        let is_hex = |len| {
+            encoded.len() == len
+                && encoded
+                    .bytes()
+                    .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        };
+        match &captures[1] {
+            "sha256" => is_hex(64),
+            "sha512" => is_hex(128),
+            _ => true,
+        }
+    });
+
+    if valid {
+        Ok(())
+    } else {
+        Err(MonocoreError::ImageReferenceError(format!(
+            "invalid digest: {}",
+            digest
+        )))
+    }
+}
+
 /// Validates the given registry string.
 ///
 /// This function checks that the registry contains only alphanumeric characters, dashes, dots,
@@ -255,6 +307,12 @@ fn extract_registry_and_path<'a>(reference: &'a str, default_registry: &str) ->
 /// If the repository part does not contain a '/', the default namespace is prepended.
 /// If no tag is provided, the default tag is used.
 fn extract_repository_and_tag(path: &str) -> Result<(String, String), MonocoreError> {
+    if path.is_empty() {
+        return Err(MonocoreError::ImageReferenceError(
+            "repository is empty".into(),
+        ));
+    }
+
     if let Some(idx) = path.rfind(':') {
         let repo_part = &path[..idx];
         let tag_part = &path[idx + 1..];
@@ -687,4 +745,157 @@ mod tests {
         let err = s.parse::<Reference>().unwrap_err();
         assert!(err.to_string().contains("invalid tag"));
     }
+
+    #[test]
+    fn test_reference_normalization() {
+        let default_registry = get_oci_registry();
+        let sha256 = "sha256:".to_string() + &"ab".repeat(32);
+        let sha512 = "sha512:".to_string() + &"cd".repeat(64);
+        let alpine_by_digest = format!("alpine@{sha256}");
+        let ghcr_by_tag_and_digest = format!("ghcr.io/org/img:v1@{sha512}");
+
+        // (input, registry, repository, tag, digest)
+        let cases = [
+            (
+                "alpine",
+                default_registry.as_str(),
+                "library/alpine",
+                "latest",
+                None,
+            ),
+            (
+                "  alpine  ",
+                default_registry.as_str(),
+                "library/alpine",
+                "latest",
+                None,
+            ),
+            (
+                "alpine:3.19",
+                default_registry.as_str(),
+                "library/alpine",
+                "3.19",
+                None,
+            ),
+            (
+                "myorg/app",
+                default_registry.as_str(),
+                "myorg/app",
+                "latest",
+                None,
+            ),
+            (
+                "docker.io/alpine:edge",
+                "docker.io",
+                "library/alpine",
+                "edge",
+                None,
+            ),
+            ("ghcr.io/org/img", "ghcr.io", "org/img", "latest", None),
+            ("localhost/img", "localhost", "library/img", "latest", None),
+            (
+                "localhost:5000/a/b/c:1.0",
+                "localhost:5000",
+                "a/b/c",
+                "1.0",
+                None,
+            ),
+            (
+                "10.0.0.1:5000/img",
+                "10.0.0.1:5000",
+                "library/img",
+                "latest",
+                None,
+            ),
+            (
+                alpine_by_digest.as_str(),
+                default_registry.as_str(),
+                "library/alpine",
+                "latest",
+                Some(sha256.as_str()),
+            ),
+            (
+                ghcr_by_tag_and_digest.as_str(),
+                "ghcr.io",
+                "org/img",
+                "v1",
+                Some(sha512.as_str()),
+            ),
+        ];
+
+        for (input, registry, repository, tag, digest) in cases {
+            let reference = input
+                .parse::<Reference>()
+                .unwrap_or_else(|e| panic!("{input}: {e}"));
+            assert_eq!(reference.registry, registry, "{input}");
+            assert_eq!(reference.repository, repository, "{input}");
+            assert_eq!(reference.get_tag(), Some(tag), "{input}");
+            assert_eq!(
+                reference.get_digest().map(ToString::to_string).as_deref(),
+                digest,
+                "{input}"
+            );
+
+            // The normalized form parses back to itself
+            assert_eq!(
+                reference.to_string().parse::<Reference>().unwrap(),
+                reference
+            );
+        }
+    }
+
+    #[test]
+    fn test_reference_malformed() {
+        let short_sha256 = "sha256:".to_string() + &"ab".repeat(31);
+        let upper_sha256 = "sha256:".to_string() + &"AB".repeat(32);
+        let short_digest = format!("alpine@{short_sha256}");
+        let upper_digest = format!("alpine@{upper_sha256}");
+
+        // (input, expected error)
+        let cases = [
+            ("   ", "input string is empty"),
+            ("ghcr.io/", "repository is empty"),
+            ("ghcr.io/:v1", "repository is empty"),
+            ("Alpine", "invalid repository"),
+            ("ghcr.io/org//img", "invalid repository"),
+            ("alpine:", "invalid tag"),
+            ("alpine@", "invalid digest"),
+            ("alpine@deadbeef", "invalid digest"),
+            ("alpine@sha256:", "invalid digest"),
+            (short_digest.as_str(), "invalid digest"),
+            (upper_digest.as_str(), "invalid digest"),
+            ("alpine@sha256:xyz@sha256:xyz", "invalid digest"),
+        ];
+
+        for (input, expected) in cases {
+            let err = input.parse::<Reference>().expect_err(input);
+            assert!(
+                err.to_string().contains(expected),
+                "{input:?}: expected {expected:?}, got {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_reference_get_tag_and_digest() {
+        let digest = ("sha256:".to_string() + &"ab".repeat(32))
+            .parse::<Digest>()
+            .unwrap();
+
+        let reference = Reference {
+            registry: "ghcr.io".into(),
+            repository: "org/img".into(),
+            selector: ReferenceSelector::digest(digest.clone()),
+        };
+        assert_eq!(reference.get_tag(), None);
+        assert_eq!(reference.get_digest(), Some(&digest));
+        assert_eq!(
+            reference
+                .to_string()
+                .parse::<Reference>()
+                .unwrap()
+                .get_digest(),
+            Some(&digest)
+        );
+    }
 }
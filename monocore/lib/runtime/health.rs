@@ -0,0 +1,185 @@
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use monoutils::{HealthProbe, HealthStatus, MonoutilsError, MonoutilsResult};
+use sqlx::{Pool, Sqlite};
+
+use crate::{
+    management::{db, exec},
+    MonocoreResult,
+};
+
+use super::{SANDBOX_HEALTH_HEALTHY, SANDBOX_HEALTH_UNHEALTHY};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The command run through the guest agent to check that it is ready and healthy
+const AGENT_HEALTH_PROBE_COMMAND: &str = "true";
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// A health probe for MicroVMs that checks whether the guest agent can run a command.
+///
+/// The health is recorded in the sandbox database whenever it changes, for `monocore status` to
+/// report.
+pub struct AgentHealthProbe {
+    /// The path of the UNIX socket the guest agent is reachable through
+    agent_socket: PathBuf,
+
+    /// The database the sandbox is tracked in
+    sandbox_db: Pool<Sqlite>,
+
+    /// The name of the sandbox
+    sandbox_name: String,
+
+    /// The config file for the sandbox
+    config_file: String,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl AgentHealthProbe {
+    /// Creates a health probe for the guest agent of a sandbox.
+    ///
+    /// ## Arguments
+    ///
+    /// * `agent_socket` - The path of the UNIX socket the guest agent is reachable through
+    /// * `sandbox_db_path` - The path of the database the sandbox is tracked in
+    /// * `sandbox_name` - The name of the sandbox
+    /// * `config_file` - The config file for the sandbox
+    pub async fn new(
+        agent_socket: impl Into<PathBuf>,
+        sandbox_db_path: impl AsRef<Path>,
+        sandbox_name: String,
+        config_file: String,
+    ) -> MonocoreResult<Self> {
+        Ok(Self {
+            agent_socket: agent_socket.into(),
+            sandbox_db: db::get_pool(sandbox_db_path.as_ref()).await?,
+            sandbox_name,
+            config_file,
+        })
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Trait Implementations
+//--------------------------------------------------------------------------------------------------
+
+#[async_trait]
+impl HealthProbe for AgentHealthProbe {
+    async fn probe(&self, _pid: u32) -> MonoutilsResult<bool> {
+        let argv = [AGENT_HEALTH_PROBE_COMMAND.to_string()];
+        let code = exec::exec_with_agent(&self.agent_socket, &argv, false)
+            .await
+            .map_err(MonoutilsError::custom)?
+            .wait()
+            .await
+            .map_err(MonoutilsError::custom)?;
+
+        Ok(code == 0)
+    }
+
+    async fn health_changed(&self, _pid: u32, status: HealthStatus) -> MonoutilsResult<()> {
+        let health = match status {
+            // A restarted MicroVM's health is cleared when it is recorded as running again
+            HealthStatus::Starting => return Ok(()),
+            HealthStatus::Healthy => SANDBOX_HEALTH_HEALTHY,
+            HealthStatus::Unhealthy => SANDBOX_HEALTH_UNHEALTHY,
+        };
+
+        db::update_sandbox_health(
+            &self.sandbox_db,
+            &self.sandbox_name,
+            &self.config_file,
+            health,
+        )
+        .await
+        .map_err(MonoutilsError::custom)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use tempfile::TempDir;
+
+    use crate::runtime::SANDBOX_STATUS_RUNNING;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_agent_health_probe_fails_without_agent() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let db_path = temp_dir.path().join("sandbox.db");
+        db::initialize(&db_path, &db::SANDBOX_DB_MIGRATOR).await?;
+
+        let probe = AgentHealthProbe::new(
+            temp_dir.path().join("agent.sock"),
+            &db_path,
+            "app".to_string(),
+            "monocore.yaml".to_string(),
+        )
+        .await?;
+
+        assert!(probe.probe(1).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_agent_health_probe_records_health_changes() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let db_path = temp_dir.path().join("sandbox.db");
+        let pool = db::initialize(&db_path, &db::SANDBOX_DB_MIGRATOR).await?;
+        db::save_or_update_sandbox(
+            &pool,
+            "app",
+            "monocore.yaml",
+            &Utc::now(),
+            None,
+            SANDBOX_STATUS_RUNNING,
+            1,
+            2,
+            "",
+            None,
+            None,
+        )
+        .await?;
+
+        let probe = AgentHealthProbe::new(
+            temp_dir.path().join("agent.sock"),
+            &db_path,
+            "app".to_string(),
+            "monocore.yaml".to_string(),
+        )
+        .await?;
+        let health = || async {
+            db::get_sandbox(&pool, "app", "monocore.yaml")
+                .await
+                .map(|sandbox| sandbox.unwrap().health)
+        };
+
+        probe.health_changed(2, HealthStatus::Healthy).await?;
+        assert_eq!(health().await?.as_deref(), Some(SANDBOX_HEALTH_HEALTHY));
+
+        probe.health_changed(2, HealthStatus::Unhealthy).await?;
+        assert_eq!(health().await?.as_deref(), Some(SANDBOX_HEALTH_UNHEALTHY));
+
+        // The health of a restarting MicroVM is cleared when it is recorded as running again
+        probe.health_changed(2, HealthStatus::Starting).await?;
+        assert_eq!(health().await?.as_deref(), Some(SANDBOX_HEALTH_UNHEALTHY));
+
+        Ok(())
+    }
+}
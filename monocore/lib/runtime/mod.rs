@@ -1,9 +1,13 @@
 //! Runtime components for the Monocore runtime.
 
+mod health;
 mod monitor;
+mod usage;
 
 //--------------------------------------------------------------------------------------------------
 // Exports
 //--------------------------------------------------------------------------------------------------
 
+pub use health::*;
 pub use monitor::*;
+pub use usage::*;
@@ -2,17 +2,24 @@ use std::{
     io::{Read, Write},
     os::fd::BorrowedFd,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use monoutils::{
-    ChildIo, MonoutilsError, MonoutilsResult, ProcessMonitor, RotatingLog, LOG_SUFFIX,
+    ChildIo, LogEncoder, LogFormat, LogStream, MonoutilsError, MonoutilsResult, ProcessMonitor,
+    RotatingLog, LOG_SUFFIX,
 };
 use sqlx::{Pool, Sqlite};
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    task::JoinHandle,
+};
+
+use crate::{config::DEFAULT_SANDBOX_USAGE_INTERVAL, management::db, vm::Rootfs, MonocoreResult};
 
-use crate::{management::db, vm::Rootfs, MonocoreResult};
+use super::{ProcfsUsage, SandboxUsage, UsageSource};
 
 //--------------------------------------------------------------------------------------------------
 // Constants
@@ -24,6 +31,12 @@ pub const SANDBOX_STATUS_RUNNING: &str = "RUNNING";
 /// The status of a sandbox when it is stopped
 pub const SANDBOX_STATUS_STOPPED: &str = "STOPPED";
 
+/// The health of a running sandbox when its guest agent answers health probes
+pub const SANDBOX_HEALTH_HEALTHY: &str = "HEALTHY";
+
+/// The health of a running sandbox when its guest agent has failed too many health probes in a row
+pub const SANDBOX_HEALTH_UNHEALTHY: &str = "UNHEALTHY";
+
 //--------------------------------------------------------------------------------------------------
 // Types
 //--------------------------------------------------------------------------------------------------
@@ -42,9 +55,18 @@ pub struct MicroVmMonitor {
     /// The last modified timestamp of the config file
     config_last_modified: DateTime<Utc>,
 
+    /// The hash of the sandbox spec the sandbox is started with
+    config_hash: Option<String>,
+
     /// The supervisor PID
     supervisor_pid: u32,
 
+    /// The source of the MicroVM's resource usage
+    usage_source: Arc<dyn UsageSource>,
+
+    /// The task recording the MicroVM's resource usage, while the MicroVM is running
+    usage_task: Option<JoinHandle<()>>,
+
     /// The MicroVM log path
     log_path: Option<PathBuf>,
 
@@ -59,6 +81,9 @@ pub struct MicroVmMonitor {
 
     /// Whether to forward output to stdout/stderr
     forward_output: bool,
+
+    /// The format the MicroVM output is written to the log in
+    log_format: LogFormat,
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -79,18 +104,44 @@ impl MicroVmMonitor {
     ) -> MonocoreResult<Self> {
         Ok(Self {
             supervisor_pid,
+            usage_source: Arc::new(ProcfsUsage),
+            usage_task: None,
             sandbox_db: db::get_pool(sandbox_db_path.as_ref()).await?,
             sandbox_name,
             config_file,
             config_last_modified,
+            config_hash: None,
             log_path: None,
             log_dir: log_dir.into(),
             rootfs,
             original_term: None,
             forward_output,
+            log_format: LogFormat::default(),
         })
     }
 
+    /// Sets the format the MicroVM output is written to the log in.
+    pub fn with_log_format(mut self, log_format: LogFormat) -> Self {
+        self.log_format = log_format;
+        self
+    }
+
+    /// Sets the hash of the sandbox spec the sandbox is started with, recorded so config changes
+    /// can be detected.
+    pub fn with_config_hash(mut self, config_hash: Option<String>) -> Self {
+        self.config_hash = config_hash;
+        self
+    }
+
+    /// Sets the source the MicroVM's resource usage is read from.
+    ///
+    /// While the MicroVM is running, a snapshot of its resource usage is recorded in the sandbox
+    /// database every [`DEFAULT_SANDBOX_USAGE_INTERVAL`], for `monocore status` to report.
+    pub fn with_usage_source(mut self, usage_source: impl UsageSource + 'static) -> Self {
+        self.usage_source = Arc::new(usage_source);
+        self
+    }
+
     fn restore_terminal_settings(&mut self) {
         if let Some(original_term) = self.original_term.take() {
             if let Err(e) = nix::sys::termios::tcsetattr(
@@ -134,11 +185,12 @@ impl ProcessMonitor for MicroVmMonitor {
         };
 
         // Insert sandbox entry into database
-        db::save_or_update_sandbox(
+        let sandbox_id = db::save_or_update_sandbox(
             &self.sandbox_db,
             &self.sandbox_name,
             &self.config_file,
             &self.config_last_modified,
+            self.config_hash.as_deref(),
             SANDBOX_STATUS_RUNNING,
             self.supervisor_pid,
             microvm_pid,
@@ -149,6 +201,14 @@ impl ProcessMonitor for MicroVmMonitor {
         .await
         .map_err(MonoutilsError::custom)?;
 
+        // Record the MicroVM's resource usage until it stops
+        self.usage_task = Some(tokio::spawn(record_usage(
+            self.sandbox_db.clone(),
+            sandbox_id,
+            microvm_pid,
+            self.usage_source.clone(),
+        )));
+
         match child_io {
             ChildIo::Piped {
                 stdin,
@@ -159,6 +219,7 @@ impl ProcessMonitor for MicroVmMonitor {
                 if let Some(mut stdout) = stdout {
                     let log = microvm_log.clone();
                     let forward_output = self.forward_output;
+                    let mut encoder = LogEncoder::new(self.log_format, LogStream::Stdout);
                     tokio::spawn(async move {
                         let mut buf = [0u8; 8192]; // NOTE(appcypher): Using 8192 as buffer size because ChatGPT recommended it lol
                         while let Ok(n) = stdout.read(&mut buf).await {
@@ -167,7 +228,7 @@ impl ProcessMonitor for MicroVmMonitor {
                             }
                             // Write to log file
                             let mut log_guard = log.lock().await;
-                            if let Err(e) = log_guard.write_all(&buf[..n]).await {
+                            if let Err(e) = log_guard.write_all(&encoder.encode(&buf[..n])).await {
                                 tracing::error!(microvm_pid = microvm_pid, error = %e, "failed to write to microvm stdout log");
                             }
                             if let Err(e) = log_guard.flush().await {
@@ -183,6 +244,18 @@ impl ProcessMonitor for MicroVmMonitor {
                                 }
                             }
                         }
+
+                        // Write out an unterminated last line
+                        let rest = encoder.finish();
+                        if !rest.is_empty() {
+                            let mut log_guard = log.lock().await;
+                            if let Err(e) = log_guard.write_all(&rest).await {
+                                tracing::error!(microvm_pid = microvm_pid, error = %e, "failed to write to microvm stdout log");
+                            }
+                            if let Err(e) = log_guard.flush().await {
+                                tracing::error!(microvm_pid = microvm_pid, error = %e, "failed to flush microvm stdout log");
+                            }
+                        }
                     });
                 }
 
@@ -190,6 +263,7 @@ impl ProcessMonitor for MicroVmMonitor {
                 if let Some(mut stderr) = stderr {
                     let log = microvm_log.clone();
                     let forward_output = self.forward_output;
+                    let mut encoder = LogEncoder::new(self.log_format, LogStream::Stderr);
                     tokio::spawn(async move {
                         let mut buf = [0u8; 8192]; // NOTE(appcypher): Using 8192 as buffer size because ChatGPT recommended it lol
                         while let Ok(n) = stderr.read(&mut buf).await {
@@ -198,7 +272,7 @@ impl ProcessMonitor for MicroVmMonitor {
                             }
                             // Write to log file
                             let mut log_guard = log.lock().await;
-                            if let Err(e) = log_guard.write_all(&buf[..n]).await {
+                            if let Err(e) = log_guard.write_all(&encoder.encode(&buf[..n])).await {
                                 tracing::error!(microvm_pid = microvm_pid, error = %e, "failed to write to microvm stderr log");
                             }
                             if let Err(e) = log_guard.flush().await {
@@ -214,6 +288,18 @@ impl ProcessMonitor for MicroVmMonitor {
                                 }
                             }
                         }
+
+                        // Write out an unterminated last line
+                        let rest = encoder.finish();
+                        if !rest.is_empty() {
+                            let mut log_guard = log.lock().await;
+                            if let Err(e) = log_guard.write_all(&rest).await {
+                                tracing::error!(microvm_pid = microvm_pid, error = %e, "failed to write to microvm stderr log");
+                            }
+                            if let Err(e) = log_guard.flush().await {
+                                tracing::error!(microvm_pid = microvm_pid, error = %e, "failed to flush microvm stderr log");
+                            }
+                        }
                     });
                 }
 
@@ -248,6 +334,8 @@ impl ProcessMonitor for MicroVmMonitor {
                 // Spawn async task to read from the master
                 let log = microvm_log.clone();
                 let forward_output = self.forward_output;
+                // A TTY merges stdout and stderr
+                let mut encoder = LogEncoder::new(self.log_format, LogStream::Stdout);
                 tokio::spawn(async move {
                     let mut buf = [0u8; 1024];
                     loop {
@@ -264,7 +352,9 @@ impl ProcessMonitor for MicroVmMonitor {
                             Ok(Ok(n)) => {
                                 // Write to log file
                                 let mut log_guard = log.lock().await;
-                                if let Err(e) = log_guard.write_all(&buf[..n]).await {
+                                if let Err(e) =
+                                    log_guard.write_all(&encoder.encode(&buf[..n])).await
+                                {
                                     tracing::error!(microvm_pid = microvm_pid, error = %e, "failed to write to microvm tty log");
                                 }
                                 if let Err(e) = log_guard.flush().await {
@@ -285,6 +375,18 @@ impl ProcessMonitor for MicroVmMonitor {
                             Err(_) => continue,
                         }
                     }
+
+                    // Write out an unterminated last line
+                    let rest = encoder.finish();
+                    if !rest.is_empty() {
+                        let mut log_guard = log.lock().await;
+                        if let Err(e) = log_guard.write_all(&rest).await {
+                            tracing::error!(microvm_pid = microvm_pid, error = %e, "failed to write to microvm tty log");
+                        }
+                        if let Err(e) = log_guard.flush().await {
+                            tracing::error!(microvm_pid = microvm_pid, error = %e, "failed to flush microvm tty log");
+                        }
+                    }
                 });
 
                 // Spawn async task to copy parent's stdin to the master
@@ -304,6 +406,11 @@ impl ProcessMonitor for MicroVmMonitor {
         // Restore terminal settings if they were modified
         self.restore_terminal_settings();
 
+        // Stop recording the MicroVM's resource usage
+        if let Some(usage_task) = self.usage_task.take() {
+            usage_task.abort();
+        }
+
         // Update sandbox status to stopped
         db::update_sandbox_status(
             &self.sandbox_db,
@@ -324,5 +431,109 @@ impl ProcessMonitor for MicroVmMonitor {
 impl Drop for MicroVmMonitor {
     fn drop(&mut self) {
         self.restore_terminal_settings();
+        if let Some(usage_task) = self.usage_task.take() {
+            usage_task.abort();
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Records a snapshot of the resources used by the MicroVM `pid` in the sandbox database every
+/// [`DEFAULT_SANDBOX_USAGE_INTERVAL`], starting straight away.
+async fn record_usage(
+    sandbox_db: Pool<Sqlite>,
+    sandbox_id: i64,
+    pid: u32,
+    usage_source: Arc<dyn UsageSource>,
+) {
+    let mut interval = tokio::time::interval(DEFAULT_SANDBOX_USAGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let usage = SandboxUsage::collect(pid, usage_source.as_ref());
+        if let Err(e) = db::save_sandbox_usage(&sandbox_db, sandbox_id, &usage).await {
+            tracing::warn!(microvm_pid = pid, error = %e, "failed to record microvm resource usage");
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_microvm_monitor_records_usage() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let db_path = temp_dir.path().join("sandbox.db");
+        let pool = db::initialize(&db_path, &db::SANDBOX_DB_MIGRATOR).await?;
+
+        let mut monitor = MicroVmMonitor::new(
+            1,
+            &db_path,
+            "app".to_string(),
+            "monocore.yaml".to_string(),
+            Utc::now(),
+            temp_dir.path(),
+            Rootfs::Native(temp_dir.path().to_path_buf()),
+            false,
+        )
+        .await?
+        .with_usage_source(helper::FixedUsage);
+
+        let child_io = ChildIo::Piped {
+            stdin: None,
+            stdout: None,
+            stderr: None,
+        };
+        monitor.start(2, child_io).await?;
+
+        // The first snapshot is recorded as soon as the MicroVM starts
+        let sandbox = db::get_sandbox(&pool, "app", "monocore.yaml")
+            .await?
+            .unwrap();
+        let usage = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Some(usage) = db::get_latest_sandbox_usage(&pool, sandbox.id).await? {
+                    return MonocoreResult::Ok(usage);
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await??;
+
+        assert_eq!(*usage.get_cpu_time_ms(), Some(1500));
+        assert_eq!(*usage.get_memory_rss_bytes(), Some(64 * 1024 * 1024));
+
+        monitor.stop().await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod helper {
+    use super::*;
+
+    /// A usage source that reports the same stats for every process.
+    pub(super) struct FixedUsage;
+
+    impl UsageSource for FixedUsage {
+        fn cpu_time(&self, _pid: u32) -> Option<std::time::Duration> {
+            Some(std::time::Duration::from_millis(1500))
+        }
+
+        fn memory_rss(&self, _pid: u32) -> Option<u64> {
+            Some(64 * 1024 * 1024)
+        }
     }
 }
@@ -0,0 +1,160 @@
+use std::time::Duration;
+
+use getset::Getters;
+use serde::{Deserialize, Serialize};
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// A snapshot of the resources used by a sandbox's MicroVM process.
+///
+/// A field is `None` when the platform doesn't report it, or the process is gone.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Getters)]
+#[getset(get = "pub with_prefix")]
+pub struct SandboxUsage {
+    /// The CPU time the MicroVM has used in user and kernel mode, in milliseconds.
+    pub(crate) cpu_time_ms: Option<u64>,
+
+    /// The resident set size of the MicroVM, including the guest memory it has touched, in bytes.
+    pub(crate) memory_rss_bytes: Option<u64>,
+}
+
+/// The procfs source of process resource usage. It reports nothing on platforms without procfs.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ProcfsUsage;
+
+//--------------------------------------------------------------------------------------------------
+// Traits
+//--------------------------------------------------------------------------------------------------
+
+/// A source of process resource usage.
+pub trait UsageSource: Send + Sync {
+    /// Returns the CPU time process `pid` has used in user and kernel mode, if available.
+    fn cpu_time(&self, pid: u32) -> Option<Duration>;
+
+    /// Returns the resident set size of process `pid` in bytes, if available.
+    fn memory_rss(&self, pid: u32) -> Option<u64>;
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl SandboxUsage {
+    /// Takes a snapshot of the resources used by the MicroVM process `pid`, as reported by
+    /// `source`.
+    pub fn collect(pid: u32, source: &dyn UsageSource) -> Self {
+        Self {
+            cpu_time_ms: source
+                .cpu_time(pid)
+                .map(|cpu_time| cpu_time.as_millis() as u64),
+            memory_rss_bytes: source.memory_rss(pid),
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Trait Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl UsageSource for ProcfsUsage {
+    fn cpu_time(&self, pid: u32) -> Option<Duration> {
+        let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+
+        // The command name can contain spaces, so the fields are counted after its closing paren.
+        // utime and stime are fields 14 and 15 of the whole line.
+        let (_, fields) = stat.rsplit_once(')')?;
+        let mut fields = fields.split_whitespace().skip(11);
+        let utime: u64 = fields.next()?.parse().ok()?;
+        let stime: u64 = fields.next()?.parse().ok()?;
+
+        // SAFETY: sysconf only reads a system configuration value and has no preconditions.
+        let ticks_per_sec = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        if ticks_per_sec <= 0 {
+            return None;
+        }
+
+        let ticks = utime + stime;
+        Some(Duration::from_millis(ticks * 1000 / ticks_per_sec as u64))
+    }
+
+    fn memory_rss(&self, pid: u32) -> Option<u64> {
+        let status = std::fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+        let rss_kib = status
+            .lines()
+            .find_map(|line| line.strip_prefix("VmRSS:"))?
+            .trim()
+            .strip_suffix("kB")?
+            .trim()
+            .parse::<u64>()
+            .ok()?;
+
+        Some(rss_kib * 1024)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sandbox_usage_collect() {
+        let source = helper::FakeStats {
+            cpu_time: Some(Duration::from_millis(1500)),
+            memory_rss: Some(64 * 1024 * 1024),
+        };
+
+        let usage = SandboxUsage::collect(42, &source);
+        assert_eq!(*usage.get_cpu_time_ms(), Some(1500));
+        assert_eq!(*usage.get_memory_rss_bytes(), Some(64 * 1024 * 1024));
+    }
+
+    #[test]
+    fn test_sandbox_usage_collect_unavailable_stats() {
+        let usage = SandboxUsage::collect(42, &helper::FakeStats::default());
+        assert_eq!(usage, SandboxUsage::default());
+
+        let json = serde_json::to_value(&usage).unwrap();
+        assert!(json["cpu_time_ms"].is_null());
+        assert!(json["memory_rss_bytes"].is_null());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_procfs_usage_reports_own_process() {
+        let pid = std::process::id();
+        assert!(ProcfsUsage.cpu_time(pid).is_some());
+        assert!(ProcfsUsage.memory_rss(pid).is_some_and(|rss| rss > 0));
+
+        // A process that doesn't exist has no usage
+        assert_eq!(ProcfsUsage.cpu_time(u32::MAX), None);
+        assert_eq!(ProcfsUsage.memory_rss(u32::MAX), None);
+    }
+}
+
+#[cfg(test)]
+mod helper {
+    use super::*;
+
+    /// A usage source that reports fixed stats for every process.
+    #[derive(Default)]
+    pub(super) struct FakeStats {
+        pub(super) cpu_time: Option<Duration>,
+        pub(super) memory_rss: Option<u64>,
+    }
+
+    impl UsageSource for FakeStats {
+        fn cpu_time(&self, _pid: u32) -> Option<Duration> {
+            self.cpu_time
+        }
+
+        fn memory_rss(&self, _pid: u32) -> Option<u64> {
+            self.memory_rss
+        }
+    }
+}
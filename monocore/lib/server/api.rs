@@ -1,22 +1,48 @@
 use axum::{
     body::Body,
-    extract::State,
+    extract::{Path as UrlPath, Query, State},
     http::{header, Request, StatusCode},
     middleware::{self, Next},
-    response::{IntoResponse, Response},
-    routing::post,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
     Json, Router,
 };
 
+use futures::{
+    stream::{self, BoxStream},
+    Stream, StreamExt, TryStreamExt,
+};
 use jsonwebtoken::{decode, DecodingKey, Validation};
+use monoutils::{log, LOG_SUFFIX};
 use serde::{Deserialize, Serialize};
-use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+use sqlx::{Pool, Sqlite};
+use std::{
+    collections::BTreeMap,
+    convert::Infallible,
+    future::{Future, IntoFuture},
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{
+    net::TcpListener,
+    signal::unix::{signal, SignalKind},
+    sync::{watch, Notify},
+};
 
 use crate::{
-    config::{DEFAULT_CONFIG, DEFAULT_SERVER_NAMESPACE},
-    management::{orchestra, server::API_KEY_PREFIX},
-    server::data::{DownRequest, ErrorResponse, ErrorType, StatusResponse, UpRequest},
-    utils::{self, MONOCORE_CONFIG_FILENAME},
+    config::{
+        PathSegment, DEFAULT_CONFIG, DEFAULT_SANDBOX_EXIT_POLL_INTERVAL, DEFAULT_SERVER_NAMESPACE,
+        DEFAULT_SERVER_SHUTDOWN_TIMEOUT,
+    },
+    management::{db, orchestra, server::API_KEY_PREFIX},
+    server::data::{DownRequest, ErrorResponse, ErrorType, LogsQuery, StatusResponse, UpRequest},
+    utils::{self, LOG_SUBDIR, MONOCORE_CONFIG_FILENAME, MONOCORE_ENV_DIR, SANDBOX_DB_FILENAME},
     MonocoreError, MonocoreResult,
 };
 
@@ -45,6 +71,15 @@ pub struct SandboxServer {
 
     /// JWT authentication key
     key: Option<String>,
+
+    /// How long to wait for in-flight requests to complete when shutting down
+    shutdown_timeout: Duration,
+
+    /// Whether to stop the sandboxes running in the namespaces when shutting down
+    stop_sandboxes_on_shutdown: bool,
+
+    /// Set once the server starts shutting down, which ends the followed log streams
+    shutting_down: Arc<watch::Sender<bool>>,
 }
 
 /// JWT Claims structure for API authentication
@@ -58,7 +93,7 @@ pub struct Claims {
 }
 
 /// Type alias for the standard API response
-type ApiResponse<T> = Result<Json<T>, Json<ErrorResponse>>;
+type ApiResponse<T> = Result<Json<T>, ErrorResponse>;
 
 //--------------------------------------------------------------------------------------------------
 // Methods
@@ -78,6 +113,9 @@ impl SandboxServer {
             enable_default_namespace,
             addr,
             key,
+            shutdown_timeout: DEFAULT_SERVER_SHUTDOWN_TIMEOUT,
+            stop_sandboxes_on_shutdown: false,
+            shutting_down: Arc::new(watch::channel(false).0),
         };
 
         // Create default namespace directory and Sandboxfile if enabled
@@ -94,8 +132,71 @@ impl SandboxServer {
         Ok(server)
     }
 
+    /// Set how long to wait for in-flight requests to complete when shutting down
+    pub fn with_shutdown_timeout(mut self, shutdown_timeout: Duration) -> Self {
+        self.shutdown_timeout = shutdown_timeout;
+        self
+    }
+
+    /// Set whether to stop the sandboxes running in the namespaces when shutting down.
+    ///
+    /// By default they are left running, so they outlive server restarts.
+    pub fn with_stop_sandboxes_on_shutdown(mut self, stop_sandboxes_on_shutdown: bool) -> Self {
+        self.stop_sandboxes_on_shutdown = stop_sandboxes_on_shutdown;
+        self
+    }
+
     /// Start the server on the specified address
+    ///
+    /// The server runs until it receives SIGTERM or SIGINT. It then stops accepting connections
+    /// and waits up to the shutdown timeout for in-flight requests to complete, before closing
+    /// the remaining connections and, if configured to, stopping the running sandboxes.
     pub async fn serve(&self) -> anyhow::Result<()> {
+        let listener = TcpListener::bind(self.addr).await?;
+
+        tracing::info!("Server listening on {}", self.addr);
+
+        let drained = self.serve_on(listener, shutdown_signal()?).await?;
+        if !drained {
+            tracing::warn!(
+                "In-flight requests did not complete within {:?}, closing them",
+                self.shutdown_timeout
+            );
+        }
+
+        if self.stop_sandboxes_on_shutdown {
+            self.stop_sandboxes().await?;
+        }
+
+        tracing::info!("Server shut down");
+
+        Ok(())
+    }
+
+    /// Serves the router on `listener` until `signal` resolves, then ends the followed log streams
+    /// and waits up to the shutdown timeout for in-flight requests to complete.
+    ///
+    /// ## Returns
+    ///
+    /// Whether all in-flight requests completed within the shutdown timeout.
+    async fn serve_on(
+        &self,
+        listener: TcpListener,
+        signal: impl Future<Output = ()> + Send + 'static,
+    ) -> io::Result<bool> {
+        let shutting_down = self.shutting_down.clone();
+        let signal = async move {
+            signal.await;
+            shutting_down.send_replace(true);
+        };
+
+        serve_until(listener, self.router(), signal, self.shutdown_timeout).await
+    }
+
+    /// Build the router with all the server's endpoints
+    ///
+    /// In secure mode every endpoint except `/health` requires a valid bearer token.
+    pub fn router(&self) -> Router {
         // Create shared application state
         let state = Arc::new(self.clone());
 
@@ -103,6 +204,7 @@ impl SandboxServer {
         let mut app = Router::new()
             .route("/up", post(up))
             .route("/down", post(down))
+            .route("/sandboxes/{name}/logs", get(logs))
             .with_state(state.clone());
 
         // Add JWT authentication if secure mode is enabled
@@ -112,15 +214,8 @@ impl SandboxServer {
             app = app.layer(middleware::from_fn_with_state(state, auth_middleware));
         }
 
-        tracing::info!("Server listening on {}", self.addr);
-
-        axum::serve(
-            tokio::net::TcpListener::bind(self.addr).await?,
-            app.into_make_service(),
-        )
-        .await?;
-
-        Ok(())
+        // Added after the authentication layer so health checks don't need a token
+        app.route("/health", get(health))
     }
 
     /// Get the path to a namespace directory, creating it if it doesn't exist
@@ -141,6 +236,55 @@ impl SandboxServer {
         Ok(namespace_path)
     }
 
+    /// Stop the sandboxes running in all namespaces
+    ///
+    /// A namespace whose sandboxes fail to stop is logged and skipped, so the others still stop.
+    async fn stop_sandboxes(&self) -> MonocoreResult<()> {
+        if !self.namespace_dir.exists() {
+            return Ok(());
+        }
+
+        let mut entries = tokio::fs::read_dir(&self.namespace_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let namespace_path = entry.path();
+            let db_path = namespace_path
+                .join(MONOCORE_ENV_DIR)
+                .join(SANDBOX_DB_FILENAME);
+            if !db_path.exists() {
+                continue;
+            }
+
+            let pool = db::get_pool(&db_path).await?;
+            let mut config_sandboxes: BTreeMap<String, Vec<String>> = BTreeMap::new();
+            for sandbox in db::get_running_sandboxes(&pool).await? {
+                config_sandboxes
+                    .entry(sandbox.config_file)
+                    .or_default()
+                    .push(sandbox.name);
+            }
+
+            for (config_file, sandboxes) in config_sandboxes {
+                tracing::info!(
+                    "Stopping sandboxes in {}: {}",
+                    namespace_path.display(),
+                    sandboxes.join(", ")
+                );
+
+                if let Err(e) =
+                    orchestra::down(sandboxes, Some(&namespace_path), Some(&config_file)).await
+                {
+                    tracing::error!(
+                        "Failed to stop sandboxes in {}: {}",
+                        namespace_path.display(),
+                        e
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Get the server's JWT authentication key
     fn get_jwt_key(&self) -> MonocoreResult<String> {
         if let Some(key) = &self.key {
@@ -261,6 +405,11 @@ async fn auth_middleware(
 // Functions: Handlers
 //--------------------------------------------------------------------------------------------------
 
+/// Handler for checking that the server is up
+async fn health() -> Json<StatusResponse> {
+    Json(StatusResponse::ok())
+}
+
 /// Handler for starting sandboxes
 async fn up(
     State(state): State<Arc<SandboxServer>>,
@@ -268,14 +417,12 @@ async fn up(
 ) -> ApiResponse<StatusResponse> {
     tracing::info!("Received up request: {:?}", request);
     let namespace_path = state.get_namespace_path(request.namespace).map_err(|e| {
-        Json(
-            ErrorResponse::new(
-                400,
-                "Invalid namespace".to_string(),
-                ErrorType::NamespaceError,
-            )
-            .with_details(e.to_string()),
+        ErrorResponse::new(
+            400,
+            "Invalid namespace".to_string(),
+            ErrorType::NamespaceError,
         )
+        .with_details(e.to_string())
     })?;
 
     orchestra::up(
@@ -286,14 +433,12 @@ async fn up(
     .await
     .map_err(|e| {
         tracing::error!("Failed to start sandboxes: {}", e);
-        Json(
-            ErrorResponse::new(
-                500,
-                "Failed to start sandboxes".to_string(),
-                ErrorType::SandboxError,
-            )
-            .with_details(e.to_string()),
+        ErrorResponse::new(
+            500,
+            "Failed to start sandboxes".to_string(),
+            ErrorType::SandboxError,
         )
+        .with_details(e.to_string())
     })?;
 
     Ok(Json(StatusResponse::ok()))
@@ -306,14 +451,12 @@ async fn down(
 ) -> ApiResponse<StatusResponse> {
     tracing::info!("Received down request: {:?}", request);
     let namespace_path = state.get_namespace_path(request.namespace).map_err(|e| {
-        Json(
-            ErrorResponse::new(
-                400,
-                "Invalid namespace".to_string(),
-                ErrorType::NamespaceError,
-            )
-            .with_details(e.to_string()),
+        ErrorResponse::new(
+            400,
+            "Invalid namespace".to_string(),
+            ErrorType::NamespaceError,
         )
+        .with_details(e.to_string())
     })?;
 
     orchestra::down(
@@ -324,23 +467,200 @@ async fn down(
     .await
     .map_err(|e| {
         tracing::error!("Failed to stop sandboxes: {}", e);
-        Json(
+        ErrorResponse::new(
+            500,
+            "Failed to stop sandboxes".to_string(),
+            ErrorType::SandboxError,
+        )
+        .with_details(e.to_string())
+    })?;
+
+    Ok(Json(StatusResponse::ok()))
+}
+
+/// Handler for streaming a sandbox's logs as Server-Sent Events
+///
+/// Each log line is sent as the data of one event. With `follow`, new lines are sent as they are
+/// written until the sandbox exits, the client disconnects or the server shuts down. An error while
+/// reading the log is sent as an `error` event, which ends the stream.
+async fn logs(
+    State(state): State<Arc<SandboxServer>>,
+    UrlPath(name): UrlPath<String>,
+    Query(query): Query<LogsQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ErrorResponse> {
+    tracing::info!("Received logs request for {}: {:?}", name, query);
+    let namespace_path = state.get_namespace_path(query.namespace).map_err(|e| {
+        ErrorResponse::new(
+            400,
+            "Invalid namespace".to_string(),
+            ErrorType::NamespaceError,
+        )
+        .with_details(e.to_string())
+    })?;
+
+    let lines = sandbox_log_lines(
+        &namespace_path,
+        query.config_file.as_deref(),
+        &name,
+        query.follow,
+        query.tail,
+    )
+    .await
+    .map_err(|e| match e {
+        MonocoreError::LogNotFound(_) => {
+            ErrorResponse::new(404, "Log not found".to_string(), ErrorType::NotFound)
+                .with_details(e.to_string())
+        }
+        MonocoreError::EmptyPathSegment | MonocoreError::InvalidPathComponent(_) => {
+            ErrorResponse::new(
+                400,
+                "Invalid sandbox name or config file".to_string(),
+                ErrorType::ValidationError,
+            )
+            .with_details(e.to_string())
+        }
+        _ => {
+            tracing::error!("Failed to read sandbox logs: {}", e);
             ErrorResponse::new(
                 500,
-                "Failed to stop sandboxes".to_string(),
+                "Failed to read sandbox logs".to_string(),
                 ErrorType::SandboxError,
             )
-            .with_details(e.to_string()),
-        )
+            .with_details(e.to_string())
+        }
     })?;
 
-    Ok(Json(StatusResponse::ok()))
+    // A followed log would otherwise hold its connection open until the shutdown timeout
+    let mut shutting_down = state.shutting_down.subscribe();
+    let lines = lines.take_until(async move {
+        let _ = shutting_down.wait_for(|shutting_down| *shutting_down).await;
+    });
+
+    let events = lines.map(|line| {
+        Ok(match line {
+            Ok(line) => Event::default().data(line),
+            Err(e) => Event::default().event("error").data(e.to_string()),
+        })
+    });
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
 }
 
 //--------------------------------------------------------------------------------------------------
 // Functions: Helpers
 //--------------------------------------------------------------------------------------------------
 
+/// Serves `app` on `listener` until `signal` resolves, then stops accepting connections and waits up
+/// to `timeout` for in-flight requests to complete.
+///
+/// ## Returns
+///
+/// Whether all in-flight requests completed within `timeout`. If they didn't, their connections
+/// are abandoned and close when the process exits.
+async fn serve_until(
+    listener: TcpListener,
+    app: Router,
+    signal: impl Future<Output = ()> + Send + 'static,
+    timeout: Duration,
+) -> io::Result<bool> {
+    let draining = Arc::new(Notify::new());
+    let server = axum::serve(listener, app).with_graceful_shutdown({
+        let draining = draining.clone();
+        async move {
+            signal.await;
+            tracing::info!("Shutting down, waiting for in-flight requests to complete");
+            draining.notify_one();
+        }
+    });
+
+    tokio::select! {
+        result = server.into_future() => result.map(|_| true),
+        _ = async {
+            draining.notified().await;
+            tokio::time::sleep(timeout).await;
+        } => Ok(false),
+    }
+}
+
+/// Returns a future that resolves when the process receives SIGTERM or SIGINT.
+fn shutdown_signal() -> io::Result<impl Future<Output = ()>> {
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut sigint = signal(SignalKind::interrupt())?;
+
+    Ok(async move {
+        tokio::select! {
+            _ = sigterm.recv() => tracing::info!("Received SIGTERM signal"),
+            _ = sigint.recv() => tracing::info!("Received SIGINT signal"),
+        }
+    })
+}
+
+/// Returns a stream of the lines in a sandbox's log, the last `tail` of them if specified.
+///
+/// With `follow`, the stream keeps yielding lines as they are written until the sandbox exits. If
+/// the sandbox isn't running, only the existing lines are yielded.
+async fn sandbox_log_lines(
+    project_dir: &Path,
+    config_file: Option<&str>,
+    name: &str,
+    follow: bool,
+    tail: Option<usize>,
+) -> MonocoreResult<BoxStream<'static, MonocoreResult<String>>> {
+    // The log path only depends on the config file name, so the config itself isn't loaded
+    let canonical_project_dir = tokio::fs::canonicalize(project_dir).await?;
+    let config_file = config_file.unwrap_or(MONOCORE_CONFIG_FILENAME).to_string();
+    let _ = PathSegment::try_from(config_file.as_str())?;
+    let _ = PathSegment::try_from(name)?;
+
+    // Construct log file path: <project_dir>/.menv/log/<config>-<sandbox>.log
+    let menv_path = canonical_project_dir.join(MONOCORE_ENV_DIR);
+    let log_path = menv_path
+        .join(LOG_SUBDIR)
+        .join(format!("{}-{}.{}", config_file, name, LOG_SUFFIX));
+    if !log_path.exists() {
+        return Err(MonocoreError::LogNotFound(format!(
+            "Log file not found at {}",
+            log_path.display()
+        )));
+    }
+
+    let db_path = menv_path.join(SANDBOX_DB_FILENAME);
+    let pool = db::get_or_create_pool(&db_path, &db::SANDBOX_DB_MIGRATOR).await?;
+    if follow && is_sandbox_running(&pool, &config_file, name).await? {
+        let lines = log::tail_follow(log_path.clone(), tail.unwrap_or(usize::MAX))
+            .await?
+            .map_err(MonocoreError::from);
+        let name = name.to_string();
+        let exited = async move { wait_for_sandbox_exit(&pool, &config_file, &name).await };
+
+        return Ok(lines.take_until(exited).boxed());
+    }
+
+    let contents = tokio::fs::read_to_string(&log_path).await?;
+    let lines: Vec<String> = contents.lines().map(String::from).collect();
+    let skip = lines.len().saturating_sub(tail.unwrap_or(usize::MAX));
+
+    Ok(stream::iter(lines.into_iter().skip(skip).map(Ok)).boxed())
+}
+
+/// Returns whether the sandbox `name` defined in `config_file` is running.
+async fn is_sandbox_running(
+    pool: &Pool<Sqlite>,
+    config_file: &str,
+    name: &str,
+) -> MonocoreResult<bool> {
+    let running_sandboxes = db::get_running_config_sandboxes(pool, config_file).await?;
+    Ok(running_sandboxes.iter().any(|s| s.name == name))
+}
+
+/// Waits until the sandbox `name` defined in `config_file` is no longer running, or its status
+/// can't be read.
+async fn wait_for_sandbox_exit(pool: &Pool<Sqlite>, config_file: &str, name: &str) {
+    while let Ok(true) = is_sandbox_running(pool, config_file, name).await {
+        tokio::time::sleep(DEFAULT_SANDBOX_EXIT_POLL_INTERVAL).await;
+    }
+}
+
 /// Convert a custom API key back to a standard JWT token
 /// Takes our custom API key format (API_KEY_PREFIX_<payload>.<signature>) and
 /// returns a standard JWT token (<header>.<payload>.<signature>)
@@ -363,3 +683,365 @@ fn convert_api_key_to_jwt(api_key: &str) -> Result<String, StatusCode> {
     // Reconstruct the JWT format with a default header
     Ok(format!("{}.{}.{}", DEFAULT_JWT_HEADER, parts[0], parts[1]))
 }
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use tempfile::TempDir;
+    use tokio::{fs::OpenOptions, io::AsyncWriteExt, net::TcpStream};
+
+    use crate::{
+        management::server,
+        runtime::{SANDBOX_STATUS_RUNNING, SANDBOX_STATUS_STOPPED},
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_logs_streams_lines_until_sandbox_exits() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let (addr, menv_path) = helper::start_server(&temp_dir, None).await?;
+        let log_path = helper::log_path(&menv_path, "app");
+        tokio::fs::write(&log_path, "zero\none\ntwo\n").await?;
+
+        let pool = db::get_or_create_pool(
+            menv_path.join(SANDBOX_DB_FILENAME),
+            &db::SANDBOX_DB_MIGRATOR,
+        )
+        .await?;
+        db::save_or_update_sandbox(
+            &pool,
+            "app",
+            MONOCORE_CONFIG_FILENAME,
+            &Utc::now(),
+            None,
+            SANDBOX_STATUS_RUNNING,
+            1,
+            2,
+            "",
+            None,
+            None,
+        )
+        .await?;
+
+        let response = reqwest::get(format!(
+            "http://{addr}/sandboxes/app/logs?follow=true&tail=2"
+        ))
+        .await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+        let mut events = helper::SseReader::new(response);
+        assert_eq!(events.next_data().await?.as_deref(), Some("one"));
+        assert_eq!(events.next_data().await?.as_deref(), Some("two"));
+
+        // Lines written after connecting are streamed in order
+        let mut log = OpenOptions::new().append(true).open(&log_path).await?;
+        log.write_all(b"three\nfour\n").await?;
+        log.flush().await?;
+        assert_eq!(events.next_data().await?.as_deref(), Some("three"));
+        assert_eq!(events.next_data().await?.as_deref(), Some("four"));
+
+        // The stream closes when the sandbox exits
+        db::update_sandbox_status(
+            &pool,
+            "app",
+            MONOCORE_CONFIG_FILENAME,
+            SANDBOX_STATUS_STOPPED,
+        )
+        .await?;
+        let end = tokio::time::timeout(Duration::from_secs(5), events.next_data()).await??;
+        assert_eq!(end, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_logs_without_follow_sends_existing_lines() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let (addr, menv_path) = helper::start_server(&temp_dir, None).await?;
+        tokio::fs::write(helper::log_path(&menv_path, "app"), "one\ntwo\nthree\n").await?;
+
+        // The sandbox isn't running, so following only sends the existing lines too
+        for url in [
+            format!("http://{addr}/sandboxes/app/logs?tail=2"),
+            format!("http://{addr}/sandboxes/app/logs?follow=true&tail=2"),
+        ] {
+            let response = reqwest::get(url).await?;
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(
+                response.headers()[header::CONTENT_TYPE],
+                "text/event-stream"
+            );
+            let mut events = helper::SseReader::new(response);
+            assert_eq!(events.next_data().await?.as_deref(), Some("two"));
+            assert_eq!(events.next_data().await?.as_deref(), Some("three"));
+            assert_eq!(events.next_data().await?, None);
+        }
+
+        // A sandbox without a log is not found
+        let response = reqwest::get(format!("http://{addr}/sandboxes/missing/logs")).await?;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body: serde_json::Value = response.json().await?;
+        assert_eq!(body["code"], 404);
+
+        // A config file or sandbox name that isn't a single path segment is rejected
+        let response = reqwest::get(format!(
+            "http://{addr}/sandboxes/app/logs?config_file=..%2Fmonocore.yaml"
+        ))
+        .await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = reqwest::get(format!("http://{addr}/sandboxes/..%2Fapp/logs")).await?;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_secure_mode_requires_bearer_token() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let (addr, menv_path) = helper::start_server(&temp_dir, Some("secret")).await?;
+        tokio::fs::write(helper::log_path(&menv_path, "app"), "one\n").await?;
+
+        let client = reqwest::Client::new();
+        let url = format!("http://{addr}/sandboxes/app/logs");
+
+        // Missing token
+        let response = client.get(&url).send().await?;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Token signed with another key
+        let other_key = server::generate_api_key("other", chrono::Duration::hours(1))?;
+        let response = client.get(&url).bearer_auth(other_key).send().await?;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Expired token
+        let expired_key = server::generate_api_key("secret", chrono::Duration::hours(-1))?;
+        let response = client.get(&url).bearer_auth(expired_key).send().await?;
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+        // Valid token
+        let api_key = server::generate_api_key("secret", chrono::Duration::hours(1))?;
+        let response = client.get(&url).bearer_auth(api_key).send().await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut events = helper::SseReader::new(response);
+        assert_eq!(events.next_data().await?.as_deref(), Some("one"));
+
+        // Health checks don't need a token
+        let response = client.get(format!("http://{addr}/health")).send().await?;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_in_flight_requests() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let (addr, menv_path, shutdown, server) =
+            helper::start_server_with_shutdown(&temp_dir, None, Duration::from_secs(5)).await?;
+        tokio::fs::write(helper::log_path(&menv_path, "app"), "one\n").await?;
+
+        let pool = db::get_or_create_pool(
+            menv_path.join(SANDBOX_DB_FILENAME),
+            &db::SANDBOX_DB_MIGRATOR,
+        )
+        .await?;
+        db::save_or_update_sandbox(
+            &pool,
+            "app",
+            MONOCORE_CONFIG_FILENAME,
+            &Utc::now(),
+            None,
+            SANDBOX_STATUS_RUNNING,
+            1,
+            2,
+            "",
+            None,
+            None,
+        )
+        .await?;
+
+        let response =
+            reqwest::get(format!("http://{addr}/sandboxes/app/logs?follow=true")).await?;
+        let mut events = helper::SseReader::new(response);
+        assert_eq!(events.next_data().await?.as_deref(), Some("one"));
+
+        // The sandbox is still running, but the followed stream ends so the server can drain
+        shutdown.send(()).unwrap();
+        let end = tokio::time::timeout(Duration::from_secs(5), events.next_data()).await??;
+        assert_eq!(end, None);
+
+        let drained = tokio::time::timeout(Duration::from_secs(5), server).await???;
+        assert!(drained);
+
+        // New connections are no longer accepted
+        assert!(reqwest::get(format!("http://{addr}/health")).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_closes_requests_after_timeout() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let (addr, _, shutdown, server) =
+            helper::start_server_with_shutdown(&temp_dir, None, Duration::from_millis(200)).await?;
+
+        // A request whose body never arrives stays in flight
+        let mut stream = TcpStream::connect(addr).await?;
+        stream
+            .write_all(
+                b"POST /up HTTP/1.1\r\nHost: localhost\r\nContent-Type: application/json\r\n\
+                  Content-Length: 64\r\n\r\n{",
+            )
+            .await?;
+        stream.flush().await?;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        shutdown.send(()).unwrap();
+
+        let drained = tokio::time::timeout(Duration::from_secs(5), server).await???;
+        assert!(!drained);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_insecure_mode_does_not_require_bearer_token() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let (addr, menv_path) = helper::start_server(&temp_dir, None).await?;
+        tokio::fs::write(helper::log_path(&menv_path, "app"), "one\n").await?;
+
+        let response = reqwest::get(format!("http://{addr}/sandboxes/app/logs")).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut events = helper::SseReader::new(response);
+        assert_eq!(events.next_data().await?.as_deref(), Some("one"));
+
+        let response = reqwest::get(format!("http://{addr}/health")).await?;
+        assert_eq!(response.status(), StatusCode::OK);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod helper {
+    use bytes::Bytes;
+    use tempfile::TempDir;
+    use tokio::{sync::oneshot, task::JoinHandle};
+
+    use crate::management::menv;
+
+    use super::*;
+
+    /// Starts a server for namespaces in `temp_dir`, secured with `key` if given, returning its
+    /// address and the menv path of the default namespace.
+    pub(super) async fn start_server(
+        temp_dir: &TempDir,
+        key: Option<&str>,
+    ) -> anyhow::Result<(SocketAddr, PathBuf)> {
+        let (addr, menv_path, _) = spawn_server(
+            temp_dir,
+            key,
+            DEFAULT_SERVER_SHUTDOWN_TIMEOUT,
+            std::future::pending(),
+        )
+        .await?;
+        Ok((addr, menv_path))
+    }
+
+    /// Starts a server for namespaces in `temp_dir`, secured with `key` if given, until the
+    /// returned sender is used to shut it down with the given drain `timeout`.
+    pub(super) async fn start_server_with_shutdown(
+        temp_dir: &TempDir,
+        key: Option<&str>,
+        timeout: Duration,
+    ) -> anyhow::Result<(
+        SocketAddr,
+        PathBuf,
+        oneshot::Sender<()>,
+        JoinHandle<io::Result<bool>>,
+    )> {
+        let (shutdown, signal) = oneshot::channel();
+        let (addr, menv_path, server) = spawn_server(temp_dir, key, timeout, async move {
+            let _ = signal.await;
+        })
+        .await?;
+
+        Ok((addr, menv_path, shutdown, server))
+    }
+
+    async fn spawn_server(
+        temp_dir: &TempDir,
+        key: Option<&str>,
+        timeout: Duration,
+        signal: impl Future<Output = ()> + Send + 'static,
+    ) -> anyhow::Result<(SocketAddr, PathBuf, JoinHandle<io::Result<bool>>)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = SandboxServer::new(
+            Some(temp_dir.path().to_path_buf()),
+            true,
+            addr,
+            key.map(String::from),
+        )?
+        .with_shutdown_timeout(timeout);
+
+        let menv_path = server.get_namespace_path(None)?.join(MONOCORE_ENV_DIR);
+        menv::ensure_menv_files(&menv_path).await?;
+
+        let server = tokio::spawn(async move { server.serve_on(listener, signal).await });
+
+        Ok((addr, menv_path, server))
+    }
+
+    /// Tracks the requests to a route that takes a while to respond.
+    /// Returns the path of the log of sandbox `name` in the default namespace.
+    pub(super) fn log_path(menv_path: &Path, name: &str) -> PathBuf {
+        menv_path
+            .join(LOG_SUBDIR)
+            .join(format!("{MONOCORE_CONFIG_FILENAME}-{name}.{LOG_SUFFIX}"))
+    }
+
+    /// Reads the data of Server-Sent Events from a response.
+    pub(super) struct SseReader {
+        body: BoxStream<'static, reqwest::Result<Bytes>>,
+        buffer: String,
+    }
+
+    impl SseReader {
+        pub(super) fn new(response: reqwest::Response) -> Self {
+            Self {
+                body: response.bytes_stream().boxed(),
+                buffer: String::new(),
+            }
+        }
+
+        /// Returns the data of the next event, skipping keep-alive comments, or `None` once the
+        /// stream has ended.
+        pub(super) async fn next_data(&mut self) -> anyhow::Result<Option<String>> {
+            loop {
+                while let Some(end) = self.buffer.find("\n\n") {
+                    let event: String = self.buffer.drain(..end + 2).collect();
+                    let data: Vec<&str> = event
+                        .lines()
+                        .filter_map(|line| line.strip_prefix("data:"))
+                        .map(|data| data.strip_prefix(' ').unwrap_or(data))
+                        .collect();
+                    if !data.is_empty() {
+                        return Ok(Some(data.join("\n")));
+                    }
+                }
+
+                match self.body.next().await {
+                    Some(chunk) => self.buffer.push_str(std::str::from_utf8(&chunk?)?),
+                    None => return Ok(None),
+                }
+            }
+        }
+    }
+}
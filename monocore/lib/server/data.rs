@@ -1,3 +1,8 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
 use serde::{Deserialize, Serialize};
 
 //--------------------------------------------------------------------------------------------------
@@ -30,6 +35,23 @@ pub struct DownRequest {
     pub sandboxes: Vec<String>,
 }
 
+/// Query parameters for streaming a sandbox's logs
+#[derive(Debug, Default, Deserialize)]
+pub struct LogsQuery {
+    /// Optional namespace name, defaults to "default" if not specified
+    pub namespace: Option<String>,
+
+    /// Optional config file name, defaults to Sandboxfile if not specified
+    pub config_file: Option<String>,
+
+    /// Whether to keep streaming new log lines until the sandbox exits
+    #[serde(default)]
+    pub follow: bool,
+
+    /// Optional number of existing log lines to send, defaults to all of them
+    pub tail: Option<usize>,
+}
+
 //--------------------------------------------------------------------------------------------------
 // Types: Responses
 //--------------------------------------------------------------------------------------------------
@@ -125,3 +147,14 @@ impl ErrorResponse {
         self
     }
 }
+
+//--------------------------------------------------------------------------------------------------
+// Trait Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl IntoResponse for ErrorResponse {
+    fn into_response(self) -> Response {
+        let status = StatusCode::from_u16(self.code).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        (status, Json(self)).into_response()
+    }
+}
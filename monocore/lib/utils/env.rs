@@ -17,6 +17,12 @@ pub const OCI_REGISTRY_ENV_VAR: &str = "OCI_REGISTRY_DOMAIN";
 /// Environment variable for the mcrun binary path
 pub const MCRUN_EXE_ENV_VAR: &str = "MCRUN_EXE";
 
+/// Environment variable for the secret key the server authenticates API requests with
+pub const MONOCORE_SERVER_KEY_ENV_VAR: &str = "MONOCORE_SERVER_KEY";
+
+/// Environment variable for pulling images from the local cache only, without registry access
+pub const MONOCORE_OFFLINE_ENV_VAR: &str = "MONOCORE_OFFLINE";
+
 //--------------------------------------------------------------------------------------------------
 // Functions
 //--------------------------------------------------------------------------------------------------
@@ -42,3 +48,20 @@ pub fn get_oci_registry() -> String {
         DEFAULT_OCI_REGISTRY.to_string()
     }
 }
+
+/// Returns the secret key the server authenticates API requests with.
+/// If the MONOCORE_SERVER_KEY environment variable is set and not empty, returns that value.
+/// Otherwise, returns `None`.
+pub fn get_server_key() -> Option<String> {
+    std::env::var(MONOCORE_SERVER_KEY_ENV_VAR)
+        .ok()
+        .filter(|key| !key.is_empty())
+}
+
+/// Returns whether images are pulled from the local cache only.
+/// Returns `true` if the MONOCORE_OFFLINE environment variable is set to `1` or `true`.
+/// Otherwise, returns `false`.
+pub fn is_offline() -> bool {
+    std::env::var(MONOCORE_OFFLINE_ENV_VAR)
+        .is_ok_and(|offline| offline == "1" || offline.eq_ignore_ascii_case("true"))
+}
@@ -18,10 +18,15 @@ pub async fn get_file_hash(path: &Path, algorithm: &DigestAlgorithm) -> Monocore
     let mut buffer = Vec::new();
     file.read_to_end(&mut buffer).await?;
 
+    get_bytes_hash(&buffer, algorithm)
+}
+
+/// Gets the hash of some bytes.
+pub fn get_bytes_hash(data: &[u8], algorithm: &DigestAlgorithm) -> MonocoreResult<Vec<u8>> {
     let hash = match algorithm {
-        DigestAlgorithm::Sha256 => Sha256::digest(&buffer).to_vec(),
-        DigestAlgorithm::Sha384 => Sha384::digest(&buffer).to_vec(),
-        DigestAlgorithm::Sha512 => Sha512::digest(&buffer).to_vec(),
+        DigestAlgorithm::Sha256 => Sha256::digest(data).to_vec(),
+        DigestAlgorithm::Sha384 => Sha384::digest(data).to_vec(),
+        DigestAlgorithm::Sha512 => Sha512::digest(data).to_vec(),
         _ => {
             return Err(MonocoreError::UnsupportedImageHashAlgorithm(format!(
                 "Unsupported algorithm: {}",
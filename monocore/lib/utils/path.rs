@@ -34,11 +34,21 @@ pub const BLOCKS_SUBDIR: &str = "blocks";
 /// Example: <PROJECT_ROOT>/<MONOCORE_ENV_DIR>/<LOG_SUBDIR>
 pub const LOG_SUBDIR: &str = "log";
 
+/// The directory where the sockets of sandbox guest agents are created
+///
+/// Example: <PROJECT_ROOT>/<MONOCORE_ENV_DIR>/<AGENT_SOCKET_SUBDIR>
+pub const AGENT_SOCKET_SUBDIR: &str = "agent";
+
 /// The directory where global image layers are stored
 ///
 /// Example: <MONOCORE_HOME_DIR>/<LAYERS_SUBDIR>
 pub const LAYERS_SUBDIR: &str = "layers";
 
+/// The directory where downloaded image layer blobs are cached
+///
+/// Example: <MONOCORE_HOME_DIR>/<DOWNLOADS_SUBDIR>
+pub const DOWNLOADS_SUBDIR: &str = "downloads";
+
 /// The directory where monocore's installed binaries are stored
 ///
 /// Example: <MONOCORE_HOME_DIR>/<BIN_SUBDIR>
@@ -4,11 +4,14 @@ use ipnetwork::Ipv4Network;
 use typed_path::Utf8UnixPathBuf;
 
 use crate::{
-    config::{EnvPair, NetworkScope, PathPair, PortPair, DEFAULT_NUM_VCPUS, DEFAULT_RAM_MIB},
+    config::{
+        Capability, EnvPair, NetworkScope, PathPair, PortPair, SeccompProfile, DEFAULT_NUM_VCPUS,
+        DEFAULT_RAM_MIB,
+    },
     MonocoreResult,
 };
 
-use super::{LinuxRlimit, LogLevel, MicroVm, MicroVmConfig, Rootfs};
+use super::{LinuxRLimitResource, LinuxRlimit, LogLevel, MicroVm, MicroVmConfig, Rootfs};
 
 //--------------------------------------------------------------------------------------------------
 // Types
@@ -26,10 +29,14 @@ use super::{LinuxRlimit, LogLevel, MicroVm, MicroVmConfig, Rootfs};
 /// - `mapped_dirs`: The directories to mount in the MicroVm.
 /// - `port_map`: The ports to map in the MicroVm.
 /// - `rlimits`: The resource limits to use for the MicroVm.
+/// - `seccomp_profile`: The seccomp profile to apply to the MicroVm process.
+/// - `cap_add`: The capabilities to add to the MicroVm process.
+/// - `cap_drop`: The capabilities to drop from the MicroVm process.
 /// - `workdir_path`: The working directory to use for the MicroVm.
 /// - `args`: The arguments to pass to the executable.
 /// - `env`: The environment variables to use for the MicroVm.
 /// - `console_output`: The path to the file to write the console output to.
+/// - `agent_socket`: The path of the UNIX socket to reach the guest agent through.
 #[derive(Debug)]
 pub struct MicroVmConfigBuilder<R, E> {
     log_level: LogLevel,
@@ -42,11 +49,15 @@ pub struct MicroVmConfigBuilder<R, E> {
     ip: Option<Ipv4Addr>,
     subnet: Option<Ipv4Network>,
     rlimits: Vec<LinuxRlimit>,
+    seccomp_profile: Option<SeccompProfile>,
+    cap_add: Vec<Capability>,
+    cap_drop: Vec<Capability>,
     workdir_path: Option<Utf8UnixPathBuf>,
     exec_path: E,
     args: Vec<String>,
     env: Vec<EnvPair>,
     console_output: Option<Utf8UnixPathBuf>,
+    agent_socket: Option<Utf8UnixPathBuf>,
 }
 
 /// The builder for a MicroVm.
@@ -69,10 +80,14 @@ pub struct MicroVmConfigBuilder<R, E> {
 /// - `ip`: The IP address to use for the MicroVm.
 /// - `subnet`: The subnet to use for the MicroVm.
 /// - `rlimits`: The resource limits to use for the MicroVm.
+/// - `seccomp_profile`: The seccomp profile to apply to the MicroVm process.
+/// - `cap_add`: The capabilities to add to the MicroVm process.
+/// - `cap_drop`: The capabilities to drop from the MicroVm process.
 /// - `workdir_path`: The working directory to use for the MicroVm.
 /// - `args`: The arguments to pass to the executable.
 /// - `env`: The environment variables to use for the MicroVm.
 /// - `console_output`: The path to the file to write the console output to.
+/// - `agent_socket`: The path of the UNIX socket to reach the guest agent through.
 ///
 /// ## Examples
 ///
@@ -177,11 +192,15 @@ impl<R, M> MicroVmConfigBuilder<R, M> {
             ip: self.ip,
             subnet: self.subnet,
             rlimits: self.rlimits,
+            seccomp_profile: self.seccomp_profile,
+            cap_add: self.cap_add,
+            cap_drop: self.cap_drop,
             workdir_path: self.workdir_path,
             exec_path: self.exec_path,
             args: self.args,
             env: self.env,
             console_output: self.console_output,
+            agent_socket: self.agent_socket,
         }
     }
 
@@ -420,6 +439,121 @@ impl<R, M> MicroVmConfigBuilder<R, M> {
         self
     }
 
+    /// Sets the limit on the number of open file descriptors (`RLIMIT_NOFILE`) in the MicroVm.
+    ///
+    /// This replaces any `RLIMIT_NOFILE` limit already set, including one set with
+    /// [`rlimits`](Self::rlimits). A soft limit above the hard limit fails validation.
+    ///
+    /// ## Examples
+    ///
+    /// ```rust
+    /// use monocore::vm::MicroVmConfigBuilder;
+    ///
+    /// let config = MicroVmConfigBuilder::default().rlimit_nofile(256, 1024);
+    /// ```
+    pub fn rlimit_nofile(self, soft: u64, hard: u64) -> Self {
+        self.rlimit(LinuxRLimitResource::RLIMIT_NOFILE, soft, hard)
+    }
+
+    /// Sets the limit on the number of processes (`RLIMIT_NPROC`) in the MicroVm.
+    ///
+    /// This replaces any `RLIMIT_NPROC` limit already set, including one set with
+    /// [`rlimits`](Self::rlimits). A soft limit above the hard limit fails validation.
+    ///
+    /// ## Examples
+    ///
+    /// ```rust
+    /// use monocore::vm::MicroVmConfigBuilder;
+    ///
+    /// let config = MicroVmConfigBuilder::default().rlimit_nproc(64, 128);
+    /// ```
+    pub fn rlimit_nproc(self, soft: u64, hard: u64) -> Self {
+        self.rlimit(LinuxRLimitResource::RLIMIT_NPROC, soft, hard)
+    }
+
+    /// Sets the limit on the bytes of memory that can be locked (`RLIMIT_MEMLOCK`) in the MicroVm.
+    ///
+    /// This replaces any `RLIMIT_MEMLOCK` limit already set, including one set with
+    /// [`rlimits`](Self::rlimits). A soft limit above the hard limit fails validation.
+    ///
+    /// ## Examples
+    ///
+    /// ```rust
+    /// use monocore::vm::MicroVmConfigBuilder;
+    ///
+    /// let config = MicroVmConfigBuilder::default().rlimit_memlock(64 << 10, 64 << 10);
+    /// ```
+    pub fn rlimit_memlock(self, soft: u64, hard: u64) -> Self {
+        self.rlimit(LinuxRLimitResource::RLIMIT_MEMLOCK, soft, hard)
+    }
+
+    /// Sets the seccomp profile for the MicroVm process.
+    ///
+    /// The profile restricts the system calls the MicroVm process, and therefore the virtual
+    /// machine monitor running the guest, is allowed to make on the host. It hardens the monitor
+    /// and does not filter the guest's system calls, which are handled by the guest kernel.
+    ///
+    /// ## Examples
+    ///
+    /// ```rust
+    /// use monocore::vm::MicroVmConfigBuilder;
+    /// use monocore::config::SeccompProfile;
+    ///
+    /// let config = MicroVmConfigBuilder::default()
+    ///     .seccomp_profile(SeccompProfile::Default);
+    /// ```
+    ///
+    /// ## Notes
+    /// - Seccomp filtering is only supported on Linux
+    /// - A profile file must exist on the host
+    pub fn seccomp_profile(mut self, seccomp_profile: SeccompProfile) -> Self {
+        self.seccomp_profile = Some(seccomp_profile);
+        self
+    }
+
+    /// Sets the capabilities to add to the MicroVm process.
+    ///
+    /// Added capabilities are raised in the ambient set, so they must already be permitted for
+    /// the MicroVm process. Like dropped capabilities, they apply to the virtual machine monitor
+    /// on the host, not to processes in the guest.
+    ///
+    /// ## Examples
+    ///
+    /// ```rust
+    /// use monocore::vm::MicroVmConfigBuilder;
+    ///
+    /// # fn main() -> anyhow::Result<()> {
+    /// let config = MicroVmConfigBuilder::default()
+    ///     .cap_add(["NET_ADMIN".parse()?]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn cap_add(mut self, cap_add: impl IntoIterator<Item = Capability>) -> Self {
+        self.cap_add = cap_add.into_iter().collect();
+        self
+    }
+
+    /// Sets the capabilities to drop from the MicroVm process.
+    ///
+    /// Dropped capabilities are removed from the bounding set. Capabilities that are also in
+    /// `cap_add` are kept, so `ALL` can be dropped while adding back only what is needed.
+    ///
+    /// ## Examples
+    ///
+    /// ```rust
+    /// use monocore::vm::MicroVmConfigBuilder;
+    ///
+    /// # fn main() -> anyhow::Result<()> {
+    /// let config = MicroVmConfigBuilder::default()
+    ///     .cap_drop(["ALL".parse()?]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn cap_drop(mut self, cap_drop: impl IntoIterator<Item = Capability>) -> Self {
+        self.cap_drop = cap_drop.into_iter().collect();
+        self
+    }
+
     /// Sets the working directory for processes in the MicroVm.
     ///
     /// This directory will be the current working directory (cwd) for any processes
@@ -478,11 +612,15 @@ impl<R, M> MicroVmConfigBuilder<R, M> {
             ip: self.ip,
             subnet: self.subnet,
             rlimits: self.rlimits,
+            seccomp_profile: self.seccomp_profile,
+            cap_add: self.cap_add,
+            cap_drop: self.cap_drop,
             workdir_path: self.workdir_path,
             exec_path: exec_path.into(),
             args: self.args,
             env: self.env,
             console_output: self.console_output,
+            agent_socket: self.agent_socket,
         }
     }
 
@@ -573,6 +711,38 @@ impl<R, M> MicroVmConfigBuilder<R, M> {
         self.console_output = Some(console_output.into());
         self
     }
+
+    /// Sets the path of the UNIX socket the guest agent is reachable through.
+    ///
+    /// The MicroVm listens on this socket on the host and forwards each connection to the vsock
+    /// port the guest agent listens on, [`AGENT_VSOCK_PORT`][crate::vm::AGENT_VSOCK_PORT], so
+    /// commands can be executed in the running MicroVm.
+    ///
+    /// ## Examples
+    ///
+    /// ```rust
+    /// use monocore::vm::MicroVmConfigBuilder;
+    ///
+    /// let config = MicroVmConfigBuilder::default()
+    ///     .agent_socket("/tmp/agent.sock")
+    ///     .exec_path("/usr/local/bin/myapp");
+    /// ```
+    ///
+    /// ## Notes
+    /// - The socket is created by the MicroVm, so it must not exist yet
+    /// - The directory of the socket must be writable on the host system
+    pub fn agent_socket(mut self, agent_socket: impl Into<Utf8UnixPathBuf>) -> Self {
+        self.agent_socket = Some(agent_socket.into());
+        self
+    }
+
+    /// Sets the limit for `resource`, replacing any limit already set for it.
+    fn rlimit(mut self, resource: LinuxRLimitResource, soft: u64, hard: u64) -> Self {
+        self.rlimits
+            .retain(|rlimit| *rlimit.get_resource() != resource);
+        self.rlimits.push(LinuxRlimit::new(resource, soft, hard));
+        self
+    }
 }
 
 impl<R, M> MicroVmBuilder<R, M> {
@@ -875,6 +1045,97 @@ impl<R, M> MicroVmBuilder<R, M> {
         self
     }
 
+    /// Sets the limit on the number of open file descriptors (`RLIMIT_NOFILE`) in the MicroVm.
+    ///
+    /// ## Examples
+    ///
+    /// ```rust
+    /// use monocore::vm::MicroVmBuilder;
+    ///
+    /// MicroVmBuilder::default().rlimit_nofile(256, 1024);
+    /// ```
+    pub fn rlimit_nofile(mut self, soft: u64, hard: u64) -> Self {
+        self.inner = self.inner.rlimit_nofile(soft, hard);
+        self
+    }
+
+    /// Sets the limit on the number of processes (`RLIMIT_NPROC`) in the MicroVm.
+    ///
+    /// ## Examples
+    ///
+    /// ```rust
+    /// use monocore::vm::MicroVmBuilder;
+    ///
+    /// MicroVmBuilder::default().rlimit_nproc(64, 128);
+    /// ```
+    pub fn rlimit_nproc(mut self, soft: u64, hard: u64) -> Self {
+        self.inner = self.inner.rlimit_nproc(soft, hard);
+        self
+    }
+
+    /// Sets the limit on the bytes of memory that can be locked (`RLIMIT_MEMLOCK`) in the MicroVm.
+    ///
+    /// ## Examples
+    ///
+    /// ```rust
+    /// use monocore::vm::MicroVmBuilder;
+    ///
+    /// MicroVmBuilder::default().rlimit_memlock(64 << 10, 64 << 10);
+    /// ```
+    pub fn rlimit_memlock(mut self, soft: u64, hard: u64) -> Self {
+        self.inner = self.inner.rlimit_memlock(soft, hard);
+        self
+    }
+
+    /// Sets the seccomp profile for the MicroVm process.
+    ///
+    /// ## Examples
+    ///
+    /// ```rust
+    /// use monocore::vm::MicroVmBuilder;
+    /// use monocore::config::SeccompProfile;
+    ///
+    /// MicroVmBuilder::default().seccomp_profile(SeccompProfile::Default);
+    /// ```
+    pub fn seccomp_profile(mut self, seccomp_profile: SeccompProfile) -> Self {
+        self.inner = self.inner.seccomp_profile(seccomp_profile);
+        self
+    }
+
+    /// Sets the capabilities to add to the MicroVm process.
+    ///
+    /// ## Examples
+    ///
+    /// ```rust
+    /// use monocore::vm::MicroVmBuilder;
+    ///
+    /// # fn main() -> anyhow::Result<()> {
+    /// MicroVmBuilder::default().cap_add(["NET_ADMIN".parse()?]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn cap_add(mut self, cap_add: impl IntoIterator<Item = Capability>) -> Self {
+        self.inner = self.inner.cap_add(cap_add);
+        self
+    }
+
+    /// Sets the capabilities to drop from the MicroVm process.
+    ///
+    /// ## Examples
+    ///
+    /// ```rust
+    /// use monocore::vm::MicroVmBuilder;
+    ///
+    /// # fn main() -> anyhow::Result<()> {
+    /// MicroVmBuilder::default().cap_drop(["ALL".parse()?]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn cap_drop(mut self, cap_drop: impl IntoIterator<Item = Capability>) -> Self {
+        self.inner = self.inner.cap_drop(cap_drop);
+        self
+    }
+
     /// Sets the working directory path for the MicroVm.
     ///
     /// ## Examples
@@ -999,6 +1260,33 @@ impl<R, M> MicroVmBuilder<R, M> {
         self.inner = self.inner.console_output(console_output);
         self
     }
+
+    /// Sets the path of the UNIX socket the guest agent is reachable through.
+    ///
+    /// The MicroVm listens on this socket on the host and forwards each connection to the vsock
+    /// port the guest agent listens on, [`AGENT_VSOCK_PORT`][crate::vm::AGENT_VSOCK_PORT], so
+    /// commands can be executed in the running MicroVm.
+    ///
+    /// ## Examples
+    ///
+    /// ```rust
+    /// use monocore::vm::MicroVmBuilder;
+    ///
+    /// # fn main() -> anyhow::Result<()> {
+    /// let vm = MicroVmBuilder::default()
+    ///     .agent_socket("/tmp/agent.sock")
+    ///     .exec_path("/usr/local/bin/myapp");
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// ## Notes
+    /// - The socket is created by the MicroVm, so it must not exist yet
+    /// - The directory of the socket must be writable on the host system
+    pub fn agent_socket(mut self, agent_socket: impl Into<Utf8UnixPathBuf>) -> Self {
+        self.inner = self.inner.agent_socket(agent_socket);
+        self
+    }
 }
 
 impl MicroVmConfigBuilder<Rootfs, Utf8UnixPathBuf> {
@@ -1015,11 +1303,15 @@ impl MicroVmConfigBuilder<Rootfs, Utf8UnixPathBuf> {
             ip: self.ip,
             subnet: self.subnet,
             rlimits: self.rlimits,
+            seccomp_profile: self.seccomp_profile,
+            cap_add: self.cap_add,
+            cap_drop: self.cap_drop,
             workdir_path: self.workdir_path,
             exec_path: self.exec_path,
             args: self.args,
             env: self.env,
             console_output: self.console_output,
+            agent_socket: self.agent_socket,
         }
     }
 }
@@ -1068,11 +1360,15 @@ impl MicroVmBuilder<Rootfs, Utf8UnixPathBuf> {
             ip: self.inner.ip,
             subnet: self.inner.subnet,
             rlimits: self.inner.rlimits,
+            seccomp_profile: self.inner.seccomp_profile,
+            cap_add: self.inner.cap_add,
+            cap_drop: self.inner.cap_drop,
             workdir_path: self.inner.workdir_path,
             exec_path: self.inner.exec_path,
             args: self.inner.args,
             env: self.inner.env,
             console_output: self.inner.console_output,
+            agent_socket: self.inner.agent_socket,
         })
     }
 }
@@ -1094,11 +1390,15 @@ impl Default for MicroVmConfigBuilder<(), ()> {
             ip: None,
             subnet: None,
             rlimits: vec![],
+            seccomp_profile: None,
+            cap_add: vec![],
+            cap_drop: vec![],
             workdir_path: None,
             exec_path: (),
             args: vec![],
             env: vec![],
             console_output: None,
+            agent_socket: None,
         }
     }
 }
@@ -1119,6 +1419,8 @@ impl Default for MicroVmBuilder<(), ()> {
 mod tests {
     use std::path::PathBuf;
 
+    use crate::{InvalidMicroVMConfigError, MonocoreError};
+
     use super::*;
 
     #[test]
@@ -1135,11 +1437,15 @@ mod tests {
             .mapped_dirs(["/guest/mount:/host/mount".parse()?])
             .port_map(["8080:80".parse()?])
             .rlimits(["RLIMIT_NOFILE=1024:1024".parse()?])
+            .seccomp_profile(SeccompProfile::Default)
+            .cap_add(["NET_ADMIN".parse()?])
+            .cap_drop(["ALL".parse()?])
             .workdir_path(workdir_path)
             .exec_path(exec_path)
             .args(["arg1", "arg2"])
             .env(["KEY1=VALUE1".parse()?, "KEY2=VALUE2".parse()?])
-            .console_output("/tmp/console.log");
+            .console_output("/tmp/console.log")
+            .agent_socket("/tmp/agent.sock");
 
         assert_eq!(builder.inner.log_level, LogLevel::Debug);
         assert_eq!(builder.inner.rootfs, rootfs);
@@ -1151,6 +1457,9 @@ mod tests {
         );
         assert_eq!(builder.inner.port_map, ["8080:80".parse()?]);
         assert_eq!(builder.inner.rlimits, ["RLIMIT_NOFILE=1024:1024".parse()?]);
+        assert_eq!(builder.inner.seccomp_profile, Some(SeccompProfile::Default));
+        assert_eq!(builder.inner.cap_add, [Capability::Single(12)]);
+        assert_eq!(builder.inner.cap_drop, [Capability::All]);
         assert_eq!(
             builder.inner.workdir_path,
             Some(Utf8UnixPathBuf::from(workdir_path))
@@ -1165,6 +1474,10 @@ mod tests {
             builder.inner.console_output,
             Some(Utf8UnixPathBuf::from("/tmp/console.log"))
         );
+        assert_eq!(
+            builder.inner.agent_socket,
+            Some(Utf8UnixPathBuf::from("/tmp/agent.sock"))
+        );
         Ok(())
     }
 
@@ -1187,11 +1500,60 @@ mod tests {
         assert!(builder.inner.mapped_dirs.is_empty());
         assert!(builder.inner.port_map.is_empty());
         assert!(builder.inner.rlimits.is_empty());
+        assert_eq!(builder.inner.seccomp_profile, None);
+        assert!(builder.inner.cap_add.is_empty());
+        assert!(builder.inner.cap_drop.is_empty());
         assert_eq!(builder.inner.workdir_path, None);
         assert_eq!(builder.inner.exec_path, Utf8UnixPathBuf::from("/bin/echo"));
         assert!(builder.inner.args.is_empty());
         assert!(builder.inner.env.is_empty());
         assert_eq!(builder.inner.console_output, None);
+        assert_eq!(builder.inner.agent_socket, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_microvm_builder_rlimit_setters() -> anyhow::Result<()> {
+        let builder = MicroVmBuilder::default()
+            .rlimits([
+                "RLIMIT_NOFILE=1024:1024".parse()?,
+                "RLIMIT_CPU=10:20".parse()?,
+            ])
+            .rlimit_nofile(256, 512)
+            .rlimit_nproc(64, 128)
+            .rlimit_memlock(65536, 65536)
+            .rlimit_nproc(32, 128);
+
+        assert_eq!(
+            builder.inner.rlimits,
+            [
+                LinuxRlimit::new(LinuxRLimitResource::RLIMIT_CPU, 10, 20),
+                LinuxRlimit::new(LinuxRLimitResource::RLIMIT_NOFILE, 256, 512),
+                LinuxRlimit::new(LinuxRLimitResource::RLIMIT_MEMLOCK, 65536, 65536),
+                LinuxRlimit::new(LinuxRLimitResource::RLIMIT_NPROC, 32, 128),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_microvm_builder_rlimit_soft_exceeds_hard() -> anyhow::Result<()> {
+        let temp_dir = tempfile::TempDir::new()?;
+        let config = MicroVmConfigBuilder::default()
+            .rootfs(Rootfs::Native(temp_dir.path().to_path_buf()))
+            .exec_path("/bin/echo")
+            .rlimit_nofile(2048, 1024)
+            .build();
+
+        // `MicroVmBuilder::build` runs the same validation before creating the MicroVm
+        assert!(matches!(
+            config.validate(),
+            Err(MonocoreError::InvalidMicroVMConfig(
+                InvalidMicroVMConfigError::RLimitSoftExceedsHard(ref rlimit)
+            )) if rlimit == "RLIMIT_NOFILE=2048:1024"
+        ));
+
         Ok(())
     }
 }
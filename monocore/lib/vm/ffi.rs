@@ -349,6 +349,25 @@ extern "C" {
     #[allow(dead_code)]
     pub(crate) fn krun_add_vsock_port(ctx_id: u32, port: u32, c_filepath: *const c_char) -> i32;
 
+    /// Adds a port-path pairing for IPC between the guest and a process in the host, choosing
+    /// which side initiates connections.
+    ///
+    /// ## Arguments
+    ///
+    /// * `ctx_id` - The configuration context ID.
+    /// * `port` - The vsock port in the guest.
+    /// * `c_filepath` - The path of the UNIX socket in the host.
+    /// * `listen` - If true, libkrun creates and listens on the UNIX socket and forwards each
+    ///   connection to the port, which a process in the guest listens on. If false, the guest
+    ///   connects to the port and libkrun connects to the UNIX socket, which a process in the
+    ///   host listens on.
+    pub(crate) fn krun_add_vsock_port2(
+        ctx_id: u32,
+        port: u32,
+        c_filepath: *const c_char,
+        listen: bool,
+    ) -> i32;
+
     /// Gets the eventfd file descriptor to signal the guest to shut down orderly. This must be
     /// called before starting the MicroVm with "krun_start_enter". Only available in libkrun-efi.
     ///
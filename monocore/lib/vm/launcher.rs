@@ -0,0 +1,40 @@
+use crate::MonocoreResult;
+
+use super::{MicroVm, MicroVmConfig};
+
+//--------------------------------------------------------------------------------------------------
+// Traits
+//--------------------------------------------------------------------------------------------------
+
+/// Launches MicroVms from their configuration.
+///
+/// This separates preparing a [`MicroVmConfig`] from actually running it, so that code preparing
+/// MicroVms can be exercised without a hypervisor.
+pub trait MicroVmLauncher {
+    /// Launches a MicroVm with the given configuration and waits for it to exit.
+    ///
+    /// ## Returns
+    /// The exit status of the guest process.
+    fn launch(&self, config: MicroVmConfig) -> MonocoreResult<i32>;
+}
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// The default launcher, which runs MicroVms with libkrun.
+///
+/// The seccomp profile and capabilities in the configuration harden the current process, which
+/// runs the virtual machine monitor, right before the MicroVm starts.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct KrunLauncher;
+
+//--------------------------------------------------------------------------------------------------
+// Trait Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl MicroVmLauncher for KrunLauncher {
+    fn launch(&self, config: MicroVmConfig) -> MonocoreResult<i32> {
+        MicroVm::from_config(config)?.start()
+    }
+}
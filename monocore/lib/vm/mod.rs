@@ -2,7 +2,9 @@
 
 mod builder;
 mod ffi;
+mod launcher;
 mod rlimit;
+mod security;
 mod vm;
 
 //--------------------------------------------------------------------------------------------------
@@ -12,5 +14,7 @@ mod vm;
 pub use builder::*;
 #[allow(unused)]
 pub use ffi::*;
+pub use launcher::*;
 pub use rlimit::*;
+pub use security::*;
 pub use vm::*;
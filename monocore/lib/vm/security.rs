@@ -0,0 +1,447 @@
+use crate::{
+    config::{Capability, PathPair, SeccompProfile},
+    MonocoreError, MonocoreResult,
+};
+
+use super::MicroVmConfig;
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The name of the filter used from seccomp profile files.
+pub const SECCOMP_FILTER_NAME: &str = "microvm";
+
+/// The syscalls the default seccomp profile blocks for the VMM process.
+///
+/// None of these are needed by the VMM to run a MicroVm, and all of them can be used to affect the
+/// host as a whole.
+#[cfg(target_os = "linux")]
+const DEFAULT_BLOCKED_SYSCALLS: &[i64] = &[
+    libc::SYS_acct,
+    libc::SYS_add_key,
+    libc::SYS_adjtimex,
+    libc::SYS_bpf,
+    libc::SYS_clock_adjtime,
+    libc::SYS_clock_settime,
+    libc::SYS_delete_module,
+    libc::SYS_finit_module,
+    libc::SYS_init_module,
+    libc::SYS_kexec_file_load,
+    libc::SYS_kexec_load,
+    libc::SYS_keyctl,
+    libc::SYS_lookup_dcookie,
+    libc::SYS_perf_event_open,
+    libc::SYS_pivot_root,
+    libc::SYS_process_vm_readv,
+    libc::SYS_process_vm_writev,
+    libc::SYS_ptrace,
+    libc::SYS_quotactl,
+    libc::SYS_reboot,
+    libc::SYS_request_key,
+    libc::SYS_settimeofday,
+    libc::SYS_swapoff,
+    libc::SYS_swapon,
+    libc::SYS_syslog,
+];
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Hardens the current process, which becomes the virtual machine monitor (VMM) of the MicroVm,
+/// with the read-only mounts, seccomp profile and capabilities of `config`.
+///
+/// These settings confine the VMM on the host, limiting what a guest that escapes into the VMM
+/// can do. They do not restrict the guest itself: the guest's system calls are handled by its own
+/// kernel and never reach the host's seccomp filter, and capabilities inside the guest are those
+/// of the guest kernel.
+///
+/// This must be called before the MicroVm is started, so that the vCPU and device threads
+/// created by the hypervisor inherit the restrictions. Dropped capabilities are removed from the
+/// bounding set as well as the effective, permitted and inheritable sets, and this happens before
+/// the seccomp filter is installed, since the filter may block the system calls involved.
+///
+/// ## Errors
+/// Returns an error if:
+/// - The settings are not supported on this platform
+/// - The host directories of read-only mounts cannot be made read-only
+/// - The seccomp profile cannot be loaded or compiled
+/// - The process lacks the privileges to change its capabilities
+pub(crate) fn harden_vmm_process(config: &MicroVmConfig) -> MonocoreResult<()> {
+    // Mounting may need capabilities that are about to be dropped
+    apply_read_only_mounts(&config.mapped_dirs)?;
+    apply_capabilities(&config.cap_add, &config.cap_drop)?;
+
+    if let Some(profile) = &config.seccomp_profile {
+        apply_seccomp_profile(profile)?;
+    }
+
+    Ok(())
+}
+
+/// Makes the host directories of read-only mounts read-only for the current thread and the
+/// threads it spawns, so the virtio-fs devices serving them cannot write to them either.
+///
+/// The current thread moves to a private mount namespace, where every read-only directory is
+/// bind mounted read-only over itself. The rest of the host is unaffected.
+#[cfg(target_os = "linux")]
+fn apply_read_only_mounts(mapped_dirs: &[PathPair]) -> MonocoreResult<()> {
+    use nix::{
+        mount::{mount, MsFlags},
+        sched::{unshare, CloneFlags},
+    };
+
+    let read_only: Vec<_> = mapped_dirs
+        .iter()
+        .filter(|dir| dir.is_read_only())
+        .collect();
+    if read_only.is_empty() {
+        return Ok(());
+    }
+
+    unshare(CloneFlags::CLONE_NEWNS).map_err(|e| {
+        MonocoreError::SecuritySettingsError(format!(
+            "failed to create a mount namespace for read-only mounts (this requires CAP_SYS_ADMIN): {}",
+            e
+        ))
+    })?;
+
+    // Keep the bind mounts from propagating back to the host's mount namespace
+    mount(
+        None::<&str>,
+        "/",
+        None::<&str>,
+        MsFlags::MS_REC | MsFlags::MS_PRIVATE,
+        None::<&str>,
+    )
+    .map_err(|e| {
+        MonocoreError::SecuritySettingsError(format!("failed to make mounts private: {}", e))
+    })?;
+
+    for dir in read_only {
+        let path = dir.get_host().as_str();
+        mount(
+            Some(path),
+            path,
+            None::<&str>,
+            MsFlags::MS_BIND | MsFlags::MS_REC,
+            None::<&str>,
+        )
+        .and_then(|_| {
+            mount(
+                None::<&str>,
+                path,
+                None::<&str>,
+                MsFlags::MS_BIND | MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY,
+                None::<&str>,
+            )
+        })
+        .map_err(|e| {
+            MonocoreError::SecuritySettingsError(format!(
+                "failed to mount {} read-only: {}",
+                path, e
+            ))
+        })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn apply_read_only_mounts(mapped_dirs: &[PathPair]) -> MonocoreResult<()> {
+    if !mapped_dirs.iter().any(PathPair::is_read_only) {
+        return Ok(());
+    }
+
+    Err(MonocoreError::SecuritySettingsError(
+        "read-only mounts are only supported on Linux".to_string(),
+    ))
+}
+
+#[cfg(target_os = "linux")]
+fn apply_capabilities(cap_add: &[Capability], cap_drop: &[Capability]) -> MonocoreResult<()> {
+    use std::collections::BTreeSet;
+
+    use caps::CapSet;
+
+    let add: BTreeSet<u8> = cap_add.iter().flat_map(Capability::get_numbers).collect();
+    let drop: BTreeSet<u8> = cap_drop
+        .iter()
+        .flat_map(Capability::get_numbers)
+        .filter(|cap| !add.contains(cap))
+        .collect();
+
+    if !drop.is_empty() {
+        // Make sure dropped capabilities can't be regained by executing a setuid binary.
+        set_no_new_privs()?;
+    }
+
+    for cap in drop {
+        // SAFETY: `PR_CAPBSET_DROP` only reads its integer arguments.
+        let status = unsafe { libc::prctl(libc::PR_CAPBSET_DROP, cap as libc::c_ulong, 0, 0, 0) };
+        if status < 0 {
+            let error = std::io::Error::last_os_error();
+
+            // Capabilities unknown to the running kernel can't be held in the first place.
+            if error.raw_os_error() == Some(libc::EINVAL) {
+                continue;
+            }
+
+            return Err(MonocoreError::SecuritySettingsError(format!(
+                "failed to drop capability {} (dropping capabilities requires SETPCAP): {}",
+                Capability::Single(cap),
+                error
+            )));
+        }
+    }
+
+    // The bounding set only limits the capabilities that can be gained later, so the dropped
+    // capabilities are also cleared from the sets they are held in now. The effective set has to
+    // be a subset of the permitted set, so it is cleared first. Capabilities unknown to the `caps`
+    // crate are skipped, like capabilities unknown to the running kernel above.
+    let held = caps::all()
+        .into_iter()
+        .filter(|cap| drop.contains(&cap.index()));
+    for cap in held {
+        for set in [CapSet::Effective, CapSet::Inheritable, CapSet::Permitted] {
+            caps::drop(None, set, cap).map_err(|e| {
+                MonocoreError::SecuritySettingsError(format!(
+                    "failed to drop capability {} from the {:?} set: {}",
+                    Capability::Single(cap.index()),
+                    set,
+                    e
+                ))
+            })?;
+        }
+    }
+
+    for cap in add {
+        // SAFETY: `PR_CAP_AMBIENT_RAISE` only reads its integer arguments.
+        let status = unsafe {
+            libc::prctl(
+                libc::PR_CAP_AMBIENT,
+                libc::PR_CAP_AMBIENT_RAISE as libc::c_ulong,
+                cap as libc::c_ulong,
+                0,
+                0,
+            )
+        };
+        if status < 0 {
+            return Err(MonocoreError::SecuritySettingsError(format!(
+                "failed to add capability {} (it must be permitted and inheritable): {}",
+                Capability::Single(cap),
+                std::io::Error::last_os_error()
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn apply_capabilities(cap_add: &[Capability], cap_drop: &[Capability]) -> MonocoreResult<()> {
+    if cap_add.is_empty() && cap_drop.is_empty() {
+        return Ok(());
+    }
+
+    Err(MonocoreError::SecuritySettingsError(
+        "capabilities are only supported on Linux".to_string(),
+    ))
+}
+
+#[cfg(target_os = "linux")]
+fn apply_seccomp_profile(profile: &SeccompProfile) -> MonocoreResult<()> {
+    use seccompiler::{BpfProgram, SeccompAction, SeccompFilter, TargetArch};
+
+    let arch = TargetArch::try_from(std::env::consts::ARCH).map_err(|e| {
+        MonocoreError::SecuritySettingsError(format!("unsupported seccomp architecture: {}", e))
+    })?;
+
+    let program: BpfProgram = match profile {
+        SeccompProfile::Unconfined => return Ok(()),
+        SeccompProfile::Default => {
+            let rules = DEFAULT_BLOCKED_SYSCALLS
+                .iter()
+                .map(|syscall| (*syscall, vec![]))
+                .collect();
+
+            let filter = SeccompFilter::new(
+                rules,
+                SeccompAction::Allow,
+                SeccompAction::Errno(libc::EPERM as u32),
+                arch,
+            )
+            .map_err(|e| MonocoreError::SecuritySettingsError(e.to_string()))?;
+
+            filter.try_into().map_err(|e: seccompiler::BackendError| {
+                MonocoreError::SecuritySettingsError(e.to_string())
+            })?
+        }
+        SeccompProfile::Path(path) => {
+            let file = std::fs::File::open(path.as_str()).map_err(|e| {
+                MonocoreError::SecuritySettingsError(format!(
+                    "failed to open seccomp profile {}: {}",
+                    path, e
+                ))
+            })?;
+
+            let mut filters = seccompiler::compile_from_json(file, arch).map_err(|e| {
+                MonocoreError::SecuritySettingsError(format!(
+                    "invalid seccomp profile {}: {}",
+                    path, e
+                ))
+            })?;
+
+            filters.remove(SECCOMP_FILTER_NAME).ok_or_else(|| {
+                MonocoreError::SecuritySettingsError(format!(
+                    "seccomp profile {} does not define a `{}` filter",
+                    path, SECCOMP_FILTER_NAME
+                ))
+            })?
+        }
+    };
+
+    seccompiler::apply_filter(&program)
+        .map_err(|e| MonocoreError::SecuritySettingsError(e.to_string()))
+}
+
+#[cfg(not(target_os = "linux"))]
+fn apply_seccomp_profile(profile: &SeccompProfile) -> MonocoreResult<()> {
+    match profile {
+        SeccompProfile::Unconfined => Ok(()),
+        _ => Err(MonocoreError::SecuritySettingsError(
+            "seccomp profiles are only supported on Linux".to_string(),
+        )),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn set_no_new_privs() -> MonocoreResult<()> {
+    // SAFETY: `PR_SET_NO_NEW_PRIVS` only reads its integer arguments.
+    let status = unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) };
+    if status < 0 {
+        return Err(MonocoreError::SecuritySettingsError(format!(
+            "failed to set no_new_privs: {}",
+            std::io::Error::last_os_error()
+        )));
+    }
+
+    Ok(())
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, io::ErrorKind, thread};
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_apply_read_only_mounts() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let read_only_dir = temp_dir.path().join("ro");
+        let read_write_dir = temp_dir.path().join("rw");
+        fs::create_dir(&read_only_dir)?;
+        fs::create_dir(&read_write_dir)?;
+
+        let mapped_dirs = vec![
+            format!("{}:/ro:ro", read_only_dir.display()).parse::<PathPair>()?,
+            format!("{}:/rw", read_write_dir.display()).parse::<PathPair>()?,
+        ];
+
+        // The mount namespace is per thread, so the test thread's view of the host is unaffected
+        let (ro_dir, rw_dir) = (read_only_dir.clone(), read_write_dir.clone());
+        let applied = thread::spawn(move || -> anyhow::Result<bool> {
+            if let Err(e) = apply_read_only_mounts(&mapped_dirs) {
+                // Creating a mount namespace needs CAP_SYS_ADMIN
+                tracing::warn!("skipping read-only mount checks: {}", e);
+                return Ok(false);
+            }
+
+            let error = fs::write(ro_dir.join("file"), b"data").unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::ReadOnlyFilesystem);
+            fs::write(rw_dir.join("file"), b"data")?;
+            Ok(true)
+        })
+        .join()
+        .unwrap()?;
+
+        if applied {
+            fs::write(read_only_dir.join("file"), b"data")?;
+            assert!(read_write_dir.join("file").exists());
+        }
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_apply_capabilities_clears_dropped_capabilities() -> anyhow::Result<()> {
+        const CAP_NET_RAW: u8 = 13;
+
+        // Capabilities are per thread, so the test thread is unaffected
+        let effective = thread::spawn(|| -> anyhow::Result<Option<u64>> {
+            if let Err(e) = apply_capabilities(&[], &["NET_RAW".parse()?]) {
+                // Dropping from the bounding set needs CAP_SETPCAP
+                tracing::warn!("skipping capability checks: {}", e);
+                return Ok(None);
+            }
+
+            // `/proc/self` is the main thread, which keeps its capabilities
+            let status = fs::read_to_string("/proc/thread-self/status")?;
+            let effective = status
+                .lines()
+                .find_map(|line| line.strip_prefix("CapEff:"))
+                .expect("status lists the effective capabilities");
+            Ok(Some(u64::from_str_radix(effective.trim(), 16)?))
+        })
+        .join()
+        .unwrap()?;
+
+        if let Some(effective) = effective {
+            assert_eq!(effective & (1 << CAP_NET_RAW), 0);
+        }
+
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_apply_seccomp_profile_blocks_vmm_syscalls() -> anyhow::Result<()> {
+        // Seccomp filters are per thread, so the test thread is unaffected
+        let blocked = thread::spawn(|| -> anyhow::Result<bool> {
+            apply_seccomp_profile(&SeccompProfile::Default)?;
+
+            // Reading our own memory is otherwise always allowed
+            let source = [1u8; 8];
+            let mut target = [0u8; 8];
+            let local = libc::iovec {
+                iov_base: target.as_mut_ptr().cast(),
+                iov_len: target.len(),
+            };
+            let remote = libc::iovec {
+                iov_base: source.as_ptr() as *mut _,
+                iov_len: source.len(),
+            };
+
+            // SAFETY: Both iovecs point to live buffers of the given lengths.
+            let read = unsafe { libc::process_vm_readv(libc::getpid(), &local, 1, &remote, 1, 0) };
+            let error = std::io::Error::last_os_error();
+
+            Ok(read < 0 && error.raw_os_error() == Some(libc::EPERM))
+        })
+        .join()
+        .unwrap()?;
+
+        assert!(blocked);
+
+        Ok(())
+    }
+}
@@ -6,11 +6,11 @@ use monoutils::SupportedPathType;
 use typed_path::Utf8UnixPathBuf;
 
 use crate::{
-    config::{EnvPair, NetworkScope, PathPair, PortPair},
+    config::{Capability, EnvPair, NetworkScope, PathPair, PortPair, SeccompProfile},
     utils, InvalidMicroVMConfigError, MonocoreError, MonocoreResult,
 };
 
-use super::{ffi, LinuxRlimit, MicroVmBuilder, MicroVmConfigBuilder};
+use super::{ffi, security, LinuxRlimit, MicroVmBuilder, MicroVmConfigBuilder};
 
 //--------------------------------------------------------------------------------------------------
 // Constants
@@ -19,6 +19,9 @@ use super::{ffi, LinuxRlimit, MicroVmBuilder, MicroVmConfigBuilder};
 /// The prefix used for virtio-fs tags when mounting shared directories
 pub const VIRTIOFS_TAG_PREFIX: &str = "virtiofs";
 
+/// The vsock port the guest agent listens on
+pub const AGENT_VSOCK_PORT: u32 = 1024;
+
 //--------------------------------------------------------------------------------------------------
 // Types
 //--------------------------------------------------------------------------------------------------
@@ -145,6 +148,15 @@ pub struct MicroVmConfig {
     /// The resource limits to use for the MicroVm.
     pub rlimits: Vec<LinuxRlimit>,
 
+    /// The seccomp profile to apply to the MicroVm process.
+    pub seccomp_profile: Option<SeccompProfile>,
+
+    /// The capabilities to add to the MicroVm process.
+    pub cap_add: Vec<Capability>,
+
+    /// The capabilities to drop from the MicroVm process.
+    pub cap_drop: Vec<Capability>,
+
     /// The working directory path to use for the MicroVm.
     pub workdir_path: Option<Utf8UnixPathBuf>,
 
@@ -159,6 +171,9 @@ pub struct MicroVmConfig {
 
     /// The console output path to use for the MicroVm.
     pub console_output: Option<Utf8UnixPathBuf>,
+
+    /// The path of the UNIX socket the guest agent is reachable through.
+    pub agent_socket: Option<Utf8UnixPathBuf>,
 }
 
 /// The log level to use for the MicroVm.
@@ -264,8 +279,17 @@ impl MicroVm {
     /// - This function takes control of stdin/stdout
     /// - The MicroVm is automatically cleaned up when this returns
     /// - A non-zero status indicates the guest process failed
+    /// - The configured seccomp profile and capabilities harden the current process, which runs
+    ///   the virtual machine monitor, before the MicroVm starts and cannot be lifted afterwards.
+    ///   They confine the monitor on the host, not the guest
+    /// - The current thread moves to a private mount namespace in which the host directories of
+    ///   read-only mounts are read-only
     pub fn start(&self) -> MonocoreResult<i32> {
         let ctx_id = self.ctx_id;
+
+        // Harden the VMM before any vCPU threads are spawned so they inherit the restrictions.
+        security::harden_vmm_process(&self.config)?;
+
         let status = unsafe { ffi::krun_start_enter(ctx_id) };
         if status < 0 {
             tracing::error!("failed to start microvm: {}", status);
@@ -294,6 +318,7 @@ impl MicroVm {
     /// - Executable and arguments
     /// - Environment variables
     /// - Console output
+    /// - Guest agent socket
     /// - Network settings
     ///
     /// ## Arguments
@@ -342,7 +367,9 @@ impl MicroVm {
 
         tracing::debug!("Applying config: {:#?}", config);
 
-        // Add mapped directories using virtio-fs
+        // Add mapped directories using virtio-fs. virtio-fs shares are always writable, so
+        // read-only directories are mounted read-only in the guest, and made read-only on the
+        // host when the MicroVm starts.
         let mapped_dirs = &config.mapped_dirs;
         for (idx, dir) in mapped_dirs.iter().enumerate() {
             let tag = CString::new(format!("{}_{}", VIRTIOFS_TAG_PREFIX, idx)).unwrap();
@@ -353,11 +380,12 @@ impl MicroVm {
             }
         }
 
-        // Set port map
+        // Set port map. TSI intercepts both TCP and UDP sockets in the guest, and its port map
+        // applies to both, so the protocol is left out.
         let c_port_map: Vec<_> = config
             .port_map
             .iter()
-            .map(|p| CString::new(p.to_string()).unwrap())
+            .map(|p| CString::new(format!("{}:{}", p.get_host(), p.get_guest())).unwrap())
             .collect();
         let c_port_map_ptrs = utils::to_null_terminated_c_array(&c_port_map);
 
@@ -435,6 +463,21 @@ impl MicroVm {
                 assert!(status >= 0, "Failed to set console output: {}", status);
             }
         }
+
+        // Listen on a UNIX socket on the host and forward its connections to the vsock port the
+        // guest agent listens on
+        if let Some(agent_socket) = &config.agent_socket {
+            let c_agent_socket = CString::new(agent_socket.to_string().as_bytes()).unwrap();
+            unsafe {
+                let status = ffi::krun_add_vsock_port2(
+                    ctx_id,
+                    AGENT_VSOCK_PORT,
+                    c_agent_socket.as_ptr(),
+                    true,
+                );
+                assert!(status >= 0, "Failed to add agent vsock port: {}", status);
+            }
+        }
     }
 }
 
@@ -523,6 +566,9 @@ impl MicroVmConfig {
     /// - Ensures RAM allocation is non-zero
     /// - Validates executable path and arguments contain only printable ASCII characters
     /// - Validates guest paths don't overlap or conflict with each other
+    /// - Verifies the seccomp profile file exists, if one is used
+    /// - Ensures no resource limit has a soft limit above its hard limit
+    /// - Ensures every port pair is TCP, the only protocol the network forwards
     ///
     /// ## Returns
     /// - `Ok(())` if the configuration is valid
@@ -603,6 +649,43 @@ impl MicroVmConfig {
         // Validate guest paths are not subsets of each other
         Self::validate_guest_paths(&self.mapped_dirs)?;
 
+        // Check that the seccomp profile file exists
+        if let Some(SeccompProfile::Path(path)) = &self.seccomp_profile {
+            if !PathBuf::from(path.as_str()).exists() {
+                return Err(MonocoreError::InvalidMicroVMConfig(
+                    InvalidMicroVMConfigError::SeccompProfileDoesNotExist(path.to_string()),
+                ));
+            }
+        }
+
+        Self::validate_rlimits(&self.rlimits)?;
+
+        Ok(())
+    }
+
+    /// Validates that no resource limit has a soft limit greater than its hard limit.
+    ///
+    /// The kernel rejects such a limit when it is applied, so it is caught before the MicroVm
+    /// starts.
+    ///
+    /// ## Arguments
+    /// * `rlimits` - The resource limits to validate
+    ///
+    /// ## Returns
+    /// - Ok(()) if every soft limit is at most its hard limit
+    /// - Err with the first offending limit otherwise
+    fn validate_rlimits(rlimits: &[LinuxRlimit]) -> MonocoreResult<()> {
+        if let Some(rlimit) = rlimits.iter().find(|r| r.get_soft() > r.get_hard()) {
+            return Err(MonocoreError::InvalidMicroVMConfig(
+                InvalidMicroVMConfigError::RLimitSoftExceedsHard(format!(
+                    "{}={}:{}",
+                    rlimit.get_resource(),
+                    rlimit.get_soft(),
+                    rlimit.get_hard()
+                )),
+            ));
+        }
+
         Ok(())
     }
 
@@ -748,6 +831,23 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_microvm_config_validation_failure_seccomp_profile() {
+        let temp_dir = TempDir::new().unwrap();
+        let config = MicroVmConfig::builder()
+            .rootfs(Rootfs::Native(temp_dir.path().to_path_buf()))
+            .exec_path("/bin/echo")
+            .seccomp_profile(SeccompProfile::Path("/non/existent/seccomp.json".into()))
+            .build();
+
+        assert!(matches!(
+            config.validate(),
+            Err(MonocoreError::InvalidMicroVMConfig(
+                InvalidMicroVMConfigError::SeccompProfileDoesNotExist(_)
+            ))
+        ));
+    }
+
     #[test]
     fn test_validate_command_line_valid_strings() {
         // Test basic ASCII strings
@@ -914,4 +1014,30 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_validate_rlimits() -> anyhow::Result<()> {
+        // Soft limits up to and including the hard limit are valid
+        assert!(MicroVmConfig::validate_rlimits(&[]).is_ok());
+        assert!(MicroVmConfig::validate_rlimits(&[
+            "RLIMIT_NOFILE=1024:1024".parse()?,
+            "RLIMIT_NPROC=64:128".parse()?,
+            "RLIMIT_MEMLOCK=0:0".parse()?,
+        ])
+        .is_ok());
+
+        // A soft limit above the hard limit is rejected
+        let result = MicroVmConfig::validate_rlimits(&[
+            "RLIMIT_NOFILE=1024:1024".parse()?,
+            "RLIMIT_MEMLOCK=65537:65536".parse()?,
+        ]);
+        assert!(matches!(
+            result,
+            Err(MonocoreError::InvalidMicroVMConfig(
+                InvalidMicroVMConfigError::RLimitSoftExceedsHard(ref rlimit)
+            )) if rlimit == "RLIMIT_MEMLOCK=65537:65536"
+        ));
+
+        Ok(())
+    }
 }
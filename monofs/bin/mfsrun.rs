@@ -15,7 +15,8 @@
 //! mfsrun nfsserver \
 //!     --host=127.0.0.1 \
 //!     --port=2049 \
-//!     --store-dir=/path/to/store
+//!     --store-dir=/path/to/store \
+//!     --fileid-log=/path/to/fileids.log
 //! ```
 //!
 //! #### NFS Server Parameters
@@ -23,6 +24,7 @@
 //! - `--host`: The address to bind to (default: "127.0.0.1")
 //! - `--port`: The port to listen on (default: 2049)
 //! - `--store-dir`: Directory path where the monofs store will be located
+//! - `--fileid-log`: Optional log the server's fileids are persisted in across restarts
 //!
 //! ### Supervisor Mode
 //!
@@ -45,6 +47,7 @@
 //! - `--host`: The address for the NFS server to bind to (default: "127.0.0.1")
 //! - `--port`: The port for the NFS server to listen on (default: 2049)
 //! - `--store-dir`: Directory path where the monofs store will be located
+//! - `--fileid-log`: Optional log the NFS server's fileids are persisted in across restarts
 //! - `--db-path`: Path to the metrics database file
 //!
 //! ## Examples
@@ -100,9 +103,14 @@ async fn main() -> Result<()> {
             host,
             port,
             store_dir,
+            fileid_log,
         } => {
             // Create and start NFS server
-            let server = MonofsServer::new(store_dir, host, port);
+            let mut server = MonofsServer::new(store_dir, host, port);
+            if let Some(fileid_log) = fileid_log {
+                server = server.with_fileid_log(fileid_log);
+            }
+
             tracing::info!(
                 "Starting NFS server on {}:{}",
                 server.get_host(),
@@ -118,8 +126,10 @@ async fn main() -> Result<()> {
             host,
             port,
             store_dir,
+            fileid_log,
             fs_db_path,
             mount_dir,
+            log_format,
         } => {
             // Get current executable path
             let child_exe = env::current_exe()?;
@@ -135,15 +145,19 @@ async fn main() -> Result<()> {
                 mount_dir,
                 log_dir.clone(),
             )
-            .await?;
+            .await?
+            .with_log_format(log_format);
 
             // Compose child arguments
-            let child_args = vec![
+            let mut child_args = vec![
                 "nfsserver".to_string(),
                 format!("--host={}", host),
                 format!("--port={}", port),
                 format!("--store-dir={}", store_dir.display()),
             ];
+            if let Some(fileid_log) = fileid_log {
+                child_args.push(format!("--fileid-log={}", fileid_log.display()));
+            }
 
             // Compose child environment variables
             let child_envs = vec![("RUST_LOG", "info")];
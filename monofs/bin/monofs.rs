@@ -2,6 +2,7 @@ use clap::{CommandFactory, Parser};
 use monofs::{
     cli::{MonofsArgs, MonofsSubcommand},
     management,
+    store::FlatFsStore,
 };
 
 //--------------------------------------------------------------------------------------------------
@@ -26,6 +27,33 @@ async fn main() -> anyhow::Result<()> {
             management::detach_mfs(mount_dir, force).await?;
             tracing::info!("successfully detached monofs");
         }
+        Some(MonofsSubcommand::Fsck { store, root }) => {
+            tracing::info!("checking filesystem store...");
+            let report = management::fsck_store(store, root).await?;
+            for problem in report.get_problems() {
+                eprintln!("{problem}");
+            }
+
+            if !report.is_ok() {
+                tracing::error!("found {} problems", report.get_problems().len());
+                std::process::exit(1);
+            }
+
+            tracing::info!("no problems found");
+        }
+        Some(MonofsSubcommand::Ingest { store, src }) => {
+            tracing::info!("ingesting {}...", src.display());
+            let store = FlatFsStore::new(store);
+            let root = management::ingest(&store, &src).await?;
+            println!("{root}");
+            tracing::info!("successfully ingested {} as {}", src.display(), root);
+        }
+        Some(MonofsSubcommand::Materialize { store, root, dest }) => {
+            tracing::info!("materializing {}...", root);
+            let store = FlatFsStore::new(store);
+            management::materialize(&store, &root, &dest).await?;
+            tracing::info!("successfully materialized {} at {}", root, dest.display());
+        }
         Some(_) => (), // TODO: implement other subcommands
         None => {
             MonofsArgs::command().print_help()?;
@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
+use monoutils::LogFormat;
 
 use crate::{
     cli::styles,
@@ -36,6 +37,10 @@ pub enum MfsRuntimeSubcommand {
         /// The directory to store the filesystem data
         #[arg(long)]
         store_dir: PathBuf,
+
+        /// The log to persist the server's fileids in, so they survive restarts
+        #[arg(long)]
+        fileid_log: Option<PathBuf>,
     },
     /// Run as supervisor
     Supervisor {
@@ -59,6 +64,10 @@ pub enum MfsRuntimeSubcommand {
         #[arg(long)]
         store_dir: PathBuf,
 
+        /// The log to persist the server's fileids in, so they survive restarts
+        #[arg(long)]
+        fileid_log: Option<PathBuf>,
+
         /// Path to the filesystem metrics and metadata database file
         #[arg(long)]
         fs_db_path: PathBuf,
@@ -66,5 +75,9 @@ pub enum MfsRuntimeSubcommand {
         /// Directory where the filesystem is mounted
         #[arg(long)]
         mount_dir: PathBuf,
+
+        /// Format of the child's log (`plain` or `json`)
+        #[arg(long, default_value_t = LogFormat::Plain)]
+        log_format: LogFormat,
     },
 }
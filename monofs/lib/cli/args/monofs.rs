@@ -2,6 +2,7 @@ use std::path::PathBuf;
 
 use crate::cli::styles;
 use clap::Parser;
+use ipldstore::ipld::cid::Cid;
 use typed_path::Utf8UnixPathBuf;
 
 //-------------------------------------------------------------------------------------------------
@@ -120,6 +121,40 @@ pub enum MonofsSubcommand {
         force: bool,
     },
 
+    /// Check the consistency of a filesystem store
+    #[command(name = "fsck")]
+    Fsck {
+        /// Directory of the block store to check
+        store: PathBuf,
+
+        /// CID of the root entity to check from. If not given, only the block hashes are checked
+        #[arg(short = 'r', long)]
+        root: Option<Cid>,
+    },
+
+    /// Build a tree from a directory on the host and print the CID of its root
+    #[command(name = "ingest")]
+    Ingest {
+        /// Directory of the block store to build the tree in
+        store: PathBuf,
+
+        /// Path to build the tree from
+        src: PathBuf,
+    },
+
+    /// Write the tree rooted at a CID out to a directory on the host
+    #[command(name = "materialize")]
+    Materialize {
+        /// Directory of the block store the tree is in
+        store: PathBuf,
+
+        /// CID of the root entity of the tree
+        root: Cid,
+
+        /// Path to write the tree to. It must not exist, or be an empty directory
+        dest: PathBuf,
+    },
+
     /// Show version information
     #[command(name = "version")]
     Version,
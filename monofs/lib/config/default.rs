@@ -13,6 +13,15 @@ pub const DEFAULT_HOST: &str = "127.0.0.1";
 /// The default NFS port number to use.
 pub const DEFAULT_NFS_PORT: u32 = 2049;
 
+/// The default number of blocks a block existence filter is sized for.
+pub const DEFAULT_BLOOM_FILTER_CAPACITY: usize = 64 * 1024;
+
+/// The default false positive rate of a block existence filter at capacity.
+pub const DEFAULT_BLOOM_FILTER_FALSE_POSITIVE_RATE: f64 = 0.01;
+
+/// The default number of locks that writes to a flat filesystem store are spread over.
+pub const DEFAULT_BLOCK_LOCK_STRIPES: usize = 64;
+
 /// The default path for the mfsrun binary.
 pub static DEFAULT_MFSRUN_EXE_PATH: LazyLock<PathBuf> = LazyLock::new(|| {
     let current_exe = std::env::current_exe().unwrap();
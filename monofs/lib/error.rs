@@ -33,6 +33,14 @@ pub enum FsError {
     #[error("Not a directory: {0:?}")]
     NotADirectory(String),
 
+    /// Is a directory.
+    #[error("Is a directory: {0:?}")]
+    IsADirectory(String),
+
+    /// Directory is not empty.
+    #[error("Directory is not empty: {0:?}")]
+    DirectoryNotEmpty(String),
+
     /// Not a symbolic CID link.
     #[error("Not a symbolic CID link: {0:?}")]
     NotASymCidLink(String),
@@ -120,10 +128,18 @@ pub enum FsError {
     #[error("Broken symbolic CID link: {0}")]
     BrokenSymCidLink(Cid),
 
+    /// An entry differs between two directories being merged.
+    #[error("Merge conflict at path: {0}")]
+    MergeConflict(String),
+
     /// Invalid operation.
     #[error("Invalid operation: {0}")]
     InvalidOperation(String),
 
+    /// Invalid chunker configuration.
+    #[error("Invalid chunker config: {0}")]
+    InvalidChunkerConfig(String),
+
     /// IO error.
     #[error("IO error: {0}")]
     IoError(#[from] io::Error),
@@ -136,6 +152,14 @@ pub enum FsError {
     #[error("Mount point is not empty: {0}")]
     MountPointNotEmpty(String),
 
+    /// Mount point does not exist
+    #[error("Mount point does not exist: {0}")]
+    MountPointNotFound(String),
+
+    /// Mount point already has a filesystem mounted on it
+    #[error("Mount point is already mounted: {0}")]
+    AlreadyMounted(String),
+
     /// Mount operation failed
     #[error("Mount operation failed: {0}")]
     MountFailed(String),
@@ -1,4 +1,6 @@
+mod diff;
 mod find;
+mod merge;
 mod ops;
 mod segment;
 
@@ -10,6 +12,7 @@ use std::{
 };
 
 use chrono::Utc;
+use futures::future::BoxFuture;
 use ipldstore::{ipld::cid::Cid, IpldReferences, IpldStore, Storable, StoreError, StoreResult};
 use serde::{Deserialize, Serialize};
 
@@ -881,13 +884,84 @@ where
         })
     }
 
+    /// Returns the total size in bytes of all the files under the directory, recursively.
+    ///
+    /// Directories cache their total size in their metadata when they are stored, so subtrees
+    /// that haven't changed since they were loaded are not walked again. The cache of a directory
+    /// is ignored as soon as it or any of its entries is modified.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use monofs::filesystem::{Dir, Entity};
+    /// use ipldstore::MemoryStore;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let mut dir = Dir::new(MemoryStore::default());
+    /// let Entity::File(file) = dir.find_or_create("foo/bar.txt", true).await? else {
+    ///     unreachable!();
+    /// };
+    /// file.write_at(0, b"Hello, World!").await?;
+    ///
+    /// assert_eq!(dir.total_size().await?, 13);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn total_size(&self) -> BoxFuture<'_, FsResult<u64>>
+    where
+        S: Send + Sync,
+    {
+        Box::pin(async move {
+            if let Some(size) = self.get_cached_size() {
+                return Ok(size);
+            }
+
+            let mut size = 0;
+            for link in self.get_entry_links() {
+                size += match link.resolve_entity(self.inner.store.clone()).await? {
+                    Entity::File(file) => file.get_size().await?,
+                    Entity::Dir(dir) => dir.total_size().await?,
+                    _ => 0,
+                };
+            }
+
+            Ok(size)
+        })
+    }
+
+    /// Returns the cached total size if the directory is unchanged since it was loaded.
+    ///
+    /// Modifying an entry turns its link into a decoded one, so the cache is only trusted if all
+    /// links are still encoded.
+    fn get_cached_size(&self) -> Option<u64> {
+        let unchanged = self
+            .inner
+            .entries
+            .values()
+            .all(|entry| matches!(entry.link, Link::Encoded { .. }));
+
+        if unchanged {
+            *self.inner.metadata.get_size()
+        } else {
+            None
+        }
+    }
+
     /// Returns a serializable representation of the directory.
     pub async fn get_serializable(&self) -> FsResult<DirSerializable>
     where
         S: Send + Sync,
     {
         let mut entries = BTreeMap::new();
+        let mut files = Vec::new();
         for (k, v) in self.inner.entries.iter() {
+            // Modified files are stored together below
+            if let Link::Decoded(Entity::File(file)) = &v.link {
+                files.push((k.to_string(), v.deleted, file.get_serializable().await?));
+                continue;
+            }
+
             entries.insert(
                 k.to_string(),
                 (
@@ -897,7 +971,18 @@ where
             );
         }
 
-        let metadata = self.get_metadata().get_serializable().await?;
+        // Store the modified files all at once, so a checkpoint doesn't pay for each one
+        let (names, files): (Vec<_>, Vec<_>) = files
+            .into_iter()
+            .map(|(name, deleted, file)| ((name, deleted), file))
+            .unzip();
+        let cids = self.inner.store.put_nodes(&files).await?;
+        for ((name, deleted), cid) in names.into_iter().zip(cids) {
+            entries.insert(name, (deleted, cid));
+        }
+
+        let mut metadata = self.get_metadata().get_serializable().await?;
+        metadata.set_size(Some(self.total_size().await?));
 
         Ok(DirSerializable {
             r#type: DIR_TYPE_TAG.to_string(),
@@ -977,6 +1062,7 @@ impl IpldReferences for DirSerializable {
 mod tests {
     use anyhow::Ok;
     use ipldstore::MemoryStore;
+    use tokio::io::AsyncReadExt;
 
     use crate::filesystem::SyncType;
 
@@ -1176,6 +1262,51 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_dir_checkpoint_stores_modified_files() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+        let mut dir = Dir::new(store.clone());
+        let mut subdir = Dir::new(store.clone());
+        subdir
+            .put_adapted_file(
+                "nested.txt",
+                File::with_content(store.clone(), b"nested".as_slice()).await?,
+            )
+            .await?;
+        dir.put_adapted_dir("subdir", subdir).await?;
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            let file = File::with_content(store.clone(), name.as_bytes()).await?;
+            dir.put_adapted_file(name, file).await?;
+        }
+
+        // Each modified file is stored under the same CID as storing it alone would give
+        let b_cid = dir.get_file("b.txt").await?.unwrap().store().await?;
+        let cid = dir.checkpoint().await?;
+        let loaded = Dir::load(&cid, store.clone()).await?;
+        assert_eq!(
+            loaded.get_entry("b.txt")?.unwrap().resolve_cid().await?,
+            b_cid
+        );
+
+        for (path, expected) in [
+            ("a.txt", &b"a.txt"[..]),
+            ("c.txt", b"c.txt"),
+            ("subdir/nested.txt", b"nested"),
+        ] {
+            let Some(Entity::File(file)) = loaded.find(path).await? else {
+                panic!("{path} is not a file");
+            };
+            let mut content = Vec::new();
+            file.get_input_stream()
+                .await?
+                .read_to_end(&mut content)
+                .await?;
+            assert_eq!(content, expected);
+        }
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_put_adapted_entry_new_entry() -> anyhow::Result<()> {
         let store = MemoryStore::default();
@@ -1284,13 +1415,58 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_dir_total_size() -> anyhow::Result<()> {
+        let mut root = Dir::new(MemoryStore::default());
+        let files = [
+            ("a.txt", b"hello".as_slice()),
+            ("docs/b.txt", b"hello, world"),
+            ("docs/nested/c.txt", b"!"),
+            ("empty/d.txt", b""),
+        ];
+        for (path, content) in files {
+            let Entity::File(file) = root.find_or_create(path, true).await? else {
+                unreachable!();
+            };
+            file.write_at(0, content).await?;
+        }
+
+        let expected = files.iter().map(|(_, c)| c.len() as u64).sum::<u64>();
+        assert_eq!(root.total_size().await?, expected);
+
+        // The size is cached on checkpoint.
+        root.checkpoint().await?;
+        assert_eq!(root.get_metadata().get_size(), &Some(expected));
+        assert_eq!(root.total_size().await?, expected);
+
+        // Changing a nested file invalidates the cache of every directory above it.
+        let Some(Entity::File(file)) = root.find_mut("docs/nested/c.txt").await? else {
+            unreachable!();
+        };
+        file.write_at(1, b"!!!").await?;
+        assert_eq!(root.total_size().await?, expected + 3);
+
+        root.remove("a.txt").await?;
+        assert_eq!(root.total_size().await?, expected - 2);
+
+        root.checkpoint().await?;
+        assert_eq!(root.get_metadata().get_size(), &Some(expected - 2));
+        assert_eq!(root.total_size().await?, expected - 2);
+        let docs = root.get_dir("docs").await?.unwrap();
+        assert_eq!(docs.get_metadata().get_size(), &Some(16));
+
+        Ok(())
+    }
 }
 
 //--------------------------------------------------------------------------------------------------
 // Exports
 //--------------------------------------------------------------------------------------------------
 
+pub use diff::*;
 pub use find::*;
+pub use merge::*;
 pub use segment::*;
 
 use super::SymPathLink;
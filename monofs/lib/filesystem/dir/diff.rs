@@ -0,0 +1,275 @@
+use std::collections::BTreeSet;
+
+use getset::Getters;
+use ipldstore::{ipld::cid::Cid, IpldStore};
+use typed_path::Utf8UnixPathBuf;
+
+use crate::{
+    filesystem::{EntityNode, FileNode, DIR_TYPE_TAG, FILE_TYPE_TAG},
+    FsResult,
+};
+
+use super::DirSerializable;
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// The differences between two directory trees, as returned by [`diff_dirs`].
+///
+/// All paths are relative to the directories being compared. When a whole directory is added or
+/// removed, only the directory itself is listed and not the entries in it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Getters)]
+#[getset(get = "pub with_prefix")]
+pub struct DirDiff {
+    /// The paths of entries that are only in the new tree.
+    added: Vec<Utf8UnixPathBuf>,
+
+    /// The paths of entries that are only in the old tree.
+    removed: Vec<Utf8UnixPathBuf>,
+
+    /// The paths of entries that are in both trees but changed.
+    modified: Vec<Utf8UnixPathBuf>,
+
+    /// The old and new paths of entries that moved without changing.
+    renamed: Vec<(Utf8UnixPathBuf, Utf8UnixPathBuf)>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl DirDiff {
+    /// Returns `true` if the two trees have no differences.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.modified.is_empty()
+            && self.renamed.is_empty()
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Compares two directory trees, usually two snapshots of the same directory.
+///
+/// Entries are compared by CID, so subtrees that didn't change are skipped without being loaded.
+/// Directories that are in both trees are compared entry by entry and are never reported as
+/// modified themselves. Entries of other types that changed, or whose type changed, are reported
+/// as modified.
+///
+/// Renames are detected on a best-effort basis: a removed entry and an added entry are reported as
+/// a rename if they are files with the same non-empty content, or other entities with the same CID.
+///
+/// ## Arguments
+/// * `store` - The store the directories are in
+/// * `old` - The CID of the old directory
+/// * `new` - The CID of the new directory
+///
+/// ## Examples
+///
+/// ```
+/// use ipldstore::MemoryStore;
+/// use monofs::filesystem::{self, Dir};
+/// use typed_path::Utf8UnixPathBuf;
+///
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// let mut dir = Dir::new(MemoryStore::default());
+/// let old = dir.checkpoint().await?;
+///
+/// dir.create_file("foo.txt").await?;
+/// let new = dir.checkpoint().await?;
+///
+/// let diff = filesystem::diff_dirs(dir.get_store(), &old, &new).await?;
+/// assert_eq!(diff.get_added(), &vec![Utf8UnixPathBuf::from("foo.txt")]);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn diff_dirs<S>(store: &S, old: &Cid, new: &Cid) -> FsResult<DirDiff>
+where
+    S: IpldStore + Send + Sync,
+{
+    let mut diff = DirDiff::default();
+    let mut added = Vec::new();
+    let mut removed = Vec::new();
+    let mut stack = vec![(*old, *new, Utf8UnixPathBuf::new())];
+    while let Some((old, new, path)) = stack.pop() {
+        if old == new {
+            continue;
+        }
+
+        let old_dir: DirSerializable = store.get_node(&old).await?;
+        let new_dir: DirSerializable = store.get_node(&new).await?;
+        let names = old_dir
+            .entries
+            .keys()
+            .chain(new_dir.entries.keys())
+            .collect::<BTreeSet<_>>();
+
+        for name in names {
+            let entry_path = path.join(name);
+            let old_cid = get_entry(&old_dir, name);
+            let new_cid = get_entry(&new_dir, name);
+            match (old_cid, new_cid) {
+                (Some(old_cid), Some(new_cid)) if old_cid == new_cid => {}
+                (Some(old_cid), Some(new_cid)) => {
+                    let old_type = get_type(store, old_cid).await?;
+                    let new_type = get_type(store, new_cid).await?;
+                    if old_type == DIR_TYPE_TAG && new_type == DIR_TYPE_TAG {
+                        stack.push((*old_cid, *new_cid, entry_path));
+                    } else if old_type == new_type {
+                        diff.modified.push(entry_path);
+                    } else {
+                        removed.push((entry_path.clone(), get_rename_key(store, old_cid).await?));
+                        added.push((entry_path, get_rename_key(store, new_cid).await?));
+                    }
+                }
+                (Some(old_cid), None) => {
+                    removed.push((entry_path, get_rename_key(store, old_cid).await?));
+                }
+                (None, Some(new_cid)) => {
+                    added.push((entry_path, get_rename_key(store, new_cid).await?));
+                }
+                (None, None) => {}
+            }
+        }
+    }
+
+    // Pair up removed and added entries with the same content as renames.
+    for (old_path, key) in removed {
+        let renamed_to = key.and_then(|key| {
+            added
+                .iter()
+                .position(|(_, added_key)| *added_key == Some(key))
+        });
+
+        match renamed_to {
+            Some(index) => diff.renamed.push((old_path, added.remove(index).0)),
+            None => diff.removed.push(old_path),
+        }
+    }
+
+    diff.added = added.into_iter().map(|(path, _)| path).collect();
+    diff.added.sort();
+    diff.removed.sort();
+    diff.modified.sort();
+    diff.renamed.sort();
+
+    Ok(diff)
+}
+
+/// Returns the CID of the entry with the given name, unless it is missing or deleted.
+fn get_entry<'a>(dir: &'a DirSerializable, name: &str) -> Option<&'a Cid> {
+    match dir.entries.get(name) {
+        Some((false, cid)) => Some(cid),
+        _ => None,
+    }
+}
+
+/// Returns the type tag of the entity with the given CID.
+async fn get_type<S>(store: &S, cid: &Cid) -> FsResult<String>
+where
+    S: IpldStore + Send + Sync,
+{
+    let entity: EntityNode = store.get_node(cid).await?;
+    Ok(entity.r#type)
+}
+
+/// Returns the key used to match the entity with the given CID when detecting renames.
+///
+/// Files are matched by content so renames are found even if their metadata changed. Empty files
+/// are never matched, since they would all match each other.
+async fn get_rename_key<S>(store: &S, cid: &Cid) -> FsResult<Option<Cid>>
+where
+    S: IpldStore + Send + Sync,
+{
+    if get_type(store, cid).await? == FILE_TYPE_TAG {
+        let file: FileNode = store.get_node(cid).await?;
+        return Ok(file.content);
+    }
+
+    Ok(Some(*cid))
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use ipldstore::MemoryStore;
+
+    use crate::filesystem::{Dir, Entity};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_diff_dirs() -> anyhow::Result<()> {
+        let mut root = Dir::new(MemoryStore::default());
+        for (path, content) in [
+            ("moved.txt", b"moved".as_slice()),
+            ("removed.txt", b"removed"),
+            ("docs/modified.txt", b"modified"),
+            ("docs/unchanged.txt", b"unchanged"),
+            ("untouched/file.txt", b"untouched"),
+        ] {
+            let Entity::File(file) = root.find_or_create(path, true).await? else {
+                unreachable!();
+            };
+            file.write_at(0, content).await?;
+        }
+        root.create_dir("archive").await?;
+        let old = root.checkpoint().await?;
+        assert!(diff_dirs(root.get_store(), &old, &old).await?.is_empty());
+
+        let Some(Entity::File(file)) = root.find_mut("docs/modified.txt").await? else {
+            unreachable!();
+        };
+        file.write_at(8, b", again").await?;
+        root.remove("removed.txt").await?;
+        root.rename("moved.txt", "archive/moved.txt").await?;
+        let Entity::File(file) = root.find_or_create("docs/added.txt", true).await? else {
+            unreachable!();
+        };
+        file.write_at(0, b"added").await?;
+        let new = root.checkpoint().await?;
+
+        let diff = diff_dirs(root.get_store(), &old, &new).await?;
+        assert_eq!(
+            diff.get_added(),
+            &vec![Utf8UnixPathBuf::from("docs/added.txt")]
+        );
+        assert_eq!(
+            diff.get_removed(),
+            &vec![Utf8UnixPathBuf::from("removed.txt")]
+        );
+        assert_eq!(
+            diff.get_modified(),
+            &vec![Utf8UnixPathBuf::from("docs/modified.txt")]
+        );
+        assert_eq!(
+            diff.get_renamed(),
+            &vec![("moved.txt".into(), "archive/moved.txt".into())]
+        );
+
+        // The other way around
+        let diff = diff_dirs(root.get_store(), &new, &old).await?;
+        assert_eq!(
+            diff.get_added(),
+            &vec![Utf8UnixPathBuf::from("removed.txt")]
+        );
+        assert_eq!(
+            diff.get_removed(),
+            &vec![Utf8UnixPathBuf::from("docs/added.txt")]
+        );
+        assert_eq!(
+            diff.get_renamed(),
+            &vec![("archive/moved.txt".into(), "moved.txt".into())]
+        );
+
+        Ok(())
+    }
+}
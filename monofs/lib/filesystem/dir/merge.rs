@@ -0,0 +1,301 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use futures::future::BoxFuture;
+use ipldstore::IpldStore;
+use typed_path::Utf8UnixPathBuf;
+
+use crate::{
+    filesystem::{Entity, EntityCidLink},
+    FsError, FsResult,
+};
+
+use super::{Dir, Entry, Utf8UnixPathSegment};
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// How [`Dir::merge`] resolves entries that are in both directories but differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergePolicy {
+    /// Keep the entry of the directory being merged into.
+    #[default]
+    PreferSelf,
+
+    /// Take the entry of the directory being merged in.
+    PreferOther,
+
+    /// Fail with [`FsError::MergeConflict`].
+    Error,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl<S> Dir<S>
+where
+    S: IpldStore + Send + Sync,
+{
+    /// Overlays `other` onto this directory and returns the result as a new directory. Neither
+    /// directory is changed.
+    ///
+    /// Entries that are only in one of the directories are taken as they are, and entries with the
+    /// same CID on both sides are kept without being loaded. Directories on both sides are merged
+    /// recursively. Any other entries that differ are conflicts, resolved according to `policy`.
+    ///
+    /// Entries deleted in `other` are ignored, so a merge never removes anything. Both directories
+    /// are expected to be in the same store. If anything changes, the result is a new version of
+    /// this directory, with this directory as its previous version if it was loaded from the store.
+    /// The same goes for each subdirectory merged along the way.
+    ///
+    /// ## Arguments
+    /// * `other` - The directory to overlay onto this one
+    /// * `policy` - How to resolve conflicting entries
+    ///
+    /// ## Errors
+    /// Returns `FsError::MergeConflict` with the path of a conflicting entry if `policy` is
+    /// [`MergePolicy::Error`].
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use monofs::filesystem::{Dir, MergePolicy};
+    /// use ipldstore::MemoryStore;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let store = MemoryStore::default();
+    /// let mut a = Dir::new(store.clone());
+    /// a.find_or_create("foo/a.txt", true).await?;
+    ///
+    /// let mut b = Dir::new(store);
+    /// b.find_or_create("foo/b.txt", true).await?;
+    ///
+    /// let merged = a.merge(&b, MergePolicy::Error).await?;
+    /// assert!(merged.find("foo/a.txt").await?.is_some());
+    /// assert!(merged.find("foo/b.txt").await?.is_some());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn merge(&self, other: &Dir<S>, policy: MergePolicy) -> FsResult<Dir<S>> {
+        merge_dirs(self, other, policy, Utf8UnixPathBuf::new()).await
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Merges `other` into a copy of `base`, where `path` is the path of both directories relative to
+/// the roots being merged.
+fn merge_dirs<'a, S>(
+    base: &'a Dir<S>,
+    other: &'a Dir<S>,
+    policy: MergePolicy,
+    path: Utf8UnixPathBuf,
+) -> BoxFuture<'a, FsResult<Dir<S>>>
+where
+    S: IpldStore + Send + Sync,
+{
+    Box::pin(async move {
+        let store = base.get_store().clone();
+        let mut merged = base.clone();
+        let mut changed = false;
+        for (name, other_entry) in other.inner.entries.iter() {
+            if other_entry.deleted {
+                continue;
+            }
+
+            let base_link = match base.inner.entries.get(name) {
+                Some(base_entry) if !base_entry.deleted => &base_entry.link,
+                _ => {
+                    put_entry(&mut merged, name, other_entry.link.clone());
+                    changed = true;
+                    continue;
+                }
+            };
+
+            if base_link.resolve_cid().await? == other_entry.link.resolve_cid().await? {
+                continue;
+            }
+
+            let base_entity = base_link.resolve_entity(store.clone()).await?;
+            let other_entity = other_entry.link.resolve_entity(store.clone()).await?;
+            let link = match (base_entity, other_entity) {
+                (Entity::Dir(base_dir), Entity::Dir(other_dir)) => {
+                    let entry_path = path.join(name.as_str());
+                    EntityCidLink::from(merge_dirs(base_dir, other_dir, policy, entry_path).await?)
+                }
+                _ => match policy {
+                    MergePolicy::PreferSelf => continue,
+                    MergePolicy::PreferOther => other_entry.link.clone(),
+                    MergePolicy::Error => {
+                        return Err(FsError::MergeConflict(path.join(name.as_str()).to_string()));
+                    }
+                },
+            };
+
+            put_entry(&mut merged, name, link);
+            changed = true;
+        }
+
+        if changed {
+            let inner = Arc::make_mut(&mut merged.inner);
+            inner.metadata.set_modified_at(Utc::now());
+            if let Some(base_cid) = base.get_initial_load_cid() {
+                inner.previous = Some(*base_cid);
+            }
+        }
+
+        Ok(merged)
+    })
+}
+
+/// Adds an entry to `dir` as it is, without updating its version or timestamps.
+fn put_entry<S>(dir: &mut Dir<S>, name: &Utf8UnixPathSegment, link: EntityCidLink<S>)
+where
+    S: IpldStore,
+{
+    let inner = Arc::make_mut(&mut dir.inner);
+    inner.entries.insert(
+        name.clone(),
+        Entry {
+            deleted: false,
+            link,
+        },
+    );
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use ipldstore::{MemoryStore, Storable};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dir_merge_disjoint() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+        let a = helper::dir_with_files(&store, &[("a.txt", "a"), ("docs/a.md", "a")]).await?;
+        let b = helper::dir_with_files(&store, &[("b.txt", "b"), ("docs/b.md", "b")]).await?;
+
+        let merged = a.merge(&b, MergePolicy::Error).await?;
+        for path in ["a.txt", "b.txt", "docs/a.md", "docs/b.md"] {
+            assert!(merged.find(path).await?.is_some(), "missing {path}");
+        }
+
+        // Neither side changes.
+        assert!(a.find("b.txt").await?.is_none());
+        assert!(b.find("docs/a.md").await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dir_merge_identical_entries() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+        let mut base = helper::dir_with_files(&store, &[("shared/file.txt", "shared")]).await?;
+        let base_cid = base.checkpoint().await?;
+
+        // Both sides share the untouched entry by CID.
+        let mut a = Dir::load(&base_cid, store.clone()).await?;
+        a.find_or_create("a.txt", true).await?;
+        let mut b = Dir::load(&base_cid, store.clone()).await?;
+        b.find_or_create("b.txt", true).await?;
+
+        let merged = a.merge(&b, MergePolicy::Error).await?;
+        assert!(merged.find("a.txt").await?.is_some());
+        assert!(merged.find("b.txt").await?.is_some());
+        assert_eq!(
+            helper::entry_cid(&merged, "shared").await?,
+            helper::entry_cid(&base, "shared").await?
+        );
+
+        // Merging a directory with itself changes nothing.
+        let merged = base.merge(&base, MergePolicy::Error).await?;
+        assert_eq!(merged.store().await?, base.store().await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dir_merge_bumps_version() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+        let mut base = helper::dir_with_files(&store, &[("docs/a.md", "a")]).await?;
+        let base_cid = base.checkpoint().await?;
+        let docs_cid = helper::entry_cid(&base, "docs").await?;
+        let other = helper::dir_with_files(&store, &[("docs/b.md", "b")]).await?;
+
+        // The merged directory and subdirectory are new versions of the ones merged into
+        let mut merged = base.merge(&other, MergePolicy::Error).await?;
+        assert_eq!(merged.get_previous(), Some(&base_cid));
+        let docs = merged.get_dir("docs").await?.unwrap();
+        assert_eq!(docs.get_previous(), Some(&docs_cid));
+
+        merged.checkpoint().await?;
+        assert_eq!(merged.get_previous(), Some(&base_cid));
+
+        // A merge that changes nothing is the same version
+        let empty = Dir::new(store.clone());
+        let unchanged = base.merge(&empty, MergePolicy::Error).await?;
+        assert_eq!(unchanged.get_previous(), base.get_previous());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_dir_merge_conflicts() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+        let a = helper::dir_with_files(&store, &[("docs/conflict.txt", "a")]).await?;
+        let b = helper::dir_with_files(&store, &[("docs/conflict.txt", "b")]).await?;
+        let a_file = helper::entry_cid(a.get_dir("docs").await?.unwrap(), "conflict.txt").await?;
+        let b_file = helper::entry_cid(b.get_dir("docs").await?.unwrap(), "conflict.txt").await?;
+        assert_ne!(a_file, b_file);
+
+        let merged = a.merge(&b, MergePolicy::PreferSelf).await?;
+        let docs = merged.get_dir("docs").await?.unwrap();
+        assert_eq!(helper::entry_cid(docs, "conflict.txt").await?, a_file);
+
+        let merged = a.merge(&b, MergePolicy::PreferOther).await?;
+        let docs = merged.get_dir("docs").await?.unwrap();
+        assert_eq!(helper::entry_cid(docs, "conflict.txt").await?, b_file);
+
+        let result = a.merge(&b, MergePolicy::Error).await;
+        assert!(matches!(result, Err(FsError::MergeConflict(path)) if path == "docs/conflict.txt"));
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod helper {
+    use ipldstore::{ipld::cid::Cid, MemoryStore};
+
+    use super::*;
+
+    /// Creates a directory with files at the given paths and with the given contents.
+    pub(super) async fn dir_with_files(
+        store: &MemoryStore,
+        files: &[(&str, &str)],
+    ) -> FsResult<Dir<MemoryStore>> {
+        let mut dir = Dir::new(store.clone());
+        for (path, content) in files {
+            let Entity::File(file) = dir.find_or_create(path, true).await? else {
+                unreachable!();
+            };
+            file.write_at(0, content.as_bytes()).await?;
+        }
+
+        Ok(dir)
+    }
+
+    /// Returns the CID of the entry with the given name.
+    pub(super) async fn entry_cid(dir: &Dir<MemoryStore>, name: &str) -> FsResult<Cid> {
+        dir.get_entry(name)?.unwrap().resolve_cid().await
+    }
+}
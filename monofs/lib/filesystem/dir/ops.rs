@@ -472,6 +472,16 @@ where
 
     /// Renames (moves) an entity from one path to another.
     ///
+    /// Like POSIX `rename`, an existing entity at `new_path` is replaced in a single step. A file
+    /// may replace any non-directory entity, and a directory may only replace an empty directory.
+    ///
+    /// ## Errors
+    ///
+    /// * `FsError::PathNotFound` - The source or the target's parent directory does not exist
+    /// * `FsError::IsADirectory` - A non-directory would replace a directory
+    /// * `FsError::NotADirectory` - A directory would replace a non-directory
+    /// * `FsError::DirectoryNotEmpty` - A directory would replace a non-empty directory
+    ///
     /// ## Examples
     ///
     /// ```
@@ -515,18 +525,44 @@ where
         let (old_parent, old_filename) = path::split_last(old_path)?;
         let (new_parent, new_filename) = path::split_last(new_path)?;
 
-        // First check if target exists to fail fast
-        let target_exists = if let Some(parent_path) = new_parent {
-            match find::find_dir(self, parent_path).await? {
-                find::FindResult::Found { dir } => dir.has_entry(&new_filename)?,
-                _ => return Err(FsError::PathNotFound(new_path.to_string())),
-            }
-        } else {
-            self.has_entry(&new_filename)?
+        // The source must exist before anything else is checked
+        let source_is_dir = match self.find(old_path.as_str()).await? {
+            Some(entity) => matches!(entity, Entity::Dir(_)),
+            None => return Err(FsError::PathNotFound(old_path.to_string())),
         };
 
-        if target_exists {
-            return Err(FsError::PathExists(new_path.to_string()));
+        // Renaming an entity onto itself leaves the tree unchanged
+        if old_path == new_path {
+            return Ok(());
+        }
+
+        // An existing target is replaced as long as its type is compatible with the source
+        let target_parent_exists = match new_parent {
+            Some(parent_path) => matches!(
+                find::find_dir(self, parent_path).await?,
+                find::FindResult::Found { .. }
+            ),
+            None => true,
+        };
+
+        if !target_parent_exists {
+            return Err(FsError::PathNotFound(new_path.to_string()));
+        }
+
+        match self.find(new_path.as_str()).await? {
+            Some(Entity::Dir(target_dir)) => {
+                if !source_is_dir {
+                    return Err(FsError::IsADirectory(new_path.to_string()));
+                }
+
+                if !target_dir.is_empty() {
+                    return Err(FsError::DirectoryNotEmpty(new_path.to_string()));
+                }
+            }
+            Some(_) if source_is_dir => {
+                return Err(FsError::NotADirectory(new_path.to_string()));
+            }
+            _ => {}
         }
 
         // Get the source entity without removing it
@@ -1054,12 +1090,15 @@ mod tests {
         ));
 
         // Test 6: Verify error cases
-        // Test 6.1: Rename to existing path
+        // Test 6.1: Rename a file onto an existing directory
         dir.find_or_create("file1.txt", true).await?;
-        dir.find_or_create("file2.txt", true).await?;
-        assert!(dir.rename("file1.txt", "file2.txt").await.is_err());
+        dir.find_or_create("existing_dir", false).await?;
+        assert!(matches!(
+            dir.rename("file1.txt", "existing_dir").await,
+            Err(FsError::IsADirectory(_))
+        ));
         assert!(dir.find("file1.txt").await?.is_some()); // Original file should still exist
-        assert!(dir.find("file2.txt").await?.is_some());
+        assert!(dir.find("existing_dir").await?.is_some());
 
         // Test 6.2: Rename non-existent source
         assert!(dir.rename("nonexistent.txt", "newfile.txt").await.is_err());
@@ -1111,6 +1150,65 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_ops_rename_replaces_existing() -> anyhow::Result<()> {
+        let mut dir = Dir::new(MemoryStore::default());
+
+        // Renaming a file onto an existing file replaces it and preserves the source content
+        dir.find_or_create("source.txt", true).await?;
+        dir.find_or_create("target/existing.txt", true).await?;
+        if let Some(Entity::File(file)) = dir.find_mut("source.txt").await? {
+            let content_cid = file.get_store().put_bytes(b"source".as_slice()).await?;
+            file.set_content(Some(content_cid));
+        }
+
+        dir.rename("source.txt", "target/existing.txt").await?;
+        assert!(dir.find("source.txt").await?.is_none());
+
+        let Some(Entity::File(file)) = dir.find("target/existing.txt").await? else {
+            panic!("expected a file at the target path");
+        };
+        let content_cid = file.get_content().expect("File should have content");
+        let mut content = Vec::new();
+        file.get_store()
+            .get_bytes(content_cid)
+            .await?
+            .read_to_end(&mut content)
+            .await?;
+        assert_eq!(content, b"source");
+
+        // A directory can replace an empty directory
+        dir.find_or_create("full/file.txt", true).await?;
+        dir.find_or_create("empty", false).await?;
+        dir.rename("full", "empty").await?;
+        assert!(dir.find("full").await?.is_none());
+        assert!(matches!(
+            dir.find("empty/file.txt").await?,
+            Some(Entity::File(_))
+        ));
+
+        // A directory cannot replace a non-empty directory
+        dir.find_or_create("other/nested.txt", true).await?;
+        assert!(matches!(
+            dir.rename("other", "empty").await,
+            Err(FsError::DirectoryNotEmpty(_))
+        ));
+        assert!(dir.find("other/nested.txt").await?.is_some());
+        assert!(dir.find("empty/file.txt").await?.is_some());
+
+        // A directory cannot replace a file
+        assert!(matches!(
+            dir.rename("other", "target/existing.txt").await,
+            Err(FsError::NotADirectory(_))
+        ));
+
+        // Renaming an entity onto itself is a no-op
+        dir.rename("other", "other").await?;
+        assert!(dir.find("other/nested.txt").await?.is_some());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_ops_create_entity() -> anyhow::Result<()> {
         let store = MemoryStore::default();
@@ -1,17 +1,27 @@
+mod chunker;
+mod diff;
 mod io;
 
 use std::{
     fmt::{self, Debug},
+    pin::Pin,
     sync::{Arc, OnceLock},
 };
 
 use chrono::Utc;
-use ipldstore::{ipld::cid::Cid, IpldReferences, IpldStore, Storable, StoreError, StoreResult};
+use ipldstore::{
+    ipld::cid::Cid, IpldReferences, IpldStore, IpldStoreSeekable, Storable, StorableStream,
+    StoreError, StoreResult,
+};
+use monoutils::{EmptySeekableReader, SeekableReader};
 use serde::{Deserialize, Serialize};
 use tokio::io::AsyncRead;
 
 use crate::{
-    filesystem::{kind::EntityType, Metadata, MetadataSerializable},
+    filesystem::{
+        kind::{EntityType, SpecialFileType},
+        Metadata, MetadataSerializable,
+    },
     FsResult,
 };
 
@@ -120,6 +130,36 @@ where
         }
     }
 
+    /// Creates a new special file, such as a device node or a FIFO.
+    ///
+    /// Special files have no content. Their type is recorded in the metadata as
+    /// [`EntityType::Special`].
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use monofs::filesystem::{EntityType, File, SpecialFileType};
+    /// use ipldstore::MemoryStore;
+    ///
+    /// let file = File::new_special(MemoryStore::default(), SpecialFileType::Fifo);
+    ///
+    /// assert_eq!(
+    ///     file.get_metadata().get_entity_type(),
+    ///     &EntityType::Special(SpecialFileType::Fifo)
+    /// );
+    /// ```
+    pub fn new_special(store: S, special_type: SpecialFileType) -> Self {
+        Self {
+            inner: Arc::new(FileInner {
+                initial_load_cid: OnceLock::new(),
+                previous: None,
+                metadata: Metadata::new(EntityType::Special(special_type), store.clone()),
+                content: None,
+                store,
+            }),
+        }
+    }
+
     /// Creates a new file with the given content.
     ///
     /// ## Examples
@@ -152,6 +192,50 @@ where
         })
     }
 
+    /// Creates a new file with the given content, split into blocks with `chunker` rather than
+    /// the store's own chunker.
+    ///
+    /// ## Errors
+    /// Returns [`FsError::InvalidChunkerConfig`][crate::FsError::InvalidChunkerConfig] if `chunker`
+    /// is invalid.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use monofs::filesystem::{ChunkerConfig, File};
+    /// use ipldstore::MemoryStore;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let store = MemoryStore::default();
+    /// let chunker = ChunkerConfig::fixed(4)?;
+    /// let file = File::with_chunked_content(store, b"Hello, World!".as_slice(), &chunker).await?;
+    ///
+    /// assert_eq!(file.get_size().await?, 13);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn with_chunked_content(
+        store: S,
+        content: impl AsyncRead + Send + Sync,
+        chunker: &ChunkerConfig,
+    ) -> FsResult<Self>
+    where
+        S: Send + Sync + 'static,
+    {
+        let cid = chunker.put_bytes(&store, content).await?;
+
+        Ok(Self {
+            inner: Arc::new(FileInner {
+                initial_load_cid: OnceLock::new(),
+                previous: None,
+                metadata: Metadata::new(EntityType::File, store.clone()),
+                content: Some(cid),
+                store,
+            }),
+        })
+    }
+
     /// Returns the CID of the file when it was initially loaded from the store.
     ///
     /// It returns `None` if the file was not loaded from the store.
@@ -453,6 +537,24 @@ where
     }
 }
 
+impl<S> StorableStream<S> for File<S>
+where
+    S: IpldStoreSeekable + Send + Sync,
+{
+    async fn store_stream(&mut self, reader: impl AsyncRead + Send + Sync) -> StoreResult<Cid> {
+        let cid = self.inner.store.put_bytes(reader).await?;
+        self.set_content(Some(cid));
+        Ok(cid)
+    }
+
+    async fn load_stream(&self) -> StoreResult<Pin<Box<dyn SeekableReader + Send>>> {
+        match self.inner.content {
+            Some(cid) => self.inner.store.get_seekable_bytes(&cid).await,
+            None => Ok(Box::pin(EmptySeekableReader)),
+        }
+    }
+}
+
 impl<S> Debug for File<S>
 where
     S: IpldStore,
@@ -481,7 +583,7 @@ impl IpldReferences for FileSerializable {
 
 #[cfg(test)]
 mod tests {
-    use ipldstore::{MemoryStore, Storable};
+    use ipldstore::{MemoryStore, Storable, StorableStream};
     use tokio::io::AsyncReadExt;
 
     use super::*;
@@ -573,6 +675,45 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_file_store_and_load_stream() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+        let mut file = File::new(store.clone());
+
+        // An empty file streams no bytes.
+        let mut content = Vec::new();
+        file.load_stream().await?.read_to_end(&mut content).await?;
+        assert!(content.is_empty());
+
+        let data = (0..4 * 1024 * 1024u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 24) as u8)
+            .collect::<Vec<_>>();
+
+        let content_cid = file.store_stream(data.as_slice()).await?;
+        assert_eq!(file.get_content(), Some(&content_cid));
+        assert_eq!(file.get_size().await?, data.len() as u64);
+
+        // Read the content back in chunks through a loaded copy of the file.
+        let cid = file.store().await?;
+        let loaded_file = File::load(&cid, store).await?;
+        let mut reader = loaded_file.load_stream().await?;
+        let mut buffer = vec![0; 64 * 1024];
+        let mut offset = 0;
+        loop {
+            let n = reader.read(&mut buffer).await?;
+            if n == 0 {
+                break;
+            }
+
+            assert_eq!(&buffer[..n], &data[offset..offset + n]);
+            offset += n;
+        }
+
+        assert_eq!(offset, data.len());
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_file_get_initial_load_cid() -> anyhow::Result<()> {
         let store = MemoryStore::default();
@@ -626,4 +767,6 @@ mod tests {
 // Exports
 //--------------------------------------------------------------------------------------------------
 
+pub use chunker::*;
+pub use diff::*;
 pub use io::*;
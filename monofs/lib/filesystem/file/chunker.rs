@@ -0,0 +1,327 @@
+use async_stream::try_stream;
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{stream::BoxStream, StreamExt};
+use ipldstore::{
+    ipld::cid::Cid, Chunker, FastCDCChunker, FixedSizeChunker, FlatLayout, IpldStore, Layout,
+    StoreError, StoreResult, DEFAULT_DESIRED_CHUNK_SIZE, DEFAULT_GEAR_TABLE,
+    DEFAULT_MAX_CHUNK_SIZE, DEFAULT_MIN_CHUNK_SIZE,
+};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncRead;
+
+use crate::{FsError, FsResult};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The largest chunk size FastCDC supports.
+const MAX_FASTCDC_CHUNK_SIZE: u64 = 1 << 48;
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// The chunking algorithm and chunk sizes used to split the content of a file into blocks.
+///
+/// Workloads with many small files or huge media files may dedupe or perform better with other
+/// chunk sizes. A config is also a [`Chunker`], and it is the chunker of a
+/// [`FlatFsStore`][crate::store::FlatFsStore], so a store-wide default is set when the store is
+/// built and every write through the store honors it, including NFS writes and
+/// [`File::with_content`][crate::filesystem::File::with_content]. A single file can still be
+/// chunked differently with
+/// [`File::with_chunked_content`][crate::filesystem::File::with_chunked_content].
+///
+/// The default is FastCDC with the same chunk sizes as [`FastCDCChunker::default`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "algorithm", rename_all = "snake_case")]
+pub enum ChunkerConfig {
+    /// Splits the content into chunks of `chunk_size` bytes, regardless of the content.
+    Fixed {
+        /// The size of each chunk in bytes.
+        chunk_size: u64,
+    },
+
+    /// Splits the content at content-defined boundaries, so chunks survive insertions and
+    /// deletions elsewhere in the content.
+    FastCdc {
+        /// The minimum size of a chunk in bytes, apart from the last one.
+        min_size: u64,
+
+        /// The chunk size in bytes the chunker aims for.
+        avg_size: u64,
+
+        /// The maximum size of a chunk in bytes.
+        max_size: u64,
+    },
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl ChunkerConfig {
+    /// Creates a config that splits content into chunks of `chunk_size` bytes.
+    ///
+    /// ## Errors
+    /// Returns [`FsError::InvalidChunkerConfig`] if `chunk_size` is 0.
+    pub fn fixed(chunk_size: u64) -> FsResult<Self> {
+        let config = Self::Fixed { chunk_size };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Creates a config that splits content with FastCDC, aiming for chunks of `avg_size` bytes.
+    ///
+    /// ## Errors
+    /// Returns [`FsError::InvalidChunkerConfig`] unless `0 < min_size <= avg_size <= max_size`.
+    pub fn fast_cdc(min_size: u64, avg_size: u64, max_size: u64) -> FsResult<Self> {
+        let config = Self::FastCdc {
+            min_size,
+            avg_size,
+            max_size,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks that the chunk sizes are usable.
+    ///
+    /// A config built with [`ChunkerConfig::fixed`] or [`ChunkerConfig::fast_cdc`] is always
+    /// valid, but one that was deserialized may not be.
+    ///
+    /// ## Errors
+    /// Returns [`FsError::InvalidChunkerConfig`] if a chunk size is 0, or if the FastCDC sizes
+    /// don't satisfy `min_size <= avg_size <= max_size`.
+    pub fn validate(&self) -> FsResult<()> {
+        match *self {
+            Self::Fixed { chunk_size: 0 } => Err(FsError::InvalidChunkerConfig(
+                "chunk size must be greater than 0".to_string(),
+            )),
+            Self::FastCdc {
+                min_size,
+                avg_size,
+                max_size,
+            } if min_size == 0
+                || min_size > avg_size
+                || avg_size > max_size
+                || max_size > MAX_FASTCDC_CHUNK_SIZE =>
+            {
+                Err(FsError::InvalidChunkerConfig(format!(
+                    "chunk sizes must satisfy 0 < min ({min_size}) <= avg ({avg_size}) <= max \
+                    ({max_size}) <= 2^48"
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Chunks `reader` with this config and stores the chunks in `store`, returning the CID of the
+    /// content.
+    ///
+    /// The chunks are organized with a [`FlatLayout`], the layout of the monofs stores, so the
+    /// content reads back with [`IpldStore::get_bytes`] like any other.
+    ///
+    /// ## Errors
+    /// Returns [`FsError::InvalidChunkerConfig`] if the config is invalid, and a store error if
+    /// `reader` is empty or a chunk is larger than the store accepts.
+    pub async fn put_bytes<S>(
+        &self,
+        store: &S,
+        reader: impl AsyncRead + Send + Sync,
+    ) -> FsResult<Cid>
+    where
+        S: IpldStore + Send + Sync + 'static,
+    {
+        self.validate()?;
+        put_chunked_bytes(self, store, reader).await
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Chunks `reader` with `chunker` and stores the chunks in `store` with a [`FlatLayout`].
+async fn put_chunked_bytes<S>(
+    chunker: &(impl Chunker + Sync),
+    store: &S,
+    reader: impl AsyncRead + Send + Sync,
+) -> FsResult<Cid>
+where
+    S: IpldStore + Send + Sync + 'static,
+{
+    let layout = FlatLayout::default();
+    let chunk_stream = chunker.chunk(reader).await?;
+    let mut cid_stream = layout.organize(chunk_stream, store.clone()).await?;
+
+    // Take the last CID from the stream, which is the root of the content
+    let mut cid = cid_stream.next().await.unwrap()?;
+    while let Some(result) = cid_stream.next().await {
+        cid = result?;
+    }
+
+    Ok(cid)
+}
+
+//--------------------------------------------------------------------------------------------------
+// Trait Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl Default for ChunkerConfig {
+    fn default() -> Self {
+        Self::FastCdc {
+            min_size: DEFAULT_MIN_CHUNK_SIZE,
+            avg_size: DEFAULT_DESIRED_CHUNK_SIZE,
+            max_size: DEFAULT_MAX_CHUNK_SIZE,
+        }
+    }
+}
+
+#[async_trait]
+impl Chunker for ChunkerConfig {
+    async fn chunk(
+        &self,
+        reader: impl AsyncRead + Send + Sync + 'life0,
+    ) -> StoreResult<BoxStream<'_, StoreResult<Bytes>>> {
+        self.validate().map_err(StoreError::custom)?;
+
+        // The chunker is built inside the stream, since its stream of chunks borrows it
+        let config = *self;
+        let s = try_stream! {
+            let fixed;
+            let fast_cdc;
+            let mut chunks = match config {
+                Self::Fixed { chunk_size } => {
+                    fixed = FixedSizeChunker::new(chunk_size);
+                    fixed.chunk(reader).await?
+                }
+                Self::FastCdc {
+                    min_size,
+                    avg_size,
+                    max_size,
+                } => {
+                    fast_cdc =
+                        FastCDCChunker::new(avg_size, min_size, max_size, DEFAULT_GEAR_TABLE);
+                    fast_cdc.chunk(reader).await?
+                }
+            };
+
+            while let Some(chunk) = chunks.next().await {
+                yield chunk?;
+            }
+        };
+
+        Ok(Box::pin(s))
+    }
+
+    async fn chunk_max_size(&self) -> StoreResult<Option<u64>> {
+        Ok(Some(match *self {
+            Self::Fixed { chunk_size } => chunk_size,
+            Self::FastCdc { max_size, .. } => max_size,
+        }))
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use ipldstore::{IpldStoreExt, MemoryStore};
+    use tempfile::TempDir;
+
+    use crate::{filesystem::File, store::FlatFsStore, utils::fixtures::random_bytes};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_chunker_config_changes_chunks_not_content() -> anyhow::Result<()> {
+        let data = random_bytes(1024 * 1024);
+        let configs = [
+            ChunkerConfig::fixed(64 * 1024)?,
+            ChunkerConfig::fixed(256 * 1024)?,
+            ChunkerConfig::fast_cdc(8 * 1024, 16 * 1024, 64 * 1024)?,
+        ];
+
+        let mut block_counts = Vec::new();
+        for config in configs {
+            let store = MemoryStore::default();
+            let file = File::with_chunked_content(store.clone(), &data[..], &config).await?;
+
+            // The content reads back the same whatever the chunk sizes
+            let content = store.read_all(file.get_content().unwrap()).await?;
+            assert_eq!(content, data);
+
+            block_counts.push(store.get_block_count().await?);
+        }
+
+        // 16 and 4 chunks plus the node listing them, and many small content-defined chunks
+        assert_eq!(block_counts[..2], [17, 5]);
+        assert!(block_counts[2] > block_counts[0], "{block_counts:?}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chunker_config_validation() -> anyhow::Result<()> {
+        assert!(ChunkerConfig::fixed(0).is_err());
+        assert!(ChunkerConfig::fast_cdc(0, 16, 64).is_err());
+        assert!(ChunkerConfig::fast_cdc(32, 16, 64).is_err());
+        assert!(ChunkerConfig::fast_cdc(8, 128, 64).is_err());
+        assert!(matches!(
+            ChunkerConfig::fast_cdc(32, 16, 64),
+            Err(FsError::InvalidChunkerConfig(_))
+        ));
+
+        // Equal sizes are fine
+        ChunkerConfig::fast_cdc(16, 16, 16)?;
+        ChunkerConfig::default().validate()?;
+
+        // Deserialized configs are validated when they are used
+        let config: ChunkerConfig = serde_json::from_str(
+            r#"{"algorithm":"fast_cdc","min_size":64,"avg_size":16,"max_size":32}"#,
+        )?;
+        assert!(config.validate().is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_chunker_config_as_store_default() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let data = random_bytes(1024 * 1024);
+
+        // Every write through the store is chunked with its config
+        let store = FlatFsStore::builder()
+            .path(temp_dir.path())
+            .chunker(Arc::new(ChunkerConfig::fixed(64 * 1024)?))
+            .build();
+        let file = File::with_content(store.clone(), &data[..]).await?;
+        assert_eq!(store.get_block_count().await?, 17);
+        assert_eq!(store.read_all(file.get_content().unwrap()).await?, data);
+
+        // The default config chunks like the default FastCDC chunker
+        let chunks = ChunkerConfig::default()
+            .chunk(&data[..])
+            .await?
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<StoreResult<Vec<_>>>()?;
+        let fastcdc_chunks = FastCDCChunker::default()
+            .chunk(&data[..])
+            .await?
+            .collect::<Vec<_>>()
+            .await
+            .into_iter()
+            .collect::<StoreResult<Vec<_>>>()?;
+        assert_eq!(chunks, fastcdc_chunks);
+
+        Ok(())
+    }
+}
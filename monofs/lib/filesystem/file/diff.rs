@@ -0,0 +1,285 @@
+use std::{collections::HashMap, ops::Range};
+
+use bytes::Bytes;
+use ipldstore::{ipld::cid::Cid, Codec, IpldStore, IpldStoreExt, Storable};
+
+use crate::{filesystem::File, FsResult};
+
+use super::io::get_flat_chunks;
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// A span of bytes that differs between two versions of a file, as returned by [`diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChunkDiff {
+    /// Bytes that are only in the new file, given as a range of the new file.
+    Added(Range<u64>),
+
+    /// Bytes that are only in the old file, given as a range of the old file.
+    Removed(Range<u64>),
+
+    /// Bytes of the old file that were replaced by different bytes in the new file.
+    Changed {
+        /// The replaced range of the old file.
+        old: Range<u64>,
+
+        /// The replacing range of the new file.
+        new: Range<u64>,
+    },
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Returns the spans of bytes that differ between the content of two versions of a file.
+///
+/// The chunk CIDs of the two files are compared first, so chunks shared by both files are never
+/// read. Only the chunks that differ are read, to narrow each span down to the bytes that actually
+/// changed. The spans are returned in file order and identical files produce an empty diff.
+///
+/// ## Arguments
+/// * `store` - The store the files are in
+/// * `old` - The CID of the old version of the file
+/// * `new` - The CID of the new version of the file
+///
+/// ## Examples
+///
+/// ```
+/// use ipldstore::MemoryStore;
+/// use monofs::filesystem::{self, ChunkDiff, File};
+///
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// let store = MemoryStore::default();
+/// let mut file = File::with_content(store.clone(), b"Hello".as_slice()).await?;
+/// let old = file.checkpoint().await?;
+///
+/// file.write_at(5, b", World!").await?;
+/// let new = file.checkpoint().await?;
+///
+/// let diff = filesystem::diff(&store, &old, &new).await?;
+/// assert_eq!(diff, vec![ChunkDiff::Added(5..13)]);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn diff<S>(store: &S, old: &Cid, new: &Cid) -> FsResult<Vec<ChunkDiff>>
+where
+    S: IpldStore + Send + Sync + 'static,
+{
+    let old_file = File::load(old, store.clone()).await?;
+    let new_file = File::load(new, store.clone()).await?;
+    if old_file.get_content() == new_file.get_content() {
+        return Ok(Vec::new());
+    }
+
+    let old_chunks = get_chunks(&old_file).await?;
+    let new_chunks = get_chunks(&new_file).await?;
+
+    // Index the positions of the new chunks to find chunks both files share.
+    let mut new_positions: HashMap<&Cid, Vec<usize>> = HashMap::new();
+    for (position, (cid, _)) in new_chunks.iter().enumerate() {
+        new_positions.entry(cid).or_default().push(position);
+    }
+
+    let mut diffs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    let (mut old_offset, mut new_offset) = (0, 0);
+    while i < old_chunks.len() || j < new_chunks.len() {
+        if i < old_chunks.len() && j < new_chunks.len() && old_chunks[i].0 == new_chunks[j].0 {
+            old_offset += old_chunks[i].1 as u64;
+            new_offset += new_chunks[j].1 as u64;
+            i += 1;
+            j += 1;
+            continue;
+        }
+
+        // Find the next old chunk that is also in the rest of the new file.
+        let (next_i, next_j) = (i..old_chunks.len())
+            .find_map(|k| {
+                let positions = new_positions.get(&old_chunks[k].0)?;
+                let index = positions.partition_point(|position| *position < j);
+                positions.get(index).map(|l| (k, *l))
+            })
+            .unwrap_or((old_chunks.len(), new_chunks.len()));
+
+        let old_bytes = read_chunks(store, &old_chunks[i..next_i]).await?;
+        let new_bytes = read_chunks(store, &new_chunks[j..next_j]).await?;
+        diffs.extend(diff_bytes(&old_bytes, &new_bytes, old_offset, new_offset));
+
+        old_offset += old_bytes.len() as u64;
+        new_offset += new_bytes.len() as u64;
+        (i, j) = (next_i, next_j);
+    }
+
+    Ok(diffs)
+}
+
+/// Returns the chunks of the content of `file`.
+///
+/// Content that isn't a flat list of raw chunks is treated as a single chunk.
+async fn get_chunks<S>(file: &File<S>) -> FsResult<Vec<(Cid, usize)>>
+where
+    S: IpldStore + Send + Sync + 'static,
+{
+    let Some(content) = file.get_content() else {
+        return Ok(Vec::new());
+    };
+
+    match get_flat_chunks(file.get_store(), content).await {
+        Some(chunks) => Ok(chunks),
+        None => Ok(vec![(*content, file.get_size().await? as usize)]),
+    }
+}
+
+/// Reads and concatenates the bytes of `chunks`.
+async fn read_chunks<S>(store: &S, chunks: &[(Cid, usize)]) -> FsResult<Vec<u8>>
+where
+    S: IpldStore + Send + Sync,
+{
+    let mut bytes = Vec::with_capacity(chunks.iter().map(|(_, size)| size).sum());
+    for (cid, _) in chunks {
+        let chunk: Bytes = match Codec::try_from(cid.codec())? {
+            Codec::Raw => store.get_raw_block(cid).await?,
+            _ => store.read_all(cid).await?,
+        };
+
+        bytes.extend_from_slice(&chunk);
+    }
+
+    Ok(bytes)
+}
+
+/// Narrows a pair of differing spans down to the bytes that actually differ.
+fn diff_bytes(old: &[u8], new: &[u8], old_offset: u64, new_offset: u64) -> Option<ChunkDiff> {
+    let head = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let tail = old[head..]
+        .iter()
+        .rev()
+        .zip(new[head..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_range = old_offset + head as u64..old_offset + (old.len() - tail) as u64;
+    let new_range = new_offset + head as u64..new_offset + (new.len() - tail) as u64;
+    match (old_range.is_empty(), new_range.is_empty()) {
+        (true, true) => None,
+        (true, false) => Some(ChunkDiff::Added(new_range)),
+        (false, true) => Some(ChunkDiff::Removed(old_range)),
+        (false, false) => Some(ChunkDiff::Changed {
+            old: old_range,
+            new: new_range,
+        }),
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use ipldstore::MemoryStore;
+
+    use crate::utils::fixtures;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_diff_identical_and_small_files() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+        let mut file = File::with_content(store.clone(), b"Hello, World!".as_slice()).await?;
+        let v1 = file.checkpoint().await?;
+        let v2 = file.checkpoint().await?;
+        assert_eq!(diff(&store, &v1, &v2).await?, vec![]);
+
+        file.write_at(7, b"there!").await?;
+        let v3 = file.checkpoint().await?;
+        assert_eq!(
+            diff(&store, &v1, &v3).await?,
+            vec![ChunkDiff::Changed {
+                old: 7..12,
+                new: 7..12
+            }]
+        );
+
+        // Differing lengths, in both directions
+        let mut short = File::with_content(store.clone(), b"Hello".as_slice()).await?;
+        let short = short.checkpoint().await?;
+        assert_eq!(
+            diff(&store, &v1, &short).await?,
+            vec![ChunkDiff::Removed(5..13)]
+        );
+        assert_eq!(
+            diff(&store, &short, &v1).await?,
+            vec![ChunkDiff::Added(5..13)]
+        );
+
+        // Empty files
+        let mut empty = File::new(store.clone());
+        let empty = empty.checkpoint().await?;
+        assert_eq!(
+            diff(&store, &empty, &short).await?,
+            vec![ChunkDiff::Added(0..5)]
+        );
+        assert_eq!(diff(&store, &empty, &empty).await?, vec![]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_diff_multi_chunk_file() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+        let data = fixtures::random_bytes(4 * 1024 * 1024);
+        let mut file = File::with_content(store.clone(), data.as_slice()).await?;
+        let original = file.checkpoint().await?;
+
+        // Append
+        let mut appended = file.clone();
+        appended.write_at(data.len() as u64, b"appended").await?;
+        let appended = appended.checkpoint().await?;
+        let len = data.len() as u64;
+        assert_eq!(
+            diff(&store, &original, &appended).await?,
+            vec![ChunkDiff::Added(len..len + 8)]
+        );
+
+        // Prepend
+        let prepended = [b"prepended".as_slice(), &data].concat();
+        let mut prepended = File::with_content(store.clone(), prepended.as_slice()).await?;
+        let prepended = prepended.checkpoint().await?;
+        assert_eq!(
+            diff(&store, &original, &prepended).await?,
+            vec![ChunkDiff::Added(0..9)]
+        );
+
+        // Overwrite in two places
+        let patch = |offset: usize| {
+            data[offset..offset + 16]
+                .iter()
+                .map(|b| !b)
+                .collect::<Vec<_>>()
+        };
+        file.write_at(1024 * 1024, &patch(1024 * 1024)).await?;
+        file.write_at(3 * 1024 * 1024, &patch(3 * 1024 * 1024))
+            .await?;
+        let overwritten = file.checkpoint().await?;
+        assert_eq!(
+            diff(&store, &original, &overwritten).await?,
+            vec![
+                ChunkDiff::Changed {
+                    old: 1024 * 1024..1024 * 1024 + 16,
+                    new: 1024 * 1024..1024 * 1024 + 16,
+                },
+                ChunkDiff::Changed {
+                    old: 3 * 1024 * 1024..3 * 1024 * 1024 + 16,
+                    new: 3 * 1024 * 1024..3 * 1024 * 1024 + 16,
+                },
+            ]
+        );
+
+        Ok(())
+    }
+}
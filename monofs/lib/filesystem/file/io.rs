@@ -7,11 +7,11 @@ use std::{
 use bytes::Bytes;
 use chrono::Utc;
 use futures::{future::BoxFuture, FutureExt};
-use ipldstore::{ipld::cid::Cid, IpldStore, IpldStoreSeekable};
+use ipldstore::{ipld::cid::Cid, Codec, IpldStore, IpldStoreSeekable, MerkleNode};
 use monoutils::{EmptySeekableReader, SeekableReader};
-use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, ReadBuf};
 
-use crate::filesystem::File;
+use crate::{filesystem::File, FsError, FsResult};
 
 //--------------------------------------------------------------------------------------------------
 // Types
@@ -29,6 +29,7 @@ where
 {
     file: &'a mut File<S>,
     buffer: Vec<u8>,
+    synced: bool,
     flush_state: FlushState<'a>,
 }
 
@@ -61,6 +62,120 @@ where
     pub fn get_output_stream(&mut self) -> FileOutputStream<'_, S> {
         FileOutputStream::new(self)
     }
+
+    /// Writes `data` to the file at `offset`, overwriting existing bytes and growing the file if
+    /// the write goes past its end.
+    ///
+    /// When the content is a flat list of raw chunks, only the chunks overlapping the written range
+    /// are read and rewritten, and every other chunk keeps its CID. Otherwise the whole content is
+    /// rewritten.
+    ///
+    /// ## Arguments
+    /// * `offset` - The byte offset to start writing at
+    /// * `data` - The bytes to write
+    ///
+    /// ## Errors
+    /// Returns `FsError::InvalidOperation` if `offset` is past the end of the file, since sparse
+    /// files are not supported.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use monofs::filesystem::File;
+    /// use ipldstore::MemoryStore;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let store = MemoryStore::default();
+    /// let mut file = File::with_content(store, b"Hello, World!".as_slice()).await?;
+    ///
+    /// file.write_at(7, b"there!").await?;
+    /// assert_eq!(file.get_size().await?, 13);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn write_at(&mut self, offset: u64, data: &[u8]) -> FsResult<()>
+    where
+        S: IpldStoreSeekable,
+    {
+        let size = self.get_size().await?;
+        if offset > size {
+            return Err(FsError::InvalidOperation(format!(
+                "cannot write at offset {offset} past the end of a file of size {size}"
+            )));
+        }
+
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        let store = self.get_store().clone();
+        let Some(content) = self.get_content().copied() else {
+            let cid = store.put_bytes(data).await?;
+            self.set_content(Some(cid));
+            return Ok(());
+        };
+
+        // Treat content that isn't a flat list of raw chunks as a single chunk.
+        let chunks = get_flat_chunks(&store, &content)
+            .await
+            .unwrap_or_else(|| vec![(content, size as usize)]);
+
+        // Find the first chunk overlapping the write and the first chunk after it.
+        let end = offset + data.len() as u64;
+        let mut starts = Vec::with_capacity(chunks.len() + 1);
+        let mut start = 0;
+        for (_, chunk_size) in chunks.iter() {
+            starts.push(start);
+            start += *chunk_size as u64;
+        }
+        starts.push(start);
+
+        let first = starts[1..].partition_point(|chunk_end| *chunk_end <= offset);
+        let after = first + starts[first..chunks.len()].partition_point(|s| *s < end);
+
+        // Rewrite the affected range, keeping the untouched parts of the chunks it overlaps.
+        let range_start = starts[first];
+        let range_end = starts[after].max(end);
+        let mut buffer = Vec::with_capacity((range_end - range_start) as usize);
+        let mut reader = store.get_seekable_bytes(&content).await?;
+        if offset > range_start {
+            buffer.resize((offset - range_start) as usize, 0);
+            reader.seek(SeekFrom::Start(range_start)).await?;
+            reader.read_exact(&mut buffer).await?;
+        }
+
+        buffer.extend_from_slice(data);
+        if end < range_end {
+            let mut suffix = vec![0; (range_end - end) as usize];
+            reader.seek(SeekFrom::Start(end)).await?;
+            reader.read_exact(&mut suffix).await?;
+            buffer.extend_from_slice(&suffix);
+        }
+
+        let rewritten = store.put_bytes(buffer.as_slice()).await?;
+        if first == 0 && after == chunks.len() {
+            self.set_content(Some(rewritten));
+            return Ok(());
+        }
+
+        let rewritten_chunks = get_flat_chunks(&store, &rewritten).await.ok_or_else(|| {
+            FsError::InvalidOperation("store did not lay out content as flat chunks".into())
+        })?;
+
+        let node = MerkleNode::new(
+            chunks[..first]
+                .iter()
+                .chain(rewritten_chunks.iter())
+                .chain(chunks[after..].iter())
+                .copied(),
+        );
+
+        let cid = store.put_node(&node).await?;
+        self.set_content(Some(cid));
+
+        Ok(())
+    }
 }
 
 impl<'a> FileInputStream<'a> {
@@ -93,9 +208,61 @@ where
         Self {
             file,
             buffer: Vec::new(),
+            synced: false,
             flush_state: FlushState::NotStarted,
         }
     }
+
+    /// Returns the file being written to.
+    pub fn get_file(&self) -> &File<S> {
+        self.file
+    }
+
+    /// Persists the bytes written so far to the store and points the file at them, without
+    /// checkpointing the file.
+    ///
+    /// This acts as a barrier for readers of the file: once it returns, a new input stream on the
+    /// file sees everything written to this stream. Bytes written after a sync are appended to the
+    /// synced content, so only the chunks they land in are stored.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use monofs::filesystem::File;
+    /// use ipldstore::MemoryStore;
+    /// use tokio::io::AsyncWriteExt;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let mut file = File::new(MemoryStore::default());
+    /// let mut output_stream = file.get_output_stream();
+    ///
+    /// output_stream.write_all(b"Hello").await?;
+    /// output_stream.sync().await?;
+    /// assert_eq!(output_stream.get_file().get_size().await?, 5);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn sync(&mut self) -> FsResult<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let buffer = Bytes::from(std::mem::take(&mut self.buffer));
+        let content = if self.synced {
+            self.file.get_content().copied()
+        } else {
+            None
+        };
+
+        let content = append_bytes(self.file.get_store(), content, buffer).await?;
+        self.file.set_content(content);
+        self.file.get_metadata_mut().set_modified_at(Utc::now());
+        self.synced = true;
+        self.flush_state = FlushState::NotStarted;
+
+        Ok(())
+    }
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -141,17 +308,24 @@ where
                 FlushState::NotStarted => {
                     let buffer = std::mem::take(&mut self.buffer);
                     let store = self.file.get_store().clone();
+                    let synced_content = if self.synced {
+                        self.file.get_content().copied()
+                    } else {
+                        None
+                    };
                     let fut = async move {
-                        if !buffer.is_empty() {
+                        if buffer.is_empty() {
+                            Ok(None)
+                        } else if let Some(content) = synced_content {
+                            // Bytes were already synced, so the rest goes after them
+                            append_bytes(&store, Some(content), Bytes::from(buffer))
+                                .await
+                                .map_err(io::Error::other)
+                        } else {
                             let bytes = Bytes::from(buffer);
                             let reader = &bytes[..];
-                            let cid = store
-                                .put_bytes(reader)
-                                .await
-                                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+                            let cid = store.put_bytes(reader).await.map_err(io::Error::other)?;
                             Ok(Some(cid))
-                        } else {
-                            Ok(None)
                         }
                     }
                     .boxed();
@@ -184,6 +358,52 @@ where
     }
 }
 
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Returns the chunks of the content at `cid` if it is a merkle node whose children are all raw
+/// blocks.
+pub(super) async fn get_flat_chunks<S>(store: &S, cid: &Cid) -> Option<Vec<(Cid, usize)>>
+where
+    S: IpldStore + Send + Sync,
+{
+    let node: MerkleNode = store.get_node(cid).await.ok()?;
+    node.children
+        .iter()
+        .all(|(cid, _)| matches!(Codec::try_from(cid.codec()), Ok(Codec::Raw)))
+        .then_some(node.children)
+}
+
+/// Appends `bytes` to `content` and returns the CID of the combined content.
+///
+/// The existing chunks of `content` are kept as they are, and content that isn't a flat list of raw
+/// chunks is kept as a single chunk.
+async fn append_bytes<S>(store: &S, content: Option<Cid>, bytes: Bytes) -> FsResult<Option<Cid>>
+where
+    S: IpldStore + Send + Sync,
+{
+    if bytes.is_empty() {
+        return Ok(content);
+    }
+
+    let appended = store.put_bytes(&bytes[..]).await?;
+    let Some(content) = content else {
+        return Ok(Some(appended));
+    };
+
+    let chunks = match get_flat_chunks(store, &content).await {
+        Some(chunks) => chunks,
+        None => vec![(content, store.get_bytes_size(&content).await? as usize)],
+    };
+    let appended_chunks = get_flat_chunks(store, &appended)
+        .await
+        .unwrap_or_else(|| vec![(appended, bytes.len())]);
+
+    let node = MerkleNode::new(chunks.into_iter().chain(appended_chunks));
+    Ok(Some(store.put_node(&node).await?))
+}
+
 //--------------------------------------------------------------------------------------------------
 // Tests
 //--------------------------------------------------------------------------------------------------
@@ -245,6 +465,62 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_file_output_stream_sync() -> Result<()> {
+        let store = MemoryStore::default();
+        let mut file = File::with_content(store, b"Old content".as_slice()).await?;
+        let mut output_stream = file.get_output_stream();
+
+        // Nothing is visible before the first sync
+        output_stream.write_all(b"Hello").await?;
+        let mut content = Vec::new();
+        output_stream
+            .get_file()
+            .get_input_stream()
+            .await?
+            .read_to_end(&mut content)
+            .await?;
+        assert_eq!(content, b"Old content");
+
+        // Synced bytes replace the old content
+        output_stream.sync().await?;
+        let mut content = Vec::new();
+        output_stream
+            .get_file()
+            .get_input_stream()
+            .await?
+            .read_to_end(&mut content)
+            .await?;
+        assert_eq!(content, b"Hello");
+
+        // Later syncs append the tail
+        output_stream.write_all(b", World").await?;
+        output_stream.sync().await?;
+        let mut content = Vec::new();
+        output_stream
+            .get_file()
+            .get_input_stream()
+            .await?
+            .read_to_end(&mut content)
+            .await?;
+        assert_eq!(content, b"Hello, World");
+        assert_eq!(output_stream.get_file().get_size().await?, 12);
+
+        // Flushing after a sync appends the rest
+        output_stream.write_all(b"!").await?;
+        output_stream.shutdown().await?;
+        drop(output_stream);
+
+        let mut content = Vec::new();
+        file.get_input_stream()
+            .await?
+            .read_to_end(&mut content)
+            .await?;
+        assert_eq!(content, b"Hello, World!");
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_file_input_stream_seek() -> Result<()> {
         let store = MemoryStore::default();
@@ -21,4 +21,28 @@ pub enum EntityType {
 
     /// The entity is a symbolic path link.
     SymPathLink,
+
+    /// The entity is a special file, such as a device node or a FIFO.
+    ///
+    /// Special files are stored as [`File`][crate::filesystem::File]s without content. The device
+    /// numbers of device nodes are kept in the
+    /// [`UNIX_RDEV_MAJOR_KEY`][crate::filesystem::UNIX_RDEV_MAJOR_KEY] and
+    /// [`UNIX_RDEV_MINOR_KEY`][crate::filesystem::UNIX_RDEV_MINOR_KEY] attributes.
+    Special(SpecialFileType),
+}
+
+/// The kind of a special file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpecialFileType {
+    /// A character device node.
+    CharDevice,
+
+    /// A block device node.
+    BlockDevice,
+
+    /// A named pipe.
+    Fifo,
+
+    /// A Unix domain socket.
+    Socket,
 }
@@ -25,6 +25,9 @@ use super::{kind::EntityType, AttributesCidLink};
 /// Key for storing Unix file mode in extended attributes.
 pub const UNIX_MODE_KEY: &str = "unix.mode";
 
+/// The permission bits of a Unix file mode, including the setuid, setgid and sticky bits.
+pub const MODE_PERMISSION_BITS: u32 = 0o7777;
+
 /// Key for storing Unix user ID in extended attributes.
 pub const UNIX_UID_KEY: &str = "unix.uid";
 
@@ -37,6 +40,12 @@ pub const UNIX_ATIME_KEY: &str = "unix.atime";
 /// Key for storing Unix modification time in extended attributes.
 pub const UNIX_MTIME_KEY: &str = "unix.mtime";
 
+/// Key for storing the major device number of a device node in extended attributes.
+pub const UNIX_RDEV_MAJOR_KEY: &str = "unix.rdev.major";
+
+/// Key for storing the minor device number of a device node in extended attributes.
+pub const UNIX_RDEV_MINOR_KEY: &str = "unix.rdev.minor";
+
 //--------------------------------------------------------------------------------------------------
 // Types
 //--------------------------------------------------------------------------------------------------
@@ -44,8 +53,9 @@ pub const UNIX_MTIME_KEY: &str = "unix.mtime";
 /// Relevant metadata for a file system entity.
 ///
 /// This mostly corresponds to the `fd-stat` in POSIX. `monofs` does not support
-/// hard links, so there is no `link-count` field. Also `size` is not stored here, but rather
-/// requested when needed.
+/// hard links, so there is no `link-count` field. The size of a file is not stored here, but
+/// rather requested when needed. Directories cache the total size of their files here when they
+/// are stored, see [`Dir::total_size`][crate::filesystem::Dir::total_size].
 ///
 /// ## Examples
 ///
@@ -81,6 +91,11 @@ where
     /// Extended attributes.
     extended_attrs: Option<AttributesCidLink<S>>,
 
+    /// The cached total size of the files under a directory, as of when it was stored.
+    ///
+    /// It is cleared when the modified timestamp changes.
+    size: Option<u64>,
+
     /// The store of the metadata.
     store: S,
 }
@@ -138,7 +153,7 @@ pub struct ExtendedAttributes<S> {
     inner: Arc<RwLock<ExtendedAttributesInner<S>>>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct ExtendedAttributesInner<S> {
     /// The map of extended attributes.
     map: BTreeMap<String, Arc<Ipld>>,
@@ -159,6 +174,8 @@ pub struct MetadataSerializable {
     modified_at: DateTime<Utc>,
     sync_type: SyncType,
     extended_attrs: Option<Cid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<u64>,
 }
 
 /// A serializable representation of [`ExtendedAttributes`].
@@ -182,6 +199,7 @@ where
             modified_at: now,
             sync_type: SyncType::default(),
             extended_attrs: None,
+            size: None,
             store,
         }
     }
@@ -196,6 +214,7 @@ where
             extended_attrs: serializable
                 .extended_attrs
                 .map(|cid| AttributesCidLink::from(cid)),
+            size: serializable.size,
             store,
         })
     }
@@ -217,6 +236,7 @@ where
             modified_at: self.modified_at,
             sync_type: self.sync_type,
             extended_attrs,
+            size: self.size,
         })
     }
 
@@ -302,6 +322,14 @@ where
         match &mut self.extended_attrs {
             Some(link) => {
                 let attrs = link.resolve_value_mut(self.store.clone()).await?;
+
+                // Attributes shared with a clone of the metadata are copied before writing, so
+                // the clone doesn't see the change.
+                if Arc::strong_count(&attrs.inner) > 1 {
+                    let copy = attrs.inner.read().await.clone();
+                    attrs.inner = Arc::new(RwLock::new(copy));
+                }
+
                 attrs.inner.write().await.map.insert(key, Arc::new(value));
             }
             None => {
@@ -325,8 +353,11 @@ where
     }
 
     /// Sets the modified timestamp.
+    ///
+    /// This also clears the cached size, since the entity may have changed.
     pub fn set_modified_at(&mut self, modified_at: DateTime<Utc>) {
         self.modified_at = modified_at;
+        self.size = None;
     }
 
     /// Sets the created timestamp.
@@ -335,6 +366,13 @@ where
     }
 }
 
+impl MetadataSerializable {
+    /// Sets the cached size.
+    pub(crate) fn set_size(&mut self, size: Option<u64>) {
+        self.size = size;
+    }
+}
+
 //--------------------------------------------------------------------------------------------------
 // Trait Implementations
 //--------------------------------------------------------------------------------------------------
@@ -412,6 +450,7 @@ where
                 "extended_attrs",
                 &self.extended_attrs.as_ref().map(|link| link.get_cid()),
             )
+            .field("size", &self.size)
             .finish()
     }
 }
@@ -528,4 +567,25 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_metadata_clone_does_not_share_attribute_writes() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+        let mut metadata = Metadata::new(EntityType::File, store);
+        metadata.set_attribute("user.tag", "original").await?;
+
+        let mut copy = metadata.clone();
+        copy.set_attribute("user.tag", "copy").await?;
+
+        assert_eq!(
+            metadata.get_attribute("user.tag").await?,
+            Some(Arc::new(Ipld::String("original".to_string())))
+        );
+        assert_eq!(
+            copy.get_attribute("user.tag").await?,
+            Some(Arc::new(Ipld::String("copy".to_string())))
+        );
+
+        Ok(())
+    }
 }
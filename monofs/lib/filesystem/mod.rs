@@ -7,6 +7,7 @@ mod eq;
 mod file;
 mod kind;
 mod metadata;
+mod node;
 mod symcidlink;
 mod sympathlink;
 
@@ -21,5 +22,6 @@ pub use eq::*;
 pub use file::*;
 pub use kind::*;
 pub use metadata::*;
+pub(crate) use node::*;
 pub use symcidlink::*;
 pub use sympathlink::*;
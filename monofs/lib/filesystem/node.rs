@@ -0,0 +1,37 @@
+//! Partial views of the stored entity nodes.
+//!
+//! These decode only the fields a caller needs from an entity block, which is cheaper than loading
+//! the whole entity when walking a tree.
+
+use std::collections::BTreeMap;
+
+use ipldstore::ipld::cid::Cid;
+use serde::Deserialize;
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// The type tag of any entity node.
+#[derive(Debug, Deserialize)]
+pub(crate) struct EntityNode {
+    pub(crate) r#type: String,
+}
+
+/// The entries of a directory node.
+#[derive(Debug, Deserialize)]
+pub(crate) struct DirNode {
+    pub(crate) entries: BTreeMap<String, (bool, Cid)>,
+}
+
+/// The content of a file node.
+#[derive(Debug, Deserialize)]
+pub(crate) struct FileNode {
+    pub(crate) content: Option<Cid>,
+}
+
+/// The target of a symbolic CID link node.
+#[derive(Debug, Deserialize)]
+pub(crate) struct SymCidLinkNode {
+    pub(crate) target: Cid,
+}
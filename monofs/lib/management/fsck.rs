@@ -0,0 +1,332 @@
+use std::{
+    fmt::{self, Display},
+    path::{Path, PathBuf},
+};
+
+use getset::Getters;
+use ipldstore::{
+    ipld::cid::Cid,
+    merkle::{self, MerkleProblem},
+    IpldStore,
+};
+use typed_path::Utf8UnixPathBuf;
+
+use crate::{
+    filesystem::{
+        DirNode, EntityNode, FileNode, SymCidLinkNode, DIR_TYPE_TAG, FILE_TYPE_TAG,
+        SYMCIDLINK_TYPE_TAG, SYMPATHLINK_TYPE_TAG,
+    },
+    store::FlatFsStore,
+    FsResult,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// A problem found by [`fsck`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FsckProblem {
+    /// A block reachable from the root is not in the store.
+    MissingBlock {
+        /// The path of the entity the block belongs to.
+        path: Utf8UnixPathBuf,
+
+        /// The CID of the missing block.
+        cid: Cid,
+    },
+
+    /// A block reachable from the root does not hash to its CID.
+    HashMismatch {
+        /// The path of the entity the block belongs to.
+        path: Utf8UnixPathBuf,
+
+        /// The CID of the corrupt block.
+        cid: Cid,
+    },
+
+    /// A symbolic CID link whose target is missing or corrupt.
+    BrokenSymCidLink {
+        /// The path of the symbolic CID link.
+        path: Utf8UnixPathBuf,
+
+        /// The CID the link points to.
+        target: Cid,
+    },
+
+    /// A block that is referenced as an entity but isn't one.
+    NotAnEntity {
+        /// The path the block is referenced at.
+        path: Utf8UnixPathBuf,
+
+        /// The CID of the block.
+        cid: Cid,
+    },
+
+    /// A block file whose content does not hash to the digest it is stored under.
+    CorruptBlockFile(PathBuf),
+}
+
+/// The result of checking a store with [`fsck`].
+#[derive(Debug, Clone, Default, Getters)]
+#[getset(get = "pub with_prefix")]
+pub struct FsckReport {
+    /// The number of entities that were checked.
+    checked_entities: u64,
+
+    /// The problems that were found.
+    problems: Vec<FsckProblem>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl FsckReport {
+    /// Returns `true` if no problems were found.
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+impl FsckProblem {
+    fn from_merkle(problem: MerkleProblem, path: &Utf8UnixPathBuf) -> Self {
+        match problem {
+            MerkleProblem::MissingBlock(cid) => Self::MissingBlock {
+                path: path.clone(),
+                cid,
+            },
+            MerkleProblem::HashMismatch(cid) => Self::HashMismatch {
+                path: path.clone(),
+                cid,
+            },
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Trait Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl Display for FsckProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingBlock { path, cid } => write!(f, "{path}: missing block {cid}"),
+            Self::HashMismatch { path, cid } => write!(f, "{path}: hash mismatch in block {cid}"),
+            Self::BrokenSymCidLink { path, target } => {
+                write!(f, "{path}: broken symbolic CID link to {target}")
+            }
+            Self::NotAnEntity { path, cid } => write!(f, "{path}: block {cid} is not an entity"),
+            Self::CorruptBlockFile(path) => write!(f, "{}: corrupt block file", path.display()),
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Checks the consistency of the filesystem rooted at `root`.
+///
+/// Every entity reachable from `root` is checked to be in the store and to hash to its CID, the
+/// content of every file is checked with [`merkle::verify`], and the target of every symbolic CID
+/// link is checked to resolve. Previous versions of entities and the entities symbolic CID links
+/// point to are not walked.
+///
+/// ## Arguments
+/// * `store` - The store the filesystem is in
+/// * `root` - The CID of the root entity, usually a directory
+///
+/// ## Example
+/// ```
+/// use ipldstore::MemoryStore;
+/// use monofs::{filesystem::Dir, management};
+///
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// let store = MemoryStore::default();
+/// let mut root = Dir::new(store.clone());
+/// root.create_file("foo.txt").await?;
+/// let root_cid = root.checkpoint().await?;
+///
+/// let report = management::fsck(&store, &root_cid).await?;
+/// assert!(report.is_ok());
+/// # Ok(())
+/// # }
+/// ```
+pub async fn fsck<S>(store: &S, root: &Cid) -> FsResult<FsckReport>
+where
+    S: IpldStore + Send + Sync,
+{
+    let mut report = FsckReport::default();
+    let mut stack = vec![(*root, Utf8UnixPathBuf::from("/"))];
+    while let Some((cid, path)) = stack.pop() {
+        report.checked_entities += 1;
+
+        if let Err(problem) = merkle::verify_block(store, &cid).await? {
+            report
+                .problems
+                .push(FsckProblem::from_merkle(problem, &path));
+            continue;
+        }
+
+        let Ok(entity) = store.get_node::<EntityNode>(&cid).await else {
+            report.problems.push(FsckProblem::NotAnEntity { path, cid });
+            continue;
+        };
+
+        match entity.r#type.as_str() {
+            DIR_TYPE_TAG => {
+                let dir: DirNode = store.get_node(&cid).await?;
+                for (name, (deleted, entry_cid)) in dir.entries.into_iter().rev() {
+                    if !deleted {
+                        stack.push((entry_cid, path.join(name)));
+                    }
+                }
+            }
+            FILE_TYPE_TAG => {
+                let file: FileNode = store.get_node(&cid).await?;
+                if let Some(content) = file.content {
+                    for problem in merkle::verify(store, &content).await? {
+                        report
+                            .problems
+                            .push(FsckProblem::from_merkle(problem, &path));
+                    }
+                }
+            }
+            SYMCIDLINK_TYPE_TAG => {
+                let symlink: SymCidLinkNode = store.get_node(&cid).await?;
+                if merkle::verify_block(store, &symlink.target).await?.is_err() {
+                    report.problems.push(FsckProblem::BrokenSymCidLink {
+                        path,
+                        target: symlink.target,
+                    });
+                }
+            }
+            SYMPATHLINK_TYPE_TAG => {}
+            _ => report.problems.push(FsckProblem::NotAnEntity { path, cid }),
+        }
+    }
+
+    Ok(report)
+}
+
+/// Checks the consistency of the blocks in a flat filesystem store directory.
+///
+/// If `root` is given, the filesystem rooted at it is checked with [`fsck`]. Otherwise, since the
+/// roots are not known, every block file is checked to hash to the digest it is stored under.
+///
+/// ## Arguments
+/// * `store_dir` - The directory of the [`FlatFsStore`]
+/// * `root` - The CID of the root entity, if known
+///
+/// ## Example
+/// ```no_run
+/// use monofs::management;
+///
+/// # async fn example() -> anyhow::Result<()> {
+/// let report = management::fsck_store("mfstest.mfs/blocks", None).await?;
+/// for problem in report.get_problems() {
+///     println!("{problem}");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn fsck_store(store_dir: impl AsRef<Path>, root: Option<Cid>) -> FsResult<FsckReport> {
+    let store = FlatFsStore::new(store_dir.as_ref());
+    match root {
+        Some(root) => fsck(&store, &root).await,
+        None => {
+            let problems = store
+                .find_corrupt_blocks()
+                .await?
+                .into_iter()
+                .map(FsckProblem::CorruptBlockFile)
+                .collect();
+
+            Ok(FsckReport {
+                checked_entities: 0,
+                problems,
+            })
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+    use tokio::fs;
+
+    use crate::filesystem::{Dir, File, SymCidLink};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fsck_reports_corrupt_block_and_dangling_link() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let store = FlatFsStore::new(temp_dir.path());
+
+        // Build a small filesystem
+        let mut docs = Dir::new(store.clone());
+        let mut file = File::new(store.clone());
+        let content = store.put_bytes(&b"Hello, World!"[..]).await?;
+        file.set_content(Some(content));
+        docs.put_adapted_file("hello.txt", file).await?;
+
+        let mut target = File::new(store.clone());
+        let target_cid = target.checkpoint().await?;
+        let symlink = SymCidLink::with_cid(store.clone(), target_cid);
+        docs.put_adapted_symcidlink("link", symlink).await?;
+
+        let mut root = Dir::new(store.clone());
+        root.put_adapted_dir("docs", docs).await?;
+        let root_cid = root.checkpoint().await?;
+        assert!(fsck_store(temp_dir.path(), Some(root_cid)).await?.is_ok());
+        assert!(fsck_store(temp_dir.path(), None).await?.is_ok());
+
+        // Corrupt the file content and dangle the symlink
+        let content_path = store.get_block_path(&content);
+        let mut bytes = fs::read(&content_path).await?;
+        *bytes.last_mut().unwrap() ^= 0xff;
+        fs::write(&content_path, bytes).await?;
+        fs::remove_file(store.get_block_path(&target_cid)).await?;
+
+        let report = fsck_store(temp_dir.path(), Some(root_cid)).await?;
+        assert!(!report.is_ok());
+        assert_eq!(report.get_problems().len(), 2);
+        assert!(report.get_problems().contains(&FsckProblem::HashMismatch {
+            path: "/docs/hello.txt".into(),
+            cid: content,
+        }));
+        assert!(report
+            .get_problems()
+            .contains(&FsckProblem::BrokenSymCidLink {
+                path: "/docs/link".into(),
+                target: target_cid,
+            }));
+
+        // Without a root, only the corrupt block file can be found
+        let report = fsck_store(temp_dir.path(), None).await?;
+        assert_eq!(
+            report.get_problems(),
+            &vec![FsckProblem::CorruptBlockFile(content_path)]
+        );
+
+        // A missing root is reported at the root path
+        fs::remove_file(store.get_block_path(&root_cid)).await?;
+        let report = fsck(&store, &root_cid).await?;
+        assert_eq!(
+            report.get_problems(),
+            &vec![FsckProblem::MissingBlock {
+                path: "/".into(),
+                cid: root_cid,
+            }]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,440 @@
+use std::{
+    fs::Metadata as HostMetadata,
+    os::unix::fs::{FileTypeExt, MetadataExt},
+    path::{Path, PathBuf},
+};
+
+use async_recursion::async_recursion;
+use ipldstore::{ipld::cid::Cid, IpldStore};
+use monoutils::{ProgressEvent, ProgressReader, ProgressTracker};
+use nix::sys::stat;
+use tokio::fs;
+
+use crate::{
+    filesystem::{
+        Dir, Entity, File, Metadata, SpecialFileType, SymPathLink, MODE_PERMISSION_BITS,
+        UNIX_GID_KEY, UNIX_MODE_KEY, UNIX_RDEV_MAJOR_KEY, UNIX_RDEV_MINOR_KEY, UNIX_UID_KEY,
+    },
+    FsError, FsResult,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Builds a tree in `store` from the host directory `src`, and returns the CID of its root.
+///
+/// File contents are chunked and stored as they are read, so identical files, and identical
+/// chunks of different files, are only stored once. Symbolic links are stored as symbolic path
+/// links, and FIFOs, sockets and device nodes as special files. Absolute symbolic link targets
+/// are relative to the root of the tree, as [`materialize`][super::materialize] expects, so
+/// targets under `src` are rewritten relative to it. Other targets are kept unchanged.
+/// The mode and owner of every entry are kept in its metadata, apart from the mode of symbolic
+/// links, which has no meaning on Linux.
+///
+/// This is the inverse of [`materialize`][super::materialize]. Use [`ingest_with_progress`] to
+/// follow the progress of large ingests.
+///
+/// ## Arguments
+/// * `store` - The store to build the tree in
+/// * `src` - The host path to build the tree from. It is usually a directory, but may be any kind
+///   of file
+///
+/// ## Errors
+/// Returns [`FsError::InvalidPathComponent`] if the name of an entry or the target of a symbolic
+/// link isn't valid UTF-8, and an I/O error if an entry can't be read.
+///
+/// ## Example
+/// ```
+/// use ipldstore::{MemoryStore, Storable};
+/// use monofs::{filesystem::Dir, management};
+///
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// let src = tempfile::tempdir()?;
+/// std::fs::write(src.path().join("hello.txt"), "Hello, World!")?;
+///
+/// let store = MemoryStore::default();
+/// let root_cid = management::ingest(&store, src.path()).await?;
+///
+/// let root = Dir::load(&root_cid, store).await?;
+/// assert!(root.get_file("hello.txt").await?.is_some());
+/// # Ok(())
+/// # }
+/// ```
+pub async fn ingest<S>(store: &S, src: &Path) -> FsResult<Cid>
+where
+    S: IpldStore + Clone + Send + Sync + 'static,
+{
+    ingest_with_progress(store, src, |_| {}).await
+}
+
+/// Builds a tree in `store` from the host directory `src` like [`ingest`], reporting its progress
+/// to `progress`.
+///
+/// The total is the size of the regular files under `src`, which is measured before anything is
+/// ingested, and the bytes done grow as the files are read. Every entry is reported as an item,
+/// named after its host path, once it and everything under it is ingested.
+///
+/// ## Arguments
+/// * `store` - The store to build the tree in
+/// * `src` - The host path to build the tree from
+/// * `progress` - The callback the progress is reported to
+///
+/// ## Errors
+/// Returns the same errors as [`ingest`].
+pub async fn ingest_with_progress<S>(
+    store: &S,
+    src: &Path,
+    progress: impl Fn(ProgressEvent) + Send + Sync,
+) -> FsResult<Cid>
+where
+    S: IpldStore + Clone + Send + Sync + 'static,
+{
+    let tracker = ProgressTracker::new(get_host_size(src).await?, &progress);
+    let root = fs::canonicalize(src).await?;
+    let mut entity = ingest_entity(store, src, &root, &tracker).await?;
+    let root_cid = entity.checkpoint().await?;
+    tracing::debug!("ingested {} as {}", src.display(), root_cid);
+
+    Ok(root_cid)
+}
+
+/// Builds the entity for the host file at `path`, and the entries under it if it is a directory.
+///
+/// `root` is the canonical host path of the root of the tree.
+#[async_recursion]
+async fn ingest_entity<'a, S>(
+    store: &S,
+    path: &Path,
+    root: &Path,
+    tracker: &ProgressTracker<'a>,
+) -> FsResult<Entity<S>>
+where
+    S: IpldStore + Clone + Send + Sync + 'static,
+{
+    let host_metadata = fs::symlink_metadata(path).await?;
+    let file_type = host_metadata.file_type();
+
+    let mut entity: Entity<S> = if file_type.is_dir() {
+        let mut dir = Dir::new(store.clone());
+        let mut entries = fs::read_dir(path).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            let entity = ingest_entity(store, &path.join(&name), root, tracker).await?;
+            let name = name
+                .to_str()
+                .ok_or_else(|| FsError::InvalidPathComponent(name.to_string_lossy().into()))?;
+            dir.put_adapted_entity(name, entity).await?;
+        }
+
+        dir.into()
+    } else if file_type.is_symlink() {
+        let target = get_tree_link_target(fs::read_link(path).await?, root);
+        let target = target
+            .to_str()
+            .ok_or_else(|| FsError::InvalidPathComponent(target.to_string_lossy().into()))?;
+        SymPathLink::with_path(store.clone(), target)?.into()
+    } else if file_type.is_file() && host_metadata.len() == 0 {
+        // Stores can't chunk empty content, so empty files are stored without any
+        File::new(store.clone()).into()
+    } else if file_type.is_file() {
+        let content = ProgressReader::new(fs::File::open(path).await?, tracker);
+        File::with_content(store.clone(), content).await?.into()
+    } else {
+        let special_type = if file_type.is_fifo() {
+            SpecialFileType::Fifo
+        } else if file_type.is_char_device() {
+            SpecialFileType::CharDevice
+        } else if file_type.is_block_device() {
+            SpecialFileType::BlockDevice
+        } else {
+            SpecialFileType::Socket
+        };
+
+        let mut file = File::new_special(store.clone(), special_type);
+        if file_type.is_char_device() || file_type.is_block_device() {
+            let rdev = host_metadata.rdev();
+            let metadata = file.get_metadata_mut();
+            metadata
+                .set_attribute(UNIX_RDEV_MAJOR_KEY, stat::major(rdev as _) as u32)
+                .await?;
+            metadata
+                .set_attribute(UNIX_RDEV_MINOR_KEY, stat::minor(rdev as _) as u32)
+                .await?;
+        }
+
+        file.into()
+    };
+
+    set_metadata(entity.get_metadata_mut(), &host_metadata).await?;
+    tracker.complete_item(path.display().to_string());
+
+    Ok(entity)
+}
+
+/// Returns the total size of the regular files at or under the host path `path`.
+#[async_recursion]
+async fn get_host_size(path: &Path) -> FsResult<u64> {
+    let host_metadata = fs::symlink_metadata(path).await?;
+    if host_metadata.is_file() {
+        return Ok(host_metadata.len());
+    }
+
+    let mut size = 0;
+    if host_metadata.is_dir() {
+        let mut entries = fs::read_dir(path).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            size += get_host_size(&entry.path()).await?;
+        }
+    }
+
+    Ok(size)
+}
+
+/// Returns the target to give the symbolic path link for a host symbolic link to `target`.
+///
+/// Absolute targets under `root` are rewritten relative to the root of the tree, which is how
+/// [`materialize`][super::materialize] resolves absolute targets.
+fn get_tree_link_target(target: PathBuf, root: &Path) -> PathBuf {
+    match target.strip_prefix(root) {
+        Ok(rest) => Path::new("/").join(rest),
+        Err(_) => target,
+    }
+}
+
+/// Records the mode and owner of a host file in the metadata of its entity.
+async fn set_metadata<S>(metadata: &mut Metadata<S>, host_metadata: &HostMetadata) -> FsResult<()>
+where
+    S: IpldStore + Clone + Send + Sync + 'static,
+{
+    if !host_metadata.file_type().is_symlink() {
+        metadata
+            .set_attribute(UNIX_MODE_KEY, host_metadata.mode() & MODE_PERMISSION_BITS)
+            .await?;
+    }
+
+    metadata
+        .set_attribute(UNIX_UID_KEY, host_metadata.uid())
+        .await?;
+    metadata
+        .set_attribute(UNIX_GID_KEY, host_metadata.gid())
+        .await?;
+
+    Ok(())
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use ipldstore::{MemoryStore, Storable};
+    use tempfile::TempDir;
+
+    use crate::management::materialize;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_ingest_deduplicates_files() -> anyhow::Result<()> {
+        let src = TempDir::new()?;
+        helper::build_host_tree(src.path())?;
+
+        let store = MemoryStore::default();
+        let root_cid = ingest(&store, src.path()).await?;
+
+        // The duplicate shares its content with the original
+        let root = Dir::load(&root_cid, store.clone()).await?;
+        let original = root.find("docs/readme.md").await?.unwrap();
+        let duplicate = root.find("docs/copy/readme.md").await?.unwrap();
+        let (Entity::File(original), Entity::File(duplicate)) = (original, duplicate) else {
+            panic!("expected files");
+        };
+        assert!(original.get_content().is_some());
+        assert_eq!(original.get_content(), duplicate.get_content());
+
+        // Modes and symbolic links are kept in the tree
+        let script = root.get_file("run.sh").await?.unwrap();
+        let mode = script.get_metadata().get_attribute(UNIX_MODE_KEY).await?;
+        assert_eq!(mode.as_deref(), Some(&0o755.into()));
+
+        let Some(Entity::SymPathLink(link)) = root.find("latest").await? else {
+            panic!("expected a symbolic path link");
+        };
+        assert_eq!(link.get_target_path().as_str(), "docs/readme.md");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ingest_materialize_round_trip() -> anyhow::Result<()> {
+        let src = TempDir::new()?;
+        helper::build_host_tree(src.path())?;
+
+        let store = MemoryStore::default();
+        let root_cid = ingest(&store, src.path()).await?;
+
+        let dest = TempDir::new()?;
+        let out = dest.path().join("out");
+        materialize(&store, &root_cid, &out).await?;
+
+        helper::assert_same_tree(src.path(), &out)?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ingest_absolute_link_targets() -> anyhow::Result<()> {
+        let src = TempDir::new()?;
+        let src_root = std::fs::canonicalize(src.path())?;
+        std::fs::create_dir(src_root.join("docs"))?;
+        std::fs::write(src_root.join("docs/readme.md"), "Hello, World!")?;
+        std::os::unix::fs::symlink(
+            src_root.join("docs/readme.md"),
+            src_root.join("docs/inside"),
+        )?;
+        std::os::unix::fs::symlink("/etc/hosts", src_root.join("docs/outside"))?;
+
+        let store = MemoryStore::default();
+        let root_cid = ingest(&store, src.path()).await?;
+
+        // Targets under the source are made absolute to the root of the tree
+        let root = Dir::load(&root_cid, store.clone()).await?;
+        let Some(Entity::SymPathLink(inside)) = root.find("docs/inside").await? else {
+            panic!("expected a symbolic path link");
+        };
+        assert_eq!(inside.get_target_path().as_str(), "/docs/readme.md");
+        let Some(Entity::SymPathLink(outside)) = root.find("docs/outside").await? else {
+            panic!("expected a symbolic path link");
+        };
+        assert_eq!(outside.get_target_path().as_str(), "/etc/hosts");
+
+        // So the materialized link points into the materialized tree, not back at the source
+        let dest = TempDir::new()?;
+        let out = dest.path().join("out");
+        materialize(&store, &root_cid, &out).await?;
+        assert_eq!(
+            std::fs::read_link(out.join("docs/inside"))?,
+            Path::new("../docs/readme.md")
+        );
+        assert_eq!(
+            std::fs::read_to_string(out.join("docs/inside"))?,
+            "Hello, World!"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ingest_reports_progress() -> anyhow::Result<()> {
+        let src = TempDir::new()?;
+        helper::build_host_tree(src.path())?;
+
+        let events = std::sync::Mutex::new(Vec::new());
+        let store = MemoryStore::default();
+        ingest_with_progress(&store, src.path(), |event| {
+            events.lock().unwrap().push(event)
+        })
+        .await?;
+        let events = events.into_inner().unwrap();
+
+        // The bytes done never decrease and end up at the total
+        let bytes = events
+            .iter()
+            .filter_map(|event| match event {
+                ProgressEvent::Bytes { done, total } => Some((*done, *total)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert!(bytes.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        let (done, total) = *bytes.last().unwrap();
+        assert_eq!(done, total);
+        assert_eq!(total, 2 * 256 * 1024 + 21);
+
+        // Every entry is reported once, and the root last
+        let items = events
+            .iter()
+            .filter_map(|event| match event {
+                ProgressEvent::ItemCompleted { item } => Some(item.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(items.len(), 9);
+        assert_eq!(
+            items.last(),
+            Some(&src.path().display().to_string().as_str())
+        );
+
+        Ok(())
+    }
+
+    mod helper {
+        use super::*;
+
+        /// Builds a host tree with a duplicated file, a symbolic link, a FIFO and various modes.
+        pub(super) fn build_host_tree(root: &Path) -> anyhow::Result<()> {
+            let content: Vec<u8> = (0..256 * 1024).map(|i| (i % 251) as u8).collect();
+            std::fs::create_dir_all(root.join("docs/copy"))?;
+            std::fs::write(root.join("docs/readme.md"), &content)?;
+            std::fs::write(root.join("docs/copy/readme.md"), &content)?;
+            std::fs::write(root.join("docs/empty.txt"), "")?;
+
+            std::fs::write(root.join("run.sh"), "#!/bin/sh\necho hello\n")?;
+            std::fs::set_permissions(root.join("run.sh"), PermissionsExt::from_mode(0o755))?;
+            std::fs::set_permissions(root.join("docs/copy"), PermissionsExt::from_mode(0o700))?;
+
+            std::os::unix::fs::symlink("docs/readme.md", root.join("latest"))?;
+            nix::unistd::mkfifo(&root.join("pipe"), stat::Mode::from_bits_truncate(0o600))?;
+
+            Ok(())
+        }
+
+        /// Asserts that two host trees have the same entries, contents, modes and link targets.
+        pub(super) fn assert_same_tree(a: &Path, b: &Path) -> anyhow::Result<()> {
+            let a_metadata = std::fs::symlink_metadata(a)?;
+            let b_metadata = std::fs::symlink_metadata(b)?;
+            assert_eq!(
+                a_metadata.file_type(),
+                b_metadata.file_type(),
+                "{}",
+                b.display()
+            );
+
+            if a_metadata.file_type().is_symlink() {
+                assert_eq!(std::fs::read_link(a)?, std::fs::read_link(b)?);
+                return Ok(());
+            }
+
+            assert_eq!(
+                a_metadata.mode() & MODE_PERMISSION_BITS,
+                b_metadata.mode() & MODE_PERMISSION_BITS,
+                "{}",
+                b.display()
+            );
+
+            if a_metadata.is_file() {
+                assert_eq!(std::fs::read(a)?, std::fs::read(b)?, "{}", b.display());
+            } else if a_metadata.is_dir() {
+                let names = |dir: &Path| -> std::io::Result<Vec<_>> {
+                    let mut names = std::fs::read_dir(dir)?
+                        .map(|entry| entry.map(|entry| entry.file_name()))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    names.sort();
+                    Ok(names)
+                };
+
+                let a_names = names(a)?;
+                assert_eq!(a_names, names(b)?, "{}", b.display());
+                for name in a_names {
+                    assert_same_tree(&a.join(&name), &b.join(&name))?;
+                }
+            }
+
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,418 @@
+use std::{
+    fs::Permissions,
+    io::{self, ErrorKind},
+    os::unix::fs::{self as unix_fs, PermissionsExt},
+    path::{Path, PathBuf},
+};
+
+use async_recursion::async_recursion;
+use ipldstore::{
+    ipld::{cid::Cid, ipld::Ipld},
+    IpldStore, Storable,
+};
+use nix::sys::stat::{self, Mode, SFlag};
+use tokio::fs::{self, OpenOptions};
+
+use crate::{
+    filesystem::{
+        Entity, EntityType, File, Metadata, SpecialFileType, SymPathLink, MODE_PERMISSION_BITS,
+        UNIX_GID_KEY, UNIX_MODE_KEY, UNIX_RDEV_MAJOR_KEY, UNIX_RDEV_MINOR_KEY, UNIX_UID_KEY,
+    },
+    FsError, FsResult,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The mode special files are created with when the tree doesn't record one.
+const DEFAULT_SPECIAL_FILE_MODE: u32 = 0o644;
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Writes the tree rooted at `root` out to `dest` on the host filesystem.
+///
+/// Directories, files and symbolic path links are recreated as they are. Symbolic CID links have
+/// no host equivalent, so the entity they resolve to is written in their place. Absolute symbolic
+/// path link targets are rewritten relative to the link, so that they resolve inside `dest`
+/// rather than on the host. FIFOs and device nodes are recreated, although device nodes are
+/// skipped if the process isn't allowed to create them. Sockets are skipped.
+///
+/// The recorded modes are applied to everything but symbolic links. The recorded owners are
+/// applied where the process is allowed to change them, which usually requires root.
+///
+/// ## Arguments
+/// * `store` - The store the tree is in
+/// * `root` - The CID of the root entity. If it is a directory, its entries are written into `dest`
+/// * `dest` - The host path to write the tree to. It must not exist, or be an empty directory if
+///   `root` is a directory
+///
+/// ## Errors
+/// Returns [`FsError::PathExists`] if `dest`, or any path the tree is written to, already exists.
+/// Nothing is ever overwritten.
+///
+/// ## Example
+/// ```
+/// use ipldstore::MemoryStore;
+/// use monofs::{filesystem::{Dir, File}, management};
+///
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// let store = MemoryStore::default();
+/// let mut root = Dir::new(store.clone());
+/// let file = File::with_content(store.clone(), &b"Hello, World!"[..]).await?;
+/// root.put_adapted_file("hello.txt", file).await?;
+/// let root_cid = root.checkpoint().await?;
+///
+/// let dest = tempfile::tempdir()?;
+/// management::materialize(&store, &root_cid, &dest.path().join("out")).await?;
+/// assert_eq!(std::fs::read_to_string(dest.path().join("out/hello.txt"))?, "Hello, World!");
+/// # Ok(())
+/// # }
+/// ```
+pub async fn materialize<S>(store: &S, root: &Cid, dest: &Path) -> FsResult<()>
+where
+    S: IpldStore + Clone + Send + Sync + 'static,
+{
+    let entity = Entity::load(root, store.clone()).await?;
+
+    // Only an empty directory can take the entries of a root directory
+    if fs::symlink_metadata(dest).await.is_ok() {
+        let is_empty_dir = matches!(entity, Entity::Dir(_))
+            && fs::metadata(dest).await?.is_dir()
+            && fs::read_dir(dest).await?.next_entry().await?.is_none();
+        if !is_empty_dir {
+            return Err(FsError::PathExists(dest.display().to_string()));
+        }
+    }
+
+    materialize_entity(&entity, dest, 0).await?;
+    tracing::debug!("materialized {} at {}", root, dest.display());
+
+    Ok(())
+}
+
+/// Writes `entity` to `path`, and the entries under it if it is a directory.
+///
+/// `depth` is the number of directories between the root of the tree and `entity`, and is used to
+/// rewrite absolute symbolic path link targets.
+#[async_recursion]
+async fn materialize_entity<S>(entity: &Entity<S>, path: &Path, depth: usize) -> FsResult<()>
+where
+    S: IpldStore + Clone + Send + Sync + 'static,
+{
+    match entity {
+        Entity::Dir(dir) => {
+            match fs::create_dir(path).await {
+                Err(e) if e.kind() == ErrorKind::AlreadyExists && depth == 0 => {}
+                result => result.map_err(|e| collision_error(e, path))?,
+            }
+
+            for (name, link) in dir.get_entries() {
+                let entity = link.resolve_entity(dir.get_store().clone()).await?;
+                materialize_entity(entity, &path.join(name.as_str()), depth + 1).await?;
+            }
+
+            // Apply the mode last, in case it makes the directory read-only
+            apply_metadata(dir.get_metadata(), path, false).await
+        }
+        Entity::File(file) => {
+            if let EntityType::Special(special_type) = file.get_metadata().get_entity_type() {
+                return materialize_special_file(file, *special_type, path).await;
+            }
+
+            let mut output = OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(path)
+                .await
+                .map_err(|e| collision_error(e, path))?;
+
+            if let Some(content) = file.get_content() {
+                let mut input = file.get_store().get_bytes(content).await?;
+                tokio::io::copy(&mut input, &mut output).await?;
+            }
+
+            apply_metadata(file.get_metadata(), path, false).await
+        }
+        Entity::SymCidLink(symlink) => {
+            materialize_entity(symlink.resolve().await?, path, depth).await
+        }
+        Entity::SymPathLink(symlink) => {
+            let target = get_host_link_target(symlink, depth);
+            fs::symlink(&target, path)
+                .await
+                .map_err(|e| collision_error(e, path))?;
+
+            apply_metadata(symlink.get_metadata(), path, true).await
+        }
+    }
+}
+
+/// Creates the special file `file` at `path`.
+async fn materialize_special_file<S>(
+    file: &File<S>,
+    special_type: SpecialFileType,
+    path: &Path,
+) -> FsResult<()>
+where
+    S: IpldStore + Clone + Send + Sync + 'static,
+{
+    let metadata = file.get_metadata();
+    let kind = match special_type {
+        SpecialFileType::Fifo => SFlag::S_IFIFO,
+        SpecialFileType::CharDevice => SFlag::S_IFCHR,
+        SpecialFileType::BlockDevice => SFlag::S_IFBLK,
+        SpecialFileType::Socket => {
+            tracing::warn!("skipping socket {}", path.display());
+            return Ok(());
+        }
+    };
+
+    let mode = get_integer_attribute(metadata, UNIX_MODE_KEY)
+        .await?
+        .unwrap_or(DEFAULT_SPECIAL_FILE_MODE);
+    let major = get_integer_attribute(metadata, UNIX_RDEV_MAJOR_KEY).await?;
+    let minor = get_integer_attribute(metadata, UNIX_RDEV_MINOR_KEY).await?;
+    let dev = stat::makedev(major.unwrap_or(0) as _, minor.unwrap_or(0) as _);
+
+    let perm = Mode::from_bits_truncate((mode & MODE_PERMISSION_BITS) as _);
+    match stat::mknod(path, kind, perm, dev) {
+        Ok(()) => {}
+        Err(nix::errno::Errno::EPERM) if kind != SFlag::S_IFIFO => {
+            tracing::warn!("not permitted to create device node {}", path.display());
+            return Ok(());
+        }
+        Err(errno) => return Err(collision_error(io::Error::from(errno), path)),
+    }
+
+    apply_metadata(metadata, path, false).await
+}
+
+/// Applies the recorded mode and owner of an entity to the file at `path`.
+///
+/// The mode of symbolic links is left alone, since it can't be changed on Linux. Owners are only
+/// applied if the process is permitted to change them.
+async fn apply_metadata<S>(metadata: &Metadata<S>, path: &Path, is_symlink: bool) -> FsResult<()>
+where
+    S: IpldStore + Clone + Send + Sync + 'static,
+{
+    if !is_symlink {
+        if let Some(mode) = get_integer_attribute(metadata, UNIX_MODE_KEY).await? {
+            let permissions = Permissions::from_mode(mode & MODE_PERMISSION_BITS);
+            fs::set_permissions(path, permissions).await?;
+        }
+    }
+
+    let uid = get_integer_attribute(metadata, UNIX_UID_KEY).await?;
+    let gid = get_integer_attribute(metadata, UNIX_GID_KEY).await?;
+    if uid.is_some() || gid.is_some() {
+        match unix_fs::lchown(path, uid, gid) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+                tracing::debug!("not permitted to change the owner of {}", path.display());
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the target to give the host symbolic link for `symlink`.
+///
+/// Absolute targets are relative to the root of the tree, so they are rewritten relative to the
+/// directory the link is in, `depth - 1` levels below the root.
+fn get_host_link_target<S>(symlink: &SymPathLink<S>, depth: usize) -> PathBuf
+where
+    S: IpldStore,
+{
+    let target = symlink.get_target_path();
+    if !target.is_absolute() {
+        return PathBuf::from(target.as_str());
+    }
+
+    let mut host_target = PathBuf::new();
+    for _ in 1..depth {
+        host_target.push("..");
+    }
+
+    host_target.push(target.as_str().trim_start_matches('/'));
+    if host_target.as_os_str().is_empty() {
+        host_target.push(".");
+    }
+
+    host_target
+}
+
+/// Returns an unsigned integer attribute of an entity, if it is set and valid.
+async fn get_integer_attribute<S>(metadata: &Metadata<S>, key: &str) -> FsResult<Option<u32>>
+where
+    S: IpldStore + Clone + Send + Sync + 'static,
+{
+    let value = metadata.get_attribute(key).await?;
+    Ok(value.and_then(|ipld| match &*ipld {
+        Ipld::Integer(value) => u32::try_from(*value).ok(),
+        Ipld::String(value) => value.parse().ok(),
+        _ => None,
+    }))
+}
+
+/// Reports an error creating `path` because it already exists as a name collision.
+fn collision_error(error: io::Error, path: &Path) -> FsError {
+    if error.kind() == ErrorKind::AlreadyExists {
+        FsError::PathExists(path.display().to_string())
+    } else {
+        error.into()
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+    use ipldstore::MemoryStore;
+    use tempfile::TempDir;
+
+    use crate::filesystem::{Dir, SymCidLink};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_materialize() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+        let root_cid = helper::build_tree(&store).await?;
+
+        let temp_dir = TempDir::new()?;
+        let dest = temp_dir.path().join("out");
+        materialize(&store, &root_cid, &dest).await?;
+
+        // Directories and files, with their content and modes
+        assert!(dest.join("docs").is_dir());
+        assert_eq!(
+            std::fs::read_to_string(dest.join("docs/readme.md"))?,
+            "Hello, World!"
+        );
+        let metadata = std::fs::metadata(dest.join("docs/secret.txt"))?;
+        assert_eq!(metadata.mode() & MODE_PERMISSION_BITS, 0o600);
+        assert_eq!(std::fs::read(dest.join("docs/secret.txt"))?, b"");
+        let metadata = std::fs::metadata(dest.join("bin"))?;
+        assert_eq!(metadata.mode() & MODE_PERMISSION_BITS, 0o750);
+
+        // Symbolic path links, with absolute targets rewritten to stay inside the tree
+        assert_eq!(
+            std::fs::read_link(dest.join("docs/latest"))?,
+            PathBuf::from("readme.md")
+        );
+        assert_eq!(
+            std::fs::read_link(dest.join("docs/tool"))?,
+            PathBuf::from("../bin/tool")
+        );
+        assert_eq!(
+            std::fs::read_link(dest.join("home"))?,
+            PathBuf::from("docs")
+        );
+        assert_eq!(
+            std::fs::read_to_string(dest.join("docs/tool"))?,
+            "#!/bin/sh"
+        );
+
+        // Symbolic CID links are written as a copy of their target
+        let copy = std::fs::symlink_metadata(dest.join("readme-copy.md"))?;
+        assert!(copy.is_file());
+        assert_eq!(
+            std::fs::read_to_string(dest.join("readme-copy.md"))?,
+            "Hello, World!"
+        );
+
+        // Special files
+        let pipe = std::fs::symlink_metadata(dest.join("pipe"))?;
+        assert!(pipe.file_type().is_fifo());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_materialize_collisions() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+        let root_cid = helper::build_tree(&store).await?;
+
+        // An empty directory takes the entries of the root
+        let temp_dir = TempDir::new()?;
+        materialize(&store, &root_cid, temp_dir.path()).await?;
+        assert!(temp_dir.path().join("docs/readme.md").is_file());
+
+        // Anything else in the way is left alone
+        let result = materialize(&store, &root_cid, temp_dir.path()).await;
+        assert!(matches!(result, Err(FsError::PathExists(_))));
+
+        let file_path = temp_dir.path().join("docs/readme.md");
+        let result = materialize(&store, &root_cid, &file_path).await;
+        assert!(matches!(result, Err(FsError::PathExists(_))));
+        assert_eq!(std::fs::read_to_string(&file_path)?, "Hello, World!");
+
+        Ok(())
+    }
+
+    mod helper {
+        use super::*;
+
+        /// Builds a tree with every kind of entity, and returns the CID of its root.
+        pub(super) async fn build_tree(store: &MemoryStore) -> anyhow::Result<Cid> {
+            let readme = File::with_content(store.clone(), &b"Hello, World!"[..]).await?;
+            let mut secret = File::new(store.clone());
+            secret
+                .get_metadata_mut()
+                .set_attribute(UNIX_MODE_KEY, 0o600)
+                .await?;
+
+            let mut docs = Dir::new(store.clone());
+            docs.put_adapted_file("readme.md", readme.clone()).await?;
+            docs.put_adapted_file("secret.txt", secret).await?;
+            docs.put_adapted_sympathlink(
+                "latest",
+                SymPathLink::with_path(store.clone(), "readme.md")?,
+            )
+            .await?;
+            docs.put_adapted_sympathlink(
+                "tool",
+                SymPathLink::with_path(store.clone(), "/bin/tool")?,
+            )
+            .await?;
+
+            let mut bin = Dir::new(store.clone());
+            bin.get_metadata_mut()
+                .set_attribute(UNIX_MODE_KEY, 0o750)
+                .await?;
+            let tool = File::with_content(store.clone(), &b"#!/bin/sh"[..]).await?;
+            bin.put_adapted_file("tool", tool).await?;
+
+            let mut readme = readme;
+            let readme_cid = readme.checkpoint().await?;
+
+            let mut root = Dir::new(store.clone());
+            root.put_adapted_dir("docs", docs).await?;
+            root.put_adapted_dir("bin", bin).await?;
+            root.put_adapted_sympathlink("home", SymPathLink::with_path(store.clone(), "/docs")?)
+                .await?;
+            root.put_adapted_symcidlink(
+                "readme-copy.md",
+                SymCidLink::with_cid(store.clone(), readme_cid),
+            )
+            .await?;
+            root.put_adapted_file(
+                "pipe",
+                File::new_special(store.clone(), SpecialFileType::Fifo),
+            )
+            .await?;
+
+            Ok(root.checkpoint().await?)
+        }
+    }
+}
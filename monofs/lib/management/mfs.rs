@@ -2,7 +2,10 @@ use crate::{
     config::{DEFAULT_HOST, DEFAULT_MFSRUN_EXE_PATH, DEFAULT_NFS_PORT},
     management::{db, find, FS_DB_MIGRATOR},
     utils::{
-        path::{BLOCKS_SUBDIR, FS_DB_FILENAME, LOG_SUBDIR, MFS_DIR_SUFFIX, MFS_LINK_FILENAME},
+        path::{
+            BLOCKS_SUBDIR, FILEID_LOG_FILENAME, FS_DB_FILENAME, LOG_SUBDIR, MFS_DIR_SUFFIX,
+            MFS_LINK_FILENAME,
+        },
         MFSRUN_EXE_ENV_VAR,
     },
     FsError, FsResult,
@@ -97,6 +100,8 @@ pub async fn init_mfs(mount_dir: Option<PathBuf>) -> FsResult<u32> {
         .arg(port.to_string())
         .arg("--store-dir")
         .arg(&blocks_dir)
+        .arg("--fileid-log")
+        .arg(mfs_data_dir.join(FILEID_LOG_FILENAME))
         .arg("--fs-db-path")
         .arg(&fs_db_path)
         .arg("--mount-dir")
@@ -241,7 +246,7 @@ async fn get_supervisor_pid(
 }
 
 /// Unmount a filesystem at the specified mount point
-async fn unmount_fs(mount_dir: impl AsRef<Path>, force: bool) -> FsResult<()> {
+pub(crate) async fn unmount_fs(mount_dir: impl AsRef<Path>, force: bool) -> FsResult<()> {
     let mount_dir = mount_dir.as_ref();
 
     // Check if mount point exists
@@ -254,14 +259,9 @@ async fn unmount_fs(mount_dir: impl AsRef<Path>, force: bool) -> FsResult<()> {
 
     tracing::info!("unmounting filesystem at {}", mount_dir.display());
 
-    // Construct the unmount command
-    let mut cmd = Command::new("umount");
-    if force {
-        cmd.arg("-f");
-    }
-    cmd.arg(mount_dir);
-
-    let status = cmd.status().await?;
+    let status = Command::from(unmount_command(mount_dir, force))
+        .status()
+        .await?;
 
     if !status.success() {
         return Err(FsError::UnmountFailed(format!(
@@ -278,7 +278,7 @@ async fn unmount_fs(mount_dir: impl AsRef<Path>, force: bool) -> FsResult<()> {
 }
 
 /// Mount a remote NFS filesystem at the specified mount point
-async fn mount_fs(mount_dir: impl AsRef<Path>, host: &str, port: u32) -> FsResult<()> {
+pub(crate) async fn mount_fs(mount_dir: impl AsRef<Path>, host: &str, port: u32) -> FsResult<()> {
     let mount_dir = mount_dir.as_ref();
 
     // Create mount point if it doesn't exist
@@ -298,25 +298,8 @@ async fn mount_fs(mount_dir: impl AsRef<Path>, host: &str, port: u32) -> FsResul
     // 5+ seconds on macos.
     wait_for_port(host, port).await;
 
-    // Construct the mount command
-    // Using standard NFS mount options:
-    // - nolocks: disable NFS file locking
-    // - vers=3: use NFSv3
-    // - tcp: use TCP transport
-    // - soft: return errors rather than hang on timeouts
-    // - mountport=port: use same port for mount protocol
-    let source = format!("{}:/", host);
     let start = Instant::now();
-    let status = Command::new("mount")
-        .arg("-t")
-        .arg("nfs")
-        .arg("-o")
-        .arg(format!(
-            "nolocks,vers=3,tcp,port={port},mountport={port},soft",
-            port = port
-        ))
-        .arg(source)
-        .arg(&mount_dir)
+    let status = Command::from(mount_command(mount_dir, host, port))
         .status()
         .await?;
 
@@ -337,6 +320,39 @@ async fn mount_fs(mount_dir: impl AsRef<Path>, host: &str, port: u32) -> FsResul
 // Functions: Helpers
 //--------------------------------------------------------------------------------------------------
 
+/// Constructs the command that mounts the NFS share served at `host:port` on `mount_dir`.
+pub(crate) fn mount_command(mount_dir: &Path, host: &str, port: u32) -> std::process::Command {
+    // Using standard NFS mount options:
+    // - nolocks: disable NFS file locking
+    // - vers=3: use NFSv3
+    // - tcp: use TCP transport
+    // - soft: return errors rather than hang on timeouts
+    // - mountport=port: use same port for mount protocol
+    let mut cmd = std::process::Command::new("mount");
+    cmd.arg("-t")
+        .arg("nfs")
+        .arg("-o")
+        .arg(format!(
+            "nolocks,vers=3,tcp,port={port},mountport={port},soft",
+            port = port
+        ))
+        .arg(format!("{}:/", host))
+        .arg(mount_dir);
+
+    cmd
+}
+
+/// Constructs the command that unmounts the filesystem mounted on `mount_dir`.
+pub(crate) fn unmount_command(mount_dir: &Path, force: bool) -> std::process::Command {
+    let mut cmd = std::process::Command::new("umount");
+    if force {
+        cmd.arg("-f");
+    }
+    cmd.arg(mount_dir);
+
+    cmd
+}
+
 /// Wait for the given host and port to become available.
 ///
 /// This function tries to open a TCP connection to the address. If it fails,
@@ -2,7 +2,11 @@
 
 mod db;
 mod find;
+mod fsck;
+mod ingest;
+mod materialize;
 mod mfs;
+mod pin;
 
 //--------------------------------------------------------------------------------------------------
 // Exports
@@ -10,4 +14,8 @@ mod mfs;
 
 pub use db::*;
 pub use find::*;
+pub use fsck::*;
+pub use ingest::*;
+pub use materialize::*;
 pub use mfs::*;
+pub use pin::*;
@@ -0,0 +1,229 @@
+use ipldstore::{ipld::cid::Cid, IpldStore, StoreError};
+
+use crate::{store::FlatFsStore, FsResult};
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Pins a CID in the store, so that [`gc`][FlatFsStore::gc] treats it as a root.
+///
+/// A pinned CID and every block reachable from it are never collected, even when no filesystem
+/// handle refers to them. Pins are kept in the store directory, so they persist across restarts.
+///
+/// ## Arguments
+/// * `store` - The store to pin the CID in
+/// * `cid` - The CID to pin
+///
+/// ## Returns
+/// `true` if the CID was pinned, or `false` if it was already pinned.
+///
+/// ## Errors
+/// Returns [`StoreError::BlockNotFound`] if the CID is not in the store.
+///
+/// ## Example
+/// ```
+/// use ipldstore::IpldStore;
+/// use monofs::{management, store::FlatFsStore};
+///
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// # let temp_dir = tempfile::tempdir()?;
+/// let store = FlatFsStore::new(temp_dir.path());
+/// let cid = store.put_bytes(&b"hello"[..]).await?;
+///
+/// management::pin(&store, &cid).await?;
+/// assert_eq!(management::list_pins(&store).await?, vec![cid]);
+/// # Ok(())
+/// # }
+/// ```
+pub async fn pin(store: &FlatFsStore, cid: &Cid) -> FsResult<bool> {
+    // Holding the lock keeps a running gc from collecting the CID before it is pinned
+    let _guard = store.lock_pins().await;
+    if !store.has(cid).await {
+        return Err(StoreError::BlockNotFound(*cid).into());
+    }
+
+    let mut pins = store.read_pins().await?;
+    if pins.contains(cid) {
+        return Ok(false);
+    }
+
+    pins.push(*cid);
+    store.write_pins(&pins).await?;
+    tracing::debug!("pinned {}", cid);
+
+    Ok(true)
+}
+
+/// Unpins a CID in the store, so that [`gc`][FlatFsStore::gc] collects it once nothing else
+/// keeps it alive.
+///
+/// ## Arguments
+/// * `store` - The store to unpin the CID in
+/// * `cid` - The CID to unpin
+///
+/// ## Returns
+/// `true` if the CID was unpinned, or `false` if it wasn't pinned.
+pub async fn unpin(store: &FlatFsStore, cid: &Cid) -> FsResult<bool> {
+    let _guard = store.lock_pins().await;
+    let mut pins = store.read_pins().await?;
+    let Some(index) = pins.iter().position(|pin| pin == cid) else {
+        return Ok(false);
+    };
+
+    pins.remove(index);
+    store.write_pins(&pins).await?;
+    tracing::debug!("unpinned {}", cid);
+
+    Ok(true)
+}
+
+/// Returns the CIDs pinned in the store, in the order they were pinned.
+///
+/// ## Arguments
+/// * `store` - The store to list the pins of
+pub async fn list_pins(store: &FlatFsStore) -> FsResult<Vec<Cid>> {
+    Ok(store.read_pins().await?)
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use ipldstore::RawStore;
+    use tempfile::TempDir;
+
+    use crate::{
+        filesystem::{Dir, File},
+        management::fsck,
+        store::DirLevels,
+        FsError,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_pin_survives_gc() -> anyhow::Result<()> {
+        let (store, _temp) = helper::setup_store(DirLevels::Zero);
+
+        // Build a filesystem, pin its root and drop every handle to it
+        let content = store.put_bytes(&b"Hello, World!"[..]).await?;
+        let root_cid = {
+            let mut docs = Dir::new(store.clone());
+            let mut file = File::new(store.clone());
+            file.set_content(Some(content));
+            docs.put_adapted_file("hello.txt", file).await?;
+
+            let mut root = Dir::new(store.clone());
+            root.put_adapted_dir("docs", docs).await?;
+            root.checkpoint().await?
+        };
+        let orphan = store.put_raw_block(b"orphan".to_vec()).await?;
+
+        assert!(pin(&store, &root_cid).await?);
+        assert!(!pin(&store, &root_cid).await?);
+
+        // Pins are persisted in the store directory, so a fresh handle sees them
+        let reopened = FlatFsStore::builder()
+            .dir_levels(DirLevels::Zero)
+            .path(store.get_path())
+            .build();
+        assert_eq!(list_pins(&reopened).await?, vec![root_cid]);
+
+        // The pins file is not mistaken for a block
+        let block_count = store.get_block_count().await?;
+        let report = reopened.gc(&[]).await?;
+        assert_eq!(
+            report.get_kept_blocks() + report.get_deleted_blocks(),
+            block_count
+        );
+
+        // The orphan is collected while the pinned filesystem survives intact
+        assert!(!store.has(&orphan).await);
+        assert!(store.has(&content).await);
+        assert!(fsck(&store, &root_cid).await?.is_ok());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unpin() -> anyhow::Result<()> {
+        let (store, _temp) = helper::setup_store(DirLevels::One);
+
+        let first = store.put_raw_block(b"first".to_vec()).await?;
+        let second = store.put_raw_block(b"second".to_vec()).await?;
+        pin(&store, &first).await?;
+        pin(&store, &second).await?;
+        assert_eq!(list_pins(&store).await?, vec![first, second]);
+
+        assert!(unpin(&store, &first).await?);
+        assert!(!unpin(&store, &first).await?);
+        assert_eq!(list_pins(&store).await?, vec![second]);
+
+        // Once unpinned, the block is garbage
+        let report = store.gc(&[]).await?;
+        assert_eq!(report.get_deleted_blocks(), 1);
+        assert!(!store.has(&first).await);
+        assert!(store.has(&second).await);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pin_missing_block() -> anyhow::Result<()> {
+        let (store, _temp) = helper::setup_store(DirLevels::One);
+
+        let cid = store.put_raw_block(b"missing".to_vec()).await?;
+        store.gc(&[]).await?;
+
+        let result = pin(&store, &cid).await;
+        assert!(matches!(
+            result,
+            Err(FsError::IpldStore(StoreError::BlockNotFound(missing))) if missing == cid
+        ));
+        assert!(list_pins(&store).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_gc_waits_for_pinning() -> anyhow::Result<()> {
+        let (store, _temp) = helper::setup_store(DirLevels::One);
+        let cid = store.put_raw_block(b"pinned".to_vec()).await?;
+
+        // A gc started while a pin is in progress doesn't read the pins until it is done
+        let guard = store.lock_pins().await;
+        let gc = tokio::spawn({
+            let store = store.clone();
+            async move { store.gc(&[]).await }
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert!(!gc.is_finished());
+
+        let mut pins = store.read_pins().await?;
+        pins.push(cid);
+        store.write_pins(&pins).await?;
+        drop(guard);
+
+        assert_eq!(gc.await??.get_deleted_blocks(), 0);
+        assert!(store.has(&cid).await);
+
+        Ok(())
+    }
+
+    mod helper {
+        use super::*;
+
+        pub(super) fn setup_store(dir_levels: DirLevels) -> (FlatFsStore, TempDir) {
+            let temp_dir = TempDir::new().unwrap();
+            let store = FlatFsStore::builder()
+                .dir_levels(dir_levels)
+                .path(temp_dir.path())
+                .build();
+            (store, temp_dir)
+        }
+    }
+}
@@ -6,7 +6,8 @@ use std::{
 
 use async_trait::async_trait;
 use monoutils::{
-    ChildIo, MonoutilsError, MonoutilsResult, ProcessMonitor, RotatingLog, LOG_SUFFIX,
+    ChildIo, LogEncoder, LogFormat, LogStream, MonoutilsError, MonoutilsResult, ProcessMonitor,
+    RotatingLog, LOG_SUFFIX,
 };
 use sqlx::{Pool, Sqlite};
 use tokio::io::AsyncReadExt;
@@ -36,6 +37,9 @@ pub struct NfsServerMonitor {
 
     /// The log path
     log_path: Option<PathBuf>,
+
+    /// The format the NFS server output is written to the log in
+    log_format: LogFormat,
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -58,9 +62,16 @@ impl NfsServerMonitor {
             mount_dir: mount_dir.into(),
             log_dir: log_dir.into(),
             log_path: None,
+            log_format: LogFormat::default(),
         })
     }
 
+    /// Sets the format the NFS server output is written to the log in.
+    pub fn with_log_format(mut self, log_format: LogFormat) -> Self {
+        self.log_format = log_format;
+        self
+    }
+
     /// Generates a unique log name using name, process ID, and current timestamp.
     ///
     /// The ID format is: "mfsrun-{name}-{timestamp}-{child_pid}.log"
@@ -115,6 +126,7 @@ impl ProcessMonitor for NfsServerMonitor {
 
         // Spawn tasks to handle stdout/stderr
         if let Some(mut stdout) = stdout {
+            let mut encoder = LogEncoder::new(self.log_format, LogStream::Stdout);
             tokio::spawn(async move {
                 let mut buf = [0u8; 1024];
 
@@ -122,17 +134,29 @@ impl ProcessMonitor for NfsServerMonitor {
                     if n == 0 {
                         break;
                     }
-                    if let Err(e) = stdout_writer.write_all(&buf[..n]) {
+                    if let Err(e) = stdout_writer.write_all(&encoder.encode(&buf[..n])) {
                         tracing::error!(pid = pid, error = %e, "Failed to write to nfs server stdout log");
                     }
                     if let Err(e) = stdout_writer.flush() {
                         tracing::error!(pid = pid, error = %e, "Failed to flush nfs server stdout log");
                     }
                 }
+
+                // Write out an unterminated last line
+                let rest = encoder.finish();
+                if !rest.is_empty() {
+                    if let Err(e) = stdout_writer
+                        .write_all(&rest)
+                        .and_then(|_| stdout_writer.flush())
+                    {
+                        tracing::error!(pid = pid, error = %e, "Failed to write to nfs server stdout log");
+                    }
+                }
             });
         }
 
         if let Some(mut stderr) = stderr {
+            let mut encoder = LogEncoder::new(self.log_format, LogStream::Stderr);
             tokio::spawn(async move {
                 let mut buf = [0u8; 1024];
 
@@ -140,13 +164,24 @@ impl ProcessMonitor for NfsServerMonitor {
                     if n == 0 {
                         break;
                     }
-                    if let Err(e) = stderr_writer.write_all(&buf[..n]) {
+                    if let Err(e) = stderr_writer.write_all(&encoder.encode(&buf[..n])) {
                         tracing::error!(pid = pid, error = %e, "Failed to write to nfs server stderr log");
                     }
                     if let Err(e) = stderr_writer.flush() {
                         tracing::error!(pid = pid, error = %e, "Failed to flush nfs server stderr log");
                     }
                 }
+
+                // Write out an unterminated last line
+                let rest = encoder.finish();
+                if !rest.is_empty() {
+                    if let Err(e) = stderr_writer
+                        .write_all(&rest)
+                        .and_then(|_| stderr_writer.flush())
+                    {
+                        tracing::error!(pid = pid, error = %e, "Failed to write to nfs server stderr log");
+                    }
+                }
             });
         }
 
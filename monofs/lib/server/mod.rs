@@ -57,7 +57,12 @@
 //!
 //! All operations are implemented in a thread-safe manner, allowing concurrent access
 //! from multiple NFS clients.
+//!
+//! With the `mount` feature enabled, [`MonofsServer::mount`] starts the server and mounts it on a
+//! local directory in one step.
 
+#[cfg(feature = "mount")]
+mod mount;
 mod nfs;
 mod server;
 
@@ -65,5 +70,7 @@ mod server;
 // Exports
 //--------------------------------------------------------------------------------------------------
 
+#[cfg(feature = "mount")]
+pub use mount::*;
 pub use nfs::*;
 pub use server::*;
@@ -0,0 +1,254 @@
+use std::{
+    io,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use getset::Getters;
+use nfsserve::tcp::{NFSTcp, NFSTcpListener};
+use tokio::{fs, runtime::Handle, task::JoinHandle};
+
+use crate::{
+    management::{mount_fs, unmount_command, unmount_fs},
+    FsError, FsResult,
+};
+
+use super::MonofsServer;
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// A [`MonofsServer`] mounted on a local directory, as returned by [`MonofsServer::mount`].
+///
+/// The filesystem is unmounted and the server is stopped when the guard is dropped. Dropping the
+/// guard inside a Tokio runtime does this in the background on the blocking pool, so use
+/// [`unmount`][MountGuard::unmount] to wait for the unmount and handle any errors.
+#[derive(Debug, Getters)]
+pub struct MountGuard {
+    /// The directory the filesystem is mounted on.
+    #[getset(get = "pub with_prefix")]
+    mount_dir: PathBuf,
+
+    /// The task running the server, until it is stopped.
+    server: Option<JoinHandle<io::Result<()>>>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl MonofsServer {
+    /// Starts the NFS server in the background and mounts it on `mount_dir` with the platform's
+    /// `mount` command.
+    ///
+    /// Mounting usually requires elevated privileges.
+    ///
+    /// ## Arguments
+    /// * `mount_dir` - An existing, empty directory to mount the filesystem on
+    ///
+    /// ## Errors
+    /// * `FsError::MountPointNotFound` - `mount_dir` does not exist
+    /// * `FsError::NotADirectory` - `mount_dir` is not a directory
+    /// * `FsError::AlreadyMounted` - A filesystem is already mounted on `mount_dir`
+    /// * `FsError::MountPointNotEmpty` - `mount_dir` is not empty
+    /// * `FsError::MountFailed` - The `mount` command failed
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// use monofs::server::MonofsServer;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let server = MonofsServer::new("/path/to/store", "127.0.0.1", 2049);
+    /// let guard = server.mount("/path/to/mount").await?;
+    ///
+    /// // The filesystem is available at /path/to/mount until it is unmounted
+    /// guard.unmount().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn mount(&self, mount_dir: impl AsRef<Path>) -> FsResult<MountGuard> {
+        let mount_dir = mount_dir.as_ref();
+        check_mount_dir(mount_dir).await?;
+
+        // Start the server before mounting so the mount command can reach it
+        let addr = format!("{}:{}", self.get_host(), self.get_port());
+        let listener = NFSTcpListener::bind(&addr, self.create_fs().await?).await?;
+        let server = tokio::spawn(async move { listener.handle_forever().await });
+
+        if let Err(e) = mount_fs(mount_dir, self.get_host(), *self.get_port()).await {
+            server.abort();
+            return Err(e);
+        }
+
+        Ok(MountGuard {
+            mount_dir: mount_dir.to_path_buf(),
+            server: Some(server),
+        })
+    }
+}
+
+impl MountGuard {
+    /// Unmounts the filesystem and stops the server.
+    pub async fn unmount(mut self) -> FsResult<()> {
+        let result = unmount_fs(&self.mount_dir, false).await;
+        if let Some(server) = self.server.take() {
+            server.abort();
+        }
+
+        result
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Trait Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl Drop for MountGuard {
+    fn drop(&mut self) {
+        let Some(server) = self.server.take() else {
+            return;
+        };
+
+        // Drop can't await, so the unmount command runs on the blocking pool instead of stalling
+        // the runtime thread. The server is stopped once the unmount is done, since unmounting
+        // still talks to it.
+        let mount_dir = self.mount_dir.clone();
+        let unmount = move || {
+            match unmount_command(&mount_dir, false).status() {
+                Ok(status) if status.success() => {
+                    tracing::info!("unmounted filesystem at {}", mount_dir.display());
+                }
+                Ok(status) => tracing::error!(
+                    "failed to unmount filesystem at {}: unmount command exited with status: {}",
+                    mount_dir.display(),
+                    status
+                ),
+                Err(e) => tracing::error!(
+                    "failed to unmount filesystem at {}: {}",
+                    mount_dir.display(),
+                    e
+                ),
+            }
+
+            server.abort();
+        };
+
+        match Handle::try_current() {
+            Ok(handle) => {
+                handle.spawn_blocking(unmount);
+            }
+            Err(_) => unmount(),
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Checks that `mount_dir` is an existing directory that nothing is mounted on.
+async fn check_mount_dir(mount_dir: &Path) -> FsResult<()> {
+    let metadata = match fs::metadata(mount_dir).await {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(FsError::MountPointNotFound(
+                mount_dir.to_string_lossy().to_string(),
+            ));
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    if !metadata.is_dir() {
+        return Err(FsError::NotADirectory(
+            mount_dir.to_string_lossy().to_string(),
+        ));
+    }
+
+    if is_mount_point(mount_dir).await? {
+        return Err(FsError::AlreadyMounted(
+            mount_dir.to_string_lossy().to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns `true` if a filesystem is mounted on `dir`, which is the case when `dir` is on a
+/// different device than its parent.
+async fn is_mount_point(dir: &Path) -> FsResult<bool> {
+    let dir = fs::canonicalize(dir).await?;
+    let Some(parent) = dir.parent() else {
+        return Ok(true); // The root directory
+    };
+
+    let dir_dev = fs::metadata(&dir).await?.dev();
+    let parent_dev = fs::metadata(parent).await?.dev();
+    Ok(dir_dev != parent_dev)
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use crate::management::mount_command;
+
+    use super::*;
+
+    #[test]
+    fn test_mount_command() {
+        let cmd = mount_command(Path::new("/mnt/mfs"), "127.0.0.1", 2049);
+        let args = cmd.get_args().collect::<Vec<_>>();
+
+        assert_eq!(cmd.get_program(), "mount");
+        assert_eq!(
+            args,
+            [
+                "-t",
+                "nfs",
+                "-o",
+                "nolocks,vers=3,tcp,port=2049,mountport=2049,soft",
+                "127.0.0.1:/",
+                "/mnt/mfs"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unmount_command() {
+        let cmd = unmount_command(Path::new("/mnt/mfs"), false);
+        assert_eq!(cmd.get_program(), "umount");
+        assert_eq!(cmd.get_args().collect::<Vec<_>>(), ["/mnt/mfs"]);
+
+        let cmd = unmount_command(Path::new("/mnt/mfs"), true);
+        assert_eq!(cmd.get_args().collect::<Vec<_>>(), ["-f", "/mnt/mfs"]);
+    }
+
+    #[tokio::test]
+    async fn test_mount_checks_mount_dir() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let server = MonofsServer::new(temp_dir.path().join("store"), "127.0.0.1", 0);
+
+        // Missing mount point
+        let result = server.mount(temp_dir.path().join("missing")).await;
+        assert!(matches!(result, Err(FsError::MountPointNotFound(_))));
+
+        // Mount point that is a file
+        let file_path = temp_dir.path().join("file");
+        fs::write(&file_path, b"not a directory").await?;
+        let result = server.mount(&file_path).await;
+        assert!(matches!(result, Err(FsError::NotADirectory(_))));
+
+        // Mount points are told apart from plain directories
+        assert!(!is_mount_point(temp_dir.path()).await?);
+        assert!(is_mount_point(Path::new("/")).await?);
+
+        Ok(())
+    }
+}
@@ -1,17 +1,10 @@
-use std::{
-    collections::HashMap,
-    str,
-    sync::{
-        atomic::{AtomicU64, Ordering},
-        Arc,
-    },
-};
+use std::{path::Path, str, sync::Arc};
 
 use async_trait::async_trait;
-use chrono::{TimeZone, Utc};
+use chrono::{DateTime, TimeZone, Utc};
 use getset::Getters;
-use intaglio::{Symbol, SymbolTable};
-use ipldstore::{ipld::ipld::Ipld, IpldStore, IpldStoreSeekable, MemoryStore, Storable};
+use ipldstore::{ipld::ipld::Ipld, IpldStore, IpldStoreSeekable, MemoryStore};
+use monoutils::{path, FileidMap};
 use nfsserve::{
     nfs::{
         fattr3, fileid3, filename3, ftype3, nfspath3, nfsstat3, nfstime3, sattr3, set_atime,
@@ -23,11 +16,11 @@ use tokio::sync::Mutex;
 
 use crate::{
     filesystem::{
-        Dir, Entity, EntityType, File, Metadata, SymPathLink, UNIX_ATIME_KEY, UNIX_GID_KEY,
-        UNIX_MODE_KEY, UNIX_UID_KEY,
+        Dir, Entity, EntityType, File, Metadata, SpecialFileType, SymPathLink, UNIX_ATIME_KEY,
+        UNIX_GID_KEY, UNIX_MODE_KEY, UNIX_RDEV_MAJOR_KEY, UNIX_RDEV_MINOR_KEY, UNIX_UID_KEY,
     },
     store::FlatFsStore,
-    FsError,
+    FsError, FsResult,
 };
 
 //--------------------------------------------------------------------------------------------------
@@ -46,6 +39,16 @@ pub const DEFAULT_DIR_MODE: u32 = 0o755;
 /// Equivalent to 777 in octal (rwxrwxrwx).
 pub const DEFAULT_SYMLINK_MODE: u32 = 0o777;
 
+// nfsserve decodes the `AUTH_UNIX` credentials of each request, but never passes them to the
+// `NFSFileSystem` methods, so entities can't be owned by the client that created them. Every
+// entity without a recorded owner is reported as owned by these IDs instead.
+
+/// Default owner user ID of entities with no recorded owner.
+pub const DEFAULT_OWNER_UID: u32 = 507;
+
+/// Default owner group ID of entities with no recorded owner.
+pub const DEFAULT_OWNER_GID: u32 = 507;
+
 //--------------------------------------------------------------------------------------------------
 // Types
 //--------------------------------------------------------------------------------------------------
@@ -101,10 +104,8 @@ where
     S: IpldStore + Send + Sync + 'static,
 {
     root: Arc<Mutex<Dir<S>>>,
-    next_fileid: AtomicU64,
-    filenames: Arc<Mutex<SymbolTable>>,
-    fileid_to_path_map: Arc<Mutex<HashMap<fileid3, Vec<Symbol>>>>,
-    path_to_fileid_map: Arc<Mutex<HashMap<Vec<Symbol>, fileid3>>>,
+    fileids: FileidMap,
+    read_only: bool,
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -125,108 +126,120 @@ where
     /// let server = MemoryMonofsNFS::new(MemoryStore::default());
     /// ```
     pub fn new(store: S) -> Self {
-        Self {
-            root: Arc::new(Mutex::new(Dir::new(store))),
-            filenames: Arc::new(Mutex::new(SymbolTable::new())),
-            next_fileid: AtomicU64::new(1),
-            fileid_to_path_map: Arc::new(Mutex::new(HashMap::from([(0, vec![])]))),
-            path_to_fileid_map: Arc::new(Mutex::new(HashMap::from([(vec![], 0)]))),
-        }
+        Self::from_dir(Dir::new(store))
     }
 
-    fn next_fileid(&self) -> fileid3 {
-        self.next_fileid.fetch_add(1, Ordering::SeqCst)
+    /// Creates a new MonofsNFS instance serving an existing directory tree.
+    ///
+    /// ## Example
+    /// ```rust
+    /// use monofs::{filesystem::Dir, server::MemoryMonofsNFS};
+    /// use ipldstore::MemoryStore;
+    ///
+    /// let server = MemoryMonofsNFS::from_dir(Dir::new(MemoryStore::default()));
+    /// ```
+    pub fn from_dir(root: Dir<S>) -> Self {
+        Self {
+            root: Arc::new(Mutex::new(root)),
+            fileids: FileidMap::new(),
+            read_only: false,
+        }
     }
 
-    /// Converts a file ID to its corresponding path by looking up the symbols in the mapping
-    /// and converting them back to strings.
-    async fn fileid_to_path(&self, id: fileid3) -> Result<String, nfsstat3> {
-        let fileid_to_path_map = self.fileid_to_path_map.lock().await;
-        let symbols = fileid_to_path_map.get(&id).ok_or(nfsstat3::NFS3ERR_NOENT)?;
-        let filenames = self.filenames.lock().await;
-
-        let path = symbols
-            .iter()
-            .map(|s| filenames.get(*s).ok_or(nfsstat3::NFS3ERR_STALE))
-            .collect::<Result<Vec<_>, _>>()?
-            .join("/");
-        Ok(path)
+    /// Persists the fileids issued by the server in an append-only log at `path`.
+    ///
+    /// Fileids recorded by a previous run are restored and new fileids are allocated after them,
+    /// so file handles held by clients stay valid when the server is restarted over the same
+    /// directory tree. Fileids of removed entities keep reporting `NFS3ERR_STALE`. The log is
+    /// created if it does not exist, and compacted when it is opened and whenever it has doubled
+    /// in size since.
+    ///
+    /// ## Errors
+    /// Returns an error if the log cannot be read, compacted or opened for appending.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// use monofs::server::MemoryMonofsNFS;
+    /// use ipldstore::MemoryStore;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let server = MemoryMonofsNFS::new(MemoryStore::default())
+    ///     .with_fileid_log("/path/to/fileids.log")
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn with_fileid_log(mut self, path: impl AsRef<Path>) -> FsResult<Self> {
+        self.fileids = self.fileids.with_log(path).await?;
+        Ok(self)
     }
 
-    /// Converts a path string into a vector of symbols.
-    /// Each path component is converted to a symbol using the server's symbol table.
-    /// Empty path components are filtered out.
-    async fn path_to_symbols(&self, path: impl AsRef<str>) -> Result<Vec<Symbol>, nfsstat3> {
-        let path = path.as_ref();
-
-        // Split path into segments, filtering out empty segments
-        let segments: Vec<String> = path
-            .split('/')
-            .filter(|s| !s.is_empty())
-            .map(|s| s.to_string())
-            .collect();
-
-        // Convert segments to symbols
-        let mut path_symbols = Vec::with_capacity(segments.len());
-        let mut filenames = self.filenames.lock().await;
-
-        for segment in segments {
-            let symbol = filenames
-                .intern(segment)
-                .map_err(|_| nfsstat3::NFS3ERR_INVAL)?;
-            path_symbols.push(symbol);
-        }
-        drop(filenames);
-
-        Ok(path_symbols)
+    /// Limits the number of fileids the server keeps track of, besides the root's, to
+    /// `max_fileids`.
+    ///
+    /// When the limit is exceeded, the fileids of the least recently used paths are evicted. The
+    /// server remembers up to `max_fileids` evicted fileids, which keep resolving and keep their
+    /// paths' inode numbers. Fileids evicted beyond that are forgotten: they report
+    /// `NFS3ERR_STALE`, which makes clients look their paths up again, readdir cookies pointing at
+    /// them report `NFS3ERR_BAD_COOKIE`, and their paths are given new fileids when they are next
+    /// looked up. The limit should exceed the number of entries clients work with at once, such
+    /// as the entries of a directory being listed. By default the number of fileids is not
+    /// limited.
+    ///
+    /// The server also remembers the last paths of up to `max_fileids` removed entities, or
+    /// [`DEFAULT_MAX_REMOVED_FILEIDS`][monoutils::DEFAULT_MAX_REMOVED_FILEIDS] if the number of
+    /// fileids is not limited.
+    ///
+    /// ## Example
+    /// ```rust
+    /// use monofs::server::MemoryMonofsNFS;
+    /// use ipldstore::MemoryStore;
+    ///
+    /// let server = MemoryMonofsNFS::new(MemoryStore::default()).with_max_fileids(100_000);
+    /// assert_eq!(server.get_max_fileids(), Some(100_000));
+    /// ```
+    pub fn with_max_fileids(mut self, max_fileids: usize) -> Self {
+        self.fileids = self.fileids.with_max_fileids(max_fileids);
+        self
     }
 
-    /// Gets the fileid for a registered path if it exists.
-    /// This is a convenience wrapper around `get_path_registered` that takes a string path.
-    async fn get_path_registered_str(
-        &self,
-        path: impl AsRef<str>,
-    ) -> Result<Option<fileid3>, nfsstat3> {
-        let path_symbols = self.path_to_symbols(path).await?;
-        self.get_path_registered(&path_symbols).await
+    /// Returns the maximum number of fileids the server keeps track of besides the root's, if
+    /// limited.
+    pub fn get_max_fileids(&self) -> Option<usize> {
+        self.fileids.get_max_fileids()
     }
 
-    /// Gets the fileid for a registered path if it exists.
-    /// This is the core path lookup function that works directly with symbols.
-    async fn get_path_registered(
-        &self,
-        path_symbols: &[Symbol],
-    ) -> Result<Option<fileid3>, nfsstat3> {
-        let path_to_fileid_map = self.path_to_fileid_map.lock().await;
-        Ok(path_to_fileid_map.get(path_symbols).copied())
+    /// Sets whether the filesystem is exported read-only.
+    ///
+    /// A read-only export reports `VFSCapabilities::ReadOnly` and rejects every mutating
+    /// operation with `NFS3ERR_ROFS`, which makes it safe to expose a snapshot.
+    ///
+    /// ## Example
+    /// ```rust
+    /// use monofs::server::MemoryMonofsNFS;
+    /// use ipldstore::MemoryStore;
+    ///
+    /// let server = MemoryMonofsNFS::new(MemoryStore::default()).with_read_only(true);
+    /// assert!(server.is_read_only());
+    /// ```
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
     }
 
-    /// Ensures a path is registered in the path-fileid mapping system and returns its fileid.
-    /// This is a convenience wrapper around `ensure_path_registered` that takes a string path.
-    async fn ensure_path_registered_str(&self, path: impl AsRef<str>) -> Result<fileid3, nfsstat3> {
-        let path_symbols = self.path_to_symbols(path).await?;
-        self.ensure_path_registered(&path_symbols).await
+    /// Returns `true` if the filesystem is exported read-only.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
     }
 
-    /// Ensures a path is registered in the path-fileid mapping system and returns its fileid.
-    /// This is the core path registration function that works directly with symbols.
-    /// If the path is already registered, returns the existing fileid.
-    /// If not, creates a new fileid and registers the bidirectional mappings.
-    async fn ensure_path_registered(&self, path_symbols: &[Symbol]) -> Result<fileid3, nfsstat3> {
-        // First check if the path is already registered
-        if let Some(existing_id) = self.get_path_registered(path_symbols).await? {
-            return Ok(existing_id);
+    /// Returns `NFS3ERR_ROFS` if the filesystem is exported read-only.
+    fn check_writable(&self) -> Result<(), nfsstat3> {
+        if self.read_only {
+            return Err(nfsstat3::NFS3ERR_ROFS);
         }
 
-        // Create new mapping
-        let fileid = self.next_fileid();
-        let mut fileid_to_path_map = self.fileid_to_path_map.lock().await;
-        let mut path_to_fileid_map = self.path_to_fileid_map.lock().await;
-
-        fileid_to_path_map.insert(fileid, path_symbols.to_vec());
-        path_to_fileid_map.insert(path_symbols.to_vec(), fileid);
-
-        Ok(fileid)
+        Ok(())
     }
 
     /// Helper method to update attributes on an entity's metadata
@@ -312,6 +325,10 @@ where
                 EntityType::File => ftype3::NF3REG,
                 EntityType::Dir => ftype3::NF3DIR,
                 EntityType::SymCidLink | EntityType::SymPathLink => ftype3::NF3LNK,
+                EntityType::Special(SpecialFileType::CharDevice) => ftype3::NF3CHR,
+                EntityType::Special(SpecialFileType::BlockDevice) => ftype3::NF3BLK,
+                EntityType::Special(SpecialFileType::Fifo) => ftype3::NF3FIFO,
+                EntityType::Special(SpecialFileType::Socket) => ftype3::NF3SOCK,
             },
             // Default mode is 0o755 (rwxr-xr-x) if not set or invalid
             mode: metadata
@@ -330,12 +347,12 @@ where
                     _ => None,
                 })
                 .unwrap_or(match metadata.get_entity_type() {
-                    EntityType::File => DEFAULT_FILE_MODE,
+                    EntityType::File | EntityType::Special(_) => DEFAULT_FILE_MODE,
                     EntityType::Dir => DEFAULT_DIR_MODE,
                     EntityType::SymCidLink | EntityType::SymPathLink => DEFAULT_SYMLINK_MODE,
                 }),
             nlink: 1, // We don't support hard links
-            // Default uid is 0 (root) if not set or invalid
+            // Default uid is `DEFAULT_OWNER_UID` if not set or invalid
             uid: metadata
                 .get_attribute(UNIX_UID_KEY)
                 .await
@@ -351,8 +368,8 @@ where
                     }
                     _ => None,
                 })
-                .unwrap_or(507), // TODO: Maybe use the uid of the user that made the request
-            // Default gid is 0 (root) if not set or invalid
+                .unwrap_or(DEFAULT_OWNER_UID),
+            // Default gid is `DEFAULT_OWNER_GID` if not set or invalid
             gid: metadata
                 .get_attribute(UNIX_GID_KEY)
                 .await
@@ -368,27 +385,23 @@ where
                     }
                     _ => None,
                 })
-                .unwrap_or(507), // TODO: Maybe use the gid of the user that made the request
+                .unwrap_or(DEFAULT_OWNER_GID),
             size,
             used: 0, // TODO: Space used is not tracked
+            // Device numbers are only set on device nodes
             rdev: specdata3 {
-                specdata1: 0,
-                specdata2: 0,
+                specdata1: get_u32_attribute(metadata, UNIX_RDEV_MAJOR_KEY)
+                    .await?
+                    .unwrap_or(0),
+                specdata2: get_u32_attribute(metadata, UNIX_RDEV_MINOR_KEY)
+                    .await?
+                    .unwrap_or(0),
             },
             fsid: 0,    // Single filesystem
             fileid: id, // Use the provided fileid
-            atime: nfstime3 {
-                seconds: metadata.get_created_at().timestamp() as u32,
-                nseconds: 0,
-            },
-            mtime: nfstime3 {
-                seconds: metadata.get_modified_at().timestamp() as u32,
-                nseconds: 0,
-            },
-            ctime: nfstime3 {
-                seconds: metadata.get_created_at().timestamp() as u32,
-                nseconds: 0,
-            },
+            atime: to_nfstime(metadata.get_created_at()),
+            mtime: to_nfstime(metadata.get_modified_at()),
+            ctime: to_nfstime(metadata.get_created_at()),
         })
     }
 }
@@ -406,8 +419,16 @@ where
         0
     }
 
+    // nfsserve answers ACCESS requests itself and has no `NFSFileSystem` method for them. It grants
+    // every requested permission, masked to read and lookup when this reports `ReadOnly`, so
+    // per-file permission checks against the mode bits can't be implemented here. Writes to a
+    // read-only export are still rejected by the mutating operations with `NFS3ERR_ROFS`.
     fn capabilities(&self) -> VFSCapabilities {
-        VFSCapabilities::ReadWrite
+        if self.read_only {
+            VFSCapabilities::ReadOnly
+        } else {
+            VFSCapabilities::ReadWrite
+        }
     }
 
     async fn lookup(&self, dirid: fileid3, filename: &filename3) -> Result<fileid3, nfsstat3> {
@@ -422,7 +443,7 @@ where
         }
 
         // Get parent directory path
-        let parent_path = self.fileid_to_path(dirid).await?;
+        let parent_path = self.fileids.fileid_to_path(dirid).await?;
 
         // Get root directory
         let root = self.root.lock().await;
@@ -433,32 +454,32 @@ where
         let parent_dir = if parent_path.is_empty() {
             &*root
         } else {
-            match root.find(&parent_path).await? {
+            match find_following_links(&root, &parent_path, true).await? {
                 Some(Entity::Dir(dir)) => dir,
                 Some(_) => return Err(nfsstat3::NFS3ERR_NOTDIR),
                 None => return Err(nfsstat3::NFS3ERR_NOENT),
             }
         };
 
-        // Check if the entry exists
-        if !parent_dir.has_entity(filename_str).await? {
+        // Construct full path, resolving `.` and `..`
+        let full_path = join_path(&parent_path, filename_str)?;
+
+        // Check if the entry exists. `.` and `..` resolve to directories that do
+        if is_entry_name(filename_str) && !parent_dir.has_entity(filename_str).await? {
             return Err(nfsstat3::NFS3ERR_NOENT);
         }
 
         drop(root);
 
-        // Construct full path
-        let full_path = join_path(&parent_path, filename_str);
-
         // Ensure path is registered and get its fileid
-        self.ensure_path_registered_str(&full_path).await
+        self.fileids.ensure_path_registered(&full_path).await
     }
 
     async fn getattr(&self, id: fileid3) -> Result<fattr3, nfsstat3> {
         tracing::trace!("getattr: id: {}", id);
 
         // Get path from fileid
-        let path = self.fileid_to_path(id).await?;
+        let path = self.fileids.fileid_to_path(id).await?;
 
         // Get root directory
         let root = self.root.lock().await;
@@ -467,7 +488,9 @@ where
         let (metadata, size) = if path.is_empty() {
             (root.get_metadata(), 0)
         } else {
-            let entity = root.find(&path).await?.ok_or(nfsstat3::NFS3ERR_NOENT)?;
+            let entity = find_following_links(&root, &path, true)
+                .await?
+                .ok_or(nfsstat3::NFS3ERR_NOENT)?;
             (entity.get_metadata(), entity.get_size().await?)
         };
 
@@ -478,8 +501,10 @@ where
     async fn setattr(&self, id: fileid3, setattr: sattr3) -> Result<fattr3, nfsstat3> {
         tracing::trace!("setattr: id: {}, setattr: {:?}", id, setattr);
 
+        self.check_writable()?;
+
         // Get path from fileid
-        let path = self.fileid_to_path(id).await?;
+        let path = self.fileids.fileid_to_path(id).await?;
 
         // Get root directory
         let mut root = self.root.lock().await;
@@ -509,7 +534,7 @@ where
         tracing::trace!("read: id: {}, offset: {}, count: {}", id, offset, count);
 
         // Get path from fileid
-        let path = self.fileid_to_path(id).await?;
+        let path = self.fileids.fileid_to_path(id).await?;
 
         // Get root directory
         let root = self.root.lock().await;
@@ -518,7 +543,9 @@ where
         let entity = if path.is_empty() {
             return Err(nfsstat3::NFS3ERR_INVAL); // Root cannot be read
         } else {
-            root.find(&path).await?.ok_or(nfsstat3::NFS3ERR_NOENT)?
+            find_following_links(&root, &path, true)
+                .await?
+                .ok_or(nfsstat3::NFS3ERR_NOENT)?
         };
 
         // Ensure it's a file and read its content
@@ -526,6 +553,14 @@ where
             Entity::File(file) => {
                 use tokio::io::{AsyncReadExt, AsyncSeekExt, SeekFrom};
 
+                // Special files have no content of their own
+                if matches!(
+                    file.get_metadata().get_entity_type(),
+                    EntityType::Special(_)
+                ) {
+                    return Err(nfsstat3::NFS3ERR_INVAL);
+                }
+
                 if offset >= file.get_size().await? {
                     return Ok((Vec::new(), true));
                 }
@@ -544,15 +579,16 @@ where
                         nfsstat3::NFS3ERR_IO
                     })?;
 
-                // Read requested bytes
-                let mut buffer = vec![0; count as usize];
-                let bytes_read = input_stream.read(&mut buffer).await.map_err(|e| {
-                    tracing::error!("Failed to read: {}", e);
-                    nfsstat3::NFS3ERR_IO
-                })?;
-
-                // Truncate buffer to actual bytes read
-                buffer.truncate(bytes_read);
+                // Read requested bytes, which may span several chunks
+                let mut buffer = Vec::with_capacity(count as usize);
+                (&mut input_stream)
+                    .take(count as u64)
+                    .read_to_end(&mut buffer)
+                    .await
+                    .map_err(|e| {
+                        tracing::error!("Failed to read: {}", e);
+                        nfsstat3::NFS3ERR_IO
+                    })?;
 
                 // Check if we've reached the end by trying to read one more byte
                 let mut peek_buf = [0u8; 1];
@@ -570,8 +606,10 @@ where
     async fn write(&self, id: fileid3, offset: u64, data: &[u8]) -> Result<fattr3, nfsstat3> {
         tracing::trace!("write: id: {}, offset: {}, data: {:?}", id, offset, data);
 
+        self.check_writable()?;
+
         // Get path from fileid
-        let path = self.fileid_to_path(id).await?;
+        let path = self.fileids.fileid_to_path(id).await?;
 
         // Get root directory
         let mut root = self.root.lock().await;
@@ -586,7 +624,13 @@ where
         // Ensure it's a file and write its content
         match entity {
             Entity::File(file) => {
-                use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, SeekFrom};
+                // Special files have no content of their own
+                if matches!(
+                    file.get_metadata().get_entity_type(),
+                    EntityType::Special(_)
+                ) {
+                    return Err(nfsstat3::NFS3ERR_INVAL);
+                }
 
                 // Get original file size
                 let original_size = file.get_size().await.map_err(|e| {
@@ -601,85 +645,17 @@ where
                 }
 
                 // First checkpoint the file to create a versioned copy
-                let checkpoint_cid = file.checkpoint().await.map_err(|e| {
+                file.checkpoint().await.map_err(|e| {
                     tracing::error!("Failed to checkpoint file: {}", e);
                     nfsstat3::NFS3ERR_IO
                 })?;
 
-                // Load the checkpointed version as our original file
-                let original_file = File::load(&checkpoint_cid, file.get_store().clone())
-                    .await
-                    .map_err(|e| {
-                        tracing::error!("Failed to load checkpointed file: {}", e);
-                        nfsstat3::NFS3ERR_IO
-                    })?;
-
-                // Create output stream for the new version
-                let mut output = file.get_output_stream();
-
-                // If we're not writing at the start, copy existing data up to offset
-                if offset > 0 {
-                    let mut input = original_file.get_input_stream().await.map_err(|e| {
-                        tracing::error!("Failed to get input stream: {}", e);
-                        nfsstat3::NFS3ERR_IO
-                    })?;
-
-                    // Copy data up to offset
-                    let mut buffer = vec![0u8; offset as usize];
-                    let bytes_read = input.read(&mut buffer).await.map_err(|e| {
-                        tracing::error!("Failed to read existing data: {}", e);
-                        nfsstat3::NFS3ERR_IO
-                    })?;
-                    buffer.truncate(bytes_read);
-
-                    output.write_all(&buffer).await.map_err(|e| {
-                        tracing::error!("Failed to write existing data: {}", e);
-                        nfsstat3::NFS3ERR_IO
-                    })?;
-                }
-
-                // Write the new data
-                output.write_all(data).await.map_err(|e| {
-                    tracing::error!("Failed to write new data: {}", e);
-                    nfsstat3::NFS3ERR_IO
-                })?;
-
-                // If there's existing data after our write, append it
-                let end_offset = offset + data.len() as u64;
-                if end_offset < original_size {
-                    let mut input = original_file.get_input_stream().await.map_err(|e| {
-                        tracing::error!("Failed to get input stream: {}", e);
-                        nfsstat3::NFS3ERR_IO
-                    })?;
-
-                    // Seek to where we ended our write
-                    input.seek(SeekFrom::Start(end_offset)).await.map_err(|e| {
-                        tracing::error!("Failed to seek input stream: {}", e);
-                        nfsstat3::NFS3ERR_IO
-                    })?;
-
-                    // Read and write the remaining data
-                    let mut buffer = vec![0u8; (original_size - end_offset) as usize];
-                    let bytes_read = input.read(&mut buffer).await.map_err(|e| {
-                        tracing::error!("Failed to read remaining data: {}", e);
-                        nfsstat3::NFS3ERR_IO
-                    })?;
-                    buffer.truncate(bytes_read);
-
-                    output.write_all(&buffer).await.map_err(|e| {
-                        tracing::error!("Failed to write remaining data: {}", e);
-                        nfsstat3::NFS3ERR_IO
-                    })?;
-                }
-
-                // Finalize the write
-                output.flush().await.map_err(|e| {
-                    tracing::error!("Failed to finalize write: {}", e);
+                // Write the new data, rewriting only the chunks it overlaps
+                file.write_at(offset, data).await.map_err(|e| {
+                    tracing::error!("Failed to write data: {}", e);
                     nfsstat3::NFS3ERR_IO
                 })?;
 
-                drop(output);
-
                 // Get updated attributes
                 let final_size = file.get_size().await.map_err(|e| {
                     tracing::error!("Failed to get final file size: {}", e);
@@ -704,16 +680,19 @@ where
             filename,
             attr
         );
+
+        self.check_writable()?;
+
         // Convert filename bytes to string, ensuring valid UTF-8
         let filename_str = str::from_utf8(filename).map_err(|_| nfsstat3::NFS3ERR_INVAL)?;
 
-        // Validate filename doesn't contain path separators
-        if filename_str.contains('/') {
+        // Validate filename is a single entry name
+        if !is_entry_name(filename_str) {
             return Err(nfsstat3::NFS3ERR_INVAL);
         }
 
         // Get parent directory path
-        let parent_path = self.fileid_to_path(dirid).await?;
+        let parent_path = self.fileids.fileid_to_path(dirid).await?;
 
         // Get root directory
         let mut root = self.root.lock().await;
@@ -761,10 +740,10 @@ where
         drop(root);
 
         // Construct full path and ensure it is registered
-        let full_path = join_path(&parent_path, filename_str);
+        let full_path = join_path(&parent_path, filename_str)?;
 
         // Ensure path is registered and get its fileid
-        let fileid = self.ensure_path_registered_str(&full_path).await?;
+        let fileid = self.fileids.ensure_path_registered(&full_path).await?;
 
         // Get the attributes of the created file
         let attrs = self.getattr(fileid).await?;
@@ -782,16 +761,19 @@ where
             dirid,
             filename
         );
+
+        self.check_writable()?;
+
         // Convert filename bytes to string, ensuring valid UTF-8
         let filename_str = str::from_utf8(filename).map_err(|_| nfsstat3::NFS3ERR_INVAL)?;
 
-        // Validate filename doesn't contain path separators
-        if filename_str.contains('/') {
+        // Validate filename is a single entry name
+        if !is_entry_name(filename_str) {
             return Err(nfsstat3::NFS3ERR_INVAL);
         }
 
         // Get parent directory path
-        let parent_path = self.fileid_to_path(dirid).await?;
+        let parent_path = self.fileids.fileid_to_path(dirid).await?;
 
         // Get root directory
         let mut root = self.root.lock().await;
@@ -829,10 +811,10 @@ where
         drop(root);
 
         // Construct full path and ensure it is registered
-        let full_path = join_path(&parent_path, filename_str);
+        let full_path = join_path(&parent_path, filename_str)?;
 
         // Ensure path is registered and get its fileid
-        self.ensure_path_registered_str(&full_path).await
+        self.fileids.ensure_path_registered(&full_path).await
     }
 
     async fn mkdir(
@@ -841,16 +823,19 @@ where
         dirname: &filename3,
     ) -> Result<(fileid3, fattr3), nfsstat3> {
         tracing::trace!("mkdir: dirid: {}, dirname: {:?}", dirid, dirname);
+
+        self.check_writable()?;
+
         // Convert dirname bytes to string, ensuring valid UTF-8
         let dirname_str = str::from_utf8(dirname).map_err(|_| nfsstat3::NFS3ERR_INVAL)?;
 
-        // Validate dirname doesn't contain path separators
-        if dirname_str.contains('/') {
+        // Validate dirname is a single entry name
+        if !is_entry_name(dirname_str) {
             return Err(nfsstat3::NFS3ERR_INVAL);
         }
 
         // Get parent directory path
-        let parent_path = self.fileid_to_path(dirid).await?;
+        let parent_path = self.fileids.fileid_to_path(dirid).await?;
 
         // Get root directory
         let mut root = self.root.lock().await;
@@ -888,10 +873,10 @@ where
         drop(root);
 
         // Construct full path and ensure it is registered
-        let full_path = join_path(&parent_path, dirname_str);
+        let full_path = join_path(&parent_path, dirname_str)?;
 
         // Ensure path is registered and get its fileid
-        let fileid = self.ensure_path_registered_str(&full_path).await?;
+        let fileid = self.fileids.ensure_path_registered(&full_path).await?;
 
         // Get the attributes of the created directory
         let attrs = self.getattr(fileid).await?;
@@ -902,25 +887,33 @@ where
     async fn remove(&self, dirid: fileid3, filename: &filename3) -> Result<(), nfsstat3> {
         tracing::trace!("remove: dirid: {}, filename: {:?}", dirid, filename);
 
+        self.check_writable()?;
+
         // Convert filename bytes to string, ensuring valid UTF-8
         let filename_str = str::from_utf8(filename).map_err(|_| nfsstat3::NFS3ERR_INVAL)?;
 
-        // Validate filename doesn't contain path separators
-        if filename_str.contains('/') {
+        // Validate filename is a single entry name
+        if !is_entry_name(filename_str) {
             return Err(nfsstat3::NFS3ERR_INVAL);
         }
 
         // Get parent directory path
-        let parent_path = self.fileid_to_path(dirid).await?;
+        let parent_path = self.fileids.fileid_to_path(dirid).await?;
 
         // Get root directory
         let mut root = self.root.lock().await;
 
         // Construct the full path
-        let full_path = join_path(&parent_path, filename_str);
+        let full_path = join_path(&parent_path, filename_str)?;
 
         // Use Dir's remove operation
-        root.remove(&full_path).await.map_err(nfsstat3::from)
+        root.remove(&full_path).await.map_err(nfsstat3::from)?;
+        drop(root);
+
+        // Fileids of the removed entity and its descendants are now stale
+        self.fileids.remove_path_registration(&full_path).await?;
+
+        Ok(())
     }
 
     async fn rename(
@@ -938,29 +931,38 @@ where
             to_filename
         );
 
+        self.check_writable()?;
+
         // Convert filenames to strings, ensuring valid UTF-8
         let from_filename_str =
             str::from_utf8(from_filename).map_err(|_| nfsstat3::NFS3ERR_INVAL)?;
         let to_filename_str = str::from_utf8(to_filename).map_err(|_| nfsstat3::NFS3ERR_INVAL)?;
 
-        // Validate filenames don't contain path separators
-        if from_filename_str.contains('/') || to_filename_str.contains('/') {
+        // Validate filenames are single entry names
+        if !is_entry_name(from_filename_str) || !is_entry_name(to_filename_str) {
             return Err(nfsstat3::NFS3ERR_INVAL);
         }
 
         // Get directory paths
-        let from_dir_path = self.fileid_to_path(from_dirid).await?;
-        let to_dir_path = self.fileid_to_path(to_dirid).await?;
+        let from_dir_path = self.fileids.fileid_to_path(from_dirid).await?;
+        let to_dir_path = self.fileids.fileid_to_path(to_dirid).await?;
 
         // Construct full paths
-        let from_path = join_path(&from_dir_path, from_filename_str);
-        let to_path = join_path(&to_dir_path, to_filename_str);
+        let from_path = join_path(&from_dir_path, from_filename_str)?;
+        let to_path = join_path(&to_dir_path, to_filename_str)?;
 
-        // Get root directory and use Dir's rename operation
+        // Get root directory and use Dir's rename operation, replacing any existing target
         let mut root = self.root.lock().await;
         root.rename(&from_path, &to_path)
             .await
-            .map_err(nfsstat3::from)
+            .map_err(nfsstat3::from)?;
+
+        // Keep the fileids of the renamed entities pointing at their new location
+        self.fileids
+            .move_path_registration(&from_path, &to_path)
+            .await?;
+
+        Ok(())
     }
 
     async fn readdir(
@@ -977,7 +979,26 @@ where
         );
 
         // Get path from fileid
-        let dir_path = self.fileid_to_path(dirid).await?;
+        let dir_path = self.fileids.fileid_to_path(dirid).await?;
+
+        // Find the name of the entry the cookie points at. Entries are listed in name order, so
+        // listing resumes at the right place even if entries were added or removed since,
+        // including the cookie's own entry. A cookie that isn't an entry of this directory can't
+        // be resumed from, so the client has to restart the listing.
+        let start_after_name = if start_after == 0 {
+            None
+        } else {
+            let Some(start_path) = self.fileids.cookie_to_path(start_after).await else {
+                return Err(nfsstat3::NFS3ERR_BAD_COOKIE);
+            };
+
+            let (parent, name) = start_path.rsplit_once('/').unwrap_or(("", &start_path));
+            if parent != dir_path {
+                return Err(nfsstat3::NFS3ERR_BAD_COOKIE);
+            }
+
+            Some(name.to_string())
+        };
 
         // Get root directory
         let root = self.root.lock().await;
@@ -986,51 +1007,55 @@ where
         let dir = if dir_path.is_empty() {
             &*root
         } else {
-            match root.find(&dir_path).await? {
+            match find_following_links(&root, &dir_path, true).await? {
                 Some(Entity::Dir(dir)) => dir,
                 Some(_) => return Err(nfsstat3::NFS3ERR_NOTDIR),
                 None => return Err(nfsstat3::NFS3ERR_NOENT),
             }
         };
 
+        let mut dir_entries = dir.get_entries().collect::<Vec<_>>();
+        dir_entries.sort_unstable_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
+
         let mut entries = Vec::new();
-        let mut found_start = start_after == 0;
         let mut has_more = false;
 
-        for (name, link) in dir.get_entries() {
-            // Skip entries until we find the start_after fileid
-            if !found_start {
-                let entry_path = join_path(&dir_path, name.as_str());
-
-                // Try to get existing fileid without creating a new one
-                if let Some(entry_id) = self.get_path_registered_str(&entry_path).await? {
-                    if entry_id == start_after {
-                        found_start = true;
-                    }
+        for (name, link) in dir_entries {
+            // Skip entries up to and including the one the cookie points at
+            if let Some(start_after_name) = &start_after_name {
+                if name.as_str() <= start_after_name.as_str() {
+                    continue;
                 }
-                continue;
             }
 
-            // Resolve the entity to get its metadata
+            // If we've reached max_entries, note that there are more entries and break
+            if entries.len() >= max_entries {
+                has_more = true;
+                break;
+            }
+
+            // Resolve the entity to get its metadata, presenting symbolic CID links as their target
             let entity = link.resolve_entity(dir.get_store().clone()).await?;
+            let entity = match entity {
+                Entity::SymCidLink(symlink) => match symlink.resolve().await {
+                    Ok(target) => target,
+                    Err(FsError::BrokenSymCidLink(_) | FsError::MaxFollowDepthReached) => entity,
+                    Err(e) => return Err(e.into()),
+                },
+                _ => entity,
+            };
 
             // Get the full path for this entry
-            let entry_path = join_path(&dir_path, name.as_str());
+            let entry_path = join_path(&dir_path, name.as_str())?;
 
             // Get or create fileid for this entry
-            let fileid = self.ensure_path_registered_str(&entry_path).await?;
+            let fileid = self.fileids.ensure_path_registered(&entry_path).await?;
 
             // Construct attributes for this entry
             let attr =
                 Self::construct_attributes(entity.get_metadata(), entity.get_size().await?, fileid)
                     .await?;
 
-            // If we've reached max_entries, note that there are more entries and break
-            if entries.len() >= max_entries {
-                has_more = true;
-                break;
-            }
-
             entries.push(DirEntry {
                 fileid,
                 name: filename3::from(name.as_str().as_bytes()),
@@ -1059,19 +1084,21 @@ where
             attr
         );
 
+        self.check_writable()?;
+
         // Convert linkname bytes to string, ensuring valid UTF-8
         let linkname_str = str::from_utf8(linkname).map_err(|_| nfsstat3::NFS3ERR_INVAL)?;
 
         // Convert symlink target path bytes to string
         let target_path = str::from_utf8(symlink).map_err(|_| nfsstat3::NFS3ERR_INVAL)?;
 
-        // Validate linkname doesn't contain path separators
-        if linkname_str.contains('/') {
+        // Validate linkname is a single entry name
+        if !is_entry_name(linkname_str) {
             return Err(nfsstat3::NFS3ERR_INVAL);
         }
 
         // Get parent directory path
-        let parent_path = self.fileid_to_path(dirid).await?;
+        let parent_path = self.fileids.fileid_to_path(dirid).await?;
 
         // Get root directory
         let mut root = self.root.lock().await;
@@ -1116,10 +1143,10 @@ where
         drop(root);
 
         // Construct full path and ensure it is registered
-        let full_path = join_path(&parent_path, linkname_str);
+        let full_path = join_path(&parent_path, linkname_str)?;
 
         // Ensure path is registered and get its fileid
-        let fileid = self.ensure_path_registered_str(&full_path).await?;
+        let fileid = self.fileids.ensure_path_registered(&full_path).await?;
 
         // Get the attributes of the created symlink
         let attrs = self.getattr(fileid).await?;
@@ -1131,7 +1158,7 @@ where
         tracing::trace!("readlink: id: {}", id);
 
         // Get path from fileid
-        let path = self.fileid_to_path(id).await?;
+        let path = self.fileids.fileid_to_path(id).await?;
 
         // Get root directory
         let root = self.root.lock().await;
@@ -1140,7 +1167,9 @@ where
         let entity = if path.is_empty() {
             return Err(nfsstat3::NFS3ERR_INVAL); // Root cannot be a symlink
         } else {
-            root.find(&path).await?.ok_or(nfsstat3::NFS3ERR_NOENT)?
+            find_following_links(&root, &path, false)
+                .await?
+                .ok_or(nfsstat3::NFS3ERR_NOENT)?
         };
 
         // Ensure it's a symlink and get the target path
@@ -1149,6 +1178,11 @@ where
                 let target_path = symlink.get_target_path().as_str();
                 Ok(nfspath3::from(target_path.as_bytes()))
             }
+            Entity::SymCidLink(symlink) => {
+                // A CID link has no target path, so its target CID serves as a stable stand-in
+                let target_cid = symlink.get_cid().await?.to_string();
+                Ok(nfspath3::from(target_cid.as_bytes()))
+            }
             _ => Err(nfsstat3::NFS3ERR_INVAL),
         }
     }
@@ -1163,9 +1197,13 @@ impl From<FsError> for nfsstat3 {
             FsError::IpldStore(_) => nfsstat3::NFS3ERR_IO,
             FsError::NotAFile(_) => nfsstat3::NFS3ERR_NOTDIR,
             FsError::NotADirectory(_) => nfsstat3::NFS3ERR_NOTDIR,
+            FsError::IsADirectory(_) => nfsstat3::NFS3ERR_ISDIR,
+            FsError::DirectoryNotEmpty(_) => nfsstat3::NFS3ERR_NOTEMPTY,
+            FsError::PathExists(_) => nfsstat3::NFS3ERR_EXIST,
             FsError::NotASymCidLink(_) => nfsstat3::NFS3ERR_INVAL,
             FsError::NotASymPathLink(_) => nfsstat3::NFS3ERR_INVAL,
             FsError::BrokenSymCidLink(_) => nfsstat3::NFS3ERR_NOENT,
+            FsError::MaxFollowDepthReached => nfsstat3::NFS3ERR_INVAL,
             _ => nfsstat3::NFS3ERR_IO,
         }
     }
@@ -1175,20 +1213,105 @@ impl From<FsError> for nfsstat3 {
 // Functions
 //--------------------------------------------------------------------------------------------------
 
-fn join_path(base_path: &str, name: &str) -> String {
-    if base_path.is_empty() {
-        name.to_string()
-    } else {
-        format!("{}/{}", base_path, name)
+/// Finds the entity at `path` relative to `root`, following symbolic CID links along the way.
+///
+/// Links in intermediate components are always followed. A link in the last component is only
+/// followed if `follow_last` is `true`, so callers can still inspect the link itself.
+///
+/// ## Errors
+/// * `FsError::NotADirectory` - An intermediate component is not a directory
+/// * `FsError::BrokenSymCidLink` - A link points to an entity that is not in the store
+/// * `FsError::MaxFollowDepthReached` - A chain of links is too long to follow
+async fn find_following_links<'a, S>(
+    root: &'a Dir<S>,
+    path: &str,
+    follow_last: bool,
+) -> FsResult<Option<&'a Entity<S>>>
+where
+    S: IpldStore + Send + Sync + 'static,
+{
+    let segments = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>();
+    let mut dir = root;
+    for (index, segment) in segments.iter().enumerate() {
+        let is_last = index == segments.len() - 1;
+        let entity = match dir.get_entity(segment).await? {
+            Some(Entity::SymCidLink(symlink)) if follow_last || !is_last => {
+                symlink.resolve().await?
+            }
+            Some(entity) => entity,
+            None => return Ok(None),
+        };
+
+        if is_last {
+            return Ok(Some(entity));
+        }
+
+        match entity {
+            Entity::Dir(next) => dir = next,
+            _ => return Err(FsError::NotADirectory(segments[..=index].join("/"))),
+        }
+    }
+
+    Ok(None)
+}
+
+/// Converts a timestamp to an NFS time, keeping its sub-second precision.
+fn to_nfstime(time: &DateTime<Utc>) -> nfstime3 {
+    nfstime3 {
+        seconds: time.timestamp() as u32,
+        nseconds: time.timestamp_subsec_nanos(),
     }
 }
 
+/// Returns the value of a non-negative integer attribute, accepting integers stored as strings.
+async fn get_u32_attribute<S>(metadata: &Metadata<S>, key: &str) -> Result<Option<u32>, nfsstat3>
+where
+    S: IpldStore + Send + Sync,
+{
+    let value = metadata.get_attribute(key).await.map_err(nfsstat3::from)?;
+
+    Ok(value.and_then(|ipld| match &*ipld {
+        Ipld::String(s) => s.parse().ok(),
+        Ipld::Integer(i) => u32::try_from(*i).ok(),
+        _ => None,
+    }))
+}
+
+/// Returns `true` if `name` names an entry in a directory, rather than being `.`, `..` or a path.
+fn is_entry_name(name: &str) -> bool {
+    !name.contains('/') && !matches!(name, "." | "..")
+}
+
+/// Joins `name` to `base_path`, resolving `.` and `..` and rejecting paths that escape the root.
+fn join_path(base_path: &str, name: &str) -> Result<String, nfsstat3> {
+    let path = path::normalize(&Path::new(base_path).join(name)).map_err(|e| {
+        tracing::debug!("rejecting path {}/{}: {}", base_path, name, e);
+        nfsstat3::NFS3ERR_INVAL
+    })?;
+
+    path.to_str()
+        .map(str::to_string)
+        .ok_or(nfsstat3::NFS3ERR_INVAL)
+}
+
 //--------------------------------------------------------------------------------------------------
 // Tests
 //--------------------------------------------------------------------------------------------------
 
 #[cfg(test)]
 mod tests {
+    use ipldstore::{ipld::cid::Cid, CountingStore, MerkleNode, DEFAULT_MAX_CHUNK_SIZE};
+    use tempfile::TempDir;
+
+    use crate::{
+        config::DEFAULT_SYMLINK_DEPTH,
+        filesystem::{File, SymCidLink},
+        utils::fixtures,
+    };
+
     use super::*;
 
     #[tokio::test]
@@ -1278,6 +1401,28 @@ mod tests {
         let invalid_filename = filename3::from("test/invalid.txt".as_bytes());
         let result = server.lookup(0, &invalid_filename).await;
         assert!(matches!(result, Err(nfsstat3::NFS3ERR_INVAL)));
+
+        // `.` and `..` resolve within the filesystem, but not above its root
+        let dirname = filename3::from("dir".as_bytes());
+        let (dir_id, _) = server.mkdir(0, &dirname).await.unwrap();
+        let dot = filename3::from(".".as_bytes());
+        let dotdot = filename3::from("..".as_bytes());
+        assert_eq!(server.lookup(dir_id, &dot).await.unwrap(), dir_id);
+        assert_eq!(server.lookup(dir_id, &dotdot).await.unwrap(), 0);
+        assert!(matches!(
+            server.lookup(0, &dotdot).await,
+            Err(nfsstat3::NFS3ERR_INVAL)
+        ));
+
+        // They can't be used to name entries to change
+        assert!(matches!(
+            server.remove(dir_id, &dotdot).await,
+            Err(nfsstat3::NFS3ERR_INVAL)
+        ));
+        assert!(matches!(
+            server.mkdir(dir_id, &dot).await,
+            Err(nfsstat3::NFS3ERR_INVAL)
+        ));
     }
 
     #[tokio::test]
@@ -1307,6 +1452,95 @@ mod tests {
         assert!(matches!(result, Err(nfsstat3::NFS3ERR_NOENT)));
     }
 
+    #[tokio::test]
+    async fn test_nfs_setattr_timestamp_precision() {
+        let server = MemoryMonofsNFS::new(MemoryStore::default());
+        let filename = filename3::from("test.txt".as_bytes());
+        let (fileid, _) = server
+            .create(0, &filename, sattr3::default())
+            .await
+            .unwrap();
+
+        // Sub-second client times survive setattr and getattr
+        let time = nfstime3 {
+            seconds: 1_700_000_000,
+            nseconds: 123_456_789,
+        };
+        let new_attr = sattr3 {
+            mtime: set_mtime::SET_TO_CLIENT_TIME(time),
+            ..Default::default()
+        };
+        let updated_attrs = server.setattr(fileid, new_attr).await.unwrap();
+        assert_eq!(updated_attrs.mtime.seconds, time.seconds);
+        assert_eq!(updated_attrs.mtime.nseconds, time.nseconds);
+
+        let attrs = server.getattr(fileid).await.unwrap();
+        assert_eq!(attrs.mtime.seconds, time.seconds);
+        assert_eq!(attrs.mtime.nseconds, time.nseconds);
+    }
+
+    #[tokio::test]
+    async fn test_nfs_special_files() -> anyhow::Result<()> {
+        let server = MemoryMonofsNFS::new(MemoryStore::default());
+
+        // Special files come from ingested trees, since nfsserve doesn't pass MKNOD through
+        {
+            let mut root = server.root.lock().await;
+            let store = root.get_store().clone();
+            root.put_adapted_file(
+                "fifo",
+                File::new_special(store.clone(), SpecialFileType::Fifo),
+            )
+            .await?;
+
+            let mut null = File::new_special(store, SpecialFileType::CharDevice);
+            let metadata = null.get_metadata_mut();
+            metadata.set_attribute(UNIX_RDEV_MAJOR_KEY, 1).await?;
+            metadata.set_attribute(UNIX_RDEV_MINOR_KEY, 3).await?;
+            root.put_adapted_file("null", null).await?;
+        }
+
+        // The type and device numbers are read back through lookup and getattr
+        let fifo_id = server
+            .lookup(0, &filename3::from("fifo".as_bytes()))
+            .await
+            .unwrap();
+        let null_id = server
+            .lookup(0, &filename3::from("null".as_bytes()))
+            .await
+            .unwrap();
+        let attrs = server.getattr(fifo_id).await.unwrap();
+        assert!(matches!(attrs.ftype, ftype3::NF3FIFO));
+        assert_eq!(attrs.mode, DEFAULT_FILE_MODE);
+        assert_eq!(attrs.rdev.specdata1, 0);
+        assert_eq!(attrs.rdev.specdata2, 0);
+        let attrs = server.getattr(null_id).await.unwrap();
+        assert!(matches!(attrs.ftype, ftype3::NF3CHR));
+        assert_eq!(attrs.rdev.specdata1, 1);
+        assert_eq!(attrs.rdev.specdata2, 3);
+
+        // Special files are listed like any other entry
+        let result = server.readdir(0, 0, 10).await.unwrap();
+        assert!(result
+            .entries
+            .iter()
+            .any(|entry| entry.fileid == null_id && matches!(entry.attr.ftype, ftype3::NF3CHR)));
+
+        // Special files have no content to read or write
+        for id in [fifo_id, null_id] {
+            assert!(matches!(
+                server.read(id, 0, 10).await,
+                Err(nfsstat3::NFS3ERR_INVAL)
+            ));
+            assert!(matches!(
+                server.write(id, 0, b"data").await,
+                Err(nfsstat3::NFS3ERR_INVAL)
+            ));
+        }
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_nfs_fileid_to_path() {
         let server = MemoryMonofsNFS::new(MemoryStore::default());
@@ -1319,11 +1553,11 @@ mod tests {
             .unwrap();
 
         // Convert ID back to path
-        let path = server.fileid_to_path(fileid).await.unwrap();
+        let path = server.fileids.fileid_to_path(fileid).await.unwrap();
         assert_eq!(path, "test.txt");
 
         // Try with non-existent file ID
-        let result = server.fileid_to_path(999).await;
+        let result = server.fileids.fileid_to_path(999).await;
         assert!(matches!(result, Err(nfsstat3::NFS3ERR_NOENT)));
     }
 
@@ -1517,6 +1751,112 @@ mod tests {
         assert_eq!(read_nested_target.as_ref(), nested_target.as_ref());
     }
 
+    #[tokio::test]
+    async fn test_nfs_symcidlink() -> anyhow::Result<()> {
+        let server = MemoryMonofsNFS::new(MemoryStore::default());
+
+        // Link to a file, a directory, a missing entity and the end of a long chain of links
+        let (file_cid, dir_cid) = {
+            let mut root = server.root.lock().await;
+            let store = root.get_store().clone();
+
+            let mut file = File::with_content(store.clone(), b"Hello, World!".as_slice()).await?;
+            let file_cid = file.checkpoint().await?;
+            root.create_symcidlink("file_link", file_cid).await?;
+
+            let mut dir = Dir::new(store.clone());
+            let Entity::File(inner) = dir.find_or_create("inner.txt", true).await? else {
+                unreachable!();
+            };
+            inner.write_at(0, b"inner").await?;
+            let dir_cid = dir.checkpoint().await?;
+            root.create_symcidlink("dir_link", dir_cid).await?;
+
+            root.create_symcidlink("broken_link", Cid::default())
+                .await?;
+
+            let mut cid = file_cid;
+            for _ in 0..=DEFAULT_SYMLINK_DEPTH {
+                cid = SymCidLink::with_cid(store.clone(), cid)
+                    .checkpoint()
+                    .await?;
+            }
+            root.create_symcidlink("deep_link", cid).await?;
+
+            (file_cid, dir_cid)
+        };
+
+        // Test 1: Read a file through a link
+        let file_id = server
+            .lookup(0, &filename3::from("file_link".as_bytes()))
+            .await
+            .unwrap();
+        let attrs = server.getattr(file_id).await.unwrap();
+        assert!(matches!(attrs.ftype, ftype3::NF3REG));
+        assert_eq!(attrs.size, 13);
+        let (data, eof) = server.read(file_id, 0, 100).await.unwrap();
+        assert_eq!(data, b"Hello, World!");
+        assert!(eof);
+
+        // Test 2: Look up and list entries through a directory link
+        let dir_id = server
+            .lookup(0, &filename3::from("dir_link".as_bytes()))
+            .await
+            .unwrap();
+        let attrs = server.getattr(dir_id).await.unwrap();
+        assert!(matches!(attrs.ftype, ftype3::NF3DIR));
+        let inner_id = server
+            .lookup(dir_id, &filename3::from("inner.txt".as_bytes()))
+            .await
+            .unwrap();
+        let (data, _) = server.read(inner_id, 0, 100).await.unwrap();
+        assert_eq!(data, b"inner");
+        let result = server.readdir(dir_id, 0, 10).await.unwrap();
+        assert_eq!(result.entries.len(), 1);
+        assert_eq!(result.entries[0].name.as_ref(), b"inner.txt");
+
+        // Test 3: Links are listed as their targets, unless they can't be followed
+        let result = server.readdir(0, 0, 10).await.unwrap();
+        let ftype = |name: &str| {
+            let entry = result
+                .entries
+                .iter()
+                .find(|entry| entry.name.as_ref() == name.as_bytes())
+                .unwrap();
+            entry.attr.ftype
+        };
+        assert!(matches!(ftype("file_link"), ftype3::NF3REG));
+        assert!(matches!(ftype("dir_link"), ftype3::NF3DIR));
+
+        // Test 4: Read the links themselves
+        let target = server.readlink(file_id).await.unwrap();
+        assert_eq!(target.as_ref(), file_cid.to_string().as_bytes());
+        let target = server.readlink(dir_id).await.unwrap();
+        assert_eq!(target.as_ref(), dir_cid.to_string().as_bytes());
+
+        // Test 5: Broken links
+        let broken_id = server
+            .lookup(0, &filename3::from("broken_link".as_bytes()))
+            .await
+            .unwrap();
+        let result = server.getattr(broken_id).await;
+        assert!(matches!(result, Err(nfsstat3::NFS3ERR_NOENT)));
+        let result = server
+            .lookup(broken_id, &filename3::from("inner.txt".as_bytes()))
+            .await;
+        assert!(matches!(result, Err(nfsstat3::NFS3ERR_NOENT)));
+
+        // Test 6: Chains of links longer than the maximum follow depth
+        let deep_id = server
+            .lookup(0, &filename3::from("deep_link".as_bytes()))
+            .await
+            .unwrap();
+        let result = server.read(deep_id, 0, 100).await;
+        assert!(matches!(result, Err(nfsstat3::NFS3ERR_INVAL)));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_nfs_readdir() {
         let server = MemoryMonofsNFS::new(MemoryStore::default());
@@ -1697,6 +2037,94 @@ mod tests {
         assert!(!result.end);
     }
 
+    #[tokio::test]
+    async fn test_nfs_readdir_stable_pagination() {
+        let server = MemoryMonofsNFS::new(MemoryStore::default());
+        let (dir_id, _) = server
+            .mkdir(0, &filename3::from("dir".as_bytes()))
+            .await
+            .unwrap();
+        for i in 0..30 {
+            let name = format!("file{:02}.txt", i);
+            server
+                .create(dir_id, &filename3::from(name.as_bytes()), sattr3::default())
+                .await
+                .unwrap();
+        }
+
+        // Page through the directory, changing it between pages
+        let mut seen = Vec::new();
+        let mut cookie = 0;
+        let mut page = 0;
+        loop {
+            let result = server.readdir(dir_id, cookie, 4).await.unwrap();
+            for entry in &result.entries {
+                seen.push(String::from_utf8(entry.name.as_ref().to_vec()).unwrap());
+            }
+
+            if result.end {
+                break;
+            }
+
+            cookie = result.entries.last().unwrap().fileid;
+            page += 1;
+
+            // Remove the entry the cookie points at and add one that sorts before the cursor
+            if page == 2 {
+                let last = seen.last().unwrap().clone();
+                server
+                    .remove(dir_id, &filename3::from(last.as_bytes()))
+                    .await
+                    .unwrap();
+                server
+                    .create(
+                        dir_id,
+                        &filename3::from("a_new.txt".as_bytes()),
+                        sattr3::default(),
+                    )
+                    .await
+                    .unwrap();
+            }
+        }
+
+        // Every original entry is visited exactly once, in name order
+        let expected = (0..30)
+            .map(|i| format!("file{:02}.txt", i))
+            .collect::<Vec<_>>();
+        assert_eq!(seen, expected);
+
+        // Paging through the directory again gives the same order
+        let mut second_pass = Vec::new();
+        let mut cookie = 0;
+        loop {
+            let result = server.readdir(dir_id, cookie, 7).await.unwrap();
+            for entry in &result.entries {
+                second_pass.push(String::from_utf8(entry.name.as_ref().to_vec()).unwrap());
+            }
+
+            if result.end {
+                break;
+            }
+
+            cookie = result.entries.last().unwrap().fileid;
+        }
+
+        let mut expected = expected;
+        expected.retain(|name| name != "file07.txt");
+        expected.insert(0, "a_new.txt".to_string());
+        assert_eq!(second_pass, expected);
+
+        // Cookies that don't point into the directory are rejected
+        assert!(matches!(
+            server.readdir(dir_id, dir_id, 4).await,
+            Err(nfsstat3::NFS3ERR_BAD_COOKIE)
+        ));
+        assert!(matches!(
+            server.readdir(dir_id, u64::MAX, 4).await,
+            Err(nfsstat3::NFS3ERR_BAD_COOKIE)
+        ));
+    }
+
     #[tokio::test]
     async fn test_nfs_remove() {
         let server = MemoryMonofsNFS::new(MemoryStore::default());
@@ -1830,6 +2258,196 @@ mod tests {
         assert!(matches!(moved_entity, Entity::File(_)));
     }
 
+    #[tokio::test]
+    async fn test_nfs_max_fileids() {
+        let server = MemoryMonofsNFS::new(MemoryStore::default()).with_max_fileids(4);
+
+        let mut files = Vec::new();
+        for i in 0..10 {
+            let name = filename3::from(format!("file{}.txt", i).as_bytes());
+            let (fileid, _) = server.create(0, &name, sattr3::default()).await.unwrap();
+            files.push((name, fileid));
+        }
+
+        // The root is kept besides the limit
+        server.getattr(0).await.unwrap();
+
+        // Recently used fileids still resolve
+        for (name, fileid) in &files[6..] {
+            assert_eq!(server.getattr(*fileid).await.unwrap().fileid, *fileid);
+            assert_eq!(server.lookup(0, name).await.unwrap(), *fileid);
+        }
+
+        // Remembered evicted fileids keep resolving, and their paths keep their fileids
+        let (name, fileid) = &files[5];
+        assert_eq!(server.getattr(*fileid).await.unwrap().fileid, *fileid);
+        assert_eq!(server.lookup(0, name).await.unwrap(), *fileid);
+
+        // Forgotten fileids are stale, and their paths get new fileids when looked up again
+        let (name, fileid) = &files[0];
+        assert!(matches!(
+            server.getattr(*fileid).await,
+            Err(nfsstat3::NFS3ERR_STALE)
+        ));
+
+        let new_fileid = server.lookup(0, name).await.unwrap();
+        assert_ne!(new_fileid, *fileid);
+        assert_eq!(server.getattr(new_fileid).await.unwrap().fileid, new_fileid);
+
+        // Fileids that were never issued still don't exist
+        assert!(matches!(
+            server.getattr(999).await,
+            Err(nfsstat3::NFS3ERR_NOENT)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_nfs_fileids_survive_restart() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("fileids.log");
+
+        let server = MemoryMonofsNFS::new(MemoryStore::default())
+            .with_fileid_log(&log_path)
+            .await
+            .unwrap();
+
+        let dir_name = filename3::from("dir".as_bytes());
+        let file_name = filename3::from("file.txt".as_bytes());
+        let moved_name = filename3::from("moved.txt".as_bytes());
+        let renamed_name = filename3::from("renamed.txt".as_bytes());
+        let removed_name = filename3::from("removed.txt".as_bytes());
+
+        let (dir_id, _) = server.mkdir(0, &dir_name).await.unwrap();
+        let (file_id, _) = server
+            .create(dir_id, &file_name, sattr3::default())
+            .await
+            .unwrap();
+        server.write(file_id, 0, b"hello").await.unwrap();
+        let (moved_id, _) = server
+            .create(0, &moved_name, sattr3::default())
+            .await
+            .unwrap();
+        server
+            .rename(0, &moved_name, dir_id, &renamed_name)
+            .await
+            .unwrap();
+        let (removed_id, _) = server
+            .create(0, &removed_name, sattr3::default())
+            .await
+            .unwrap();
+        server.remove(0, &removed_name).await.unwrap();
+
+        // Restart the server over the same directory tree and fileid log
+        let root = server.root.lock().await.clone();
+        drop(server);
+        let server = MemoryMonofsNFS::from_dir(root)
+            .with_fileid_log(&log_path)
+            .await
+            .unwrap();
+
+        // Previously issued fileids still resolve, including after a rename
+        let attr = server.getattr(file_id).await.unwrap();
+        assert_eq!(attr.fileid, file_id);
+        let (data, _) = server.read(file_id, 0, 64).await.unwrap();
+        assert_eq!(data, b"hello");
+        assert_eq!(server.lookup(0, &dir_name).await.unwrap(), dir_id);
+        assert_eq!(server.lookup(dir_id, &file_name).await.unwrap(), file_id);
+        assert_eq!(
+            server.lookup(dir_id, &renamed_name).await.unwrap(),
+            moved_id
+        );
+
+        // Only removed entities are stale
+        assert!(matches!(
+            server.getattr(removed_id).await,
+            Err(nfsstat3::NFS3ERR_STALE)
+        ));
+        assert!(matches!(
+            server.getattr(999).await,
+            Err(nfsstat3::NFS3ERR_NOENT)
+        ));
+
+        // New fileids are allocated after the ones issued before the restart
+        let (new_id, _) = server
+            .create(0, &filename3::from("new.txt".as_bytes()), sattr3::default())
+            .await
+            .unwrap();
+        assert!(new_id > removed_id);
+
+        // A malformed trailing entry, such as one cut short by a crash, is skipped
+        let mut log = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&log_path)
+            .unwrap();
+        std::io::Write::write_all(&mut log, b"{\"id\": 4").unwrap();
+
+        let root = server.root.lock().await.clone();
+        drop(server);
+        let server = MemoryMonofsNFS::from_dir(root)
+            .with_fileid_log(&log_path)
+            .await
+            .unwrap();
+        assert_eq!(server.lookup(0, &dir_name).await.unwrap(), dir_id);
+        assert_eq!(
+            server
+                .lookup(0, &filename3::from("new.txt".as_bytes()))
+                .await
+                .unwrap(),
+            new_id
+        );
+    }
+
+    #[tokio::test]
+    async fn test_nfs_rename_replaces_existing() {
+        let server = MemoryMonofsNFS::new(MemoryStore::default());
+
+        let source = filename3::from("source.txt".as_bytes());
+        let target = filename3::from("target.txt".as_bytes());
+        let (source_id, _) = server.create(0, &source, sattr3::default()).await.unwrap();
+        let (target_id, _) = server.create(0, &target, sattr3::default()).await.unwrap();
+        server.write(source_id, 0, b"source content").await.unwrap();
+        server.write(target_id, 0, b"target").await.unwrap();
+
+        // Renaming onto an existing file replaces it
+        server.rename(0, &source, 0, &target).await.unwrap();
+        assert!(matches!(
+            server.lookup(0, &source).await,
+            Err(nfsstat3::NFS3ERR_NOENT)
+        ));
+
+        // The renamed file keeps its fileid and content
+        assert_eq!(server.lookup(0, &target).await.unwrap(), source_id);
+        let (data, eof) = server.read(source_id, 0, 64).await.unwrap();
+        assert_eq!(data, b"source content");
+        assert!(eof);
+
+        // The replaced file's fileid is no longer valid
+        assert!(matches!(
+            server.getattr(target_id).await,
+            Err(nfsstat3::NFS3ERR_STALE)
+        ));
+
+        // A directory cannot replace a non-empty directory
+        let dir1 = filename3::from("dir1".as_bytes());
+        let dir2 = filename3::from("dir2".as_bytes());
+        server.mkdir(0, &dir1).await.unwrap();
+        let (dir2_id, _) = server.mkdir(0, &dir2).await.unwrap();
+        server
+            .create(dir2_id, &source, sattr3::default())
+            .await
+            .unwrap();
+        assert!(matches!(
+            server.rename(0, &dir1, 0, &dir2).await,
+            Err(nfsstat3::NFS3ERR_NOTEMPTY)
+        ));
+
+        // A file cannot replace a directory
+        assert!(matches!(
+            server.rename(0, &target, 0, &dir1).await,
+            Err(nfsstat3::NFS3ERR_ISDIR)
+        ));
+    }
+
     #[test_log::test(tokio::test)]
     async fn test_nfs_read_write() {
         let server = MemoryMonofsNFS::new(MemoryStore::default());
@@ -1929,7 +2547,164 @@ mod tests {
             .unwrap();
         assert!(matches!(
             server.read(fileid, 0, 10).await,
+            Err(nfsstat3::NFS3ERR_STALE)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_nfs_read_only() {
+        let server = MemoryMonofsNFS::new(MemoryStore::default());
+        assert!(matches!(server.capabilities(), VFSCapabilities::ReadWrite));
+
+        // Populate the filesystem before switching to read-only mode
+        let file_name = filename3::from("file.txt".as_bytes());
+        let dir_name = filename3::from("dir".as_bytes());
+        let (fileid, _) = server
+            .create(0, &file_name, sattr3::default())
+            .await
+            .unwrap();
+        server.write(fileid, 0, b"snapshot").await.unwrap();
+        let (dirid, _) = server.mkdir(0, &dir_name).await.unwrap();
+
+        let server = server.with_read_only(true);
+        assert!(server.is_read_only());
+        assert!(matches!(server.capabilities(), VFSCapabilities::ReadOnly));
+
+        // Reads still succeed
+        assert_eq!(server.lookup(0, &file_name).await.unwrap(), fileid);
+        assert_eq!(server.getattr(fileid).await.unwrap().size, 8);
+        let (data, eof) = server.read(fileid, 0, 8).await.unwrap();
+        assert_eq!(data, b"snapshot");
+        assert!(eof);
+        assert_eq!(server.readdir(0, 0, 10).await.unwrap().entries.len(), 2);
+
+        // Every mutating operation is rejected
+        let new_name = filename3::from("new.txt".as_bytes());
+        let truncate = sattr3 {
+            size: set_size3::size(0),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            server.write(fileid, 0, b"data").await,
+            Err(nfsstat3::NFS3ERR_ROFS)
+        ));
+        assert!(matches!(
+            server.create(0, &new_name, sattr3::default()).await,
+            Err(nfsstat3::NFS3ERR_ROFS)
+        ));
+        assert!(matches!(
+            server.create_exclusive(0, &new_name).await,
+            Err(nfsstat3::NFS3ERR_ROFS)
+        ));
+        assert!(matches!(
+            server.mkdir(dirid, &new_name).await,
+            Err(nfsstat3::NFS3ERR_ROFS)
+        ));
+        assert!(matches!(
+            server.remove(0, &file_name).await,
+            Err(nfsstat3::NFS3ERR_ROFS)
+        ));
+        assert!(matches!(
+            server.rename(0, &file_name, dirid, &new_name).await,
+            Err(nfsstat3::NFS3ERR_ROFS)
+        ));
+        assert!(matches!(
+            server.setattr(fileid, truncate).await,
+            Err(nfsstat3::NFS3ERR_ROFS)
+        ));
+        assert!(matches!(
+            server
+                .symlink(
+                    0,
+                    &new_name,
+                    &nfspath3::from("file.txt".as_bytes()),
+                    &sattr3::default()
+                )
+                .await,
+            Err(nfsstat3::NFS3ERR_ROFS)
+        ));
+
+        // The snapshot is unchanged
+        let (data, _) = server.read(fileid, 0, 8).await.unwrap();
+        assert_eq!(data, b"snapshot");
+        assert!(matches!(
+            server.lookup(0, &new_name).await,
             Err(nfsstat3::NFS3ERR_NOENT)
         ));
     }
+
+    #[tokio::test]
+    async fn test_nfs_write_rewrites_only_overlapping_chunks() -> anyhow::Result<()> {
+        let store = CountingStore::new(MemoryStore::default());
+        let server = MonofsNFS::new(store.clone());
+        let (fileid, _) = server
+            .create(0, &filename3::from("big.bin".as_bytes()), sattr3::default())
+            .await
+            .unwrap();
+
+        // Write a file large enough to be split into several chunks
+        let data = fixtures::random_bytes(4 * 1024 * 1024);
+        server.write(fileid, 0, &data).await.unwrap();
+
+        let before = get_chunks(&server).await;
+        assert!(before.len() > 4);
+
+        // Overwrite a few bytes in the middle of the file
+        let offset = 2 * 1024 * 1024;
+        store.reset_metrics();
+        let attrs = server.write(fileid, offset, b"overwritten").await.unwrap();
+        assert_eq!(attrs.size, data.len() as u64);
+
+        // Only the overlapping chunks are rewritten
+        let metrics = store.get_metrics();
+        assert_eq!(metrics.get_writes(), 1);
+        assert!(metrics.get_bytes_written() <= 2 * DEFAULT_MAX_CHUNK_SIZE + 11);
+
+        let after = get_chunks(&server).await;
+        let end = offset + 11;
+        let mut start = 0;
+        let mut first = 0;
+        let mut next = before.len();
+        for (i, (_, size)) in before.iter().enumerate() {
+            if start + (*size as u64) <= offset {
+                first = i + 1;
+            }
+            if start >= end {
+                next = next.min(i);
+            }
+            start += *size as u64;
+        }
+
+        let tail = before.len() - next;
+        assert!(first > 0 && tail > 0);
+        assert_eq!(before[..first], after[..first]);
+        assert_eq!(before[next..], after[after.len() - tail..]);
+
+        // The content reads back with the overwrite applied
+        let (read, _) = server.read(fileid, offset - 4, 19).await.unwrap();
+        assert_eq!(&read[..4], &data[offset as usize - 4..offset as usize]);
+        assert_eq!(&read[4..15], b"overwritten");
+        assert_eq!(
+            &read[15..],
+            &data[offset as usize + 11..offset as usize + 15]
+        );
+
+        Ok(())
+    }
+
+    async fn get_chunks(server: &MonofsNFS<CountingStore<MemoryStore>>) -> Vec<(Cid, usize)> {
+        let root = server.root.lock().await;
+        let Some(Entity::File(file)) = root.find("big.bin").await.unwrap() else {
+            panic!("big.bin is not a file");
+        };
+
+        let node: MerkleNode = file
+            .get_store()
+            .get_node(file.get_content().unwrap())
+            .await
+            .unwrap();
+
+        node.children
+    }
 }
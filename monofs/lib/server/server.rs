@@ -1,8 +1,8 @@
 use getset::Getters;
 use nfsserve::tcp::{NFSTcp, NFSTcpListener};
-use std::path::PathBuf;
+use std::{path::PathBuf, sync::Arc};
 
-use crate::store::FlatFsStore;
+use crate::{filesystem::ChunkerConfig, store::FlatFsStore, FsResult};
 
 use super::MonofsNFS;
 
@@ -23,6 +23,16 @@ pub struct MonofsServer {
 
     /// The port to listen on.
     port: u32,
+
+    /// Whether the filesystem is exported read-only.
+    #[getset(skip)]
+    read_only: bool,
+
+    /// The path of the log the server's fileids are persisted in, if any.
+    fileid_log: Option<PathBuf>,
+
+    /// The chunker file contents written through the server are split with.
+    chunker: ChunkerConfig,
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -36,20 +46,64 @@ impl MonofsServer {
             store_dir: store_dir.into(),
             host: host.into(),
             port,
+            read_only: false,
+            fileid_log: None,
+            chunker: ChunkerConfig::default(),
         }
     }
 
+    /// Sets the chunker file contents written through the server are split with.
+    ///
+    /// It only affects new writes. Contents already in the store keep their chunks.
+    pub fn with_chunker(mut self, chunker: ChunkerConfig) -> Self {
+        self.chunker = chunker;
+        self
+    }
+
+    /// Persists the fileids the server issues in a log at `path`, so clients' file handles stay
+    /// valid across restarts.
+    ///
+    /// See [`MonofsNFS::with_fileid_log`] for how the log is kept.
+    pub fn with_fileid_log(mut self, path: impl Into<PathBuf>) -> Self {
+        self.fileid_log = Some(path.into());
+        self
+    }
+
+    /// Sets whether the filesystem is exported read-only.
+    ///
+    /// A read-only server rejects every mutating NFS operation with `NFS3ERR_ROFS`.
+    pub fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Returns `true` if the filesystem is exported read-only.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
     /// Starts the NFS server and blocks until it is shut down.
     pub async fn start(&self) -> anyhow::Result<()> {
-        // Create the store and NFS filesystem
-        let store = FlatFsStore::new(&self.store_dir);
-        let fs = MonofsNFS::new(store);
-
         // Create and start the NFS listener
         let addr = format!("{}:{}", self.host, self.port);
-        let listener = NFSTcpListener::bind(&addr, fs).await?;
+        let listener = NFSTcpListener::bind(&addr, self.create_fs().await?).await?;
         listener.handle_forever().await?;
 
         Ok(())
     }
+
+    /// Creates the NFS filesystem the server exports, restoring its fileids from the fileid log
+    /// if one is set.
+    pub(crate) async fn create_fs(&self) -> FsResult<MonofsNFS<FlatFsStore>> {
+        self.chunker.validate()?;
+        let store = FlatFsStore::builder()
+            .path(&self.store_dir)
+            .chunker(Arc::new(self.chunker))
+            .build();
+        let fs = MonofsNFS::new(store).with_read_only(self.read_only);
+        match &self.fileid_log {
+            Some(path) => fs.with_fileid_log(path).await,
+            None => Ok(fs),
+        }
+    }
 }
@@ -1,12 +1,23 @@
-use std::{collections::HashSet, path::PathBuf, pin::Pin, sync::Arc};
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    ffi::OsStr,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 
 use async_trait::async_trait;
 use bytes::Bytes;
 use futures::StreamExt;
-use getset::Getters;
+use getset::{CopyGetters, Getters};
 use ipldstore::{
+    codetable::MultihashDigest,
     ipld::{cid::Cid, codec::Links},
-    Chunker, Codec, FastCDCChunker, FixedSizeChunker, FlatLayout, IpldReferences, IpldStore,
+    walk_dag, Chunker, CidConfig, Codec, FixedSizeChunker, FlatLayout, IpldReferences, IpldStore,
     IpldStoreSeekable, Layout, LayoutSeekable, RawStore, StoreError, StoreResult,
     DEFAULT_MAX_NODE_BLOCK_SIZE,
 };
@@ -16,9 +27,31 @@ use serde_ipld_dagcbor::codec::DagCborCodec;
 use tokio::{
     fs::{self, File},
     io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, SeekFrom},
+    sync::{Mutex, MutexGuard, RwLock},
 };
 use typed_builder::TypedBuilder;
 
+use crate::{
+    config::{
+        DEFAULT_BLOCK_LOCK_STRIPES, DEFAULT_BLOOM_FILTER_CAPACITY,
+        DEFAULT_BLOOM_FILTER_FALSE_POSITIVE_RATE,
+    },
+    filesystem::ChunkerConfig,
+};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The extension of the temporary files blocks are written to before being moved into place.
+const TEMP_BLOCK_EXTENSION: &str = "tmp";
+
+/// A counter that makes the names of temporary block files unique within the process.
+static TEMP_BLOCK_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// The name of the file in the store's root directory that lists the pinned CIDs, one per line.
+const PINS_FILENAME: &str = "pins";
+
 //--------------------------------------------------------------------------------------------------
 // Types: FlatFsStore
 //--------------------------------------------------------------------------------------------------
@@ -105,9 +138,15 @@ pub enum DirLevels {
 /// The store uses a configurable chunking strategy to split data into smaller blocks. The chunker
 /// is configurable via the `chunker` field. The layout strategy is configurable via the `layout`
 /// field.
+///
+/// ## Existence Filter
+///
+/// The store can keep an in-memory Bloom filter of the blocks it holds, built with
+/// [`with_bloom_filter`][FlatFsStoreImpl::with_bloom_filter]. Existence checks for blocks the
+/// filter has never seen are then answered without touching the filesystem.
 #[derive(Debug, Clone, TypedBuilder, Getters)]
 #[getset(get = "pub with_prefix")]
-pub struct FlatFsStoreImpl<C = FastCDCChunker, L = FlatLayout>
+pub struct FlatFsStoreImpl<C = ChunkerConfig, L = FlatLayout>
 where
     C: Chunker + Default,
     L: Layout + Default,
@@ -131,6 +170,38 @@ where
     /// Whether to enable reference counting for garbage collection.
     #[builder(default = true)]
     enable_refcount: bool,
+
+    /// How the CIDs of the stored blocks are generated.
+    #[builder(default)]
+    #[getset(skip)]
+    cid_config: CidConfig,
+
+    /// A filter of the blocks in the store, used to rule out missing blocks without a syscall.
+    #[builder(default, setter(skip))]
+    #[getset(skip)]
+    bloom_filter: Option<Arc<BloomFilter>>,
+
+    /// Locks that serialize writes to the same block, shared between clones of the store.
+    ///
+    /// Blocks hash onto a fixed number of stripes, so unrelated blocks may share a lock. The locks
+    /// only reach this process. Other processes writing to the same directory are kept consistent
+    /// by the atomic link in [`write_new_block`][Self::write_new_block] instead, but their
+    /// reference count updates are not, so only one process should write to a store that counts
+    /// references.
+    #[builder(default = new_block_locks(), setter(skip))]
+    #[getset(skip)]
+    block_locks: Arc<[Mutex<()>]>,
+
+    /// A lock that serializes updates to the pins file, shared between clones of the store.
+    #[builder(default, setter(skip))]
+    #[getset(skip)]
+    pins_lock: Arc<Mutex<()>>,
+
+    /// A lock that keeps writes out while [`gc`][Self::gc] runs, shared between clones of the
+    /// store.
+    #[builder(default, setter(skip))]
+    #[getset(skip)]
+    gc_lock: Arc<RwLock<()>>,
 }
 
 /// A flat filesystem store that organizes blocks in a configurable directory structure based on
@@ -170,12 +241,73 @@ where
 ///
 /// ## Chunking and Layout
 ///
-/// This version of the store uses a [`FastCDCChunker`] for chunking and [`FlatLayout`] for layout.
-pub type FlatFsStore = FlatFsStoreImpl<FastCDCChunker, FlatLayout>;
+/// This version of the store uses a [`ChunkerConfig`] for chunking and [`FlatLayout`] for layout.
+/// The chunker defaults to FastCDC, and can be set to other chunk sizes when the store is built,
+/// e.g. `FlatFsStore::builder().chunker(Arc::new(config))`.
+pub type FlatFsStore = FlatFsStoreImpl<ChunkerConfig, FlatLayout>;
 
 /// A [`FlatFsStoreImpl`] with a [`FixedSizeChunker`] for chunking and [`FlatLayout`] for layout.
 pub type FlatFsStoreFixed = FlatFsStoreImpl<FixedSizeChunker, FlatLayout>;
 
+/// A summary of a mark-and-sweep garbage collection run on a [`FlatFsStoreImpl`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, CopyGetters)]
+#[getset(get_copy = "pub with_prefix")]
+pub struct GcReport {
+    /// The number of blocks that were kept.
+    kept_blocks: u64,
+
+    /// The number of blocks that were deleted.
+    deleted_blocks: u64,
+
+    /// The number of bytes freed on disk by deleting blocks.
+    freed_bytes: u64,
+}
+
+/// The result of checking the block files of a [`FlatFsStoreImpl`] with
+/// [`fsck`][FlatFsStoreImpl::fsck].
+///
+/// It is named apart from [`crate::management::FsckReport`], which reports on a filesystem tree
+/// rather than on individual block files.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Getters)]
+#[getset(get = "pub with_prefix")]
+pub struct StoreFsckReport {
+    /// The number of block files that were checked.
+    checked_blocks: u64,
+
+    /// The problems that were found.
+    problems: Vec<BlockProblem>,
+
+    /// The paths bad block files were moved to, if they were quarantined.
+    quarantined: Vec<PathBuf>,
+}
+
+/// A problem with a block file found by [`FlatFsStoreImpl::fsck`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockProblem {
+    /// The content of the block file does not hash to the digest it is stored under.
+    HashMismatch(PathBuf),
+
+    /// The block file could not be read.
+    Unreadable(PathBuf),
+
+    /// The block file has no content, usually because a write was interrupted.
+    Empty(PathBuf),
+}
+
+/// A Bloom filter of block digests.
+///
+/// It can tell for sure that a block is not in the store, but may wrongly report blocks as present.
+/// Blocks are never removed from it, so deleted blocks also turn into false positives. Bits are set
+/// atomically, so it can be shared between clones of the store.
+#[derive(Debug)]
+struct BloomFilter {
+    /// The bits of the filter.
+    bits: Vec<AtomicU64>,
+
+    /// The number of hashes set for each digest.
+    hash_count: u32,
+}
+
 //--------------------------------------------------------------------------------------------------
 // Methods: FlatFsStore
 //--------------------------------------------------------------------------------------------------
@@ -197,16 +329,186 @@ where
             chunker: Default::default(),
             layout: Default::default(),
             enable_refcount: true,
+            cid_config: CidConfig::default(),
+            bloom_filter: None,
+            block_locks: new_block_locks(),
+            pins_lock: Default::default(),
+            gc_lock: Default::default(),
         }
     }
 
+    /// Builds an in-memory Bloom filter of the blocks currently in the store and uses it to answer
+    /// existence checks from then on.
+    ///
+    /// Blocks that the filter has not seen are reported missing without touching the filesystem.
+    /// Blocks that it may have seen are still checked on disk, so false positives only cost the
+    /// syscall the filter would otherwise have saved. The filter is sized for twice the current
+    /// number of blocks, or [`DEFAULT_BLOOM_FILTER_CAPACITY`] if that is larger.
+    ///
+    /// The filter assumes that only this store writes to its directory. It lives in memory only
+    /// and has to be rebuilt every time the store is opened.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the store directory can't be read.
+    pub async fn with_bloom_filter(mut self) -> StoreResult<Self> {
+        let block_paths = self.get_block_paths().await?;
+        let filter = BloomFilter::new(
+            (block_paths.len() * 2).max(DEFAULT_BLOOM_FILTER_CAPACITY),
+            DEFAULT_BLOOM_FILTER_FALSE_POSITIVE_RATE,
+        );
+
+        for block_path in block_paths {
+            let digest = block_path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| hex::decode(name).ok());
+
+            if let Some(digest) = digest {
+                filter.insert(&digest);
+            }
+        }
+
+        self.bloom_filter = Some(Arc::new(filter));
+        Ok(self)
+    }
+
     /// Returns whether reference counting is enabled for this store.
     pub fn is_refcount_enabled(&self) -> bool {
         self.enable_refcount
     }
 
+    /// Removes every block that is not reachable from the given roots.
+    ///
+    /// This is a mark-and-sweep collection. The mark phase walks the DAG of each root with
+    /// [`walk_dag`] and the sweep phase deletes every other block in the store.
+    /// Unlike [`garbage_collect`][IpldStore::garbage_collect], it does not rely on reference
+    /// counts, so it also reclaims blocks that were never released explicitly and works whether
+    /// reference counting is enabled or not.
+    ///
+    /// Reads can run concurrently with the collection, but writes from this process wait until it
+    /// finishes. Writes skip blocks that are already on disk, so a block written again while the
+    /// collection runs could otherwise be deleted after the write reported it stored. Pinning
+    /// waits too, so a CID can't be pinned after the pins are read and then swept.
+    ///
+    /// Reference counts of the kept blocks are not adjusted, so they may over-count references
+    /// from deleted blocks. This only makes later reference counted collections more conservative.
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`StoreError::BlockNotFound`] if one of the roots or pinned CIDs is not in the
+    /// store. Nothing is deleted in that case.
+    pub async fn gc(&self, roots: &[Cid]) -> StoreResult<GcReport>
+    where
+        C: Clone + Send + Sync + 'static,
+        L: Clone + Send + Sync + 'static,
+    {
+        // The pins lock is taken first, like pinning does before checking the CID is stored
+        let _pins_guard = self.lock_pins().await;
+        let _guard = self.gc_lock.write().await;
+        let candidates = self.get_block_paths().await?;
+
+        // Pinned CIDs are roots too
+        let mut roots = roots.to_vec();
+        roots.extend(self.read_pins().await?);
+
+        // Mark
+        let mut live = HashSet::new();
+        for root in &roots {
+            // Roots reachable from an earlier root are already marked, along with their DAGs
+            if live.contains(&self.get_block_path(root)) {
+                continue;
+            }
+
+            walk_dag(self, root, |cid| {
+                live.insert(self.get_block_path(cid));
+            })
+            .await?;
+        }
+
+        // Sweep
+        let mut report = GcReport::default();
+        for block_path in candidates {
+            if live.contains(&block_path) {
+                report.kept_blocks += 1;
+                continue;
+            }
+
+            let metadata = match fs::metadata(&block_path).await {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+
+            match fs::remove_file(&block_path).await {
+                Ok(()) => {
+                    report.deleted_blocks += 1;
+                    report.freed_bytes += metadata.len();
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(StoreError::custom(e)),
+            }
+        }
+
+        tracing::debug!(
+            "gc kept {} blocks, deleted {} blocks, freed {} bytes",
+            report.kept_blocks,
+            report.deleted_blocks,
+            report.freed_bytes
+        );
+
+        Ok(report)
+    }
+
+    /// Returns the CIDs pinned in the store, in the order they were pinned.
+    ///
+    /// See [`management::pin`](crate::management::pin()) for pinning CIDs.
+    pub(crate) async fn read_pins(&self) -> StoreResult<Vec<Cid>> {
+        let contents = match fs::read_to_string(self.path.join(PINS_FILENAME)).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(StoreError::custom(e)),
+        };
+
+        contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| Cid::try_from(line).map_err(StoreError::custom))
+            .collect()
+    }
+
+    /// Replaces the CIDs pinned in the store with `pins`.
+    ///
+    /// The pins file is written to a temporary file first and then moved into place, so readers
+    /// never see a partially written file.
+    pub(crate) async fn write_pins(&self, pins: &[Cid]) -> StoreResult<()> {
+        let pins_path = self.path.join(PINS_FILENAME);
+        let temp_path = Self::get_temp_block_path(&pins_path);
+        let contents: String = pins.iter().map(|cid| format!("{cid}\n")).collect();
+
+        fs::create_dir_all(&self.path)
+            .await
+            .map_err(StoreError::custom)?;
+        fs::write(&temp_path, contents)
+            .await
+            .map_err(StoreError::custom)?;
+        fs::rename(&temp_path, &pins_path)
+            .await
+            .map_err(StoreError::custom)?;
+
+        Ok(())
+    }
+
+    /// Locks the pins file against concurrent updates from this process.
+    ///
+    /// [`gc`][Self::gc] holds the lock while it runs, so checking that a CID is stored and pinning
+    /// it under the lock can't race with the CID being collected.
+    pub(crate) async fn lock_pins(&self) -> MutexGuard<'_, ()> {
+        self.pins_lock.lock().await
+    }
+
     /// Get the path for a given CID using the configured directory structure
-    fn get_block_path(&self, cid: &Cid) -> PathBuf {
+    pub(crate) fn get_block_path(&self, cid: &Cid) -> PathBuf {
         let digest = hex::encode(cid.hash().digest());
         match self.dir_levels {
             DirLevels::Zero => self.path.join(&digest),
@@ -222,6 +524,179 @@ where
         }
     }
 
+    /// Checks if a block exists, consulting the Bloom filter first if there is one.
+    fn block_exists(&self, cid: &Cid) -> bool {
+        if let Some(filter) = &self.bloom_filter {
+            if !filter.may_contain(cid.hash().digest()) {
+                return false;
+            }
+        }
+
+        self.get_block_path(cid).exists()
+    }
+
+    /// Returns the paths of the blocks whose content doesn't hash to the digest they are stored
+    /// under.
+    ///
+    /// Blocks are checked without following any links, so this finds corrupt blocks even when the
+    /// roots of the store are not known. It does not find missing blocks. Unreadable and empty
+    /// block files are also considered corrupt.
+    pub async fn find_corrupt_blocks(&self) -> StoreResult<Vec<PathBuf>> {
+        let report = self.fsck().await?;
+        Ok(report
+            .problems
+            .into_iter()
+            .map(BlockProblem::into_path)
+            .collect())
+    }
+
+    /// Checks the integrity of every block file in the store.
+    ///
+    /// The content of each block file is hashed again and compared with the digest it is stored
+    /// under. Block files that can't be read or have no content are reported too. Blocks are
+    /// checked without following any links, so missing blocks are not found.
+    ///
+    /// ## Returns
+    ///
+    /// A [`StoreFsckReport`] listing the bad block files.
+    ///
+    /// ## Examples
+    ///
+    /// ```no_run
+    /// use monofs::store::FlatFsStore;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let store = FlatFsStore::new("/path/to/store");
+    /// let report = store.fsck().await?;
+    /// for problem in report.get_problems() {
+    ///     println!("{problem:?}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn fsck(&self) -> StoreResult<StoreFsckReport> {
+        let mut report = StoreFsckReport::default();
+        for block_path in self.get_block_paths().await? {
+            report.checked_blocks += 1;
+            if let Some(problem) = self.check_block_file(block_path).await {
+                report.problems.push(problem);
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Checks the integrity of every block file in the store like [`fsck`][Self::fsck], and moves
+    /// the bad block files into `quarantine_dir`.
+    ///
+    /// Quarantined blocks are no longer in the store, so they can be written again. The quarantine
+    /// directory should be outside the store directory, or its files may be checked as blocks.
+    ///
+    /// ## Arguments
+    ///
+    /// * `quarantine_dir` - The directory to move bad block files into. It is created if needed.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if the quarantine directory can't be created or a block file can't be
+    /// moved into it.
+    pub async fn fsck_with_quarantine(
+        &self,
+        quarantine_dir: impl AsRef<Path>,
+    ) -> StoreResult<StoreFsckReport> {
+        let quarantine_dir = quarantine_dir.as_ref();
+        let mut report = self.fsck().await?;
+        if report.problems.is_empty() {
+            return Ok(report);
+        }
+
+        fs::create_dir_all(quarantine_dir)
+            .await
+            .map_err(StoreError::custom)?;
+
+        for problem in &report.problems {
+            let block_path = problem.get_path();
+            let Some(file_name) = block_path.file_name() else {
+                continue;
+            };
+
+            let quarantined_path = quarantine_dir.join(file_name);
+            fs::rename(block_path, &quarantined_path)
+                .await
+                .map_err(StoreError::custom)?;
+            report.quarantined.push(quarantined_path);
+        }
+
+        Ok(report)
+    }
+
+    /// Checks a single block file, returning the problem with it if there is one.
+    async fn check_block_file(&self, block_path: PathBuf) -> Option<BlockProblem> {
+        let Ok(bytes) = fs::read(&block_path).await else {
+            return Some(BlockProblem::Unreadable(block_path));
+        };
+
+        // Skip the refcount header. A file too short to hold it was never fully written.
+        let header_len = if self.enable_refcount { 8 } else { 0 };
+        let Some(data) = bytes.get(header_len..) else {
+            return Some(BlockProblem::Empty(block_path));
+        };
+
+        let digest = hex::encode(self.cid_config.get_hash().digest(data).digest());
+        if block_path.file_name() == Some(OsStr::new(&digest)) {
+            return None;
+        }
+
+        if data.is_empty() {
+            Some(BlockProblem::Empty(block_path))
+        } else {
+            Some(BlockProblem::HashMismatch(block_path))
+        }
+    }
+
+    /// Returns the paths of all the block files in the store.
+    async fn get_block_paths(&self) -> StoreResult<Vec<PathBuf>> {
+        let depth = match self.dir_levels {
+            DirLevels::Zero => 0,
+            DirLevels::One => 1,
+            DirLevels::Two => 2,
+        };
+
+        let mut paths = Vec::new();
+        if fs::try_exists(&self.path)
+            .await
+            .map_err(StoreError::custom)?
+        {
+            Self::collect_block_paths(&self.path, depth, &mut paths).await?;
+        }
+
+        Ok(paths)
+    }
+
+    /// Collects the block files under `dir`, descending `depth` levels of subdirectories.
+    async fn collect_block_paths(
+        dir: &Path,
+        depth: u8,
+        paths: &mut Vec<PathBuf>,
+    ) -> StoreResult<()> {
+        let mut entries = fs::read_dir(dir).await.map_err(StoreError::custom)?;
+        while let Some(entry) = entries.next_entry().await.map_err(StoreError::custom)? {
+            let file_type = entry.file_type().await.map_err(StoreError::custom)?;
+            let is_temp = Path::new(&entry.file_name())
+                .extension()
+                .is_some_and(|extension| extension == TEMP_BLOCK_EXTENSION);
+            let is_pins = entry.file_name() == PINS_FILENAME;
+            if depth == 0 && file_type.is_file() && !is_temp && !is_pins {
+                paths.push(entry.path());
+            } else if depth > 0 && file_type.is_dir() {
+                Box::pin(Self::collect_block_paths(&entry.path(), depth - 1, paths)).await?;
+            }
+        }
+
+        Ok(())
+    }
+
     /// Ensure the parent directories exist for a given block path
     async fn ensure_directories(&self, block_path: &PathBuf) -> StoreResult<()> {
         if let Some(parent) = block_path.parent() {
@@ -273,10 +748,49 @@ where
         Ok(data.into())
     }
 
-    /// Writes a new block with initial refcount
-    async fn write_new_block(&self, block_path: &PathBuf, bytes: &[u8]) -> StoreResult<()> {
-        self.ensure_directories(block_path).await?;
-        let mut file = File::create(block_path).await.map_err(StoreError::custom)?;
+    /// Writes a new block with initial refcount, returning whether this call created it.
+    ///
+    /// The block is written to a temporary file first and then linked into place, so readers never
+    /// see a partially written block. Concurrent writers of the same block in this process wait on
+    /// the block's lock. Writers in other processes may still race, but blocks are immutable, so
+    /// the first one to link its file wins and the others discard theirs. Only the winner gets
+    /// `true`, which lets callers do the once-per-block work, like counting the block's references.
+    async fn write_new_block(&self, cid: &Cid, bytes: &[u8]) -> StoreResult<bool> {
+        let _guard = self.lock_block(cid).await;
+        let block_path = self.get_block_path(cid);
+        if fs::try_exists(&block_path)
+            .await
+            .map_err(StoreError::custom)?
+        {
+            return Ok(false);
+        }
+
+        self.ensure_directories(&block_path).await?;
+        let temp_path = Self::get_temp_block_path(&block_path);
+        let result = self.write_temp_block(&temp_path, bytes).await;
+        let result = match result {
+            Ok(()) => match fs::hard_link(&temp_path, &block_path).await {
+                Ok(()) => Ok(true),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+                Err(e) => Err(StoreError::custom(e)),
+            },
+            Err(e) => Err(e),
+        };
+
+        // The temporary file is no longer needed, whether it was linked into place or not
+        let _ = fs::remove_file(&temp_path).await;
+        let created = result?;
+
+        if let Some(filter) = &self.bloom_filter {
+            filter.insert(cid.hash().digest());
+        }
+
+        Ok(created)
+    }
+
+    /// Writes the content of a new block to a temporary file and flushes it to disk.
+    async fn write_temp_block(&self, temp_path: &Path, bytes: &[u8]) -> StoreResult<()> {
+        let mut file = File::create(temp_path).await.map_err(StoreError::custom)?;
 
         if self.enable_refcount {
             // Write initial refcount (0)
@@ -287,9 +801,31 @@ where
 
         // Write block data
         file.write_all(bytes).await.map_err(StoreError::custom)?;
+        file.sync_all().await.map_err(StoreError::custom)?;
+
         Ok(())
     }
 
+    /// Returns a unique path next to `block_path` to write the block to before it is moved into
+    /// place.
+    fn get_temp_block_path(block_path: &Path) -> PathBuf {
+        let counter = TEMP_BLOCK_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let mut file_name = block_path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(format!(
+            ".{}.{counter}.{TEMP_BLOCK_EXTENSION}",
+            std::process::id()
+        ));
+        block_path.with_file_name(file_name)
+    }
+
+    /// Locks the block with the given CID against concurrent writes from this process.
+    async fn lock_block(&self, cid: &Cid) -> MutexGuard<'_, ()> {
+        let mut hasher = DefaultHasher::new();
+        cid.hash().digest().hash(&mut hasher);
+        let index = (hasher.finish() % self.block_locks.len() as u64) as usize;
+        self.block_locks[index].lock().await
+    }
+
     /// Increments reference counts for the given CIDs
     async fn increment_reference_counts(
         &self,
@@ -300,6 +836,7 @@ where
         }
 
         for cid in cids {
+            let _guard = self.lock_block(cid).await;
             let block_path = self.get_block_path(cid);
             if let Ok(mut file) = File::options()
                 .read(true)
@@ -315,6 +852,76 @@ where
     }
 }
 
+//--------------------------------------------------------------------------------------------------
+// Methods: BlockProblem
+//--------------------------------------------------------------------------------------------------
+
+impl BlockProblem {
+    /// Returns the path of the bad block file.
+    pub fn get_path(&self) -> &Path {
+        match self {
+            Self::HashMismatch(path) | Self::Unreadable(path) | Self::Empty(path) => path,
+        }
+    }
+
+    /// Returns the path of the bad block file, consuming the problem.
+    pub fn into_path(self) -> PathBuf {
+        match self {
+            Self::HashMismatch(path) | Self::Unreadable(path) | Self::Empty(path) => path,
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods: BloomFilter
+//--------------------------------------------------------------------------------------------------
+
+impl BloomFilter {
+    /// Creates an empty filter sized to hold `capacity` digests at the given false positive rate.
+    fn new(capacity: usize, false_positive_rate: f64) -> Self {
+        let ln2 = std::f64::consts::LN_2;
+        let bit_count = (-(capacity.max(1) as f64) * false_positive_rate.ln() / (ln2 * ln2))
+            .ceil()
+            .max(64.0) as usize;
+        let hash_count = ((bit_count as f64 / capacity.max(1) as f64) * ln2)
+            .round()
+            .max(1.0) as u32;
+
+        Self {
+            bits: (0..bit_count.div_ceil(64))
+                .map(|_| AtomicU64::new(0))
+                .collect(),
+            hash_count,
+        }
+    }
+
+    /// Adds a digest to the filter.
+    fn insert(&self, digest: &[u8]) {
+        for bit in self.bit_indices(digest) {
+            self.bits[bit / 64].fetch_or(1 << (bit % 64), Ordering::Relaxed);
+        }
+    }
+
+    /// Returns false if the digest was definitely never added to the filter.
+    fn may_contain(&self, digest: &[u8]) -> bool {
+        self.bit_indices(digest)
+            .all(|bit| self.bits[bit / 64].load(Ordering::Relaxed) & (1 << (bit % 64)) != 0)
+    }
+
+    /// Returns the bits set for a digest, derived from two hashes by double hashing.
+    fn bit_indices(&self, digest: &[u8]) -> impl Iterator<Item = usize> {
+        let mut hasher = DefaultHasher::new();
+        digest.hash(&mut hasher);
+        let h1 = hasher.finish();
+        0u8.hash(&mut hasher);
+        let h2 = hasher.finish() | 1;
+
+        let bit_count = self.bits.len() as u64 * 64;
+        (0..self.hash_count as u64)
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % bit_count) as usize)
+    }
+}
+
 //--------------------------------------------------------------------------------------------------
 // Trait Implementations
 //--------------------------------------------------------------------------------------------------
@@ -340,12 +947,11 @@ where
         }
 
         // Create CID and store the block
-        let cid = ipldstore::generate_cid(Codec::DagCbor, &bytes);
-        let block_path = self.get_block_path(&cid);
-
-        if !block_path.exists() {
-            self.write_new_block(&block_path, &bytes).await?;
-            // Increment reference counts for referenced blocks
+        let cid = self.cid_config.generate_cid(Codec::DagCbor, &bytes)?;
+        let _guard = self.gc_lock.read().await;
+        // Only the writer that creates the block counts its references, or racing writers of the
+        // same node would count them more than once
+        if !self.block_exists(&cid) && self.write_new_block(&cid, &bytes).await? {
             self.increment_reference_counts(data.get_references())
                 .await?;
         }
@@ -392,7 +998,7 @@ where
     }
 
     async fn has(&self, cid: &Cid) -> bool {
-        self.get_block_path(cid).exists()
+        self.block_exists(cid)
     }
 
     async fn get_supported_codecs(&self) -> HashSet<Codec> {
@@ -406,11 +1012,15 @@ where
         Ok(Some(DEFAULT_MAX_NODE_BLOCK_SIZE))
     }
 
+    async fn get_cid_config(&self) -> CidConfig {
+        self.cid_config
+    }
+
     async fn get_block_count(&self) -> StoreResult<u64> {
         let mut count = 0;
         match self.dir_levels {
             DirLevels::Zero => {
-                // Count all files in the root directory
+                // Count all files in the root directory, except for the pins file
                 let mut entries = fs::read_dir(&self.path).await.map_err(StoreError::custom)?;
                 while let Some(entry) = entries.next_entry().await.map_err(StoreError::custom)? {
                     if entry
@@ -418,6 +1028,7 @@ where
                         .await
                         .map_err(StoreError::custom)?
                         .is_file()
+                        && entry.file_name() != PINS_FILENAME
                     {
                         count += 1;
                     }
@@ -615,11 +1226,10 @@ where
             }
         }
 
-        let cid = ipldstore::generate_cid(Codec::Raw, bytes.as_ref());
-        let block_path = self.get_block_path(&cid);
-
-        if !block_path.exists() {
-            self.write_new_block(&block_path, &bytes).await?;
+        let cid = self.cid_config.generate_cid(Codec::Raw, bytes.as_ref())?;
+        let _guard = self.gc_lock.read().await;
+        if !self.block_exists(&cid) {
+            self.write_new_block(&cid, &bytes).await?;
         }
 
         Ok(cid)
@@ -661,15 +1271,29 @@ where
     }
 }
 
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Creates the locks that serialize writes to the blocks of a store.
+fn new_block_locks() -> Arc<[Mutex<()>]> {
+    (0..DEFAULT_BLOCK_LOCK_STRIPES)
+        .map(|_| Mutex::new(()))
+        .collect()
+}
+
 //--------------------------------------------------------------------------------------------------
 // Tests
 //--------------------------------------------------------------------------------------------------
 
 #[cfg(test)]
 mod tests {
+    use std::sync::atomic::AtomicBool;
+
     use ipldstore::{
         codetable::{Code, MultihashDigest},
-        DEFAULT_MAX_CHUNK_SIZE, DEFAULT_MAX_NODE_BLOCK_SIZE,
+        ipld::cid::Version,
+        FastCDCChunker, DEFAULT_MAX_CHUNK_SIZE, DEFAULT_MAX_NODE_BLOCK_SIZE,
     };
     use tempfile::TempDir;
     use tokio::fs;
@@ -679,6 +1303,194 @@ mod tests {
         *,
     };
 
+    #[tokio::test]
+    async fn test_flatfsstore_has_many_with_bloom_filter() -> anyhow::Result<()> {
+        for dir_level in [DirLevels::Zero, DirLevels::One, DirLevels::Two] {
+            let (store, temp) = fixtures::setup_store(dir_level).await;
+            let existing = store.put_raw_block(b"existing".to_vec()).await?;
+
+            // Blocks written before the filter is built are picked up from the directory.
+            let store = store.with_bloom_filter().await?;
+            let added = store.put_raw_block(b"added".to_vec()).await?;
+            let missing = ipldstore::generate_cid(Codec::Raw, b"missing");
+
+            let present = store.has_many(&[existing, missing, added]).await?;
+            assert_eq!(present, vec![true, false, true]);
+
+            // Reopening the store rebuilds the filter from the blocks on disk.
+            let reopened = FlatFsStore::builder()
+                .dir_levels(dir_level)
+                .path(temp.path())
+                .build()
+                .with_bloom_filter()
+                .await?;
+
+            let present = reopened.has_many(&[existing, missing, added]).await?;
+            assert_eq!(present, vec![true, false, true]);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_flatfsstore_bloom_filter_false_positive() -> anyhow::Result<()> {
+        let (store, _temp) = fixtures::setup_store(DirLevels::One).await;
+        let store = store.with_bloom_filter().await?;
+
+        // Make the filter wrongly report a missing block as present.
+        let missing = ipldstore::generate_cid(Codec::Raw, b"missing");
+        let filter = store.bloom_filter.as_ref().unwrap();
+        filter.insert(missing.hash().digest());
+        assert!(filter.may_contain(missing.hash().digest()));
+
+        // The real check still finds that it is missing.
+        assert!(!store.has(&missing).await);
+        assert_eq!(store.has_many(&[missing]).await?, vec![false]);
+
+        // And it can still be written.
+        let cid = store.put_raw_block(b"missing".to_vec()).await?;
+        assert_eq!(cid, missing);
+        assert!(store.has(&missing).await);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_flatfsstore_fsck() -> anyhow::Result<()> {
+        for dir_level in [DirLevels::Zero, DirLevels::One, DirLevels::Two] {
+            let (store, _temp) = fixtures::setup_store(dir_level).await;
+            let good = store.put_raw_block(b"good".to_vec()).await?;
+            let corrupt = store.put_raw_block(b"corrupt".to_vec()).await?;
+            let truncated = store.put_raw_block(b"truncated".to_vec()).await?;
+            let empty = store.put_raw_block(Vec::new()).await?;
+
+            let report = store.fsck().await?;
+            assert_eq!(*report.get_checked_blocks(), 4);
+            assert!(report.get_problems().is_empty());
+
+            // Flip a byte in one block and truncate another, as a partial write would
+            let corrupt_path = store.get_block_path(&corrupt);
+            let mut bytes = fs::read(&corrupt_path).await?;
+            *bytes.last_mut().unwrap() ^= 0xff;
+            fs::write(&corrupt_path, bytes).await?;
+
+            let truncated_path = store.get_block_path(&truncated);
+            fs::write(&truncated_path, b"").await?;
+
+            let report = store.fsck().await?;
+            assert_eq!(*report.get_checked_blocks(), 4);
+            assert_eq!(report.get_problems().len(), 2);
+            assert!(report
+                .get_problems()
+                .contains(&BlockProblem::HashMismatch(corrupt_path.clone())));
+            assert!(report
+                .get_problems()
+                .contains(&BlockProblem::Empty(truncated_path.clone())));
+            assert!(report.get_quarantined().is_empty());
+
+            let mut corrupt_blocks = store.find_corrupt_blocks().await?;
+            corrupt_blocks.sort();
+            let mut expected = vec![corrupt_path.clone(), truncated_path.clone()];
+            expected.sort();
+            assert_eq!(corrupt_blocks, expected);
+
+            // Quarantining moves the bad blocks out of the store
+            let quarantine = TempDir::new()?;
+            let report = store.fsck_with_quarantine(quarantine.path()).await?;
+            assert_eq!(report.get_quarantined().len(), 2);
+            assert!(report.get_quarantined().iter().all(|path| path.exists()));
+            assert!(!corrupt_path.exists());
+            assert!(!truncated_path.exists());
+
+            assert!(store.has(&good).await);
+            assert!(store.has(&empty).await);
+            assert!(!store.has(&corrupt).await);
+            assert!(store.fsck().await?.get_problems().is_empty());
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_flatfsstore_concurrent_writes() -> anyhow::Result<()> {
+        let (store, temp) = fixtures::setup_store(DirLevels::One).await;
+
+        // Separate store instances don't share locks, like stores in different processes
+        let other = FlatFsStore::new(temp.path());
+        let contents: Vec<Vec<u8>> = (0..4u8)
+            .map(|seed| {
+                (0..DEFAULT_MAX_CHUNK_SIZE * 2)
+                    .map(|i| (i as u8) ^ seed)
+                    .collect()
+            })
+            .collect();
+
+        let mut handles = Vec::new();
+        for task in 0..32 {
+            let store = if task % 2 == 0 {
+                store.clone()
+            } else {
+                other.clone()
+            };
+            let content = contents[task % contents.len()].clone();
+            handles.push(tokio::spawn(async move {
+                let cid = store.put_bytes(content.as_slice()).await?;
+                anyhow::Ok((cid, content))
+            }));
+        }
+
+        for handle in handles {
+            let (cid, content) = handle.await??;
+            let mut reader = store.get_bytes(&cid).await?;
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data).await?;
+            assert_eq!(data, content);
+        }
+
+        // Every block is intact and no temporary files are left behind
+        let report = store.fsck().await?;
+        assert!(*report.get_checked_blocks() > 0);
+        assert!(report.get_problems().is_empty());
+        for path in fixtures::list_files(temp.path())? {
+            assert_ne!(path.extension(), Some(OsStr::new(TEMP_BLOCK_EXTENSION)));
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_flatfsstore_concurrent_node_writes_count_references_once() -> anyhow::Result<()> {
+        let (store, temp) = fixtures::setup_store(DirLevels::One).await;
+        let other = FlatFsStore::new(temp.path());
+        let data_cid = store.put_raw_block(b"shared data".to_vec()).await?;
+        let node = TestNode {
+            name: "node".to_string(),
+            value: 1,
+            refs: vec![data_cid],
+        };
+
+        let mut handles = Vec::new();
+        for task in 0..16 {
+            let store = if task % 2 == 0 {
+                store.clone()
+            } else {
+                other.clone()
+            };
+            let node = node.clone();
+            handles.push(tokio::spawn(async move { store.put_node(&node).await }));
+        }
+
+        for handle in handles {
+            handle.await??;
+        }
+
+        // The node was created once, so the data block is referenced once
+        let mut file = File::open(store.get_block_path(&data_cid)).await?;
+        assert_eq!(store.read_refcount(&mut file).await?, 1);
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_flatfsstore_raw_block() -> anyhow::Result<()> {
         for dir_level in [DirLevels::Zero, DirLevels::One, DirLevels::Two] {
@@ -748,6 +1560,40 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_flatfsstore_cid_config() -> anyhow::Result<()> {
+        let temp = TempDir::new()?;
+        let store = FlatFsStore::builder()
+            .path(temp.path())
+            .cid_config(CidConfig::new(Code::Sha2_256, Version::V1)?)
+            .build();
+
+        // Blocks are hashed with the configured hash, and fsck checks them with it
+        let data = b"Hello, World!".to_vec();
+        let cid = store.put_raw_block(data.clone()).await?;
+        assert_eq!(cid.hash().code(), u64::from(Code::Sha2_256));
+        assert_eq!(store.get_raw_block(&cid).await?, data);
+        assert!(store.fsck().await?.get_problems().is_empty());
+
+        // Chunk statistics use the same CIDs as the store
+        let stats = FastCDCChunker::default()
+            .chunk_with_stats(&data[..], &store)
+            .await?;
+        assert_eq!(stats.get_deduplicated_size(), data.len() as u64);
+
+        // Version 0 CIDs can't address raw blocks or nodes
+        let v0_store = FlatFsStore::builder()
+            .path(temp.path().join("v0"))
+            .cid_config(CidConfig::new(Code::Sha2_256, Version::V0)?)
+            .build();
+        assert!(matches!(
+            v0_store.put_raw_block(data).await,
+            Err(StoreError::InvalidCidConfig(_))
+        ));
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_flatfsstore_directory_structure() -> anyhow::Result<()> {
         for dir_level in [DirLevels::Zero, DirLevels::One, DirLevels::Two] {
@@ -1111,6 +1957,171 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_flatfsstore_gc_removes_overwritten_blocks() -> anyhow::Result<()> {
+        for dir_level in [DirLevels::Zero, DirLevels::One, DirLevels::Two] {
+            let (store, _temp) = fixtures::setup_store(dir_level).await;
+
+            // Write a file and then overwrite it twice, keeping only the last root alive
+            let mut roots = Vec::new();
+            for version in 0..3u8 {
+                let data: Vec<u8> = (0..(DEFAULT_MAX_CHUNK_SIZE * 2) as usize)
+                    .map(|i| (i % 251) as u8 ^ version)
+                    .collect();
+                let data_cid = store.put_bytes(&data[..]).await?;
+                let root = TestNode {
+                    name: format!("version {version}"),
+                    value: version as i32,
+                    refs: vec![data_cid],
+                };
+                roots.push((store.put_node(&root).await?, data_cid, data));
+            }
+
+            let block_count = store.get_block_count().await?;
+            let (live_root, live_data_cid, live_data) = roots.pop().unwrap();
+
+            let report = store.gc(&[live_root]).await?;
+            assert!(report.get_deleted_blocks() > 0);
+            assert!(report.get_freed_bytes() > 0);
+            assert_eq!(
+                report.get_kept_blocks() + report.get_deleted_blocks(),
+                block_count
+            );
+            assert_eq!(store.get_block_count().await?, report.get_kept_blocks());
+
+            // The orphans are gone
+            for (root, data_cid, _) in &roots {
+                assert!(!store.has(root).await);
+                assert!(!store.has(data_cid).await);
+            }
+
+            // The live data survives
+            let node: TestNode = store.get_node(&live_root).await?;
+            assert_eq!(node.refs, vec![live_data_cid]);
+
+            let mut retrieved = Vec::new();
+            store
+                .get_bytes(&live_data_cid)
+                .await?
+                .read_to_end(&mut retrieved)
+                .await?;
+            assert_eq!(retrieved, live_data);
+
+            // Running it again finds nothing to collect
+            let report = store.gc(&[live_root]).await?;
+            assert_eq!(report.get_deleted_blocks(), 0);
+            assert_eq!(report.get_freed_bytes(), 0);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_flatfsstore_gc_missing_root() -> anyhow::Result<()> {
+        let (store, _temp) = fixtures::setup_store(DirLevels::One).await;
+
+        let data_cid = store.put_raw_block(b"orphan".to_vec()).await?;
+        let missing = store.put_raw_block(b"missing".to_vec()).await?;
+        fs::remove_file(store.get_block_path(&missing)).await?;
+
+        let result = store.gc(&[missing]).await;
+        assert!(matches!(result, Err(StoreError::BlockNotFound(cid)) if cid == missing));
+        assert!(store.has(&data_cid).await);
+
+        // With no roots, everything is garbage
+        let report = store.gc(&[]).await?;
+        assert_eq!(report.get_deleted_blocks(), 1);
+        assert!(store.is_empty().await?);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_flatfsstore_gc_concurrent_reads() -> anyhow::Result<()> {
+        let (store, _temp) = fixtures::setup_store(DirLevels::One).await;
+
+        let data: Vec<u8> = (0..(DEFAULT_MAX_CHUNK_SIZE * 3) as usize)
+            .map(|i| (i % 255) as u8)
+            .collect();
+        let live_cid = store.put_bytes(&data[..]).await?;
+        for i in 0..10u8 {
+            store.put_raw_block(vec![i; 64]).await?;
+        }
+
+        let reader = {
+            let store = store.clone();
+            tokio::spawn(async move {
+                for _ in 0..10 {
+                    let mut retrieved = Vec::new();
+                    store
+                        .get_bytes(&live_cid)
+                        .await?
+                        .read_to_end(&mut retrieved)
+                        .await?;
+                    assert_eq!(retrieved.len(), (DEFAULT_MAX_CHUNK_SIZE * 3) as usize);
+                }
+                anyhow::Ok(())
+            })
+        };
+
+        let report = store.gc(&[live_cid]).await?;
+        reader.await??;
+
+        assert_eq!(report.get_deleted_blocks(), 10);
+        assert_eq!(store.get_bytes_size(&live_cid).await?, data.len() as u64);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_flatfsstore_gc_concurrent_writes() -> anyhow::Result<()> {
+        let (store, _temp) = fixtures::setup_store(DirLevels::One).await;
+
+        let live_cid = store.put_raw_block(b"live".to_vec()).await?;
+        let contents: Vec<Vec<u8>> = (0..500u32).map(|i| i.to_be_bytes().to_vec()).collect();
+        for content in &contents {
+            store.put_raw_block(content.clone()).await?;
+        }
+
+        // Keep writing the unreachable blocks again while they are being collected
+        let done = Arc::new(AtomicBool::new(false));
+        let writer = {
+            let store = store.clone();
+            let done = done.clone();
+            tokio::spawn(async move {
+                let mut cids = Vec::new();
+                for content in contents.iter().cycle() {
+                    if done.load(Ordering::Acquire) {
+                        break;
+                    }
+                    cids.push(store.put_raw_block(content.clone()).await?);
+                }
+                anyhow::Ok(cids)
+            })
+        };
+
+        store.gc(&[live_cid]).await?;
+        done.store(true, Ordering::Release);
+        let cids = writer.await??;
+
+        // Each write lands either before the collection, which deletes the block, or after it,
+        // which keeps it. So once a block is found written after the collection, every block
+        // written last after it must be there too.
+        let mut seen = HashSet::new();
+        let mut written_after = false;
+        for cid in cids.iter().rev().filter(|cid| seen.insert(**cid)).rev() {
+            let present = store.has(cid).await;
+            assert!(
+                present || !written_after,
+                "{cid} was deleted after being written"
+            );
+            written_after |= present;
+        }
+        assert!(store.has(&live_cid).await);
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -1130,7 +2141,22 @@ mod fixtures {
         (store, temp_dir)
     }
 
-    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    // Helper function to list every file under a directory
+    pub(super) fn list_files(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                files.extend(list_files(&path)?);
+            } else {
+                files.push(path);
+            }
+        }
+
+        Ok(files)
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
     pub(super) struct TestNode {
         pub(super) name: String,
         pub(super) value: i32,
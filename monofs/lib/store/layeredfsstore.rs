@@ -3,9 +3,10 @@ use std::{collections::HashSet, path::PathBuf, pin::Pin};
 use async_trait::async_trait;
 use bytes::Bytes;
 use ipldstore::{
-    ipld::cid::Cid, Codec, DualStore, DualStoreConfig, IpldReferences, IpldStore, RawStore,
-    StoreResult,
+    ipld::cid::Cid, CachedStore, CidConfig, Codec, DualStore, DualStoreConfig, IpldReferences,
+    IpldStore, IpldStoreSeekable, RawStore, StoreResult,
 };
+use monoutils::SeekableReader;
 use serde::{de::DeserializeOwned, Serialize};
 use tokio::io::AsyncRead;
 
@@ -29,6 +30,12 @@ use super::FlatFsStore;
 /// 2. If not found, falls back to checking the base layer
 /// 3. Returns the data from whichever layer it was found in first
 ///
+/// ## Caching
+/// A store created with [`with_cache_capacity`][LayeredFsStore::with_cache_capacity] keeps
+/// recently read blocks from both layers in memory, up to the given number of bytes, and evicts the
+/// least recently used blocks first. Whole blocks are cached, so seeking within cached data works
+/// the same as without the cache. Blocks are content-addressed, so cached blocks never go stale.
+///
 /// ## Write Behavior
 /// - All writes are directed exclusively to the write layer
 /// - The base layer remains completely immutable
@@ -56,11 +63,13 @@ use super::FlatFsStore;
 /// - The base layer's reference counting is disabled.
 #[derive(Debug, Clone)]
 pub struct LayeredFsStore {
-    inner: DualStore<
-        // Write store - mutable layer for new writes
-        FlatFsStore,
-        // Base store - immutable foundation layer
-        FlatFsStore,
+    inner: CachedStore<
+        DualStore<
+            // Write store - mutable layer for new writes
+            FlatFsStore,
+            // Base store - immutable foundation layer
+            FlatFsStore,
+        >,
     >,
 }
 
@@ -70,18 +79,52 @@ pub struct LayeredFsStore {
 
 impl LayeredFsStore {
     /// Creates a new `LayeredFsStore` with separate paths for the write and base layers.
+    ///
+    /// The store does not cache any blocks.
     pub fn new(write_store_path: impl Into<PathBuf>, base_store_path: impl Into<PathBuf>) -> Self {
+        Self::with_cache_capacity(write_store_path, base_store_path, 0)
+    }
+
+    /// Creates a new `LayeredFsStore` with separate paths for the write and base layers that caches
+    /// at most `cache_capacity` bytes of recently read blocks.
+    pub fn with_cache_capacity(
+        write_store_path: impl Into<PathBuf>,
+        base_store_path: impl Into<PathBuf>,
+        cache_capacity: u64,
+    ) -> Self {
+        let store = DualStore::new(
+            FlatFsStore::new(write_store_path),
+            FlatFsStore::builder()
+                .path(base_store_path)
+                .enable_refcount(false)
+                .build(),
+            DualStoreConfig::default(),
+        );
+
         Self {
-            inner: DualStore::new(
-                FlatFsStore::new(write_store_path),
-                FlatFsStore::builder()
-                    .path(base_store_path)
-                    .enable_refcount(false)
-                    .build(),
-                DualStoreConfig::default(),
-            ),
+            inner: CachedStore::new(store, cache_capacity),
         }
     }
+
+    /// Returns the maximum total size of the cached blocks in bytes.
+    pub fn get_cache_capacity(&self) -> u64 {
+        self.inner.get_capacity()
+    }
+
+    /// Returns the total size of the currently cached blocks in bytes.
+    pub async fn get_cached_size(&self) -> u64 {
+        self.inner.get_cached_size().await
+    }
+
+    /// Returns the number of block reads served from the cache.
+    pub async fn get_cache_hits(&self) -> u64 {
+        self.inner.get_hits().await
+    }
+
+    /// Returns the number of block reads that went to the layers.
+    pub async fn get_cache_misses(&self) -> u64 {
+        self.inner.get_misses().await
+    }
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -120,6 +163,21 @@ impl IpldStore for LayeredFsStore {
         self.inner.has(cid).await
     }
 
+    async fn put_many(&self, blocks: Vec<Bytes>) -> StoreResult<Vec<Cid>> {
+        self.inner.put_many(blocks).await
+    }
+
+    async fn put_nodes<T>(&self, nodes: &[T]) -> StoreResult<Vec<Cid>>
+    where
+        T: Serialize + IpldReferences + Sync,
+    {
+        self.inner.put_nodes(nodes).await
+    }
+
+    async fn get_many(&self, cids: &[Cid]) -> StoreResult<Vec<Option<Bytes>>> {
+        self.inner.get_many(cids).await
+    }
+
     async fn get_supported_codecs(&self) -> HashSet<Codec> {
         self.inner.get_supported_codecs().await
     }
@@ -128,6 +186,10 @@ impl IpldStore for LayeredFsStore {
         self.inner.get_max_node_block_size().await
     }
 
+    async fn get_cid_config(&self) -> CidConfig {
+        self.inner.get_cid_config().await
+    }
+
     async fn get_block_count(&self) -> StoreResult<u64> {
         self.inner.get_block_count().await
     }
@@ -147,3 +209,96 @@ impl RawStore for LayeredFsStore {
         self.inner.get_max_raw_block_size().await
     }
 }
+
+#[async_trait]
+impl IpldStoreSeekable for LayeredFsStore {
+    async fn get_seekable_bytes(
+        &self,
+        cid: &Cid,
+    ) -> StoreResult<Pin<Box<dyn SeekableReader + Send + 'static>>> {
+        self.inner.get_seekable_bytes(cid).await
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+    use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_layeredfsstore_caches_reads() -> anyhow::Result<()> {
+        let write_dir = TempDir::new()?;
+        let base_dir = TempDir::new()?;
+
+        // Put a block in the base layer
+        let base = FlatFsStore::builder()
+            .path(base_dir.path())
+            .enable_refcount(false)
+            .build();
+        let base_cid = base.put_raw_block(b"base block".to_vec()).await?;
+
+        let store = LayeredFsStore::with_cache_capacity(write_dir.path(), base_dir.path(), 1024);
+        let write_cid = store.put_raw_block(b"write block".to_vec()).await?;
+
+        // Repeated reads from either layer are served from the cache
+        for _ in 0..3 {
+            assert_eq!(
+                store.get_raw_block(&base_cid).await?.as_ref(),
+                b"base block"
+            );
+            assert_eq!(
+                store.get_raw_block(&write_cid).await?.as_ref(),
+                b"write block"
+            );
+        }
+        assert_eq!(store.get_cache_misses().await, 2);
+        assert_eq!(store.get_cache_hits().await, 4);
+        assert_eq!(store.get_cached_size().await, 21);
+
+        // Seeking within cached chunked data
+        let data = b"Hello, layered world!".to_vec();
+        let cid = store.put_bytes(data.as_slice()).await?;
+        for _ in 0..2 {
+            let mut reader = store.get_seekable_bytes(&cid).await?;
+            reader.seek(std::io::SeekFrom::Start(7)).await?;
+            let mut retrieved = Vec::new();
+            reader.read_to_end(&mut retrieved).await?;
+            assert_eq!(retrieved, b"layered world!");
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_layeredfsstore_cache_respects_capacity() -> anyhow::Result<()> {
+        let write_dir = TempDir::new()?;
+        let base_dir = TempDir::new()?;
+        let store = LayeredFsStore::with_cache_capacity(write_dir.path(), base_dir.path(), 10);
+
+        let a = store.put_raw_block(vec![b'a'; 6]).await?;
+        let b = store.put_raw_block(vec![b'b'; 6]).await?;
+
+        // Only one block fits, so reading `b` evicts `a`
+        store.get_raw_block(&a).await?;
+        store.get_raw_block(&b).await?;
+        assert_eq!(store.get_cached_size().await, 6);
+        store.get_raw_block(&a).await?;
+        assert_eq!(store.get_cache_hits().await, 0);
+        assert_eq!(store.get_cache_misses().await, 3);
+
+        // Without a cache, nothing is kept
+        let store = LayeredFsStore::new(write_dir.path(), base_dir.path());
+        store.get_raw_block(&a).await?;
+        store.get_raw_block(&a).await?;
+        assert_eq!(store.get_cached_size().await, 0);
+        assert_eq!(store.get_cache_hits().await, 0);
+
+        Ok(())
+    }
+}
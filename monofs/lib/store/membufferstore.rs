@@ -1,15 +1,29 @@
-use std::{collections::HashSet, pin::Pin};
+use std::{
+    collections::HashSet,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
 use async_trait::async_trait;
 use bytes::Bytes;
+use getset::{CopyGetters, Getters};
 use ipldstore::{
     ipld::{cid::Cid, ipld::Ipld},
-    Codec, DualStore, DualStoreConfig, IpldReferences, IpldStore, MemoryStore, RawStore,
+    CidConfig, Codec, DualStore, DualStoreConfig, IpldReferences, IpldStore, MemoryStore, RawStore,
     StoreError, StoreResult,
 };
 use serde::{de::DeserializeOwned, Serialize};
 use serde_ipld_dagcbor;
-use tokio::io::AsyncRead;
+use tokio::{
+    io::AsyncRead,
+    sync::Mutex,
+    task::JoinHandle,
+    time::{self, Instant, MissedTickBehavior},
+};
 
 use crate::FsResult;
 
@@ -17,20 +31,24 @@ use crate::FsResult;
 // Types: MemoryBufferStore
 //--------------------------------------------------------------------------------------------------
 
-/// A write-through caching [`IpldStore`] that combines an in-memory write buffer with a persistent store.
+/// A write-back caching [`IpldStore`] that combines an in-memory write buffer with a persistent store.
 ///
-/// This store implements a write-through caching pattern with two layers:
+/// This store implements a write-back caching pattern with two layers:
 /// 1. A fast, ephemeral in-memory buffer store for writes
 /// 2. A persistent underlying store that serves as the source of truth
 ///
 /// ## Write Behavior
 /// - All writes go to the memory buffer first
-/// - Data in the buffer can be explicitly flushed to the underlying store using [`flush`](Self::flush)
-/// - The buffer is cleared after a successful flush
+/// - Buffered blocks are moved to the underlying store by [`flush`](Self::flush), either when it
+///   is called explicitly or automatically as configured by the [`FlushPolicy`]
+/// - Flushed blocks are removed from the buffer, while blocks written during a flush stay buffered
+///   for the next one
 ///
 /// ## Read Behavior
 /// - Reads check the memory buffer first
 /// - If not found in the buffer, falls back to reading from the underlying store
+/// - A block is only removed from the buffer once it has been written to the underlying store, so
+///   buffered blocks are readable at all times, including during a flush
 ///
 /// ## Use Cases
 /// This store is particularly useful when you need to:
@@ -41,7 +59,8 @@ use crate::FsResult;
 /// ## Example
 /// ```ignore
 /// let underlying_store = FlatFsStore::new(path);
-/// let buffer_store = MemoryBufferStore::new(underlying_store);
+/// let buffer_store = MemoryBufferStore::new(underlying_store)
+///     .with_flush_policy(FlushPolicy::SizeThreshold(64 * 1024 * 1024));
 ///
 /// // Write structured data using put_node
 /// let node = MyStruct { /* ... */ };
@@ -51,7 +70,7 @@ use crate::FsResult;
 /// let bytes = /* ... */;
 /// let bytes_cid = buffer_store.put_bytes(bytes).await?;
 ///
-/// // Later, flush buffer to underlying store
+/// // Later, flush the rest of the buffer to the underlying store
 /// let blocks_flushed = buffer_store.flush().await?;
 /// ```
 ///
@@ -62,7 +81,7 @@ use crate::FsResult;
 ///
 /// Avoid using [`put_raw_block`](RawStore::put_raw_block) directly as it's a low-level API
 /// intended for implementing stores, not for general use.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Getters)]
 pub struct MemoryBufferStore<S>
 where
     S: IpldStore,
@@ -73,17 +92,77 @@ where
         // Underlying store
         S,
     >,
+
+    /// When buffered blocks are flushed to the underlying store.
+    #[getset(get = "pub with_prefix")]
+    flush_policy: FlushPolicy,
+
+    /// The flush lock and counters, shared between clones of the store.
+    flush_state: Arc<FlushState>,
+
+    /// The task that flushes the buffer periodically, if the policy asks for one.
+    flush_task: Option<Arc<FlushTask>>,
 }
 
+/// Determines when a [`MemoryBufferStore`] flushes its buffered blocks to the underlying store.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlushPolicy {
+    /// Blocks are only flushed when [`flush`](MemoryBufferStore::flush) is called.
+    #[default]
+    Manual,
+
+    /// The buffer is flushed after a write once it holds at least this many bytes.
+    SizeThreshold(u64),
+
+    /// The buffer is flushed in the background at this interval.
+    Periodic(Duration),
+}
+
+/// A snapshot of the state of a [`MemoryBufferStore`]'s buffer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, CopyGetters)]
+#[getset(get_copy = "pub with_prefix")]
+pub struct MemoryBufferStoreMetrics {
+    /// The number of blocks in the buffer.
+    buffered_blocks: u64,
+
+    /// The total size of the blocks in the buffer.
+    buffered_bytes: u64,
+
+    /// The number of flushes that have completed.
+    flushes: u64,
+
+    /// The number of blocks written to the underlying store by flushes.
+    blocks_flushed: u64,
+}
+
+/// State shared by all clones of a [`MemoryBufferStore`] and its flush task.
+#[derive(Debug, Default)]
+struct FlushState {
+    /// Serializes flushes, so the same blocks aren't written twice.
+    lock: Mutex<()>,
+
+    /// The number of flushes that have completed.
+    flushes: AtomicU64,
+
+    /// The number of blocks written to the underlying store by flushes.
+    blocks_flushed: AtomicU64,
+}
+
+/// A periodic flush task that is stopped when the last clone of its store is dropped.
+#[derive(Debug)]
+struct FlushTask(JoinHandle<()>);
+
 //--------------------------------------------------------------------------------------------------
 // Methods: MemoryBufferStore
 //--------------------------------------------------------------------------------------------------
 
 impl<S> MemoryBufferStore<S>
 where
-    S: IpldStore,
+    S: IpldStore + Sync,
 {
     /// Creates a new `MemoryBufferStore` with the given underlying store.
+    ///
+    /// The store starts with the [`FlushPolicy::Manual`] policy.
     pub fn new(underlying_store: S) -> Self {
         Self {
             inner: DualStore::new(
@@ -91,73 +170,179 @@ where
                 underlying_store,
                 DualStoreConfig::default(),
             ),
+            flush_policy: FlushPolicy::Manual,
+            flush_state: Arc::new(FlushState::default()),
+            flush_task: None,
         }
     }
 
-    /// Flushes all blocks from the memory buffer to the underlying store and clears the buffer.
+    /// Flushes all blocks from the memory buffer to the underlying store and removes them from the
+    /// buffer.
     ///
     /// This method will:
     /// 1. Copy all blocks from the memory buffer to the underlying store, preserving their codec type
     /// 2. For DagCbor blocks, properly handle IPLD references
-    /// 3. Clear the memory buffer after successful copying
+    /// 3. Remove the copied blocks from the buffer after successful copying
+    ///
+    /// Blocks written while the flush is running are left in the buffer.
     ///
     /// ## Returns
     ///
     /// Returns the number of blocks that were flushed to the underlying store.
     pub async fn flush(&self) -> FsResult<u64> {
-        let memory_store = self.inner.get_store_a();
-        let underlying_store = self.inner.get_store_b();
+        Ok(Self::flush_buffer(&self.inner, &self.flush_state).await?)
+    }
+
+    /// Returns a snapshot of the buffer's size and the flushes done so far.
+    pub async fn get_metrics(&self) -> MemoryBufferStoreMetrics {
+        let blocks = self.inner.get_store_a().get_blocks().read().await;
+        MemoryBufferStoreMetrics {
+            buffered_blocks: blocks.len() as u64,
+            buffered_bytes: blocks.values().map(|(_, bytes)| bytes.len() as u64).sum(),
+            flushes: self.flush_state.flushes.load(Ordering::Relaxed),
+            blocks_flushed: self.flush_state.blocks_flushed.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns the total size of the blocks in the buffer.
+    pub async fn get_buffered_bytes(&self) -> u64 {
+        let blocks = self.inner.get_store_a().get_blocks().read().await;
+        blocks.values().map(|(_, bytes)| bytes.len() as u64).sum()
+    }
+
+    /// Flushes the buffer if the flush policy's size threshold has been reached.
+    async fn flush_if_needed(&self) -> StoreResult<()> {
+        let FlushPolicy::SizeThreshold(threshold) = self.flush_policy else {
+            return Ok(());
+        };
+
+        if self.get_buffered_bytes().await >= threshold {
+            Self::flush_buffer(&self.inner, &self.flush_state).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Moves the blocks in the buffer of `inner` to its underlying store.
+    async fn flush_buffer(
+        inner: &DualStore<MemoryStore, S>,
+        flush_state: &FlushState,
+    ) -> StoreResult<u64> {
+        let _guard = flush_state.lock.lock().await;
+        let memory_store = inner.get_store_a();
+        let underlying_store = inner.get_store_b();
         let mut blocks_flushed = 0;
 
-        // Get all CIDs from memory store
-        let block_count = memory_store.get_block_count().await?;
-        if block_count == 0 {
+        // Take a snapshot of the buffer, so it stays readable and writable while blocks are copied
+        let blocks = memory_store
+            .get_blocks()
+            .read()
+            .await
+            .iter()
+            .map(|(cid, (_, block_data))| (*cid, block_data.clone()))
+            .collect::<Vec<_>>();
+
+        if blocks.is_empty() {
             return Ok(0);
         }
 
-        // Get a reference to the blocks in memory store
-        let blocks = memory_store.get_blocks().read().await;
+        // Check which blocks already exist in the underlying store all at once
+        let cids = blocks.iter().map(|(cid, _)| *cid).collect::<Vec<_>>();
+        let present = underlying_store.has_many(&cids).await?;
 
         // For each block in memory store
-        for (cid, (_, block_data)) in blocks.iter() {
+        let mut raw_blocks = Vec::new();
+        let mut nodes = Vec::new();
+        for ((cid, block_data), present) in blocks.iter().zip(present) {
             // Skip if block already exists in underlying store
-            if underlying_store.has(cid).await {
+            if present {
                 continue;
             }
 
             // Handle the block based on its codec
             match cid.codec().try_into()? {
-                // For raw blocks, just copy them directly
-                Codec::Raw => {
-                    underlying_store.put_raw_block(block_data.clone()).await?;
-                    blocks_flushed += 1;
-                }
-                // For DagCbor blocks, deserialize to preserve references
-                Codec::DagCbor => {
-                    // Deserialize the block to Ipld to preserve references
-                    let ipld: Ipld = serde_ipld_dagcbor::from_slice(block_data)?;
-
-                    // Put the node in the underlying store, which will handle reference counting
-                    underlying_store.put_node(&ipld).await?;
-                    blocks_flushed += 1;
-                }
+                // For raw blocks, copy them directly all at once
+                Codec::Raw => raw_blocks.push(block_data.clone()),
+                // For DagCbor blocks, deserialize them below to preserve references
+                Codec::DagCbor => nodes.push(block_data),
                 // Return error for unsupported codecs
                 codec => {
-                    return Err(StoreError::UnexpectedBlockCodec(Codec::DagCbor, codec).into());
+                    return Err(StoreError::UnexpectedBlockCodec(Codec::DagCbor, codec));
                 }
             }
         }
 
-        // Drop the read lock before clearing
-        drop(blocks);
+        // Raw blocks go first, so they are in place when the nodes referencing them are counted
+        blocks_flushed += underlying_store.put_many(raw_blocks).await?.len() as u64;
+
+        // Deserialize the nodes to Ipld to preserve references, and put them in the underlying
+        // store all at once, which will handle reference counting
+        let nodes = nodes
+            .into_iter()
+            .map(|block_data| serde_ipld_dagcbor::from_slice(block_data))
+            .collect::<Result<Vec<Ipld>, _>>()
+            .map_err(StoreError::custom)?;
+        blocks_flushed += underlying_store.put_nodes(&nodes).await?.len() as u64;
+
+        // Remove only the flushed blocks, keeping any written since the snapshot
+        let mut buffered = memory_store.get_blocks().write().await;
+        for cid in cids {
+            buffered.remove(&cid);
+        }
 
-        // Clear the memory store after successful flush
-        memory_store.clear().await?;
+        flush_state.flushes.fetch_add(1, Ordering::Relaxed);
+        flush_state
+            .blocks_flushed
+            .fetch_add(blocks_flushed, Ordering::Relaxed);
 
         Ok(blocks_flushed)
     }
 }
 
+impl<S> MemoryBufferStore<S>
+where
+    S: IpldStore + Send + Sync + 'static,
+{
+    /// Sets when buffered blocks are flushed to the underlying store.
+    ///
+    /// A [`FlushPolicy::Periodic`] policy spawns a background task that flushes the buffer at the
+    /// given interval until the last clone of the store is dropped. Flush errors in the background
+    /// task are logged and retried at the next interval.
+    ///
+    /// ## Panics
+    ///
+    /// Panics if the policy is [`FlushPolicy::Periodic`] and this is not called from within a
+    /// Tokio runtime.
+    pub fn with_flush_policy(mut self, flush_policy: FlushPolicy) -> Self {
+        self.flush_policy = flush_policy;
+        self.flush_task = match flush_policy {
+            FlushPolicy::Periodic(period) => Some(Arc::new(FlushTask(tokio::spawn(
+                Self::flush_periodically(self.inner.clone(), self.flush_state.clone(), period),
+            )))),
+            _ => None,
+        };
+
+        self
+    }
+
+    /// Flushes the buffer of `inner` every `period`.
+    async fn flush_periodically(
+        inner: DualStore<MemoryStore, S>,
+        flush_state: Arc<FlushState>,
+        period: Duration,
+    ) {
+        let mut interval = time::interval_at(Instant::now() + period, period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            if let Err(e) = Self::flush_buffer(&inner, &flush_state).await {
+                tracing::error!("failed to flush memory buffer: {}", e);
+            }
+        }
+    }
+}
+
 //--------------------------------------------------------------------------------------------------
 // Trait Implementations
 //--------------------------------------------------------------------------------------------------
@@ -171,11 +356,15 @@ where
     where
         T: Serialize + IpldReferences + Sync,
     {
-        self.inner.put_node(data).await
+        let cid = self.inner.put_node(data).await?;
+        self.flush_if_needed().await?;
+        Ok(cid)
     }
 
     async fn put_bytes(&self, reader: impl AsyncRead + Send + Sync) -> StoreResult<Cid> {
-        self.inner.put_bytes(reader).await
+        let cid = self.inner.put_bytes(reader).await?;
+        self.flush_if_needed().await?;
+        Ok(cid)
     }
 
     async fn get_node<T>(&self, cid: &Cid) -> StoreResult<T>
@@ -197,6 +386,25 @@ where
         self.inner.has(cid).await
     }
 
+    async fn put_many(&self, blocks: Vec<Bytes>) -> StoreResult<Vec<Cid>> {
+        let cids = self.inner.put_many(blocks).await?;
+        self.flush_if_needed().await?;
+        Ok(cids)
+    }
+
+    async fn put_nodes<T>(&self, nodes: &[T]) -> StoreResult<Vec<Cid>>
+    where
+        T: Serialize + IpldReferences + Sync,
+    {
+        let cids = self.inner.put_nodes(nodes).await?;
+        self.flush_if_needed().await?;
+        Ok(cids)
+    }
+
+    async fn get_many(&self, cids: &[Cid]) -> StoreResult<Vec<Option<Bytes>>> {
+        self.inner.get_many(cids).await
+    }
+
     async fn get_supported_codecs(&self) -> HashSet<Codec> {
         self.inner.get_supported_codecs().await
     }
@@ -205,6 +413,10 @@ where
         self.inner.get_max_node_block_size().await
     }
 
+    async fn get_cid_config(&self) -> CidConfig {
+        self.inner.get_cid_config().await
+    }
+
     async fn get_block_count(&self) -> StoreResult<u64> {
         self.inner.get_block_count().await
     }
@@ -216,7 +428,9 @@ where
     S: IpldStore + Sync,
 {
     async fn put_raw_block(&self, bytes: impl Into<Bytes> + Send) -> StoreResult<Cid> {
-        self.inner.put_raw_block(bytes).await
+        let cid = self.inner.put_raw_block(bytes).await?;
+        self.flush_if_needed().await?;
+        Ok(cid)
     }
 
     async fn get_raw_block(&self, cid: &Cid) -> StoreResult<Bytes> {
@@ -228,6 +442,12 @@ where
     }
 }
 
+impl Drop for FlushTask {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
 //--------------------------------------------------------------------------------------------------
 // Tests
 //--------------------------------------------------------------------------------------------------
@@ -235,7 +455,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use ipldstore::MemoryStore;
+    use ipldstore::{IpldStoreExt, MemoryStore};
 
     use super::helper::TestNode;
 
@@ -386,6 +606,104 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_memory_buffer_store_reads_unflushed_data() -> anyhow::Result<()> {
+        let underlying_store = MemoryStore::default();
+        let buffer_store = MemoryBufferStore::new(underlying_store.clone());
+
+        let data = b"unflushed data".to_vec();
+        let cid = buffer_store.put_bytes(data.as_slice()).await?;
+        let node = TestNode {
+            name: "node".to_string(),
+            value: 1,
+            refs: vec![cid],
+        };
+        let node_cid = buffer_store.put_node(&node).await?;
+
+        // Unflushed blocks are only in the buffer, but readable through the store
+        assert!(!underlying_store.has(&cid).await);
+        assert!(!underlying_store.has(&node_cid).await);
+        assert_eq!(buffer_store.read_all(&cid).await?.as_ref(), data.as_slice());
+        assert_eq!(buffer_store.get_node::<TestNode>(&node_cid).await?, node);
+
+        // The bytes are stored as a chunk plus its layout node, alongside the test node
+        let metrics = buffer_store.get_metrics().await;
+        assert_eq!(metrics.get_buffered_blocks(), 3);
+        assert!(metrics.get_buffered_bytes() >= data.len() as u64);
+        assert_eq!(metrics.get_flushes(), 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_memory_buffer_store_size_threshold_flush() -> anyhow::Result<()> {
+        let underlying_store = MemoryStore::default();
+        let buffer_store = MemoryBufferStore::new(underlying_store.clone())
+            .with_flush_policy(FlushPolicy::SizeThreshold(16));
+
+        // Writes below the threshold stay buffered
+        let cid1 = buffer_store.put_raw_block(b"0123456789".to_vec()).await?;
+        assert!(!underlying_store.has(&cid1).await);
+        assert_eq!(buffer_store.get_buffered_bytes().await, 10);
+
+        // Reaching the threshold flushes everything buffered so far
+        let cid2 = buffer_store.put_raw_block(b"abcdefghij".to_vec()).await?;
+        assert!(underlying_store.has(&cid1).await);
+        assert!(underlying_store.has(&cid2).await);
+
+        let metrics = buffer_store.get_metrics().await;
+        assert_eq!(metrics.get_buffered_blocks(), 0);
+        assert_eq!(metrics.get_buffered_bytes(), 0);
+        assert_eq!(metrics.get_flushes(), 1);
+        assert_eq!(metrics.get_blocks_flushed(), 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_memory_buffer_store_periodic_flush() -> anyhow::Result<()> {
+        let underlying_store = MemoryStore::default();
+        let buffer_store = MemoryBufferStore::new(underlying_store.clone())
+            .with_flush_policy(FlushPolicy::Periodic(Duration::from_millis(10)));
+
+        let cid = buffer_store.put_raw_block(b"periodic".to_vec()).await?;
+
+        // Wait for the background task to flush the block
+        for _ in 0..100 {
+            if underlying_store.has(&cid).await {
+                break;
+            }
+            time::sleep(Duration::from_millis(10)).await;
+        }
+
+        assert!(underlying_store.has(&cid).await);
+        assert_eq!(buffer_store.get_buffered_bytes().await, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_memory_buffer_store_flush_empties_buffer() -> anyhow::Result<()> {
+        let underlying_store = MemoryStore::default();
+        let buffer_store = MemoryBufferStore::new(underlying_store.clone());
+
+        buffer_store.put_raw_block(b"block 1".to_vec()).await?;
+        buffer_store.put_raw_block(b"block 2".to_vec()).await?;
+        assert_eq!(buffer_store.get_metrics().await.get_buffered_blocks(), 2);
+        assert_eq!(buffer_store.get_buffered_bytes().await, 14);
+
+        assert_eq!(buffer_store.flush().await?, 2);
+
+        let metrics = buffer_store.get_metrics().await;
+        assert_eq!(metrics.get_buffered_blocks(), 0);
+        assert_eq!(metrics.get_buffered_bytes(), 0);
+        assert_eq!(metrics.get_flushes(), 1);
+        assert_eq!(metrics.get_blocks_flushed(), 2);
+        assert_eq!(underlying_store.get_block_count().await?, 2);
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,24 @@
+//! Test data shared by the unit tests.
+
+use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The seed of [`random_bytes`], fixed so that failures reproduce.
+const RANDOM_BYTES_SEED: u64 = 0x2545_f491_4f6c_dd1d;
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Returns `len` pseudo-random bytes.
+///
+/// The bytes don't repeat, so content-defined chunking finds boundaries in them and no chunk gets
+/// deduplicated.
+pub(crate) fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0; len];
+    StdRng::seed_from_u64(RANDOM_BYTES_SEED).fill_bytes(&mut bytes);
+    bytes
+}
@@ -2,6 +2,8 @@
 
 pub mod dir;
 pub mod env;
+#[cfg(test)]
+pub(crate) mod fixtures;
 pub mod path;
 
 //--------------------------------------------------------------------------------------------------
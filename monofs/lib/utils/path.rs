@@ -20,6 +20,9 @@ pub const BLOCKS_SUBDIR: &str = "blocks";
 /// The filename of the database that stores the filesystem's metadata
 pub const FS_DB_FILENAME: &str = "fs.db";
 
+/// The filename of the log the NFS server's fileids are persisted in
+pub const FILEID_LOG_FILENAME: &str = "fileids.log";
+
 /// The name of the symlink that links to the actual filesystem data
 pub const MFS_LINK_FILENAME: &str = ".mfs_link";
 
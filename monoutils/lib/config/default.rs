@@ -1,6 +1,41 @@
+use std::time::Duration;
+
 //--------------------------------------------------------------------------------------------------
 // Constants
 //--------------------------------------------------------------------------------------------------
 
 /// Default maximum log file size (10MB)
 pub const DEFAULT_LOG_MAX_SIZE: u64 = 10 * 1024 * 1024;
+
+/// Default maximum number of rotated log files to keep
+pub const DEFAULT_LOG_MAX_FILES: usize = 1;
+
+/// Default interval at which followed log files are checked for new lines
+pub const DEFAULT_TAIL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Default time a supervised child is given to exit after SIGTERM before it is sent SIGKILL
+pub const DEFAULT_SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// Default interval at which a supervised child is checked for having exited
+pub const DEFAULT_CHILD_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Default delay before the first restart of a supervised child
+pub const DEFAULT_RESTART_BACKOFF_BASE: Duration = Duration::from_secs(1);
+
+/// Default upper bound on the delay between restarts of a supervised child
+pub const DEFAULT_RESTART_BACKOFF_CAP: Duration = Duration::from_secs(60);
+
+/// Default number of times a supervised child is restarted before giving up
+pub const DEFAULT_RESTART_MAX_RETRIES: u32 = 5;
+
+/// Default time a supervised child has to stay up for its restart count to be reset
+pub const DEFAULT_RESTART_RESET_AFTER: Duration = Duration::from_secs(60);
+
+/// Default time a supervised child is given to report that it is ready
+pub const DEFAULT_BOOT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Default interval at which a ready supervised child is probed for health
+pub const DEFAULT_HEALTH_PROBE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Default number of consecutive failed health probes after which a child is unhealthy
+pub const DEFAULT_HEALTH_FAILURE_THRESHOLD: u32 = 3;
@@ -0,0 +1,373 @@
+//! Layered configuration loading.
+//!
+//! [`load_layered`] reads a configuration file and overlays environment variables onto it, so a
+//! deployment can override any field without editing the file.
+
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::{MonoutilsError, MonoutilsResult};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The separator between the names of nested fields in an environment variable name.
+const ENV_NESTING_SEPARATOR: &str = "__";
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Loads a configuration of type `T` from `file`, with fields overridden by environment variables.
+///
+/// The file is parsed as TOML, YAML or JSON, based on its extension. Then every environment
+/// variable named `<env_prefix>_<FIELD>` overrides the field it names. Nested fields are separated
+/// by a double underscore, so with the prefix `MONOCORE`, `MONOCORE_SERVER__PORT=8080` sets
+/// `server.port`.
+///
+/// A field that is already in the file is matched case-insensitively, and keeps the key the file
+/// spells it with. A field missing from the file must be named exactly as `T` deserializes it,
+/// e.g. `MONOCORE_server__maxConnections` for a camelCase field.
+///
+/// Values from the environment are converted to the type of the value they override in the file.
+/// Values for fields missing from the file are read as JSON if possible, and as strings otherwise.
+///
+/// ## Arguments
+///
+/// * `file` - The configuration file, with a `.toml`, `.yaml`, `.yml` or `.json` extension
+/// * `env_prefix` - The prefix of the environment variables that override fields
+///
+/// ## Errors
+///
+/// Returns `MonoutilsError::Config` if the file can't be parsed, an environment variable can't be
+/// converted to the type of the field it overrides, or the result doesn't match `T`. The message
+/// names the offending field.
+///
+/// ## Examples
+///
+/// ```no_run
+/// use std::path::Path;
+///
+/// use monoutils::config::load_layered;
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///     server: Server,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Server {
+///     port: u16,
+/// }
+///
+/// # fn main() -> anyhow::Result<()> {
+/// // MONOCORE_SERVER__PORT overrides `server.port` in the file
+/// let config: Config = load_layered(Path::new("monocore.toml"), "MONOCORE")?;
+/// println!("listening on {}", config.server.port);
+/// # Ok(())
+/// # }
+/// ```
+pub fn load_layered<T: DeserializeOwned>(file: &Path, env_prefix: &str) -> MonoutilsResult<T> {
+    load_layered_with_vars(file, env_prefix, std::env::vars())
+}
+
+/// Loads a configuration like [`load_layered`], taking the environment variables from `vars`.
+fn load_layered_with_vars<T: DeserializeOwned>(
+    file: &Path,
+    env_prefix: &str,
+    vars: impl IntoIterator<Item = (String, String)>,
+) -> MonoutilsResult<T> {
+    let mut value = read_config_file(file)?;
+
+    let prefix = format!("{}_", env_prefix);
+    let mut overrides = vars
+        .into_iter()
+        .filter_map(|(key, raw)| Some((key.strip_prefix(&prefix)?.to_string(), key, raw)))
+        .collect::<Vec<_>>();
+
+    // Apply overrides in a stable order, so nested fields are set after their parents
+    overrides.sort();
+    for (name, key, raw) in overrides {
+        let fields = name.split(ENV_NESTING_SEPARATOR).collect::<Vec<_>>();
+        if fields.iter().any(|field| field.is_empty()) {
+            return Err(MonoutilsError::Config(format!(
+                "environment variable {key} does not name a field"
+            )));
+        }
+
+        apply_override(&mut value, &fields, &key, &raw)?;
+    }
+
+    serde_path_to_error::deserialize(value).map_err(|e| {
+        MonoutilsError::Config(format!("invalid value for `{}`: {}", e.path(), e.inner()))
+    })
+}
+
+/// Reads a configuration file into a JSON value, choosing the format by extension.
+fn read_config_file(file: &Path) -> MonoutilsResult<Value> {
+    let content = std::fs::read_to_string(file)?;
+    let extension = file
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+
+    let value = match extension {
+        "toml" => toml::from_str(&content).map_err(|e| e.to_string()),
+        "yaml" | "yml" => serde_yaml::from_str(&content).map_err(|e| e.to_string()),
+        "json" => serde_json::from_str(&content).map_err(|e| e.to_string()),
+        _ => {
+            return Err(MonoutilsError::Config(format!(
+                "unsupported config file extension: {}",
+                file.display()
+            )))
+        }
+    };
+
+    value.map_err(|e| MonoutilsError::Config(format!("failed to parse {}: {e}", file.display())))
+}
+
+/// Sets the field at `fields` in `value` to `raw`, converted to the type of the value it replaces.
+fn apply_override(value: &mut Value, fields: &[&str], key: &str, raw: &str) -> MonoutilsResult<()> {
+    let mut path = Vec::with_capacity(fields.len());
+    let mut current = value;
+    for field in fields {
+        if current.is_null() {
+            *current = Value::Object(Map::new());
+        }
+
+        let Value::Object(map) = current else {
+            return Err(MonoutilsError::Config(format!(
+                "{key} sets `{}`, but `{}` is not a table",
+                fields.join("."),
+                path.join(".")
+            )));
+        };
+
+        let field = resolve_field(map, field);
+        path.push(field.clone());
+        current = map.entry(field).or_insert(Value::Null);
+    }
+
+    let path = path.join(".");
+    let coerced = coerce(current, raw).ok_or_else(|| {
+        MonoutilsError::Config(format!(
+            "{key} sets `{path}` to `{raw}`, which is not a valid {}",
+            type_name(current)
+        ))
+    })?;
+    *current = coerced;
+
+    Ok(())
+}
+
+/// Returns the key in `map` that `field` names.
+///
+/// An exact match wins over a case-insensitive one. A field missing from `map` is used as is.
+fn resolve_field(map: &Map<String, Value>, field: &str) -> String {
+    if map.contains_key(field) {
+        return field.to_string();
+    }
+
+    map.keys()
+        .find(|key| key.eq_ignore_ascii_case(field))
+        .cloned()
+        .unwrap_or_else(|| field.to_string())
+}
+
+/// Converts `raw` to the type of `existing`, or guesses its type if there is no existing value.
+fn coerce(existing: &Value, raw: &str) -> Option<Value> {
+    match existing {
+        Value::Null => Some(serde_json::from_str(raw).unwrap_or_else(|_| raw.into())),
+        Value::Bool(_) => raw.parse::<bool>().ok().map(Value::Bool),
+        Value::Number(_) => serde_json::from_str::<serde_json::Number>(raw)
+            .ok()
+            .map(Value::Number),
+        Value::String(_) => Some(raw.into()),
+        Value::Array(_) => serde_json::from_str(raw).ok().filter(Value::is_array),
+        Value::Object(_) => serde_json::from_str(raw).ok().filter(Value::is_object),
+    }
+}
+
+/// Returns the name of the type of a JSON value, for error messages.
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "value",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "table",
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        name: String,
+        server: Server,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Server {
+        host: String,
+        port: u16,
+        #[serde(default)]
+        tls: bool,
+    }
+
+    #[test]
+    fn test_load_layered_env_overrides_file() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let file = temp_dir.path().join("config.toml");
+        std::fs::write(
+            &file,
+            "name = \"sandbox\"\n\n[server]\nhost = \"127.0.0.1\"\nport = 3000\n",
+        )?;
+
+        // A prefix unique to this test, so other tests can't see or change the variable
+        std::env::set_var("LAYERED_TEST_SERVER__PORT", "8080");
+        let config: Config = load_layered(&file, "LAYERED_TEST")?;
+        std::env::remove_var("LAYERED_TEST_SERVER__PORT");
+
+        assert_eq!(
+            config,
+            Config {
+                name: "sandbox".to_string(),
+                server: Server {
+                    host: "127.0.0.1".to_string(),
+                    port: 8080,
+                    tls: false,
+                },
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_layered_yaml_and_missing_fields() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let file = temp_dir.path().join("config.yaml");
+        std::fs::write(&file, "name: sandbox\nserver:\n  host: localhost\n")?;
+
+        // Fields missing from the file are named exactly, the others in any case
+        let vars = [
+            ("APP_SERVER__port", "9000"),
+            ("APP_SERVER__tls", "true"),
+            ("APP_NAME", "1234"),
+            ("OTHER_NAME", "ignored"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+
+        let config: Config = load_layered_with_vars(&file, "APP", vars)?;
+        assert_eq!(config.name, "1234");
+        assert_eq!(config.server.host, "localhost");
+        assert_eq!(config.server.port, 9000);
+        assert!(config.server.tls);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_layered_matches_serde_field_names() -> anyhow::Result<()> {
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(rename_all = "camelCase")]
+        struct Limits {
+            max_connections: u32,
+            #[serde(rename = "idle-timeout", default)]
+            idle_timeout: u64,
+            #[serde(default)]
+            read_only: bool,
+        }
+
+        let temp_dir = TempDir::new()?;
+        let file = temp_dir.path().join("config.yaml");
+        std::fs::write(
+            &file,
+            "maxConnections: 10
+",
+        )?;
+
+        let vars = [
+            ("APP_MAXCONNECTIONS", "20"),
+            ("APP_idle-timeout", "30"),
+            ("APP_readOnly", "true"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+
+        let limits: Limits = load_layered_with_vars(&file, "APP", vars)?;
+        assert_eq!(
+            limits,
+            Limits {
+                max_connections: 20,
+                idle_timeout: 30,
+                read_only: true,
+            }
+        );
+
+        // A lowercased name doesn't match a camelCase field missing from the file
+        let vars = [("APP_READONLY".to_string(), "true".to_string())];
+        let limits: Limits = load_layered_with_vars(&file, "APP", vars)?;
+        assert!(!limits.read_only);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_layered_errors_name_the_field() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let file = temp_dir.path().join("config.json");
+        std::fs::write(
+            &file,
+            r#"{"name": "sandbox", "server": {"host": "localhost", "port": 3000}}"#,
+        )?;
+
+        // The environment variable doesn't match the type in the file
+        let vars = [("APP_SERVER__PORT".to_string(), "http".to_string())];
+        let err = load_layered_with_vars::<Config>(&file, "APP", vars).unwrap_err();
+        assert!(
+            matches!(&err, MonoutilsError::Config(m) if m.contains("APP_SERVER__PORT") && m.contains("`server.port`") && m.contains("number")),
+            "{err}"
+        );
+
+        // The value doesn't fit the field's type
+        let vars = [("APP_SERVER__PORT".to_string(), "70000".to_string())];
+        let err = load_layered_with_vars::<Config>(&file, "APP", vars).unwrap_err();
+        assert!(
+            matches!(&err, MonoutilsError::Config(m) if m.contains("`server.port`")),
+            "{err}"
+        );
+
+        // A field nested in a scalar
+        let vars = [("APP_NAME__FIRST".to_string(), "x".to_string())];
+        let err = load_layered_with_vars::<Config>(&file, "APP", vars).unwrap_err();
+        assert!(
+            matches!(&err, MonoutilsError::Config(m) if m.contains("`name` is not a table")),
+            "{err}"
+        );
+
+        // Unsupported file format
+        let file = temp_dir.path().join("config.ini");
+        std::fs::write(&file, "")?;
+        assert!(matches!(
+            load_layered::<Config>(&file, "APP"),
+            Err(MonoutilsError::Config(_))
+        ));
+
+        Ok(())
+    }
+}
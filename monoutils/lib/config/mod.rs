@@ -1,9 +1,11 @@
 //! `monoutils::config` is a module containing configuration utilities for the monocore project.
 
 mod default;
+mod layered;
 
 //--------------------------------------------------------------------------------------------------
 // Exports
 //--------------------------------------------------------------------------------------------------
 
 pub use default::*;
+pub use layered::*;
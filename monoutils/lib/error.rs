@@ -33,6 +33,22 @@ pub enum MonoutilsError {
     #[error("runtime error: {0}")]
     Runtime(String),
 
+    /// An error that occurred when loading a configuration
+    #[error("config error: {0}")]
+    Config(String),
+
+    /// An error that occurred when parsing an unknown log format
+    #[error("invalid log format: {0}, expected `plain` or `json`")]
+    InvalidLogFormat(String),
+
+    /// An error that occurred when parsing an unknown restart policy
+    #[error("invalid restart policy: {0}, expected `never`, `always` or `on-failure`")]
+    InvalidRestartPolicy(String),
+
+    /// An error that occurred when a supervised MicroVM did not report ready within its boot timeout
+    #[error("vm did not report ready within {0:?} of starting")]
+    VmBootTimeout(std::time::Duration),
+
     /// An error from the nix crate
     #[error("nix error: {0}")]
     NixError(#[from] nix::Error),
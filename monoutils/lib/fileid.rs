@@ -0,0 +1,812 @@
+//! `monoutils::fileid` is a module for keeping track of the NFS fileids issued for the paths of a
+//! filesystem.
+
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+use intaglio::{Symbol, SymbolTable};
+use lru::LruCache;
+use nfsserve::nfs::{fileid3, nfsstat3};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs,
+    io::AsyncWriteExt,
+    sync::{Mutex, MutexGuard},
+};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The fileid of the root directory, which is always registered.
+pub const ROOT_FILEID: fileid3 = 0;
+
+/// Default number of fileids of removed entities whose last paths are remembered, when the number
+/// of fileids is not limited.
+pub const DEFAULT_MAX_REMOVED_FILEIDS: usize = 64 * 1024;
+
+/// Minimum number of records appended to a fileid log before it is compacted.
+const MIN_FILEID_LOG_COMPACTION_RECORDS: usize = 1024;
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// Keeps track of the NFS fileids issued for the paths of a filesystem.
+///
+/// Fileids are issued in increasing order as paths are registered, after the root directory's
+/// [`ROOT_FILEID`]. Path components are interned, so components shared by many paths are stored
+/// once. The fileids of removed entities are remembered with their last paths, so they report
+/// `NFS3ERR_STALE` and readdir cookies pointing at them can still be resumed from.
+///
+/// A path keeps its fileid for as long as the map remembers it. Only when the number of fileids
+/// is limited with [`with_max_fileids`][FileidMap::with_max_fileids] can a path be forgotten, in
+/// which case its old fileid reports `NFS3ERR_STALE`, readdir cookies pointing at it can't be
+/// resolved, and the path is issued a new fileid when it is registered again.
+///
+/// ## Examples
+///
+/// ```
+/// use monoutils::FileidMap;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let fileids = FileidMap::new();
+/// let fileid = fileids.ensure_path_registered("dir/file.txt").await.unwrap();
+///
+/// assert_eq!(fileids.fileid_to_path(fileid).await.unwrap(), "dir/file.txt");
+/// assert_eq!(fileids.ensure_path_registered("dir/file.txt").await.unwrap(), fileid);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct FileidMap {
+    /// The registrations, behind a single lock so they are always updated together.
+    state: Mutex<FileidState>,
+
+    /// The log the registrations are persisted in, if any.
+    log: Option<Mutex<FileidLog>>,
+
+    /// The maximum number of fileids to keep track of besides the root's, if limited.
+    max_fileids: Option<usize>,
+}
+
+/// The registrations of a [`FileidMap`].
+#[derive(Debug)]
+struct FileidState {
+    /// The fileid the next registered path is given.
+    next_fileid: fileid3,
+
+    /// The interned path components.
+    filenames: SymbolTable,
+
+    /// Maps fileids to the paths they are registered to, least recently used first.
+    fileid_to_path: LruCache<fileid3, Vec<Symbol>>,
+
+    /// Maps registered paths to their fileids.
+    path_to_fileid: HashMap<Vec<Symbol>, fileid3>,
+
+    /// Maps the fileids of evicted registrations to their paths, least recently evicted first.
+    evicted: LruCache<fileid3, Vec<Symbol>>,
+
+    /// Maps the paths of evicted registrations to their fileids.
+    evicted_paths: HashMap<Vec<Symbol>, fileid3>,
+
+    /// Maps the fileids of removed entities to their last paths, least recently removed first.
+    removed: LruCache<fileid3, Vec<Symbol>>,
+}
+
+/// An open fileid log, along with what is needed to decide when to compact it.
+#[derive(Debug)]
+struct FileidLog {
+    /// The path of the log.
+    path: PathBuf,
+
+    /// The log, opened for appending.
+    file: fs::File,
+
+    /// The number of records the log had when it was last compacted.
+    compacted_records: usize,
+
+    /// The number of records appended since the log was last compacted.
+    appended_records: usize,
+}
+
+/// An entry in a fileid log, recording the path a fileid was assigned to, or the last path of
+/// the entity behind it when it was removed.
+#[derive(Debug, Serialize, Deserialize)]
+struct FileidRecord {
+    id: fileid3,
+    path: String,
+    #[serde(default)]
+    removed: bool,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl FileidMap {
+    /// Creates a new map with only the root directory registered.
+    pub fn new() -> Self {
+        let mut fileid_to_path = LruCache::unbounded();
+        fileid_to_path.put(ROOT_FILEID, vec![]);
+
+        Self {
+            state: Mutex::new(FileidState {
+                next_fileid: ROOT_FILEID + 1,
+                filenames: SymbolTable::new(),
+                fileid_to_path,
+                path_to_fileid: HashMap::from([(vec![], ROOT_FILEID)]),
+                evicted: LruCache::unbounded(),
+                evicted_paths: HashMap::new(),
+                removed: LruCache::unbounded(),
+            }),
+            log: None,
+            max_fileids: None,
+        }
+    }
+
+    /// Limits the number of fileids kept track of, besides the root's, to `max_fileids`.
+    ///
+    /// When the limit is exceeded, the registrations of the least recently used paths are
+    /// evicted. Up to `max_fileids` evicted registrations are remembered, so their fileids keep
+    /// resolving and their paths keep their fileids when they are registered again. Beyond that,
+    /// the least recently evicted registrations are forgotten: their fileids report
+    /// `NFS3ERR_STALE`, which makes clients look their paths up again, and the paths are given
+    /// new fileids. By default the number of fileids is not limited.
+    ///
+    /// The last paths of up to `max_fileids` removed entities are also remembered, or
+    /// [`DEFAULT_MAX_REMOVED_FILEIDS`] if the number of fileids is not limited.
+    pub fn with_max_fileids(mut self, max_fileids: usize) -> Self {
+        self.max_fileids = Some(max_fileids);
+        self
+    }
+
+    /// Returns the maximum number of fileids kept track of besides the root's, if limited.
+    pub fn get_max_fileids(&self) -> Option<usize> {
+        self.max_fileids
+    }
+
+    /// Persists the registrations in an append-only log at `path`.
+    ///
+    /// Registrations recorded by a previous run are restored and new fileids are issued after
+    /// them, so file handles held by clients stay valid across restarts over the same directory
+    /// tree. The log is created if it does not exist, and compacted when it is opened and
+    /// whenever it has doubled in size since.
+    ///
+    /// ## Errors
+    /// Returns an error if the log cannot be read, compacted or opened for appending.
+    pub async fn with_log(mut self, path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+
+        // Replay the log on top of the current registrations
+        let state = self.state.get_mut();
+        for record in read_fileid_log(path).await? {
+            state.next_fileid = state.next_fileid.max(record.id + 1);
+            state.restore(record, self.max_fileids);
+        }
+
+        // Rewrite the log with only the current registrations and open it for appending
+        let records = state.get_records();
+        write_fileid_log(path, &records).await?;
+
+        let file = fs::OpenOptions::new().append(true).open(path).await?;
+        self.log = Some(Mutex::new(FileidLog {
+            path: path.to_path_buf(),
+            file,
+            compacted_records: records.len(),
+            appended_records: 0,
+        }));
+
+        Ok(self)
+    }
+
+    /// Returns the path `id` is registered to.
+    ///
+    /// Resolving a fileid keeps it from being evicted.
+    ///
+    /// ## Errors
+    /// * `NFS3ERR_STALE` - The entity behind `id` was removed or its registration forgotten
+    /// * `NFS3ERR_NOENT` - `id` was never issued
+    pub async fn fileid_to_path(&self, id: fileid3) -> Result<String, nfsstat3> {
+        let mut state = self.state.lock().await;
+        let path_symbols = match state.fileid_to_path.get(&id) {
+            Some(path_symbols) => path_symbols.clone(),
+            None => {
+                // An evicted fileid is registered again under the path it was evicted with
+                if let Some(path_symbols) = state.evicted.pop(&id) {
+                    state.evicted_paths.remove(&path_symbols);
+                    state.register(id, path_symbols.clone(), self.max_fileids);
+                    path_symbols
+                } else if id < state.next_fileid {
+                    // Fileids are issued in order, so a lower one was issued and has since been
+                    // removed or forgotten
+                    return Err(nfsstat3::NFS3ERR_STALE);
+                } else {
+                    return Err(nfsstat3::NFS3ERR_NOENT);
+                }
+            }
+        };
+
+        symbols_to_path(&state.filenames, &path_symbols).ok_or(nfsstat3::NFS3ERR_STALE)
+    }
+
+    /// Returns the path of the entry a readdir cookie points at, which may have been removed
+    /// since the cookie was issued, or `None` if the cookie can't be resolved.
+    pub async fn cookie_to_path(&self, cookie: fileid3) -> Option<String> {
+        let mut state = self.state.lock().await;
+        let state = &mut *state;
+        let path_symbols = match state.fileid_to_path.get(&cookie) {
+            Some(path_symbols) => path_symbols,
+            None => state
+                .evicted
+                .peek(&cookie)
+                .or_else(|| state.removed.peek(&cookie))?,
+        };
+
+        symbols_to_path(&state.filenames, path_symbols)
+    }
+
+    /// Returns the fileid of `path`, registering the path if it isn't already.
+    ///
+    /// ## Errors
+    /// * `NFS3ERR_INVAL` - A component of `path` can't be interned
+    pub async fn ensure_path_registered(&self, path: impl AsRef<str>) -> Result<fileid3, nfsstat3> {
+        let mut state = self.state.lock().await;
+        let path_symbols = state.intern(path.as_ref())?;
+
+        // Looking a path up keeps its fileid from being evicted
+        if let Some(&fileid) = state.path_to_fileid.get(&path_symbols) {
+            state.fileid_to_path.promote(&fileid);
+            return Ok(fileid);
+        }
+
+        // An evicted path is registered again with the fileid it had
+        if let Some(fileid) = state.evicted_paths.remove(&path_symbols) {
+            state.evicted.pop(&fileid);
+            state.register(fileid, path_symbols, self.max_fileids);
+            return Ok(fileid);
+        }
+
+        let fileid = state.next_fileid;
+        state.next_fileid += 1;
+        state.register(fileid, path_symbols.clone(), self.max_fileids);
+
+        let records = state.to_records(&[(fileid, path_symbols)], false);
+        self.log_records(state, records).await;
+
+        Ok(fileid)
+    }
+
+    /// Moves the registrations of `from` and everything below it to `to`, so renamed entities
+    /// keep their fileids.
+    ///
+    /// Any registrations under `to` belong to a replaced entity and are marked removed.
+    ///
+    /// ## Errors
+    /// * `NFS3ERR_INVAL` - A component of either path can't be interned
+    pub async fn move_path_registration(
+        &self,
+        from: impl AsRef<str>,
+        to: impl AsRef<str>,
+    ) -> Result<(), nfsstat3> {
+        let mut state = self.state.lock().await;
+        let from_symbols = state.intern(from.as_ref())?;
+        let to_symbols = state.intern(to.as_ref())?;
+        if from_symbols == to_symbols {
+            return Ok(());
+        }
+
+        // Drop the registrations of the replaced target
+        let replaced = state.drop_registrations(&to_symbols);
+        state.mark_removed(&replaced, self.max_fileids);
+
+        // Re-register the source and its descendants under the new path
+        let moved = state.drop_registrations(&from_symbols);
+        let mut registrations = Vec::with_capacity(moved.len());
+        for (fileid, path) in moved {
+            let mut new_path = to_symbols.clone();
+            new_path.extend_from_slice(&path[from_symbols.len()..]);
+
+            state.register(fileid, new_path.clone(), self.max_fileids);
+            registrations.push((fileid, new_path));
+        }
+
+        let mut records = state.to_records(&replaced, true);
+        records.extend(state.to_records(&registrations, false));
+        self.log_records(state, records).await;
+
+        Ok(())
+    }
+
+    /// Drops the registrations of `path` and everything below it after the entity at that path
+    /// has been removed, remembering their fileids so they report `NFS3ERR_STALE`.
+    ///
+    /// ## Errors
+    /// * `NFS3ERR_INVAL` - A component of `path` can't be interned
+    pub async fn remove_path_registration(&self, path: impl AsRef<str>) -> Result<(), nfsstat3> {
+        let mut state = self.state.lock().await;
+        let path_symbols = state.intern(path.as_ref())?;
+
+        let removed = state.drop_registrations(&path_symbols);
+        state.mark_removed(&removed, self.max_fileids);
+
+        let records = state.to_records(&removed, true);
+        self.log_records(state, records).await;
+
+        Ok(())
+    }
+
+    /// Appends records to the fileid log, if one is configured.
+    ///
+    /// The log is locked before the registrations are released, so records are appended in the
+    /// order the registrations were made, but the log is written once they are released. Once
+    /// the records appended since the log was last compacted outnumber the ones it was compacted
+    /// to, the log is rewritten with only the current and removed registrations instead. Failing
+    /// to write is logged rather than failing the registration.
+    async fn log_records(&self, state: MutexGuard<'_, FileidState>, records: Vec<FileidRecord>) {
+        let Some(log) = &self.log else {
+            return;
+        };
+
+        if records.is_empty() {
+            return;
+        }
+
+        let mut log = log.lock().await;
+        log.appended_records += records.len();
+        let compacted = (log.appended_records
+            > log.compacted_records.max(MIN_FILEID_LOG_COMPACTION_RECORDS))
+        .then(|| state.get_records());
+        drop(state);
+
+        match compacted {
+            Some(records) => {
+                if let Err(e) = compact_fileid_log(&mut log, &records).await {
+                    tracing::error!("failed to compact the fileid log: {}", e);
+                }
+            }
+            None => {
+                if let Err(e) = append_fileid_records(&mut log.file, &records).await {
+                    tracing::error!("failed to append to the fileid log: {}", e);
+                }
+            }
+        }
+    }
+}
+
+impl FileidState {
+    /// Interns the components of `path`, skipping empty ones.
+    fn intern(&mut self, path: &str) -> Result<Vec<Symbol>, nfsstat3> {
+        path.split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| {
+                self.filenames
+                    .intern(segment.to_string())
+                    .map_err(|_| nfsstat3::NFS3ERR_INVAL)
+            })
+            .collect()
+    }
+
+    /// Registers `path_symbols` under `fileid` as the most recently used path, evicting the
+    /// registrations of cold paths if that exceeds `max_fileids`.
+    fn register(&mut self, fileid: fileid3, path_symbols: Vec<Symbol>, max_fileids: Option<usize>) {
+        self.path_to_fileid.insert(path_symbols.clone(), fileid);
+        self.fileid_to_path.put(fileid, path_symbols);
+        self.evict_cold_registrations(max_fileids);
+    }
+
+    /// Evicts the registrations of the least recently used paths until at most `max_fileids`
+    /// remain besides the root's, which is never evicted.
+    ///
+    /// Up to `max_fileids` evicted registrations are remembered, and the least recently evicted
+    /// ones beyond that are forgotten.
+    fn evict_cold_registrations(&mut self, max_fileids: Option<usize>) {
+        let Some(max_fileids) = max_fileids else {
+            return;
+        };
+
+        while self.fileid_to_path.len() > max_fileids + 1 {
+            if let Some((&ROOT_FILEID, _)) = self.fileid_to_path.peek_lru() {
+                self.fileid_to_path.promote(&ROOT_FILEID);
+                continue;
+            }
+
+            let Some((fileid, path)) = self.fileid_to_path.pop_lru() else {
+                break;
+            };
+            self.path_to_fileid.remove(&path);
+            self.evicted_paths.insert(path.clone(), fileid);
+            self.evicted.put(fileid, path);
+        }
+
+        while self.evicted.len() > max_fileids {
+            let Some((_, path)) = self.evicted.pop_lru() else {
+                break;
+            };
+            self.evicted_paths.remove(&path);
+        }
+    }
+
+    /// Forgets the least recently removed fileids until at most `max_fileids`, or
+    /// [`DEFAULT_MAX_REMOVED_FILEIDS`] if unlimited, remain. Forgotten fileids still report
+    /// `NFS3ERR_STALE`.
+    fn evict_cold_removals(&mut self, max_fileids: Option<usize>) {
+        let max_fileids = max_fileids.unwrap_or(DEFAULT_MAX_REMOVED_FILEIDS);
+        while self.removed.len() > max_fileids {
+            self.removed.pop_lru();
+        }
+    }
+
+    /// Removes the registrations of `path_symbols` and everything below it, including evicted
+    /// ones, and returns them.
+    fn drop_registrations(&mut self, path_symbols: &[Symbol]) -> Vec<(fileid3, Vec<Symbol>)> {
+        let mut dropped = Vec::new();
+        let fileid_to_path = &mut self.fileid_to_path;
+        self.path_to_fileid.retain(|path, fileid| {
+            let matches = path.starts_with(path_symbols);
+            if matches {
+                fileid_to_path.pop(fileid);
+                dropped.push((*fileid, path.clone()));
+            }
+            !matches
+        });
+
+        let evicted = &mut self.evicted;
+        self.evicted_paths.retain(|path, fileid| {
+            let matches = path.starts_with(path_symbols);
+            if matches {
+                evicted.pop(fileid);
+                dropped.push((*fileid, path.clone()));
+            }
+            !matches
+        });
+
+        dropped
+    }
+
+    /// Remembers the last paths of the fileids of removed entities.
+    fn mark_removed(&mut self, removed: &[(fileid3, Vec<Symbol>)], max_fileids: Option<usize>) {
+        for (fileid, path_symbols) in removed {
+            self.removed.put(*fileid, path_symbols.clone());
+        }
+        self.evict_cold_removals(max_fileids);
+    }
+
+    /// Restores a registration read from the fileid log, replacing any registration of the same
+    /// fileid or path made by an earlier record.
+    fn restore(&mut self, record: FileidRecord, max_fileids: Option<usize>) {
+        if record.id == ROOT_FILEID {
+            return;
+        }
+
+        let Ok(path_symbols) = self.intern(&record.path) else {
+            return;
+        };
+
+        if let Some(old_path) = self.fileid_to_path.pop(&record.id) {
+            self.path_to_fileid.remove(&old_path);
+        }
+        if let Some(old_path) = self.evicted.pop(&record.id) {
+            self.evicted_paths.remove(&old_path);
+        }
+
+        if record.removed {
+            self.removed.put(record.id, path_symbols);
+            self.evict_cold_removals(max_fileids);
+            return;
+        }
+
+        if let Some(old_fileid) = self.path_to_fileid.remove(&path_symbols) {
+            self.fileid_to_path.pop(&old_fileid);
+        }
+        if let Some(old_fileid) = self.evicted_paths.remove(&path_symbols) {
+            self.evicted.pop(&old_fileid);
+        }
+        self.removed.pop(&record.id);
+        self.register(record.id, path_symbols, max_fileids);
+    }
+
+    /// Returns records describing every current, evicted and removed registration, ordered by
+    /// fileid.
+    fn get_records(&self) -> Vec<FileidRecord> {
+        let registered = self
+            .fileid_to_path
+            .iter()
+            .chain(self.evicted.iter())
+            .filter(|(fileid, _)| **fileid != ROOT_FILEID)
+            .map(|(fileid, path_symbols)| (*fileid, path_symbols.clone()))
+            .collect::<Vec<_>>();
+        let removed = self
+            .removed
+            .iter()
+            .map(|(fileid, path_symbols)| (*fileid, path_symbols.clone()))
+            .collect::<Vec<_>>();
+
+        let mut records = self.to_records(&registered, false);
+        records.extend(self.to_records(&removed, true));
+        records.sort_by_key(|record| record.id);
+
+        records
+    }
+
+    /// Converts registrations, or removals if `removed` is set, to log records.
+    fn to_records(
+        &self,
+        registrations: &[(fileid3, Vec<Symbol>)],
+        removed: bool,
+    ) -> Vec<FileidRecord> {
+        registrations
+            .iter()
+            .filter_map(|(fileid, path_symbols)| {
+                Some(FileidRecord {
+                    id: *fileid,
+                    path: symbols_to_path(&self.filenames, path_symbols)?,
+                    removed,
+                })
+            })
+            .collect()
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Trait Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl Default for FileidMap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Joins the names behind path symbols into a path, or returns `None` if a symbol is not in the
+/// table.
+fn symbols_to_path(filenames: &SymbolTable, path_symbols: &[Symbol]) -> Option<String> {
+    let segments = path_symbols
+        .iter()
+        .map(|s| filenames.get(*s))
+        .collect::<Option<Vec<_>>>()?;
+
+    Some(segments.join("/"))
+}
+
+/// Reads the records of a fileid log, in the order they were appended.
+///
+/// A missing log has no records. Lines that cannot be parsed, such as one cut short by a crash,
+/// are skipped.
+async fn read_fileid_log(path: &Path) -> io::Result<Vec<FileidRecord>> {
+    let contents = match fs::read_to_string(path).await {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+
+    let records = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match serde_json::from_str(line) {
+            Ok(record) => Some(record),
+            Err(e) => {
+                tracing::warn!("skipping malformed fileid log entry {:?}: {}", line, e);
+                None
+            }
+        })
+        .collect();
+
+    Ok(records)
+}
+
+/// Replaces the contents of a fileid log.
+///
+/// The records are written to a temporary file that is then renamed over the log, so the log is
+/// never left partially written.
+async fn write_fileid_log(path: &Path, records: &[FileidRecord]) -> io::Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+
+    let mut file = fs::File::create(&temp_path).await?;
+    append_fileid_records(&mut file, records).await?;
+    file.sync_all().await?;
+    drop(file);
+
+    fs::rename(&temp_path, path).await
+}
+
+/// Replaces the contents of an open fileid log and reopens it for appending.
+async fn compact_fileid_log(log: &mut FileidLog, records: &[FileidRecord]) -> io::Result<()> {
+    write_fileid_log(&log.path, records).await?;
+    log.file = fs::OpenOptions::new().append(true).open(&log.path).await?;
+    log.compacted_records = records.len();
+    log.appended_records = 0;
+
+    Ok(())
+}
+
+/// Appends records to a fileid log file as JSON lines.
+async fn append_fileid_records(file: &mut fs::File, records: &[FileidRecord]) -> io::Result<()> {
+    let mut buffer = Vec::new();
+    for record in records {
+        serde_json::to_writer(&mut buffer, record)?;
+        buffer.push(b'\n');
+    }
+
+    file.write_all(&buffer).await?;
+    file.flush().await
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fileid_map_registrations() {
+        let fileids = FileidMap::new();
+        assert_eq!(fileids.fileid_to_path(ROOT_FILEID).await.unwrap(), "");
+
+        let dir_id = fileids.ensure_path_registered("dir").await.unwrap();
+        let file_id = fileids
+            .ensure_path_registered("dir/file.txt")
+            .await
+            .unwrap();
+        assert!(file_id > dir_id);
+        assert_eq!(
+            fileids.ensure_path_registered("/dir/").await.unwrap(),
+            dir_id
+        );
+
+        // Renamed entities keep their fileids, and the entities they replace are removed
+        let other_id = fileids.ensure_path_registered("other").await.unwrap();
+        fileids
+            .move_path_registration("dir", "other")
+            .await
+            .unwrap();
+        assert_eq!(fileids.fileid_to_path(dir_id).await.unwrap(), "other");
+        assert_eq!(
+            fileids.fileid_to_path(file_id).await.unwrap(),
+            "other/file.txt"
+        );
+        assert!(matches!(
+            fileids.fileid_to_path(other_id).await,
+            Err(nfsstat3::NFS3ERR_STALE)
+        ));
+
+        // Removed entities are stale, but their cookies still resolve
+        fileids.remove_path_registration("other").await.unwrap();
+        assert!(matches!(
+            fileids.fileid_to_path(file_id).await,
+            Err(nfsstat3::NFS3ERR_STALE)
+        ));
+        assert_eq!(
+            fileids.cookie_to_path(file_id).await.as_deref(),
+            Some("other/file.txt")
+        );
+
+        // Fileids that were never issued don't exist
+        assert!(matches!(
+            fileids.fileid_to_path(999).await,
+            Err(nfsstat3::NFS3ERR_NOENT)
+        ));
+        assert_eq!(fileids.cookie_to_path(999).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_fileid_map_max_fileids() {
+        let fileids = FileidMap::new().with_max_fileids(4);
+
+        let mut registered = Vec::new();
+        for i in 0..10 {
+            let path = format!("file{}.txt", i);
+            let fileid = fileids.ensure_path_registered(&path).await.unwrap();
+            registered.push((path, fileid));
+        }
+
+        // The maps stay bounded, with the root kept besides the limit
+        {
+            let state = fileids.state.lock().await;
+            assert_eq!(state.fileid_to_path.len(), 5);
+            assert_eq!(state.path_to_fileid.len(), 5);
+            assert_eq!(state.evicted.len(), 4);
+            assert_eq!(state.evicted_paths.len(), 4);
+        }
+        assert!(fileids.fileid_to_path(ROOT_FILEID).await.is_ok());
+
+        // Recently used fileids still resolve
+        for (path, fileid) in &registered[6..] {
+            assert_eq!(fileids.fileid_to_path(*fileid).await.unwrap(), *path);
+        }
+
+        // Remembered evictions keep their fileids, whether resolved by fileid, cookie or path
+        let (path, fileid) = &registered[5];
+        assert_eq!(
+            fileids.cookie_to_path(*fileid).await.as_deref(),
+            Some(&**path)
+        );
+        assert_eq!(fileids.fileid_to_path(*fileid).await.unwrap(), *path);
+        let (path, fileid) = &registered[4];
+        assert_eq!(fileids.ensure_path_registered(path).await.unwrap(), *fileid);
+
+        // Forgotten fileids are stale and can't be resumed from, and their paths get new fileids
+        let (path, fileid) = &registered[0];
+        assert!(matches!(
+            fileids.fileid_to_path(*fileid).await,
+            Err(nfsstat3::NFS3ERR_STALE)
+        ));
+        assert_eq!(fileids.cookie_to_path(*fileid).await, None);
+        assert_ne!(fileids.ensure_path_registered(path).await.unwrap(), *fileid);
+    }
+
+    #[tokio::test]
+    async fn test_fileid_map_removed_fileids_bounded_by_default() {
+        let fileids = FileidMap::new();
+        assert_eq!(fileids.get_max_fileids(), None);
+
+        let mut state = fileids.state.lock().await;
+        let removed = (1..=(DEFAULT_MAX_REMOVED_FILEIDS + 10) as fileid3)
+            .map(|fileid| (fileid, vec![]))
+            .collect::<Vec<_>>();
+        state.mark_removed(&removed, None);
+
+        // The least recently removed fileids are forgotten first
+        assert_eq!(state.removed.len(), DEFAULT_MAX_REMOVED_FILEIDS);
+        assert!(!state.removed.contains(&10));
+        assert!(state.removed.contains(&11));
+    }
+
+    #[tokio::test]
+    async fn test_fileid_map_log_compaction() {
+        let temp_dir = TempDir::new().unwrap();
+        let log_path = temp_dir.path().join("fileids.log");
+
+        let fileids = FileidMap::new()
+            .with_max_fileids(8)
+            .with_log(&log_path)
+            .await
+            .unwrap();
+        let kept_id = fileids.ensure_path_registered("kept.txt").await.unwrap();
+
+        // Churn through many more entities than the log is compacted at
+        let mut removed_id = 0;
+        for i in 0..2 * MIN_FILEID_LOG_COMPACTION_RECORDS {
+            let path = format!("file{}.txt", i);
+            removed_id = fileids.ensure_path_registered(&path).await.unwrap();
+            fileids.remove_path_registration(&path).await.unwrap();
+        }
+
+        // The log only grows to a bound set by the live and removed registrations
+        let lines = std::fs::read_to_string(&log_path).unwrap().lines().count();
+        assert!(lines <= MIN_FILEID_LOG_COMPACTION_RECORDS + 2 * 8);
+
+        // Registrations are still restored from the compacted log
+        drop(fileids);
+        let fileids = FileidMap::new()
+            .with_max_fileids(8)
+            .with_log(&log_path)
+            .await
+            .unwrap();
+        assert_eq!(
+            fileids.ensure_path_registered("kept.txt").await.unwrap(),
+            kept_id
+        );
+        assert!(matches!(
+            fileids.fileid_to_path(removed_id).await,
+            Err(nfsstat3::NFS3ERR_STALE)
+        ));
+        assert!(fileids.state.lock().await.removed.len() <= 8);
+
+        let new_id = fileids.ensure_path_registered("new.txt").await.unwrap();
+        assert!(new_id > removed_id);
+    }
+}
@@ -5,8 +5,11 @@
 
 pub mod config;
 pub mod error;
+#[cfg(feature = "nfs")]
+pub mod fileid;
 pub mod log;
 pub mod path;
+pub mod progress;
 pub mod runtime;
 pub mod seekable;
 pub mod term;
@@ -17,8 +20,11 @@ pub mod term;
 
 pub use config::*;
 pub use error::*;
+#[cfg(feature = "nfs")]
+pub use fileid::*;
 pub use log::*;
 pub use path::*;
+pub use progress::*;
 pub use runtime::*;
 pub use seekable::*;
 pub use term::*;
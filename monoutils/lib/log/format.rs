@@ -0,0 +1,300 @@
+//! Formats for the output of supervised processes written to their logs.
+//!
+//! With [`LogFormat::Plain`] the output is written as is. With [`LogFormat::Json`] every line of
+//! output becomes a JSON object on its own line, a [`LogRecord`], which log aggregators can parse.
+
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{MonoutilsError, MonoutilsResult};
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// The format output is written to a log in.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// The output is written as is.
+    #[default]
+    Plain,
+
+    /// Each line of output is written as a JSON [`LogRecord`] on its own line.
+    Json,
+}
+
+/// The output stream of a process that a log entry was read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+    /// The standard output.
+    Stdout,
+
+    /// The standard error.
+    Stderr,
+}
+
+/// A log entry as written by [`LogFormat::Json`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogRecord {
+    /// When the entry was read from the process.
+    pub timestamp: DateTime<Utc>,
+
+    /// The level of the entry, `info` for stdout and `error` for stderr.
+    pub level: String,
+
+    /// The stream the entry was read from.
+    pub stream: LogStream,
+
+    /// The content of the entry, with invalid UTF-8 replaced by `U+FFFD`.
+    pub message: String,
+
+    /// The exact content of the entry hex-encoded, only set if it isn't valid UTF-8.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_hex: Option<String>,
+}
+
+/// Encodes the output read from one stream of a process in a [`LogFormat`].
+///
+/// Output may be read in chunks that don't end on line boundaries, so with [`LogFormat::Json`]
+/// the encoder holds back an unterminated line until the rest of it is read or
+/// [`finish`][LogEncoder::finish] is called.
+#[derive(Debug)]
+pub struct LogEncoder {
+    /// The format to encode in.
+    format: LogFormat,
+
+    /// The stream the output is read from.
+    stream: LogStream,
+
+    /// Bytes of a line that has not been terminated yet.
+    partial: Vec<u8>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl LogStream {
+    /// Returns the level of entries read from the stream.
+    pub fn level(&self) -> &'static str {
+        match self {
+            LogStream::Stdout => "info",
+            LogStream::Stderr => "error",
+        }
+    }
+}
+
+impl LogRecord {
+    /// Creates a record of `message` read from `stream` now.
+    pub fn new(stream: LogStream, message: impl Into<String>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            level: stream.level().to_string(),
+            stream,
+            message: message.into(),
+            message_hex: None,
+        }
+    }
+
+    /// Creates a record of the raw `bytes` read from `stream` now.
+    ///
+    /// If `bytes` isn't valid UTF-8, it is kept in [`message_hex`][LogRecord::message_hex] so
+    /// nothing is lost.
+    pub fn from_bytes(stream: LogStream, bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(message) => Self::new(stream, message),
+            Err(_) => Self {
+                message_hex: Some(hex::encode(bytes)),
+                ..Self::new(stream, String::from_utf8_lossy(bytes))
+            },
+        }
+    }
+
+    /// Returns the exact bytes of the entry.
+    ///
+    /// ## Errors
+    /// Returns an error if [`message_hex`][LogRecord::message_hex] is not valid hex.
+    pub fn get_message_bytes(&self) -> MonoutilsResult<Vec<u8>> {
+        match &self.message_hex {
+            Some(message_hex) => hex::decode(message_hex).map_err(MonoutilsError::custom),
+            None => Ok(self.message.as_bytes().to_vec()),
+        }
+    }
+
+    /// Serializes the record to a JSON object followed by a newline.
+    ///
+    /// Newlines and quotes in the message are escaped, so the record always takes a single line.
+    pub fn to_json_line(&self) -> MonoutilsResult<String> {
+        let mut line = serde_json::to_string(self).map_err(MonoutilsError::custom)?;
+        line.push('\n');
+        Ok(line)
+    }
+
+    /// Parses a record from a line written by [`to_json_line`][LogRecord::to_json_line].
+    pub fn from_json_line(line: &str) -> MonoutilsResult<Self> {
+        serde_json::from_str(line.trim_end_matches(['\r', '\n'])).map_err(MonoutilsError::custom)
+    }
+}
+
+impl LogEncoder {
+    /// Creates an encoder for output read from `stream`.
+    pub fn new(format: LogFormat, stream: LogStream) -> Self {
+        Self {
+            format,
+            stream,
+            partial: Vec::new(),
+        }
+    }
+
+    /// Encodes a chunk of output, returning the bytes to write to the log.
+    pub fn encode(&mut self, bytes: &[u8]) -> Vec<u8> {
+        if self.format == LogFormat::Plain {
+            return bytes.to_vec();
+        }
+
+        self.partial.extend_from_slice(bytes);
+        let mut encoded = Vec::new();
+        while let Some(index) = self.partial.iter().position(|b| *b == b'\n') {
+            let line = self.partial.drain(..=index).collect::<Vec<_>>();
+            self.encode_line(&line[..index], &mut encoded);
+        }
+
+        encoded
+    }
+
+    /// Encodes an unterminated last line, if any, returning the bytes to write to the log.
+    ///
+    /// Call this once the stream has ended.
+    pub fn finish(&mut self) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        if !self.partial.is_empty() {
+            let line = std::mem::take(&mut self.partial);
+            self.encode_line(&line, &mut encoded);
+        }
+
+        encoded
+    }
+
+    fn encode_line(&self, line: &[u8], encoded: &mut Vec<u8>) {
+        let record = LogRecord::from_bytes(self.stream, line);
+        match record.to_json_line() {
+            Ok(json) => encoded.extend_from_slice(json.as_bytes()),
+            Err(e) => tracing::error!(error = %e, "failed to encode log record"),
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Trait Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl FromStr for LogFormat {
+    type Err = MonoutilsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(LogFormat::Plain),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(MonoutilsError::InvalidLogFormat(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogFormat::Plain => write!(f, "plain"),
+            LogFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_record_round_trip() -> anyhow::Result<()> {
+        let message = "he said \"hi\"\nthen left\r\n\ttab \\ backslash";
+        let record = LogRecord::new(LogStream::Stderr, message);
+
+        let line = record.to_json_line()?;
+        assert_eq!(line.matches('\n').count(), 1);
+        assert!(line.ends_with('\n'));
+
+        let parsed = LogRecord::from_json_line(&line)?;
+        assert_eq!(parsed, record);
+        assert_eq!(parsed.message, message);
+        assert_eq!(parsed.level, "error");
+
+        // Any JSON parser sees the same fields
+        let value: serde_json::Value = serde_json::from_str(&line)?;
+        assert_eq!(value["message"], message);
+        assert_eq!(value["stream"], "stderr");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_log_encoder_json() -> anyhow::Result<()> {
+        let mut encoder = LogEncoder::new(LogFormat::Json, LogStream::Stdout);
+        let mut encoded = encoder.encode(b"first \"line\"\nsec");
+        encoded.extend(encoder.encode(b"ond line\nunterminated"));
+        encoded.extend(encoder.finish());
+
+        let encoded = String::from_utf8(encoded)?;
+        let records = encoded
+            .lines()
+            .map(LogRecord::from_json_line)
+            .collect::<MonoutilsResult<Vec<_>>>()?;
+
+        let messages = records
+            .iter()
+            .map(|r| r.message.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(messages, ["first \"line\"", "second line", "unterminated"]);
+        assert!(records.iter().all(|r| r.stream == LogStream::Stdout));
+        assert!(records.iter().all(|r| r.message_hex.is_none()));
+        assert!(encoder.finish().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_log_encoder_json_keeps_invalid_utf8() -> anyhow::Result<()> {
+        let mut encoder = LogEncoder::new(LogFormat::Json, LogStream::Stderr);
+        let line = b"bad \xff\xfe bytes";
+        let encoded = String::from_utf8(encoder.encode(&[&line[..], b"\n"].concat()))?;
+
+        let record = LogRecord::from_json_line(&encoded)?;
+        assert_eq!(record.message, "bad \u{fffd}\u{fffd} bytes");
+        assert_eq!(record.get_message_bytes()?, line);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_log_encoder_plain() {
+        let mut encoder = LogEncoder::new(LogFormat::Plain, LogStream::Stdout);
+        assert_eq!(
+            encoder.encode(b"as \"is\"\nno newline"),
+            b"as \"is\"\nno newline"
+        );
+        assert!(encoder.finish().is_empty());
+    }
+
+    #[test]
+    fn test_log_format_from_str() {
+        assert_eq!("plain".parse::<LogFormat>().unwrap(), LogFormat::Plain);
+        assert_eq!("json".parse::<LogFormat>().unwrap(), LogFormat::Json);
+        assert_eq!(LogFormat::Json.to_string(), "json");
+        assert!("yaml".parse::<LogFormat>().is_err());
+    }
+}
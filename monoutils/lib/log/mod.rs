@@ -1,9 +1,13 @@
 //! `monoutils::log` is a module containing logging utilities for the monocore project.
 
+mod format;
 mod rotating;
+mod tail;
 
 //--------------------------------------------------------------------------------------------------
 // Exports
 //--------------------------------------------------------------------------------------------------
 
+pub use format::*;
 pub use rotating::*;
+pub use tail::*;
@@ -1,15 +1,19 @@
 //! Log rotation implementation for the Monocore runtime.
 //!
 //! This module provides a rotating log implementation that automatically rotates log files
-//! when they reach a specified size. The rotation process involves:
-//! 1. Renaming the current log file to .old extension
-//! 2. Creating a new empty log file
-//! 3. Continuing writing to the new file
+//! when they reach a specified size or age. The rotation process involves:
+//! 1. Shifting older segments along, so `app.log.1` becomes `app.log.2` and so on
+//! 2. Moving the current log file to `app.log.1`, optionally compressing it with gzip
+//! 3. Continuing writing to a new empty log file
+//!
+//! The log file is replaced atomically, so readers following it by path never find it missing.
 //!
 //! The implementation is fully asynchronous and implements AsyncWrite.
 
+use flate2::{write::GzEncoder, Compression};
 use futures::future::BoxFuture;
 use std::{
+    ffi::OsString,
     io::{self, Write},
     path::{Path, PathBuf},
     pin::Pin,
@@ -18,24 +22,26 @@ use std::{
         Arc,
     },
     task::{Context, Poll},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use tokio::{
-    fs::{remove_file, rename, File, OpenOptions},
+    fs::{hard_link, remove_file, rename, File, OpenOptions},
     io::{AsyncWrite, AsyncWriteExt},
     sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
     task::JoinHandle,
 };
 
-use crate::DEFAULT_LOG_MAX_SIZE;
+use crate::{DEFAULT_LOG_MAX_FILES, DEFAULT_LOG_MAX_SIZE};
 
 //--------------------------------------------------------------------------------------------------
 // Types
 //--------------------------------------------------------------------------------------------------
 
-/// A rotating log file that automatically rotates when reaching a maximum size.
+/// A rotating log file that automatically rotates when reaching a maximum size or age.
 ///
-/// The log rotation process preserves the last full log file with a ".old" extension
-/// while continuing to write to a new log file with the original name.
+/// The log rotation process keeps the most recent full log files as numbered segments next to
+/// the log file, `app.log.1` being the most recent, while continuing to write to a new log file
+/// with the original name. See [`RotationPolicy`] for what can be configured.
 ///
 /// # Example
 ///
@@ -55,12 +61,16 @@ pub struct RotatingLog {
     /// Path to the current log file
     path: PathBuf,
 
-    /// Maximum size in bytes before rotation
-    max_size: u64,
+    /// When and how to rotate the log file
+    policy: Arc<RotationPolicy>,
 
     /// Current size of the log file (shared between sync and async paths)
     current_size: Arc<AtomicU64>,
 
+    /// When the current log file was started, in seconds since the Unix epoch (shared between
+    /// sync and async paths)
+    started_at: Arc<AtomicU64>,
+
     /// Current state of the log rotation
     state: State,
 
@@ -71,6 +81,37 @@ pub struct RotatingLog {
     _background_task: JoinHandle<()>,
 }
 
+/// Configures when a [`RotatingLog`] rotates and what it keeps.
+///
+/// ## Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use monoutils::log::RotationPolicy;
+///
+/// // Rotate daily or at 1MB, keeping a week of compressed logs
+/// let policy = RotationPolicy {
+///     max_size: 1024 * 1024,
+///     max_age: Some(Duration::from_secs(24 * 60 * 60)),
+///     max_files: 7,
+///     compress: true,
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RotationPolicy {
+    /// Maximum size in bytes before rotation.
+    pub max_size: u64,
+
+    /// Maximum age of a log file before rotation. Empty log files are never rotated for age.
+    pub max_age: Option<Duration>,
+
+    /// Maximum number of rotated log files to keep. With `0`, rotated logs are discarded.
+    pub max_files: usize,
+
+    /// Whether to compress rotated log files with gzip, adding a `.gz` extension.
+    pub compress: bool,
+}
+
 /// Internal state machine for managing log rotation
 enum State {
     /// Normal operation, ready to accept writes
@@ -90,6 +131,36 @@ pub struct SyncChannelWriter {
 
 type RotationFuture = BoxFuture<'static, io::Result<(File, PathBuf)>>;
 
+//--------------------------------------------------------------------------------------------------
+// Methods: RotationPolicy
+//--------------------------------------------------------------------------------------------------
+
+impl RotationPolicy {
+    /// Returns the path of the `index`th most recent rotated segment of the log file at `path`.
+    pub fn get_segment_path(&self, path: &Path, index: usize) -> PathBuf {
+        let mut segment = OsString::from(path.as_os_str());
+        segment.push(format!(".{index}"));
+        if self.compress {
+            segment.push(".gz");
+        }
+
+        PathBuf::from(segment)
+    }
+
+    /// Returns `true` if a log file that is `size` bytes and was started at `started_at` seconds
+    /// since the Unix epoch must be rotated before `len` more bytes are written to it.
+    fn should_rotate(&self, size: u64, len: u64, started_at: u64) -> bool {
+        if size + len > self.max_size {
+            return true;
+        }
+
+        match self.max_age {
+            Some(max_age) => size > 0 && now_secs().saturating_sub(started_at) >= max_age.as_secs(),
+            None => false,
+        }
+    }
+}
+
 //--------------------------------------------------------------------------------------------------
 // Methods
 //--------------------------------------------------------------------------------------------------
@@ -113,7 +184,8 @@ impl RotatingLog {
         Self::with_max_size(path, DEFAULT_LOG_MAX_SIZE).await
     }
 
-    /// Creates a new rotating log file.
+    /// Creates a new rotating log file that rotates at `max_size` bytes, with the default policy
+    /// otherwise.
     ///
     /// ## Errors
     ///
@@ -121,6 +193,22 @@ impl RotatingLog {
     /// * The file cannot be created or opened
     /// * File metadata cannot be read
     pub async fn with_max_size(path: impl AsRef<Path>, max_size: u64) -> io::Result<Self> {
+        let policy = RotationPolicy {
+            max_size,
+            ..Default::default()
+        };
+
+        Self::with_policy(path, policy).await
+    }
+
+    /// Creates a new rotating log file that rotates according to `policy`.
+    ///
+    /// ## Errors
+    ///
+    /// Will return an error if:
+    /// * The file cannot be created or opened
+    /// * File metadata cannot be read
+    pub async fn with_policy(path: impl AsRef<Path>, policy: RotationPolicy) -> io::Result<Self> {
         let path = path.as_ref().to_path_buf();
         let file = OpenOptions::new()
             .create(true)
@@ -130,25 +218,37 @@ impl RotatingLog {
         let metadata = file.metadata().await?;
         let (tx, rx) = mpsc::unbounded_channel();
 
-        // Create shared atomic counter for current size
+        // Create shared atomic counters for current size and start time. An existing file is
+        // considered started when it was last modified, as its creation time may be unavailable.
         let current_size = Arc::new(AtomicU64::new(metadata.len()));
-
-        // Create a clone of the file and size counter for the background task
+        let started_at = match metadata.len() {
+            0 => now_secs(),
+            _ => metadata
+                .modified()
+                .map(to_secs)
+                .unwrap_or_else(|_| now_secs()),
+        };
+        let started_at = Arc::new(AtomicU64::new(started_at));
+        let policy = Arc::new(policy);
+
+        // Create a clone of the file and counters for the background task
         let bg_file = file.try_clone().await?;
         let bg_path = path.clone();
-        let bg_max_size = max_size;
+        let bg_policy = Arc::clone(&policy);
         let bg_size = Arc::clone(&current_size);
+        let bg_started_at = Arc::clone(&started_at);
 
         // Spawn background task to handle channel data
         let background_task = tokio::spawn(async move {
-            handle_channel_data(rx, bg_file, bg_path, bg_max_size, bg_size).await
+            handle_channel_data(rx, bg_file, bg_path, bg_policy, bg_size, bg_started_at).await
         });
 
         Ok(Self {
             file,
             path,
-            max_size,
+            policy,
             current_size,
+            started_at,
             state: State::Idle,
             tx,
             _background_task: background_task,
@@ -178,6 +278,7 @@ impl SyncChannelWriter {
 ///
 /// * `file` - The current log file to be rotated
 /// * `path` - Path to the current log file
+/// * `policy` - How many rotated files to keep and whether to compress them
 ///
 /// # Returns
 ///
@@ -189,46 +290,117 @@ impl SyncChannelWriter {
 ///
 /// Will return an error if:
 /// * File synchronization fails
-/// * Old backup file cannot be removed
-/// * File rename operation fails
+/// * Old segments cannot be removed, renamed or compressed
 /// * New log file cannot be created
-async fn do_rotation(file: File, path: PathBuf) -> io::Result<(File, PathBuf)> {
+async fn do_rotation(
+    file: File,
+    path: PathBuf,
+    policy: Arc<RotationPolicy>,
+) -> io::Result<(File, PathBuf)> {
     file.sync_all().await?;
-    let backup_path = path.with_extension("old");
-    if backup_path.exists() {
-        remove_file(&backup_path).await?;
+
+    // Shift the older segments along, dropping the oldest
+    if policy.max_files > 0 {
+        let oldest = policy.get_segment_path(&path, policy.max_files);
+        if oldest.exists() {
+            remove_file(&oldest).await?;
+        }
+
+        for index in (1..policy.max_files).rev() {
+            let segment = policy.get_segment_path(&path, index);
+            if segment.exists() {
+                rename(&segment, policy.get_segment_path(&path, index + 1)).await?;
+            }
+        }
     }
 
-    rename(&path, &backup_path).await?;
+    // Keep the current file as the newest segment, uncompressed for now
+    let newest = PathBuf::from({
+        let mut newest = OsString::from(path.as_os_str());
+        newest.push(".1");
+        newest
+    });
+    if policy.max_files > 0 {
+        if newest.exists() {
+            remove_file(&newest).await?;
+        }
 
+        hard_link(&path, &newest).await?;
+    }
+
+    // Atomically replace the log file with an empty one, so followers never find it missing
+    let mut new_path = OsString::from(path.as_os_str());
+    new_path.push(".new");
+    let new_path = PathBuf::from(new_path);
     let new_file = OpenOptions::new()
         .create(true)
-        .append(true)
-        .open(&path)
+        .truncate(true)
+        .write(true)
+        .open(&new_path)
         .await?;
+    rename(&new_path, &path).await?;
+
+    if policy.max_files > 0 && policy.compress {
+        let compressed = policy.get_segment_path(&path, 1);
+        tokio::task::spawn_blocking(move || compress_file(&newest, &compressed))
+            .await
+            .map_err(io::Error::other)??;
+    }
 
     Ok((new_file, path))
 }
 
+/// Compresses the file at `source` into a gzip file at `target` and removes `source`.
+///
+/// The compressed data is written to a temporary file that is renamed to `target` when complete,
+/// so `target` never holds a partially compressed file.
+fn compress_file(source: &Path, target: &Path) -> io::Result<()> {
+    let mut temp = OsString::from(target.as_os_str());
+    temp.push(".tmp");
+    let temp = PathBuf::from(temp);
+
+    let mut input = std::fs::File::open(source)?;
+    let mut encoder = GzEncoder::new(std::fs::File::create(&temp)?, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?.sync_all()?;
+
+    std::fs::rename(&temp, target)?;
+    std::fs::remove_file(source)
+}
+
+/// Returns the current time in seconds since the Unix epoch.
+fn now_secs() -> u64 {
+    to_secs(SystemTime::now())
+}
+
+/// Converts a time to seconds since the Unix epoch.
+fn to_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
 /// Background task that handles data from the sync channel
 async fn handle_channel_data(
     mut rx: UnboundedReceiver<Vec<u8>>,
     mut file: File,
     path: PathBuf,
-    max_size: u64,
+    policy: Arc<RotationPolicy>,
     current_size: Arc<AtomicU64>,
+    started_at: Arc<AtomicU64>,
 ) {
     while let Some(data) = rx.recv().await {
         let data_len = data.len() as u64;
         let size = current_size.fetch_add(data_len, Ordering::Relaxed);
 
-        if size + data_len > max_size {
+        if policy.should_rotate(size, data_len, started_at.load(Ordering::Relaxed)) {
             // Clone the file handle before rotation
             if let Ok(file_clone) = file.try_clone().await {
-                match do_rotation(file_clone, path.clone()).await {
+                match do_rotation(file_clone, path.clone(), Arc::clone(&policy)).await {
                     Ok((new_file, _)) => {
                         file = new_file;
-                        current_size.store(0, Ordering::Relaxed);
+                        current_size.store(data_len, Ordering::Relaxed);
+                        started_at.store(now_secs(), Ordering::Relaxed);
                     }
                     Err(e) => {
                         tracing::error!("failed to rotate log file: {}", e);
@@ -266,13 +438,15 @@ impl AsyncWrite for RotatingLog {
             match &mut this.state {
                 State::Idle => {
                     let size = this.current_size.fetch_add(buf_len, Ordering::Relaxed);
-                    if size + buf_len > this.max_size {
+                    let started_at = this.started_at.load(Ordering::Relaxed);
+                    if this.policy.should_rotate(size, buf_len, started_at) {
                         let old_file = std::mem::replace(
                             &mut this.file,
                             File::from_std(std::fs::File::open("/dev/null").unwrap()),
                         );
                         let old_path = this.path.clone();
-                        let fut = Box::pin(do_rotation(old_file, old_path));
+                        let policy = Arc::clone(&this.policy);
+                        let fut = Box::pin(do_rotation(old_file, old_path, policy));
                         this.state = State::Rotating(fut);
                     } else {
                         this.state = State::Writing;
@@ -290,7 +464,8 @@ impl AsyncWrite for RotatingLog {
                         Poll::Ready(Ok((new_file, new_path))) => {
                             this.file = new_file;
                             this.path = new_path;
-                            this.current_size.store(0, Ordering::Relaxed);
+                            this.current_size.store(buf_len, Ordering::Relaxed);
+                            this.started_at.store(now_secs(), Ordering::Relaxed);
                             this.state = State::Writing;
                         }
                     }
@@ -324,6 +499,17 @@ impl AsyncWrite for RotatingLog {
     }
 }
 
+impl Default for RotationPolicy {
+    fn default() -> Self {
+        Self {
+            max_size: DEFAULT_LOG_MAX_SIZE,
+            max_age: None,
+            max_files: DEFAULT_LOG_MAX_FILES,
+            compress: false,
+        }
+    }
+}
+
 impl Write for SyncChannelWriter {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         let data = buf.to_vec();
@@ -345,7 +531,8 @@ impl Write for SyncChannelWriter {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs;
+    use flate2::read::GzDecoder;
+    use std::{fs, io::Read};
     use tempfile::tempdir;
     use tokio::io::AsyncWriteExt;
 
@@ -356,7 +543,7 @@ mod tests {
 
         let log = RotatingLog::with_max_size(&log_path, 1024).await?;
         assert!(log_path.exists());
-        assert_eq!(log.max_size, 1024);
+        assert_eq!(log.policy.max_size, 1024);
         assert_eq!(log.current_size.load(Ordering::Relaxed), 0);
 
         Ok(())
@@ -402,12 +589,13 @@ mod tests {
         // Give some time for rotation to complete
         tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
 
-        // Check that both current and old log files exist
+        // Check that both current and rotated log files exist
+        let rotated_path = dir.path().join("test.log.1");
         assert!(log_path.exists());
-        assert!(log_path.with_extension("old").exists());
+        assert!(rotated_path.exists());
 
-        // Verify old file contains our first entry
-        let old_content = fs::read_to_string(log_path.with_extension("old"))?;
+        // Verify rotated file contains our first entry
+        let old_content = fs::read_to_string(&rotated_path)?;
         assert_eq!(old_content, String::from_utf8_lossy(first_entry));
 
         // Verify new file contains our second entry
@@ -480,9 +668,103 @@ mod tests {
             tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
         }
 
-        // Verify only one .old file exists (latest rotation)
+        // Verify only one rotated file exists (latest rotation)
         assert!(log_path.exists());
-        assert!(log_path.with_extension("old").exists());
+        assert!(dir.path().join("test.log.1").exists());
+        assert!(!dir.path().join("test.log.2").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rotation_keeps_max_files() -> io::Result<()> {
+        let dir = tempdir()?;
+        let log_path = dir.path().join("test.log");
+        let policy = RotationPolicy {
+            max_size: 20,
+            max_files: 3,
+            ..Default::default()
+        };
+
+        let mut log = RotatingLog::with_policy(&log_path, policy).await?;
+
+        // Each write fills a file, so every write after the first rotates
+        for i in 0..6 {
+            let test_data = format!("rotation entry {}\n", i).into_bytes();
+            log.write_all(&test_data).await?;
+            log.flush().await?;
+        }
+
+        assert_eq!(fs::read_to_string(&log_path)?, "rotation entry 5\n");
+        for (index, entry) in [(1, 4), (2, 3), (3, 2)] {
+            let segment = fs::read_to_string(dir.path().join(format!("test.log.{index}")))?;
+            assert_eq!(segment, format!("rotation entry {}\n", entry));
+        }
+        assert!(!dir.path().join("test.log.4").exists());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rotation_compresses_segments() -> io::Result<()> {
+        let dir = tempdir()?;
+        let log_path = dir.path().join("test.log");
+        let policy = RotationPolicy {
+            max_size: 20,
+            max_files: 2,
+            compress: true,
+            ..Default::default()
+        };
+
+        let mut log = RotatingLog::with_policy(&log_path, policy.clone()).await?;
+        for i in 0..3 {
+            let test_data = format!("compressed entry {}\n", i).into_bytes();
+            log.write_all(&test_data).await?;
+            log.flush().await?;
+        }
+
+        for (index, entry) in [(1, 1), (2, 0)] {
+            let segment_path = policy.get_segment_path(&log_path, index);
+            assert!(segment_path.to_string_lossy().ends_with(".gz"));
+
+            let mut content = String::new();
+            GzDecoder::new(fs::File::open(&segment_path)?).read_to_string(&mut content)?;
+            assert_eq!(content, format!("compressed entry {}\n", entry));
+        }
+
+        // Only the compressed segments are left
+        let mut files = fs::read_dir(dir.path())?
+            .map(|entry| Ok(entry?.file_name().to_string_lossy().into_owned()))
+            .collect::<io::Result<Vec<_>>>()?;
+        files.sort();
+        assert_eq!(files, ["test.log", "test.log.1.gz", "test.log.2.gz"]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rotation_by_age() -> io::Result<()> {
+        let dir = tempdir()?;
+        let log_path = dir.path().join("test.log");
+        let policy = RotationPolicy {
+            max_age: Some(Duration::ZERO),
+            ..Default::default()
+        };
+
+        let mut log = RotatingLog::with_policy(&log_path, policy).await?;
+
+        // An empty file is never too old, but a written one is
+        log.write_all(b"first entry\n").await?;
+        log.flush().await?;
+        assert!(!dir.path().join("test.log.1").exists());
+
+        log.write_all(b"second entry\n").await?;
+        log.flush().await?;
+        assert_eq!(
+            fs::read_to_string(dir.path().join("test.log.1"))?,
+            "first entry\n"
+        );
+        assert_eq!(fs::read_to_string(&log_path)?, "second entry\n");
 
         Ok(())
     }
@@ -0,0 +1,324 @@
+//! Following log files as they grow.
+//!
+//! [`tail_follow`] reads the last lines of a log file and then keeps yielding lines as they are
+//! appended, like `tail -n <lines> -f`. It keeps following the file by path across rotations and
+//! truncations.
+
+use std::{
+    collections::VecDeque,
+    io::{self, SeekFrom},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use futures::{stream, Stream};
+use tokio::{
+    fs::{self, File},
+    io::{AsyncReadExt, AsyncSeekExt},
+};
+
+use crate::{MonoutilsResult, SeekableReader, DEFAULT_TAIL_POLL_INTERVAL};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The size of the blocks read when searching backward for line boundaries.
+const TAIL_BLOCK_SIZE: u64 = 8 * 1024;
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// The state of a file being followed.
+struct Follower {
+    /// The path of the followed file.
+    path: PathBuf,
+
+    /// The currently open file.
+    file: File,
+
+    /// The inode of the currently open file, used to detect rotation.
+    ino: u64,
+
+    /// The offset up to which the file has been read.
+    position: u64,
+
+    /// Bytes of a line that has not been terminated yet.
+    partial: Vec<u8>,
+
+    /// Complete lines that have not been yielded yet.
+    lines: VecDeque<String>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl Follower {
+    /// Opens `path` for following, starting at `position`.
+    async fn open(path: PathBuf, position: u64) -> io::Result<Self> {
+        let mut file = File::open(&path).await?;
+        let ino = file.metadata().await?.ino();
+        file.seek(SeekFrom::Start(position)).await?;
+
+        Ok(Self {
+            path,
+            file,
+            ino,
+            position,
+            partial: Vec::new(),
+            lines: VecDeque::new(),
+        })
+    }
+
+    /// Returns the next complete line, waiting for the file to grow if needed.
+    async fn next_line(&mut self) -> io::Result<String> {
+        let mut buffer = vec![0; TAIL_BLOCK_SIZE as usize];
+        loop {
+            if let Some(line) = self.lines.pop_front() {
+                return Ok(line);
+            }
+
+            let read = self.file.read(&mut buffer).await?;
+            if read > 0 {
+                self.position += read as u64;
+                self.push_bytes(&buffer[..read]);
+                continue;
+            }
+
+            // Nothing new in the open file, so check whether it was rotated or truncated
+            if self.reopen_if_replaced().await? {
+                continue;
+            }
+
+            tokio::time::sleep(DEFAULT_TAIL_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Splits `bytes` into lines, keeping any unterminated line for later.
+    fn push_bytes(&mut self, bytes: &[u8]) {
+        self.partial.extend_from_slice(bytes);
+        while let Some(index) = self.partial.iter().position(|b| *b == b'\n') {
+            let line = self.partial.drain(..=index).collect::<Vec<_>>();
+            self.lines.push_back(to_line(&line));
+        }
+    }
+
+    /// Reopens the file from the start if the file at the path was replaced or truncated.
+    ///
+    /// Returns `true` if the file was reopened.
+    async fn reopen_if_replaced(&mut self) -> io::Result<bool> {
+        let metadata = match fs::metadata(&self.path).await {
+            Ok(metadata) => metadata,
+            // The file may be missing for a moment while it is being rotated
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+
+        if metadata.ino() == self.ino && metadata.len() >= self.position {
+            return Ok(false);
+        }
+
+        // An unterminated line at the end of the old file is complete now
+        if !self.partial.is_empty() {
+            let line = std::mem::take(&mut self.partial);
+            self.lines.push_back(to_line(&line));
+        }
+
+        let reopened = Self::open(self.path.clone(), 0).await?;
+        self.file = reopened.file;
+        self.ino = reopened.ino;
+        self.position = 0;
+
+        Ok(true)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Returns a stream of the last `lines` lines of the file at `path`, followed by every line
+/// appended to it afterwards.
+///
+/// The stream never ends on its own. It checks for new lines every
+/// [`DEFAULT_TAIL_POLL_INTERVAL`]. If the file is replaced, as when a [`RotatingLog`] rotates it,
+/// or truncated to less than has been read, the stream starts again from the beginning of the
+/// file. Lines are yielded without their line terminators.
+///
+/// [`RotatingLog`]: crate::RotatingLog
+///
+/// ## Arguments
+/// * `path` - The path of the file to follow
+/// * `lines` - The number of existing lines to yield before following
+///
+/// ## Errors
+/// Returns an error if the file cannot be opened or read. Errors while following are yielded by
+/// the stream, which then ends.
+///
+/// ## Examples
+///
+/// ```no_run
+/// use futures::StreamExt;
+/// use monoutils::log;
+///
+/// # #[tokio::main]
+/// # async fn main() -> anyhow::Result<()> {
+/// let stream = log::tail_follow("app.log", 10).await?;
+/// let mut stream = Box::pin(stream);
+/// while let Some(line) = stream.next().await {
+///     println!("{}", line?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn tail_follow(
+    path: impl AsRef<Path>,
+    lines: usize,
+) -> MonoutilsResult<impl Stream<Item = MonoutilsResult<String>>> {
+    let path = path.as_ref().to_path_buf();
+    let mut file = File::open(&path).await?;
+    let start = find_tail_start(&mut file, lines).await?;
+    let follower = Follower::open(path, start).await?;
+
+    Ok(stream::unfold(Some(follower), |follower| async move {
+        let mut follower = follower?;
+        match follower.next_line().await {
+            Ok(line) => Some((Ok(line), Some(follower))),
+            Err(e) => Some((Err(e.into()), None)),
+        }
+    }))
+}
+
+/// Returns the offset where the last `lines` lines of `reader` start.
+///
+/// The reader is searched backward from its end one block at a time, so only the tail is read. An
+/// unterminated last line counts as a line.
+async fn find_tail_start<R>(reader: &mut R, lines: usize) -> io::Result<u64>
+where
+    R: SeekableReader + Unpin,
+{
+    let len = reader.seek(SeekFrom::End(0)).await?;
+    if lines == 0 {
+        return Ok(len);
+    }
+
+    let mut buffer = vec![0; TAIL_BLOCK_SIZE as usize];
+    let mut end = len;
+    let mut newlines = 0;
+    while end > 0 {
+        let start = end.saturating_sub(TAIL_BLOCK_SIZE);
+        let block = &mut buffer[..(end - start) as usize];
+        reader.seek(SeekFrom::Start(start)).await?;
+        reader.read_exact(block).await?;
+
+        for (index, byte) in block.iter().enumerate().rev() {
+            let offset = start + index as u64;
+            // The newline ending the last line doesn't start a new one
+            if *byte == b'\n' && offset != len - 1 {
+                newlines += 1;
+                if newlines == lines {
+                    return Ok(offset + 1);
+                }
+            }
+        }
+
+        end = start;
+    }
+
+    Ok(0)
+}
+
+/// Converts a line read from a file to a string without its line terminator.
+fn to_line(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+    let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, time::Duration};
+
+    use futures::StreamExt;
+    use tempfile::tempdir;
+    use tokio::io::AsyncWriteExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_find_tail_start() -> io::Result<()> {
+        for (content, lines, expected) in [
+            ("a\nb\nc\n", 1, "c\n"),
+            ("a\nb\nc\n", 2, "b\nc\n"),
+            ("a\nb\nc", 1, "c"),
+            ("a\nb\nc", 5, "a\nb\nc"),
+            ("a\nb\nc\n", 0, ""),
+            ("", 3, ""),
+        ] {
+            let mut reader = Cursor::new(content.as_bytes());
+            let start = find_tail_start(&mut reader, lines).await?;
+            assert_eq!(&content[start as usize..], expected);
+        }
+
+        // Lines spanning several blocks
+        let content = (0..5000).map(|i| format!("line {i}\n")).collect::<String>();
+        let mut reader = Cursor::new(content.as_bytes());
+        let start = find_tail_start(&mut reader, 2000).await?;
+        assert!(content[start as usize..].starts_with("line 3000\n"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_tail_follow() -> anyhow::Result<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("test.log");
+        fs::write(&path, "one\ntwo\nthree\n").await?;
+
+        let stream = tail_follow(&path, 2).await?;
+        let mut stream = Box::pin(stream);
+
+        // Write more lines while the stream is being read
+        let writer_path = path.clone();
+        let writer = tokio::spawn(async move {
+            let mut file = fs::OpenOptions::new()
+                .append(true)
+                .open(&writer_path)
+                .await?;
+            for line in ["four\n", "fi", "ve\n"] {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+                file.write_all(line.as_bytes()).await?;
+                file.flush().await?;
+            }
+
+            io::Result::Ok(())
+        });
+
+        let mut yielded = Vec::new();
+        while yielded.len() < 4 {
+            let line = tokio::time::timeout(Duration::from_secs(5), stream.next()).await?;
+            yielded.push(line.unwrap()?);
+        }
+        writer.await??;
+        assert_eq!(yielded, ["two", "three", "four", "five"]);
+
+        // Rotation
+        fs::rename(&path, path.with_extension("old")).await?;
+        fs::write(&path, "six\n").await?;
+        let line = tokio::time::timeout(Duration::from_secs(5), stream.next()).await?;
+        assert_eq!(line.unwrap()?, "six");
+
+        // Truncation, to less than has been read
+        fs::write(&path, "7\n").await?;
+        let line = tokio::time::timeout(Duration::from_secs(5), stream.next()).await?;
+        assert_eq!(line.unwrap()?, "7");
+
+        Ok(())
+    }
+}
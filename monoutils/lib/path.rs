@@ -1,6 +1,6 @@
 //! `monoutils::path` is a module containing path utilities for the monocore project.
 
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 use typed_path::{Utf8UnixComponent, Utf8UnixPathBuf};
 
@@ -130,6 +130,78 @@ pub fn normalize_path(path: &str, path_type: SupportedPathType) -> MonoutilsResu
     }
 }
 
+/// Normalizes a path lexically, without touching the filesystem.
+///
+/// `.` components and redundant separators are removed and `..` components are resolved against
+/// the preceding components. A normalized relative path may be empty, as for `.`.
+///
+/// ## Arguments
+///
+/// * `path` - The path to normalize
+///
+/// ## Errors
+///
+/// Returns `MonoutilsError::PathValidation` if a `..` component would escape the root of an
+/// absolute path or the start of a relative one.
+///
+/// ## Examples
+///
+/// ```
+/// use std::path::Path;
+/// use monoutils::path::normalize;
+///
+/// assert_eq!(normalize(Path::new("a//./b/../c")).unwrap(), Path::new("a/c"));
+/// assert!(normalize(Path::new("a/../../etc/passwd")).is_err());
+/// ```
+pub fn normalize(path: &Path) -> MonoutilsResult<PathBuf> {
+    let mut normalized = PathBuf::new();
+    let mut depth = 0;
+
+    for component in path.components() {
+        match component {
+            Component::Prefix(_) | Component::RootDir => normalized.push(component),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if depth == 0 {
+                    return Err(MonoutilsError::PathValidation(format!(
+                        "Invalid path: {} traverses above its root",
+                        path.display()
+                    )));
+                }
+
+                normalized.pop();
+                depth -= 1;
+            }
+            Component::Normal(name) => {
+                normalized.push(name);
+                depth += 1;
+            }
+        }
+    }
+
+    Ok(normalized)
+}
+
+/// Returns `true` if `candidate` is `base` or a path under it.
+///
+/// Relative candidates are resolved against `base`. The check is lexical, so symlinks are not
+/// followed, and a candidate that can't be normalized is never within `base`.
+///
+/// ## Arguments
+///
+/// * `base` - The directory the candidate must be within
+/// * `candidate` - The path to check
+pub fn is_within(base: impl AsRef<Path>, candidate: impl AsRef<Path>) -> bool {
+    let base = base.as_ref();
+    let (Ok(normalized_base), Ok(normalized_candidate)) =
+        (normalize(base), normalize(&base.join(candidate)))
+    else {
+        return false;
+    };
+
+    normalized_candidate.starts_with(normalized_base)
+}
+
 /// Resolves the path to a file, checking both environment variable and default locations.
 ///
 /// First checks the environment variable specified by `env_var`.
@@ -263,4 +335,36 @@ mod tests {
             Err(MonoutilsError::PathValidation(e)) if e.contains("cannot traverse above root")
         ));
     }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize(Path::new("a/./b")).unwrap(), Path::new("a/b"));
+        assert_eq!(normalize(Path::new("a//b")).unwrap(), Path::new("a/b"));
+        assert_eq!(normalize(Path::new("a/b/../c/")).unwrap(), Path::new("a/c"));
+        assert_eq!(normalize(Path::new("/a/../b")).unwrap(), Path::new("/b"));
+        assert_eq!(normalize(Path::new("./.")).unwrap(), Path::new(""));
+        assert_eq!(normalize(Path::new("/")).unwrap(), Path::new("/"));
+
+        assert!(matches!(
+            normalize(Path::new("a/../../etc/passwd")),
+            Err(MonoutilsError::PathValidation(e)) if e.contains("traverses above its root")
+        ));
+        assert!(normalize(Path::new("/..")).is_err());
+        assert!(normalize(Path::new("..")).is_err());
+    }
+
+    #[test]
+    fn test_is_within() {
+        assert!(is_within("/srv/data", "/srv/data"));
+        assert!(is_within("/srv/data", "/srv/data/a/b"));
+        assert!(is_within("/srv/data", "a/./b"));
+        assert!(is_within("/srv/data", "a/../b"));
+        assert!(is_within("/srv/data/", "/srv//data/x/../y"));
+
+        assert!(!is_within("/srv/data", "/srv/database"));
+        assert!(!is_within("/srv/data", "/etc/passwd"));
+        assert!(!is_within("/srv/data", "../data2"));
+        assert!(!is_within("/srv/data", "a/../../../etc/passwd"));
+        assert!(!is_within("data", "../../x"));
+    }
 }
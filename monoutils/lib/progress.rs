@@ -0,0 +1,185 @@
+//! `monoutils::progress` is a module containing utilities for reporting the progress of long
+//! operations, like image pulls and ingests, so they can be rendered as progress bars.
+
+use std::{
+    io,
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll},
+};
+
+use tokio::io::{AsyncRead, ReadBuf};
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// An update on the progress of a long operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgressEvent {
+    /// `done` of the `total` bytes of the operation have been processed.
+    ///
+    /// `done` never decreases from one event to the next, and reaches `total` when the operation
+    /// completes.
+    Bytes {
+        /// The number of bytes processed so far.
+        done: u64,
+
+        /// The number of bytes the operation processes in total.
+        total: u64,
+    },
+
+    /// An item of the operation, e.g. an image layer or an ingested file, is complete.
+    ItemCompleted {
+        /// The name of the item, e.g. the digest of a layer or the path of a file.
+        item: String,
+    },
+}
+
+/// Counts the bytes processed by an operation and reports them to a progress callback.
+///
+/// Updates are serialized, so the callback sees `done` grow monotonically even when several
+/// parts of the operation, e.g. concurrent layer downloads, advance at the same time.
+pub struct ProgressTracker<'a> {
+    /// The callback the progress is reported to.
+    progress: &'a (dyn Fn(ProgressEvent) + Send + Sync),
+
+    /// The number of bytes the operation processes in total.
+    total: u64,
+
+    /// The number of bytes processed so far.
+    done: Mutex<u64>,
+}
+
+/// A reader that reports the bytes read through it to a [`ProgressTracker`].
+pub struct ProgressReader<'a, R> {
+    /// The reader being read.
+    inner: R,
+
+    /// The tracker the bytes read are reported to.
+    tracker: &'a ProgressTracker<'a>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl<'a> ProgressTracker<'a> {
+    /// Creates a tracker for an operation processing `total` bytes, and reports that none of them
+    /// are done yet.
+    pub fn new(total: u64, progress: &'a (dyn Fn(ProgressEvent) + Send + Sync)) -> Self {
+        progress(ProgressEvent::Bytes { done: 0, total });
+        Self {
+            progress,
+            total,
+            done: Mutex::new(0),
+        }
+    }
+
+    /// Records that `bytes` more bytes have been processed, and reports the new count.
+    pub fn advance(&self, bytes: u64) {
+        if bytes == 0 {
+            return;
+        }
+
+        let mut done = self.done.lock().unwrap();
+        *done += bytes;
+        (self.progress)(ProgressEvent::Bytes {
+            done: *done,
+            total: self.total,
+        });
+    }
+
+    /// Reports that `item` is complete.
+    pub fn complete_item(&self, item: impl Into<String>) {
+        (self.progress)(ProgressEvent::ItemCompleted { item: item.into() });
+    }
+
+    /// Returns the number of bytes processed so far.
+    pub fn get_done(&self) -> u64 {
+        *self.done.lock().unwrap()
+    }
+
+    /// Returns the number of bytes the operation processes in total.
+    pub fn get_total(&self) -> u64 {
+        self.total
+    }
+}
+
+impl<'a, R> ProgressReader<'a, R> {
+    /// Wraps `inner`, reporting the bytes read through it to `tracker`.
+    pub fn new(inner: R, tracker: &'a ProgressTracker<'a>) -> Self {
+        Self { inner, tracker }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Trait Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl<R: AsyncRead + Unpin> AsyncRead for ProgressReader<'_, R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = result {
+            self.tracker.advance((buf.filled().len() - filled) as u64);
+        }
+
+        result
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_progress_reader_reports_bytes_read() -> anyhow::Result<()> {
+        let events = Mutex::new(Vec::new());
+        let progress = |event| events.lock().unwrap().push(event);
+        let data = vec![7u8; 10_000];
+
+        let tracker = ProgressTracker::new(data.len() as u64, &progress);
+        let mut reader = ProgressReader::new(&data[..], &tracker);
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).await?;
+        tracker.complete_item("data");
+
+        assert_eq!(read, data);
+        assert_eq!(tracker.get_done(), tracker.get_total());
+
+        let events = events.into_inner().unwrap();
+        assert_eq!(
+            events.first(),
+            Some(&ProgressEvent::Bytes {
+                done: 0,
+                total: 10_000
+            })
+        );
+        assert_eq!(
+            events.iter().rev().nth(1),
+            Some(&ProgressEvent::Bytes {
+                done: 10_000,
+                total: 10_000
+            })
+        );
+        assert_eq!(
+            events.last(),
+            Some(&ProgressEvent::ItemCompleted {
+                item: "data".to_string()
+            })
+        );
+
+        Ok(())
+    }
+}
@@ -1,11 +1,18 @@
+use std::{fmt, process::ExitStatus, str::FromStr, time::Duration};
+
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use tokio::{
     fs::File,
     io::unix::AsyncFd,
     process::{ChildStderr, ChildStdin, ChildStdout},
 };
 
-use crate::MonoutilsResult;
+use crate::{
+    MonoutilsError, MonoutilsResult, DEFAULT_BOOT_TIMEOUT, DEFAULT_HEALTH_FAILURE_THRESHOLD,
+    DEFAULT_HEALTH_PROBE_INTERVAL, DEFAULT_RESTART_BACKOFF_BASE, DEFAULT_RESTART_BACKOFF_CAP,
+    DEFAULT_RESTART_MAX_RETRIES, DEFAULT_RESTART_RESET_AFTER,
+};
 
 //--------------------------------------------------------------------------------------------------
 // Types
@@ -35,11 +42,152 @@ pub enum ChildIo {
     },
 }
 
+/// When a supervised child process is restarted after it exits.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    /// The child is never restarted.
+    #[default]
+    Never,
+
+    /// The child is restarted whenever it exits on its own.
+    Always,
+
+    /// The child is restarted only when it exits unsuccessfully.
+    OnFailure,
+}
+
+/// How long to wait between restarts of a supervised child process, and how many times to
+/// restart it.
+///
+/// The delay doubles with every restart, starting at `base` and never exceeding `cap`. A child
+/// that stays up for at least `reset_after` before exiting starts over with a fresh budget, so
+/// occasional crashes of a long-running child don't add up to a permanent stop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RestartBackoff {
+    /// The delay before the first restart.
+    pub base: Duration,
+
+    /// The upper bound on the delay.
+    pub cap: Duration,
+
+    /// The number of restarts after which the child is left stopped.
+    pub max_retries: u32,
+
+    /// How long the child has to run before exiting for the restart count to be reset.
+    pub reset_after: Duration,
+}
+
+/// How a supervised child process is checked for readiness after it starts, and for health
+/// afterwards.
+///
+/// The child is probed repeatedly until it first reports ready. If that doesn't happen within
+/// `boot_timeout`, it is stopped. Once ready, it is probed every `interval`, and marked unhealthy
+/// after `failure_threshold` consecutive failed probes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HealthCheck {
+    /// How long the child has to report ready after it starts.
+    pub boot_timeout: Duration,
+
+    /// The interval between health probes once the child is ready, or `None` to stop probing
+    /// after the child is ready.
+    pub interval: Option<Duration>,
+
+    /// The number of consecutive failed probes after which the child is unhealthy.
+    pub failure_threshold: u32,
+}
+
+/// The health of a supervised child process, as reported by its [`HealthProbe`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthStatus {
+    /// The child has not reported ready yet.
+    Starting,
+
+    /// The child is ready, and has not failed too many health probes in a row.
+    Healthy,
+
+    /// The child has failed [`HealthCheck::failure_threshold`] health probes in a row.
+    Unhealthy,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl RestartPolicy {
+    /// Returns `true` if a child that exited with `status` should be restarted.
+    pub fn should_restart(&self, status: &ExitStatus) -> bool {
+        match self {
+            RestartPolicy::Never => false,
+            RestartPolicy::Always => true,
+            RestartPolicy::OnFailure => !status.success(),
+        }
+    }
+}
+
+impl RestartBackoff {
+    /// Returns the delay before restart number `restart`, counting from zero.
+    pub fn delay(&self, restart: u32) -> Duration {
+        let factor = 2u32.checked_pow(restart).unwrap_or(u32::MAX);
+        self.base.saturating_mul(factor).min(self.cap)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Trait Implementations
+//--------------------------------------------------------------------------------------------------
+
+impl Default for RestartBackoff {
+    fn default() -> Self {
+        Self {
+            base: DEFAULT_RESTART_BACKOFF_BASE,
+            cap: DEFAULT_RESTART_BACKOFF_CAP,
+            max_retries: DEFAULT_RESTART_MAX_RETRIES,
+            reset_after: DEFAULT_RESTART_RESET_AFTER,
+        }
+    }
+}
+
+impl FromStr for RestartPolicy {
+    type Err = MonoutilsError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "never" => Ok(RestartPolicy::Never),
+            "always" => Ok(RestartPolicy::Always),
+            "on-failure" => Ok(RestartPolicy::OnFailure),
+            _ => Err(MonoutilsError::InvalidRestartPolicy(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for RestartPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RestartPolicy::Never => write!(f, "never"),
+            RestartPolicy::Always => write!(f, "always"),
+            RestartPolicy::OnFailure => write!(f, "on-failure"),
+        }
+    }
+}
+
+impl Default for HealthCheck {
+    fn default() -> Self {
+        Self {
+            boot_timeout: DEFAULT_BOOT_TIMEOUT,
+            interval: Some(DEFAULT_HEALTH_PROBE_INTERVAL),
+            failure_threshold: DEFAULT_HEALTH_FAILURE_THRESHOLD,
+        }
+    }
+}
+
 //--------------------------------------------------------------------------------------------------
 // Traits
 //--------------------------------------------------------------------------------------------------
 
 /// A trait for monitoring processes
+// `async_trait` marks the boxed futures it returns as `must_use` already
+#[allow(clippy::double_must_use)]
 #[async_trait]
 pub trait ProcessMonitor {
     /// Start monitoring a process
@@ -48,3 +196,86 @@ pub trait ProcessMonitor {
     /// Stop monitoring
     async fn stop(&mut self) -> MonoutilsResult<()>;
 }
+
+/// A trait for checking whether a supervised process, such as the agent in a MicroVM guest, is
+/// ready and healthy
+// `async_trait` marks the boxed futures it returns as `must_use` already
+#[allow(clippy::double_must_use)]
+#[async_trait]
+pub trait HealthProbe: Send + Sync {
+    /// Returns `true` if the process with `pid` is ready and healthy.
+    ///
+    /// An error is treated the same as `false`.
+    async fn probe(&self, pid: u32) -> MonoutilsResult<bool>;
+
+    /// Called when the health of the process with `pid` changes, for example to record it where
+    /// other processes can see it.
+    ///
+    /// The health is reset to [`HealthStatus::Starting`] without a call whenever the process is
+    /// restarted. Does nothing by default.
+    async fn health_changed(&self, _pid: u32, _status: HealthStatus) -> MonoutilsResult<()> {
+        Ok(())
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::process::ExitStatusExt;
+
+    use super::*;
+
+    #[test]
+    fn test_restart_policy_should_restart() {
+        let success = ExitStatus::from_raw(0);
+        let failure = ExitStatus::from_raw(1 << 8);
+
+        assert!(!RestartPolicy::Never.should_restart(&success));
+        assert!(!RestartPolicy::Never.should_restart(&failure));
+        assert!(RestartPolicy::Always.should_restart(&success));
+        assert!(RestartPolicy::Always.should_restart(&failure));
+        assert!(!RestartPolicy::OnFailure.should_restart(&success));
+        assert!(RestartPolicy::OnFailure.should_restart(&failure));
+    }
+
+    #[test]
+    fn test_restart_policy_from_str() -> anyhow::Result<()> {
+        for policy in [
+            RestartPolicy::Never,
+            RestartPolicy::Always,
+            RestartPolicy::OnFailure,
+        ] {
+            assert_eq!(policy.to_string().parse::<RestartPolicy>()?, policy);
+        }
+
+        assert_eq!(
+            "on-failure".parse::<RestartPolicy>()?,
+            RestartPolicy::OnFailure
+        );
+        assert!("on_failure".parse::<RestartPolicy>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_restart_backoff_delay() {
+        let backoff = RestartBackoff {
+            base: Duration::from_millis(100),
+            cap: Duration::from_secs(1),
+            max_retries: 10,
+            ..Default::default()
+        };
+
+        let delays = (0..6).map(|r| backoff.delay(r)).collect::<Vec<_>>();
+        assert_eq!(
+            delays,
+            [100, 200, 400, 800, 1000, 1000].map(Duration::from_millis)
+        );
+
+        // Large restart counts don't overflow
+        assert_eq!(backoff.delay(u32::MAX), Duration::from_secs(1));
+    }
+}
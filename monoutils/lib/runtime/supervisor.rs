@@ -1,22 +1,27 @@
 use nix::{
     fcntl::{fcntl, FcntlArg, OFlag},
     pty::openpty,
+    sys::signal::Signal,
     unistd::Pid,
 };
 use std::{
     os::unix::io::{AsRawFd, FromRawFd, IntoRawFd},
     path::PathBuf,
-    process::Stdio,
+    process::{ExitStatus, Stdio},
+    time::{Duration, Instant},
 };
 use tokio::{
     fs::{create_dir_all, File},
     io::unix::AsyncFd,
-    process::Command,
+    process::{Child, Command},
     signal::unix::{signal, SignalKind},
+    sync::Mutex,
 };
 
 use crate::{
-    path::SUPERVISOR_LOG_FILENAME, term, ChildIo, MonoutilsResult, ProcessMonitor, RotatingLog,
+    path::SUPERVISOR_LOG_FILENAME, term, ChildIo, HealthCheck, HealthProbe, HealthStatus,
+    MonoutilsError, MonoutilsResult, ProcessMonitor, RestartBackoff, RestartPolicy, RotatingLog,
+    DEFAULT_CHILD_POLL_INTERVAL, DEFAULT_SHUTDOWN_GRACE_PERIOD,
 };
 
 //--------------------------------------------------------------------------------------------------
@@ -37,6 +42,12 @@ where
     /// The managed child process ID
     child_pid: Option<u32>,
 
+    /// The managed child process, until it has exited
+    child: Mutex<Option<Child>>,
+
+    /// Why the managed child process exited, once it has
+    exit_reason: std::sync::Mutex<Option<ExitReason>>,
+
     /// Environment variables for the child process
     child_envs: Vec<(String, String)>,
 
@@ -45,6 +56,40 @@ where
 
     /// The metrics monitor
     process_monitor: M,
+
+    /// When the child process is restarted after it exits
+    restart_policy: RestartPolicy,
+
+    /// The delays between restarts and the number of restarts allowed
+    restart_backoff: RestartBackoff,
+
+    /// The number of times the child process has been restarted
+    restart_count: u32,
+
+    /// The exit status of the last child process that exited on its own
+    last_exit_status: Option<ExitStatus>,
+
+    /// Checks whether the child process is ready and healthy, if set
+    health_probe: Option<Box<dyn HealthProbe>>,
+
+    /// When and how often the health probe is run
+    health_check: HealthCheck,
+
+    /// The health of the child process, if it has a health probe
+    health_status: std::sync::Mutex<Option<HealthStatus>>,
+}
+
+/// Why a supervised child process exited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    /// The child exited on its own with the given status.
+    Exited(ExitStatus),
+
+    /// The child exited within the grace period after being sent SIGTERM.
+    Terminated(ExitStatus),
+
+    /// The child did not exit within the grace period and was sent SIGKILL.
+    Killed,
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -79,18 +124,255 @@ where
                 .map(|(k, v)| (k.into(), v.into()))
                 .collect(),
             child_pid: None,
+            child: Mutex::new(None),
+            exit_reason: std::sync::Mutex::new(None),
             log_dir: log_dir.into(),
             process_monitor,
+            restart_policy: RestartPolicy::default(),
+            restart_backoff: RestartBackoff::default(),
+            restart_count: 0,
+            last_exit_status: None,
+            health_probe: None,
+            health_check: HealthCheck::default(),
+            health_status: std::sync::Mutex::new(None),
         }
     }
 
+    /// Sets when the child process is restarted after it exits, and how.
+    ///
+    /// ## Arguments
+    ///
+    /// * `policy` - When the child process is restarted
+    /// * `backoff` - The delays between restarts and the number of restarts allowed
+    pub fn with_restart_policy(mut self, policy: RestartPolicy, backoff: RestartBackoff) -> Self {
+        self.restart_policy = policy;
+        self.restart_backoff = backoff;
+        self
+    }
+
+    /// Sets how the child process is checked for readiness and health.
+    ///
+    /// If the child doesn't report ready within the boot timeout, it is stopped and
+    /// [`start`][Supervisor::start] returns [`MonoutilsError::VmBootTimeout`].
+    ///
+    /// ## Arguments
+    ///
+    /// * `probe` - Checks whether the child is ready and healthy
+    /// * `health_check` - The boot timeout, and when the child is marked unhealthy
+    pub fn with_health_probe(
+        mut self,
+        probe: impl HealthProbe + 'static,
+        health_check: HealthCheck,
+    ) -> Self {
+        self.health_probe = Some(Box::new(probe));
+        self.health_check = health_check;
+        self
+    }
+
+    /// Returns the health of the child process, or `None` if it has no health probe.
+    pub fn get_health_status(&self) -> Option<HealthStatus> {
+        *self.health_status.lock().unwrap()
+    }
+
+    /// Returns the number of times the child process has been restarted.
+    pub fn get_restart_count(&self) -> u32 {
+        self.restart_count
+    }
+
+    /// Returns the exit status of the last child process that exited on its own.
+    pub fn get_last_exit_status(&self) -> Option<ExitStatus> {
+        self.last_exit_status
+    }
+
     /// Starts the supervisor and the child process.
     ///
     /// This method:
     /// 1. Creates the log directory if it doesn't exist
     /// 2. Starts the child process with appropriate IO (TTY or pipes)
     /// 3. Passes the IO to the process monitor
+    /// 4. Waits for the child to exit, or stops it gracefully on SIGTERM or SIGINT
+    /// 5. Stops the child if it has a health probe and doesn't report ready within the boot timeout
+    /// 6. Restarts the child after a backoff delay if the restart policy says so, resetting the
+    ///    restart count if the child stayed up for the backoff's `reset_after`
+    ///
+    /// ## Errors
+    ///
+    /// Returns [`MonoutilsError::VmBootTimeout`] if the child doesn't report ready in time.
     pub async fn start(&mut self) -> MonoutilsResult<()> {
+        // Setup signal handlers
+        let mut sigterm = signal(SignalKind::terminate())?;
+        let mut sigint = signal(SignalKind::interrupt())?;
+
+        loop {
+            let child_io = self.spawn().await?;
+            let child_pid = self.child_pid.expect("child process was just spawned");
+            let started_at = Instant::now();
+
+            // Start monitoring
+            self.process_monitor.start(child_pid, child_io).await?;
+
+            // Wait for either child process to exit or signal to be received
+            let status = tokio::select! {
+                status = self.wait() => {
+                    // Stop process monitoring
+                    self.process_monitor.stop().await?;
+
+                    tracing::info!("child process {} exited", child_pid);
+
+                    match status {
+                        Ok(status) if status.success() => {
+                            tracing::info!("child process {} exited successfully", child_pid);
+                            status
+                        }
+                        Ok(status) => {
+                            tracing::error!(
+                                "child process {} exited with status: {:?}",
+                                child_pid,
+                                status
+                            );
+                            status
+                        }
+                        Err(e) => {
+                            tracing::error!("failed to wait for child process {}: {:?}", child_pid, e);
+                            break;
+                        }
+                    }
+                }
+                _ = sigterm.recv() => {
+                    // Stop process monitoring
+                    self.process_monitor.stop().await?;
+
+                    tracing::info!("received SIGTERM signal");
+
+                    if let Err(e) = self.stop(DEFAULT_SHUTDOWN_GRACE_PERIOD).await {
+                        tracing::error!("failed to stop child after SIGTERM: {}", e);
+                    }
+                    break;
+                }
+                _ = sigint.recv() => {
+                    // Stop process monitoring
+                    self.process_monitor.stop().await?;
+
+                    tracing::info!("received SIGINT signal");
+
+                    if let Err(e) = self.stop(DEFAULT_SHUTDOWN_GRACE_PERIOD).await {
+                        tracing::error!("failed to stop child after SIGINT: {}", e);
+                    }
+                    break;
+                }
+                Err(e) = self.watch_health(child_pid) => {
+                    // Stop process monitoring
+                    self.process_monitor.stop().await?;
+
+                    tracing::error!("child process {} failed to boot: {}", child_pid, e);
+
+                    if let Err(e) = self.stop(DEFAULT_SHUTDOWN_GRACE_PERIOD).await {
+                        tracing::error!("failed to stop child after boot timeout: {}", e);
+                    }
+
+                    self.child_pid = None;
+                    return Err(e);
+                }
+            };
+
+            self.last_exit_status = Some(status);
+            if !self.restart_policy.should_restart(&status) {
+                break;
+            }
+
+            // A child that stayed up long enough gets a fresh restart budget
+            if started_at.elapsed() >= self.restart_backoff.reset_after {
+                self.restart_count = 0;
+            }
+
+            if self.restart_count >= self.restart_backoff.max_retries {
+                tracing::error!(
+                    event = "restart_budget_exhausted",
+                    restarts = self.restart_count,
+                    "child process exited with status {:?} after {} restarts, not restarting it again",
+                    status,
+                    self.restart_count
+                );
+                break;
+            }
+
+            let delay = self.restart_backoff.delay(self.restart_count);
+            self.restart_count += 1;
+            tracing::warn!(
+                "restarting child process in {:?} (restart {} of {})",
+                delay,
+                self.restart_count,
+                self.restart_backoff.max_retries
+            );
+
+            // Don't restart if asked to shut down while waiting
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = sigterm.recv() => break,
+                _ = sigint.recv() => break,
+            }
+        }
+
+        self.child_pid = None;
+
+        Ok(())
+    }
+
+    /// Stops the child process gracefully.
+    ///
+    /// The child is sent SIGTERM and given up to `grace` to exit. If it is still running after
+    /// that, it is sent SIGKILL. The outcome is recorded and available from
+    /// [`get_exit_reason`][Supervisor::get_exit_reason]. Does nothing if there is no running child.
+    ///
+    /// ## Arguments
+    ///
+    /// * `grace` - How long to wait for the child to exit after SIGTERM
+    ///
+    /// ## Returns
+    ///
+    /// Why the child exited, or `None` if there was no running child.
+    pub async fn stop(&self, grace: Duration) -> MonoutilsResult<Option<ExitReason>> {
+        let mut child = self.child.lock().await;
+        let Some(running) = child.as_mut() else {
+            return Ok(None);
+        };
+
+        // The child may have exited already without having been waited for
+        if let Some(status) = running.try_wait()? {
+            child.take();
+            return Ok(Some(self.record_exit(ExitReason::Exited(status))));
+        }
+
+        if let Some(pid) = running.id() {
+            tracing::info!("sending SIGTERM to child process {}", pid);
+            nix::sys::signal::kill(Pid::from_raw(pid as i32), Signal::SIGTERM)?;
+        }
+
+        let reason = match tokio::time::timeout(grace, running.wait()).await {
+            Ok(status) => ExitReason::Terminated(status?),
+            Err(_) => {
+                tracing::warn!(
+                    "child process did not exit within {:?} of SIGTERM, sending SIGKILL",
+                    grace
+                );
+
+                // Sends SIGKILL and waits for the child to exit
+                running.kill().await?;
+                ExitReason::Killed
+            }
+        };
+
+        child.take();
+        Ok(Some(self.record_exit(reason)))
+    }
+
+    /// Returns why the child process exited, or `None` if it has not exited yet.
+    pub fn get_exit_reason(&self) -> Option<ExitReason> {
+        *self.exit_reason.lock().unwrap()
+    }
+
+    /// Creates the log directory and spawns the child process with appropriate IO (TTY or pipes).
+    async fn spawn(&mut self) -> MonoutilsResult<ChildIo> {
         // Create log directory if it doesn't exist
         create_dir_all(&self.log_dir).await?;
 
@@ -98,7 +380,7 @@ where
         let _supervisor_log = RotatingLog::new(self.log_dir.join(SUPERVISOR_LOG_FILENAME)).await?;
 
         // Check if we're running in an interactive terminal
-        let (mut child, child_io) = if term::is_interactive_terminal() {
+        let (child, child_io) = if term::is_interactive_terminal() {
             tracing::info!("running in an interactive terminal");
             // Create a new pseudo terminal and set master to non-blocking mode
             let pty = openpty(None, None)?;
@@ -181,99 +463,501 @@ where
             (child, child_io)
         };
 
-        let child_pid = child.id().expect("failed to get child process id");
-        self.child_pid = Some(child_pid);
+        self.child_pid = child.id();
+        *self.child.lock().await = Some(child);
+        *self.exit_reason.lock().unwrap() = None;
+        *self.health_status.lock().unwrap() =
+            self.health_probe.as_ref().map(|_| HealthStatus::Starting);
 
-        // Start monitoring
-        self.process_monitor.start(child_pid, child_io).await?;
+        Ok(child_io)
+    }
 
-        // Setup signal handlers
-        let mut sigterm = signal(SignalKind::terminate())?;
-        let mut sigint = signal(SignalKind::interrupt())?;
+    /// Waits for the child process to exit on its own.
+    ///
+    /// The child is polled rather than waited on so that [`stop`][Supervisor::stop] can take it
+    /// over in the meantime.
+    async fn wait(&self) -> MonoutilsResult<ExitStatus> {
+        loop {
+            {
+                let mut child = self.child.lock().await;
+                let Some(running) = child.as_mut() else {
+                    return Err(MonoutilsError::Runtime(
+                        "no child process to wait for".to_string(),
+                    ));
+                };
+
+                if let Some(status) = running.try_wait()? {
+                    child.take();
+                    self.record_exit(ExitReason::Exited(status));
+                    return Ok(status);
+                }
+            }
 
-        // Wait for either child process to exit or signal to be received
-        tokio::select! {
-            status = child.wait() => {
-                // Stop process monitoring
-                self.process_monitor.stop().await?;
+            tokio::time::sleep(DEFAULT_CHILD_POLL_INTERVAL).await;
+        }
+    }
 
-                tracing::info!("child process {} exited", child_pid);
+    /// Probes the child process until it reports ready, then keeps probing it for health.
+    ///
+    /// Only returns if the child doesn't report ready within the boot timeout, and never returns
+    /// if the child has no health probe.
+    async fn watch_health(&self, pid: u32) -> MonoutilsResult<()> {
+        let Some(probe) = &self.health_probe else {
+            return std::future::pending().await;
+        };
+        let HealthCheck {
+            boot_timeout,
+            interval,
+            failure_threshold,
+        } = self.health_check;
+
+        let ready = tokio::time::timeout(boot_timeout, async {
+            while !Self::run_probe(probe.as_ref(), pid).await {
+                tokio::time::sleep(DEFAULT_CHILD_POLL_INTERVAL).await;
+            }
+        });
+        if ready.await.is_err() {
+            return Err(MonoutilsError::VmBootTimeout(boot_timeout));
+        }
 
-                if status.is_ok() {
-                    if let Ok(status) = status {
-                        if status.success() {
-                            tracing::info!(
-                                "child process {} exited successfully",
-                                child_pid
-                            );
-                        } else {
-                            tracing::error!(
-                                "child process {} exited with status: {:?}",
-                                child_pid,
-                                status
-                            );
-                        }
-                    }
-                } else {
-                    tracing::error!(
-                        "failed to wait for child process {}: {:?}",
-                        child_pid,
-                        status
-                    );
-                }
+        tracing::info!("child process {} is ready", pid);
+        self.set_health_status(pid, HealthStatus::Healthy).await;
+
+        let Some(interval) = interval else {
+            return std::future::pending().await;
+        };
+
+        let mut failures = 0;
+        loop {
+            tokio::time::sleep(interval).await;
+            if Self::run_probe(probe.as_ref(), pid).await {
+                failures = 0;
+                self.set_health_status(pid, HealthStatus::Healthy).await;
+                continue;
             }
-            _ = sigterm.recv() => {
-                // Stop process monitoring
-                self.process_monitor.stop().await?;
-
-                tracing::info!("received SIGTERM signal");
-
-                if let Some(pid) = self.child_pid.take() {
-                    if let Err(e) = nix::sys::signal::kill(Pid::from_raw(pid as i32), nix::sys::signal::Signal::SIGTERM) {
-                        tracing::error!(
-                            "failed to send SIGTERM to process {}: {}",
-                            pid,
-                            e
-                        );
-                    }
-                }
 
-                // Wait for child to exit after sending signal
-                if let Err(e) = child.wait().await {
-                    tracing::error!(
-                        "error waiting for child after SIGTERM: {}",
-                        e
-                    );
-                }
+            failures += 1;
+            if failures >= failure_threshold {
+                self.set_health_status(pid, HealthStatus::Unhealthy).await;
             }
-            _ = sigint.recv() => {
-                // Stop process monitoring
-                self.process_monitor.stop().await?;
-
-                tracing::info!("received SIGINT signal");
-
-                if let Some(pid) = self.child_pid.take() {
-                    if let Err(e) = nix::sys::signal::kill(Pid::from_raw(pid as i32), nix::sys::signal::Signal::SIGTERM) {
-                        tracing::error!(
-                            "failed to send SIGTERM to process {}: {}",
-                            pid,
-                            e
-                        );
-                    }
-                }
+        }
+    }
 
-                // Wait for child to exit after sending signal
-                if let Err(e) = child.wait().await {
-                    tracing::error!(
-                        "error waiting for child after SIGINT: {}",
-                        e
-                    );
-                }
+    /// Runs the health probe once, treating errors as failures.
+    async fn run_probe(probe: &dyn HealthProbe, pid: u32) -> bool {
+        match probe.probe(pid).await {
+            Ok(healthy) => healthy,
+            Err(e) => {
+                tracing::warn!("health probe of child process {} failed: {}", pid, e);
+                false
             }
         }
+    }
 
-        self.child_pid = None;
+    /// Records the health of the child process, logging and reporting it to the health probe when
+    /// it changes.
+    async fn set_health_status(&self, pid: u32, status: HealthStatus) {
+        let previous = self.health_status.lock().unwrap().replace(status);
+        if previous == Some(status) {
+            return;
+        }
+
+        tracing::info!("child process health changed to {:?}", status);
+        if let Some(probe) = &self.health_probe {
+            if let Err(e) = probe.health_changed(pid, status).await {
+                tracing::warn!("failed to report health of child process {}: {}", pid, e);
+            }
+        }
+    }
+
+    /// Records why the child process exited.
+    fn record_exit(&self, reason: ExitReason) -> ExitReason {
+        tracing::info!("child process exit reason: {:?}", reason);
+        *self.exit_reason.lock().unwrap() = Some(reason);
+        reason
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_supervisor_stop_kills_child_ignoring_sigterm() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut supervisor = Supervisor::new(
+            "sh",
+            ["-c", "trap '' TERM; echo ready; exec sleep 30"],
+            Vec::<(String, String)>::new(),
+            temp_dir.path(),
+            helper::NoopMonitor,
+        );
+
+        let _child_io = supervisor.spawn().await?;
+
+        // Give the shell time to install its trap before signalling it
+        tokio::time::sleep(Duration::from_millis(300)).await;
+
+        let started = Instant::now();
+        let reason = supervisor.stop(Duration::from_millis(500)).await?;
+
+        assert_eq!(reason, Some(ExitReason::Killed));
+        assert_eq!(supervisor.get_exit_reason(), Some(ExitReason::Killed));
+        assert!(started.elapsed() >= Duration::from_millis(500));
+
+        // Stopping again does nothing
+        assert_eq!(supervisor.stop(Duration::from_millis(500)).await?, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_stop_terminates_child_promptly() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut supervisor = Supervisor::new(
+            "sleep",
+            ["30"],
+            Vec::<(String, String)>::new(),
+            temp_dir.path(),
+            helper::NoopMonitor,
+        );
+
+        let _child_io = supervisor.spawn().await?;
+
+        let started = Instant::now();
+        let reason = supervisor.stop(Duration::from_secs(10)).await?;
+
+        let Some(ExitReason::Terminated(status)) = reason else {
+            panic!("expected the child to be terminated, got {:?}", reason);
+        };
+        assert_eq!(
+            std::os::unix::process::ExitStatusExt::signal(&status),
+            Some(Signal::SIGTERM as i32)
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(supervisor.get_exit_reason(), reason);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_restarts_failing_child_with_backoff() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let counter = temp_dir.path().join("runs");
+
+        // Fails on the first two runs and succeeds on the third
+        let script = format!(
+            "n=$(cat {0} 2>/dev/null || echo 0); echo $((n + 1)) > {0}; [ $n -ge 2 ]",
+            counter.display()
+        );
+        let backoff = RestartBackoff {
+            base: Duration::from_millis(100),
+            cap: Duration::from_secs(1),
+            max_retries: 5,
+            ..Default::default()
+        };
+        let mut supervisor = Supervisor::new(
+            "sh",
+            ["-c", script.as_str()],
+            Vec::<(String, String)>::new(),
+            temp_dir.path(),
+            helper::NoopMonitor,
+        )
+        .with_restart_policy(RestartPolicy::OnFailure, backoff);
+
+        let started = Instant::now();
+        supervisor.start().await?;
+
+        // The delays before the two restarts were 100ms and 200ms
+        assert!(started.elapsed() >= Duration::from_millis(300));
+        assert_eq!(supervisor.get_restart_count(), 2);
+        assert!(supervisor.get_last_exit_status().unwrap().success());
+        assert_eq!(tokio::fs::read_to_string(&counter).await?.trim(), "3");
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_supervisor_stops_restarting_after_max_retries() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let backoff = RestartBackoff {
+            base: Duration::from_millis(10),
+            cap: Duration::from_millis(20),
+            max_retries: 3,
+            ..Default::default()
+        };
+        let mut supervisor = Supervisor::new(
+            "false",
+            Vec::<String>::new(),
+            Vec::<(String, String)>::new(),
+            temp_dir.path(),
+            helper::NoopMonitor,
+        )
+        .with_restart_policy(RestartPolicy::Always, backoff);
+
+        supervisor.start().await?;
+
+        assert_eq!(supervisor.get_restart_count(), 3);
+        assert!(!supervisor.get_last_exit_status().unwrap().success());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_resets_restart_count_after_stable_run() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let counter = temp_dir.path().join("runs");
+
+        // Stays up past `reset_after` on the first two runs and fails immediately afterwards
+        let script = format!(
+            "n=$(cat {0} 2>/dev/null || echo 0); echo $((n + 1)) > {0}; [ $n -lt 2 ] && sleep 0.3; exit 1",
+            counter.display()
+        );
+        let backoff = RestartBackoff {
+            base: Duration::from_millis(10),
+            cap: Duration::from_millis(20),
+            max_retries: 1,
+            reset_after: Duration::from_millis(200),
+        };
+        let mut supervisor = Supervisor::new(
+            "sh",
+            ["-c", script.as_str()],
+            Vec::<(String, String)>::new(),
+            temp_dir.path(),
+            helper::NoopMonitor,
+        )
+        .with_restart_policy(RestartPolicy::Always, backoff);
+
+        supervisor.start().await?;
+
+        // Without the reset, the budget of one restart would have stopped it after two runs
+        assert_eq!(tokio::fs::read_to_string(&counter).await?.trim(), "3");
+        assert_eq!(supervisor.get_restart_count(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_never_restarts_by_default() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut supervisor = Supervisor::new(
+            "false",
+            Vec::<String>::new(),
+            Vec::<(String, String)>::new(),
+            temp_dir.path(),
+            helper::NoopMonitor,
+        );
+
+        supervisor.start().await?;
+
+        assert_eq!(supervisor.get_restart_count(), 0);
+        assert!(!supervisor.get_last_exit_status().unwrap().success());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_stops_child_that_never_reports_ready() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let health_check = HealthCheck {
+            boot_timeout: Duration::from_millis(500),
+            ..Default::default()
+        };
+        let mut supervisor = Supervisor::new(
+            "sleep",
+            ["30"],
+            Vec::<(String, String)>::new(),
+            temp_dir.path(),
+            helper::NoopMonitor,
+        )
+        .with_health_probe(helper::FakeGuest::never_ready(), health_check);
+
+        let started = Instant::now();
+        let result = supervisor.start().await;
+
+        assert!(matches!(
+            result,
+            Err(MonoutilsError::VmBootTimeout(timeout)) if timeout == Duration::from_millis(500)
+        ));
+        assert!(started.elapsed() >= Duration::from_millis(500));
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert!(matches!(
+            supervisor.get_exit_reason(),
+            Some(ExitReason::Terminated(_))
+        ));
+        assert_eq!(supervisor.get_health_status(), Some(HealthStatus::Starting));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_child_reports_ready_promptly() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let health_check = HealthCheck {
+            boot_timeout: Duration::from_secs(5),
+            interval: None,
+            ..Default::default()
+        };
+        let mut supervisor = Supervisor::new(
+            "sleep",
+            ["1"],
+            Vec::<(String, String)>::new(),
+            temp_dir.path(),
+            helper::NoopMonitor,
+        )
+        .with_health_probe(helper::FakeGuest::ready_after(2), health_check);
+
+        supervisor.start().await?;
+
+        assert!(supervisor.get_last_exit_status().unwrap().success());
+        assert_eq!(supervisor.get_health_status(), Some(HealthStatus::Healthy));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_marks_child_unhealthy_after_consecutive_failures() -> anyhow::Result<()>
+    {
+        let temp_dir = TempDir::new()?;
+        let health_check = HealthCheck {
+            boot_timeout: Duration::from_secs(5),
+            interval: Some(Duration::from_millis(20)),
+            failure_threshold: 3,
+        };
+        let guest = helper::FakeGuest::healthy_until(3);
+        let changes = guest.changes();
+        let mut supervisor = Supervisor::new(
+            "sleep",
+            ["1"],
+            Vec::<(String, String)>::new(),
+            temp_dir.path(),
+            helper::NoopMonitor,
+        )
+        .with_health_probe(guest, health_check);
+
+        supervisor.start().await?;
+
+        // An unhealthy child is left running
+        assert!(supervisor.get_last_exit_status().unwrap().success());
+        assert_eq!(
+            supervisor.get_health_status(),
+            Some(HealthStatus::Unhealthy)
+        );
+        assert_eq!(
+            *changes.lock().unwrap(),
+            [HealthStatus::Healthy, HealthStatus::Unhealthy]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_supervisor_without_health_probe_has_no_health_status() -> anyhow::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let mut supervisor = Supervisor::new(
+            "true",
+            Vec::<String>::new(),
+            Vec::<(String, String)>::new(),
+            temp_dir.path(),
+            helper::NoopMonitor,
+        );
+
+        supervisor.start().await?;
+
+        assert_eq!(supervisor.get_health_status(), None);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod helper {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    };
+
+    use async_trait::async_trait;
+
+    use super::*;
+
+    /// A process monitor that does nothing.
+    pub(super) struct NoopMonitor;
+
+    #[async_trait]
+    impl ProcessMonitor for NoopMonitor {
+        async fn start(&mut self, _pid: u32, _child_io: ChildIo) -> MonoutilsResult<()> {
+            Ok(())
+        }
+
+        async fn stop(&mut self) -> MonoutilsResult<()> {
+            Ok(())
+        }
+    }
+
+    /// A stand-in for a MicroVM guest agent that is ready and healthy for a range of probes.
+    pub(super) struct FakeGuest {
+        /// The number of probes before the guest reports ready
+        ready_after: usize,
+
+        /// The number of probes after which the guest stops reporting healthy
+        healthy_until: usize,
+
+        /// The number of probes so far
+        probes: AtomicUsize,
+
+        /// The health changes reported to the guest so far
+        changes: Arc<Mutex<Vec<HealthStatus>>>,
+    }
+
+    impl FakeGuest {
+        /// A guest that never reports ready.
+        pub(super) fn never_ready() -> Self {
+            Self::new(usize::MAX, usize::MAX)
+        }
+
+        /// A guest that reports ready and healthy from probe number `probes` on.
+        pub(super) fn ready_after(probes: usize) -> Self {
+            Self::new(probes, usize::MAX)
+        }
+
+        /// A guest that reports ready straight away, then fails every probe from number `probes` on.
+        pub(super) fn healthy_until(probes: usize) -> Self {
+            Self::new(0, probes)
+        }
+
+        fn new(ready_after: usize, healthy_until: usize) -> Self {
+            Self {
+                ready_after,
+                healthy_until,
+                probes: AtomicUsize::new(0),
+                changes: Arc::default(),
+            }
+        }
+
+        /// Returns the health changes reported to the guest, which keep being updated after the
+        /// guest is handed to a supervisor.
+        pub(super) fn changes(&self) -> Arc<Mutex<Vec<HealthStatus>>> {
+            self.changes.clone()
+        }
+    }
+
+    #[async_trait]
+    impl HealthProbe for FakeGuest {
+        async fn probe(&self, _pid: u32) -> MonoutilsResult<bool> {
+            let probe = self.probes.fetch_add(1, Ordering::SeqCst);
+            Ok(probe >= self.ready_after && probe < self.healthy_until)
+        }
+
+        async fn health_changed(&self, _pid: u32, status: HealthStatus) -> MonoutilsResult<()> {
+            self.changes.lock().unwrap().push(status);
+            Ok(())
+        }
+    }
 }
@@ -1,16 +1,28 @@
 //! `monoutils::seekable` is a module containing seekable utilities for the monocore project.
 
 use std::{
+    future::Future,
     io::{self, SeekFrom},
     pin::Pin,
-    task::{Context, Poll},
+    task::{ready, Context, Poll},
 };
-use tokio::io::{AsyncRead, AsyncSeek, AsyncWrite, ReadBuf};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, ReadBuf};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The size of the chunks read while indexing the frames of a zstd stream.
+const ZSTD_INDEX_CHUNK_SIZE: usize = 64 * 1024;
 
 //--------------------------------------------------------------------------------------------------
 // Types
 //--------------------------------------------------------------------------------------------------
 
+/// A future reading and decompressing a zstd frame, resolving to the reader and the frame's
+/// content.
+type LoadFuture<R> = Pin<Box<dyn Future<Output = (R, io::Result<Vec<u8>>)> + Send>>;
+
 /// A seekable reader that always reads zero bytes and reports position as 0.
 #[derive(Debug)]
 pub struct EmptySeekableReader;
@@ -19,6 +31,61 @@ pub struct EmptySeekableReader;
 #[derive(Debug)]
 pub struct EmptySeekableWriter;
 
+/// A seekable reader over the decompressed content of a zstd stream made of multiple frames.
+///
+/// zstd frames can be decompressed independently, so [`SeekableZstd`] indexes where each frame
+/// starts in both the compressed and decompressed content when it is created. A read then only
+/// decompresses the frame containing the current position, instead of everything before it. The
+/// most recently decompressed frame is kept, so sequential reads decompress each frame once.
+///
+/// A stream compressed as a single frame can still be read, but every seek into it decompresses
+/// it from the start. Skippable frames, such as the seek table of the zstd seekable format, are
+/// ignored.
+pub struct SeekableZstd<R> {
+    /// The compressed stream, while no frame is being loaded from it.
+    reader: Option<R>,
+
+    /// The frames of the stream that have decompressed content, in order.
+    frames: Vec<ZstdFrame>,
+
+    /// The length of the decompressed content.
+    len: u64,
+
+    /// The current position in the decompressed content.
+    position: u64,
+
+    /// The index and decompressed content of the most recently loaded frame.
+    cached: Option<(usize, Vec<u8>)>,
+
+    /// The frame being loaded, if any.
+    loading: Option<LoadingFrame<R>>,
+}
+
+/// The location of a zstd frame in a compressed stream and in its decompressed content.
+#[derive(Debug, Clone, Copy)]
+struct ZstdFrame {
+    /// The offset of the frame in the compressed stream.
+    compressed_offset: u64,
+
+    /// The size of the frame in the compressed stream.
+    compressed_len: usize,
+
+    /// The offset of the frame's content in the decompressed content.
+    decompressed_offset: u64,
+
+    /// The size of the frame's decompressed content.
+    decompressed_len: usize,
+}
+
+/// A frame being read and decompressed, resolving to the reader and the frame's content.
+struct LoadingFrame<R> {
+    /// The index of the frame being loaded.
+    index: usize,
+
+    /// The future loading the frame.
+    future: LoadFuture<R>,
+}
+
 //--------------------------------------------------------------------------------------------------
 // Traits
 //--------------------------------------------------------------------------------------------------
@@ -29,6 +96,116 @@ pub trait SeekableReader: AsyncRead + AsyncSeek {}
 /// A trait that extends the `AsyncWrite` and `AsyncSeek` traits to allow for seeking.
 pub trait SeekableWriter: AsyncWrite + AsyncSeek {}
 
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl<R> SeekableZstd<R>
+where
+    R: SeekableReader + Unpin + Send + 'static,
+{
+    /// Creates a seekable reader over the decompressed content of the zstd stream in `reader`.
+    ///
+    /// The whole stream is read and decompressed once, one frame at a time, to index its frames.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if `reader` can't be read or doesn't contain a valid zstd stream.
+    pub async fn new(mut reader: R) -> io::Result<Self> {
+        reader.seek(SeekFrom::Start(0)).await?;
+
+        let mut frames = Vec::new();
+        let mut buffer = Vec::new();
+        let mut chunk = vec![0; ZSTD_INDEX_CHUNK_SIZE];
+        let mut compressed_offset = 0;
+        let mut decompressed_offset = 0;
+        let mut eof = false;
+        loop {
+            // Fails until the buffer holds a complete frame
+            let Ok(compressed_len) = zstd::zstd_safe::find_frame_compressed_size(&buffer) else {
+                if eof {
+                    if buffer.is_empty() {
+                        break;
+                    }
+
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("incomplete or invalid zstd frame at offset {compressed_offset}"),
+                    ));
+                }
+
+                let n = reader.read(&mut chunk).await?;
+                buffer.extend_from_slice(&chunk[..n]);
+                eof = n == 0;
+                continue;
+            };
+
+            let decompressed_len = zstd::stream::decode_all(&buffer[..compressed_len])?.len();
+            if decompressed_len > 0 {
+                frames.push(ZstdFrame {
+                    compressed_offset,
+                    compressed_len,
+                    decompressed_offset,
+                    decompressed_len,
+                });
+            }
+
+            buffer.drain(..compressed_len);
+            compressed_offset += compressed_len as u64;
+            decompressed_offset += decompressed_len as u64;
+        }
+
+        Ok(Self {
+            reader: Some(reader),
+            frames,
+            len: decompressed_offset,
+            position: 0,
+            cached: None,
+            loading: None,
+        })
+    }
+
+    /// Returns the length of the decompressed content.
+    pub fn get_len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns the index of the frame containing `position`, which must be before the end.
+    fn frame_at(&self, position: u64) -> usize {
+        self.frames
+            .partition_point(|f| f.decompressed_offset + f.decompressed_len as u64 <= position)
+    }
+
+    /// Starts loading the frame at `index`.
+    fn load_frame(&mut self, index: usize) {
+        let mut reader = self.reader.take().expect("no frame is being loaded");
+        let frame = self.frames[index];
+        let future = Box::pin(async move {
+            let result = read_zstd_frame(&mut reader, &frame).await;
+            (reader, result)
+        });
+
+        self.loading = Some(LoadingFrame { index, future });
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Reads and decompresses `frame` from `reader`.
+async fn read_zstd_frame<R>(reader: &mut R, frame: &ZstdFrame) -> io::Result<Vec<u8>>
+where
+    R: SeekableReader + Unpin,
+{
+    reader
+        .seek(SeekFrom::Start(frame.compressed_offset))
+        .await?;
+    let mut compressed = vec![0; frame.compressed_len];
+    reader.read_exact(&mut compressed).await?;
+    zstd::bulk::decompress(&compressed, frame.decompressed_len)
+}
+
 //--------------------------------------------------------------------------------------------------
 // Trait Implementations
 //--------------------------------------------------------------------------------------------------
@@ -58,3 +235,178 @@ impl AsyncSeek for EmptySeekableReader {
         Poll::Ready(Ok(0))
     }
 }
+
+impl<R> AsyncRead for SeekableZstd<R>
+where
+    R: SeekableReader + Unpin + Send + 'static,
+{
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(loading) = this.loading.as_mut() {
+                let (reader, result) = ready!(loading.future.as_mut().poll(cx));
+                let index = loading.index;
+                this.loading = None;
+                this.reader = Some(reader);
+                this.cached = Some((index, result?));
+            }
+
+            // Reads at or past the end read nothing
+            if this.position >= this.len || buf.remaining() == 0 {
+                return Poll::Ready(Ok(()));
+            }
+
+            let index = this.frame_at(this.position);
+            match &this.cached {
+                Some((cached_index, content)) if *cached_index == index => {
+                    let start = (this.position - this.frames[index].decompressed_offset) as usize;
+                    let n = buf.remaining().min(content.len() - start);
+                    buf.put_slice(&content[start..start + n]);
+                    this.position += n as u64;
+                    return Poll::Ready(Ok(()));
+                }
+                _ => this.load_frame(index),
+            }
+        }
+    }
+}
+
+impl<R> AsyncSeek for SeekableZstd<R>
+where
+    R: SeekableReader + Unpin + Send + 'static,
+{
+    fn start_seek(self: Pin<&mut Self>, position: SeekFrom) -> io::Result<()> {
+        let this = self.get_mut();
+        let (base, offset) = match position {
+            SeekFrom::Start(offset) => {
+                this.position = offset;
+                return Ok(());
+            }
+            SeekFrom::End(offset) => (this.len, offset),
+            SeekFrom::Current(offset) => (this.position, offset),
+        };
+
+        // Seeking past the end is allowed, but not before the start
+        this.position = base.checked_add_signed(offset).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        Ok(())
+    }
+
+    fn poll_complete(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<u64>> {
+        Poll::Ready(Ok(self.position))
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_seekable_zstd_reads_ranges() -> anyhow::Result<()> {
+        let (source, compressed) = helper::multi_frame_blob(5, 10_000)?;
+        let mut reader = SeekableZstd::new(Cursor::new(compressed)).await?;
+        assert_eq!(reader.get_len(), source.len() as u64);
+        assert_eq!(reader.frames.len(), 5);
+
+        // Ranges within a frame, across frames, and seeking backward
+        for (start, len) in [
+            (0, 100),
+            (12_345, 1_000),
+            (9_990, 20),
+            (25_000, 20_000),
+            (3, 7),
+            (49_900, 100),
+        ] {
+            reader.seek(SeekFrom::Start(start as u64)).await?;
+            let mut read = vec![0; len];
+            reader.read_exact(&mut read).await?;
+            assert_eq!(read, &source[start..start + len]);
+        }
+
+        // Reading everything from the start
+        reader.seek(SeekFrom::Start(0)).await?;
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).await?;
+        assert_eq!(read, source);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_seekable_zstd_seek_bounds() -> anyhow::Result<()> {
+        let (source, compressed) = helper::multi_frame_blob(3, 1_000)?;
+        let mut reader = SeekableZstd::new(Cursor::new(compressed)).await?;
+
+        // Relative seeks
+        assert_eq!(reader.seek(SeekFrom::End(-10)).await?, 2_990);
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).await?;
+        assert_eq!(read, &source[2_990..]);
+
+        assert_eq!(reader.seek(SeekFrom::Current(-1_500)).await?, 1_500);
+        let mut read = vec![0; 10];
+        reader.read_exact(&mut read).await?;
+        assert_eq!(read, &source[1_500..1_510]);
+
+        // Past the end reads nothing
+        assert_eq!(reader.seek(SeekFrom::Start(10_000)).await?, 10_000);
+        let mut read = vec![0; 10];
+        assert_eq!(reader.read(&mut read).await?, 0);
+
+        // Before the start is an error
+        assert!(reader.seek(SeekFrom::End(-3_001)).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_seekable_zstd_invalid_stream() -> anyhow::Result<()> {
+        let (_, mut compressed) = helper::multi_frame_blob(2, 1_000)?;
+        compressed.truncate(compressed.len() - 5);
+
+        let result = SeekableZstd::new(Cursor::new(compressed)).await;
+        assert_eq!(result.err().unwrap().kind(), io::ErrorKind::InvalidData);
+
+        // An empty stream has no content
+        let reader = SeekableZstd::new(Cursor::new(Vec::new())).await?;
+        assert_eq!(reader.get_len(), 0);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod helper {
+    /// Returns `frames * frame_len` bytes of content and that content compressed as `frames`
+    /// zstd frames.
+    pub(super) fn multi_frame_blob(
+        frames: usize,
+        frame_len: usize,
+    ) -> std::io::Result<(Vec<u8>, Vec<u8>)> {
+        let source = (0..frames * frame_len)
+            .map(|i| (i * 31 % 251) as u8)
+            .collect::<Vec<_>>();
+
+        let mut compressed = Vec::new();
+        for chunk in source.chunks(frame_len) {
+            compressed.extend(zstd::encode_all(chunk, 3)?);
+        }
+
+        Ok((source, compressed))
+    }
+}
@@ -7,3 +7,6 @@ pub const DEFAULT_NFS_HOST: &str = "127.0.0.1";
 
 /// The default NFS port number to use.
 pub const DEFAULT_NFS_PORT: u32 = 2049;
+
+/// The maximum number of symbolic links followed when resolving a path.
+pub const DEFAULT_SYMLINK_DEPTH: u32 = 40;
@@ -63,10 +63,28 @@ pub enum VfsError {
     #[error("filesystem is read-only")]
     ReadOnlyFilesystem,
 
+    /// The extended attribute is not set on the path
+    #[error("extended attribute {name} is not set on: {path}")]
+    AttributeNotFound {
+        /// The path of the file
+        path: PathBuf,
+
+        /// The name of the attribute
+        name: String,
+    },
+
     /// Invalid symlink target
     #[error("invalid symlink target: {0}")]
     InvalidSymlinkTarget(PathBuf),
 
+    /// Too many levels of symbolic links were encountered while resolving a path
+    #[error("too many levels of symbolic links: {0}")]
+    TooManySymlinks(PathBuf),
+
+    /// A parent directory in a path is a symbolic link, which is not followed
+    #[error("parent directory is a symbolic link: {0}")]
+    ParentIsSymlink(PathBuf),
+
     /// Empty path segment
     #[error("empty path segment")]
     EmptyPathSegment,
@@ -159,7 +177,10 @@ impl From<VfsError> for nfsserve::nfs::nfsstat3 {
             VfsError::InvalidOffset { .. } => nfsstat3::NFS3ERR_INVAL,
             VfsError::PermissionDenied(_) => nfsstat3::NFS3ERR_PERM,
             VfsError::ReadOnlyFilesystem => nfsstat3::NFS3ERR_ROFS,
+            VfsError::AttributeNotFound { .. } => nfsstat3::NFS3ERR_NOENT,
             VfsError::InvalidSymlinkTarget(_) => nfsstat3::NFS3ERR_INVAL,
+            VfsError::TooManySymlinks(_) => nfsstat3::NFS3ERR_INVAL,
+            VfsError::ParentIsSymlink(_) => nfsstat3::NFS3ERR_NOTDIR,
             VfsError::EmptyPathSegment => nfsstat3::NFS3ERR_INVAL,
             VfsError::InvalidPathComponent(_) => nfsstat3::NFS3ERR_INVAL,
             VfsError::Io(_) => nfsstat3::NFS3ERR_IO,
@@ -1,13 +1,49 @@
-use crate::{Metadata, PathSegment, VfsResult};
+use crate::{Metadata, PathSegment, VfsError, VfsResult, DEFAULT_SYMLINK_DEPTH};
 
 use std::{
-    path::{Path, PathBuf},
+    collections::BTreeSet,
+    path::{Component, Path, PathBuf},
     pin::Pin,
 };
 
 use async_trait::async_trait;
+use getset::Getters;
 use tokio::io::AsyncRead;
 
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// A position in a directory listing returned by
+/// [`read_directory_paged`][VirtualFileSystem::read_directory_paged].
+///
+/// The cursor holds the name of the last entry returned. Listing resumes with the entries that
+/// sort after it, so the cursor stays valid when entries are added or removed in between.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Getters)]
+#[getset(get = "pub with_prefix")]
+pub struct Cursor {
+    /// The name of the last entry returned.
+    last_entry: PathSegment,
+}
+
+/// Collects a page of directory entries from entries given in any order.
+///
+/// Only the entries after the cursor that can still make it into the page are kept, so a page of
+/// a large directory is built without holding all of its entries. Entries given more than once
+/// are kept once.
+#[derive(Debug)]
+pub(crate) struct PageCollector {
+    /// The cursor the page resumes after, if any.
+    cursor: Option<Cursor>,
+
+    /// The maximum number of entries in the page.
+    limit: usize,
+
+    /// The smallest entries seen so far, one more than the page holds so it is known whether
+    /// entries remain after it.
+    entries: BTreeSet<PathSegment>,
+}
+
 //--------------------------------------------------------------------------------------------------
 // Traits
 //--------------------------------------------------------------------------------------------------
@@ -119,6 +155,48 @@ pub trait VirtualFileSystem {
         path: &Path,
     ) -> VfsResult<Box<dyn Iterator<Item = PathSegment> + Send + Sync + 'static>>;
 
+    /// Lists a page of the contents of a directory, in sorted order.
+    ///
+    /// Pass the cursor returned with a page to get the next one. Each entry present for the whole
+    /// listing is returned exactly once, and entries added or removed while listing may or may
+    /// not be.
+    ///
+    /// The default implementation reads the whole directory to build each page. Implementations
+    /// that can list entries in order should override it.
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The path of the directory to read
+    /// * `cursor` - The cursor returned with the previous page, or `None` for the first page
+    /// * `limit` - The maximum number of entries to return; at least one entry is returned if any
+    ///   remain
+    ///
+    /// ## Returns
+    ///
+    /// The entries of the page, and a cursor for the next page if any entries remain.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if:
+    /// - The path doesn't exist
+    /// - The path is not a directory
+    async fn read_directory_paged(
+        &self,
+        path: &Path,
+        cursor: Option<Cursor>,
+        limit: usize,
+    ) -> VfsResult<(Vec<PathSegment>, Option<Cursor>)> {
+        let last_entry = cursor.as_ref().map(Cursor::get_last_entry);
+        let mut entries = self
+            .read_directory(path)
+            .await?
+            .filter(|entry| Some(entry) > last_entry)
+            .collect::<Vec<_>>();
+        entries.sort_unstable();
+
+        Ok(paginate(entries, limit))
+    }
+
     /// Reads the target of a symbolic link.
     ///
     /// ## Arguments
@@ -132,6 +210,9 @@ pub trait VirtualFileSystem {
 
     /// Gets the metadata of a file or directory.
     ///
+    /// Symbolic links are not followed, so the metadata of a link describes the link itself.
+    /// Use [`get_metadata_follow`](Self::get_metadata_follow) to get the metadata of its target.
+    ///
     /// ## Arguments
     ///
     /// * `path` - The path of the file or directory to get metadata for
@@ -141,6 +222,39 @@ pub trait VirtualFileSystem {
     /// Returns the metadata of the file or directory.
     async fn get_metadata(&self, path: &Path) -> VfsResult<Metadata>;
 
+    /// Gets the metadata of a file or directory, following symbolic links.
+    ///
+    /// Each link in the chain is resolved relative to its parent directory, with absolute targets
+    /// resolved relative to the root of the filesystem.
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The path of the file, directory or symlink to get metadata for
+    ///
+    /// ## Returns
+    ///
+    /// Returns the metadata of the entity the path ultimately resolves to.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if:
+    /// - The path or any target in the chain doesn't exist
+    /// - More than `DEFAULT_SYMLINK_DEPTH` symlinks need to be followed
+    async fn get_metadata_follow(&self, path: &Path) -> VfsResult<Metadata> {
+        let mut current = path.to_path_buf();
+        for _ in 0..=DEFAULT_SYMLINK_DEPTH {
+            let metadata = self.get_metadata(&current).await?;
+            if !metadata.is_symlink() {
+                return Ok(metadata);
+            }
+
+            let target = self.read_symlink(&current).await?;
+            current = resolve_symlink_target(&current, &target);
+        }
+
+        Err(VfsError::TooManySymlinks(path.to_path_buf()))
+    }
+
     /// Sets the metadata of a file or directory.
     ///
     /// ## Arguments
@@ -155,6 +269,99 @@ pub trait VirtualFileSystem {
     /// - The path is not a file or directory
     async fn set_metadata(&self, path: &Path, metadata: Metadata) -> VfsResult<()>;
 
+    /// Sets the permission bits of a file or directory, leaving the rest of its metadata as is.
+    ///
+    /// Unlike changing the permissions with [`get_metadata`][Self::get_metadata] and
+    /// [`set_metadata`][Self::set_metadata], this can't overwrite metadata changed concurrently.
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The path of the file or directory
+    /// * `mode` - The new permission bits; bits outside `0o777` are ignored
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if:
+    /// - The path doesn't exist
+    #[cfg(unix)]
+    async fn set_permissions(&self, path: &Path, mode: u32) -> VfsResult<()>;
+
+    /// Sets the owner and group of a file or directory, leaving the rest of its metadata as is.
+    ///
+    /// Like `chown`, only the IDs that are `Some` are changed.
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The path of the file or directory
+    /// * `uid` - The new user ID of the owner, if it should change
+    /// * `gid` - The new group ID, if it should change
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if:
+    /// - The path doesn't exist
+    /// - The caller is not allowed to change the owner
+    #[cfg(unix)]
+    async fn set_owner(&self, path: &Path, uid: Option<u32>, gid: Option<u32>) -> VfsResult<()>;
+
+    /// Gets the value of an extended attribute of a file or directory.
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The path of the file or directory
+    /// * `name` - The name of the attribute, such as `user.comment` or `security.selinux`
+    ///
+    /// ## Returns
+    ///
+    /// The value of the attribute, or `None` if it is not set.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if:
+    /// - The path doesn't exist
+    async fn get_xattr(&self, path: &Path, name: &str) -> VfsResult<Option<Vec<u8>>>;
+
+    /// Sets an extended attribute of a file or directory, replacing any existing value.
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The path of the file or directory
+    /// * `name` - The name of the attribute
+    /// * `value` - The value of the attribute
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if:
+    /// - The path doesn't exist
+    /// - The filesystem doesn't support extended attributes
+    async fn set_xattr(&self, path: &Path, name: &str, value: Vec<u8>) -> VfsResult<()>;
+
+    /// Lists the names of the extended attributes of a file or directory, in sorted order.
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The path of the file or directory
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if:
+    /// - The path doesn't exist
+    async fn list_xattr(&self, path: &Path) -> VfsResult<Vec<String>>;
+
+    /// Removes an extended attribute of a file or directory.
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The path of the file or directory
+    /// * `name` - The name of the attribute
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if:
+    /// - The path doesn't exist
+    /// - The attribute is not set
+    async fn remove_xattr(&self, path: &Path, name: &str) -> VfsResult<()>;
+
     /// Writes data to a file starting at the specified offset.
     ///
     /// ## Arguments
@@ -175,6 +382,31 @@ pub trait VirtualFileSystem {
         data: Pin<Box<dyn AsyncRead + Send + Sync + 'static>>,
     ) -> VfsResult<()>;
 
+    /// Appends data to the end of a file.
+    ///
+    /// The data is appended in a single operation, so concurrent appends to the same file don't
+    /// interleave or overwrite each other.
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The path of the file to append to
+    /// * `data` - An `AsyncRead` implementation providing the data to append
+    ///
+    /// ## Returns
+    ///
+    /// The length of the file after the data was appended.
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if:
+    /// - The file doesn't exist
+    /// - The path is not a file
+    async fn append_file(
+        &self,
+        path: &Path,
+        data: Pin<Box<dyn AsyncRead + Send + Sync + 'static>>,
+    ) -> VfsResult<u64>;
+
     /// Removes a file from the filesystem.
     ///
     /// ## Arguments
@@ -188,6 +420,20 @@ pub trait VirtualFileSystem {
     /// - The path is a directory (use `remove_directory` instead)
     async fn remove(&self, path: &Path) -> VfsResult<()>;
 
+    /// Removes a file or directory, along with everything under it if it is a directory.
+    ///
+    /// Unlike [`remove`][Self::remove], the directory doesn't need to be empty.
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The path of the file or directory to remove
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if:
+    /// - The path doesn't exist
+    async fn remove_tree(&self, path: &Path) -> VfsResult<()>;
+
     /// Renames (moves) a file or directory to a new location.
     ///
     /// ## Arguments
@@ -202,4 +448,121 @@ pub trait VirtualFileSystem {
     /// - The destination path already exists
     /// - The parent directory of the destination doesn't exist
     async fn rename(&self, old_path: &Path, new_path: &Path) -> VfsResult<()>;
+
+    /// Makes the changes made so far durable.
+    ///
+    /// Write-back implementations keep changes in memory and only persist them when flushed.
+    /// Implementations that have nothing to persist don't need to override this, the default does
+    /// nothing.
+    ///
+    /// ## Arguments
+    ///
+    /// * `path` - The file or directory whose changes to persist, or `None` for the whole file
+    ///   system. Implementations may persist more than asked for
+    ///
+    /// ## Errors
+    ///
+    /// Returns an error if:
+    /// - The path doesn't exist
+    /// - The changes can't be persisted
+    async fn flush(&self, path: Option<&Path>) -> VfsResult<()> {
+        let _ = path;
+        Ok(())
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl Cursor {
+    /// Creates a cursor that resumes a directory listing after the entry named `last_entry`.
+    pub fn new(last_entry: PathSegment) -> Self {
+        Self { last_entry }
+    }
+}
+
+impl PageCollector {
+    /// Creates a collector for the page of at most `limit`, but at least one, entries after
+    /// `cursor`.
+    pub(crate) fn new(cursor: Option<Cursor>, limit: usize) -> Self {
+        Self {
+            cursor,
+            limit: limit.max(1),
+            entries: BTreeSet::new(),
+        }
+    }
+
+    /// Returns the cursor the page resumes after.
+    pub(crate) fn get_cursor(&self) -> Option<&Cursor> {
+        self.cursor.as_ref()
+    }
+
+    /// Returns the number of entries a source listing entries in sorted order has to provide
+    /// before none of its later entries can make it into the page.
+    pub(crate) fn get_capacity(&self) -> usize {
+        self.limit + 1
+    }
+
+    /// Adds an entry, which is dropped unless it sorts after the cursor and among the smallest
+    /// seen so far.
+    pub(crate) fn push(&mut self, entry: PathSegment) {
+        if self.cursor.as_ref().map(Cursor::get_last_entry) >= Some(&entry) {
+            return;
+        }
+
+        self.entries.insert(entry);
+        if self.entries.len() > self.get_capacity() {
+            self.entries.pop_last();
+        }
+    }
+
+    /// Returns the page, with a cursor for the next page if any entries remain.
+    pub(crate) fn finish(self) -> (Vec<PathSegment>, Option<Cursor>) {
+        paginate(self.entries, self.limit)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Takes a page of at most `limit`, but at least one, of the sorted directory `entries`, with a
+/// cursor for the next page if any entries remain.
+pub(crate) fn paginate(
+    entries: impl IntoIterator<Item = PathSegment>,
+    limit: usize,
+) -> (Vec<PathSegment>, Option<Cursor>) {
+    let mut entries = entries.into_iter();
+    let page = entries.by_ref().take(limit.max(1)).collect::<Vec<_>>();
+
+    let cursor = match (page.last(), entries.next()) {
+        (Some(last_entry), Some(_)) => Some(Cursor::new(last_entry.clone())),
+        _ => None,
+    };
+
+    (page, cursor)
+}
+
+/// Resolves the `target` of the symlink at `link` to a path relative to the filesystem root.
+///
+/// `.` components are dropped and `..` components never go above the root.
+fn resolve_symlink_target(link: &Path, target: &Path) -> PathBuf {
+    let mut resolved = if target.has_root() {
+        PathBuf::new()
+    } else {
+        link.parent().map(Path::to_path_buf).unwrap_or_default()
+    };
+
+    for component in target.components() {
+        match component {
+            Component::Normal(segment) => resolved.push(segment),
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+        }
+    }
+
+    resolved
 }
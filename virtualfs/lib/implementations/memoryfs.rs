@@ -1,6 +1,6 @@
 use std::{
     collections::HashMap,
-    path::{Component, Path, PathBuf},
+    path::{Path, PathBuf},
     pin::Pin,
     sync::Arc,
     task::{Context, Poll},
@@ -8,9 +8,15 @@ use std::{
 
 use async_trait::async_trait;
 use getset::Getters;
+use monoutils::path;
 use tokio::{io::AsyncRead, sync::RwLock};
 
-use crate::{Metadata, ModeType, PathSegment, VfsError, VfsResult, VirtualFileSystem};
+use crate::{
+    filesystem::paginate, Cursor, Metadata, ModeType, PathSegment, VfsError, VfsResult,
+    VirtualFileSystem,
+};
+#[cfg(unix)]
+use crate::{Mode, S_IPERM};
 
 //--------------------------------------------------------------------------------------------------
 // Types
@@ -106,11 +112,14 @@ impl MemoryFileSystem {
         }
     }
 
-    /// Splits the given path into its parent and the last path segment.
+    /// Normalizes the given path and splits it into its parent and the last path segment.
     /// If the path has no explicit parent, an empty path is used as the parent.
     #[inline]
-    fn split_path(path: &Path) -> VfsResult<(&Path, PathSegment)> {
-        let parent = path.parent().unwrap_or_else(|| Path::new(""));
+    fn split_path(path: &Path) -> VfsResult<(PathBuf, PathSegment)> {
+        let path = normalize_path(path)?;
+        let parent = path
+            .parent()
+            .map_or_else(PathBuf::new, |parent| parent.to_path_buf());
         let name_os = path
             .file_name()
             .ok_or_else(|| VfsError::InvalidPathComponent("No filename provided".into()))?;
@@ -134,6 +143,32 @@ impl MemoryFileSystem {
             }
         }
     }
+
+    /// Applies `update` to the metadata of the entity at `path`.
+    ///
+    /// The write lock is held from reading the metadata until it is written back, so concurrent
+    /// updates of different fields don't overwrite each other.
+    fn update_metadata<T>(
+        root: &mut Dir,
+        path: &Path,
+        update: impl FnOnce(&mut Metadata) -> T,
+    ) -> VfsResult<T> {
+        if path == Path::new("") {
+            return Ok(update(&mut root.metadata));
+        }
+
+        let entity = root
+            .find_mut(path)?
+            .ok_or_else(|| VfsError::NotFound(path.to_path_buf()))?;
+
+        let result = match entity {
+            Entity::File(file) => update(&mut file.metadata),
+            Entity::Dir(dir) => update(&mut dir.metadata),
+            Entity::Symlink(symlink) => update(&mut symlink.metadata),
+        };
+
+        Ok(result)
+    }
 }
 
 impl File {
@@ -324,7 +359,8 @@ impl Dir {
     ///
     /// ## Arguments
     ///
-    /// * `path` - The path to traverse, can be empty, ".", or a path with normal components
+    /// * `path` - The path to traverse. It is normalized first, so `.` and `..` components are
+    ///   resolved, but it must not be empty or escape this directory
     ///
     /// ## Returns
     ///
@@ -353,49 +389,24 @@ impl Dir {
     /// # }
     /// ```
     pub fn find(&self, path: impl AsRef<Path> + Send + Sync) -> VfsResult<Option<&Entity>> {
-        let path = path.as_ref();
+        let path = normalize_path(path.as_ref())?;
+        let segments = PathSegment::split_path(&path)?;
 
         // Ensure the path is not empty
-        let mut components = path.components().peekable();
-        if components.peek().is_none() {
+        let Some((last, parents)) = segments.split_last() else {
             return Err(VfsError::InvalidPathComponent("empty path provided".into()));
-        }
+        };
 
-        // Traverse the components
+        // Traverse the parent directories
         let mut current_dir = self;
-
-        while let Some(component) = components.next() {
-            match component {
-                // Only allow normal components
-                Component::Normal(os_str) => {
-                    // Convert the component to a PathSegment
-                    let segment = PathSegment::try_from(os_str.to_str().ok_or_else(|| {
-                        VfsError::InvalidPathComponent(os_str.to_string_lossy().into_owned())
-                    })?)?;
-
-                    let entry = current_dir.get(&segment);
-
-                    // If this is the last component, return the entry
-                    if components.peek().is_none() {
-                        return Ok(entry);
-                    }
-
-                    // Otherwise, ensure it's a directory and continue traversing
-                    match entry {
-                        Some(entity) => current_dir = entity.as_dir()?,
-                        None => return Ok(None),
-                    }
-                }
-                // Reject all non-normal components, including CurDir (.) and ParentDir (..)
-                _ => {
-                    return Err(VfsError::InvalidPathComponent(
-                        component.as_os_str().to_string_lossy().into_owned(),
-                    ))
-                }
+        for segment in parents {
+            match current_dir.get(segment) {
+                Some(entity) => current_dir = entity.as_dir()?,
+                None => return Ok(None),
             }
         }
 
-        unreachable!()
+        Ok(current_dir.get(last))
     }
 
     /// Traverses a path starting from this directory to find an entity, returning a mutable reference.
@@ -405,7 +416,8 @@ impl Dir {
     ///
     /// ## Arguments
     ///
-    /// * `path` - The path to traverse, can be empty, ".", or a path with normal components
+    /// * `path` - The path to traverse. It is normalized first, so `.` and `..` components are
+    ///   resolved, but it must not be empty or escape this directory
     ///
     /// ## Returns
     ///
@@ -438,49 +450,24 @@ impl Dir {
         &mut self,
         path: impl AsRef<Path> + Send + Sync,
     ) -> VfsResult<Option<&mut Entity>> {
-        let path = path.as_ref();
+        let path = normalize_path(path.as_ref())?;
+        let segments = PathSegment::split_path(&path)?;
 
         // Ensure the path is not empty
-        let mut components = path.components().peekable();
-        if components.peek().is_none() {
+        let Some((last, parents)) = segments.split_last() else {
             return Err(VfsError::InvalidPathComponent("Empty path provided".into()));
-        }
+        };
 
-        // Traverse the components
+        // Traverse the parent directories
         let mut current_dir = self;
-
-        while let Some(component) = components.next() {
-            match component {
-                // Only allow normal components
-                Component::Normal(os_str) => {
-                    // Convert the component to a PathSegment
-                    let segment = PathSegment::try_from(os_str.to_str().ok_or_else(|| {
-                        VfsError::InvalidPathComponent(os_str.to_string_lossy().into_owned())
-                    })?)?;
-
-                    let entry = current_dir.get_mut(segment);
-
-                    // If this is the last component, return the entry
-                    if components.peek().is_none() {
-                        return Ok(entry);
-                    }
-
-                    // Otherwise, ensure it's a directory and continue traversing
-                    match entry {
-                        Some(entity) => current_dir = entity.as_mut_dir()?,
-                        None => return Ok(None),
-                    }
-                }
-                // Reject all non-normal components, including CurDir (.) and ParentDir (..)
-                _ => {
-                    return Err(VfsError::InvalidPathComponent(
-                        component.as_os_str().to_string_lossy().into_owned(),
-                    ))
-                }
+        for segment in parents {
+            match current_dir.get_mut(segment.clone()) {
+                Some(entity) => current_dir = entity.as_mut_dir()?,
+                None => return Ok(None),
             }
         }
 
-        unreachable!()
+        Ok(current_dir.get_mut(last.clone()))
     }
 }
 
@@ -517,7 +504,7 @@ impl VirtualFileSystem for MemoryFileSystem {
         let (parent, filename) = MemoryFileSystem::split_path(path)?;
 
         let mut root = self.root_dir.write().await;
-        let parent_dir = MemoryFileSystem::get_parent_dir(&mut root, parent)?;
+        let parent_dir = MemoryFileSystem::get_parent_dir(&mut root, &parent)?;
 
         if let Some(_) = parent_dir.get(&filename) {
             if !exists_ok {
@@ -535,7 +522,7 @@ impl VirtualFileSystem for MemoryFileSystem {
         let (parent, dirname) = MemoryFileSystem::split_path(path)?;
 
         let mut root = self.root_dir.write().await;
-        let parent_dir = MemoryFileSystem::get_parent_dir(&mut root, parent)?;
+        let parent_dir = MemoryFileSystem::get_parent_dir(&mut root, &parent)?;
 
         if parent_dir.get(&dirname).is_some() {
             return Err(VfsError::AlreadyExists(path.to_path_buf()));
@@ -554,7 +541,7 @@ impl VirtualFileSystem for MemoryFileSystem {
         let (parent, linkname) = MemoryFileSystem::split_path(path)?;
 
         let mut root = self.root_dir.write().await;
-        let parent_dir = MemoryFileSystem::get_parent_dir(&mut root, parent)?;
+        let parent_dir = MemoryFileSystem::get_parent_dir(&mut root, &parent)?;
 
         if parent_dir.get(&linkname).is_some() {
             return Err(VfsError::AlreadyExists(path.to_path_buf()));
@@ -608,6 +595,35 @@ impl VirtualFileSystem for MemoryFileSystem {
         Ok(Box::new(entries.into_iter()))
     }
 
+    async fn read_directory_paged(
+        &self,
+        path: &Path,
+        cursor: Option<Cursor>,
+        limit: usize,
+    ) -> VfsResult<(Vec<PathSegment>, Option<Cursor>)> {
+        // Find the directory
+        let root = self.root_dir.read().await;
+        let entity = if path == Path::new("") {
+            Ok(&*root)
+        } else {
+            root.find(path)?
+                .ok_or_else(|| VfsError::NotFound(path.to_path_buf()))?
+                .as_dir()
+        }?;
+
+        // Sort the names after the cursor, so pages follow a stable order, and only clone the
+        // ones in the page
+        let last_entry = cursor.as_ref().map(Cursor::get_last_entry);
+        let mut names = entity
+            .entries
+            .keys()
+            .filter(|name| Some(*name) > last_entry)
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+
+        Ok(paginate(names.into_iter().cloned(), limit))
+    }
+
     async fn read_symlink(&self, path: &Path) -> VfsResult<PathBuf> {
         // Find the symlink
         let root = self.root_dir.read().await;
@@ -689,11 +705,37 @@ impl VirtualFileSystem for MemoryFileSystem {
         Ok(())
     }
 
+    async fn append_file(
+        &self,
+        path: &Path,
+        data: Pin<Box<dyn AsyncRead + Send + Sync + 'static>>,
+    ) -> VfsResult<u64> {
+        // Read the data before taking the lock, so a slow reader doesn't block the filesystem
+        let mut buffer = Vec::new();
+        let mut pinned_data = Box::pin(data);
+        tokio::io::copy(&mut pinned_data, &mut buffer)
+            .await
+            .map_err(VfsError::Io)?;
+
+        // The lock is held for the whole append, so concurrent appends can't interleave
+        let mut root = self.root_dir.write().await;
+        let file = root
+            .find_mut(path)?
+            .ok_or_else(|| VfsError::NotFound(path.to_path_buf()))?
+            .as_mut_file()?;
+
+        file.content.extend_from_slice(&buffer);
+        let len = file.content.len() as u64;
+        file.metadata.set_size(len);
+
+        Ok(len)
+    }
+
     async fn remove(&self, path: &Path) -> VfsResult<()> {
         let (parent, key) = MemoryFileSystem::split_path(path)?;
 
         let mut root = self.root_dir.write().await;
-        let parent_dir = MemoryFileSystem::get_parent_dir(&mut root, parent)?;
+        let parent_dir = MemoryFileSystem::get_parent_dir(&mut root, &parent)?;
 
         match parent_dir.get(&key) {
             Some(entity) => {
@@ -710,6 +752,18 @@ impl VirtualFileSystem for MemoryFileSystem {
         }
     }
 
+    async fn remove_tree(&self, path: &Path) -> VfsResult<()> {
+        let (parent, key) = MemoryFileSystem::split_path(path)?;
+
+        let mut root = self.root_dir.write().await;
+        let parent_dir = MemoryFileSystem::get_parent_dir(&mut root, &parent)?;
+
+        match parent_dir.entries.remove(&key) {
+            Some(_) => Ok(()),
+            None => Err(VfsError::NotFound(path.to_path_buf())),
+        }
+    }
+
     async fn rename(&self, old_path: &Path, new_path: &Path) -> VfsResult<()> {
         let (old_parent, old_segment) = MemoryFileSystem::split_path(old_path)?;
         let (new_parent, new_segment) = MemoryFileSystem::split_path(new_path)?;
@@ -718,7 +772,7 @@ impl VirtualFileSystem for MemoryFileSystem {
             let root = self.root_dir.read().await;
 
             if old_parent != Path::new("") {
-                match root.find(old_parent)? {
+                match root.find(&old_parent)? {
                     Some(entity) => {
                         if !matches!(entity, Entity::Dir(_)) {
                             return Err(VfsError::NotADirectory(old_parent.to_path_buf()));
@@ -731,7 +785,7 @@ impl VirtualFileSystem for MemoryFileSystem {
             }
 
             if new_parent != Path::new("") {
-                match root.find(new_parent)? {
+                match root.find(&new_parent)? {
                     Some(entity) => {
                         if !matches!(entity, Entity::Dir(_)) {
                             return Err(VfsError::NotADirectory(new_parent.to_path_buf()));
@@ -746,7 +800,7 @@ impl VirtualFileSystem for MemoryFileSystem {
             let source_dir = if old_parent == Path::new("") {
                 &root
             } else {
-                root.find(old_parent)?.unwrap().as_dir()?
+                root.find(&old_parent)?.unwrap().as_dir()?
             };
 
             if !source_dir.entries.contains_key(&old_segment) {
@@ -756,7 +810,7 @@ impl VirtualFileSystem for MemoryFileSystem {
             let dest_dir = if new_parent == Path::new("") {
                 &root
             } else {
-                root.find(new_parent)?.unwrap().as_dir()?
+                root.find(&new_parent)?.unwrap().as_dir()?
             };
 
             if dest_dir.entries.contains_key(&new_segment) {
@@ -767,7 +821,7 @@ impl VirtualFileSystem for MemoryFileSystem {
         let mut root = self.root_dir.write().await;
 
         if old_parent == new_parent {
-            let parent_dir = MemoryFileSystem::get_parent_dir(&mut root, old_parent)?;
+            let parent_dir = MemoryFileSystem::get_parent_dir(&mut root, &old_parent)?;
             let entity = parent_dir.entries.remove(&old_segment).unwrap();
             parent_dir.entries.insert(new_segment, entity);
             return Ok(());
@@ -776,7 +830,7 @@ impl VirtualFileSystem for MemoryFileSystem {
         let entity = if old_parent == Path::new("") {
             root.entries.remove(&old_segment).unwrap()
         } else {
-            MemoryFileSystem::get_parent_dir(&mut root, old_parent)?
+            MemoryFileSystem::get_parent_dir(&mut root, &old_parent)?
                 .entries
                 .remove(&old_segment)
                 .unwrap()
@@ -785,7 +839,7 @@ impl VirtualFileSystem for MemoryFileSystem {
         if new_parent == Path::new("") {
             root.entries.insert(new_segment, entity);
         } else {
-            MemoryFileSystem::get_parent_dir(&mut root, new_parent)?
+            MemoryFileSystem::get_parent_dir(&mut root, &new_parent)?
                 .entries
                 .insert(new_segment, entity);
         }
@@ -819,6 +873,63 @@ impl VirtualFileSystem for MemoryFileSystem {
 
         Ok(())
     }
+
+    async fn get_xattr(&self, path: &Path, name: &str) -> VfsResult<Option<Vec<u8>>> {
+        let metadata = self.get_metadata(path).await?;
+        Ok(metadata.get_attribute(name).map(<[u8]>::to_vec))
+    }
+
+    async fn set_xattr(&self, path: &Path, name: &str, value: Vec<u8>) -> VfsResult<()> {
+        let mut root = self.root_dir.write().await;
+        MemoryFileSystem::update_metadata(&mut root, path, |metadata| {
+            metadata.set_attribute(name, value);
+        })
+    }
+
+    async fn list_xattr(&self, path: &Path) -> VfsResult<Vec<String>> {
+        let metadata = self.get_metadata(path).await?;
+        let mut names = metadata
+            .get_attributes()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        names.sort();
+        Ok(names)
+    }
+
+    async fn remove_xattr(&self, path: &Path, name: &str) -> VfsResult<()> {
+        let mut root = self.root_dir.write().await;
+        MemoryFileSystem::update_metadata(&mut root, path, |metadata| {
+            metadata.remove_attribute(name)
+        })?
+        .map(|_| ())
+        .ok_or_else(|| VfsError::AttributeNotFound {
+            path: path.to_path_buf(),
+            name: name.to_string(),
+        })
+    }
+
+    #[cfg(unix)]
+    async fn set_permissions(&self, path: &Path, mode: u32) -> VfsResult<()> {
+        let mut root = self.root_dir.write().await;
+        MemoryFileSystem::update_metadata(&mut root, path, |metadata| {
+            metadata.set_permissions(Mode::from(mode & S_IPERM).get_permissions());
+        })
+    }
+
+    #[cfg(unix)]
+    async fn set_owner(&self, path: &Path, uid: Option<u32>, gid: Option<u32>) -> VfsResult<()> {
+        let mut root = self.root_dir.write().await;
+        MemoryFileSystem::update_metadata(&mut root, path, |metadata| {
+            if let Some(uid) = uid {
+                metadata.set_uid(uid);
+            }
+
+            if let Some(gid) = gid {
+                metadata.set_gid(gid);
+            }
+        })
+    }
 }
 
 impl Default for MemoryFileSystem {
@@ -863,6 +974,15 @@ impl AsyncRead for MemoryFileReader {
     }
 }
 
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Normalizes `path` with [`path::normalize`], rejecting paths that escape the root.
+fn normalize_path(path: &Path) -> VfsResult<PathBuf> {
+    path::normalize(path).map_err(|_| VfsError::InvalidPathComponent(path.display().to_string()))
+}
+
 //--------------------------------------------------------------------------------------------------
 // Tests
 //--------------------------------------------------------------------------------------------------
@@ -939,6 +1059,19 @@ mod tests {
         // Test nonexistent path
         assert!(root.find("nonexistent/path").unwrap().is_none());
 
+        // Test paths that are normalized
+        for path in [
+            "subdir/./test.txt",
+            "subdir//test.txt",
+            "subdir/../subdir/test.txt",
+        ] {
+            assert!(matches!(root.find(path), Ok(Some(Entity::File(_)))));
+        }
+        assert!(matches!(
+            root.find("subdir/../../etc/passwd"),
+            Err(VfsError::InvalidPathComponent(_))
+        ));
+
         // Test invalid path components
         assert!(matches!(
             root.find(".."),
@@ -1411,6 +1544,82 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn test_memoryfs_read_directory_paged() {
+        let fs = MemoryFileSystem::new();
+        fs.create_directory(Path::new("large")).await.unwrap();
+        for i in 0..1000 {
+            fs.create_file(Path::new(&format!("large/file{}.txt", i)), false)
+                .await
+                .unwrap();
+        }
+
+        // Paginate through the whole directory
+        let mut listed = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (page, next) = fs
+                .read_directory_paged(Path::new("large"), cursor, 64)
+                .await
+                .unwrap();
+            assert!(page.len() <= 64);
+            listed.extend(page);
+
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        // Every entry is listed exactly once, in sorted order
+        let mut expected: Vec<_> = (0..1000)
+            .map(|i| PathSegment::try_from(format!("file{}.txt", i).as_str()).unwrap())
+            .collect();
+        expected.sort();
+        assert_eq!(listed, expected);
+
+        // The cursor stays valid when entries change between pages
+        let (first_page, cursor) = fs
+            .read_directory_paged(Path::new("large"), None, 10)
+            .await
+            .unwrap();
+        fs.remove(Path::new("large").join(first_page[9].as_os_str()).as_path())
+            .await
+            .unwrap();
+        fs.create_file(Path::new("large/a_new.txt"), false)
+            .await
+            .unwrap();
+        let (second_page, _) = fs
+            .read_directory_paged(Path::new("large"), cursor, 10)
+            .await
+            .unwrap();
+        assert_eq!(second_page, expected[10..20]);
+
+        // A limit of zero still makes progress
+        let (page, cursor) = fs
+            .read_directory_paged(Path::new("large"), None, 0)
+            .await
+            .unwrap();
+        assert_eq!(page.len(), 1);
+        assert!(cursor.is_some());
+
+        // An empty directory has a single empty page
+        fs.create_directory(Path::new("empty")).await.unwrap();
+        let (page, cursor) = fs
+            .read_directory_paged(Path::new("empty"), None, 10)
+            .await
+            .unwrap();
+        assert!(page.is_empty());
+        assert!(cursor.is_none());
+
+        // Test reading non-existent directory
+        assert!(matches!(
+            fs.read_directory_paged(Path::new("nonexistent"), None, 10)
+                .await,
+            Err(VfsError::NotFound(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_memoryfs_read_symlink() {
         let fs = MemoryFileSystem::new();
@@ -1450,6 +1659,64 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn test_memoryfs_append_file_concurrent() {
+        let fs = MemoryFileSystem::new();
+        fs.create_file(Path::new("log.txt"), false).await.unwrap();
+
+        // Every appender writes a run of its own byte, so interleaved appends would split a run.
+        let appenders = (0..16u8)
+            .map(|i| {
+                let fs = fs.clone();
+                tokio::spawn(async move {
+                    let chunk = vec![b'a' + i; 1000 + i as usize];
+                    fs.append_file(Path::new("log.txt"), Box::pin(std::io::Cursor::new(chunk)))
+                        .await
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut lengths = Vec::new();
+        for appender in appenders {
+            lengths.push(appender.await.unwrap().unwrap());
+        }
+
+        let expected_len = (0..16).map(|i| 1000 + i as u64).sum::<u64>();
+        assert_eq!(lengths.iter().max(), Some(&expected_len));
+
+        let mut buf = Vec::new();
+        let mut reader = fs
+            .read_file(Path::new("log.txt"), 0, expected_len)
+            .await
+            .unwrap();
+        tokio::io::copy(&mut reader, &mut buf).await.unwrap();
+        assert_eq!(buf.len() as u64, expected_len);
+
+        let mut runs = buf.chunk_by(|a, b| a == b).collect::<Vec<_>>();
+        runs.sort();
+        assert_eq!(runs.len(), 16);
+        for (i, run) in runs.iter().enumerate() {
+            assert_eq!(*run, vec![b'a' + i as u8; 1000 + i]);
+        }
+
+        // Appending to missing files and directories fails
+        let result = fs
+            .append_file(
+                Path::new("missing.txt"),
+                Box::pin(std::io::Cursor::new(b"".to_vec())),
+            )
+            .await;
+        assert!(matches!(result, Err(VfsError::NotFound(_))));
+        fs.create_directory(Path::new("dir")).await.unwrap();
+        let result = fs
+            .append_file(
+                Path::new("dir"),
+                Box::pin(std::io::Cursor::new(b"".to_vec())),
+            )
+            .await;
+        assert!(matches!(result, Err(VfsError::NotAFile(_))));
+    }
+
     #[tokio::test]
     async fn test_memoryfs_write_file() {
         let fs = MemoryFileSystem::new();
@@ -1597,6 +1864,256 @@ mod tests {
         ));
     }
 
+    #[tokio::test]
+    async fn test_memoryfs_set_metadata() {
+        let fs = MemoryFileSystem::new();
+        fs.create_file(Path::new("file.txt"), false).await.unwrap();
+        fs.create_directory(Path::new("dir")).await.unwrap();
+
+        // Set the size of a file and read it back
+        let mut metadata = fs.get_metadata(Path::new("file.txt")).await.unwrap();
+        metadata.set_size(42);
+        fs.set_metadata(Path::new("file.txt"), metadata)
+            .await
+            .unwrap();
+        let metadata = fs.get_metadata(Path::new("file.txt")).await.unwrap();
+        assert_eq!(metadata.get_size(), 42);
+
+        // Set the mode of a file and a directory and read it back
+        #[cfg(unix)]
+        {
+            use crate::{Group, Other, User};
+
+            let mut metadata = fs.get_metadata(Path::new("file.txt")).await.unwrap();
+            metadata.set_permissions(User::RW | Group::R | Other::R);
+            fs.set_metadata(Path::new("file.txt"), metadata)
+                .await
+                .unwrap();
+            let metadata = fs.get_metadata(Path::new("file.txt")).await.unwrap();
+            assert_eq!(u32::from(*metadata.get_mode()) & 0o777, 0o644);
+            assert_eq!(metadata.get_type(), Some(ModeType::File));
+            assert_eq!(metadata.get_size(), 42);
+
+            let mut metadata = fs.get_metadata(Path::new("dir")).await.unwrap();
+            metadata.set_permissions(User::RWX);
+            fs.set_metadata(Path::new("dir"), metadata).await.unwrap();
+            let metadata = fs.get_metadata(Path::new("dir")).await.unwrap();
+            assert_eq!(u32::from(*metadata.get_mode()) & 0o777, 0o700);
+            assert_eq!(metadata.get_type(), Some(ModeType::Directory));
+        }
+
+        // The metadata of the root directory can be set as well
+        let mut metadata = fs.get_metadata(Path::new("")).await.unwrap();
+        metadata.set_size(7);
+        fs.set_metadata(Path::new(""), metadata).await.unwrap();
+        assert_eq!(fs.get_metadata(Path::new("")).await.unwrap().get_size(), 7);
+
+        // Test setting metadata for non-existent path
+        let metadata = fs.get_metadata(Path::new("file.txt")).await.unwrap();
+        assert!(matches!(
+            fs.set_metadata(Path::new("nonexistent"), metadata).await,
+            Err(VfsError::NotFound(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_memoryfs_set_permissions_and_owner() {
+        let fs = MemoryFileSystem::new();
+        fs.create_file(Path::new("file.txt"), false).await.unwrap();
+        fs.write_file(
+            Path::new("file.txt"),
+            0,
+            Box::pin(std::io::Cursor::new(b"content".to_vec())),
+        )
+        .await
+        .unwrap();
+        let before = fs.get_metadata(Path::new("file.txt")).await.unwrap();
+
+        // Only the permission bits change; the type bits are kept
+        fs.set_permissions(Path::new("file.txt"), 0o100600)
+            .await
+            .unwrap();
+        let after = fs.get_metadata(Path::new("file.txt")).await.unwrap();
+        assert_eq!(u32::from(*after.get_mode()) & 0o777, 0o600);
+        assert_eq!(after.get_type(), Some(ModeType::File));
+        assert_eq!(after.get_size(), before.get_size());
+        assert_eq!(after.get_uid(), before.get_uid());
+        assert_eq!(after.get_gid(), before.get_gid());
+        assert_eq!(after.get_created_at(), before.get_created_at());
+        assert_eq!(after.get_modified_at(), before.get_modified_at());
+        assert_eq!(after.get_accessed_at(), before.get_accessed_at());
+
+        // Only the given IDs change
+        fs.set_owner(Path::new("file.txt"), Some(1000), None)
+            .await
+            .unwrap();
+        let owned = fs.get_metadata(Path::new("file.txt")).await.unwrap();
+        assert_eq!(owned.get_uid(), 1000);
+        assert_eq!(owned.get_gid(), before.get_gid());
+        assert_eq!(owned.get_mode(), after.get_mode());
+
+        fs.set_owner(Path::new("file.txt"), None, Some(1001))
+            .await
+            .unwrap();
+        let owned = fs.get_metadata(Path::new("file.txt")).await.unwrap();
+        assert_eq!((owned.get_uid(), owned.get_gid()), (1000, 1001));
+
+        // The root directory can be changed too
+        fs.set_permissions(Path::new(""), 0o700).await.unwrap();
+        let root = fs.get_metadata(Path::new("")).await.unwrap();
+        assert_eq!(u32::from(*root.get_mode()) & 0o777, 0o700);
+        assert_eq!(root.get_type(), Some(ModeType::Directory));
+
+        assert!(matches!(
+            fs.set_permissions(Path::new("nonexistent"), 0o600).await,
+            Err(VfsError::NotFound(_))
+        ));
+        assert!(matches!(
+            fs.set_owner(Path::new("nonexistent"), Some(0), None).await,
+            Err(VfsError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_memoryfs_xattrs() {
+        let fs = MemoryFileSystem::new();
+        fs.create_file(Path::new("file.txt"), false).await.unwrap();
+
+        fs.set_xattr(Path::new("file.txt"), "user.comment", b"hello".to_vec())
+            .await
+            .unwrap();
+        fs.set_xattr(
+            Path::new("file.txt"),
+            "security.selinux",
+            b"system_u:object_r:tmp_t:s0".to_vec(),
+        )
+        .await
+        .unwrap();
+        fs.set_xattr(Path::new("file.txt"), "user.empty", Vec::new())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs.list_xattr(Path::new("file.txt")).await.unwrap(),
+            ["security.selinux", "user.comment", "user.empty"]
+        );
+        assert_eq!(
+            fs.get_xattr(Path::new("file.txt"), "user.comment")
+                .await
+                .unwrap(),
+            Some(b"hello".to_vec())
+        );
+        assert_eq!(
+            fs.get_xattr(Path::new("file.txt"), "user.empty")
+                .await
+                .unwrap(),
+            Some(Vec::new())
+        );
+        assert_eq!(
+            fs.get_xattr(Path::new("file.txt"), "user.missing")
+                .await
+                .unwrap(),
+            None
+        );
+
+        // Replacing and removing
+        fs.set_xattr(Path::new("file.txt"), "user.comment", b"bye".to_vec())
+            .await
+            .unwrap();
+        fs.remove_xattr(Path::new("file.txt"), "user.empty")
+            .await
+            .unwrap();
+        let metadata = fs.get_metadata(Path::new("file.txt")).await.unwrap();
+        assert_eq!(metadata.get_attributes().len(), 2);
+        assert_eq!(metadata.get_attribute("user.comment"), Some(&b"bye"[..]));
+        assert!(matches!(
+            fs.remove_xattr(Path::new("file.txt"), "user.empty").await,
+            Err(VfsError::AttributeNotFound { .. })
+        ));
+
+        // Attributes survive metadata updates that start from the current metadata
+        let mut metadata = fs.get_metadata(Path::new("file.txt")).await.unwrap();
+        metadata.set_size(0);
+        fs.set_metadata(Path::new("file.txt"), metadata)
+            .await
+            .unwrap();
+        assert_eq!(fs.list_xattr(Path::new("file.txt")).await.unwrap().len(), 2);
+
+        assert!(matches!(
+            fs.set_xattr(Path::new("nonexistent"), "user.a", Vec::new())
+                .await,
+            Err(VfsError::NotFound(_))
+        ));
+        assert!(matches!(
+            fs.list_xattr(Path::new("nonexistent")).await,
+            Err(VfsError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_memoryfs_get_metadata_follow() {
+        let fs = MemoryFileSystem::new();
+
+        fs.create_directory(Path::new("dir")).await.unwrap();
+        fs.create_file(Path::new("file.txt"), false).await.unwrap();
+        fs.create_symlink(Path::new("link"), Path::new("file.txt"))
+            .await
+            .unwrap();
+        fs.create_symlink(Path::new("dir/link"), Path::new("../link"))
+            .await
+            .unwrap();
+        fs.create_symlink(Path::new("dir_link"), Path::new("/dir"))
+            .await
+            .unwrap();
+
+        cfg_if::cfg_if! {
+            if #[cfg(unix)] {
+                // The link itself is reported when not following
+                let metadata = fs.get_metadata(Path::new("link")).await.unwrap();
+                assert_eq!(metadata.get_type(), Some(ModeType::Symlink));
+
+                // The target is reported when following
+                let metadata = fs.get_metadata_follow(Path::new("link")).await.unwrap();
+                assert_eq!(metadata.get_type(), Some(ModeType::File));
+
+                // Chained relative links are resolved against their parent directory
+                let metadata = fs.get_metadata_follow(Path::new("dir/link")).await.unwrap();
+                assert_eq!(metadata.get_type(), Some(ModeType::File));
+
+                // Absolute targets are resolved from the root
+                let metadata = fs.get_metadata_follow(Path::new("dir_link")).await.unwrap();
+                assert_eq!(metadata.get_type(), Some(ModeType::Directory));
+
+                // Non-links are returned as is
+                let metadata = fs.get_metadata_follow(Path::new("file.txt")).await.unwrap();
+                assert_eq!(metadata.get_type(), Some(ModeType::File));
+            }
+        }
+
+        // Dangling links cannot be followed
+        fs.create_symlink(Path::new("dangling"), Path::new("missing"))
+            .await
+            .unwrap();
+        assert!(fs.get_metadata(Path::new("dangling")).await.is_ok());
+        assert!(matches!(
+            fs.get_metadata_follow(Path::new("dangling")).await,
+            Err(VfsError::NotFound(_))
+        ));
+
+        // Symlink loops are detected
+        fs.create_symlink(Path::new("loop_a"), Path::new("loop_b"))
+            .await
+            .unwrap();
+        fs.create_symlink(Path::new("loop_b"), Path::new("loop_a"))
+            .await
+            .unwrap();
+        assert!(matches!(
+            fs.get_metadata_follow(Path::new("loop_a")).await,
+            Err(VfsError::TooManySymlinks(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_memoryfs_remove() {
         let fs = MemoryFileSystem::new();
@@ -1716,6 +2233,50 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_memoryfs_remove_tree() {
+        let fs = MemoryFileSystem::new();
+
+        // Create a nested directory tree
+        fs.create_directory(Path::new("dir")).await.unwrap();
+        fs.create_directory(Path::new("dir/subdir")).await.unwrap();
+        fs.create_file(Path::new("dir/file.txt"), false)
+            .await
+            .unwrap();
+        fs.create_file(Path::new("dir/subdir/nested.txt"), false)
+            .await
+            .unwrap();
+        fs.create_file(Path::new("keep.txt"), false).await.unwrap();
+
+        // Remove a subtree
+        fs.remove_tree(Path::new("dir/subdir")).await.unwrap();
+        assert!(!fs.exists(Path::new("dir/subdir")).await.unwrap());
+        assert!(fs.exists(Path::new("dir/file.txt")).await.unwrap());
+
+        // Remove the whole tree
+        fs.remove_tree(Path::new("dir")).await.unwrap();
+        assert!(!fs.exists(Path::new("dir")).await.unwrap());
+        assert!(fs.exists(Path::new("keep.txt")).await.unwrap());
+
+        // Remove a single file
+        fs.remove_tree(Path::new("keep.txt")).await.unwrap();
+        assert!(!fs.exists(Path::new("keep.txt")).await.unwrap());
+
+        // Test removing non-existent path
+        assert!(matches!(
+            fs.remove_tree(Path::new("nonexistent")).await,
+            Err(VfsError::NotFound(_))
+        ));
+
+        // Test removing with invalid path components
+        for invalid_path in [".", "..", "/"] {
+            assert!(matches!(
+                fs.remove_tree(Path::new(invalid_path)).await,
+                Err(VfsError::InvalidPathComponent(_))
+            ));
+        }
+    }
+
     #[tokio::test]
     async fn test_memoryfs_rename() {
         let fs = MemoryFileSystem::new();
@@ -1,4 +1,6 @@
 mod memoryfs;
+#[cfg(feature = "monofs")]
+mod monofsvfs;
 mod nativefs;
 mod overlayfs;
 
@@ -7,5 +9,7 @@ mod overlayfs;
 //--------------------------------------------------------------------------------------------------
 
 pub use memoryfs::*;
+#[cfg(feature = "monofs")]
+pub use monofsvfs::*;
 pub use nativefs::*;
 pub use overlayfs::*;
@@ -0,0 +1,981 @@
+use std::{
+    collections::BTreeMap,
+    io::Cursor,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+use chrono::{TimeZone, Utc};
+use getset::Getters;
+use ipldstore::{
+    ipld::{cid::Cid, ipld::Ipld},
+    IpldStore, IpldStoreSeekable,
+};
+use monofs::{
+    filesystem::{
+        Dir, Entity, File, Metadata as MonofsMetadata, SymPathLink, UNIX_ATIME_KEY, UNIX_GID_KEY,
+        UNIX_MODE_KEY, UNIX_UID_KEY,
+    },
+    FsError,
+};
+use monoutils::path;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncSeekExt, SeekFrom},
+    sync::RwLock,
+};
+
+use crate::{Metadata, ModeType, PathSegment, VfsError, VfsResult, VirtualFileSystem};
+#[cfg(unix)]
+use crate::{Mode, S_IPERM};
+
+//--------------------------------------------------------------------------------------------------
+// Constants
+//--------------------------------------------------------------------------------------------------
+
+/// The monofs attribute that holds the extended attributes of an entity as a map of names to
+/// bytes.
+pub const VFS_XATTRS_KEY: &str = "virtualfs.xattrs";
+
+//--------------------------------------------------------------------------------------------------
+// Types
+//--------------------------------------------------------------------------------------------------
+
+/// A virtual file system backed by a monofs directory tree.
+///
+/// This makes content-addressed storage usable anywhere a [`VirtualFileSystem`] is expected, for
+/// example as a layer of an [`OverlayFileSystem`][crate::OverlayFileSystem]. Changes are kept in
+/// the in-memory tree until [`checkpoint`][Self::checkpoint] stores it.
+///
+/// Symbolic path links are exposed as symlinks. Symbolic CID links are followed transparently,
+/// since they have no target path to report.
+#[derive(Debug, Clone, Getters)]
+#[getset(get = "pub with_prefix")]
+pub struct MonofsVfs<S>
+where
+    S: IpldStore + Send + Sync + 'static,
+{
+    /// The root directory of the file system
+    root_dir: Arc<RwLock<Dir<S>>>,
+}
+
+//--------------------------------------------------------------------------------------------------
+// Methods
+//--------------------------------------------------------------------------------------------------
+
+impl<S> MonofsVfs<S>
+where
+    S: IpldStoreSeekable + Send + Sync + 'static,
+{
+    /// Creates a new empty file system in the given store.
+    pub fn new(store: S) -> Self {
+        Self::from_dir(Dir::new(store))
+    }
+
+    /// Creates a file system rooted at an existing monofs directory.
+    ///
+    /// ## Examples
+    ///
+    /// ```rust
+    /// use ipldstore::MemoryStore;
+    /// use monofs::filesystem::Dir;
+    /// use virtualfs::{MonofsVfs, VirtualFileSystem};
+    /// use std::path::Path;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> anyhow::Result<()> {
+    /// let mut dir = Dir::new(MemoryStore::default());
+    /// dir.create_file("hello.txt").await?;
+    ///
+    /// let fs = MonofsVfs::from_dir(dir);
+    /// assert!(fs.exists(Path::new("hello.txt")).await?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_dir(dir: Dir<S>) -> Self {
+        Self {
+            root_dir: Arc::new(RwLock::new(dir)),
+        }
+    }
+
+    /// Stores the directory tree and returns the CID of its root.
+    pub async fn checkpoint(&self) -> VfsResult<Cid> {
+        let mut root = self.root_dir.write().await;
+        root.checkpoint().await.map_err(VfsError::custom)
+    }
+
+    /// Normalizes the given path into a monofs path, which is empty for the root.
+    fn to_monofs_path(path: &Path) -> VfsResult<String> {
+        let normalized = path::normalize(path)
+            .map_err(|_| VfsError::InvalidPathComponent(path.display().to_string()))?;
+
+        normalized
+            .to_str()
+            .map(str::to_string)
+            .ok_or_else(|| VfsError::InvalidPathComponent(path.display().to_string()))
+    }
+
+    /// Normalizes the given path and splits it into its parent and the last path segment.
+    /// If the path has no explicit parent, an empty path is used as the parent.
+    fn split_path(path: &Path) -> VfsResult<(String, String)> {
+        let path = Self::to_monofs_path(path)?;
+        match path.rsplit_once('/') {
+            Some((parent, name)) => Ok((parent.to_string(), name.to_string())),
+            None if path.is_empty() => Err(VfsError::InvalidPathComponent(
+                "No filename provided".into(),
+            )),
+            None => Ok((String::new(), path)),
+        }
+    }
+
+    /// Returns the directory at `parent`, which is the root if it is empty.
+    async fn get_parent_dir<'a>(root: &'a mut Dir<S>, parent: &str) -> VfsResult<&'a mut Dir<S>> {
+        if parent.is_empty() {
+            return Ok(root);
+        }
+
+        match root.find_mut(parent).await? {
+            Some(Entity::Dir(dir)) => Ok(dir),
+            Some(_) => Err(VfsError::NotADirectory(parent.into())),
+            None => Err(VfsError::ParentDirectoryNotFound(parent.into())),
+        }
+    }
+
+    /// Returns the entity at `path`, following symbolic CID links.
+    async fn find_entity<'a>(root: &'a Dir<S>, path: &Path) -> VfsResult<&'a Entity<S>> {
+        let monofs_path = Self::to_monofs_path(path)?;
+        match root.find(&monofs_path).await? {
+            Some(Entity::SymCidLink(symlink)) => Ok(symlink.resolve().await?),
+            Some(entity) => Ok(entity),
+            None => Err(VfsError::NotFound(path.to_path_buf())),
+        }
+    }
+
+    /// Returns the monofs metadata of the entity at `path`, which is the root if it is empty.
+    async fn get_metadata_mut<'a>(
+        root: &'a mut Dir<S>,
+        path: &Path,
+    ) -> VfsResult<&'a mut MonofsMetadata<S>> {
+        let monofs_path = Self::to_monofs_path(path)?;
+        if monofs_path.is_empty() {
+            return Ok(root.get_metadata_mut());
+        }
+
+        root.find_mut(&monofs_path)
+            .await?
+            .map(Entity::get_metadata_mut)
+            .ok_or_else(|| VfsError::NotFound(path.to_path_buf()))
+    }
+
+    /// Returns the file at `path`.
+    async fn get_file_mut<'a>(root: &'a mut Dir<S>, path: &Path) -> VfsResult<&'a mut File<S>> {
+        let monofs_path = Self::to_monofs_path(path)?;
+        match root.find_mut(&monofs_path).await? {
+            Some(Entity::File(file)) => Ok(file),
+            Some(_) => Err(VfsError::NotAFile(path.to_path_buf())),
+            None => Err(VfsError::NotFound(path.to_path_buf())),
+        }
+    }
+
+    /// Converts the metadata of a monofs entity to virtual file system metadata.
+    async fn to_vfs_metadata(entity: &Entity<S>) -> VfsResult<Metadata> {
+        let monofs_metadata = entity.get_metadata();
+        let (mode_type, size) = match entity {
+            Entity::File(file) => (ModeType::File, file.get_size().await?),
+            Entity::Dir(_) => (ModeType::Directory, 0),
+            Entity::SymPathLink(symlink) => (
+                ModeType::Symlink,
+                symlink.get_target_path().as_str().len() as u64,
+            ),
+            Entity::SymCidLink(_) => (ModeType::Symlink, 0),
+        };
+
+        Self::convert_metadata(monofs_metadata, mode_type, size).await
+    }
+
+    /// Builds virtual file system metadata from monofs metadata.
+    async fn convert_metadata(
+        monofs_metadata: &MonofsMetadata<S>,
+        mode_type: ModeType,
+        size: u64,
+    ) -> VfsResult<Metadata> {
+        let mut metadata = Metadata::new(mode_type);
+        metadata.set_size(size);
+        metadata.set_created_at(*monofs_metadata.get_created_at());
+        metadata.set_modified_at(*monofs_metadata.get_modified_at());
+
+        if let Some(atime) = get_integer_attribute(monofs_metadata, UNIX_ATIME_KEY).await? {
+            if let Some(accessed_at) = Utc.timestamp_opt(atime, 0).single() {
+                metadata.set_accessed_at(accessed_at);
+            }
+        }
+
+        #[cfg(unix)]
+        {
+            if let Some(mode) = get_integer_attribute(monofs_metadata, UNIX_MODE_KEY).await? {
+                metadata.set_permissions(Mode::from(mode as u32 & S_IPERM).get_permissions());
+            }
+
+            if let Some(uid) = get_integer_attribute(monofs_metadata, UNIX_UID_KEY).await? {
+                metadata.set_uid(uid as u32);
+            }
+
+            if let Some(gid) = get_integer_attribute(monofs_metadata, UNIX_GID_KEY).await? {
+                metadata.set_gid(gid as u32);
+            }
+        }
+
+        for (name, value) in get_xattrs(monofs_metadata).await? {
+            metadata.set_attribute(name, value);
+        }
+
+        Ok(metadata)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Trait Implementations
+//--------------------------------------------------------------------------------------------------
+
+#[async_trait]
+impl<S> VirtualFileSystem for MonofsVfs<S>
+where
+    S: IpldStoreSeekable + Send + Sync + 'static,
+{
+    async fn exists(&self, path: &Path) -> VfsResult<bool> {
+        let monofs_path = Self::to_monofs_path(path)?;
+        if monofs_path.is_empty() {
+            return Ok(true);
+        }
+
+        let root = self.root_dir.read().await;
+        let result = root.find(&monofs_path).await?.is_some();
+        Ok(result)
+    }
+
+    async fn create_file(&self, path: &Path, exists_ok: bool) -> VfsResult<()> {
+        let (parent, filename) = Self::split_path(path)?;
+
+        let mut root = self.root_dir.write().await;
+        let store = root.get_store().clone();
+        let parent_dir = Self::get_parent_dir(&mut root, &parent).await?;
+
+        if parent_dir.has_entry(&filename)? {
+            if !exists_ok {
+                return Err(VfsError::AlreadyExists(path.to_path_buf()));
+            }
+            return Ok(());
+        }
+
+        parent_dir
+            .put_adapted_file(filename, File::new(store))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn create_directory(&self, path: &Path) -> VfsResult<()> {
+        let (parent, dirname) = Self::split_path(path)?;
+
+        let mut root = self.root_dir.write().await;
+        let store = root.get_store().clone();
+        let parent_dir = Self::get_parent_dir(&mut root, &parent).await?;
+
+        if parent_dir.has_entry(&dirname)? {
+            return Err(VfsError::AlreadyExists(path.to_path_buf()));
+        }
+
+        parent_dir.put_adapted_dir(dirname, Dir::new(store)).await?;
+
+        Ok(())
+    }
+
+    async fn create_symlink(&self, path: &Path, target: &Path) -> VfsResult<()> {
+        let target_str = target
+            .to_str()
+            .filter(|target| !target.is_empty())
+            .ok_or_else(|| VfsError::InvalidSymlinkTarget(target.to_path_buf()))?;
+
+        let (parent, linkname) = Self::split_path(path)?;
+
+        let mut root = self.root_dir.write().await;
+        let store = root.get_store().clone();
+        let parent_dir = Self::get_parent_dir(&mut root, &parent).await?;
+
+        if parent_dir.has_entry(&linkname)? {
+            return Err(VfsError::AlreadyExists(path.to_path_buf()));
+        }
+
+        let symlink = SymPathLink::with_path(store, target_str)
+            .map_err(|_| VfsError::InvalidSymlinkTarget(target.to_path_buf()))?;
+        parent_dir
+            .put_adapted_sympathlink(linkname, symlink)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn read_file(
+        &self,
+        path: &Path,
+        offset: u64,
+        length: u64,
+    ) -> VfsResult<Pin<Box<dyn AsyncRead + Send + Sync + 'static>>> {
+        let root = self.root_dir.read().await;
+        let Entity::File(file) = Self::find_entity(&root, path).await? else {
+            return Err(VfsError::NotAFile(path.to_path_buf()));
+        };
+
+        // Read the requested range, since the file's input stream borrows the tree
+        let mut content = Vec::new();
+        if offset < file.get_size().await? {
+            let mut input_stream = file.get_input_stream().await?;
+            input_stream.seek(SeekFrom::Start(offset)).await?;
+            input_stream.take(length).read_to_end(&mut content).await?;
+        }
+
+        Ok(Box::pin(Cursor::new(content)))
+    }
+
+    async fn read_directory(
+        &self,
+        path: &Path,
+    ) -> VfsResult<Box<dyn Iterator<Item = PathSegment> + Send + Sync + 'static>> {
+        let root = self.root_dir.read().await;
+        let dir = if Self::to_monofs_path(path)?.is_empty() {
+            &*root
+        } else {
+            match Self::find_entity(&root, path).await? {
+                Entity::Dir(dir) => dir,
+                _ => return Err(VfsError::NotADirectory(path.to_path_buf())),
+            }
+        };
+
+        let entries = dir
+            .get_entry_names()
+            .map(|name| PathSegment::try_from(name.as_str()))
+            .collect::<VfsResult<Vec<_>>>()?;
+
+        Ok(Box::new(entries.into_iter()))
+    }
+
+    async fn read_symlink(&self, path: &Path) -> VfsResult<PathBuf> {
+        let monofs_path = Self::to_monofs_path(path)?;
+        let root = self.root_dir.read().await;
+        match root.find(&monofs_path).await? {
+            Some(Entity::SymPathLink(symlink)) => {
+                Ok(PathBuf::from(symlink.get_target_path().as_str()))
+            }
+            Some(_) => Err(VfsError::NotASymlink(path.to_path_buf())),
+            None => Err(VfsError::NotFound(path.to_path_buf())),
+        }
+    }
+
+    async fn get_metadata(&self, path: &Path) -> VfsResult<Metadata> {
+        let root = self.root_dir.read().await;
+        if Self::to_monofs_path(path)?.is_empty() {
+            return Self::convert_metadata(root.get_metadata(), ModeType::Directory, 0).await;
+        }
+
+        let entity = Self::find_entity(&root, path).await?;
+        Self::to_vfs_metadata(entity).await
+    }
+
+    async fn set_metadata(&self, path: &Path, metadata: Metadata) -> VfsResult<()> {
+        let mut root = self.root_dir.write().await;
+        let monofs_metadata = Self::get_metadata_mut(&mut root, path).await?;
+
+        #[cfg(unix)]
+        {
+            let mode = u32::from(*metadata.get_mode()) & S_IPERM;
+            monofs_metadata.set_attribute(UNIX_MODE_KEY, mode).await?;
+            monofs_metadata
+                .set_attribute(UNIX_UID_KEY, metadata.get_uid())
+                .await?;
+            monofs_metadata
+                .set_attribute(UNIX_GID_KEY, metadata.get_gid())
+                .await?;
+        }
+
+        monofs_metadata
+            .set_attribute(UNIX_ATIME_KEY, metadata.get_accessed_at().timestamp())
+            .await?;
+
+        let xattrs = metadata
+            .get_attributes()
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        set_xattrs(monofs_metadata, xattrs).await?;
+
+        monofs_metadata.set_created_at(*metadata.get_created_at());
+        monofs_metadata.set_modified_at(*metadata.get_modified_at());
+
+        Ok(())
+    }
+
+    async fn get_xattr(&self, path: &Path, name: &str) -> VfsResult<Option<Vec<u8>>> {
+        let metadata = self.get_metadata(path).await?;
+        Ok(metadata.get_attribute(name).map(<[u8]>::to_vec))
+    }
+
+    async fn set_xattr(&self, path: &Path, name: &str, value: Vec<u8>) -> VfsResult<()> {
+        let mut root = self.root_dir.write().await;
+        let monofs_metadata = Self::get_metadata_mut(&mut root, path).await?;
+        let mut xattrs = get_xattrs(monofs_metadata).await?;
+        xattrs.insert(name.to_string(), value);
+        set_xattrs(monofs_metadata, xattrs).await
+    }
+
+    async fn list_xattr(&self, path: &Path) -> VfsResult<Vec<String>> {
+        let metadata = self.get_metadata(path).await?;
+        let mut names = metadata
+            .get_attributes()
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        names.sort();
+        Ok(names)
+    }
+
+    async fn remove_xattr(&self, path: &Path, name: &str) -> VfsResult<()> {
+        let mut root = self.root_dir.write().await;
+        let monofs_metadata = Self::get_metadata_mut(&mut root, path).await?;
+        let mut xattrs = get_xattrs(monofs_metadata).await?;
+        if xattrs.remove(name).is_none() {
+            return Err(VfsError::AttributeNotFound {
+                path: path.to_path_buf(),
+                name: name.to_string(),
+            });
+        }
+
+        set_xattrs(monofs_metadata, xattrs).await
+    }
+
+    #[cfg(unix)]
+    async fn set_permissions(&self, path: &Path, mode: u32) -> VfsResult<()> {
+        let mut root = self.root_dir.write().await;
+        let monofs_metadata = Self::get_metadata_mut(&mut root, path).await?;
+        monofs_metadata
+            .set_attribute(UNIX_MODE_KEY, mode & S_IPERM)
+            .await?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    async fn set_owner(&self, path: &Path, uid: Option<u32>, gid: Option<u32>) -> VfsResult<()> {
+        let mut root = self.root_dir.write().await;
+        let monofs_metadata = Self::get_metadata_mut(&mut root, path).await?;
+        if let Some(uid) = uid {
+            monofs_metadata.set_attribute(UNIX_UID_KEY, uid).await?;
+        }
+
+        if let Some(gid) = gid {
+            monofs_metadata.set_attribute(UNIX_GID_KEY, gid).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn write_file(
+        &self,
+        path: &Path,
+        offset: u64,
+        data: Pin<Box<dyn AsyncRead + Send + Sync + 'static>>,
+    ) -> VfsResult<()> {
+        // Read the data before taking the lock, so a slow reader doesn't block the filesystem
+        let mut buffer = Vec::new();
+        let mut pinned_data = Box::pin(data);
+        tokio::io::copy(&mut pinned_data, &mut buffer)
+            .await
+            .map_err(VfsError::Io)?;
+
+        let mut root = self.root_dir.write().await;
+        let file = Self::get_file_mut(&mut root, path).await?;
+
+        // monofs files can't be sparse, so a write past the end is padded with zeros
+        let size = file.get_size().await?;
+        if offset > size {
+            let padding = usize::try_from(offset - size).map_err(|_| VfsError::InvalidOffset {
+                path: path.to_path_buf(),
+                offset,
+            })?;
+            file.write_at(size, &vec![0; padding]).await?;
+        }
+
+        if !buffer.is_empty() {
+            file.write_at(offset, &buffer).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn append_file(
+        &self,
+        path: &Path,
+        data: Pin<Box<dyn AsyncRead + Send + Sync + 'static>>,
+    ) -> VfsResult<u64> {
+        // Read the data before taking the lock, so a slow reader doesn't block the filesystem
+        let mut buffer = Vec::new();
+        let mut pinned_data = Box::pin(data);
+        tokio::io::copy(&mut pinned_data, &mut buffer)
+            .await
+            .map_err(VfsError::Io)?;
+
+        // The lock is held for the whole append, so concurrent appends can't interleave
+        let mut root = self.root_dir.write().await;
+        let file = Self::get_file_mut(&mut root, path).await?;
+        let size = file.get_size().await?;
+        if !buffer.is_empty() {
+            file.write_at(size, &buffer).await?;
+        }
+
+        Ok(size + buffer.len() as u64)
+    }
+
+    async fn remove(&self, path: &Path) -> VfsResult<()> {
+        let (parent, name) = Self::split_path(path)?;
+
+        let mut root = self.root_dir.write().await;
+        let parent_dir = Self::get_parent_dir(&mut root, &parent).await?;
+
+        match parent_dir.get_entity(&name).await? {
+            Some(Entity::Dir(dir)) if !dir.is_empty() => {
+                return Err(VfsError::NotEmpty(path.to_path_buf()))
+            }
+            Some(_) => {}
+            None => return Err(VfsError::NotFound(path.to_path_buf())),
+        }
+
+        parent_dir.remove_entry(&name)?;
+        Ok(())
+    }
+
+    async fn remove_tree(&self, path: &Path) -> VfsResult<()> {
+        let (parent, name) = Self::split_path(path)?;
+
+        let mut root = self.root_dir.write().await;
+        let parent_dir = Self::get_parent_dir(&mut root, &parent).await?;
+
+        if !parent_dir.has_entry(&name)? {
+            return Err(VfsError::NotFound(path.to_path_buf()));
+        }
+
+        parent_dir.remove_entry(&name)?;
+        Ok(())
+    }
+
+    async fn rename(&self, old_path: &Path, new_path: &Path) -> VfsResult<()> {
+        let (old_parent, old_name) = Self::split_path(old_path)?;
+        let (new_parent, new_name) = Self::split_path(new_path)?;
+
+        let mut root = self.root_dir.write().await;
+        if !Self::get_parent_dir(&mut root, &old_parent)
+            .await?
+            .has_entry(&old_name)?
+        {
+            return Err(VfsError::NotFound(old_path.to_path_buf()));
+        }
+
+        if Self::get_parent_dir(&mut root, &new_parent)
+            .await?
+            .has_entry(&new_name)?
+        {
+            return Err(VfsError::AlreadyExists(new_path.to_path_buf()));
+        }
+
+        root.rename(
+            Self::to_monofs_path(old_path)?,
+            Self::to_monofs_path(new_path)?,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn flush(&self, path: Option<&Path>) -> VfsResult<()> {
+        if let Some(path) = path {
+            if !self.exists(path).await? {
+                return Err(VfsError::NotFound(path.to_path_buf()));
+            }
+        }
+
+        // A checkpoint stores the whole tree, since every change reaches the root's CID
+        self.checkpoint().await?;
+        Ok(())
+    }
+}
+
+impl From<FsError> for VfsError {
+    fn from(error: FsError) -> Self {
+        match error {
+            FsError::PathNotFound(path) => VfsError::NotFound(path.into()),
+            FsError::PathExists(path) => VfsError::AlreadyExists(path.into()),
+            FsError::NotADirectory(path)
+            | FsError::SourceIsNotADir(path)
+            | FsError::TargetIsNotADir(path) => VfsError::NotADirectory(path.into()),
+            FsError::NotAFile(path) | FsError::IsADirectory(path) => {
+                VfsError::NotAFile(path.into())
+            }
+            FsError::NotASymPathLink(path) => VfsError::NotASymlink(path.into()),
+            FsError::DirectoryNotEmpty(path) => VfsError::NotEmpty(path.into()),
+            FsError::InvalidPathComponent(component) => VfsError::InvalidPathComponent(component),
+            FsError::PathHasRoot(path) => VfsError::InvalidPathComponent(path),
+            FsError::MaxFollowDepthReached => VfsError::TooManySymlinks(PathBuf::new()),
+            FsError::IoError(error) => VfsError::Io(error),
+            error => VfsError::custom(error),
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Returns the value of an integer attribute, accepting integers stored as strings.
+async fn get_integer_attribute<S>(metadata: &MonofsMetadata<S>, key: &str) -> VfsResult<Option<i64>>
+where
+    S: IpldStore + Send + Sync,
+{
+    let value = metadata.get_attribute(key).await?;
+    Ok(value.and_then(|ipld| match &*ipld {
+        Ipld::Integer(value) => i64::try_from(*value).ok(),
+        Ipld::String(value) => value.parse().ok(),
+        _ => None,
+    }))
+}
+
+/// Returns the extended attributes stored under [`VFS_XATTRS_KEY`].
+async fn get_xattrs<S>(metadata: &MonofsMetadata<S>) -> VfsResult<BTreeMap<String, Vec<u8>>>
+where
+    S: IpldStore + Send + Sync,
+{
+    let Some(value) = metadata.get_attribute(VFS_XATTRS_KEY).await? else {
+        return Ok(BTreeMap::new());
+    };
+
+    let Ipld::Map(map) = &*value else {
+        return Ok(BTreeMap::new());
+    };
+
+    Ok(map
+        .iter()
+        .filter_map(|(name, value)| match value {
+            Ipld::Bytes(bytes) => Some((name.clone(), bytes.clone())),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Replaces the extended attributes stored under [`VFS_XATTRS_KEY`].
+async fn set_xattrs<S>(
+    metadata: &mut MonofsMetadata<S>,
+    xattrs: BTreeMap<String, Vec<u8>>,
+) -> VfsResult<()>
+where
+    S: IpldStore + Send + Sync,
+{
+    let map = xattrs
+        .into_iter()
+        .map(|(name, value)| (name, Ipld::Bytes(value)))
+        .collect();
+
+    metadata
+        .set_attribute(VFS_XATTRS_KEY, Ipld::Map(map))
+        .await?;
+
+    Ok(())
+}
+
+//--------------------------------------------------------------------------------------------------
+// Tests
+//--------------------------------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use ipldstore::{MemoryStore, Storable};
+
+    use crate::OverlayFileSystem;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_monofsvfs_create_read_write() -> anyhow::Result<()> {
+        let fs = MonofsVfs::new(MemoryStore::default());
+
+        fs.create_directory(Path::new("docs")).await?;
+        fs.create_file(Path::new("docs/hello.txt"), false).await?;
+        assert!(fs.exists(Path::new("docs/hello.txt")).await?);
+        assert!(matches!(
+            fs.create_file(Path::new("docs/hello.txt"), false).await,
+            Err(VfsError::AlreadyExists(_))
+        ));
+        fs.create_file(Path::new("docs/hello.txt"), true).await?;
+
+        // Writes past the end are padded with zeros
+        fs.write_file(
+            Path::new("docs/hello.txt"),
+            0,
+            Box::pin(Cursor::new(b"Hello, World!".to_vec())),
+        )
+        .await?;
+        fs.write_file(
+            Path::new("docs/hello.txt"),
+            15,
+            Box::pin(Cursor::new(b"!".to_vec())),
+        )
+        .await?;
+        let len = fs
+            .append_file(
+                Path::new("docs/hello.txt"),
+                Box::pin(Cursor::new(b"?".to_vec())),
+            )
+            .await?;
+        assert_eq!(len, 17);
+
+        let content = helper::read_to_vec(&fs, "docs/hello.txt", 0, 100).await?;
+        assert_eq!(content, b"Hello, World!\0\0!?");
+        let content = helper::read_to_vec(&fs, "docs/hello.txt", 7, 5).await?;
+        assert_eq!(content, b"World");
+        assert!(helper::read_to_vec(&fs, "docs/hello.txt", 100, 5)
+            .await?
+            .is_empty());
+
+        let metadata = fs.get_metadata(Path::new("docs/hello.txt")).await?;
+        assert_eq!(metadata.get_type(), Some(ModeType::File));
+        assert_eq!(metadata.get_size(), 17);
+
+        let entries = fs
+            .read_directory(Path::new("docs"))
+            .await?
+            .collect::<Vec<_>>();
+        assert_eq!(entries, vec![PathSegment::try_from("hello.txt")?]);
+
+        // Errors are mapped to their virtual file system equivalents
+        assert!(matches!(
+            fs.read_file(Path::new("docs"), 0, 1).await,
+            Err(VfsError::NotAFile(_))
+        ));
+        assert!(matches!(
+            fs.create_file(Path::new("missing/file.txt"), false).await,
+            Err(VfsError::ParentDirectoryNotFound(_))
+        ));
+        assert!(matches!(
+            fs.remove(Path::new("docs")).await,
+            Err(VfsError::NotEmpty(_))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_monofsvfs_rename() -> anyhow::Result<()> {
+        let fs = MonofsVfs::new(MemoryStore::default());
+        fs.create_directory(Path::new("src")).await?;
+        fs.create_directory(Path::new("dst")).await?;
+        fs.create_file(Path::new("src/file.txt"), false).await?;
+        fs.create_file(Path::new("dst/existing.txt"), false).await?;
+        fs.write_file(
+            Path::new("src/file.txt"),
+            0,
+            Box::pin(Cursor::new(b"content".to_vec())),
+        )
+        .await?;
+
+        fs.rename(Path::new("src/file.txt"), Path::new("dst/moved.txt"))
+            .await?;
+        assert!(!fs.exists(Path::new("src/file.txt")).await?);
+        let content = helper::read_to_vec(&fs, "dst/moved.txt", 0, 100).await?;
+        assert_eq!(content, b"content");
+
+        assert!(matches!(
+            fs.rename(Path::new("dst/moved.txt"), Path::new("dst/existing.txt"))
+                .await,
+            Err(VfsError::AlreadyExists(_))
+        ));
+        assert!(matches!(
+            fs.rename(Path::new("src/missing.txt"), Path::new("dst/other.txt"))
+                .await,
+            Err(VfsError::NotFound(_))
+        ));
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_monofsvfs_metadata_and_xattrs() -> anyhow::Result<()> {
+        let fs = MonofsVfs::new(MemoryStore::default());
+        fs.create_file(Path::new("file.txt"), false).await?;
+        fs.create_symlink(Path::new("link"), Path::new("file.txt"))
+            .await?;
+
+        assert_eq!(
+            fs.read_symlink(Path::new("link")).await?,
+            PathBuf::from("file.txt")
+        );
+        assert!(fs.get_metadata(Path::new("link")).await?.is_symlink());
+        assert_eq!(
+            fs.get_metadata_follow(Path::new("link")).await?.get_type(),
+            Some(ModeType::File)
+        );
+
+        fs.set_permissions(Path::new("file.txt"), 0o640).await?;
+        fs.set_owner(Path::new("file.txt"), Some(1000), Some(1001))
+            .await?;
+        let metadata = fs.get_metadata(Path::new("file.txt")).await?;
+        assert_eq!(u32::from(*metadata.get_mode()) & S_IPERM, 0o640);
+        assert_eq!(metadata.get_uid(), 1000);
+        assert_eq!(metadata.get_gid(), 1001);
+
+        fs.set_xattr(Path::new("file.txt"), "user.b", b"2".to_vec())
+            .await?;
+        fs.set_xattr(Path::new("file.txt"), "user.a", b"1".to_vec())
+            .await?;
+        assert_eq!(
+            fs.get_xattr(Path::new("file.txt"), "user.a").await?,
+            Some(b"1".to_vec())
+        );
+        assert_eq!(
+            fs.list_xattr(Path::new("file.txt")).await?,
+            vec!["user.a".to_string(), "user.b".to_string()]
+        );
+        fs.remove_xattr(Path::new("file.txt"), "user.a").await?;
+        assert!(matches!(
+            fs.remove_xattr(Path::new("file.txt"), "user.a").await,
+            Err(VfsError::AttributeNotFound { .. })
+        ));
+
+        // Metadata survives a checkpoint
+        fs.checkpoint().await?;
+        let metadata = fs.get_metadata(Path::new("file.txt")).await?;
+        assert_eq!(metadata.get_uid(), 1000);
+        assert_eq!(metadata.get_attribute("user.b"), Some(&b"2"[..]));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_monofsvfs_flush() -> anyhow::Result<()> {
+        let store = MemoryStore::default();
+        let fs = MonofsVfs::new(store.clone());
+
+        fs.create_file(Path::new("data.txt"), false).await?;
+        fs.write_file(
+            Path::new("data.txt"),
+            0,
+            Box::pin(Cursor::new(b"first".to_vec())),
+        )
+        .await?;
+
+        // Nothing is stored until the changes are flushed
+        assert!(fs
+            .get_root_dir()
+            .read()
+            .await
+            .get_initial_load_cid()
+            .is_none());
+        assert!(matches!(
+            fs.flush(Some(Path::new("missing.txt"))).await,
+            Err(VfsError::NotFound(_))
+        ));
+
+        fs.flush(Some(Path::new("data.txt"))).await?;
+        let cid = *fs
+            .get_root_dir()
+            .read()
+            .await
+            .get_initial_load_cid()
+            .unwrap();
+
+        // Later changes don't reach the store until the next flush
+        fs.append_file(
+            Path::new("data.txt"),
+            Box::pin(Cursor::new(b", second".to_vec())),
+        )
+        .await?;
+
+        let flushed = MonofsVfs::from_dir(Dir::load(&cid, store.clone()).await?);
+        let content = helper::read_to_vec(&flushed, "data.txt", 0, 100).await?;
+        assert_eq!(content, b"first");
+
+        fs.flush(None).await?;
+        let cid = *fs
+            .get_root_dir()
+            .read()
+            .await
+            .get_initial_load_cid()
+            .unwrap();
+
+        let flushed = MonofsVfs::from_dir(Dir::load(&cid, store).await?);
+        let content = helper::read_to_vec(&flushed, "data.txt", 0, 100).await?;
+        assert_eq!(content, b"first, second");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_monofsvfs_as_overlay_lower_layer() -> anyhow::Result<()> {
+        let lower = MonofsVfs::new(MemoryStore::default());
+        lower.create_directory(Path::new("etc")).await?;
+        lower.create_file(Path::new("etc/config"), false).await?;
+        lower
+            .write_file(
+                Path::new("etc/config"),
+                0,
+                Box::pin(Cursor::new(b"lower".to_vec())),
+            )
+            .await?;
+
+        let overlay = OverlayFileSystem::new([
+            Box::new(lower.clone()) as Box<dyn VirtualFileSystem + Send + Sync>,
+            Box::new(crate::MemoryFileSystem::new()),
+        ])?;
+
+        // Reads come from the lower layer
+        let mut reader = overlay.read_file(Path::new("etc/config"), 0, 100).await?;
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content).await?;
+        assert_eq!(content, b"lower");
+
+        // Writes go to the top layer and leave the content-addressed layer untouched
+        overlay
+            .write_file(
+                Path::new("etc/config"),
+                0,
+                Box::pin(Cursor::new(b"upper".to_vec())),
+            )
+            .await?;
+        let mut reader = overlay.read_file(Path::new("etc/config"), 0, 100).await?;
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content).await?;
+        assert_eq!(content, b"upper");
+
+        let content = helper::read_to_vec(&lower, "etc/config", 0, 100).await?;
+        assert_eq!(content, b"lower");
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod helper {
+    use super::*;
+
+    // Helper function to read a range of a file into a vector
+    pub(super) async fn read_to_vec<S>(
+        fs: &MonofsVfs<S>,
+        path: &str,
+        offset: u64,
+        length: u64,
+    ) -> VfsResult<Vec<u8>>
+    where
+        S: IpldStoreSeekable + Send + Sync + 'static,
+    {
+        let mut reader = fs.read_file(Path::new(path), offset, length).await?;
+        let mut content = Vec::new();
+        reader.read_to_end(&mut content).await?;
+        Ok(content)
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    io::{self, SeekFrom},
+    io::{self, SeekFrom, Write},
     os::unix::fs::PermissionsExt,
     path::{Path, PathBuf},
     pin::Pin,
@@ -8,12 +8,15 @@ use std::{
 use async_trait::async_trait;
 use tokio::{
     fs::{File, OpenOptions},
-    io::{AsyncRead, AsyncSeekExt, ReadBuf},
+    io::{AsyncRead, AsyncReadExt, AsyncSeekExt, ReadBuf},
 };
 
 #[cfg(unix)]
 use crate::metadata::Mode;
-use crate::{Metadata, ModeType, PathSegment, VfsError, VfsResult, VirtualFileSystem};
+use crate::{
+    filesystem::PageCollector, Cursor, Metadata, ModeType, PathSegment, VfsError, VfsResult,
+    VirtualFileSystem,
+};
 
 //--------------------------------------------------------------------------------------------------
 // Types
@@ -287,6 +290,37 @@ impl VirtualFileSystem for NativeFileSystem {
         Ok(Box::new(entries.into_iter()))
     }
 
+    async fn read_directory_paged(
+        &self,
+        path: &Path,
+        cursor: Option<Cursor>,
+        limit: usize,
+    ) -> VfsResult<(Vec<PathSegment>, Option<Cursor>)> {
+        let native_path = self.to_native_path(path);
+
+        let meta = self.symlink_metadata_checked(&native_path).await?;
+        if !meta.is_dir() {
+            return Err(VfsError::NotADirectory(path.to_path_buf()));
+        }
+
+        // The native listing isn't sorted, so only the entries that can still make it into the
+        // page are kept while streaming through it
+        let mut page = PageCollector::new(cursor, limit);
+        let mut dir = tokio::fs::read_dir(&native_path)
+            .await
+            .map_err(VfsError::Io)?;
+
+        while let Some(entry) = dir.next_entry().await.map_err(VfsError::Io)? {
+            if let Some(name) = entry.file_name().to_str() {
+                if let Ok(segment) = PathSegment::try_from(name) {
+                    page.push(segment);
+                }
+            }
+        }
+
+        Ok(page.finish())
+    }
+
     async fn read_symlink(&self, path: &Path) -> VfsResult<PathBuf> {
         let native_path = self.to_native_path(path);
 
@@ -367,6 +401,61 @@ impl VirtualFileSystem for NativeFileSystem {
         Ok(())
     }
 
+    async fn get_xattr(&self, path: &Path, _name: &str) -> VfsResult<Option<Vec<u8>>> {
+        // Extended attributes aren't read from the native filesystem, so none are ever set
+        self.symlink_metadata_checked(&self.to_native_path(path))
+            .await?;
+        Ok(None)
+    }
+
+    async fn set_xattr(&self, path: &Path, _name: &str, _value: Vec<u8>) -> VfsResult<()> {
+        self.symlink_metadata_checked(&self.to_native_path(path))
+            .await?;
+        Err(VfsError::Io(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "extended attributes are not supported by the native filesystem",
+        )))
+    }
+
+    async fn list_xattr(&self, path: &Path) -> VfsResult<Vec<String>> {
+        self.symlink_metadata_checked(&self.to_native_path(path))
+            .await?;
+        Ok(Vec::new())
+    }
+
+    async fn remove_xattr(&self, path: &Path, name: &str) -> VfsResult<()> {
+        self.symlink_metadata_checked(&self.to_native_path(path))
+            .await?;
+        Err(VfsError::AttributeNotFound {
+            path: path.to_path_buf(),
+            name: name.to_string(),
+        })
+    }
+
+    #[cfg(unix)]
+    async fn set_permissions(&self, path: &Path, mode: u32) -> VfsResult<()> {
+        let native_path = self.to_native_path(path);
+
+        self.symlink_metadata_checked(&native_path).await?;
+
+        // chmod changes only the permissions, so nothing else can be overwritten
+        tokio::fs::set_permissions(&native_path, std::fs::Permissions::from_mode(mode & 0o777))
+            .await
+            .map_err(VfsError::Io)
+    }
+
+    #[cfg(unix)]
+    async fn set_owner(&self, path: &Path, uid: Option<u32>, gid: Option<u32>) -> VfsResult<()> {
+        let native_path = self.to_native_path(path);
+
+        self.symlink_metadata_checked(&native_path).await?;
+
+        tokio::task::spawn_blocking(move || std::os::unix::fs::lchown(native_path, uid, gid))
+            .await
+            .map_err(|e| VfsError::Io(io::Error::other(e)))?
+            .map_err(VfsError::Io)
+    }
+
     async fn write_file(
         &self,
         path: &Path,
@@ -395,6 +484,32 @@ impl VirtualFileSystem for NativeFileSystem {
         Ok(())
     }
 
+    async fn append_file(
+        &self,
+        path: &Path,
+        mut data: Pin<Box<dyn AsyncRead + Send + Sync + 'static>>,
+    ) -> VfsResult<u64> {
+        let native_path = self.to_native_path(path);
+
+        let meta = self.symlink_metadata_checked(&native_path).await?;
+        if !meta.is_file() {
+            return Err(VfsError::NotAFile(path.to_path_buf()));
+        }
+
+        let mut buffer = Vec::new();
+        data.read_to_end(&mut buffer).await.map_err(VfsError::Io)?;
+
+        // A single write to a file opened in append mode is not interleaved with other appends
+        tokio::task::spawn_blocking(move || -> io::Result<u64> {
+            let mut file = std::fs::OpenOptions::new().append(true).open(native_path)?;
+            file.write_all(&buffer)?;
+            Ok(file.metadata()?.len())
+        })
+        .await
+        .map_err(|e| VfsError::Io(io::Error::other(e)))?
+        .map_err(VfsError::Io)
+    }
+
     async fn remove(&self, path: &Path) -> VfsResult<()> {
         let native_path = self.to_native_path(path);
 
@@ -420,6 +535,28 @@ impl VirtualFileSystem for NativeFileSystem {
         }
     }
 
+    async fn remove_tree(&self, path: &Path) -> VfsResult<()> {
+        if path == Path::new("") {
+            return Err(VfsError::InvalidPathComponent(
+                "Cannot remove the root directory".into(),
+            ));
+        }
+
+        let native_path = self.to_native_path(path);
+
+        let metadata = self.symlink_metadata_checked(&native_path).await?;
+
+        if metadata.is_dir() {
+            tokio::fs::remove_dir_all(&native_path)
+                .await
+                .map_err(VfsError::Io)
+        } else {
+            tokio::fs::remove_file(&native_path)
+                .await
+                .map_err(VfsError::Io)
+        }
+    }
+
     async fn rename(&self, old_path: &Path, new_path: &Path) -> VfsResult<()> {
         let native_old_path = self.to_native_path(old_path);
         let native_new_path = self.to_native_path(new_path);
@@ -602,6 +739,46 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_append_file() {
+        let (_temp_dir, fs) = helper::setup_fs().await;
+        fs.create_file(Path::new("test.txt"), false).await.unwrap();
+
+        let len = fs
+            .append_file(
+                Path::new("test.txt"),
+                Box::pin(std::io::Cursor::new(b"Hello".to_vec())),
+            )
+            .await
+            .unwrap();
+        assert_eq!(len, 5);
+        let len = fs
+            .append_file(
+                Path::new("test.txt"),
+                Box::pin(std::io::Cursor::new(b", World!".to_vec())),
+            )
+            .await
+            .unwrap();
+        assert_eq!(len, 13);
+
+        let mut reader = fs.read_file(Path::new("test.txt"), 0, 13).await.unwrap();
+        let mut read_data = Vec::new();
+        tokio::io::copy(&mut reader, &mut read_data).await.unwrap();
+        assert_eq!(read_data, b"Hello, World!");
+
+        // Test appending to non-existent file
+        match fs
+            .append_file(
+                Path::new("nonexistent"),
+                Box::pin(std::io::Cursor::new(b"".to_vec())),
+            )
+            .await
+        {
+            Err(VfsError::NotFound(_)) => {}
+            _ => panic!("Expected NotFound error"),
+        }
+    }
+
     #[tokio::test]
     async fn test_read_directory() {
         let (_temp_dir, fs) = helper::setup_fs().await;
@@ -642,6 +819,49 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_read_directory_paged() {
+        let (_temp_dir, fs) = helper::setup_fs().await;
+
+        fs.create_directory(Path::new("testdir")).await.unwrap();
+        for i in 0..25 {
+            fs.create_file(Path::new(&format!("testdir/file{:02}.txt", i)), false)
+                .await
+                .unwrap();
+        }
+
+        // Paginate through the directory
+        let mut listed = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (page, next) = fs
+                .read_directory_paged(Path::new("testdir"), cursor, 10)
+                .await
+                .unwrap();
+            listed.extend(page);
+
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+
+        // Every entry is listed exactly once, in sorted order
+        let expected: Vec<_> = (0..25)
+            .map(|i| PathSegment::try_from(format!("file{:02}.txt", i).as_str()).unwrap())
+            .collect();
+        assert_eq!(listed, expected);
+
+        // Test reading file as directory
+        match fs
+            .read_directory_paged(Path::new("testdir/file00.txt"), None, 10)
+            .await
+        {
+            Err(VfsError::NotADirectory(_)) => {}
+            _ => panic!("Expected NotADirectory error"),
+        }
+    }
+
     #[tokio::test]
     async fn test_read_symlink() {
         let (_temp_dir, fs) = helper::setup_fs().await;
@@ -743,6 +963,39 @@ mod tests {
         assert!(matches!(err, VfsError::NotFound(_)));
     }
 
+    #[tokio::test]
+    async fn test_remove_tree() {
+        let (_temp_dir, fs) = helper::setup_fs().await;
+
+        // Create a nested directory tree
+        fs.create_directory(Path::new("dir")).await.unwrap();
+        fs.create_directory(Path::new("dir/subdir")).await.unwrap();
+        fs.create_file(Path::new("dir/file.txt"), false)
+            .await
+            .unwrap();
+        fs.create_file(Path::new("dir/subdir/nested.txt"), false)
+            .await
+            .unwrap();
+        fs.create_file(Path::new("keep.txt"), false).await.unwrap();
+
+        // Remove the whole tree
+        fs.remove_tree(Path::new("dir")).await.unwrap();
+        assert!(!fs.exists(Path::new("dir")).await.unwrap());
+        assert!(fs.exists(Path::new("keep.txt")).await.unwrap());
+
+        // Remove a single file
+        fs.remove_tree(Path::new("keep.txt")).await.unwrap();
+        assert!(!fs.exists(Path::new("keep.txt")).await.unwrap());
+
+        // Try to remove non-existent path
+        let err = fs.remove_tree(Path::new("nonexistent")).await.unwrap_err();
+        assert!(matches!(err, VfsError::NotFound(_)));
+
+        // The root can't be removed
+        let err = fs.remove_tree(Path::new("")).await.unwrap_err();
+        assert!(matches!(err, VfsError::InvalidPathComponent(_)));
+    }
+
     #[tokio::test]
     async fn test_rename() {
         let (_temp_dir, fs) = helper::setup_fs().await;
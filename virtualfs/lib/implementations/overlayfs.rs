@@ -1,15 +1,22 @@
 use std::{
+    collections::HashMap,
     path::{Path, PathBuf},
     pin::Pin,
+    sync::{Arc, Mutex},
 };
 
 use async_recursion::async_recursion;
 use async_trait::async_trait;
 use getset::Getters;
-use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt},
+    sync::{Mutex as AsyncMutex, OwnedMutexGuard},
+};
 
 use crate::{
-    error::VfsError, filesystem::VirtualFileSystem, Metadata, ModeType, PathSegment, VfsResult,
+    error::VfsError,
+    filesystem::{PageCollector, VirtualFileSystem},
+    Cursor, Metadata, ModeType, PathSegment, VfsResult,
 };
 
 //--------------------------------------------------------------------------------------------------
@@ -124,6 +131,28 @@ pub struct OverlayFileSystem {
 
     /// The read-only lower layers, ordered from bottom to top
     lower_layers: Vec<Box<dyn VirtualFileSystem + Send + Sync>>,
+
+    /// Locks serializing operations on the same path, such as concurrent creates
+    #[getset(skip)]
+    path_locks: PathLocks,
+}
+
+/// A map from paths to the locks held on them.
+///
+/// Entries are removed when the last holder or waiter releases them, so the map only holds the
+/// paths currently in use.
+type PathLocks = Mutex<HashMap<PathBuf, Arc<AsyncMutex<()>>>>;
+
+/// A lock on a path of an [`OverlayFileSystem`], held until it is dropped.
+struct PathLock<'a> {
+    /// The map the lock belongs to
+    locks: &'a PathLocks,
+
+    /// The locked path
+    path: PathBuf,
+
+    /// The guard of the path's mutex
+    _guard: OwnedMutexGuard<()>,
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -161,9 +190,33 @@ impl OverlayFileSystem {
         Ok(Self {
             top_layer: layers.pop().unwrap(),
             lower_layers: layers,
+            path_locks: Mutex::new(HashMap::new()),
         })
     }
 
+    /// Locks `path`, waiting until no other operation holds it.
+    ///
+    /// Operations that check the state of a path and then change it hold the lock in between, so
+    /// they can't interleave with each other. The path is normalized first, so spellings like
+    /// `dir//x` and `dir/./x` share the lock of `dir/x`. A path that can't be normalized is locked
+    /// as is, since the operation rejects it anyway.
+    async fn lock_path(&self, path: &Path) -> PathLock<'_> {
+        let path = monoutils::path::normalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let mutex = self
+            .path_locks
+            .lock()
+            .unwrap()
+            .entry(path.clone())
+            .or_default()
+            .clone();
+
+        PathLock {
+            locks: &self.path_locks,
+            path,
+            _guard: mutex.lock_owned().await,
+        }
+    }
+
     /// Checks if a given path corresponds to a whiteout file.
     ///
     /// Whiteout files are used by overlay filesystems to mark an entry that should be hidden
@@ -176,6 +229,42 @@ impl OverlayFileSystem {
             .unwrap_or(false)
     }
 
+    /// Checks if a whiteout in the top layer for one of the ancestors of `path` hides it.
+    ///
+    /// A whiteout for a directory masks the whole lower-layer subtree under it, not just the
+    /// directory itself, so lookups of descendants must check every ancestor.
+    async fn is_ancestor_whited_out(&self, path: &Path) -> VfsResult<bool> {
+        for ancestor in path.ancestors().skip(1) {
+            let (Some(parent), Some(name)) = (ancestor.parent(), ancestor.file_name()) else {
+                continue;
+            };
+
+            let whiteout_path =
+                parent.join(format!("{}{}", WHITEOUT_PREFIX, name.to_string_lossy()));
+            if self.get_top_layer().exists(&whiteout_path).await? {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Checks if an opaque marker in the top layer for one of the ancestors of `path` hides its
+    /// lower-layer copies.
+    ///
+    /// An opaque directory hides everything below it in the lower layers, so a lower-layer entry
+    /// is only visible if none of the directories above it is opaque.
+    async fn is_ancestor_opaque(&self, path: &Path) -> VfsResult<bool> {
+        for ancestor in path.ancestors().skip(1) {
+            let opaque_marker = ancestor.join(OPAQUE_MARKER);
+            if self.get_top_layer().exists(&opaque_marker).await? {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
     /// Recursively ensures that the parent directory of a given path exists in the top (writable) layer.
     ///
     /// This function implements the "copy-up" mechanism: if the parent directory is not present in the top layer
@@ -185,9 +274,16 @@ impl OverlayFileSystem {
     /// Additionally, if a whiteout exists in the parent's parent for the specified directory, it will return an error,
     /// as this indicates that the parent directory is masked and should not be available.
     ///
+    /// Parent directories that are symbolic links in the lower layers are not followed, since
+    /// copying them up as directories would replace the links.
+    ///
     /// ## Errors
     ///
     /// Returns a `VfsError::ParentDirectoryNotFound` if the parent directory cannot be found in any layer.
+    ///
+    /// Returns a `VfsError::ParentIsSymlink` if the parent directory is a symbolic link in the
+    /// lower layers, or a `VfsError::TooManySymlinks` if the link is part of a loop or a chain
+    /// longer than `DEFAULT_SYMLINK_DEPTH`.
     #[async_recursion]
     async fn ensure_parent_in_top(&self, path: &Path) -> VfsResult<()> {
         if let Some(parent) = path.parent() {
@@ -225,6 +321,26 @@ impl OverlayFileSystem {
                 return Err(VfsError::ParentDirectoryNotFound(parent.to_path_buf()));
             }
 
+            // A symlinked parent can't be copied up as a directory. Follow the link with a bounded
+            // depth so that loops are reported as such.
+            for layer in self.get_lower_layers().iter().rev() {
+                if !layer.exists(parent).await? {
+                    continue;
+                }
+
+                if layer.get_metadata(parent).await?.is_symlink() {
+                    if let Err(e @ VfsError::TooManySymlinks(_)) =
+                        self.get_metadata_follow(parent).await
+                    {
+                        return Err(e);
+                    }
+
+                    return Err(VfsError::ParentIsSymlink(parent.to_path_buf()));
+                }
+
+                break;
+            }
+
             // Recursively ensure the parent's parent exists in the top layer.
             self.ensure_parent_in_top(parent).await?;
 
@@ -247,6 +363,52 @@ impl OverlayFileSystem {
         Ok(())
     }
 
+    /// Copies `path` up from the topmost lower layer that has it, unless it is already in the top
+    /// layer.
+    ///
+    /// Metadata, including extended attributes, is copied along with the entity. A directory is
+    /// copied without its contents, which stay visible from the lower layers.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `VfsError::NotFound` if the path is not visible through the overlay.
+    async fn copy_up(&self, path: &Path) -> VfsResult<()> {
+        let top = self.get_top_layer();
+        if top.exists(path).await? {
+            return Ok(());
+        }
+
+        // Whiteouts hide entities in lower layers, so check visibility through the overlay.
+        if Self::is_whiteout_file(path) || !self.exists(path).await? {
+            return Err(VfsError::NotFound(path.to_path_buf()));
+        }
+
+        self.ensure_parent_in_top(path).await?;
+        for layer in self.get_lower_layers().iter().rev() {
+            if !layer.exists(path).await? {
+                continue;
+            }
+
+            let metadata = layer.get_metadata(path).await?;
+            #[cfg(unix)]
+            let is_dir = metadata.get_mode().get_type() == Some(ModeType::Directory);
+            #[cfg(not(unix))]
+            let is_dir = matches!(metadata.get_entity_type(), EntityType::Directory);
+
+            if is_dir {
+                top.create_directory(path).await?;
+                top.set_metadata(path, metadata).await?;
+            } else {
+                self.ensure_parent_in_top_recursive(layer.as_ref(), path)
+                    .await?;
+            }
+
+            return Ok(());
+        }
+
+        Err(VfsError::NotFound(path.to_path_buf()))
+    }
+
     #[async_recursion]
     async fn ensure_parent_in_top_recursive(
         &self,
@@ -347,6 +509,74 @@ impl OverlayFileSystem {
         }
         Ok(())
     }
+
+    /// Returns the layers whose entries make up the directory at `path`: the top layer if it has
+    /// the directory, and the lower layers that have it and aren't hidden, topmost first.
+    ///
+    /// Whiteouts in the top layer's directory hide the entries of the lower layers.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `VfsError::NotFound` if the directory is whited out.
+    async fn get_directory_layers(
+        &self,
+        path: &Path,
+    ) -> VfsResult<(
+        Option<&(dyn VirtualFileSystem + Send + Sync)>,
+        Vec<&(dyn VirtualFileSystem + Send + Sync)>,
+    )> {
+        // Check for a whiteout for this directory
+        let whiteout_path = if let Some(parent) = path.parent() {
+            parent.join(format!(
+                "{}{}",
+                WHITEOUT_PREFIX,
+                path.file_name()
+                    .ok_or_else(|| VfsError::InvalidPathComponent(
+                        "Path must have a file name".to_string()
+                    ))?
+                    .to_string_lossy()
+            ))
+        } else {
+            PathBuf::from(format!("{}{}", WHITEOUT_PREFIX, path.to_string_lossy()))
+        };
+
+        if self.get_top_layer().exists(&whiteout_path).await? {
+            return Err(VfsError::NotFound(path.to_path_buf()));
+        }
+
+        let top_layer = self.get_top_layer().as_ref();
+        let top = if top_layer.exists(path).await? {
+            Some(top_layer)
+        } else {
+            None
+        };
+
+        // If an ancestor directory is opaque or whited out, only the top layer can provide the
+        // directory
+        if self.is_ancestor_opaque(path).await? || self.is_ancestor_whited_out(path).await? {
+            return match top {
+                Some(top) => Ok((Some(top), Vec::new())),
+                None => Err(VfsError::NotFound(path.to_path_buf())),
+            };
+        }
+
+        // If the directory itself is opaque in the top layer, it hides the lower layers
+        let is_opaque = match top {
+            Some(top) => top.exists(&path.join(OPAQUE_MARKER)).await?,
+            None => false,
+        };
+
+        let mut lower = Vec::new();
+        if !is_opaque {
+            for layer in self.get_lower_layers().iter().rev() {
+                if layer.exists(path).await? {
+                    lower.push(layer.as_ref());
+                }
+            }
+        }
+
+        Ok((top, lower))
+    }
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -380,19 +610,17 @@ impl VirtualFileSystem for OverlayFileSystem {
             PathBuf::from(format!("{}{}", WHITEOUT_PREFIX, path.to_string_lossy()))
         };
 
-        // If a whiteout file exists, the path is considered non-existent
-        if self.get_top_layer().exists(&whiteout_path).await? {
+        // If a whiteout file exists for the path or one of its ancestors, the path is considered
+        // non-existent
+        if self.get_top_layer().exists(&whiteout_path).await?
+            || self.is_ancestor_whited_out(path).await?
+        {
             return Ok(false);
         }
 
-        // Check if the closest parent directory is marked as opaque
-        // We only need to check the immediate parent since opaque markers only affect their direct children
-        if let Some(parent) = path.parent() {
-            let opaque_marker = parent.join(OPAQUE_MARKER);
-            if self.get_top_layer().exists(&opaque_marker).await? {
-                // If parent is opaque and the file doesn't exist in top layer, it doesn't exist
-                return Ok(false);
-            }
+        // If any ancestor directory is marked as opaque, the lower layers can't provide the path
+        if self.is_ancestor_opaque(path).await? {
+            return Ok(false);
         }
 
         // Check lower layers in reverse order (top to bottom)
@@ -408,6 +636,10 @@ impl VirtualFileSystem for OverlayFileSystem {
         // Get the top layer where we'll create the file
         let top_layer = self.get_top_layer();
 
+        // Hold the path from the existence check until the file is created, so only one of
+        // several concurrent creators can succeed
+        let _lock = self.lock_path(path).await;
+
         // Check if the file exists in any layer
         let file_exists = self.exists(path).await?;
         if file_exists && !exists_ok {
@@ -475,12 +707,23 @@ impl VirtualFileSystem for OverlayFileSystem {
             PathBuf::from(format!("{}{}", WHITEOUT_PREFIX, path.to_string_lossy()))
         };
 
-        if self.get_top_layer().exists(&whiteout_path).await? {
-            self.get_top_layer().remove(&whiteout_path).await?;
+        let replaces_whiteout = top_layer.exists(&whiteout_path).await?;
+        if replaces_whiteout {
+            top_layer.remove(&whiteout_path).await?;
         }
 
         // Create the directory in the top layer
-        top_layer.create_directory(path).await
+        top_layer.create_directory(path).await?;
+
+        // A directory replacing a removed one starts out empty, so mark it opaque to keep the
+        // old lower-layer contents hidden
+        if replaces_whiteout {
+            top_layer
+                .create_file(&path.join(OPAQUE_MARKER), false)
+                .await?;
+        }
+
+        Ok(())
     }
 
     async fn create_symlink(&self, path: &Path, target: &Path) -> VfsResult<()> {
@@ -535,16 +778,15 @@ impl VirtualFileSystem for OverlayFileSystem {
             PathBuf::from(format!("{}{}", WHITEOUT_PREFIX, path.to_string_lossy()))
         };
 
-        if self.get_top_layer().exists(&whiteout_path).await? {
+        if self.get_top_layer().exists(&whiteout_path).await?
+            || self.is_ancestor_whited_out(path).await?
+        {
             return Err(VfsError::NotFound(path.to_path_buf()));
         }
 
-        // If the immediate parent is opaque, lower-layer content is hidden.
-        if let Some(parent) = path.parent() {
-            let opaque_marker = parent.join(OPAQUE_MARKER);
-            if self.get_top_layer().exists(&opaque_marker).await? {
-                return Err(VfsError::NotFound(path.to_path_buf()));
-            }
+        // If any ancestor directory is opaque, lower-layer content is hidden.
+        if self.is_ancestor_opaque(path).await? {
+            return Err(VfsError::NotFound(path.to_path_buf()));
         }
 
         // Otherwise, search lower layers (highest priority first).
@@ -561,69 +803,21 @@ impl VirtualFileSystem for OverlayFileSystem {
         &self,
         path: &Path,
     ) -> VfsResult<Box<dyn Iterator<Item = PathSegment> + Send + Sync + 'static>> {
-        // Check for a whiteout for this directory
-        let whiteout_path = if let Some(parent) = path.parent() {
-            parent.join(format!(
-                "{}{}",
-                WHITEOUT_PREFIX,
-                path.file_name()
-                    .ok_or_else(|| VfsError::InvalidPathComponent(
-                        "Path must have a file name".to_string()
-                    ))?
-                    .to_string_lossy()
-            ))
-        } else {
-            PathBuf::from(format!("{}{}", WHITEOUT_PREFIX, path.to_string_lossy()))
-        };
-
-        if self.get_top_layer().exists(&whiteout_path).await? {
-            return Err(VfsError::NotFound(path.to_path_buf()));
-        }
-
-        // Check if the parent directory is opaque
-        if let Some(parent) = path.parent() {
-            let opaque_marker = parent.join(OPAQUE_MARKER);
-            if self.get_top_layer().exists(&opaque_marker).await? {
-                if self.get_top_layer().exists(path).await? {
-                    let entries = self.get_top_layer().read_directory(path).await?;
-                    // Filter out whiteout files and opaque markers from the result
-                    return Ok(Box::new(entries.filter(|seg| {
-                        let name = seg.to_string();
-                        !name.starts_with(WHITEOUT_PREFIX)
-                    })));
-                } else {
-                    return Err(VfsError::NotFound(path.to_path_buf()));
-                }
-            }
-        }
-
-        use std::collections::HashMap;
+        let (top, lower) = self.get_directory_layers(path).await?;
         let mut union: HashMap<String, PathSegment> = HashMap::new();
 
-        // First check if the directory itself is opaque in the top layer
-        let is_opaque = if self.get_top_layer().exists(path).await? {
-            let opaque_marker = path.join(OPAQUE_MARKER);
-            self.get_top_layer().exists(&opaque_marker).await?
-        } else {
-            false
-        };
-
-        // If not opaque, process lower layers first (from bottom to top)
-        if !is_opaque {
-            for layer in self.get_lower_layers().iter() {
-                if layer.exists(path).await? {
-                    let entries = layer.read_directory(path).await?;
-                    for seg in entries {
-                        let name = seg.to_string();
-                        union.insert(name, seg);
-                    }
-                }
+        // Process the lower layers first (from bottom to top)
+        for layer in lower.iter().rev() {
+            let entries = layer.read_directory(path).await?;
+            for seg in entries {
+                let name = seg.to_string();
+                union.insert(name, seg);
             }
         }
 
         // Process the top layer
-        if self.get_top_layer().exists(path).await? {
-            let entries = self.get_top_layer().read_directory(path).await?;
+        if let Some(top) = top {
+            let entries = top.read_directory(path).await?;
             for seg in entries {
                 let name = seg.to_string();
                 if name.starts_with(WHITEOUT_PREFIX) {
@@ -647,6 +841,61 @@ impl VirtualFileSystem for OverlayFileSystem {
         Ok(Box::new(result.into_iter()))
     }
 
+    async fn read_directory_paged(
+        &self,
+        path: &Path,
+        cursor: Option<Cursor>,
+        limit: usize,
+    ) -> VfsResult<(Vec<PathSegment>, Option<Cursor>)> {
+        let (top, lower) = self.get_directory_layers(path).await?;
+        let layers = top
+            .iter()
+            .map(|layer| (*layer, true))
+            .chain(lower.iter().map(|layer| (*layer, false)));
+
+        // Merge the pages of each layer, which are sorted, so each layer is only read up to the
+        // entries that can make it into the page
+        let mut page = PageCollector::new(cursor, limit);
+        for (layer, is_top) in layers {
+            let mut layer_cursor = page.get_cursor().cloned();
+            let mut listed = 0;
+            loop {
+                let (entries, next_cursor) = layer
+                    .read_directory_paged(path, layer_cursor, page.get_capacity())
+                    .await?;
+
+                for entry in entries {
+                    let name = entry.to_string();
+                    if name.starts_with(WHITEOUT_PREFIX) {
+                        continue;
+                    }
+
+                    // Whiteouts in the top layer hide the entries of the lower layers
+                    if let (false, Some(top)) = (is_top, top) {
+                        let whiteout_path = path.join(format!("{}{}", WHITEOUT_PREFIX, name));
+                        if top.exists(&whiteout_path).await? {
+                            continue;
+                        }
+                    }
+
+                    page.push(entry);
+                    listed += 1;
+                }
+
+                // The layer's later entries sort after the ones it already listed, so once it
+                // has listed enough to fill the page none of them can make it in
+                match next_cursor {
+                    Some(next_cursor) if listed < page.get_capacity() => {
+                        layer_cursor = Some(next_cursor)
+                    }
+                    _ => break,
+                }
+            }
+        }
+
+        Ok(page.finish())
+    }
+
     async fn read_symlink(&self, path: &Path) -> VfsResult<PathBuf> {
         if self.get_top_layer().exists(path).await? {
             return self.get_top_layer().read_symlink(path).await;
@@ -662,15 +911,14 @@ impl VirtualFileSystem for OverlayFileSystem {
             PathBuf::from(format!("{}{}", WHITEOUT_PREFIX, path.to_string_lossy()))
         };
 
-        if self.get_top_layer().exists(&whiteout_path).await? {
+        if self.get_top_layer().exists(&whiteout_path).await?
+            || self.is_ancestor_whited_out(path).await?
+        {
             return Err(VfsError::NotFound(path.to_path_buf()));
         }
 
-        if let Some(parent) = path.parent() {
-            let opaque_marker = parent.join(OPAQUE_MARKER);
-            if self.get_top_layer().exists(&opaque_marker).await? {
-                return Err(VfsError::NotFound(path.to_path_buf()));
-            }
+        if self.is_ancestor_opaque(path).await? {
+            return Err(VfsError::NotFound(path.to_path_buf()));
         }
 
         for layer in self.get_lower_layers().iter().rev() {
@@ -697,15 +945,14 @@ impl VirtualFileSystem for OverlayFileSystem {
             PathBuf::from(format!("{}{}", WHITEOUT_PREFIX, path.to_string_lossy()))
         };
 
-        if self.get_top_layer().exists(&whiteout_path).await? {
+        if self.get_top_layer().exists(&whiteout_path).await?
+            || self.is_ancestor_whited_out(path).await?
+        {
             return Err(VfsError::NotFound(path.to_path_buf()));
         }
 
-        if let Some(parent) = path.parent() {
-            let opaque_marker = parent.join(OPAQUE_MARKER);
-            if self.get_top_layer().exists(&opaque_marker).await? {
-                return Err(VfsError::NotFound(path.to_path_buf()));
-            }
+        if self.is_ancestor_opaque(path).await? {
+            return Err(VfsError::NotFound(path.to_path_buf()));
         }
 
         for layer in self.get_lower_layers().iter().rev() {
@@ -733,6 +980,70 @@ impl VirtualFileSystem for OverlayFileSystem {
         Err(VfsError::NotFound(path.to_path_buf()))
     }
 
+    async fn get_xattr(&self, path: &Path, name: &str) -> VfsResult<Option<Vec<u8>>> {
+        if !self.exists(path).await? {
+            return Err(VfsError::NotFound(path.to_path_buf()));
+        }
+
+        // The topmost layer that has the entity owns its attributes.
+        if self.get_top_layer().exists(path).await? {
+            return self.get_top_layer().get_xattr(path, name).await;
+        }
+
+        for layer in self.get_lower_layers().iter().rev() {
+            if layer.exists(path).await? {
+                return layer.get_xattr(path, name).await;
+            }
+        }
+
+        Err(VfsError::NotFound(path.to_path_buf()))
+    }
+
+    async fn set_xattr(&self, path: &Path, name: &str, value: Vec<u8>) -> VfsResult<()> {
+        // Copy-up first, so the lower layers stay untouched and the other attributes are kept.
+        self.copy_up(path).await?;
+        self.get_top_layer().set_xattr(path, name, value).await
+    }
+
+    async fn list_xattr(&self, path: &Path) -> VfsResult<Vec<String>> {
+        if !self.exists(path).await? {
+            return Err(VfsError::NotFound(path.to_path_buf()));
+        }
+
+        if self.get_top_layer().exists(path).await? {
+            return self.get_top_layer().list_xattr(path).await;
+        }
+
+        for layer in self.get_lower_layers().iter().rev() {
+            if layer.exists(path).await? {
+                return layer.list_xattr(path).await;
+            }
+        }
+
+        Err(VfsError::NotFound(path.to_path_buf()))
+    }
+
+    async fn remove_xattr(&self, path: &Path, name: &str) -> VfsResult<()> {
+        self.copy_up(path).await?;
+        self.get_top_layer().remove_xattr(path, name).await
+    }
+
+    #[cfg(unix)]
+    async fn set_permissions(&self, path: &Path, mode: u32) -> VfsResult<()> {
+        // The lower layers are read-only, so copy the entity up before changing it.
+        let _lock = self.lock_path(path).await;
+        self.copy_up(path).await?;
+        self.get_top_layer().set_permissions(path, mode).await
+    }
+
+    #[cfg(unix)]
+    async fn set_owner(&self, path: &Path, uid: Option<u32>, gid: Option<u32>) -> VfsResult<()> {
+        // The lower layers are read-only, so copy the entity up before changing it.
+        let _lock = self.lock_path(path).await;
+        self.copy_up(path).await?;
+        self.get_top_layer().set_owner(path, uid, gid).await
+    }
+
     async fn write_file(
         &self,
         path: &Path,
@@ -746,6 +1057,9 @@ impl VirtualFileSystem for OverlayFileSystem {
 
         let top = self.get_top_layer();
 
+        // Hold the path across the copy-up, like `append_file`
+        let _lock = self.lock_path(path).await;
+
         // If the file exists in the upper (top) layer, simply delegate.
         if top.exists(path).await? {
             return top.write_file(path, offset, data).await;
@@ -787,6 +1101,32 @@ impl VirtualFileSystem for OverlayFileSystem {
         }
     }
 
+    async fn append_file(
+        &self,
+        path: &Path,
+        data: Pin<Box<dyn AsyncRead + Send + Sync + 'static>>,
+    ) -> VfsResult<u64> {
+        // Do not allow appends directly on whiteout files.
+        if Self::is_whiteout_file(path) {
+            return Err(VfsError::NotFound(path.to_path_buf()));
+        }
+
+        let top = self.get_top_layer();
+
+        // Hold the path across the copy-up, so concurrent writers can't copy it up twice or
+        // append to a half-copied file
+        let _lock = self.lock_path(path).await;
+
+        // If the file exists in the upper (top) layer, simply delegate.
+        if top.exists(path).await? {
+            return top.append_file(path, data).await;
+        }
+
+        // Copy-up the file from a lower layer, then append in the top layer.
+        self.copy_up(path).await?;
+        top.append_file(path, data).await
+    }
+
     async fn remove(&self, path: &Path) -> VfsResult<()> {
         if Self::is_whiteout_file(path) {
             return Err(VfsError::NotFound(path.to_path_buf()));
@@ -831,6 +1171,47 @@ impl VirtualFileSystem for OverlayFileSystem {
         }
     }
 
+    async fn remove_tree(&self, path: &Path) -> VfsResult<()> {
+        if Self::is_whiteout_file(path) || !self.exists(path).await? {
+            return Err(VfsError::NotFound(path.to_path_buf()));
+        }
+
+        let top = self.get_top_layer();
+
+        // Delete whatever part of the subtree is in the top layer in one go.
+        if top.exists(path).await? {
+            top.remove_tree(path).await?;
+        }
+
+        // Check if the lower layers still have content at the path.
+        let mut exists_in_lower = false;
+        for layer in self.get_lower_layers().iter().rev() {
+            if layer.exists(path).await? {
+                exists_in_lower = true;
+                break;
+            }
+        }
+
+        if exists_in_lower {
+            // A single whiteout masks the whole lower-layer subtree, however deep it is.
+            self.ensure_parent_in_top(path).await?;
+
+            let whiteout_path = if let Some(parent) = path.parent() {
+                parent.join(format!(
+                    "{}{}",
+                    WHITEOUT_PREFIX,
+                    path.file_name().unwrap().to_string_lossy()
+                ))
+            } else {
+                PathBuf::from(format!("{}{}", WHITEOUT_PREFIX, path.to_string_lossy()))
+            };
+
+            top.create_file(&whiteout_path, true).await?;
+        }
+
+        Ok(())
+    }
+
     async fn rename(&self, old_path: &Path, new_path: &Path) -> VfsResult<()> {
         // Ensure new_path does not already exist.
         if self.exists(new_path).await? {
@@ -931,6 +1312,37 @@ impl VirtualFileSystem for OverlayFileSystem {
 
         top.create_file(&whiteout_path, false).await
     }
+
+    async fn flush(&self, path: Option<&Path>) -> VfsResult<()> {
+        // Lower layers are read-only, so only the top layer can have changes to persist
+        let top = self.get_top_layer();
+        let Some(path) = path else {
+            return top.flush(None).await;
+        };
+
+        if top.exists(path).await? {
+            return top.flush(Some(path)).await;
+        }
+
+        if !self.exists(path).await? {
+            return Err(VfsError::NotFound(path.to_path_buf()));
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for PathLock<'_> {
+    fn drop(&mut self) {
+        let mut locks = self.locks.lock().unwrap();
+
+        // The map and this guard hold the last references, so nobody else is waiting
+        if let Some(mutex) = locks.get(&self.path) {
+            if Arc::strong_count(mutex) == 2 {
+                locks.remove(&self.path);
+            }
+        }
+    }
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -939,7 +1351,7 @@ impl VirtualFileSystem for OverlayFileSystem {
 
 #[cfg(test)]
 mod tests {
-    use crate::ModeType;
+    use crate::{MemoryFileSystem, ModeType};
 
     use super::*;
 
@@ -986,6 +1398,82 @@ mod tests {
         assert!(overlay.exists(Path::new("dir1")).await.unwrap()); // Directory itself should exist
     }
 
+    #[tokio::test]
+    async fn test_overlayfs_reads_hidden_by_deep_opaque_ancestor() {
+        // Lower layer has content two levels below "dir1", which is opaque in the upper layer
+        let lower = helper::create_fs(&["dir1/sub/deep/file.txt"]).await;
+        lower
+            .create_symlink(Path::new("dir1/sub/link"), Path::new("deep/file.txt"))
+            .await
+            .unwrap();
+        let upper = helper::create_fs(&["dir1/.wh..wh..opq", "dir1/upper.txt"]).await;
+
+        let overlay = OverlayFileSystem::new(vec![lower, upper]).unwrap();
+
+        // Nothing below the opaque directory is read from the lower layer
+        assert!(matches!(
+            overlay
+                .read_file(Path::new("dir1/sub/deep/file.txt"), 0, u64::MAX)
+                .await,
+            Err(VfsError::NotFound(_))
+        ));
+        assert!(matches!(
+            overlay
+                .get_metadata(Path::new("dir1/sub/deep/file.txt"))
+                .await,
+            Err(VfsError::NotFound(_))
+        ));
+        assert!(matches!(
+            overlay.get_metadata(Path::new("dir1/sub/deep")).await,
+            Err(VfsError::NotFound(_))
+        ));
+        assert!(matches!(
+            overlay.read_symlink(Path::new("dir1/sub/link")).await,
+            Err(VfsError::NotFound(_))
+        ));
+        assert!(matches!(
+            overlay.read_directory(Path::new("dir1/sub/deep")).await,
+            Err(VfsError::NotFound(_))
+        ));
+        assert!(matches!(
+            overlay
+                .read_directory_paged(Path::new("dir1/sub/deep"), None, 10)
+                .await,
+            Err(VfsError::NotFound(_))
+        ));
+
+        // The opaque directory itself only lists the upper layer
+        let entries: Vec<_> = overlay
+            .read_directory(Path::new("dir1"))
+            .await
+            .unwrap()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(entries, vec!["upper.txt"]);
+    }
+
+    #[tokio::test]
+    async fn test_overlayfs_exists_with_masked_ancestors() {
+        let lower = helper::create_fs(&["a/b/c.txt", "d/e/f.txt", "g/h/i.txt"]).await;
+        let upper = helper::create_fs(&[".wh.a", "d/.wh..wh..opq", "g/h/j.txt"]).await;
+
+        let overlay = OverlayFileSystem::new(vec![lower, upper]).unwrap();
+
+        // A whited out ancestor hides the whole lower-layer subtree
+        assert!(!overlay.exists(Path::new("a")).await.unwrap());
+        assert!(!overlay.exists(Path::new("a/b")).await.unwrap());
+        assert!(!overlay.exists(Path::new("a/b/c.txt")).await.unwrap());
+
+        // An opaque ancestor hides lower-layer entries more than one level down
+        assert!(overlay.exists(Path::new("d")).await.unwrap());
+        assert!(!overlay.exists(Path::new("d/e")).await.unwrap());
+        assert!(!overlay.exists(Path::new("d/e/f.txt")).await.unwrap());
+
+        // Unmasked ancestors leave lower-layer entries visible
+        assert!(overlay.exists(Path::new("g/h/i.txt")).await.unwrap());
+        assert!(overlay.exists(Path::new("g/h/j.txt")).await.unwrap());
+    }
+
     #[tokio::test]
     async fn test_overlayfs_exists_layer_precedence() {
         // Create test layers with overlapping files
@@ -1072,6 +1560,66 @@ mod tests {
             .unwrap());
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_overlayfs_create_file_exclusive_concurrent() {
+        for _ in 0..50 {
+            // One path is new, the other is whited out in the top layer
+            let lower = helper::create_fs(&["dir/hidden.txt"]).await;
+            let top = helper::create_fs(&["dir/.wh.hidden.txt"]).await;
+            let overlay = Arc::new(OverlayFileSystem::new(vec![lower, top]).unwrap());
+
+            for path in ["dir/new.txt", "dir/hidden.txt"] {
+                let creators = (0..2)
+                    .map(|_| {
+                        let overlay = overlay.clone();
+                        tokio::spawn(
+                            async move { overlay.create_file(Path::new(path), false).await },
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut results = Vec::new();
+                for creator in creators {
+                    results.push(creator.await.unwrap());
+                }
+
+                assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1);
+                assert!(results
+                    .iter()
+                    .any(|r| matches!(r, Err(VfsError::AlreadyExists(_)))));
+            }
+
+            // The locks are released once nobody holds them
+            assert!(overlay.path_locks.lock().unwrap().is_empty());
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_overlayfs_create_file_exclusive_concurrent_unnormalized_paths() {
+        for _ in 0..50 {
+            let lower = helper::create_fs(&["dir/other.txt"]).await;
+            let top = helper::create_fs(&[]).await;
+            let overlay = Arc::new(OverlayFileSystem::new(vec![lower, top]).unwrap());
+
+            // Different spellings of the same path contend for the same lock
+            let creators = ["dir/new.txt", "dir//new.txt", "dir/./new.txt"]
+                .into_iter()
+                .map(|path| {
+                    let overlay = overlay.clone();
+                    tokio::spawn(async move { overlay.create_file(Path::new(path), false).await })
+                })
+                .collect::<Vec<_>>();
+
+            let mut results = Vec::new();
+            for creator in creators {
+                results.push(creator.await.unwrap());
+            }
+
+            assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1);
+            assert!(overlay.path_locks.lock().unwrap().is_empty());
+        }
+    }
+
     #[tokio::test]
     async fn test_overlayfs_create_file_in_opaque_directory() {
         // Setup: dir with files in lower layer, made opaque in top layer
@@ -1266,6 +1814,71 @@ mod tests {
             .unwrap());
     }
 
+    #[tokio::test]
+    async fn test_overlayfs_create_file_symlinked_parent() {
+        // Lower layer has "real/existing.txt", a link to "real" and a loop of links
+        let lower = MemoryFileSystem::new();
+        lower.create_directory(Path::new("real")).await.unwrap();
+        lower
+            .create_file(Path::new("real/existing.txt"), false)
+            .await
+            .unwrap();
+        lower
+            .create_symlink(Path::new("link"), Path::new("real"))
+            .await
+            .unwrap();
+        lower
+            .create_symlink(Path::new("loop_a"), Path::new("loop_b"))
+            .await
+            .unwrap();
+        lower
+            .create_symlink(Path::new("loop_b"), Path::new("loop_a"))
+            .await
+            .unwrap();
+
+        let lower: Box<dyn VirtualFileSystem + Send + Sync> = Box::new(lower);
+        let top = helper::create_fs(&[]).await;
+        let overlay = OverlayFileSystem::new(vec![lower, top]).unwrap();
+
+        // A symlinked parent is not copied up as a directory
+        assert!(matches!(
+            overlay
+                .ensure_parent_in_top(Path::new("link/new.txt"))
+                .await,
+            Err(VfsError::ParentIsSymlink(_))
+        ));
+        assert!(overlay
+            .create_file(Path::new("link/new.txt"), false)
+            .await
+            .is_err());
+        assert!(overlay
+            .create_directory(Path::new("link/sub/dir"))
+            .await
+            .is_err());
+
+        // A loop of links is detected rather than followed forever
+        assert!(matches!(
+            overlay
+                .ensure_parent_in_top(Path::new("loop_a/new.txt"))
+                .await,
+            Err(VfsError::TooManySymlinks(_))
+        ));
+        assert!(overlay
+            .create_file(Path::new("loop_a/new.txt"), false)
+            .await
+            .is_err());
+
+        // The links are left as they are
+        let top = overlay.get_top_layer();
+        assert!(!top.exists(Path::new("link")).await.unwrap());
+        assert!(!top.exists(Path::new("loop_a")).await.unwrap());
+        assert!(overlay
+            .get_metadata(Path::new("link"))
+            .await
+            .unwrap()
+            .is_symlink());
+    }
+
     #[tokio::test]
     async fn test_overlayfs_read_file_upper_layer() {
         // Create top layer containing "file_upper.txt"
@@ -1545,6 +2158,269 @@ mod tests {
         assert_eq!(buf, b"NewContent");
     }
 
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_overlayfs_set_permissions_and_owner_copy_up() {
+        let lower = helper::create_fs(&["dir/file.txt", "hidden.txt"]).await;
+        lower
+            .write_file(
+                Path::new("dir/file.txt"),
+                0,
+                Box::pin(std::io::Cursor::new(b"content".to_vec())),
+            )
+            .await
+            .unwrap();
+        let lower_mode = u32::from(
+            *lower
+                .get_metadata(Path::new("dir/file.txt"))
+                .await
+                .unwrap()
+                .get_mode(),
+        ) & 0o777;
+
+        let top = helper::create_fs(&[".wh.hidden.txt"]).await;
+        let overlay = OverlayFileSystem::new(vec![lower, top]).unwrap();
+
+        overlay
+            .set_permissions(Path::new("dir/file.txt"), 0o600)
+            .await
+            .unwrap();
+        overlay
+            .set_owner(Path::new("dir/file.txt"), Some(1000), Some(1000))
+            .await
+            .unwrap();
+
+        // The change is made to a copy in the top layer, content included
+        let metadata = overlay
+            .get_top_layer()
+            .get_metadata(Path::new("dir/file.txt"))
+            .await
+            .unwrap();
+        assert_eq!(u32::from(*metadata.get_mode()) & 0o777, 0o600);
+        assert_eq!(metadata.get_uid(), 1000);
+        assert_eq!(metadata.get_gid(), 1000);
+
+        let mut reader = overlay
+            .read_file(Path::new("dir/file.txt"), 0, u64::MAX)
+            .await
+            .unwrap();
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"content");
+
+        // The lower layer is untouched
+        let metadata = overlay.get_lower_layers()[0]
+            .get_metadata(Path::new("dir/file.txt"))
+            .await
+            .unwrap();
+        assert_eq!(u32::from(*metadata.get_mode()) & 0o777, lower_mode);
+
+        // Whited out and missing entities can't be changed
+        for path in ["hidden.txt", "missing.txt"] {
+            assert!(matches!(
+                overlay.set_permissions(Path::new(path), 0o600).await,
+                Err(VfsError::NotFound(_))
+            ));
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_overlayfs_append_file_copyup_concurrent() {
+        for _ in 0..20 {
+            let lower = helper::create_fs(&["dir/log.txt"]).await;
+            lower
+                .write_file(
+                    Path::new("dir/log.txt"),
+                    0,
+                    Box::pin(std::io::Cursor::new(b"base".to_vec())),
+                )
+                .await
+                .unwrap();
+            let top = helper::create_fs(&[]).await;
+            let overlay = Arc::new(OverlayFileSystem::new(vec![lower, top]).unwrap());
+
+            let appenders = (0..4)
+                .map(|_| {
+                    let overlay = overlay.clone();
+                    tokio::spawn(async move {
+                        overlay
+                            .append_file(
+                                Path::new("dir/log.txt"),
+                                Box::pin(std::io::Cursor::new(b"+x".to_vec())),
+                            )
+                            .await
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            for appender in appenders {
+                appender.await.unwrap().unwrap();
+            }
+
+            // The file was copied up once and every append landed after the copied content
+            let mut reader = overlay
+                .read_file(Path::new("dir/log.txt"), 0, u64::MAX)
+                .await
+                .unwrap();
+            let mut buf = Vec::new();
+            reader.read_to_end(&mut buf).await.unwrap();
+            assert_eq!(buf, b"base+x+x+x+x");
+
+            assert!(overlay.path_locks.lock().unwrap().is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_overlayfs_append_file_copyup() {
+        let lower = helper::create_fs(&["dir/test.txt", "hidden.txt"]).await;
+        lower
+            .write_file(
+                Path::new("dir/test.txt"),
+                0,
+                Box::pin(std::io::Cursor::new(b"Existing".to_vec())),
+            )
+            .await
+            .unwrap();
+
+        let top = helper::create_fs(&[".wh.hidden.txt"]).await;
+        let overlay = OverlayFileSystem::new(vec![lower, top]).unwrap();
+
+        // The first append copies the file up, the second appends in the top layer.
+        let len = overlay
+            .append_file(
+                Path::new("dir/test.txt"),
+                Box::pin(std::io::Cursor::new(b" more".to_vec())),
+            )
+            .await
+            .unwrap();
+        assert_eq!(len, 13);
+        let len = overlay
+            .append_file(
+                Path::new("dir/test.txt"),
+                Box::pin(std::io::Cursor::new(b" again".to_vec())),
+            )
+            .await
+            .unwrap();
+        assert_eq!(len, 19);
+
+        let mut reader = overlay
+            .get_top_layer()
+            .read_file(Path::new("dir/test.txt"), 0, 1024)
+            .await
+            .unwrap();
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"Existing more again");
+
+        // The lower layer is untouched.
+        let mut reader = overlay.get_lower_layers()[0]
+            .read_file(Path::new("dir/test.txt"), 0, 1024)
+            .await
+            .unwrap();
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"Existing");
+
+        // Whited out and missing files can't be appended to.
+        for path in ["hidden.txt", "missing.txt"] {
+            let result = overlay
+                .append_file(
+                    Path::new(path),
+                    Box::pin(std::io::Cursor::new(b"x".to_vec())),
+                )
+                .await;
+            assert!(matches!(result, Err(VfsError::NotFound(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_overlayfs_xattrs_copyup() {
+        let lower = helper::create_fs(&["dir/file.txt"]).await;
+        for (path, name, value) in [
+            ("dir", "user.dir", b"lower dir".as_slice()),
+            ("dir/file.txt", "user.comment", b"lower".as_slice()),
+            ("dir/file.txt", "security.selinux", b"label".as_slice()),
+        ] {
+            lower
+                .set_xattr(Path::new(path), name, value.to_vec())
+                .await
+                .unwrap();
+        }
+
+        let top = helper::create_fs(&[]).await;
+        let overlay = OverlayFileSystem::new(vec![lower, top]).unwrap();
+
+        // Attributes are read from the lower layer before copy-up.
+        assert_eq!(
+            overlay.list_xattr(Path::new("dir/file.txt")).await.unwrap(),
+            ["security.selinux", "user.comment"]
+        );
+
+        // Setting an attribute copies the file and its parent up with all their attributes.
+        overlay
+            .set_xattr(Path::new("dir/file.txt"), "user.new", b"top".to_vec())
+            .await
+            .unwrap();
+        let top = overlay.get_top_layer();
+        assert_eq!(
+            top.list_xattr(Path::new("dir/file.txt")).await.unwrap(),
+            ["security.selinux", "user.comment", "user.new"]
+        );
+        assert_eq!(
+            top.get_xattr(Path::new("dir/file.txt"), "security.selinux")
+                .await
+                .unwrap(),
+            Some(b"label".to_vec())
+        );
+        assert_eq!(
+            top.get_xattr(Path::new("dir"), "user.dir").await.unwrap(),
+            Some(b"lower dir".to_vec())
+        );
+
+        // Removing an attribute leaves the lower layer untouched.
+        overlay
+            .remove_xattr(Path::new("dir/file.txt"), "user.comment")
+            .await
+            .unwrap();
+        assert_eq!(
+            overlay.list_xattr(Path::new("dir/file.txt")).await.unwrap(),
+            ["security.selinux", "user.new"]
+        );
+        assert_eq!(
+            overlay.get_lower_layers()[0]
+                .list_xattr(Path::new("dir/file.txt"))
+                .await
+                .unwrap(),
+            ["security.selinux", "user.comment"]
+        );
+
+        // Setting an attribute on a lower directory copies only the directory up.
+        let lower = helper::create_fs(&["dir/a.txt"]).await;
+        let overlay = OverlayFileSystem::new(vec![lower, helper::create_fs(&[]).await]).unwrap();
+        overlay
+            .set_xattr(Path::new("dir"), "user.tag", b"x".to_vec())
+            .await
+            .unwrap();
+        assert!(overlay
+            .get_top_layer()
+            .exists(Path::new("dir"))
+            .await
+            .unwrap());
+        assert!(!overlay
+            .get_top_layer()
+            .exists(Path::new("dir/a.txt"))
+            .await
+            .unwrap());
+        assert!(overlay.exists(Path::new("dir/a.txt")).await.unwrap());
+
+        assert!(matches!(
+            overlay
+                .set_xattr(Path::new("missing"), "user.tag", Vec::new())
+                .await,
+            Err(VfsError::NotFound(_))
+        ));
+    }
+
     #[tokio::test]
     async fn test_overlayfs_write_file_top_layer() {
         // Create an overlay with a single layer (this becomes the top layer).
@@ -1700,6 +2576,105 @@ mod tests {
             .unwrap());
     }
 
+    #[tokio::test]
+    async fn test_overlayfs_remove_tree_in_both_layers() {
+        let lower = helper::create_fs(&[
+            "dir/lower.txt",
+            "dir/sub/lower_nested.txt",
+            "dir/sub/deep/lower_deep.txt",
+            "keep.txt",
+        ])
+        .await;
+        let top = helper::create_fs(&["dir/top.txt", "dir/sub/top_nested.txt"]).await;
+        let overlay = OverlayFileSystem::new(vec![lower, top]).unwrap();
+
+        overlay.remove_tree(Path::new("dir")).await.unwrap();
+
+        // Neither the directory nor any of its descendants are visible anymore
+        for path in [
+            "dir",
+            "dir/lower.txt",
+            "dir/top.txt",
+            "dir/sub",
+            "dir/sub/lower_nested.txt",
+            "dir/sub/top_nested.txt",
+            "dir/sub/deep",
+            "dir/sub/deep/lower_deep.txt",
+        ] {
+            assert!(!overlay.exists(Path::new(path)).await.unwrap(), "{path}");
+        }
+
+        assert!(matches!(
+            overlay.read_directory(Path::new("dir/sub")).await,
+            Err(VfsError::NotFound(_))
+        ));
+        assert!(matches!(
+            overlay
+                .read_file(Path::new("dir/sub/deep/lower_deep.txt"), 0, u64::MAX)
+                .await,
+            Err(VfsError::NotFound(_))
+        ));
+        assert!(matches!(
+            overlay
+                .get_metadata(Path::new("dir/sub/lower_nested.txt"))
+                .await,
+            Err(VfsError::NotFound(_))
+        ));
+
+        let entries: Vec<String> = overlay
+            .read_directory(Path::new(""))
+            .await
+            .unwrap()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(entries, vec!["keep.txt".to_string()]);
+
+        // The top-layer subtree is gone and a single whiteout masks the lower layer
+        let top = overlay.get_top_layer();
+        assert!(!top.exists(Path::new("dir")).await.unwrap());
+        assert!(top.exists(Path::new(".wh.dir")).await.unwrap());
+
+        // Removing it again fails
+        assert!(matches!(
+            overlay.remove_tree(Path::new("dir")).await,
+            Err(VfsError::NotFound(_))
+        ));
+
+        // Recreating the directory replaces the whiteout with an opaque directory, so the
+        // lower-layer contents stay hidden
+        overlay.create_directory(Path::new("dir")).await.unwrap();
+        assert!(!top.exists(Path::new(".wh.dir")).await.unwrap());
+        assert!(top.exists(Path::new("dir/.wh..wh..opq")).await.unwrap());
+        assert_eq!(
+            overlay
+                .read_directory(Path::new("dir"))
+                .await
+                .unwrap()
+                .count(),
+            0
+        );
+        for path in ["dir/lower.txt", "dir/sub", "dir/sub/deep/lower_deep.txt"] {
+            assert!(!overlay.exists(Path::new(path)).await.unwrap(), "{path}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_overlayfs_remove_tree_top_layer_only() {
+        let lower = helper::create_fs(&["keep.txt"]).await;
+        let top = helper::create_fs(&["dir/sub/file.txt"]).await;
+        let overlay = OverlayFileSystem::new(vec![lower, top]).unwrap();
+
+        overlay.remove_tree(Path::new("dir")).await.unwrap();
+
+        // Nothing to mask in the lower layer, so no whiteout is created
+        assert!(!overlay.exists(Path::new("dir")).await.unwrap());
+        assert!(!overlay
+            .get_top_layer()
+            .exists(Path::new(".wh.dir"))
+            .await
+            .unwrap());
+    }
+
     #[tokio::test]
     async fn test_overlayfs_read_directory_merged_after_copyup() {
         // Create a lower layer that contains the directory "dir" with a file "lower.txt"
@@ -1734,6 +2709,61 @@ mod tests {
         assert_eq!(entries, expected);
     }
 
+    #[tokio::test]
+    async fn test_overlayfs_read_directory_paged() {
+        let lower = helper::create_fs(&[
+            "dir/a.txt",
+            "dir/c.txt",
+            "dir/e.txt",
+            "dir/hidden.txt",
+            "opaque/lower.txt",
+        ])
+        .await;
+        let top = helper::create_fs(&[
+            "dir/b.txt",
+            "dir/c.txt",
+            "dir/d.txt",
+            "dir/.wh.hidden.txt",
+            "opaque/.wh..wh..opq",
+            "opaque/upper.txt",
+        ])
+        .await;
+        let overlay = OverlayFileSystem::new(vec![lower, top]).unwrap();
+
+        // Pages merge the layers in sorted order, without duplicates or whited-out entries
+        let mut pages = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (page, next) = overlay
+                .read_directory_paged(Path::new("dir"), cursor, 2)
+                .await
+                .unwrap();
+            pages.push(page.iter().map(|s| s.to_string()).collect::<Vec<_>>());
+
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert_eq!(
+            pages,
+            vec![
+                vec!["a.txt", "b.txt"],
+                vec!["c.txt", "d.txt"],
+                vec!["e.txt"]
+            ]
+        );
+
+        // Opaque directories only list the top layer
+        let (page, next) = overlay
+            .read_directory_paged(Path::new("opaque"), None, 10)
+            .await
+            .unwrap();
+        let names: Vec<_> = page.iter().map(|s| s.to_string()).collect();
+        assert_eq!(names, vec!["upper.txt"]);
+        assert!(next.is_none());
+    }
+
     #[tokio::test]
     async fn test_rename_directory_with_markers() {
         // Setup: lower filesystem with a directory "old_dir" that contains:
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use cfg_if::cfg_if;
 use chrono::{DateTime, Utc};
 use getset::{CopyGetters, Getters};
@@ -108,6 +110,7 @@ cfg_if! {
 /// - Last access timestamp
 /// - User ID (Unix only)
 /// - Group ID (Unix only)
+/// - Extended attributes
 #[derive(Debug, Clone, CopyGetters, Getters, PartialEq, Eq)]
 pub struct Metadata {
     /// The mode of the file, combining file type and permissions
@@ -144,6 +147,10 @@ pub struct Metadata {
     #[cfg(unix)]
     #[getset(get_copy = "pub with_prefix")]
     gid: u32,
+
+    /// Extended attributes, such as SELinux labels and user metadata, by name
+    #[getset(get = "pub with_prefix")]
+    attributes: HashMap<String, Vec<u8>>,
 }
 
 cfg_if! {
@@ -386,6 +393,7 @@ impl Metadata {
             uid: get_current_uid(),
             #[cfg(unix)]
             gid: get_current_gid(),
+            attributes: HashMap::new(),
         }
     }
 
@@ -411,6 +419,18 @@ impl Metadata {
         self.mode.get_type()
     }
 
+    /// Returns `true` if the metadata describes a symbolic link.
+    #[cfg(unix)]
+    pub fn is_symlink(&self) -> bool {
+        self.get_type() == Some(ModeType::Symlink)
+    }
+
+    /// Returns `true` if the metadata describes a symbolic link.
+    #[cfg(not(unix))]
+    pub fn is_symlink(&self) -> bool {
+        self.entity_type == EntityType::Symlink
+    }
+
     /// Sets the permission bits of the mode.
     ///
     /// This method:
@@ -470,6 +490,16 @@ impl Metadata {
         self.size = size;
     }
 
+    /// Sets the creation time of the file.
+    pub fn set_created_at(&mut self, time: DateTime<Utc>) {
+        self.created_at = time;
+    }
+
+    /// Sets the last modification time of the file.
+    pub fn set_modified_at(&mut self, time: DateTime<Utc>) {
+        self.modified_at = time;
+    }
+
     /// Sets the last access time of the file.
     pub fn set_accessed_at(&mut self, time: DateTime<Utc>) {
         self.accessed_at = time;
@@ -487,6 +517,30 @@ impl Metadata {
         self.gid = gid;
     }
 
+    /// Gets the value of an extended attribute.
+    pub fn get_attribute(&self, name: &str) -> Option<&[u8]> {
+        self.attributes.get(name).map(Vec::as_slice)
+    }
+
+    /// Sets an extended attribute, replacing any existing value.
+    ///
+    /// ## Examples
+    /// ```rust
+    /// use virtualfs::{Metadata, ModeType};
+    ///
+    /// let mut metadata = Metadata::new(ModeType::File);
+    /// metadata.set_attribute("user.origin", b"upload".to_vec());
+    /// assert_eq!(metadata.get_attribute("user.origin"), Some(&b"upload"[..]));
+    /// ```
+    pub fn set_attribute(&mut self, name: impl Into<String>, value: Vec<u8>) {
+        self.attributes.insert(name.into(), value);
+    }
+
+    /// Removes an extended attribute, returning its value if it was set.
+    pub fn remove_attribute(&mut self, name: &str) -> Option<Vec<u8>> {
+        self.attributes.remove(name)
+    }
+
     #[cfg(test)]
     #[cfg(unix)]
     fn with_root_ownership(entity_type: ModeType) -> Self {
@@ -499,6 +553,7 @@ impl Metadata {
             accessed_at: now,
             uid: 0,
             gid: 0,
+            attributes: HashMap::new(),
         }
     }
 }
@@ -5,7 +5,7 @@ use std::{
     str::FromStr,
 };
 
-use crate::VfsError;
+use crate::{VfsError, VfsResult};
 
 //--------------------------------------------------------------------------------------------------
 // Types
@@ -42,6 +42,46 @@ impl PathSegment {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// Splits a path into its segments, validating every component in one pass.
+    ///
+    /// An empty path has no segments.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `VfsError::InvalidPathComponent` with the first component that is not a normal
+    /// segment, such as `.`, `..` or the root directory.
+    ///
+    /// ## Examples
+    ///
+    /// ```
+    /// use std::path::Path;
+    /// use virtualfs::PathSegment;
+    ///
+    /// let segments = PathSegment::split_path(Path::new("foo/bar")).unwrap();
+    /// assert_eq!(segments, ["foo".parse().unwrap(), "bar".parse().unwrap()]);
+    ///
+    /// assert!(PathSegment::split_path(Path::new("foo/../bar")).is_err());
+    /// ```
+    pub fn split_path(path: &Path) -> VfsResult<Vec<PathSegment>> {
+        path.components().map(PathSegment::try_from).collect()
+    }
+
+    /// Creates a segment from a path made of exactly one normal component.
+    ///
+    /// ## Errors
+    ///
+    /// Returns `VfsError::InvalidPathComponent` if the path is empty, has more than one component,
+    /// or its component is not a normal segment.
+    pub fn from_path(path: &Path) -> VfsResult<PathSegment> {
+        let mut components = path.components();
+        match (components.next(), components.next()) {
+            (Some(component), None) => PathSegment::try_from(component),
+            _ => Err(VfsError::InvalidPathComponent(
+                path.to_string_lossy().into_owned(),
+            )),
+        }
+    }
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -208,6 +248,49 @@ mod tests {
         assert!(PathSegment::try_from("").is_err());
     }
 
+    #[test]
+    fn test_segment_split_path() {
+        let segments = PathSegment::split_path(Path::new("a/b/c.txt")).unwrap();
+        let expected = ["a", "b", "c.txt"].map(|s| PathSegment::from_str(s).unwrap());
+        assert_eq!(segments, expected);
+
+        // Redundant separators and a trailing separator are not components
+        assert_eq!(
+            PathSegment::split_path(Path::new("a//b/c.txt/")).unwrap(),
+            expected
+        );
+        assert!(PathSegment::split_path(Path::new("")).unwrap().is_empty());
+
+        // Each rejected component is reported, the first one when there are several
+        for (path, component) in [
+            ("a/../b", ".."),
+            ("./a/b", "."),
+            ("/a/b", "/"),
+            ("a/b/..", ".."),
+            ("../a/.", ".."),
+        ] {
+            assert!(matches!(
+                PathSegment::split_path(Path::new(path)),
+                Err(VfsError::InvalidPathComponent(c)) if c == component
+            ));
+        }
+    }
+
+    #[test]
+    fn test_segment_from_path() {
+        assert_eq!(
+            PathSegment::from_path(Path::new("file.txt")).unwrap(),
+            PathSegment::from_str("file.txt").unwrap()
+        );
+
+        for path in ["", "a/b", "..", "/"] {
+            assert!(matches!(
+                PathSegment::from_path(Path::new(path)),
+                Err(VfsError::InvalidPathComponent(_))
+            ));
+        }
+    }
+
     #[test]
     fn test_segment_from_path_segment_to_component() {
         let segment = PathSegment::from_str("example").unwrap();
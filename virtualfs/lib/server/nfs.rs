@@ -3,17 +3,11 @@
 //! This module provides an implementation of the NFSv3 protocol for the virtual filesystem.
 //! It handles file operations, metadata management, and path-to-fileid mapping required by the NFS protocol.
 
-use std::{
-    collections::HashMap,
-    sync::{
-        atomic::{AtomicU64, Ordering},
-        Arc,
-    },
-};
+use std::path::Path;
 
 use async_trait::async_trait;
-use chrono::{TimeZone, Utc};
-use intaglio::{Symbol, SymbolTable};
+use chrono::{DateTime, TimeZone, Utc};
+use monoutils::FileidMap;
 use nfsserve::{
     nfs::{
         fattr3, fileid3, filename3, ftype3, nfspath3, nfsstat3, nfstime3, sattr3, set_atime,
@@ -21,14 +15,13 @@ use nfsserve::{
     },
     vfs::{DirEntry, NFSFileSystem, ReadDirResult, VFSCapabilities},
 };
-use tokio::sync::Mutex;
 
 #[cfg(not(unix))]
 use crate::metadata::EntityType;
 #[cfg(unix)]
 use crate::metadata::{Mode, ModeType};
 
-use crate::{VfsError, VirtualFileSystem};
+use crate::{Cursor, PathSegment, VfsError, VfsResult, VirtualFileSystem};
 
 //--------------------------------------------------------------------------------------------------
 // Types
@@ -42,19 +35,13 @@ use crate::{VfsError, VirtualFileSystem};
 ///
 /// # Fields
 /// * `root` - The underlying virtual filesystem implementation
-/// * `next_fileid` - Counter for generating unique file IDs
-/// * `filenames` - Symbol table for storing path components
-/// * `fileid_to_path_map` - Maps file IDs to paths (as sequences of symbols)
-/// * `path_to_fileid_map` - Maps paths to file IDs
+/// * `fileids` - Maps file IDs to the paths they were issued for
 pub struct VirtualFileSystemNFS<F>
 where
     F: VirtualFileSystem + Send + Sync,
 {
     root: F,
-    next_fileid: AtomicU64,
-    filenames: Arc<Mutex<SymbolTable>>,
-    fileid_to_path_map: Arc<Mutex<HashMap<fileid3, Vec<Symbol>>>>,
-    path_to_fileid_map: Arc<Mutex<HashMap<Vec<Symbol>, fileid3>>>,
+    fileids: FileidMap,
 }
 
 //--------------------------------------------------------------------------------------------------
@@ -76,137 +63,58 @@ where
     pub fn new(root: F) -> Self {
         Self {
             root,
-            next_fileid: AtomicU64::new(1),
-            filenames: Arc::new(Mutex::new(SymbolTable::new())),
-            fileid_to_path_map: Arc::new(Mutex::new(HashMap::from([(0, vec![])]))),
-            path_to_fileid_map: Arc::new(Mutex::new(HashMap::from([(vec![], 0)]))),
+            fileids: FileidMap::new(),
         }
     }
 
-    /// Generates the next unique file ID.
+    /// Limits the number of file IDs the server keeps track of, besides the root's.
     ///
-    /// ## Returns
-    /// A new unique file ID.
-    fn next_fileid(&self) -> fileid3 {
-        self.next_fileid.fetch_add(1, Ordering::SeqCst) as fileid3
-    }
-
-    /// Converts a file ID to its corresponding filesystem path.
+    /// When the limit is exceeded, the file IDs of the least recently used paths are evicted.
+    /// The server remembers up to `max_fileids` evicted file IDs, which keep resolving and keep
+    /// their paths' inode numbers. File IDs evicted beyond that are forgotten: they report
+    /// `NFS3ERR_STALE`, which makes clients look their paths up again, and their paths are given
+    /// new file IDs when they are next looked up. By default the number of file IDs is not
+    /// limited.
     ///
-    /// ## Arguments
-    /// * `id` - The file ID to convert
-    ///
-    /// ## Returns
-    /// The path corresponding to the file ID, or an error if the ID is invalid.
-    async fn fileid_to_path(&self, id: fileid3) -> Result<String, nfsstat3> {
-        let fileid_to_path_map = self.fileid_to_path_map.lock().await;
-        let symbols = fileid_to_path_map.get(&id).ok_or(nfsstat3::NFS3ERR_NOENT)?;
-        let filenames = self.filenames.lock().await;
-
-        let path = symbols
-            .iter()
-            .map(|s| filenames.get(*s).ok_or(nfsstat3::NFS3ERR_STALE))
-            .collect::<Result<Vec<_>, _>>()?
-            .join("/");
-        Ok(path)
-    }
-
-    /// Converts a path string into a sequence of symbols.
+    /// The server also remembers the last paths of up to `max_fileids` removed entries, or
+    /// [`DEFAULT_MAX_REMOVED_FILEIDS`][monoutils::DEFAULT_MAX_REMOVED_FILEIDS] if the number of
+    /// file IDs is not limited.
     ///
     /// ## Arguments
-    /// * `path` - The path to convert
+    /// * `max_fileids` - The maximum number of file IDs to keep track of
     ///
     /// ## Returns
-    /// A vector of symbols representing the path components, or an error if the path is invalid.
-    async fn path_to_symbols(&self, path: impl AsRef<str>) -> Result<Vec<Symbol>, nfsstat3> {
-        let path = path.as_ref();
-
-        // Split path into segments, filtering out empty segments
-        let segments: Vec<String> = path
-            .split('/')
-            .filter(|s| !s.is_empty())
-            .map(|s| s.to_string())
-            .collect();
-
-        // Convert segments to symbols
-        let mut path_symbols = Vec::with_capacity(segments.len());
-        let mut filenames = self.filenames.lock().await;
-
-        for segment in segments {
-            let symbol = filenames
-                .intern(segment)
-                .map_err(|_| nfsstat3::NFS3ERR_INVAL)?;
-            path_symbols.push(symbol);
-        }
-        drop(filenames);
-
-        Ok(path_symbols)
-    }
-
-    /// Gets the file ID for a registered path.
-    ///
-    /// ## Arguments
-    /// * `path` - The path to look up
-    ///
-    /// ## Returns
-    /// The file ID if the path is registered, or None if it isn't.
-    async fn get_path_registered_str(
-        &self,
-        path: impl AsRef<str>,
-    ) -> Result<Option<fileid3>, nfsstat3> {
-        let path_symbols = self.path_to_symbols(path).await?;
-        self.get_path_registered(&path_symbols).await
+    /// The server with the limit applied.
+    pub fn with_max_fileids(mut self, max_fileids: usize) -> Self {
+        self.fileids = self.fileids.with_max_fileids(max_fileids);
+        self
     }
 
-    /// Gets the file ID for a registered path using symbols.
-    ///
-    /// ## Arguments
-    /// * `path_symbols` - The path components as symbols
+    /// Gets the maximum number of file IDs the server keeps track of besides the root's.
     ///
     /// ## Returns
-    /// The file ID if the path is registered, or None if it isn't.
-    async fn get_path_registered(
-        &self,
-        path_symbols: &[Symbol],
-    ) -> Result<Option<fileid3>, nfsstat3> {
-        let path_to_fileid_map = self.path_to_fileid_map.lock().await;
-        Ok(path_to_fileid_map.get(path_symbols).copied())
+    /// The limit, or `None` if the number of file IDs is not limited.
+    pub fn get_max_fileids(&self) -> Option<usize> {
+        self.fileids.get_max_fileids()
     }
 
-    /// Ensures a path is registered and returns its file ID.
-    ///
-    /// ## Arguments
-    /// * `path` - The path to register
+    /// Persists the file IDs issued by the server in an append-only log.
     ///
-    /// ## Returns
-    /// The file ID for the path, creating a new one if necessary.
-    async fn ensure_path_registered_str(&self, path: impl AsRef<str>) -> Result<fileid3, nfsstat3> {
-        let path_symbols = self.path_to_symbols(path).await?;
-        self.ensure_path_registered(&path_symbols).await
-    }
-
-    /// Ensures a path is registered using symbols and returns its file ID.
+    /// File IDs recorded by a previous run are restored and new file IDs are allocated after
+    /// them, so file handles held by clients stay valid when the server is restarted over the
+    /// same filesystem. File IDs of removed entries keep reporting `NFS3ERR_STALE`. The log is
+    /// created if it does not exist, and compacted when it is opened and whenever it has doubled
+    /// in size since.
     ///
     /// ## Arguments
-    /// * `path_symbols` - The path components as symbols
+    /// * `path` - The path of the log file
     ///
     /// ## Returns
-    /// The file ID for the path, creating a new one if necessary.
-    async fn ensure_path_registered(&self, path_symbols: &[Symbol]) -> Result<fileid3, nfsstat3> {
-        // First check if the path is already registered
-        if let Some(existing_id) = self.get_path_registered(path_symbols).await? {
-            return Ok(existing_id);
-        }
-
-        // Create new mapping
-        let fileid = self.next_fileid();
-        let mut fileid_to_path_map = self.fileid_to_path_map.lock().await;
-        let mut path_to_fileid_map = self.path_to_fileid_map.lock().await;
-
-        fileid_to_path_map.insert(fileid, path_symbols.to_vec());
-        path_to_fileid_map.insert(path_symbols.to_vec(), fileid);
-
-        Ok(fileid)
+    /// The server with its file IDs restored from the log, or an error if the log cannot be
+    /// read, compacted or opened for appending.
+    pub async fn with_fileid_log(mut self, path: impl AsRef<Path>) -> VfsResult<Self> {
+        self.fileids = self.fileids.with_log(path).await?;
+        Ok(self)
     }
 }
 
@@ -237,7 +145,7 @@ where
         }
 
         // Get parent directory path
-        let parent_path = self.fileid_to_path(dirid).await?;
+        let parent_path = self.fileids.fileid_to_path(dirid).await?;
 
         // Construct full path
         let full_path = if parent_path.is_empty() {
@@ -257,12 +165,12 @@ where
         }
 
         // Ensure path is registered and get its fileid
-        self.ensure_path_registered_str(&full_path).await
+        self.fileids.ensure_path_registered(&full_path).await
     }
 
     async fn getattr(&self, id: fileid3) -> Result<fattr3, nfsstat3> {
         // Get path from fileid
-        let path = self.fileid_to_path(id).await?;
+        let path = self.fileids.fileid_to_path(id).await?;
 
         // Get metadata from underlying filesystem
         let metadata = self
@@ -275,6 +183,7 @@ where
             return Err(nfsstat3::NFS3ERR_NOENT);
         }
 
+        // Symlinks are not followed so that links are reported as `NF3LNK`
         let metadata = self
             .root
             .get_metadata(std::path::Path::new(&path))
@@ -328,24 +237,15 @@ where
             },
             fsid: 0, // Single filesystem
             fileid: id,
-            atime: nfstime3 {
-                seconds: metadata.get_accessed_at().timestamp() as u32,
-                nseconds: 0,
-            },
-            mtime: nfstime3 {
-                seconds: metadata.get_modified_at().timestamp() as u32,
-                nseconds: 0,
-            },
-            ctime: nfstime3 {
-                seconds: metadata.get_created_at().timestamp() as u32,
-                nseconds: 0,
-            },
+            atime: to_nfstime(metadata.get_accessed_at()),
+            mtime: to_nfstime(metadata.get_modified_at()),
+            ctime: to_nfstime(metadata.get_created_at()),
         })
     }
 
     async fn setattr(&self, id: fileid3, setattr: sattr3) -> Result<fattr3, nfsstat3> {
         // Get path from fileid
-        let path = self.fileid_to_path(id).await?;
+        let path = self.fileids.fileid_to_path(id).await?;
         let path = std::path::Path::new(&path);
 
         // Get current metadata
@@ -355,26 +255,34 @@ where
             return Err(nfsstat3::NFS3ERR_NOENT);
         }
 
-        let mut metadata = self.root.get_metadata(path).await.map_err(nfsstat3::from)?;
-
-        // Update mode if specified
+        // Change mode and ownership in place, so concurrent changes to other fields aren't lost
         #[cfg(unix)]
         if let set_mode3::mode(mode) = setattr.mode {
-            let mode = Mode::from(mode & 0o777);
-            metadata.set_permissions(mode.get_permissions());
+            self.root
+                .set_permissions(path, mode)
+                .await
+                .map_err(nfsstat3::from)?;
         }
 
-        // Update uid if specified
         #[cfg(unix)]
-        if let set_uid3::uid(uid) = setattr.uid {
-            metadata.set_uid(uid);
+        {
+            let uid = match setattr.uid {
+                set_uid3::uid(uid) => Some(uid),
+                set_uid3::Void => None,
+            };
+            let gid = match setattr.gid {
+                set_gid3::gid(gid) => Some(gid),
+                set_gid3::Void => None,
+            };
+            if uid.is_some() || gid.is_some() {
+                self.root
+                    .set_owner(path, uid, gid)
+                    .await
+                    .map_err(nfsstat3::from)?;
+            }
         }
 
-        // Update gid if specified
-        #[cfg(unix)]
-        if let set_gid3::gid(gid) = setattr.gid {
-            metadata.set_gid(gid);
-        }
+        let mut metadata = self.root.get_metadata(path).await.map_err(nfsstat3::from)?;
 
         // Update size if specified
         if let set_size3::size(size) = setattr.size {
@@ -400,14 +308,14 @@ where
         // Update mtime if specified
         match setattr.mtime {
             set_mtime::SET_TO_SERVER_TIME => {
-                metadata.set_accessed_at(Utc::now());
+                metadata.set_modified_at(Utc::now());
             }
             set_mtime::SET_TO_CLIENT_TIME(time) => {
                 if let Some(dt) = Utc
                     .timestamp_opt(time.seconds as i64, time.nseconds)
                     .earliest()
                 {
-                    metadata.set_accessed_at(dt);
+                    metadata.set_modified_at(dt);
                 }
             }
             set_mtime::DONT_CHANGE => {}
@@ -430,7 +338,7 @@ where
         count: u32,
     ) -> Result<(Vec<u8>, bool), nfsstat3> {
         // Get path from fileid
-        let path = self.fileid_to_path(id).await?;
+        let path = self.fileids.fileid_to_path(id).await?;
         let path = std::path::Path::new(&path);
 
         // Get file size to determine if we're at EOF
@@ -466,7 +374,7 @@ where
 
     async fn write(&self, id: fileid3, offset: u64, data: &[u8]) -> Result<fattr3, nfsstat3> {
         // Get path from fileid
-        let path = self.fileid_to_path(id).await?;
+        let path = self.fileids.fileid_to_path(id).await?;
         let path = std::path::Path::new(&path);
 
         // Get current file size
@@ -487,6 +395,10 @@ where
             .await
             .map_err(nfsstat3::from)?;
 
+        // nfsserve acknowledges every write as `FILE_SYNC` and never passes COMMIT on, so the data
+        // must be durable before the write returns
+        self.root.flush(Some(path)).await.map_err(nfsstat3::from)?;
+
         // Return updated attributes
         self.getattr(id).await
     }
@@ -506,7 +418,7 @@ where
         }
 
         // Get parent directory path
-        let parent_path = self.fileid_to_path(dirid).await?;
+        let parent_path = self.fileids.fileid_to_path(dirid).await?;
 
         // Construct full path
         let full_path = if parent_path.is_empty() {
@@ -556,7 +468,7 @@ where
             .map_err(nfsstat3::from)?;
 
         // Ensure path is registered and get its fileid
-        let fileid = self.ensure_path_registered_str(&full_path).await?;
+        let fileid = self.fileids.ensure_path_registered(&full_path).await?;
 
         // Get final attributes
         let attrs = self.getattr(fileid).await?;
@@ -578,7 +490,7 @@ where
         }
 
         // Get parent directory path
-        let parent_path = self.fileid_to_path(dirid).await?;
+        let parent_path = self.fileids.fileid_to_path(dirid).await?;
 
         // Construct full path
         let full_path = if parent_path.is_empty() {
@@ -604,7 +516,7 @@ where
             .map_err(nfsstat3::from)?;
 
         // Ensure path is registered and get its fileid
-        self.ensure_path_registered_str(&full_path).await
+        self.fileids.ensure_path_registered(&full_path).await
     }
 
     async fn mkdir(
@@ -621,7 +533,7 @@ where
         }
 
         // Get parent directory path
-        let parent_path = self.fileid_to_path(dirid).await?;
+        let parent_path = self.fileids.fileid_to_path(dirid).await?;
 
         // Construct full path
         let full_path = if parent_path.is_empty() {
@@ -637,7 +549,7 @@ where
             .map_err(nfsstat3::from)?;
 
         // Ensure path is registered and get its fileid
-        let fileid = self.ensure_path_registered_str(&full_path).await?;
+        let fileid = self.fileids.ensure_path_registered(&full_path).await?;
 
         // Get the directory's attributes
         let attrs = self.getattr(fileid).await?;
@@ -655,7 +567,7 @@ where
         }
 
         // Get parent directory path
-        let parent_path = self.fileid_to_path(dirid).await?;
+        let parent_path = self.fileids.fileid_to_path(dirid).await?;
 
         // Construct full path
         let full_path = if parent_path.is_empty() {
@@ -670,6 +582,9 @@ where
             .await
             .map_err(nfsstat3::from)?;
 
+        // File IDs of the removed entry and its descendants are now stale
+        self.fileids.remove_path_registration(&full_path).await?;
+
         Ok(())
     }
 
@@ -692,8 +607,8 @@ where
         }
 
         // Get directory paths
-        let from_dir_path = self.fileid_to_path(from_dirid).await?;
-        let to_dir_path = self.fileid_to_path(to_dirid).await?;
+        let from_dir_path = self.fileids.fileid_to_path(from_dirid).await?;
+        let to_dir_path = self.fileids.fileid_to_path(to_dirid).await?;
 
         // Construct full paths
         let from_path = if from_dir_path.is_empty() {
@@ -715,7 +630,12 @@ where
                 std::path::Path::new(&to_path),
             )
             .await
-            .map_err(nfsstat3::from)
+            .map_err(nfsstat3::from)?;
+
+        // Keep the file IDs of the renamed entries pointing at their new location
+        self.fileids
+            .move_path_registration(&from_path, &to_path)
+            .await
     }
 
     async fn readdir(
@@ -725,37 +645,40 @@ where
         max_entries: usize,
     ) -> Result<ReadDirResult, nfsstat3> {
         // Get directory path
-        let dir_path = self.fileid_to_path(dirid).await?;
+        let dir_path = self.fileids.fileid_to_path(dirid).await?;
+
+        // Resume after the entry the cookie points at. Pages are listed in name order, so
+        // listing resumes at the right place even if entries were added or removed since,
+        // including the cookie's own entry. A cookie that isn't an entry of this directory can't
+        // be resumed from, so the client has to restart the listing.
+        let cursor = if start_after == 0 {
+            None
+        } else {
+            let Some(start_path) = self.fileids.cookie_to_path(start_after).await else {
+                return Err(nfsstat3::NFS3ERR_BAD_COOKIE);
+            };
+
+            let (parent, name) = start_path.rsplit_once('/').unwrap_or(("", &start_path));
+            if parent != dir_path {
+                return Err(nfsstat3::NFS3ERR_BAD_COOKIE);
+            }
+
+            let name = PathSegment::try_from(name).map_err(nfsstat3::from)?;
+            Some(Cursor::new(name))
+        };
 
-        // Read directory entries
-        let entries_iter = self
+        // Read a page of directory entries
+        let (page, next_cursor) = self
             .root
-            .read_directory(std::path::Path::new(&dir_path))
+            .read_directory_paged(std::path::Path::new(&dir_path), cursor, max_entries)
             .await
             .map_err(nfsstat3::from)?;
 
-        let mut entries = Vec::new();
-        let mut found_start = start_after == 0;
-        let mut has_more = false;
-
-        // Convert entries to NFS format
-        for entry_name in entries_iter {
-            // Skip entries until we find start_after
-            if !found_start {
-                let entry_path = if dir_path.is_empty() {
-                    entry_name.to_string()
-                } else {
-                    format!("{}/{}", dir_path, entry_name)
-                };
-
-                if let Some(entry_id) = self.get_path_registered_str(&entry_path).await? {
-                    if entry_id == start_after {
-                        found_start = true;
-                    }
-                }
-                continue;
-            }
-
+        // Convert entries to NFS format. A page always has an entry if any remain, so it can be
+        // longer than a `max_entries` of zero.
+        let page_len = page.len();
+        let mut entries = Vec::with_capacity(page_len);
+        for entry_name in page.into_iter().take(max_entries) {
             // Construct full path for this entry
             let entry_path = if dir_path.is_empty() {
                 entry_name.to_string()
@@ -764,17 +687,11 @@ where
             };
 
             // Get or create fileid for this entry
-            let fileid = self.ensure_path_registered_str(&entry_path).await?;
+            let fileid = self.fileids.ensure_path_registered(&entry_path).await?;
 
             // Get entry attributes
             let attr = self.getattr(fileid).await?;
 
-            // If we've reached max_entries, note that there are more entries and break
-            if entries.len() >= max_entries {
-                has_more = true;
-                break;
-            }
-
             entries.push(DirEntry {
                 fileid,
                 name: filename3::from(entry_name.as_bytes()),
@@ -783,14 +700,14 @@ where
         }
 
         Ok(ReadDirResult {
+            end: next_cursor.is_none() && entries.len() == page_len,
             entries,
-            end: !has_more,
         })
     }
 
     async fn readlink(&self, id: fileid3) -> Result<nfspath3, nfsstat3> {
         // Get path from fileid
-        let path = self.fileid_to_path(id).await?;
+        let path = self.fileids.fileid_to_path(id).await?;
 
         // Read the symlink target
         let target = self
@@ -822,7 +739,7 @@ where
         }
 
         // Get parent directory path
-        let parent_path = self.fileid_to_path(dirid).await?;
+        let parent_path = self.fileids.fileid_to_path(dirid).await?;
 
         // Construct full path
         let full_path = if parent_path.is_empty() {
@@ -881,7 +798,7 @@ where
             .map_err(nfsstat3::from)?;
 
         // Ensure path is registered and get its fileid
-        let fileid = self.ensure_path_registered_str(&full_path).await?;
+        let fileid = self.fileids.ensure_path_registered(&full_path).await?;
 
         // Get final attributes
         let attrs = self.getattr(fileid).await?;
@@ -890,6 +807,18 @@ where
     }
 }
 
+//--------------------------------------------------------------------------------------------------
+// Functions
+//--------------------------------------------------------------------------------------------------
+
+/// Converts a timestamp to an NFS time, keeping its sub-second precision.
+fn to_nfstime(time: &DateTime<Utc>) -> nfstime3 {
+    nfstime3 {
+        seconds: time.timestamp() as u32,
+        nseconds: time.timestamp_subsec_nanos(),
+    }
+}
+
 //--------------------------------------------------------------------------------------------------
 // Tests
 //--------------------------------------------------------------------------------------------------
@@ -897,6 +826,7 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::MemoryFileSystem;
     use chrono::Utc;
     use tokio;
 
@@ -1043,6 +973,100 @@ mod tests {
             assert_eq!(attrs.uid, 1000);
             assert_eq!(attrs.gid, 1000);
         }
+
+        // Extended attributes survive setattr
+        let path = fs.fileid_to_path(file_id).await.unwrap();
+        let path = std::path::Path::new(&path);
+        fs.root
+            .set_xattr(path, "user.comment", b"kept".to_vec())
+            .await
+            .unwrap();
+        let setattr = sattr3 {
+            size: set_size3::size(10),
+            atime: set_atime::SET_TO_SERVER_TIME,
+            ..Default::default()
+        };
+        fs.setattr(file_id, setattr).await.unwrap();
+        assert_eq!(
+            fs.root.get_xattr(path, "user.comment").await.unwrap(),
+            Some(b"kept".to_vec())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_virtualfilesystemnfs_setattr_mtime() {
+        let fs = helper::setup_fs().await;
+        let root_id = fs.root_dir();
+        let filename = filename3::from(b"test.txt".to_vec());
+        let (file_id, _) = fs
+            .create(root_id, &filename, sattr3::default())
+            .await
+            .unwrap();
+        let path = fs.fileid_to_path(file_id).await.unwrap();
+        let path = std::path::Path::new(&path);
+        let accessed_at = *fs.root.get_metadata(path).await.unwrap().get_accessed_at();
+
+        // Setting the mtime changes the modified time and leaves the access time alone
+        let setattr = sattr3 {
+            mtime: set_mtime::SET_TO_CLIENT_TIME(nfstime3 {
+                seconds: 1_600_000_000,
+                nseconds: 0,
+            }),
+            ..Default::default()
+        };
+        let attrs = fs.setattr(file_id, setattr).await.unwrap();
+        assert_eq!(attrs.mtime.seconds, 1_600_000_000);
+
+        let metadata = fs.root.get_metadata(path).await.unwrap();
+        assert_eq!(metadata.get_modified_at().timestamp(), 1_600_000_000);
+        assert_eq!(*metadata.get_accessed_at(), accessed_at);
+
+        // So does setting it to the server time
+        let setattr = sattr3 {
+            mtime: set_mtime::SET_TO_SERVER_TIME,
+            ..Default::default()
+        };
+        fs.setattr(file_id, setattr).await.unwrap();
+
+        let metadata = fs.root.get_metadata(path).await.unwrap();
+        assert!(metadata.get_modified_at().timestamp() > 1_600_000_000);
+        assert_eq!(*metadata.get_accessed_at(), accessed_at);
+    }
+
+    #[tokio::test]
+    async fn test_virtualfilesystemnfs_timestamp_precision() {
+        let fs = helper::setup_fs().await;
+        let root_id = fs.root_dir();
+        let filename = filename3::from(b"test.txt".to_vec());
+        let (file_id, _) = fs
+            .create(root_id, &filename, sattr3::default())
+            .await
+            .unwrap();
+
+        // Sub-second client times survive setattr and getattr
+        let time = nfstime3 {
+            seconds: 1_700_000_000,
+            nseconds: 123_456_789,
+        };
+        let setattr = sattr3 {
+            atime: set_atime::SET_TO_CLIENT_TIME(time),
+            ..Default::default()
+        };
+        fs.setattr(file_id, setattr).await.unwrap();
+        let attrs = fs.getattr(file_id).await.unwrap();
+        assert_eq!(attrs.atime.seconds, time.seconds);
+        assert_eq!(attrs.atime.nseconds, time.nseconds);
+
+        // Sub-second timestamps in the metadata are reported by getattr
+        let modified_at = Utc.timestamp_opt(1_700_000_100, 987_654_321).unwrap();
+        let path = fs.fileid_to_path(file_id).await.unwrap();
+        let path = std::path::Path::new(&path);
+        let mut metadata = fs.root.get_metadata(path).await.unwrap();
+        metadata.set_modified_at(modified_at);
+        fs.root.set_metadata(path, metadata).await.unwrap();
+        let attrs = fs.getattr(file_id).await.unwrap();
+        assert_eq!(attrs.mtime.seconds, 1_700_000_100);
+        assert_eq!(attrs.mtime.nseconds, 987_654_321);
     }
 
     #[tokio::test]
@@ -1396,6 +1420,136 @@ mod tests {
         assert!(matches!(result, Err(nfsstat3::NFS3ERR_NOENT)));
     }
 
+    #[cfg(feature = "monofs")]
+    #[tokio::test]
+    async fn test_virtualfilesystemnfs_write_is_durable() {
+        use ipldstore::MemoryStore;
+
+        use crate::MonofsVfs;
+
+        let fs = VirtualFileSystemNFS::new(MonofsVfs::new(MemoryStore::default()));
+        let root_id = fs.root_dir();
+
+        let filename = filename3::from(b"test.txt".to_vec());
+        let (file_id, _) = fs
+            .create(root_id, &filename, sattr3::default())
+            .await
+            .unwrap();
+
+        // Written data is stored by the time the write is acknowledged
+        let root_dir = fs.root.get_root_dir();
+        assert!(root_dir.read().await.get_initial_load_cid().is_none());
+
+        fs.write(file_id, 0, b"Hello, World!").await.unwrap();
+        assert!(root_dir.read().await.get_initial_load_cid().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_virtualfilesystemnfs_max_fileids() {
+        let fs = VirtualFileSystemNFS::new(MemoryFileSystem::new()).with_max_fileids(4);
+        let root_id = fs.root_dir();
+
+        let mut files = Vec::new();
+        for i in 0..10 {
+            let filename = filename3::from(format!("file{}.txt", i).into_bytes());
+            let (fileid, _) = fs
+                .create(root_id, &filename, sattr3::default())
+                .await
+                .unwrap();
+            files.push((filename, fileid));
+        }
+
+        // The root is kept besides the limit
+        fs.getattr(root_id).await.unwrap();
+
+        // Recently used file IDs still resolve
+        for (filename, fileid) in &files[6..] {
+            assert_eq!(fs.getattr(*fileid).await.unwrap().fileid, *fileid);
+            assert_eq!(fs.lookup(root_id, filename).await.unwrap(), *fileid);
+        }
+
+        // Remembered evicted file IDs keep resolving, and their paths keep their file IDs
+        let (filename, fileid) = &files[5];
+        assert_eq!(fs.getattr(*fileid).await.unwrap().fileid, *fileid);
+        assert_eq!(fs.lookup(root_id, filename).await.unwrap(), *fileid);
+
+        // Forgotten file IDs are stale, and their paths get new file IDs when looked up again
+        let (filename, fileid) = &files[0];
+        assert!(matches!(
+            fs.getattr(*fileid).await,
+            Err(nfsstat3::NFS3ERR_STALE)
+        ));
+
+        let new_fileid = fs.lookup(root_id, filename).await.unwrap();
+        assert_ne!(new_fileid, *fileid);
+        assert_eq!(fs.getattr(new_fileid).await.unwrap().fileid, new_fileid);
+    }
+
+    #[tokio::test]
+    async fn test_virtualfilesystemnfs_fileids_survive_restart() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let log_path = temp_dir.path().join("fileids.log");
+        let memfs = MemoryFileSystem::new();
+
+        let fs = VirtualFileSystemNFS::new(memfs.clone())
+            .with_fileid_log(&log_path)
+            .await
+            .unwrap();
+        let root_id = fs.root_dir();
+
+        let dirname = filename3::from(b"testdir".to_vec());
+        let filename = filename3::from(b"test.txt".to_vec());
+        let removed_name = filename3::from(b"removed.txt".to_vec());
+
+        let (dir_id, _) = fs.mkdir(root_id, &dirname).await.unwrap();
+        let (file_id, _) = fs
+            .create(dir_id, &filename, sattr3::default())
+            .await
+            .unwrap();
+        fs.write(file_id, 0, b"hello").await.unwrap();
+        let (removed_id, _) = fs
+            .create(root_id, &removed_name, sattr3::default())
+            .await
+            .unwrap();
+        fs.remove(root_id, &removed_name).await.unwrap();
+
+        // The removed file's ID is stale, while IDs that were never issued don't exist
+        assert!(matches!(
+            fs.getattr(removed_id).await,
+            Err(nfsstat3::NFS3ERR_STALE)
+        ));
+        assert!(matches!(
+            fs.getattr(999).await,
+            Err(nfsstat3::NFS3ERR_NOENT)
+        ));
+
+        // Restart the server over the same filesystem and file ID log
+        drop(fs);
+        let fs = VirtualFileSystemNFS::new(memfs)
+            .with_fileid_log(&log_path)
+            .await
+            .unwrap();
+
+        // Previously issued file IDs still resolve
+        let attr = fs.getattr(file_id).await.unwrap();
+        assert_eq!(attr.fileid, file_id);
+        let (data, _) = fs.read(file_id, 0, 64).await.unwrap();
+        assert_eq!(data, b"hello");
+        assert_eq!(fs.lookup(root_id, &dirname).await.unwrap(), dir_id);
+        assert_eq!(fs.lookup(dir_id, &filename).await.unwrap(), file_id);
+        assert!(matches!(
+            fs.getattr(removed_id).await,
+            Err(nfsstat3::NFS3ERR_STALE)
+        ));
+
+        // New file IDs are allocated after the ones issued before the restart
+        let (new_id, _) = fs
+            .create(root_id, &removed_name, sattr3::default())
+            .await
+            .unwrap();
+        assert!(new_id > removed_id);
+    }
+
     #[tokio::test]
     async fn test_virtualfilesystemnfs_rename() {
         let fs = helper::setup_fs().await;
@@ -1544,6 +1698,37 @@ mod tests {
             .iter()
             .all(|name| !second_page_names.contains(name)));
 
+        // Listing resumes after a cookie whose entry has since been removed
+        let last_entry = second_page.last().unwrap();
+        fs.remove(root_id, &last_entry.name).await.unwrap();
+        let ReadDirResult {
+            entries: third_page,
+            end,
+        } = fs.readdir(root_id, last_entry.fileid, 100).await.unwrap();
+        assert!(end);
+        assert_eq!(
+            first_page.len() + second_page.len() + third_page.len(),
+            files.len() + dirs.len()
+        );
+
+        // Cookies that aren't entries of the directory can't be resumed from
+        let (nested_id, _) = fs
+            .create(
+                dir_ids[0],
+                &filename3::from(b"nested.txt".to_vec()),
+                sattr3::default(),
+            )
+            .await
+            .unwrap();
+        assert!(matches!(
+            fs.readdir(root_id, nested_id, 100).await,
+            Err(nfsstat3::NFS3ERR_BAD_COOKIE)
+        ));
+        assert!(matches!(
+            fs.readdir(root_id, 999999, 100).await,
+            Err(nfsstat3::NFS3ERR_BAD_COOKIE)
+        ));
+
         // Test reading from non-existent directory
         let result = fs.readdir(999999, 0, 100).await;
         assert!(matches!(result, Err(nfsstat3::NFS3ERR_NOENT)));
@@ -1553,6 +1738,50 @@ mod tests {
         assert!(matches!(result, Err(nfsstat3::NFS3ERR_NOTDIR)));
     }
 
+    #[tokio::test]
+    async fn test_virtualfilesystemnfs_readdir_large() {
+        let fs = helper::setup_fs().await;
+        let root_id = fs.root_dir();
+
+        let (dir_id, _) = fs
+            .mkdir(root_id, &filename3::from(b"large".to_vec()))
+            .await
+            .unwrap();
+        for i in 0..500 {
+            fs.create(
+                dir_id,
+                &filename3::from(format!("file{}.txt", i).into_bytes()),
+                sattr3::default(),
+            )
+            .await
+            .unwrap();
+        }
+
+        // Page through the directory with the last fileid of each page as the cookie
+        let mut names = Vec::new();
+        let mut cookie = 0;
+        loop {
+            let result = fs.readdir(dir_id, cookie, 32).await.unwrap();
+            assert!(result.entries.len() <= 32);
+            names.extend(
+                result
+                    .entries
+                    .iter()
+                    .map(|e| String::from_utf8_lossy(&e.name).into_owned()),
+            );
+
+            if result.end {
+                break;
+            }
+            cookie = result.entries.last().unwrap().fileid;
+        }
+
+        // Every entry is listed exactly once, in name order
+        let mut expected: Vec<_> = (0..500).map(|i| format!("file{}.txt", i)).collect();
+        expected.sort();
+        assert_eq!(names, expected);
+    }
+
     #[tokio::test]
     async fn test_virtualfilesystemnfs_readlink() {
         let fs = helper::setup_fs().await;